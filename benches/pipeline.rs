@@ -0,0 +1,184 @@
+/*
+* Benchmarks of the conversion pipeline and its stages on generated inputs. Inputs come from the
+* same generators as the integration tests so no binary fixtures are needed
+*/
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use ascii_gen::ascii::frames::FrameConverter;
+use ascii_gen::image_manip::edge_detect::{EdgeDetect, Sobel};
+use ascii_gen::image_manip::edge_processor::EdgeDownscaler;
+use ascii_gen::image_manip::processing::{
+    BilateralFilter, DoG, MedianBlur, Processor, Sharpen3x3, SharpenGaussian, Threshold,
+};
+use ascii_gen::image_manip::util::bufr_to_arr;
+use ascii_gen::output::ansi::grid_to_ansi;
+use ascii_gen::output::text::grid_to_text;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use image::imageops::FilterType;
+use image::{DynamicImage, Rgb};
+use ndarray::Array2;
+
+const RESOLUTIONS: [(&str, u32, u32); 3] = [
+    ("720p", 1280, 720),
+    ("1080p", 1920, 1080),
+    ("4k", 3840, 2160),
+];
+const BENCH_W: u32 = 1920;
+const BENCH_H: u32 = 1080;
+
+fn photo_like(w: u32, h: u32) -> DynamicImage {
+    // Mix of flat regions, hard edges and texture so every stage has real work to do
+    let circle = common::circle(w, h).to_luma8();
+    let lines = common::diagonal_lines(w, h).to_luma8();
+    let noise = common::noise(w, h, 7).to_luma8();
+    let mut img = common::gradient(w, h).to_luma8();
+    for (x, y, px) in img.enumerate_pixels_mut() {
+        let sum = px[0] as u32
+            + circle.get_pixel(x, y)[0] as u32
+            + lines.get_pixel(x, y)[0] as u32 / 2
+            + noise.get_pixel(x, y)[0] as u32 / 4;
+        px[0] = (sum / 3).min(255) as u8;
+    }
+    DynamicImage::ImageLuma8(img)
+}
+
+fn bench_convert_image(c: &mut Criterion) {
+    let converter = common::test_converter();
+    let mut group = c.benchmark_group("convert_image");
+    group.sample_size(10);
+    for (name, w, h) in RESOLUTIONS {
+        let img = photo_like(w, h);
+        group.bench_with_input(BenchmarkId::from_parameter(name), &img, |b, img| {
+            b.iter(|| converter.convert_image(black_box(img), 0.0).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_frames(c: &mut Criterion) {
+    // The same 720p frame converted one-shot and through a FrameConverter reusing its buffers
+    let (w, h) = (1280, 720);
+    let frame = photo_like(w, h);
+    let converter = common::test_converter();
+    let mut frame_converter = FrameConverter::new(common::test_converter(), w, h, 0.0).unwrap();
+    let mut group = c.benchmark_group("frames");
+    group.sample_size(10);
+    group.bench_function("convert_image/720p", |b| {
+        b.iter(|| converter.convert_image(black_box(&frame), 0.0).unwrap())
+    });
+    group.bench_function("convert_frame/720p", |b| {
+        b.iter(|| {
+            frame_converter
+                .convert_frame(black_box(&frame))
+                .unwrap()
+                .width()
+        })
+    });
+    group.finish();
+}
+
+fn bench_arr_to_img(c: &mut Criterion) {
+    let converter = common::test_converter();
+    let (cols, rows) = (BENCH_W / common::FONT_SIZE, BENCH_H / common::FONT_SIZE);
+    let grid = common::char_grid(rows as usize, cols as usize, 3);
+    let colors = photo_like(BENCH_W, BENCH_H).resize_exact(cols, rows, FilterType::Triangle);
+    c.bench_function("arr_to_img/1080p", |b| {
+        b.iter(|| {
+            converter
+                .arr_to_img(black_box(&grid.view()), &colors)
+                .unwrap()
+        })
+    });
+}
+
+fn bench_cell_colors(c: &mut Criterion) {
+    // Grayscale grids take the single channel path, their RGB copies the general one
+    let converter = common::test_converter();
+    let (cols, rows) = (BENCH_W / common::FONT_SIZE, BENCH_H / common::FONT_SIZE);
+    let gray = photo_like(BENCH_W, BENCH_H).resize_exact(cols, rows, FilterType::Triangle);
+    let rgb = DynamicImage::ImageRgb8(gray.to_rgb8());
+    let mut group = c.benchmark_group("cell_colors");
+    group.bench_function("gray/1080p", |b| {
+        b.iter(|| converter.cell_colors(black_box(&gray)))
+    });
+    group.bench_function("rgb/1080p", |b| {
+        b.iter(|| converter.cell_colors(black_box(&rgb)))
+    });
+    group.finish();
+}
+
+fn bench_processors(c: &mut Criterion) {
+    let bufr = photo_like(BENCH_W, BENCH_H).to_luma8();
+    let processors: Vec<Box<dyn Processor<u8, u8>>> = vec![
+        Box::new(DoG::default()),
+        Box::new(MedianBlur::default()),
+        Box::new(BilateralFilter::default()),
+        Box::new(Threshold::default()),
+        Box::new(Sharpen3x3::default()),
+        Box::new(SharpenGaussian::default()),
+    ];
+    let mut group = c.benchmark_group("processor");
+    group.sample_size(10);
+    for processor in processors.iter() {
+        group.bench_function(processor.name(), |b| {
+            b.iter(|| processor.apply(black_box(&bufr)).unwrap())
+        });
+    }
+    group.finish();
+}
+
+fn bench_sobel(c: &mut Criterion) {
+    let bufr = photo_like(BENCH_W, BENCH_H).to_luma8();
+    let sobel = Sobel::new();
+    c.bench_function("sobel/1080p", |b| {
+        b.iter(|| sobel.apply(black_box(&bufr), 5).unwrap())
+    });
+}
+
+fn bench_hist_downscale(c: &mut Criterion) {
+    let tile_size = common::FONT_SIZE as usize;
+    let mut group = c.benchmark_group("hist_downscale");
+    for (name, w, h) in RESOLUTIONS {
+        let bufr = photo_like(w, h).to_luma8();
+        let edges = bufr_to_arr(&Sobel::new().apply(&bufr, 5).unwrap());
+        let new_size = (h as usize / tile_size, w as usize / tile_size);
+        group.bench_with_input(BenchmarkId::from_parameter(name), &edges, |b, edges| {
+            b.iter(|| EdgeDownscaler::hist_downscale(black_box(edges), tile_size, 0.0, new_size))
+        });
+    }
+    group.finish();
+}
+
+fn bench_text_output(c: &mut Criterion) {
+    // A 1000x500 grid with a new color in most cells, the worst case for the ANSI escapes
+    let (cols, rows) = (1000, 500);
+    let grid = common::char_grid(rows, cols, 3);
+    let rgb = photo_like(cols as u32, rows as u32 * 3).to_luma8();
+    let colors = Array2::from_shape_fn((rows, cols), |(y, x)| {
+        Rgb(std::array::from_fn(|c| {
+            rgb.get_pixel(x as u32, (y * 3 + c) as u32)[0]
+        }))
+    });
+    let mut group = c.benchmark_group("text_output");
+    group.bench_function("ansi/1000x500", |b| {
+        b.iter(|| grid_to_ansi(black_box(&grid.view()), &colors.view(), Rgb([0, 0, 0])))
+    });
+    group.bench_function("text/1000x500", |b| {
+        b.iter(|| grid_to_text(black_box(&grid.view())))
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_convert_image,
+    bench_frames,
+    bench_arr_to_img,
+    bench_cell_colors,
+    bench_processors,
+    bench_sobel,
+    bench_hist_downscale,
+    bench_text_output
+);
+criterion_main!(benches);
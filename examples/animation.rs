@@ -0,0 +1,57 @@
+/*
+* Convert an animated GIF frame by frame into an animated ascii GIF
+*
+*   cargo run --example animation
+*/
+use ascii_gen::ascii::converter::Converter;
+use ascii_gen::ascii::frames::FrameConverter;
+use ascii_gen::output::animation::AnimationWriter;
+use image::codecs::gif::{GifDecoder, GifEncoder};
+use image::{AnimationDecoder, Delay, DynamicImage, Frame, Rgba, RgbaImage};
+use std::error::Error;
+use std::fs::File;
+use std::io::BufReader;
+
+const FRAMES: u32 = 12;
+
+pub fn main() -> Result<(), Box<dyn Error>> {
+    // A ball bouncing across the frame, written as a GIF so the example needs no fixtures
+    let input = std::env::temp_dir().join("ruscii-gen-example-anim.gif");
+    let output = std::env::temp_dir().join("ruscii-gen-example-anim-ascii.gif");
+    let mut encoder = GifEncoder::new(File::create(&input)?);
+    for i in 0..FRAMES {
+        let (cx, cy) = (
+            40.0 + i as f32 * 20.0,
+            120.0 - (i as f32 - 6.0).abs() * 14.0,
+        );
+        let frame = RgbaImage::from_fn(320, 160, |x, y| {
+            let d = (x as f32 - cx).hypot(y as f32 - cy);
+            if d < 30.0 {
+                Rgba([250, 200, 60, 255])
+            } else {
+                Rgba([30, 40, 90, 255])
+            }
+        });
+        encoder.encode_frame(Frame::from_parts(
+            frame,
+            0,
+            0,
+            Delay::from_numer_denom_ms(80, 1),
+        ))?;
+    }
+    drop(encoder);
+
+    // Decode the frames back, then convert them with buffers shared between frames
+    let decoder = GifDecoder::new(BufReader::new(File::open(&input)?))?;
+    let frames = decoder.into_frames().collect_frames()?;
+    let (w, h) = frames[0].buffer().dimensions();
+    let mut converter = FrameConverter::new(Converter::default(), w, h, 0.0)?;
+    let mut writer = AnimationWriter::gif(output.to_str().unwrap(), 12);
+    for frame in frames {
+        let ascii = converter.convert_frame(&DynamicImage::ImageRgba8(frame.into_buffer()))?;
+        writer.push_frame(ascii.clone())?;
+    }
+    writer.finish()?;
+    println!("wrote {}", output.display());
+    Ok(())
+}
@@ -0,0 +1,34 @@
+/*
+* Show an image in the terminal as ANSI colored characters
+*
+*   cargo run --example ansi_preview
+*/
+use ascii_gen::ascii::config::ConverterConfig;
+use image::{DynamicImage, Rgb, RgbImage};
+use std::error::Error;
+
+pub fn main() -> Result<(), Box<dyn Error>> {
+    // Color wheel, generated so the example needs no fixtures
+    let img = DynamicImage::ImageRgb8(RgbImage::from_fn(480, 240, |x, y| {
+        let (dx, dy) = (x as f32 - 240.0, (y as f32 - 120.0) * 2.0);
+        let angle = dy.atan2(dx);
+        let channel = |offset: f32| ((angle + offset).cos() * 0.5 + 0.5) * 255.0;
+        if dx.hypot(dy) < 220.0 {
+            Rgb([channel(0.0) as u8, channel(2.1) as u8, channel(4.2) as u8])
+        } else {
+            Rgb([0, 0, 0])
+        }
+    }));
+
+    // Settings can also come from a config, here the defaults with a larger font
+    let converter = ConverterConfig {
+        font_size: 8,
+        ..ConverterConfig::default()
+    }
+    .build()?;
+    // The full conversion, one character per 8x8 pixel tile
+    print!("{}", converter.convert_to_ansi(&img, 0.0)?);
+    // A quick approximation at most 40 columns wide, for interactive use
+    print!("{}", converter.preview(&img, 40, 0.0)?);
+    Ok(())
+}
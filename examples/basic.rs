@@ -0,0 +1,32 @@
+/*
+* Convert an image file into an ascii image with the default settings
+*
+*   cargo run --example basic
+*/
+use ascii_gen::ascii::converter::Converter;
+use image::{Rgb, RgbImage};
+use std::error::Error;
+
+pub fn main() -> Result<(), Box<dyn Error>> {
+    // A generated input, a lit sphere over a gradient, so the example needs no fixtures
+    let input = std::env::temp_dir().join("ruscii-gen-example-basic.png");
+    let output = std::env::temp_dir().join("ruscii-gen-example-basic-ascii.png");
+    RgbImage::from_fn(480, 320, |x, y| {
+        let (dx, dy) = (x as f32 - 240.0, y as f32 - 160.0);
+        let d = (dx * dx + dy * dy).sqrt() / 120.0;
+        if d < 1.0 {
+            let light = (1.0 - d * d).sqrt() * 255.0;
+            Rgb([light as u8, (light * 0.8) as u8, 60])
+        } else {
+            Rgb([20, 30, (40 + y / 4) as u8])
+        }
+    })
+    .save(&input)?;
+
+    // Reads font.ttf from the working directory, the root of the repository with cargo run
+    let converter = Converter::default();
+    // A sharpen threshold of 0 keeps every edge the detector finds
+    converter.convert_img(input.to_str().unwrap(), output.to_str().unwrap(), 0.0)?;
+    println!("wrote {}", output.display());
+    Ok(())
+}
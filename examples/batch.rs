@@ -0,0 +1,52 @@
+/*
+* Convert every image in a directory, reporting progress as it goes. Needs the batch feature
+*
+*   cargo run --example batch --features batch
+*/
+use ascii_gen::ascii::config::ConverterConfig;
+use ascii_gen::batch::{convert_dir_with_progress, BatchOptions, Outcome};
+use image::{GrayImage, Luma};
+use std::error::Error;
+use std::fs;
+
+pub fn main() -> Result<(), Box<dyn Error>> {
+    // A few generated inputs so the example needs no fixtures
+    let root = std::env::temp_dir().join("ruscii-gen-example-batch");
+    let (input_dir, output_dir) = (root.join("in"), root.join("out"));
+    fs::create_dir_all(&input_dir)?;
+    for i in 1..=4u32 {
+        GrayImage::from_fn(320, 200, |x, y| Luma([((x * i + y) % 256) as u8]))
+            .save(input_dir.join(format!("stripes-{}.png", i)))?;
+    }
+
+    // Incremental runs skip the inputs converted with the same config before
+    let options = BatchOptions::new(true).with_output_template("{stem}-{cols}x{rows}.{ext}");
+    let report = convert_dir_with_progress(
+        input_dir.to_str().unwrap(),
+        output_dir.to_str().unwrap(),
+        &ConverterConfig::default(),
+        &options,
+        |progress| {
+            let status = match progress.outcome {
+                Ok(Outcome::Converted) => "converted".to_string(),
+                Ok(Outcome::Skipped) => "up to date".to_string(),
+                Err(e) => format!("failed: {}", e),
+            };
+            println!(
+                "[{}/{}] {} {}",
+                progress.done,
+                progress.total,
+                progress.input.display(),
+                status
+            );
+        },
+    )?;
+    println!(
+        "{} converted, {} skipped, {} failed, outputs in {}",
+        report.converted.len(),
+        report.skipped.len(),
+        report.failed.len(),
+        output_dir.display()
+    );
+    Ok(())
+}
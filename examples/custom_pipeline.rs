@@ -0,0 +1,53 @@
+/*
+* Build a converter by hand: a short character ramp, a denoising tile preprocessor and an edge
+* pipeline of sharpening, difference of gaussians, threshold and thinning
+*
+*   cargo run --example custom_pipeline
+*/
+use ascii_gen::ascii::char_set::CharacterSet;
+use ascii_gen::ascii::converter::Converter;
+use ascii_gen::ascii::edge_color::EdgeColorMode;
+use ascii_gen::ascii::font_loader::FontSettings;
+use ascii_gen::image_manip::edge_detect::Sobel;
+use ascii_gen::image_manip::processing::{
+    DoG, MedianBlur, Processor, SharpenGaussian, Thin, Threshold,
+};
+use image::{DynamicImage, GrayImage, Luma, Rgb};
+use std::error::Error;
+
+pub fn main() -> Result<(), Box<dyn Error>> {
+    // Concentric rings with some texture, generated so the example needs no fixtures
+    let img = DynamicImage::ImageLuma8(GrayImage::from_fn(480, 320, |x, y| {
+        let (dx, dy) = (x as f32 - 240.0, y as f32 - 160.0);
+        let ring = ((dx * dx + dy * dy).sqrt() / 24.0) as u32 % 2;
+        let grain = (x * 7 + y * 13) % 23;
+        Luma([(ring * 180 + grain) as u8])
+    }));
+
+    let tile_preprocessors: Vec<Box<dyn Processor<u8, u8>>> = vec![Box::new(MedianBlur::new(1))];
+    let edge_preprocessors: Vec<Box<dyn Processor<u8, u8>>> = vec![
+        Box::new(SharpenGaussian::new(1.0, 1.0)),
+        Box::new(DoG::new(1.0, 3.5)),
+        Box::new(Threshold::new(10)),
+        Box::new(Thin::new()),
+    ];
+    let converter = Converter::new(
+        FontSettings::new(8, "font.ttf"),
+        CharacterSet::new(&[' ', '.', ':', '+', '#']),
+        tile_preprocessors,
+        edge_preprocessors,
+        Box::new(Sobel::new()),
+        Rgb([16, 16, 24]),
+        false,
+        Rgb([230, 230, 210]),
+    )
+    .with_edge_color(EdgeColorMode::Fixed(Rgb([255, 190, 80])));
+    // Catches settings that would fail part way through a conversion
+    converter.validate()?;
+
+    println!("{}", converter.convert_to_text(&img, 0.0)?);
+    let output = std::env::temp_dir().join("ruscii-gen-example-custom.png");
+    converter.convert_image(&img, 0.0)?.save(&output)?;
+    println!("wrote {}", output.display());
+    Ok(())
+}
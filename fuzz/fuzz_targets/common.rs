@@ -0,0 +1,33 @@
+#![allow(dead_code)]
+
+#[path = "../../tests/common/font.rs"]
+mod font;
+
+use ascii_gen::ascii::char_set::CharacterSet;
+use std::env;
+use std::fs;
+use std::sync::OnceLock;
+
+// Decoded inputs larger than this are skipped since they only test the allocator
+pub const MAX_PIXELS: u64 = 512 * 512;
+
+pub fn font_path() -> &'static str {
+    /*
+     * Write the generated test font once per fuzzing process and return its path
+     */
+    static PATH: OnceLock<String> = OnceLock::new();
+    PATH.get_or_init(|| {
+        let charset = CharacterSet::default();
+        let mut chars: Vec<char> = vec![];
+        for &ch in charset.tile.iter().chain(charset.edge.iter()) {
+            if !chars.contains(&ch) {
+                chars.push(ch);
+            }
+        }
+        let path = env::temp_dir().join(format!("ruscii-fuzz-font-{}.ttf", std::process::id()));
+        fs::write(&path, font::build_test_font(&chars)).expect("Failed writing fuzz font");
+        path.to_str()
+            .expect("Temp dir is not valid utf-8")
+            .to_string()
+    })
+}
@@ -0,0 +1,34 @@
+#![no_main]
+
+mod common;
+
+use ascii_gen::ascii::config::ConverterConfig;
+use ascii_gen::output::OutputFormat;
+use image::io::Reader as ImageReader;
+use libfuzzer_sys::fuzz_target;
+use std::io::Cursor;
+
+/*
+* Arbitrary bytes through decoding and the full pipeline must end in Ok or Err, never a panic
+*/
+fuzz_target!(|data: &[u8]| {
+    // Check the claimed size before decoding so huge headers do not exhaust memory
+    let Ok(reader) = ImageReader::new(Cursor::new(data)).with_guessed_format() else {
+        return;
+    };
+    match reader.into_dimensions() {
+        Ok((w, h)) if w as u64 * h as u64 <= common::MAX_PIXELS => {}
+        _ => return,
+    }
+
+    let converter = ConverterConfig {
+        font_size: 4,
+        font_path: common::font_path().to_string(),
+        ..ConverterConfig::default()
+    }
+    .build()
+    .unwrap();
+    for format in [OutputFormat::Png, OutputFormat::Txt, OutputFormat::Ansi] {
+        let _ = converter.convert_bytes(data, format, 0.0);
+    }
+});
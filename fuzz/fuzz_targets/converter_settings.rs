@@ -0,0 +1,67 @@
+#![no_main]
+
+mod common;
+
+use arbitrary::Arbitrary;
+use ascii_gen::ascii::char_set::CharacterSet;
+use ascii_gen::ascii::config::ConverterConfig;
+use ascii_gen::ascii::converter::Converter;
+use ascii_gen::ascii::font_loader::FontSettings;
+use ascii_gen::image_manip::edge_detect::Sobel;
+use image::{DynamicImage, Rgb, RgbImage};
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Arbitrary, Debug)]
+struct Settings {
+    font_size: u32,
+    tile: Vec<char>,
+    edge: Vec<char>,
+    draw_edges: bool,
+    use_image_color: bool,
+    thres: f32,
+    width: u8,
+    height: u8,
+    seed: u32,
+}
+
+/*
+* Character set and font settings combinations must end in Ok or Err, never a panic
+*/
+fuzz_target!(|settings: Settings| {
+    let config = ConverterConfig::default();
+    let charset = CharacterSet {
+        tile: settings.tile,
+        edge: settings.edge,
+    };
+    let converter = Converter::new(
+        FontSettings::new(settings.font_size, common::font_path()),
+        charset,
+        config
+            .tile_preprocessors
+            .iter()
+            .map(|p| p.build())
+            .collect(),
+        config
+            .edge_preprocessors
+            .iter()
+            .map(|p| p.build())
+            .collect(),
+        Box::new(Sobel::new()),
+        Rgb(config.bg_color),
+        settings.use_image_color,
+        Rgb(config.color),
+    )
+    .with_edges(settings.draw_edges);
+
+    let mut state = settings.seed.max(1);
+    let img = RgbImage::from_fn(settings.width as u32, settings.height as u32, |_, _| {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        Rgb([state as u8, (state >> 8) as u8, (state >> 16) as u8])
+    });
+    let img = DynamicImage::ImageRgb8(img);
+
+    let _ = converter.convert_image(&img, settings.thres);
+    let _ = converter.convert_to_text(&img, settings.thres);
+});
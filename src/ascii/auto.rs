@@ -0,0 +1,224 @@
+use super::config::{ConverterConfig, ProcessorConfig};
+use super::converter::Converter;
+use super::error::ConvertError;
+use crate::image_manip::processing::{clipped_range, AutoLevels, DoG, Processor};
+use image::imageops::FilterType;
+use image::{DynamicImage, GrayImage};
+use imageproc::contrast::otsu_level;
+use imageproc::gradients::sobel_gradients;
+use std::fmt;
+
+// Images whose clipped luminance range is narrower than this get AutoLevels
+pub const LOW_CONTRAST_SPREAD: u8 = 96;
+// Median local standard deviation, in luminance steps, above which edges only trace noise
+pub const NOISY_LEVEL: f32 = 12.0;
+// Share of strong gradient pixels below which there is nothing worth outlining
+pub const MIN_EDGE_DENSITY: f32 = 0.002;
+
+// Clip of the AutoLevels the tuner adds, also used to measure the spread
+const LEVELS_CLIP: f32 = 0.01;
+// Longest side of the copy edge density is measured on
+const PROFILE_SIDE: u32 = 256;
+// Sobel magnitude counted as a strong gradient in an image using the full luminance range
+const EDGE_MAGNITUDE: u16 = 256;
+// Number of pixels the noise is measured around
+const NOISE_SAMPLES: u32 = 65536;
+// Ratio between the two DoG sigmas, the one of the default pipeline
+const DOG_RATIO: f32 = 3.5;
+
+/*
+* Statistics the tuner decides from. The spread is the luminance range left after clipping 1% of
+* the darkest and brightest pixels, the noise the median standard deviation of 3x3 neighbourhoods
+* at full resolution, and the edge density the share of pixels with a strong Sobel gradient in a
+* copy downscaled to PROFILE_SIDE. What counts as strong scales with the spread, so the edges of a
+* low contrast image count as well
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageProfile {
+    pub spread: u8,
+    pub noise: f32,
+    pub edge_density: f32,
+}
+
+impl ImageProfile {
+    pub fn new(img: &DynamicImage) -> Self {
+        let gray = img.to_luma8();
+        let (low, high) = clipped_range(&gray, LEVELS_CLIP);
+        let spread = high - low;
+        ImageProfile {
+            spread,
+            noise: median_local_deviation(&gray),
+            edge_density: edge_density(img, spread),
+        }
+    }
+}
+
+fn median_local_deviation(gray: &GrayImage) -> f32 {
+    /*
+     * Median over an even spread of sample pixels of the standard deviation of their 3x3
+     * neighbourhood. Edges only touch a minority of neighbourhoods, so the median follows the noise
+     */
+    let (w, h) = gray.dimensions();
+    if w < 3 || h < 3 {
+        return 0.0;
+    }
+    let step = (((w as f32 * h as f32) / NOISE_SAMPLES as f32).sqrt() as u32).max(1);
+    // Deviations of 8 bit values stay under 128
+    let mut histogram = [0usize; 128];
+    let mut samples = 0;
+    for y in (1..h - 1).step_by(step as usize) {
+        for x in (1..w - 1).step_by(step as usize) {
+            let (mut sum, mut sum_sq) = (0.0, 0.0);
+            for py in y - 1..=y + 1 {
+                for px in x - 1..=x + 1 {
+                    let v = gray.get_pixel(px, py)[0] as f32;
+                    sum += v;
+                    sum_sq += v * v;
+                }
+            }
+            let mean = sum / 9.0;
+            let deviation = (sum_sq / 9.0 - mean * mean).max(0.0).sqrt();
+            histogram[(deviation.round() as usize).min(127)] += 1;
+            samples += 1;
+        }
+    }
+
+    let mut seen = 0;
+    histogram
+        .iter()
+        .position(|&count| {
+            seen += count;
+            seen * 2 > samples
+        })
+        .unwrap_or(0) as f32
+}
+
+fn edge_density(img: &DynamicImage, spread: u8) -> f32 {
+    let (w, h) = (img.width(), img.height());
+    let scale = (PROFILE_SIDE as f32 / w.max(h) as f32).min(1.0);
+    let small = img
+        .resize_exact(
+            ((w as f32 * scale) as u32).max(1),
+            ((h as f32 * scale) as u32).max(1),
+            FilterType::Triangle,
+        )
+        .to_luma8();
+    let gradients = sobel_gradients(&small);
+    let min_magnitude = (EDGE_MAGNITUDE as u32 * spread.max(1) as u32 / 255).max(1) as u16;
+    let strong = gradients.iter().filter(|&&g| g >= min_magnitude).count();
+    strong as f32 / gradients.len() as f32
+}
+
+/*
+* Picks the preprocessing of a converter from the statistics of an image: AutoLevels for low
+* contrast images, DoG sigmas widened with the noise level, the edge threshold from Otsu's method
+* on the DoG response, and no edges at all for noisy or featureless images. Settings the tuner
+* does not decide are taken from the base config
+*/
+#[derive(Clone, Debug, Default)]
+pub struct AutoTuner {
+    base: ConverterConfig,
+}
+
+impl AutoTuner {
+    pub fn new(base: ConverterConfig) -> Self {
+        AutoTuner { base }
+    }
+
+    pub fn tune(&self, img: &DynamicImage) -> AutoTuning {
+        let _span = stage_span!("auto_tune", width = img.width(), height = img.height());
+        let profile = ImageProfile::new(img);
+
+        let auto_levels = profile.spread < LOW_CONTRAST_SPREAD;
+        let sigma_1 = (1.0 + profile.noise / 10.0).min(3.0);
+        let dog = (sigma_1, sigma_1 * DOG_RATIO);
+        let draw_edges = profile.noise < NOISY_LEVEL && profile.edge_density >= MIN_EDGE_DENSITY;
+
+        // Otsu splits the DoG response into the edges and the flat background
+        let mut gray = img.to_luma8();
+        if auto_levels {
+            gray = AutoLevels::new(LEVELS_CLIP)
+                .apply(&gray)
+                .expect("auto levels does not fail");
+        }
+        let response = Processor::<u8, u8>::apply(&DoG::new(dog.0, dog.1), &gray)
+            .expect("difference of gaussians does not fail");
+        let threshold = otsu_level(&response).max(1);
+        stage_event!(
+            spread = profile.spread,
+            noise = profile.noise,
+            edge_density = profile.edge_density,
+            auto_levels = auto_levels,
+            threshold = threshold,
+            draw_edges = draw_edges;
+            "auto tuned"
+        );
+
+        let mut config = self.base.clone();
+        let levels = ProcessorConfig::AutoLevels { clip: LEVELS_CLIP };
+        if auto_levels {
+            config.tile_preprocessors.insert(0, levels.clone());
+        }
+        for stage in config.edge_preprocessors.iter_mut() {
+            match stage {
+                ProcessorConfig::DoG { sigma_1, sigma_2 } => {
+                    (*sigma_1, *sigma_2) = dog;
+                }
+                ProcessorConfig::Threshold { threshold: t, .. } => *t = threshold,
+                _ => {}
+            }
+        }
+        if auto_levels {
+            config.edge_preprocessors.insert(0, levels);
+        }
+        config.draw_edges &= draw_edges;
+
+        AutoTuning {
+            profile,
+            auto_levels,
+            dog,
+            threshold,
+            draw_edges,
+            config,
+        }
+    }
+}
+
+/*
+* Decisions of the tuner and the config they make up. The config can be saved to pin them
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub struct AutoTuning {
+    pub profile: ImageProfile,
+    pub auto_levels: bool,
+    pub dog: (f32, f32),
+    pub threshold: u8,
+    pub draw_edges: bool,
+    pub config: ConverterConfig,
+}
+
+impl AutoTuning {
+    pub fn build(&self) -> Result<Converter, ConvertError> {
+        self.config.build()
+    }
+}
+
+impl fmt::Display for AutoTuning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "spread: {}, noise: {:.1}, edge density: {:.2}%",
+            self.profile.spread,
+            self.profile.noise,
+            self.profile.edge_density * 100.0
+        )?;
+        writeln!(
+            f,
+            "auto levels: {}",
+            if self.auto_levels { "on" } else { "off" }
+        )?;
+        writeln!(f, "dog sigmas: {:.2}, {:.2}", self.dog.0, self.dog.1)?;
+        writeln!(f, "edge threshold: {}", self.threshold)?;
+        write!(f, "edges: {}", if self.draw_edges { "on" } else { "off" })
+    }
+}
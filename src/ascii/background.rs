@@ -0,0 +1,136 @@
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, ImageBuffer, Rgb, Rgba};
+use imageproc::filter::gaussian_blur_f32;
+
+/*
+* What the glyphs of a rendered image are drawn over. Solid uses the converter's bg_color,
+* VerticalGradient blends from the top color to the bottom one and BlurredImage puts a blurred
+* copy of the image, darkened by the darken ratio, behind the glyphs
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum BackgroundMode {
+    #[default]
+    Solid,
+    VerticalGradient(Rgb<u8>, Rgb<u8>),
+    BlurredImage {
+        sigma: f32,
+        darken: f32,
+    },
+}
+
+impl BackgroundMode {
+    pub fn fill(
+        &self,
+        w: u32,
+        h: u32,
+        bg_color: Rgb<u8>,
+        source: Option<&DynamicImage>,
+    ) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+        /*
+         * Background buffer of an output image of w x h pixels. BlurredImage falls back to bg_color
+         * when there is no source image
+         */
+        match *self {
+            BackgroundMode::Solid => ImageBuffer::from_pixel(w, h, bg_color),
+            BackgroundMode::VerticalGradient(top, bottom) => {
+                // Interpolated per output row so the gradient has no steps at cell borders
+                let last = h.saturating_sub(1).max(1) as f32;
+                let mut bufr = ImageBuffer::new(w, h);
+                for (y, row) in bufr.enumerate_rows_mut() {
+                    let t = y as f32 / last;
+                    let color = Rgb(std::array::from_fn(|c| {
+                        (top[c] as f32 + (bottom[c] as f32 - top[c] as f32) * t).round() as u8
+                    }));
+                    for (_, _, pixel) in row {
+                        *pixel = color;
+                    }
+                }
+                bufr
+            }
+            BackgroundMode::BlurredImage { sigma, darken } => {
+                let Some(source) = source else {
+                    return ImageBuffer::from_pixel(w, h, bg_color);
+                };
+                let resized = source.resize_exact(w, h, FilterType::Triangle).to_rgb8();
+                let mut bufr = gaussian_blur_f32(&resized, sigma);
+                let keep = 1.0 - darken;
+                for pixel in bufr.pixels_mut() {
+                    for c in pixel.0.iter_mut() {
+                        *c = (*c as f32 * keep).round() as u8;
+                    }
+                }
+                bufr
+            }
+        }
+    }
+}
+
+// Smallest luminance difference, out of 255, kept between a glyph and the background under it when
+// adapting glyph colors
+pub const MIN_GLYPH_CONTRAST: f32 = 96.0;
+
+pub(crate) fn luminance(color: Rgb<u8>) -> f32 {
+    // Rec. 709 weights, as used for the tile luminance
+    0.2126 * color[0] as f32 + 0.7152 * color[1] as f32 + 0.0722 * color[2] as f32
+}
+
+pub fn cell_luminance(
+    background: &ImageBuffer<Rgb<u8>, Vec<u8>>,
+    x: u32,
+    y: u32,
+    size: u32,
+) -> f32 {
+    /*
+     * Average luminance of the size x size background pixels of the cell whose top left corner is
+     * at x, y, clipped to the buffer
+     */
+    let (w, h) = background.dimensions();
+    let (cw, ch) = (size.min(w.saturating_sub(x)), size.min(h.saturating_sub(y)));
+    if cw == 0 || ch == 0 {
+        return 0.0;
+    }
+    let total: f32 = background
+        .view(x, y, cw, ch)
+        .pixels()
+        .map(|(_, _, pixel)| luminance(pixel))
+        .sum();
+    total / (cw * ch) as f32
+}
+
+pub fn contrast_glyph_color(color: Rgb<u8>, bg_luminance: f32) -> Rgb<u8> {
+    /*
+     * Glyph color nudged toward black over light backgrounds and toward white over dark ones, just
+     * far enough to be MIN_GLYPH_CONTRAST away from the background. Colors that already stand out
+     * are kept
+     */
+    let glyph = luminance(color);
+    let (target, goal) = if bg_luminance >= 127.5 {
+        (0.0, bg_luminance - MIN_GLYPH_CONTRAST)
+    } else {
+        (255.0, bg_luminance + MIN_GLYPH_CONTRAST)
+    };
+    if (glyph - bg_luminance).abs() >= MIN_GLYPH_CONTRAST {
+        return color;
+    }
+    // Luminance is linear in the blend, so t is exact
+    let span = target - glyph;
+    let t = if span.abs() < f32::EPSILON {
+        1.0
+    } else {
+        ((goal - glyph) / span).clamp(0.0, 1.0)
+    };
+    Rgb(std::array::from_fn(|c| {
+        (color[c] as f32 + (target - color[c] as f32) * t).round() as u8
+    }))
+}
+
+pub fn composite_over(glyph: Rgba<u8>, dst: Rgb<u8>) -> Rgb<u8> {
+    /*
+     * Premultiplied glyph pixel, its alpha being the glyph coverage, drawn over a destination
+     * pixel. Full coverage replaces the destination and no coverage leaves it as is
+     */
+    let keep = 1.0 - glyph[3] as f32 / 255.0;
+    Rgb(std::array::from_fn(|c| {
+        (glyph[c] as f32 + dst[c] as f32 * keep).round().min(255.0) as u8
+    }))
+}
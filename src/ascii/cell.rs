@@ -0,0 +1,41 @@
+use super::char_set::CharacterSet;
+use ndarray::{Array2, ArrayView2};
+
+/*
+* Grid cell as an index into the tile or edge set of a CharacterSet. Characters are only looked
+* up when the grid is drawn or written out, so nothing depends on what the sets contain
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CellValue {
+    Tile(usize),
+    Edge(usize),
+}
+
+impl CellValue {
+    pub fn to_char(self, charset: &CharacterSet) -> char {
+        match self {
+            CellValue::Tile(idx) => charset.tile[idx],
+            CellValue::Edge(idx) => charset.edge[idx],
+        }
+    }
+}
+
+pub fn cells_to_chars(cells: &ArrayView2<CellValue>, charset: &CharacterSet) -> Array2<char> {
+    cells.mapv(|cell| cell.to_char(charset))
+}
+
+pub fn chars_to_cells(grid: &ArrayView2<char>, charset: &CharacterSet) -> Array2<CellValue> {
+    /*
+     * Cells of a character grid read back from text. A character of both sets reads as a tile,
+     * as cells_to_chars can not tell them apart, and one of neither as the first tile
+     */
+    grid.mapv(|ch| {
+        match (
+            charset.tile.iter().position(|&c| c == ch),
+            charset.edge.iter().position(|&c| c == ch),
+        ) {
+            (None, Some(idx)) => CellValue::Edge(idx),
+            (idx, _) => CellValue::Tile(idx.unwrap_or(0)),
+        }
+    })
+}
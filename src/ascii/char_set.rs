@@ -1,11 +1,189 @@
+use super::error::ConvertError;
+use super::font_loader::{FontLoader, FontSettings};
+use ab_glyph::{Font, PxScale, ScaleFont};
+use image::{GrayImage, ImageBuffer, Luma, Rgb};
+use imageproc::drawing::{draw_hollow_rect_mut, draw_text_mut, text_size};
+use imageproc::rect::Rect;
+use ndarray::Array2;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+// Space in pixels around the glyph and the label of a preview cell
+const PREVIEW_PAD: u32 = 4;
+// Smallest label text in pixels, so the densities stay readable under small glyphs
+const MIN_LABEL_PX: f32 = 10.0;
+const PREVIEW_BG: Rgb<u8> = Rgb([0, 0, 0]);
+const PREVIEW_GLYPH: Rgb<u8> = Rgb([255, 255, 255]);
+const PREVIEW_LABEL: Rgb<u8> = Rgb([160, 160, 160]);
+const PREVIEW_BORDER: Rgb<u8> = Rgb([60, 60, 60]);
+
+pub fn quantize_luma(luma: u8, levels: usize) -> usize {
+    /*
+     * Index of the level a luminance value falls into when 0..=255 is split into levels evenly
+     * sized buckets, darkest first
+     */
+    ((luma as f32 / 255.0) * levels.saturating_sub(1) as f32).floor() as usize
+}
+
+/*
+* How a tile picks its character. LuminanceAndVariance moves busy tiles, those with a high
+* luminance variance in the original image, up the ramp by up to variance_weight times its length
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum TileMapping {
+    #[default]
+    Luminance,
+    LuminanceAndVariance {
+        variance_weight: f32,
+    },
+}
+
+fn variance_bias(bucket: usize, levels: usize, variance: f32, variance_weight: f32) -> usize {
+    // bucket moved up by the variance, scaled to 0..=1 from its 0..=0.25 range, times
+    // variance_weight of the levels
+    let last = levels.saturating_sub(1);
+    let bias = (variance * 4.0).clamp(0.0, 1.0) * variance_weight * last as f32;
+    (bucket + bias.round() as usize).min(last)
+}
+
+pub fn quantize_luma_biased(luma: u8, levels: usize, variance: f32, variance_weight: f32) -> usize {
+    /*
+     * quantize_luma moved up by the variance, scaled to 0..=1 from its 0..=0.25 range, times
+     * variance_weight of the levels. Stays within the levels
+     */
+    variance_bias(
+        quantize_luma(luma, levels),
+        levels,
+        variance,
+        variance_weight,
+    )
+}
+
+/*
+* Where the luminance range is split into tile buckets. Linear splits 0..=255 evenly whatever the
+* image. Percentile splits it at the quantiles of the luminance of the image, so every character
+* of the ramp covers about the same share of the cells however skewed the image is
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BucketMode {
+    #[default]
+    Linear,
+    Percentile,
+}
+
+pub fn percentile_boundaries(luma: impl IntoIterator<Item = u8>, levels: usize) -> Vec<u8> {
+    /*
+     * The levels - 1 luminances where the buckets after the first start, from the histogram of
+     * luma. Bucket k starts at the lowest luminance with at least k / levels of the cells under
+     * it. Many cells of one luminance cannot be split, so a bucket can take more than its share
+     * and the ones after it less
+     */
+    let mut histogram = [0usize; 256];
+    let mut total = 0;
+    for l in luma {
+        histogram[l as usize] += 1;
+        total += 1;
+    }
+    let mut boundaries = Vec::with_capacity(levels.saturating_sub(1));
+    // Cells darker than value
+    let mut below = 0;
+    let mut value = 0;
+    for k in 1..levels {
+        let rank = k * total / levels;
+        while below < rank {
+            below += histogram[value];
+            value += 1;
+        }
+        // Only the brightest cells left, the bucket starts at white
+        boundaries.push(value.min(255) as u8);
+    }
+    boundaries
+}
+
+pub fn quantize_luma_percentile(luma: u8, boundaries: &[u8]) -> usize {
+    // Index of the bucket luma falls into, the number of boundaries at or under it
+    boundaries.partition_point(|&b| b <= luma)
+}
+
+/*
+* Per cell inputs of the luminance to character mapping besides the luminance itself. variance is
+* the luminance variance, in 0..=0.25, of the original tile under the cell and only moves the
+* character with LuminanceAndVariance
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct MappingOptions {
+    pub tile_mapping: TileMapping,
+    pub variance: f32,
+}
+
+impl MappingOptions {
+    pub fn new(tile_mapping: TileMapping) -> Self {
+        MappingOptions {
+            tile_mapping,
+            variance: 0.0,
+        }
+    }
+
+    pub fn with_variance(mut self, variance: f32) -> Self {
+        self.variance = variance;
+        self
+    }
+}
+
+pub fn jitter_tiles(tiles: &mut Array2<usize>, levels: usize, amount: f32, seed: u64) {
+    /*
+     * Move every tile one level up or down the ramp with probability amount, so flat areas stop
+     * repeating one character. Cells are visited in row order from an RNG seeded with seed, so
+     * the result only depends on the seed and the grid. Nothing changes at an amount of 0
+     */
+    if amount <= 0.0 || levels < 2 {
+        return;
+    }
+    let mut rng = StdRng::seed_from_u64(seed);
+    let last = levels - 1;
+    for tile in tiles.iter_mut() {
+        if !rng.gen_bool(amount.min(1.0) as f64) {
+            continue;
+        }
+        *tile = if rng.gen_bool(0.5) {
+            (*tile + 1).min(last)
+        } else {
+            tile.saturating_sub(1)
+        };
+    }
+}
+
+pub fn glyph_density(font: &impl Font, ch: char, cell_px: u32) -> f32 {
+    /*
+     * Share of a cell of cell_px pixels a character covers, drawn the way the converter draws it.
+     * Antialiased pixels count by their coverage, so an empty cell is 0 and a filled one 1
+     */
+    let cell_px = cell_px.max(1);
+    let mut cell = GrayImage::new(cell_px, cell_px);
+    let scale = PxScale::from(cell_px as f32);
+    draw_text_mut(&mut cell, Luma([255]), 0, 0, scale, font, &ch.to_string());
+    let ink: u64 = cell.pixels().map(|&Luma([l])| l as u64).sum();
+    ink as f32 / (255 * cell_px as u64 * cell_px as u64) as f32
+}
+
+fn preview_font(font_settings: &FontSettings) -> Result<impl Font, ConvertError> {
+    if font_settings.font_size == 0 {
+        return Err(ConvertError::InvalidSetting {
+            field: "font_size",
+            reason: "must be at least 1",
+        });
+    }
+    FontLoader::load_font(&font_settings.font_path)
+}
+
 #[derive(Clone, Debug)]
 pub struct CharacterSet {
     pub tile: Vec<char>,
     pub edge: Vec<char>,
 }
 
-impl CharacterSet {
-    pub fn default() -> Self {
+impl Default for CharacterSet {
+    fn default() -> Self {
         CharacterSet {
             tile: vec![
                 ' ', '.', ',', '*', ':', 'c', 'o', 'P', 'O', '?', '%', '&', '@',
@@ -15,14 +193,39 @@ impl CharacterSet {
             edge: vec![' ', '_', '|', '/', '\\'],
         }
     }
+}
 
-    pub fn new(tile: &Vec<char>) -> Self {
+impl CharacterSet {
+    pub fn new(tile: &[char]) -> Self {
         CharacterSet {
-            tile: tile.clone(),
+            tile: tile.to_vec(),
             edge: vec![' ', '_', '|', '/', '\\'],
         }
     }
 
+    pub fn resampled(&self, levels: usize) -> Result<Self, ConvertError> {
+        /*
+         * Set whose tile ramp is levels characters picked evenly from this one, keeping the first
+         * and last. Characters are never repeated, so asking for fewer than 2 levels or more than
+         * the ramp has is an error
+         */
+        let len = self.tile.len();
+        if levels < 2 || levels > len {
+            return Err(ConvertError::InvalidSetting {
+                field: "tile_levels",
+                reason: "must be between 2 and the number of tile characters",
+            });
+        }
+        let last = (len - 1) as f32;
+        let step = (levels - 1) as f32;
+        Ok(CharacterSet {
+            tile: (0..levels)
+                .map(|i| self.tile[(i as f32 * last / step).round() as usize])
+                .collect(),
+            edge: self.edge.clone(),
+        })
+    }
+
     pub fn get_tile_mapping_size(&self) -> u8 {
         self.tile.len() as u8
     }
@@ -31,6 +234,53 @@ impl CharacterSet {
         self.edge.len() as u8
     }
 
+    pub fn get_tile_char(&self, luma: u8) -> char {
+        self.char_for_luminance(luma, &MappingOptions::default())
+    }
+
+    pub fn tile_index_for_luminance(&self, luma: u8, opts: &MappingOptions) -> usize {
+        /*
+         * Index into the tile ramp of the character a cell of luminance luma gets, the mapping
+         * the converter runs on every cell before the jitter and weight map, which depend on the
+         * whole grid. Always below the ramp length, or 0 for an empty ramp
+         */
+        // The length is used directly since sets longer than u8::MAX would wrap the mapping size
+        let levels = self.tile.len();
+        match opts.tile_mapping {
+            TileMapping::Luminance => quantize_luma(luma, levels),
+            TileMapping::LuminanceAndVariance { variance_weight } => {
+                quantize_luma_biased(luma, levels, opts.variance, variance_weight)
+            }
+        }
+    }
+
+    pub fn tile_index_in_buckets(
+        &self,
+        luma: u8,
+        boundaries: &[u8],
+        opts: &MappingOptions,
+    ) -> usize {
+        /*
+         * tile_index_for_luminance with the buckets split at boundaries, as computed by
+         * percentile_boundaries for the ramp, rather than evenly
+         */
+        let levels = self.tile.len();
+        let bucket = quantize_luma_percentile(luma, boundaries).min(levels.saturating_sub(1));
+        match opts.tile_mapping {
+            TileMapping::Luminance => bucket,
+            TileMapping::LuminanceAndVariance { variance_weight } => {
+                variance_bias(bucket, levels, opts.variance, variance_weight)
+            }
+        }
+    }
+
+    pub fn char_for_luminance(&self, luma: u8, opts: &MappingOptions) -> char {
+        /*
+         * Character at tile_index_for_luminance. Panics on an empty tile ramp
+         */
+        self.tile[self.tile_index_for_luminance(luma, opts)]
+    }
+
     pub fn find_edge_char_index(&self, character: &char) -> Option<usize> {
         self.edge.iter().position(|&r| r == *character)
     }
@@ -38,4 +288,85 @@ impl CharacterSet {
     pub fn find_tile_char_index(&self, character: &char) -> Option<usize> {
         self.tile.iter().position(|&r| r == *character)
     }
+
+    pub fn tile_densities(
+        &self,
+        font_settings: &FontSettings,
+    ) -> Result<Vec<(char, f32)>, ConvertError> {
+        /*
+         * Every tile character with its glyph_density at the font size, from the sparsest to the
+         * densest. Characters of equal density keep their order in the ramp
+         */
+        let font = preview_font(font_settings)?;
+        Ok(sorted_densities(&self.tile, &font, font_settings.font_size))
+    }
+
+    pub fn preview(
+        &self,
+        font_settings: &FontSettings,
+    ) -> Result<ImageBuffer<Rgb<u8>, Vec<u8>>, ConvertError> {
+        /*
+         * Reference image for designing a ramp: every tile character drawn at the font size in a
+         * cell of its own with its density as a percentage beneath, from the sparsest to the
+         * densest as tile_densities orders them, and the edge characters in a second row. Cells
+         * are wide enough for the widest label
+         */
+        let font = preview_font(font_settings)?;
+        let font_size = font_settings.font_size;
+        let tiles = sorted_densities(&self.tile, &font, font_size);
+        // The first edge character stands for cells without an edge
+        let edges: Vec<(char, f32)> = self
+            .edge
+            .iter()
+            .skip(1)
+            .map(|&ch| (ch, glyph_density(&font, ch, font_size)))
+            .collect();
+
+        let glyph_scale = PxScale::from(font_size as f32);
+        let label_scale = PxScale::from((font_size as f32 * 0.4).max(MIN_LABEL_PX));
+        let label_h = font.as_scaled(label_scale).height().ceil() as u32;
+        let label_w = text_size(label_scale, &font, "100%").0;
+        let cell_w = font_size.max(label_w) + 2 * PREVIEW_PAD;
+        let cell_h = font_size + label_h + 3 * PREVIEW_PAD;
+        let cols = tiles.len().max(edges.len()).max(1) as u32;
+
+        let mut img = ImageBuffer::from_pixel(cols * cell_w, 2 * cell_h, PREVIEW_BG);
+        for (row, chars) in [tiles, edges].iter().enumerate() {
+            for (col, &(ch, density)) in chars.iter().enumerate() {
+                let (x, y) = (col as u32 * cell_w, row as u32 * cell_h);
+                let cell = Rect::at(x as i32, y as i32).of_size(cell_w, cell_h);
+                draw_hollow_rect_mut(&mut img, cell, PREVIEW_BORDER);
+                draw_text_mut(
+                    &mut img,
+                    PREVIEW_GLYPH,
+                    (x + (cell_w - font_size) / 2) as i32,
+                    (y + PREVIEW_PAD) as i32,
+                    glyph_scale,
+                    &font,
+                    &ch.to_string(),
+                );
+                let label = format!("{:.0}%", density * 100.0);
+                let (w, _) = text_size(label_scale, &font, &label);
+                draw_text_mut(
+                    &mut img,
+                    PREVIEW_LABEL,
+                    (x + cell_w.saturating_sub(w) / 2) as i32,
+                    (y + font_size + 2 * PREVIEW_PAD) as i32,
+                    label_scale,
+                    &font,
+                    &label,
+                );
+            }
+        }
+        Ok(img)
+    }
+}
+
+fn sorted_densities(chars: &[char], font: &impl Font, font_size: u32) -> Vec<(char, f32)> {
+    let mut densities: Vec<(char, f32)> = chars
+        .iter()
+        .map(|&ch| (ch, glyph_density(font, ch, font_size)))
+        .collect();
+    densities.sort_by(|a, b| a.1.total_cmp(&b.1));
+    densities
 }
@@ -0,0 +1,1039 @@
+use super::background::BackgroundMode;
+use super::char_set::{BucketMode, CharacterSet, TileMapping};
+use super::converter::{Converter, DEFAULT_MAX_INPUT_PIXELS};
+use super::detail::DetailMode;
+use super::edge_color::EdgeColorMode;
+use super::error::ConvertError;
+use super::font_loader::FontSettings;
+use super::post_effect::PostEffect;
+use super::tone_curve::ToneCurve;
+use super::watermark::{Corner, Watermark};
+use super::weight_map::{WeightMap, WeightSource};
+use crate::image_manip::color::{ColorProcessor, SaturationBoost, WhiteBalance};
+use crate::image_manip::edge_detect::{ColorSobel, EdgeDetect, EdgeSource, Sobel, StructureTensor};
+use crate::image_manip::edge_flow::EdgeTangentFlow;
+use crate::image_manip::edge_processor::{EdgeSmoothing, MagnitudeReduce};
+use crate::image_manip::orientation::Orientation;
+use crate::image_manip::processing::{
+    AdaptiveThreshold, AutoLevels, BilateralFilter, DoG, F32Chain, MedianBlur, Normalization,
+    Processor, Sharpen3x3, SharpenGaussian, Thin, Threshold, ThresholdMode,
+};
+use crate::image_manip::tile_stats::TileSampling;
+use crate::image_manip::util::ResizeFilter;
+use crate::output::quantize::OutputQuantize;
+use crate::output::trim::TrimMode;
+use image::Rgb;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+#[cfg(feature = "serde")]
+use std::fs;
+#[cfg(feature = "serde")]
+use std::path::Path;
+#[cfg(feature = "serde")]
+use xxhash_rust::xxh3::xxh3_64;
+
+/*
+* Plain data description of a preprocessor so pipelines can be stored in config files
+*/
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
+pub enum ProcessorConfig {
+    DoG {
+        sigma_1: f32,
+        sigma_2: f32,
+    },
+    MedianBlur {
+        kernel_size: u32,
+        // Radius along one axis when it differs from kernel_size
+        #[cfg_attr(feature = "serde", serde(default))]
+        x_radius: Option<u32>,
+        #[cfg_attr(feature = "serde", serde(default))]
+        y_radius: Option<u32>,
+    },
+    BilateralFilter {
+        window_size: u32,
+        sigma_color: f32,
+        sigma_spatial: f32,
+    },
+    Threshold {
+        threshold: u8,
+        #[cfg_attr(feature = "serde", serde(default))]
+        mode: ThresholdModeConfig,
+    },
+    Sharpen3x3,
+    SharpenGaussian {
+        sigma: f32,
+        amount: f32,
+    },
+    Thin,
+    AutoLevels {
+        clip: f32,
+    },
+    AdaptiveThreshold {
+        block_radius: u32,
+        offset: u8,
+    },
+}
+
+/*
+* Plain data description of the threshold mode
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
+pub enum ThresholdModeConfig {
+    Binary,
+    BinaryInverted,
+    Truncate,
+    ToZero,
+    #[default]
+    ToZeroInverted,
+    Band {
+        low: u8,
+        high: u8,
+    },
+}
+
+impl ThresholdModeConfig {
+    pub fn build(&self) -> ThresholdMode {
+        match *self {
+            ThresholdModeConfig::Binary => ThresholdMode::Binary,
+            ThresholdModeConfig::BinaryInverted => ThresholdMode::BinaryInverted,
+            ThresholdModeConfig::Truncate => ThresholdMode::Truncate,
+            ThresholdModeConfig::ToZero => ThresholdMode::ToZero,
+            ThresholdModeConfig::ToZeroInverted => ThresholdMode::ToZeroInverted,
+            ThresholdModeConfig::Band { low, high } => ThresholdMode::Band { low, high },
+        }
+    }
+}
+
+impl ProcessorConfig {
+    pub fn build(&self) -> Box<dyn Processor<u8, u8>> {
+        match *self {
+            ProcessorConfig::DoG { sigma_1, sigma_2 } => Box::new(DoG::new(sigma_1, sigma_2)),
+            ProcessorConfig::MedianBlur {
+                kernel_size,
+                x_radius,
+                y_radius,
+            } => Box::new(MedianBlur::asymmetric(
+                x_radius.unwrap_or(kernel_size),
+                y_radius.unwrap_or(kernel_size),
+            )),
+            ProcessorConfig::BilateralFilter {
+                window_size,
+                sigma_color,
+                sigma_spatial,
+            } => Box::new(BilateralFilter::new(
+                window_size,
+                sigma_color,
+                sigma_spatial,
+            )),
+            ProcessorConfig::Threshold { threshold, mode } => {
+                Box::new(Threshold::new(threshold).with_mode(mode.build()))
+            }
+            ProcessorConfig::Sharpen3x3 => Box::new(Sharpen3x3::new()),
+            ProcessorConfig::SharpenGaussian { sigma, amount } => {
+                Box::new(SharpenGaussian::new(sigma, amount))
+            }
+            ProcessorConfig::Thin => Box::new(Thin::new()),
+            ProcessorConfig::AutoLevels { clip } => Box::new(AutoLevels::new(clip)),
+            ProcessorConfig::AdaptiveThreshold {
+                block_radius,
+                offset,
+            } => Box::new(AdaptiveThreshold::new(block_radius, offset)),
+        }
+    }
+
+    pub fn build_promote(&self) -> Option<Box<dyn Processor<u8, f32>>> {
+        /*
+         * First stage of an f32 chain, None for processors that only exist on u8
+         */
+        match *self {
+            ProcessorConfig::DoG { sigma_1, sigma_2 } => Some(Box::new(DoG::new(sigma_1, sigma_2))),
+            ProcessorConfig::Threshold { threshold, mode } => {
+                Some(Box::new(Threshold::new(threshold).with_mode(mode.build())))
+            }
+            ProcessorConfig::SharpenGaussian { sigma, amount } => {
+                Some(Box::new(SharpenGaussian::new(sigma, amount)))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn build_f32(&self) -> Option<Box<dyn Processor<f32, f32>>> {
+        match *self {
+            ProcessorConfig::DoG { sigma_1, sigma_2 } => Some(Box::new(DoG::new(sigma_1, sigma_2))),
+            ProcessorConfig::Threshold { threshold, mode } => {
+                Some(Box::new(Threshold::new(threshold).with_mode(mode.build())))
+            }
+            ProcessorConfig::SharpenGaussian { sigma, amount } => {
+                Some(Box::new(SharpenGaussian::new(sigma, amount)))
+            }
+            _ => None,
+        }
+    }
+}
+
+/*
+* Plain data description of a color preprocessor
+*/
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
+pub enum ColorProcessorConfig {
+    WhiteBalance { temperature: f32 },
+    SaturationBoost { amount: f32 },
+}
+
+impl ColorProcessorConfig {
+    pub fn build(&self) -> Box<dyn ColorProcessor> {
+        match *self {
+            ColorProcessorConfig::WhiteBalance { temperature } => {
+                Box::new(WhiteBalance::new(temperature))
+            }
+            ColorProcessorConfig::SaturationBoost { amount } => {
+                Box::new(SaturationBoost::new(amount))
+            }
+        }
+    }
+}
+
+/*
+* Plain data description of how the f32 edge stages are brought back to u8
+*/
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum NormalizationConfig {
+    #[default]
+    Clamp,
+    Abs,
+    MinMax,
+}
+
+impl NormalizationConfig {
+    pub fn build(&self) -> Normalization {
+        match *self {
+            NormalizationConfig::Clamp => Normalization::Clamp,
+            NormalizationConfig::Abs => Normalization::Abs,
+            NormalizationConfig::MinMax => Normalization::MinMax,
+        }
+    }
+}
+
+/*
+* Plain data description of the edge detector
+*/
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
+pub enum EdgeDetectorConfig {
+    Sobel {
+        // Gradient magnitude under which a pixel gets no edge
+        #[cfg_attr(feature = "serde", serde(default))]
+        min_magnitude: f32,
+    },
+    StructureTensor {
+        sigma: f32,
+        min_coherence: f32,
+    },
+    // Sobel per color channel, the only detector edge_source color works with
+    ColorSobel {
+        #[cfg_attr(feature = "serde", serde(default))]
+        min_magnitude: f32,
+    },
+}
+
+impl Default for EdgeDetectorConfig {
+    fn default() -> Self {
+        EdgeDetectorConfig::Sobel { min_magnitude: 0.0 }
+    }
+}
+
+impl EdgeDetectorConfig {
+    pub fn build(&self) -> Box<dyn EdgeDetect<u8, u8>> {
+        match *self {
+            EdgeDetectorConfig::Sobel { min_magnitude } => {
+                Box::new(Sobel::new().with_min_magnitude(min_magnitude))
+            }
+            EdgeDetectorConfig::StructureTensor {
+                sigma,
+                min_coherence,
+            } => Box::new(StructureTensor::new(sigma, min_coherence)),
+            EdgeDetectorConfig::ColorSobel { min_magnitude } => {
+                Box::new(ColorSobel::new().with_min_magnitude(min_magnitude))
+            }
+        }
+    }
+}
+
+/*
+* Plain data description of the image edges are detected on
+*/
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum EdgeSourceConfig {
+    #[default]
+    Grayscale,
+    Color,
+}
+
+impl EdgeSourceConfig {
+    pub fn build(&self) -> EdgeSource {
+        match *self {
+            EdgeSourceConfig::Grayscale => EdgeSource::Grayscale,
+            EdgeSourceConfig::Color => EdgeSource::Color,
+        }
+    }
+}
+
+/*
+* Plain data description of the edge tangent flow stage
+*/
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct EdgeFlowConfig {
+    pub iterations: u32,
+    pub radius: u32,
+}
+
+impl EdgeFlowConfig {
+    pub fn build(&self) -> EdgeTangentFlow {
+        EdgeTangentFlow::new(self.iterations, self.radius)
+    }
+}
+
+/*
+* Plain data description of the color reduction of PNG outputs
+*/
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct OutputQuantizeConfig {
+    pub colors: u16,
+    pub dither: bool,
+}
+
+impl OutputQuantizeConfig {
+    pub fn build(&self) -> OutputQuantize {
+        OutputQuantize::new(self.colors, self.dither)
+    }
+}
+
+/*
+* Plain data description of the edge map mode filter, a radius of 0 turns it off
+*/
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct EdgeSmoothingConfig {
+    pub radius: usize,
+    pub min_disagreement: usize,
+}
+
+impl Default for EdgeSmoothingConfig {
+    fn default() -> Self {
+        let smoothing = EdgeSmoothing::default();
+        EdgeSmoothingConfig {
+            radius: smoothing.radius,
+            min_disagreement: smoothing.min_disagreement,
+        }
+    }
+}
+
+impl EdgeSmoothingConfig {
+    pub fn build(&self) -> EdgeSmoothing {
+        EdgeSmoothing::new(self.radius, self.min_disagreement)
+    }
+}
+
+/*
+* Plain data description of the tile mapping
+*/
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
+pub enum TileMappingConfig {
+    #[default]
+    Luminance,
+    LuminanceAndVariance {
+        variance_weight: f32,
+    },
+}
+
+impl TileMappingConfig {
+    pub fn build(&self) -> TileMapping {
+        match *self {
+            TileMappingConfig::Luminance => TileMapping::Luminance,
+            TileMappingConfig::LuminanceAndVariance { variance_weight } => {
+                TileMapping::LuminanceAndVariance { variance_weight }
+            }
+        }
+    }
+}
+
+/*
+* Plain data description of where the tile buckets are split
+*/
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum BucketModeConfig {
+    #[default]
+    Linear,
+    Percentile,
+}
+
+impl BucketModeConfig {
+    pub fn build(&self) -> BucketMode {
+        match *self {
+            BucketModeConfig::Linear => BucketMode::Linear,
+            BucketModeConfig::Percentile => BucketMode::Percentile,
+        }
+    }
+}
+
+/*
+* Plain data description of where the weight map comes from
+*/
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
+pub enum WeightSourceConfig {
+    #[default]
+    Saliency,
+    Image {
+        path: String,
+    },
+}
+
+/*
+* Plain data description of the weight map, the image source is read when the converter is built
+*/
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct WeightMapConfig {
+    pub source: WeightSourceConfig,
+    pub min_levels: usize,
+    pub gamma: f32,
+}
+
+impl Default for WeightMapConfig {
+    fn default() -> Self {
+        WeightMapConfig {
+            source: WeightSourceConfig::default(),
+            min_levels: 4,
+            gamma: 1.0,
+        }
+    }
+}
+
+impl WeightMapConfig {
+    pub fn build(&self) -> Result<WeightMap, ConvertError> {
+        let source = match &self.source {
+            WeightSourceConfig::Saliency => WeightSource::Saliency,
+            WeightSourceConfig::Image { path } => WeightSource::Image(
+                image::open(path)
+                    .map_err(|e| ConvertError::ConfigError(format!("weight map {}: {}", path, e)))?
+                    .to_luma8(),
+            ),
+        };
+        Ok(WeightMap::new(source, self.min_levels, self.gamma))
+    }
+}
+
+/*
+* Plain data description of the tile sampling
+*/
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum TileSamplingConfig {
+    #[default]
+    Resize,
+    ExactBoxAverage,
+    TrimmedMean {
+        trim: f32,
+    },
+}
+
+impl TileSamplingConfig {
+    pub fn build(&self) -> TileSampling {
+        match *self {
+            TileSamplingConfig::Resize => TileSampling::Resize,
+            TileSamplingConfig::ExactBoxAverage => TileSampling::ExactBoxAverage,
+            TileSamplingConfig::TrimmedMean { trim } => TileSampling::TrimmedMean { trim },
+        }
+    }
+}
+
+/*
+* Plain data description of the resize filter
+*/
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ResizeFilterConfig {
+    #[default]
+    Triangle,
+    Nearest,
+}
+
+impl ResizeFilterConfig {
+    pub fn build(&self) -> ResizeFilter {
+        match *self {
+            ResizeFilterConfig::Triangle => ResizeFilter::Triangle,
+            ResizeFilterConfig::Nearest => ResizeFilter::Nearest,
+        }
+    }
+}
+
+/*
+* Plain data description of the orientation
+*/
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum OrientationConfig {
+    #[default]
+    Normal,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    FlipH,
+    FlipV,
+}
+
+impl OrientationConfig {
+    pub fn build(&self) -> Orientation {
+        match *self {
+            OrientationConfig::Normal => Orientation::Normal,
+            OrientationConfig::Rotate90 => Orientation::Rotate90,
+            OrientationConfig::Rotate180 => Orientation::Rotate180,
+            OrientationConfig::Rotate270 => Orientation::Rotate270,
+            OrientationConfig::FlipH => Orientation::FlipH,
+            OrientationConfig::FlipV => Orientation::FlipV,
+        }
+    }
+}
+
+/*
+* Plain data description of the detail mode
+*/
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
+pub enum DetailModeConfig {
+    #[default]
+    Single,
+    TwoScale {
+        fine_factor: u32,
+        variance_threshold: f32,
+    },
+}
+
+impl DetailModeConfig {
+    pub fn build(&self) -> DetailMode {
+        match *self {
+            DetailModeConfig::Single => DetailMode::Single,
+            DetailModeConfig::TwoScale {
+                fine_factor,
+                variance_threshold,
+            } => DetailMode::TwoScale {
+                fine_factor,
+                variance_threshold,
+            },
+        }
+    }
+}
+
+/*
+* Plain data description of the background mode
+*/
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
+pub enum BackgroundConfig {
+    #[default]
+    Solid,
+    VerticalGradient {
+        top: [u8; 3],
+        bottom: [u8; 3],
+    },
+    BlurredImage {
+        sigma: f32,
+        darken: f32,
+    },
+}
+
+impl BackgroundConfig {
+    pub fn build(&self) -> BackgroundMode {
+        match *self {
+            BackgroundConfig::Solid => BackgroundMode::Solid,
+            BackgroundConfig::VerticalGradient { top, bottom } => {
+                BackgroundMode::VerticalGradient(Rgb(top), Rgb(bottom))
+            }
+            BackgroundConfig::BlurredImage { sigma, darken } => {
+                BackgroundMode::BlurredImage { sigma, darken }
+            }
+        }
+    }
+}
+
+/*
+* Plain data description of the edge color mode
+*/
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
+pub enum EdgeColorConfig {
+    #[default]
+    SameAsTile,
+    Fixed {
+        color: [u8; 3],
+    },
+    Brightened {
+        factor: f32,
+    },
+    Magnitude {
+        reduce: MagnitudeReduceConfig,
+        floor: f32,
+    },
+}
+
+impl EdgeColorConfig {
+    pub fn build(&self) -> EdgeColorMode {
+        match *self {
+            EdgeColorConfig::SameAsTile => EdgeColorMode::SameAsTile,
+            EdgeColorConfig::Fixed { color } => EdgeColorMode::Fixed(Rgb(color)),
+            EdgeColorConfig::Brightened { factor } => EdgeColorMode::Brightened { factor },
+            EdgeColorConfig::Magnitude { ref reduce, floor } => EdgeColorMode::Magnitude {
+                reduce: reduce.build(),
+                floor,
+            },
+        }
+    }
+}
+
+/*
+* Plain data description of how edge magnitudes are reduced over a tile
+*/
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum MagnitudeReduceConfig {
+    #[default]
+    Max,
+    Mean,
+}
+
+impl MagnitudeReduceConfig {
+    pub fn build(&self) -> MagnitudeReduce {
+        match *self {
+            MagnitudeReduceConfig::Max => MagnitudeReduce::Max,
+            MagnitudeReduceConfig::Mean => MagnitudeReduce::Mean,
+        }
+    }
+}
+
+/*
+* Plain data description of a watermark corner
+*/
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum CornerConfig {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+}
+
+impl CornerConfig {
+    pub fn build(&self) -> Corner {
+        match *self {
+            CornerConfig::TopLeft => Corner::TopLeft,
+            CornerConfig::TopRight => Corner::TopRight,
+            CornerConfig::BottomLeft => Corner::BottomLeft,
+            CornerConfig::BottomRight => Corner::BottomRight,
+        }
+    }
+}
+
+/*
+* Plain data description of the watermark
+*/
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct WatermarkConfig {
+    pub text: String,
+    pub corner: CornerConfig,
+    pub color: [u8; 3],
+    pub padding: u32,
+    pub scale: f32,
+}
+
+impl Default for WatermarkConfig {
+    fn default() -> Self {
+        let watermark = Watermark::new("");
+        WatermarkConfig {
+            text: watermark.text,
+            corner: CornerConfig::default(),
+            color: watermark.color.0,
+            padding: watermark.padding,
+            scale: watermark.scale,
+        }
+    }
+}
+
+impl WatermarkConfig {
+    pub fn build(&self) -> Watermark {
+        Watermark::new(&self.text)
+            .with_corner(self.corner.build())
+            .with_color(Rgb(self.color))
+            .with_padding(self.padding)
+            .with_scale(self.scale)
+    }
+}
+
+/*
+* Plain data description of a post effect
+*/
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(tag = "type", rename_all = "snake_case"))]
+pub enum PostEffectConfig {
+    Scanlines { period: u32, factor: f32 },
+    ChromaticAberration { shift: u32 },
+    Vignette { strength: f32 },
+}
+
+impl PostEffectConfig {
+    pub fn build(&self) -> PostEffect {
+        match *self {
+            PostEffectConfig::Scanlines { period, factor } => {
+                PostEffect::Scanlines { period, factor }
+            }
+            PostEffectConfig::ChromaticAberration { shift } => {
+                PostEffect::ChromaticAberration { shift }
+            }
+            PostEffectConfig::Vignette { strength } => PostEffect::Vignette { strength },
+        }
+    }
+}
+
+/*
+* Plain data description of the trimming of text outputs
+*/
+#[derive(Clone, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum TrimModeConfig {
+    #[default]
+    None,
+    TrailingSpaces,
+    Bounding,
+}
+
+impl TrimModeConfig {
+    pub fn build(&self) -> TrimMode {
+        match *self {
+            TrimModeConfig::None => TrimMode::None,
+            TrimModeConfig::TrailingSpaces => TrimMode::TrailingSpaces,
+            TrimModeConfig::Bounding => TrimMode::Bounding,
+        }
+    }
+}
+
+/*
+* Serializable settings of a Converter. Fields missing from a config file take the values of
+* Converter::default()
+*/
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(default))]
+pub struct ConverterConfig {
+    pub font_size: u32,
+    // Size in pixels of a cell in rendered images, None draws cells at font_size. The grid keeps
+    // being sampled at font_size
+    pub render_cell_px: Option<u32>,
+    pub font_path: String,
+    pub tile_chars: String,
+    // Number of characters picked evenly from tile_chars, keeping the first and last
+    pub tile_levels: Option<usize>,
+    pub tile_mapping: TileMappingConfig,
+    // Split the tile buckets at the quantiles of the image's luminance rather than evenly
+    pub bucket_mode: BucketModeConfig,
+    pub tile_sampling: TileSamplingConfig,
+    // Shortens the tile ramp of low weight cells, None keeps the whole ramp everywhere
+    pub weight_map: Option<WeightMapConfig>,
+    // Control points (input, output) of the curve the tile luminance is remapped with before
+    // quantizing, None for no curve
+    pub tone_curve: Option<Vec<(u8, u8)>>,
+    pub linear_resize: bool,
+    pub resize_filter: ResizeFilterConfig,
+    // Turn every pixel of the input into one cell, for pixel art. No edges are drawn
+    pub pixel_cells: bool,
+    // Rotation or mirroring of the decoded image
+    pub orientation: OrientationConfig,
+    // Chance of a tile moving one character along the ramp, 0 turns the jitter off
+    pub tile_jitter: f32,
+    // Seed of the jitter RNG, the same seed gives the same output
+    pub seed: u64,
+    pub color_preprocessors: Vec<ColorProcessorConfig>,
+    pub tile_preprocessors: Vec<ProcessorConfig>,
+    pub edge_preprocessors: Vec<ProcessorConfig>,
+    // Rows of the bands the tile and edge preprocessors run over to bound memory, None for the
+    // whole image at once
+    pub band_rows: Option<u32>,
+    // Stages run on f32 after the edge preprocessors, only dog, threshold and sharpen_gaussian
+    pub edge_f32_stages: Vec<ProcessorConfig>,
+    pub edge_f32_normalization: NormalizationConfig,
+    pub edge_detector: EdgeDetectorConfig,
+    // Detect edges on every color channel rather than the luminance, needs color_sobel
+    pub edge_source: EdgeSourceConfig,
+    pub edge_flow: Option<EdgeFlowConfig>,
+    pub edge_smoothing: EdgeSmoothingConfig,
+    // Steps denser the tiles next to edge cells are drawn so contours read clearly, 0 for none
+    pub edge_halo_bias: u8,
+    pub edge_threshold: f32,
+    pub draw_edges: bool,
+    pub small_image_fallback: bool,
+    // Largest input in pixels, None for no limit
+    pub max_input_pixels: Option<u64>,
+    // Downscale inputs over max_input_pixels to fit instead of failing
+    pub auto_downscale_large: bool,
+    // Decode inputs cut short or slightly corrupt as far as they go, with a warning
+    pub tolerant_decode: bool,
+    // Bring inputs to sRGB by their gAMA chunk or ICC profile, warning on profiles it can not
+    pub color_management: bool,
+    // Fail on anything that would otherwise be a warning, for reproducible pipelines
+    pub strict: bool,
+    // Score how closely the render approximates the original, reported with the stats
+    pub score_fidelity: bool,
+    pub detail_mode: DetailModeConfig,
+    pub bg_color: [u8; 3],
+    pub background: BackgroundConfig,
+    // Nudge glyph colors away from the background under their cell so they stay readable
+    pub adaptive_glyph_contrast: bool,
+    // Squeeze glyphs of proportional fonts wider than a cell into it, rather than failing
+    pub fit_glyphs: bool,
+    pub use_image_color: bool,
+    pub color: [u8; 3],
+    pub edge_color: EdgeColorConfig,
+    // Text drawn in a corner of rendered images, None for no watermark
+    pub watermark: Option<WatermarkConfig>,
+    // Colors PNG outputs are reduced to and written indexed with, None for truecolor
+    pub output_quantize: Option<OutputQuantizeConfig>,
+    // Stylization of rendered images, applied in order
+    pub post_effects: Vec<PostEffectConfig>,
+    // Blank space removed around the art in text and ANSI outputs
+    pub trim: TrimModeConfig,
+    // Whether PNG outputs carry this config and the crate version as text chunks
+    pub embed_metadata: bool,
+    // Whether JSON outputs give the input pixels every cell was sampled from
+    pub include_source_map: bool,
+}
+
+impl Default for ConverterConfig {
+    fn default() -> Self {
+        let font_settings = FontSettings::default();
+        ConverterConfig {
+            font_size: font_settings.font_size,
+            render_cell_px: None,
+            font_path: font_settings.font_path,
+            tile_chars: CharacterSet::default().tile.iter().collect(),
+            tile_levels: None,
+            tile_mapping: TileMappingConfig::default(),
+            bucket_mode: BucketModeConfig::default(),
+            tile_sampling: TileSamplingConfig::default(),
+            weight_map: None,
+            tone_curve: None,
+            linear_resize: false,
+            resize_filter: ResizeFilterConfig::default(),
+            pixel_cells: false,
+            orientation: OrientationConfig::default(),
+            tile_jitter: 0.0,
+            seed: 0,
+            color_preprocessors: vec![],
+            tile_preprocessors: vec![],
+            edge_preprocessors: vec![
+                ProcessorConfig::SharpenGaussian {
+                    sigma: 1.0,
+                    amount: 1.0,
+                },
+                ProcessorConfig::DoG {
+                    sigma_1: 1.0,
+                    sigma_2: 3.5,
+                },
+                ProcessorConfig::MedianBlur {
+                    kernel_size: 2,
+                    x_radius: None,
+                    y_radius: None,
+                },
+                ProcessorConfig::Threshold {
+                    threshold: 10,
+                    mode: ThresholdModeConfig::default(),
+                },
+            ],
+            band_rows: None,
+            edge_f32_stages: vec![],
+            edge_f32_normalization: NormalizationConfig::default(),
+            edge_detector: EdgeDetectorConfig::default(),
+            edge_source: EdgeSourceConfig::default(),
+            edge_flow: None,
+            edge_smoothing: EdgeSmoothingConfig::default(),
+            edge_halo_bias: 0,
+            edge_threshold: 0.0,
+            draw_edges: true,
+            small_image_fallback: false,
+            max_input_pixels: Some(DEFAULT_MAX_INPUT_PIXELS),
+            auto_downscale_large: false,
+            tolerant_decode: false,
+            color_management: true,
+            strict: false,
+            score_fidelity: false,
+            detail_mode: DetailModeConfig::default(),
+            bg_color: [117, 33, 141],
+            background: BackgroundConfig::default(),
+            adaptive_glyph_contrast: false,
+            fit_glyphs: true,
+            use_image_color: true,
+            color: [255, 255, 255],
+            edge_color: EdgeColorConfig::default(),
+            watermark: None,
+            output_quantize: None,
+            post_effects: vec![],
+            trim: TrimModeConfig::default(),
+            embed_metadata: true,
+            include_source_map: false,
+        }
+    }
+}
+
+impl ConverterConfig {
+    pub fn build(&self) -> Result<Converter, ConvertError> {
+        /*
+         * Build the converter described by the config, failing when any setting is out of range
+         */
+        if !(0.0..=1.0).contains(&self.edge_threshold) {
+            return Err(ConvertError::InvalidSetting {
+                field: "edge_threshold",
+                reason: "must be between 0 and 1",
+            });
+        }
+
+        let edge_f32_chain = match self.edge_f32_stages.split_first() {
+            Some((first, rest)) => {
+                let unsupported = || ConvertError::InvalidSetting {
+                    field: "edge_f32_stages",
+                    reason: "only dog, threshold and sharpen_gaussian run on f32",
+                };
+                let promote = first.build_promote().ok_or_else(unsupported)?;
+                let stages = rest
+                    .iter()
+                    .map(|stage| stage.build_f32())
+                    .collect::<Option<Vec<_>>>()
+                    .ok_or_else(unsupported)?;
+                Some(F32Chain::new(
+                    promote,
+                    stages,
+                    self.edge_f32_normalization.build(),
+                ))
+            }
+            None => None,
+        };
+
+        let tile_chars: Vec<char> = self.tile_chars.chars().collect();
+        let converter = Converter::new(
+            FontSettings::new(self.font_size, &self.font_path),
+            CharacterSet::new(&tile_chars),
+            self.tile_preprocessors.iter().map(|p| p.build()).collect(),
+            self.edge_preprocessors.iter().map(|p| p.build()).collect(),
+            self.edge_detector.build(),
+            Rgb(self.bg_color),
+            self.use_image_color,
+            Rgb(self.color),
+        )
+        .with_render_cell_px(self.render_cell_px)
+        .with_tile_levels(self.tile_levels)
+        .with_tile_mapping(self.tile_mapping.build())
+        .with_bucket_mode(self.bucket_mode.build())
+        .with_tile_sampling(self.tile_sampling.build())
+        .with_weight_map(self.weight_map.as_ref().map(|w| w.build()).transpose()?)
+        .with_tone_curve(
+            self.tone_curve
+                .as_deref()
+                .map(ToneCurve::from_points)
+                .transpose()?,
+        )
+        .with_linear_resize(self.linear_resize)
+        .with_resize_filter(self.resize_filter.build())
+        .with_pixel_cells(self.pixel_cells)
+        .with_orientation(self.orientation.build())
+        .with_tile_jitter(self.tile_jitter)
+        .with_seed(self.seed)
+        .with_color_preprocessors(self.color_preprocessors.iter().map(|p| p.build()).collect())
+        .with_background(self.background.build())
+        .with_adaptive_glyph_contrast(self.adaptive_glyph_contrast)
+        .with_fit_glyphs(self.fit_glyphs)
+        .with_edge_color(self.edge_color.build())
+        .with_edge_f32_chain(edge_f32_chain)
+        .with_edges(self.draw_edges)
+        .with_edge_source(self.edge_source.build())
+        .with_edge_flow(self.edge_flow.as_ref().map(|flow| flow.build()))
+        .with_edge_smoothing(self.edge_smoothing.build())
+        .with_edge_halo_bias(self.edge_halo_bias)
+        .with_band_rows(self.band_rows)
+        .with_small_image_fallback(self.small_image_fallback)
+        .with_max_input_pixels(self.max_input_pixels)
+        .with_auto_downscale_large(self.auto_downscale_large)
+        .with_tolerant_decode(self.tolerant_decode)
+        .with_color_management(self.color_management)
+        .with_strict(self.strict)
+        .with_score_fidelity(self.score_fidelity)
+        .with_detail_mode(self.detail_mode.build())
+        .with_watermark(self.watermark.as_ref().map(|w| w.build()))
+        .with_output_quantize(self.output_quantize.as_ref().map(|q| q.build()))
+        .with_post_effects(self.post_effects.iter().map(|e| e.build()).collect())
+        .with_trim(self.trim.build())
+        .with_include_source_map(self.include_source_map);
+        #[cfg(feature = "serde")]
+        let converter = converter
+            .with_embedded_config(self.embed_metadata.then(|| self.clone()))
+            // Configs TOML can not hold, such as seeds over i64::MAX, go without a hash
+            .with_config_hash(self.config_hash().ok());
+        converter.validate()?;
+        Ok(converter)
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn config_hash(&self) -> Result<String, ConvertError> {
+        /*
+         * Short id of the config for naming and tracing outputs: the first 8 hex digits of the
+         * xxh3 hash of the crate version and the config as TOML. Serializing keeps the id the
+         * same across runs and machines, and any change of a setting or the version changes it
+         */
+        let canonical = format!("{}\n{}", env!("CARGO_PKG_VERSION"), self.to_toml()?);
+        Ok(format!("{:08x}", xxh3_64(canonical.as_bytes()) >> 32))
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn from_toml(text: &str) -> Result<Self, ConvertError> {
+        toml::from_str(text).map_err(|e| ConvertError::ConfigError(e.to_string()))
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn to_toml(&self) -> Result<String, ConvertError> {
+        toml::to_string_pretty(self).map_err(|e| ConvertError::ConfigError(e.to_string()))
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ConvertError> {
+        Self::from_toml(&fs::read_to_string(path)?)
+    }
+}
@@ -1,54 +1,356 @@
-use super::char_set::CharacterSet;
+use super::background::{
+    cell_luminance, composite_over, contrast_glyph_color, luminance, BackgroundMode,
+};
+use super::cancel::CancelToken;
+use super::cell::{cells_to_chars, chars_to_cells, CellValue};
+use super::char_set::{
+    jitter_tiles, percentile_boundaries, BucketMode, CharacterSet, MappingOptions, TileMapping,
+};
+use super::config::ConverterConfig;
+use super::detail::DetailMode;
+use super::diff::GridDiff;
+use super::edge_color::EdgeColorMode;
 use super::error::ConvertError;
-use super::font_loader::{FontLoader, FontSettings};
-use crate::image_manip::edge_detect::{EdgeDetect, Sobel};
-use crate::image_manip::edge_processor::EdgeDownscaler;
-use crate::image_manip::processing::{DoG, MedianBlur, Processor, SharpenGaussian, Threshold};
-use crate::image_manip::util::bufr_to_arr;
+use super::font_loader::{FontLoader, FontSettings, GlyphFit, LoadedFont};
+use super::options::{Appearance, ConvertOptions};
+use super::post_effect::PostEffect;
+use super::provenance::{Provenance, SettingsTree};
+use super::source_map::SourceMap;
+use super::stats::GridStats;
+use super::target::{tile_size, OutputTarget, TargetData, TargetOutput, TextFit};
+use super::tone_curve::ToneCurve;
+use super::warning::ConvertWarning;
+use super::watermark::Watermark;
+use super::weight_map::{WeightMap, WeightSource};
+use crate::image_manip::banded::{apply_banded, pipeline_border};
+use crate::image_manip::color::ColorProcessor;
+use crate::image_manip::edge_detect::{sobel_magnitude, EdgeDetect, EdgeField, EdgeSource, Sobel};
+use crate::image_manip::edge_flow::EdgeTangentFlow;
+use crate::image_manip::edge_processor::{
+    bias_edge_halo, EdgeDownscaler, EdgeSmoothing, MagnitudeReduce,
+};
+use crate::image_manip::fidelity::{compare, FidelityScore};
+use crate::image_manip::orientation::Orientation;
+use crate::image_manip::pipeline::Pipeline;
+use crate::image_manip::processing::{
+    DoG, F32Chain, MedianBlur, Processor, SharpenGaussian, Threshold,
+};
+use crate::image_manip::tile_stats::{
+    box_average_rect, trimmed_mean_rect, TileSampling, TileStats,
+};
+use crate::image_manip::util::{
+    bufr_to_arr, resize_exact_linear, resize_exact_premultiplied, ResizeFilter,
+};
+use crate::input::color_space::manage_color;
+use crate::input::decode::{decode, Decoded};
+#[cfg(feature = "http")]
+use crate::input::http::{fetch, is_url, HttpOptions};
+use crate::input::text_grid::{gradient_colors, parse_text_grid, ColorSource};
+use crate::output::ans::AnsExporter;
+use crate::output::ansi::write_ansi_rows;
+use crate::output::comparison::{side_by_side, Divider};
+use crate::output::contact_sheet::contact_sheet;
+use crate::output::heatmap::render_bucket_heatmap;
+use crate::output::inline::encode_inline;
+#[cfg(feature = "serde")]
+use crate::output::json::{grid_to_json, JsonLayout};
+#[cfg(feature = "serde")]
+use crate::output::metadata::read_png_metadata;
+use crate::output::metadata::{encode_indexed_png, encode_png};
+use crate::output::quantize::{quantize_image, OutputQuantize};
+use crate::output::sixel::image_to_sixel;
+use crate::output::text::{write_text_rows, TextExporter, TextFormat};
+use crate::output::trim::{trimmed_ansi, trimmed_text, TrimMode};
+use crate::output::write::{write_file, WriteOptions};
+use crate::output::OutputFormat;
+use ab_glyph::{Font, FontVec, PxScale};
 use image::imageops::FilterType;
-use image::io::Reader as ImageReader;
-use image::{DynamicImage, GenericImageView, ImageBuffer, Pixel, Rgb};
+use image::{
+    DynamicImage, GenericImageView, GrayImage, ImageBuffer, ImageFormat, Luma, LumaA, Pixel, Rgb,
+    Rgba,
+};
 use imageproc::drawing::draw_text_mut;
-use ndarray::{ArrayView2, Zip};
+use ndarray::{Array2, ArrayView2, Zip};
 use rayon::prelude::*;
-use std::sync::{Arc, Mutex};
+use std::borrow::{Borrow, Cow};
+use std::fs;
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
 
+// Largest input converted by default, in pixels
+pub const DEFAULT_MAX_INPUT_PIXELS: u64 = 100_000_000;
+// Share of the glyph color unchanged cells keep in a diff render, the rest is the background
+const DIFF_DIM: f32 = 0.3;
+// Why an edge detector without color support can not take the color image
+const COLOR_EDGES_UNSUPPORTED: &str =
+    "color needs an edge detector that supports it, such as color_sobel";
+// Largest input converted with one cell per pixel, its render is font_size^2 times bigger
+pub const MAX_PIXEL_CELLS: u64 = 512 * 512;
+
+/*
+* Size of the grid and of the rendered image a conversion produces, see Converter::output_geometry
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OutputGeometry {
+    pub cols: u32,
+    pub rows: u32,
+    pub pixel_w: u32,
+    pub pixel_h: u32,
+}
+
+/*
+* Output of Converter::prepare, the image brought down to one pixel per grid cell
+*/
+#[derive(Clone)]
+pub struct PreparedImage {
+    // Color of every cell
+    pub resized: DynamicImage,
+    // Preprocessed luminance of every cell, which the tiles are quantized from
+    pub gray: GrayImage,
+    // Luminance variance of the original tile under every cell, only kept for variance aware
+    // tile mapping
+    pub variance: Option<Array2<f32>>,
+    // Luminances where the tile buckets after the first start, only kept for percentile buckets
+    pub bucket_boundaries: Option<Vec<u8>>,
+}
+
+/*
+* Edge directions of every input pixel, before they are brought down to the grid. Bins come
+* quantized for square cells, while a field still holds the gradients and is quantized for the
+* aspect of the cells it is downscaled to
+*/
+enum EdgeDirections {
+    Bins(Array2<u8>),
+    Field(EdgeField),
+}
+
+impl EdgeDirections {
+    fn for_aspect(&self, cell_aspect: f32) -> Cow<'_, Array2<u8>> {
+        match self {
+            EdgeDirections::Bins(bins) => Cow::Borrowed(bins),
+            EdgeDirections::Field(field) => {
+                Cow::Owned(bufr_to_arr(&field.quantize_for_aspect(cell_aspect)))
+            }
+        }
+    }
+}
+
+// Edges of every input pixel, with their gradient magnitude when the edge color needs it
+pub(crate) struct EdgeMap {
+    directions: EdgeDirections,
+    magnitude: Option<(Array2<f32>, MagnitudeReduce)>,
+}
+
+/*
+* Grid converted from an image, with the downscaled image holding the color of every cell and the
+* gradient magnitude of every edge cell when the edge color needs it
+*/
+struct ConvertedGrid {
+    cells: Array2<CellValue>,
+    resized: DynamicImage,
+    edge_strength: Option<Array2<f32>>,
+    bucket_boundaries: Option<Vec<u8>>,
+}
+
+/*
+* Conversions are deterministic: the same image and settings give byte identical outputs on every
+* run and with any number of threads. Parallel stages must write to fixed positions and break ties
+* by value, never by scan, hash or scheduling order
+*/
 pub struct Converter {
     font_settings: FontSettings,
+    // Size in pixels of a cell in rendered images, None draws cells at the font size. The grid is
+    // always sampled at the font size
+    render_cell_px: Option<u32>,
     pixel_mapping: CharacterSet,
+    // Number of characters the tile ramp was resampled to, None keeps the whole ramp
+    tile_levels: Option<usize>,
+    tile_mapping: TileMapping,
+    // Where the luminance range is split into tile buckets, evenly or at the image's quantiles
+    bucket_mode: BucketMode,
+    tile_sampling: TileSampling,
+    // Per cell length of the tile ramp, None gives every cell the whole ramp
+    weight_map: Option<WeightMap>,
+    // Remaps the tile luminance before it is quantized, None leaves it as is
+    tone_curve: Option<ToneCurve>,
+    // Chance of a tile moving one level along the ramp, drawn from an RNG seeded with seed
+    tile_jitter: f32,
+    seed: u64,
+    // Resize in linear light rather than on the sRGB values, for both the tiles and cell colors
+    linear_resize: bool,
+    // Filter the image is brought down to one pixel per cell with
+    resize_filter: ResizeFilter,
+    // When true, every pixel of the source becomes one cell and no edges are drawn
+    pixel_cells: bool,
+    // Rotation or mirroring of the decoded image, applied before the color preprocessors
+    orientation: Orientation,
+    // Run on the color image right after decoding, before any grayscale conversion
+    color_preprocessors: Vec<Box<dyn ColorProcessor>>,
     tile_preprocessors: Vec<Box<dyn Processor<u8, u8>>>,
     edge_preprocessors: Vec<Box<dyn Processor<u8, u8>>>,
+    // Rows of the bands the tile and edge preprocessors run over, each overlapped by the border
+    // of the pipeline so the result matches the whole image. None runs them on the whole image
+    band_rows: Option<u32>,
+    // Optional f32 stages run after the u8 edge preprocessors, before the edge detector
+    edge_f32_chain: Option<F32Chain>,
     edge_detector: Box<dyn EdgeDetect<u8, u8>>,
+    // Whether the edge pipeline runs on the luminance or on every color channel
+    edge_source: EdgeSource,
+    // Optional smoothing of edge directions, used when the edge detector exposes its gradients
+    edge_flow: Option<EdgeTangentFlow>,
+    // Mode filter cleaning up single cell direction flips in the downscaled edge map
+    edge_smoothing: EdgeSmoothing,
+    // Steps denser the tiles of cells next to an edge cell are drawn, 0 leaves them as they are
+    edge_halo_bias: u8,
     bg_color: Rgb<u8>,
+    // What rendered images are drawn over, bg_color by default
+    background: BackgroundMode,
+    // Whether glyph colors are nudged away from the luminance of the background under their cell
+    adaptive_glyph_contrast: bool,
+    // When true, charset glyphs wider than a cell are squeezed into it instead of an error
+    fit_glyphs: bool,
     // If use_image_color is true, then when drawing image, the drawer will use the color of the
     // pixel in the original image instead
     use_image_color: bool,
     color: Rgb<u8>,
+    // Color of the edge characters, relative to the color of their cell
+    edge_color: EdgeColorMode,
+    // When false, only the tile characters are drawn and the edge pipeline is skipped
+    draw_edges: bool,
+    // When true, images smaller than the font size become a single cell instead of an error
+    small_image_fallback: bool,
+    // Largest input in pixels, None for no limit
+    max_input_pixels: Option<u64>,
+    // When true, inputs over max_input_pixels are downscaled to fit instead of an error
+    auto_downscale_large: bool,
+    // When true, inputs that fail to decode are retried without limits and JPEGs cut short are
+    // decoded as far as they go, with a warning instead of an error
+    tolerant_decode: bool,
+    // When true, inputs are brought to sRGB by their gAMA chunk or ICC profile when decoded
+    color_management: bool,
+    // When true, anything that would be a warning is an error instead
+    strict: bool,
+    // When true, the stats of a conversion carry the fidelity of the render to the original
+    score_fidelity: bool,
+    // Whether busy cells are redrawn with a smaller font when rendering an image
+    detail_mode: DetailMode,
+    // Text drawn over the finished grid in a corner of rendered images
+    watermark: Option<Watermark>,
+    // Stylization of the finished render, applied in order after the watermark
+    post_effects: Vec<PostEffect>,
+    // Colors PNG outputs are reduced to and written as an indexed PNG with, None for truecolor
+    output_quantize: Option<OutputQuantize>,
+    // Blank space removed around the art in text outputs, never in rendered images
+    trim: TrimMode,
+    // When true, JSON outputs give the input pixels of every cell, see source_map
+    include_source_map: bool,
+    // How outputs written to a path treat missing directories and existing files
+    write_options: WriteOptions,
+    // Where every setting came from, shown by describe. None when nothing was recorded
+    provenance: Option<Provenance>,
+    #[cfg(feature = "http")]
+    http_options: HttpOptions,
+    // Config the converter was built from, embedded in the PNG outputs when set
+    #[cfg(feature = "serde")]
+    embedded_config: Option<ConverterConfig>,
+    // ConverterConfig::config_hash of the config the converter was built from, embedded with it
+    #[cfg(feature = "serde")]
+    config_hash: Option<String>,
+    // Font read on the first conversion that draws glyphs, kept for the following ones
+    font: OnceLock<LoadedFont>,
+    // Charset glyphs of the font reaching out of their cell, measured along with the font
+    wide_glyphs: OnceLock<Vec<GlyphFit>>,
 }
 
 // TODO: Remove color banding
 
-impl Converter {
-    pub fn default() -> Self {
+impl Default for Converter {
+    fn default() -> Self {
         Converter {
             font_settings: FontSettings::default(),
+            render_cell_px: None,
             pixel_mapping: CharacterSet::default(),
-            tile_preprocessors: vec![],
-            edge_preprocessors: vec![
-                Box::new(SharpenGaussian::default()),
-                Box::new(DoG::default()),
-                // No need for Bilateral as the threshold is doing most of the work
-                // Box::new(BilateralFilter::default()),
-                Box::new(MedianBlur::default()),
-                Box::new(Threshold::default()),
-            ],
+            tile_levels: None,
+            tile_mapping: TileMapping::Luminance,
+            bucket_mode: BucketMode::Linear,
+            tile_sampling: TileSampling::Resize,
+            weight_map: None,
+            tone_curve: None,
+            tile_jitter: 0.0,
+            seed: 0,
+            linear_resize: false,
+            resize_filter: ResizeFilter::Triangle,
+            pixel_cells: false,
+            orientation: Orientation::Normal,
+            color_preprocessors: vec![],
+            tile_preprocessors: Converter::default_tile_pipeline().into_stages(),
+            edge_preprocessors: Converter::default_edge_pipeline().into_stages(),
+            band_rows: None,
+            edge_f32_chain: None,
             edge_detector: Box::new(Sobel::new()),
+            edge_source: EdgeSource::Grayscale,
+            edge_flow: None,
+            edge_smoothing: EdgeSmoothing::default(),
+            edge_halo_bias: 0,
             bg_color: Rgb([117, 33, 141]),
+            background: BackgroundMode::Solid,
+            adaptive_glyph_contrast: false,
+            fit_glyphs: true,
             use_image_color: true,
             color: Rgb([255, 255, 255]),
+            edge_color: EdgeColorMode::SameAsTile,
+            draw_edges: true,
+            small_image_fallback: false,
+            max_input_pixels: Some(DEFAULT_MAX_INPUT_PIXELS),
+            auto_downscale_large: false,
+            tolerant_decode: false,
+            color_management: true,
+            strict: false,
+            score_fidelity: false,
+            detail_mode: DetailMode::Single,
+            watermark: None,
+            output_quantize: None,
+            post_effects: vec![],
+            trim: TrimMode::None,
+            include_source_map: false,
+            write_options: WriteOptions::default(),
+            provenance: None,
+            #[cfg(feature = "http")]
+            http_options: HttpOptions::default(),
+            #[cfg(feature = "serde")]
+            embedded_config: None,
+            #[cfg(feature = "serde")]
+            config_hash: None,
+            font: OnceLock::new(),
+            wide_glyphs: OnceLock::new(),
         }
     }
+}
+
+impl Converter {
+    pub fn default_edge_pipeline() -> Pipeline {
+        /*
+         * Edge preprocessors of Converter::default, in order: sharpen_gaussian, dog, median_blur
+         * and threshold. Stages are named by Processor::name, which stays the same across
+         * versions
+         */
+        Pipeline::new(vec![
+            Box::new(SharpenGaussian::default()),
+            Box::new(DoG::default()),
+            // No need for Bilateral as the threshold is doing most of the work
+            // Box::new(BilateralFilter::default()),
+            Box::new(MedianBlur::default()),
+            Box::new(Threshold::default()),
+        ])
+    }
+
+    pub fn default_tile_pipeline() -> Pipeline {
+        // Tile preprocessors of Converter::default, none as the tiles take the image as it is
+        Pipeline::default()
+    }
 
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         font_settings: FontSettings,
         pixel_mapping: CharacterSet,
@@ -61,60 +363,776 @@ impl Converter {
     ) -> Self {
         Converter {
             font_settings,
+            render_cell_px: None,
             pixel_mapping,
+            tile_levels: None,
+            tile_mapping: TileMapping::Luminance,
+            bucket_mode: BucketMode::Linear,
+            tile_sampling: TileSampling::Resize,
+            weight_map: None,
+            tone_curve: None,
+            tile_jitter: 0.0,
+            seed: 0,
+            linear_resize: false,
+            resize_filter: ResizeFilter::Triangle,
+            pixel_cells: false,
+            orientation: Orientation::Normal,
+            color_preprocessors: vec![],
             tile_preprocessors,
             edge_preprocessors,
+            band_rows: None,
+            edge_f32_chain: None,
             edge_detector,
+            edge_source: EdgeSource::Grayscale,
+            edge_flow: None,
+            edge_smoothing: EdgeSmoothing::default(),
+            edge_halo_bias: 0,
             bg_color,
+            background: BackgroundMode::Solid,
+            adaptive_glyph_contrast: false,
+            fit_glyphs: true,
             use_image_color,
             color,
+            edge_color: EdgeColorMode::SameAsTile,
+            draw_edges: true,
+            small_image_fallback: false,
+            max_input_pixels: Some(DEFAULT_MAX_INPUT_PIXELS),
+            auto_downscale_large: false,
+            tolerant_decode: false,
+            color_management: true,
+            strict: false,
+            score_fidelity: false,
+            detail_mode: DetailMode::Single,
+            watermark: None,
+            output_quantize: None,
+            post_effects: vec![],
+            trim: TrimMode::None,
+            include_source_map: false,
+            write_options: WriteOptions::default(),
+            provenance: None,
+            #[cfg(feature = "http")]
+            http_options: HttpOptions::default(),
+            #[cfg(feature = "serde")]
+            embedded_config: None,
+            #[cfg(feature = "serde")]
+            config_hash: None,
+            font: OnceLock::new(),
+            wide_glyphs: OnceLock::new(),
+        }
+    }
+
+    pub fn try_default() -> Result<Self, ConvertError> {
+        /*
+         * Converter::default that fails right away when the default font can not be loaded,
+         * rather than on the first render
+         */
+        let converter = Converter::default();
+        converter.load_font()?;
+        Ok(converter)
+    }
+
+    pub fn load_font(&self) -> Result<&FontVec, ConvertError> {
+        /*
+         * The font of the font settings, read once. Conversions that draw glyphs call this
+         * before any image work so a missing font fails fast
+         */
+        Ok(&self.loaded_font()?.font)
+    }
+
+    fn loaded_font(&self) -> Result<&LoadedFont, ConvertError> {
+        let loaded = match self.font.get() {
+            Some(font) => font,
+            None => {
+                let font = FontLoader::load(&self.font_settings.font_path)?;
+                self.font.get_or_init(|| font)
+            }
+        };
+        if self.strict {
+            self.check_strict_font(loaded)?;
+        }
+        self.check_glyph_widths(loaded)?;
+        Ok(loaded)
+    }
+
+    fn check_glyph_widths(&self, loaded: &LoadedFont) -> Result<&[GlyphFit], ConvertError> {
+        /*
+         * Charset glyphs reaching out of their cell, measured once. Without fit_glyphs the first
+         * of them is an error, as drawing it would spill over the neighboring cells
+         */
+        let wide = self.wide_glyphs.get_or_init(|| {
+            let mut wide: Vec<GlyphFit> = vec![];
+            for &ch in self
+                .pixel_mapping
+                .tile
+                .iter()
+                .chain(self.pixel_mapping.edge.iter())
+            {
+                let fit = GlyphFit::measure(&loaded.font, ch);
+                if fit.overflows() && !wide.iter().any(|w| w.ch == ch) {
+                    wide.push(fit);
+                }
+            }
+            wide
+        });
+        match wide.first() {
+            Some(fit) if !self.fit_glyphs => Err(ConvertError::GlyphTooWide {
+                ch: fit.ch,
+                width: fit.width_px(self.cell_px()),
+                cell: self.cell_px(),
+            }),
+            _ => Ok(wide),
+        }
+    }
+
+    fn check_strict_font(&self, loaded: &LoadedFont) -> Result<(), ConvertError> {
+        /*
+         * Fail on a fallback font or a character of the charset the font has no glyph for. Strict
+         * mode checks the whole charset up front, so it fails the same way whatever the image
+         */
+        if let Some(used) = &loaded.fallback {
+            return Err(ConvertError::StrictViolation(
+                ConvertWarning::FallbackFont {
+                    requested: self.font_settings.font_path.clone(),
+                    used: used.clone(),
+                },
+            ));
+        }
+        let mut missing: Vec<char> = vec![];
+        for &ch in self
+            .pixel_mapping
+            .tile
+            .iter()
+            .chain(self.pixel_mapping.edge.iter())
+        {
+            if ch != ' ' && !missing.contains(&ch) && loaded.font.glyph_id(ch).0 == 0 {
+                missing.push(ch);
+            }
+        }
+        if !missing.is_empty() {
+            return Err(ConvertError::StrictViolation(
+                ConvertWarning::MissingGlyphs(missing),
+            ));
+        }
+        Ok(())
+    }
+
+    fn decode_input(
+        &self,
+        bytes: &[u8],
+        path_format: Option<ImageFormat>,
+    ) -> Result<Decoded, ConvertError> {
+        let mut decoded = decode(bytes, path_format, self.tolerant_decode)?;
+        if self.color_management {
+            decoded = manage_color(bytes, decoded);
+        }
+        self.check_decoded(decoded)
+    }
+
+    fn check_decoded(&self, decoded: Decoded) -> Result<Decoded, ConvertError> {
+        // A partial decode or an unsupported profile is an error in strict mode
+        match decoded.warnings.first() {
+            Some(warning) if self.strict => Err(ConvertError::StrictViolation(warning.clone())),
+            _ => Ok(decoded),
+        }
+    }
+
+    pub fn with_render_cell_px(mut self, render_cell_px: Option<u32>) -> Self {
+        self.render_cell_px = render_cell_px;
+        self
+    }
+
+    fn cell_px(&self) -> u32 {
+        // Size of a cell in rendered images
+        self.render_cell_px.unwrap_or(self.font_settings.font_size)
+    }
+
+    pub fn with_tile_mapping(mut self, tile_mapping: TileMapping) -> Self {
+        self.tile_mapping = tile_mapping;
+        self
+    }
+
+    pub fn with_bucket_mode(mut self, bucket_mode: BucketMode) -> Self {
+        self.bucket_mode = bucket_mode;
+        self
+    }
+
+    pub fn with_tile_levels(mut self, tile_levels: Option<usize>) -> Self {
+        /*
+         * Resample the tile ramp to tile_levels characters. A count the ramp can not be resampled
+         * to leaves it as is, validate reports it
+         */
+        if let Some(Ok(resampled)) = tile_levels.map(|levels| self.pixel_mapping.resampled(levels))
+        {
+            self.pixel_mapping = resampled;
+        }
+        self.tile_levels = tile_levels;
+        self
+    }
+
+    pub fn with_weight_map(mut self, weight_map: Option<WeightMap>) -> Self {
+        self.weight_map = weight_map;
+        self
+    }
+
+    pub fn with_tone_curve(mut self, tone_curve: Option<ToneCurve>) -> Self {
+        self.tone_curve = tone_curve;
+        self
+    }
+
+    pub fn with_tile_sampling(mut self, tile_sampling: TileSampling) -> Self {
+        self.tile_sampling = tile_sampling;
+        self
+    }
+
+    pub fn with_tile_jitter(mut self, tile_jitter: f32) -> Self {
+        self.tile_jitter = tile_jitter;
+        self
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn with_linear_resize(mut self, linear_resize: bool) -> Self {
+        self.linear_resize = linear_resize;
+        self
+    }
+
+    pub fn with_resize_filter(mut self, resize_filter: ResizeFilter) -> Self {
+        self.resize_filter = resize_filter;
+        self
+    }
+
+    pub fn with_pixel_cells(mut self, pixel_cells: bool) -> Self {
+        self.pixel_cells = pixel_cells;
+        self
+    }
+
+    pub fn with_orientation(mut self, orientation: Orientation) -> Self {
+        self.orientation = orientation;
+        self
+    }
+
+    pub fn with_color_preprocessors(
+        mut self,
+        color_preprocessors: Vec<Box<dyn ColorProcessor>>,
+    ) -> Self {
+        self.color_preprocessors = color_preprocessors;
+        self
+    }
+
+    pub fn with_background(mut self, background: BackgroundMode) -> Self {
+        self.background = background;
+        self
+    }
+
+    pub fn with_adaptive_glyph_contrast(mut self, adaptive_glyph_contrast: bool) -> Self {
+        self.adaptive_glyph_contrast = adaptive_glyph_contrast;
+        self
+    }
+
+    pub fn with_edge_f32_chain(mut self, edge_f32_chain: Option<F32Chain>) -> Self {
+        self.edge_f32_chain = edge_f32_chain;
+        self
+    }
+
+    pub fn with_edge_color(mut self, edge_color: EdgeColorMode) -> Self {
+        self.edge_color = edge_color;
+        self
+    }
+
+    pub fn with_edges(mut self, draw_edges: bool) -> Self {
+        self.draw_edges = draw_edges;
+        self
+    }
+
+    pub fn with_edge_pipeline(mut self, pipeline: Pipeline) -> Self {
+        self.edge_preprocessors = pipeline.into_stages();
+        self
+    }
+
+    pub fn with_tile_pipeline(mut self, pipeline: Pipeline) -> Self {
+        self.tile_preprocessors = pipeline.into_stages();
+        self
+    }
+
+    pub fn with_edge_source(mut self, edge_source: EdgeSource) -> Self {
+        self.edge_source = edge_source;
+        self
+    }
+
+    pub fn with_edge_halo_bias(mut self, edge_halo_bias: u8) -> Self {
+        self.edge_halo_bias = edge_halo_bias;
+        self
+    }
+
+    pub fn with_edge_flow(mut self, edge_flow: Option<EdgeTangentFlow>) -> Self {
+        self.edge_flow = edge_flow;
+        self
+    }
+
+    pub fn with_edge_smoothing(mut self, edge_smoothing: EdgeSmoothing) -> Self {
+        self.edge_smoothing = edge_smoothing;
+        self
+    }
+
+    pub fn with_band_rows(mut self, band_rows: Option<u32>) -> Self {
+        self.band_rows = band_rows;
+        self
+    }
+
+    pub fn with_small_image_fallback(mut self, small_image_fallback: bool) -> Self {
+        self.small_image_fallback = small_image_fallback;
+        self
+    }
+
+    pub fn with_max_input_pixels(mut self, max_input_pixels: Option<u64>) -> Self {
+        self.max_input_pixels = max_input_pixels;
+        self
+    }
+
+    pub fn with_auto_downscale_large(mut self, auto_downscale_large: bool) -> Self {
+        self.auto_downscale_large = auto_downscale_large;
+        self
+    }
+
+    pub fn with_tolerant_decode(mut self, tolerant_decode: bool) -> Self {
+        self.tolerant_decode = tolerant_decode;
+        self
+    }
+
+    pub fn with_color_management(mut self, color_management: bool) -> Self {
+        self.color_management = color_management;
+        self
+    }
+
+    pub fn with_strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    pub fn with_fit_glyphs(mut self, fit_glyphs: bool) -> Self {
+        self.fit_glyphs = fit_glyphs;
+        self
+    }
+
+    pub(crate) fn is_strict(&self) -> bool {
+        self.strict
+    }
+
+    pub fn with_score_fidelity(mut self, score_fidelity: bool) -> Self {
+        self.score_fidelity = score_fidelity;
+        self
+    }
+
+    pub fn with_detail_mode(mut self, detail_mode: DetailMode) -> Self {
+        self.detail_mode = detail_mode;
+        self
+    }
+
+    pub fn with_output_quantize(mut self, output_quantize: Option<OutputQuantize>) -> Self {
+        self.output_quantize = output_quantize;
+        self
+    }
+
+    pub fn with_watermark(mut self, watermark: Option<Watermark>) -> Self {
+        self.watermark = watermark;
+        self
+    }
+
+    pub fn with_post_effects(mut self, post_effects: Vec<PostEffect>) -> Self {
+        self.post_effects = post_effects;
+        self
+    }
+
+    pub fn with_include_source_map(mut self, include_source_map: bool) -> Self {
+        self.include_source_map = include_source_map;
+        self
+    }
+
+    pub fn with_trim(mut self, trim: TrimMode) -> Self {
+        self.trim = trim;
+        self
+    }
+
+    pub fn with_write_options(mut self, write_options: WriteOptions) -> Self {
+        self.write_options = write_options;
+        self
+    }
+
+    pub fn with_provenance(mut self, provenance: Option<Provenance>) -> Self {
+        self.provenance = provenance;
+        self
+    }
+
+    #[cfg(feature = "http")]
+    pub fn with_http_options(mut self, http_options: HttpOptions) -> Self {
+        self.http_options = http_options;
+        self
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn with_embedded_config(mut self, embedded_config: Option<ConverterConfig>) -> Self {
+        self.embedded_config = embedded_config;
+        self
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn with_config_hash(mut self, config_hash: Option<String>) -> Self {
+        self.config_hash = config_hash;
+        self
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn config_hash(&self) -> Option<&str> {
+        // None for converters not built from a ConverterConfig, or from one TOML can not hold
+        self.config_hash.as_deref()
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn from_png_metadata(path: impl AsRef<Path>) -> Result<ConverterConfig, ConvertError> {
+        /*
+         * Read back the config embedded in a PNG output, to reproduce or tweak an old result
+         */
+        let path = path.as_ref();
+        let metadata = read_png_metadata(fs::File::open(path)?)?;
+        let config = metadata.config.ok_or_else(|| {
+            ConvertError::ConfigError(format!("{} has no embedded config", path.display()))
+        })?;
+        ConverterConfig::from_toml(&config)
+    }
+
+    pub fn validate(&self) -> Result<(), ConvertError> {
+        /*
+         * Check the settings for values that would make a conversion fail or panic part way
+         * through. The error names the first offending field
+         */
+        if self.font_settings.font_size == 0 {
+            return Err(ConvertError::InvalidSetting {
+                field: "font_size",
+                reason: "must be at least 1",
+            });
+        }
+        if self.band_rows == Some(0) {
+            return Err(ConvertError::InvalidSetting {
+                field: "band_rows",
+                reason: "must be at least 1",
+            });
+        }
+        if self.render_cell_px == Some(0) {
+            return Err(ConvertError::InvalidSetting {
+                field: "render_cell_px",
+                reason: "must be at least 1",
+            });
+        }
+        if self.pixel_mapping.tile.is_empty() {
+            return Err(ConvertError::InvalidSetting {
+                field: "tile",
+                reason: "needs at least one character",
+            });
+        }
+        if self.max_input_pixels == Some(0) {
+            return Err(ConvertError::InvalidSetting {
+                field: "max_input_pixels",
+                reason: "must be at least 1",
+            });
+        }
+        if let Some(levels) = self.tile_levels {
+            self.pixel_mapping.resampled(levels)?;
+        }
+        if !(0.0..=1.0).contains(&self.tile_jitter) {
+            return Err(ConvertError::InvalidSetting {
+                field: "tile_jitter",
+                reason: "must be between 0 and 1",
+            });
+        }
+        if let Some(weight_map) = &self.weight_map {
+            weight_map.validate()?;
+        }
+        if let Some(watermark) = &self.watermark {
+            watermark.validate()?;
+        }
+        for effect in self.post_effects.iter() {
+            effect.validate()?;
+        }
+        if let TileSampling::TrimmedMean { trim } = self.tile_sampling {
+            if !(0.0..0.5).contains(&trim) {
+                return Err(ConvertError::InvalidSetting {
+                    field: "trimmed_mean.trim",
+                    reason: "must be at least 0 and below 0.5",
+                });
+            }
+        }
+        self.validate_appearance(&self.appearance())?;
+        for preproc in self.color_preprocessors.iter() {
+            preproc.validate()?;
+        }
+        for preproc in self
+            .tile_preprocessors
+            .iter()
+            .chain(self.edge_preprocessors.iter())
+        {
+            preproc.validate()?;
+        }
+        if let Some(chain) = &self.edge_f32_chain {
+            chain.validate()?;
+        }
+        if self.edge_source == EdgeSource::Color && !self.edge_detector.supports_color() {
+            return Err(ConvertError::InvalidSetting {
+                field: "edge_source",
+                reason: COLOR_EDGES_UNSUPPORTED,
+            });
+        }
+        if let TileMapping::LuminanceAndVariance { variance_weight } = self.tile_mapping {
+            if variance_weight.is_nan() || variance_weight < 0.0 {
+                return Err(ConvertError::InvalidSetting {
+                    field: "luminance_and_variance.variance_weight",
+                    reason: "must not be negative",
+                });
+            }
+        }
+        if let DetailMode::TwoScale {
+            fine_factor,
+            variance_threshold,
+        } = self.detail_mode
+        {
+            if fine_factor < 2 {
+                return Err(ConvertError::InvalidSetting {
+                    field: "two_scale.fine_factor",
+                    reason: "must be at least 2",
+                });
+            }
+            // Fine cells have to tile a coarse cell exactly
+            if !self.font_settings.font_size.is_multiple_of(fine_factor) {
+                return Err(ConvertError::InvalidSetting {
+                    field: "two_scale.fine_factor",
+                    reason: "must divide the font size",
+                });
+            }
+            if !self.cell_px().is_multiple_of(fine_factor) {
+                return Err(ConvertError::InvalidSetting {
+                    field: "two_scale.fine_factor",
+                    reason: "must divide the render cell size",
+                });
+            }
+            if variance_threshold.is_nan() || variance_threshold < 0.0 {
+                return Err(ConvertError::InvalidSetting {
+                    field: "two_scale.variance_threshold",
+                    reason: "must not be negative",
+                });
+            }
+        }
+        if let Some(quantize) = &self.output_quantize {
+            quantize.validate()?;
+        }
+        Ok(())
+    }
+
+    fn validate_appearance(&self, appearance: &Appearance) -> Result<(), ConvertError> {
+        /*
+         * The part of validate covering the settings ConvertOptions can override
+         */
+        // The edge detector output indexes straight into the edge set
+        if appearance.draw_edges
+            && self.pixel_mapping.edge.len() < CharacterSet::default().edge.len()
+        {
+            return Err(ConvertError::InvalidSetting {
+                field: "edge",
+                reason: "needs a character for every edge direction",
+            });
+        }
+        if let BackgroundMode::BlurredImage { sigma, darken } = appearance.background {
+            if sigma.is_nan() || sigma <= 0.0 {
+                return Err(ConvertError::InvalidSetting {
+                    field: "blurred_image.sigma",
+                    reason: "must be positive",
+                });
+            }
+            if !(0.0..=1.0).contains(&darken) {
+                return Err(ConvertError::InvalidSetting {
+                    field: "blurred_image.darken",
+                    reason: "must be between 0 and 1",
+                });
+            }
+        }
+        if let EdgeColorMode::Brightened { factor } = appearance.edge_color {
+            if factor.is_nan() || factor < 0.0 {
+                return Err(ConvertError::InvalidSetting {
+                    field: "brightened.factor",
+                    reason: "must not be negative",
+                });
+            }
+        }
+        if let EdgeColorMode::Magnitude { floor, .. } = appearance.edge_color {
+            if !(0.0..=1.0).contains(&floor) {
+                return Err(ConvertError::InvalidSetting {
+                    field: "magnitude.floor",
+                    reason: "must be between 0 and 1",
+                });
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn appearance(&self) -> Appearance {
+        Appearance {
+            bg_color: self.bg_color,
+            color: self.color,
+            use_image_color: self.use_image_color,
+            draw_edges: self.draw_edges,
+            background: self.background,
+            adaptive_glyph_contrast: self.adaptive_glyph_contrast,
+            edge_color: self.edge_color,
         }
     }
 
-    fn arr_to_img(
+    pub fn arr_to_img(
         &self,
         arr: &ArrayView2<char>,
         arr_img: &DynamicImage,
     ) -> Result<ImageBuffer<Rgb<u8>, Vec<u8>>, ConvertError> {
+        let colors = self.cell_colors_with(arr_img, &self.appearance());
+        self.arr_to_img_with(arr, &colors.view(), Some(arr_img))
+    }
+
+    fn arr_to_img_with(
+        &self,
+        arr: &ArrayView2<char>,
+        colors: &ArrayView2<Rgb<u8>>,
+        background_src: Option<&DynamicImage>,
+    ) -> Result<ImageBuffer<Rgb<u8>, Vec<u8>>, ConvertError> {
+        // arr_to_img with the cell colors given, and no BlurredImage source without an image
+        let mut ascii_bufr = self.draw_grid(
+            arr,
+            colors,
+            self.cell_px(),
+            None,
+            background_src,
+            &self.appearance(),
+        )?;
+        self.finish_render(&mut ascii_bufr)?;
+        Ok(ascii_bufr)
+    }
+
+    fn finish_render(&self, bufr: &mut ImageBuffer<Rgb<u8>, Vec<u8>>) -> Result<(), ConvertError> {
+        // Drawn over the finished grid, so the watermark never shifts or covers part of a cell
+        // pass, and the post effects style it along with the glyphs
+        if let Some(watermark) = &self.watermark {
+            let _span = stage_span!("watermark");
+            watermark.draw(bufr, self.load_font()?, self.cell_px());
+        }
+        for effect in self.post_effects.iter() {
+            let _span = stage_span!("post_effect");
+            effect.apply(bufr);
+        }
+        Ok(())
+    }
+
+    fn draw_grid(
+        &self,
+        arr: &ArrayView2<char>,
+        colors: &ArrayView2<Rgb<u8>>,
+        cell_px: u32,
+        drawn: Option<&Array2<bool>>,
+        background_src: Option<&DynamicImage>,
+        appearance: &Appearance,
+    ) -> Result<ImageBuffer<Rgb<u8>, Vec<u8>>, ConvertError> {
+        /*
+         * Draw the grid with cells of cell_px pixels. When drawn is given, cells marked false
+         * are left as background. background_src is the image a BlurredImage background is made
+         * from
+         */
         let (h, w) = (
-            arr.shape()[0] as u32 * self.font_settings.font_size,
-            arr.shape()[1] as u32 * self.font_settings.font_size,
+            arr.shape()[0] as u32 * cell_px,
+            arr.shape()[1] as u32 * cell_px,
         );
+        let background = appearance
+            .background
+            .fill(w, h, appearance.bg_color, background_src);
+        let mut ascii_bufr = background.clone();
+        self.draw_glyphs_into(
+            &mut ascii_bufr,
+            &background,
+            arr,
+            colors,
+            cell_px,
+            drawn,
+            appearance,
+        )?;
+        Ok(ascii_bufr)
+    }
 
-        let ascii_bufr = Arc::new(Mutex::new(ImageBuffer::<Rgb<u8>, Vec<u8>>::from_pixel(
-            w,
-            h,
-            self.bg_color.clone(),
-        )));
-
-        let (font, scale) = FontLoader::load_font_from_settings(&self.font_settings)?;
+    #[allow(clippy::too_many_arguments)]
+    fn draw_glyphs_into(
+        &self,
+        bufr: &mut ImageBuffer<Rgb<u8>, Vec<u8>>,
+        background: &ImageBuffer<Rgb<u8>, Vec<u8>>,
+        arr: &ArrayView2<char>,
+        colors: &ArrayView2<Rgb<u8>>,
+        cell_px: u32,
+        drawn: Option<&Array2<bool>>,
+        appearance: &Appearance,
+    ) -> Result<(), ConvertError> {
+        /*
+         * Draw the glyphs of the grid into bufr, which already holds the background. With
+         * adaptive_glyph_contrast, every glyph color is checked against the average background
+         * under its own cell. Glyphs are drawn as coverage and composited over bufr, so
+         * antialiased edges blend into whatever is under them
+         */
+        let (w, h) = bufr.dimensions();
+        let _span = stage_span!("render", width = w, height = h, cell_px = cell_px);
+        let ascii_bufr = Mutex::new(bufr);
 
-        let font_size = self.font_settings.font_size;
-        let use_image_color = self.use_image_color;
-        let bg_color = self.bg_color;
-        let color = self.color;
+        let loaded = self.loaded_font()?;
+        let font = &loaded.font;
+        let scale = PxScale::from(cell_px as f32);
+        // Scale and offset of the glyphs squeezed into their cell
+        let fitted: Vec<(char, (PxScale, i32))> = self
+            .check_glyph_widths(loaded)?
+            .iter()
+            .map(|fit| (fit.ch, fit.fitted(cell_px)))
+            .collect();
+        let adaptive_glyph_contrast = appearance.adaptive_glyph_contrast;
 
         arr.outer_iter()
             .enumerate()
             .collect::<Vec<_>>() // Collect rows to maintain order since par_iter might not preserve order
             .par_iter() // Process rows in parallel
             .for_each(|(y, row)| {
-                let mut local_bufr = ImageBuffer::from_pixel(w, font_size, bg_color.clone());
+                // Premultiplied glyph colors of the row, the alpha being the glyph coverage
+                let mut local_bufr: ImageBuffer<Rgba<u8>, Vec<u8>> = ImageBuffer::new(w, cell_px);
                 for (x, &ch) in row.iter().enumerate() {
-                    let x_pos = (x as u32 * font_size) as i32;
+                    if drawn.is_some_and(|drawn| !drawn[(*y, x)]) {
+                        continue;
+                    }
+                    let x_pos = (x as u32 * cell_px) as i32;
                     let y_pos = 0; // local y position in the row buffer
+                    let color = if adaptive_glyph_contrast {
+                        let bg_luminance = cell_luminance(
+                            background,
+                            x as u32 * cell_px,
+                            *y as u32 * cell_px,
+                            cell_px,
+                        );
+                        contrast_glyph_color(colors[(*y, x)], bg_luminance)
+                    } else {
+                        colors[(*y, x)]
+                    };
 
-                    let mut local_color = color.clone();
-                    if use_image_color {
-                        local_color = arr_img.get_pixel(x as u32, *y as u32).to_rgb();
-                    }
+                    let (scale, shift) = fitted
+                        .iter()
+                        .find(|(wide, _)| *wide == ch)
+                        .map_or((scale, 0), |&(_, fit)| fit);
+
+                    // Blending into a transparent buffer leaves the color times the coverage
                     draw_text_mut(
                         &mut local_bufr,
-                        local_color,
-                        x_pos,
+                        color.to_rgba(),
+                        x_pos + shift,
                         y_pos,
                         scale,
-                        &font,
+                        font,
                         &ch.to_string(),
                     );
                 }
@@ -122,98 +1140,2256 @@ impl Converter {
                 let mut ascii_bufr_lock = ascii_bufr.lock().unwrap();
 
                 // Calculate the starting Y position for this row in the final image buffer
-                let start_y = *y as u32 * font_size;
+                let start_y = *y as u32 * cell_px;
 
-                // Merge the processed row into the final image buffer at the correct position
-                for (x, y, pixel) in local_bufr.enumerate_pixels() {
-                    ascii_bufr_lock.put_pixel(x, y + start_y, *pixel);
+                // Composite the glyphs of the row over the background, uncovered pixels stay as
+                // they are
+                for (x, y, glyph) in local_bufr.enumerate_pixels() {
+                    if glyph[3] == 0 {
+                        continue;
+                    }
+                    let dst = ascii_bufr_lock.get_pixel_mut(x, y + start_y);
+                    *dst = composite_over(*glyph, *dst);
                 }
             });
-
-        let final_bufr = Arc::try_unwrap(ascii_bufr)
-            .expect("Arc unwrap failed")
-            .into_inner()
-            .unwrap();
-
-        Ok(final_bufr)
+        Ok(())
     }
 
-    pub fn convert_img(
-        &self,
-        path: &str,
-        out: &str,
-        sharpen_thres: f32,
-    ) -> Result<(), ConvertError> {
+    pub fn cell_colors(&self, arr_img: &DynamicImage) -> Array2<Rgb<u8>> {
         /*
-         * Read an image given file path and convert that image into an ascii image / txt file / or
-         * print it depending on settings
+         * Color of every grid cell, taken from the downscaled image (PreparedImage::resized) or
+         * the fixed color
          */
+        self.cell_colors_with(arr_img, &self.appearance())
+    }
 
-        // Calculate the new size of the image for downscaling
-        let ori_img: DynamicImage = ImageReader::open(path)?
-            .with_guessed_format()
-            .unwrap()
-            .decode()
-            .unwrap();
-        let (ori_w, ori_h): (f32, f32) = (ori_img.width() as f32, ori_img.height() as f32);
-        let (new_w, new_h): (u32, u32) = (
-            (ori_w / self.font_settings.font_size as f32).floor() as u32,
-            (ori_h / self.font_settings.font_size as f32).floor() as u32,
-        );
+    fn cell_colors_with(&self, arr_img: &DynamicImage, appearance: &Appearance) -> Array2<Rgb<u8>> {
+        let mut colors = Array2::from_elem((0, 0), appearance.color);
+        self.cell_colors_into(arr_img, appearance, &mut colors);
+        colors
+    }
 
-        // Downscaling and grayscale the image for preprocessing
-        // Maybe let user choose resize algorithm
-        let resized_img = ori_img.resize_exact(new_w, new_h, FilterType::Triangle);
-        let mut gs_resized_img = resized_img.to_luma8();
-
-        // Apply preprocessors before quantization
-        for preproc in self.tile_preprocessors.iter() {
-            gs_resized_img = preproc.apply(&gs_resized_img)?;
-        }
-
-        // Normalize and quantize the img
-        // Convert to array for easy processing
-        let qt_tile_arr = (bufr_to_arr(&gs_resized_img)).mapv(|x: u8| -> char {
-            let index = ((x as f32 / 255.0)
-                * (self.pixel_mapping.get_tile_mapping_size() - 1) as f32)
-                .floor() as usize;
-            self.pixel_mapping.tile[index].clone()
-        });
+    fn grid_colors(&self, grid: &ConvertedGrid, appearance: &Appearance) -> Array2<Rgb<u8>> {
+        self.grid_colors_of(
+            &grid.resized,
+            &grid.cells.view(),
+            grid.edge_strength.as_ref(),
+            appearance,
+        )
+    }
 
-        // Find edges
-        let mut gs_ori_img = ori_img.to_luma8();
+    pub(crate) fn grid_colors_of(
+        &self,
+        resized: &DynamicImage,
+        cells: &ArrayView2<CellValue>,
+        edge_strength: Option<&Array2<f32>>,
+        appearance: &Appearance,
+    ) -> Array2<Rgb<u8>> {
+        // Cell colors with the edge cells recolored by the edge color mode
+        let mut colors = self.cell_colors_with(resized, appearance);
+        let edge_strength = edge_strength.map(|s| s.view());
+        appearance
+            .edge_color
+            .apply(cells, edge_strength.as_ref(), &mut colors);
+        colors
+    }
 
-        // Apply preprocessors on gs_ori_img
-        for preproc in self.edge_preprocessors.iter() {
-            gs_ori_img = preproc.apply(&gs_ori_img)?;
+    fn cell_colors_into(
+        &self,
+        arr_img: &DynamicImage,
+        appearance: &Appearance,
+        colors: &mut Array2<Rgb<u8>>,
+    ) {
+        // Fill colors in place, only reallocating when the grid shape changed
+        let (w, h) = arr_img.dimensions();
+        if colors.dim() != (h as usize, w as usize) {
+            *colors = Array2::from_elem((h as usize, w as usize), appearance.color);
         }
-
-        let qt_edge = self.edge_detector.apply(&gs_ori_img, 5)?;
-        let qt_edge_arr = bufr_to_arr(&qt_edge);
-
-        // Apply edge sharpening and map to edge char
-        let mut ds_edge_arr = EdgeDownscaler::hist_downscale(
-            &qt_edge_arr,
-            self.font_settings.font_size as usize,
-            sharpen_thres,
-            (new_h as usize, new_w as usize),
+        if !appearance.use_image_color {
+            colors.fill(appearance.color);
+            return;
+        }
+        // Single channel images read their luminance straight from the buffer, in the row order
+        // of the grid, since every channel of the color equals it. Cells without any opaque
+        // pixel under them have no color of their own and take the background
+        let bg_color = appearance.bg_color;
+        match arr_img {
+            DynamicImage::ImageLuma8(luma) => {
+                for (color, &Luma([l])) in colors.iter_mut().zip(luma.pixels()) {
+                    *color = Rgb([l, l, l]);
+                }
+            }
+            DynamicImage::ImageLumaA8(luma_alpha) => {
+                for (color, &LumaA([l, a])) in colors.iter_mut().zip(luma_alpha.pixels()) {
+                    *color = if a == 0 { bg_color } else { Rgb([l, l, l]) };
+                }
+            }
+            _ => {
+                for ((y, x), color) in colors.indexed_iter_mut() {
+                    let pixel = arr_img.get_pixel(x as u32, y as u32);
+                    *color = if pixel[3] == 0 {
+                        bg_color
+                    } else {
+                        pixel.to_rgb()
+                    };
+                }
+            }
+        }
+    }
+
+    pub(crate) fn reuses_frame_buffers(&self) -> bool {
+        // Frames can only share a background and skip the fine pass when neither depends on the
+        // image
+        self.detail_mode == DetailMode::Single
+            && !matches!(self.background, BackgroundMode::BlurredImage { .. })
+    }
+
+    pub(crate) fn frame_background(&self, w: u32, h: u32) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+        self.background.fill(w, h, self.bg_color, None)
+    }
+
+    pub(crate) fn convert_frame_into(
+        &self,
+        ori_img: &DynamicImage,
+        sharpen_thres: f32,
+        background: &ImageBuffer<Rgb<u8>, Vec<u8>>,
+        colors: &mut Array2<Rgb<u8>>,
+        out: &mut ImageBuffer<Rgb<u8>, Vec<u8>>,
+    ) -> Result<(), ConvertError> {
+        /*
+         * convert_image drawing into out over a background made once for the sequence, only for
+         * converters whose reuses_frame_buffers is true. out and background need the output size
+         */
+        let ori_img = self.color_preprocess(ori_img)?;
+        let appearance = self.appearance();
+        let converted = self.convert_to_grid_as(&ori_img, sharpen_thres, &appearance)?;
+        let grid = cells_to_chars(&converted.cells.view(), &self.pixel_mapping);
+        self.cell_colors_into(&converted.resized, &appearance, colors);
+        let edge_strength = converted.edge_strength.as_ref().map(|s| s.view());
+        appearance
+            .edge_color
+            .apply(&converted.cells.view(), edge_strength.as_ref(), colors);
+        out.copy_from_slice(background);
+        self.draw_glyphs_into(
+            out,
+            background,
+            &grid.view(),
+            &colors.view(),
+            self.cell_px(),
+            None,
+            &appearance,
+        )?;
+        self.finish_render(out)
+    }
+
+    pub fn output_geometry(
+        &self,
+        input_w: u32,
+        input_h: u32,
+    ) -> Result<OutputGeometry, ConvertError> {
+        /*
+         * Grid and pixel size of the output for an input of input_w x input_h pixels, without
+         * converting anything. Uses the same math as the conversion, so it fails the same way on
+         * images that are too small or too large. The sides of the input swap with a quarter turn
+         * orientation
+         */
+        let font_size = self.font_settings.font_size;
+        let (input_w, input_h) = self.budget_size(input_w, input_h)?;
+        let (input_w, input_h) = self.pixel_cells_size(input_w, input_h)?;
+        let (w, h) = if self.orientation.swaps_axes() {
+            (input_h, input_w)
+        } else {
+            (input_w, input_h)
+        };
+        let (cols, rows) = self.grid_size_of(w, h, (font_size, font_size))?;
+        Ok(OutputGeometry {
+            cols,
+            rows,
+            pixel_w: cols * self.cell_px(),
+            pixel_h: rows * self.cell_px(),
+        })
+    }
+
+    pub fn source_map(&self, input_w: u32, input_h: u32) -> Result<SourceMap, ConvertError> {
+        /*
+         * Pixels of an input of input_w x input_h every cell of its grid is sampled from, in the
+         * input as oriented and before any downscale to the pixel budget. The grid is the one of
+         * output_geometry
+         */
+        let geometry = self.output_geometry(input_w, input_h)?;
+        let input = if self.orientation.swaps_axes() {
+            (input_h, input_w)
+        } else {
+            (input_w, input_h)
+        };
+        Ok(SourceMap::new(input, (geometry.cols, geometry.rows)))
+    }
+
+    pub fn describe(&self) -> String {
+        /*
+         * The resolved settings of the converter as a tree: the font, the charset, every stage of
+         * the pipelines with its parameters, how the image is resized and where colors come from.
+         * With a provenance, every setting is marked with where its value came from. The glyph
+         * densities are listed when the font loads
+         */
+        let mut tree = SettingsTree::new(self.provenance.as_ref());
+        self.describe_into(&mut tree);
+        tree.into_text()
+    }
+
+    pub fn describe_input(
+        &self,
+        input_w: u32,
+        input_h: u32,
+        sharpen_thres: f32,
+    ) -> Result<String, ConvertError> {
+        /*
+         * describe followed by the grid an input of input_w x input_h pixels gives, as
+         * output_geometry computes it, and the edge threshold it would be converted with
+         */
+        let geometry = self.output_geometry(input_w, input_h)?;
+        let mut tree = SettingsTree::new(self.provenance.as_ref());
+        self.describe_into(&mut tree);
+        tree.section("input");
+        tree.derived("size", format!("{}x{}", input_w, input_h));
+        tree.derived("grid", format!("{}x{} cells", geometry.cols, geometry.rows));
+        tree.derived(
+            "render",
+            format!("{}x{} px", geometry.pixel_w, geometry.pixel_h),
+        );
+        tree.setting("edge threshold", sharpen_thres, "edge_threshold");
+        Ok(tree.into_text())
+    }
+
+    fn describe_into(&self, tree: &mut SettingsTree) {
+        let or_none = |value: Option<String>| value.unwrap_or_else(|| "none".to_string());
+
+        tree.section("font");
+        let path = &self.font_settings.font_path;
+        let source = match self.loaded_font() {
+            Ok(LoadedFont {
+                fallback: Some(used),
+                ..
+            }) => format!("{}, unreadable so drawn with {}", path, used),
+            Ok(_) => path.clone(),
+            Err(e) => format!("{}, {}", path, e),
+        };
+        tree.setting("source", source, "font_path");
+        tree.setting("size", self.font_settings.font_size, "font_size");
+        tree.setting("cell px", self.cell_px(), "render_cell_px");
+        tree.setting("fit glyphs", self.fit_glyphs, "fit_glyphs");
+
+        tree.section("charset");
+        let tile: String = self.pixel_mapping.tile.iter().collect();
+        tree.setting("tile", format!("{:?}", tile), "tile_chars");
+        let edge: String = self.pixel_mapping.edge.iter().collect();
+        tree.derived("edge", format!("{:?}", edge));
+        if let Ok(densities) = self.pixel_mapping.tile_densities(&self.font_settings) {
+            let densities: Vec<String> = densities
+                .iter()
+                .map(|(ch, density)| format!("{:?} {:.3}", ch, density))
+                .collect();
+            tree.derived("densities", densities.join(", "));
+        }
+        let levels = self.tile_levels.map(|levels| levels.to_string());
+        tree.setting(
+            "levels",
+            levels.unwrap_or_else(|| "whole ramp".to_string()),
+            "tile_levels",
+        );
+        tree.setting(
+            "mapping",
+            format!("{:?}", self.tile_mapping),
+            "tile_mapping",
+        );
+        tree.setting("buckets", format!("{:?}", self.bucket_mode), "bucket_mode");
+        let curve = self.tone_curve.as_ref().map(|curve| {
+            let points: Vec<String> = curve
+                .points()
+                .iter()
+                .map(|(input, output)| format!("{}:{}", input, output))
+                .collect();
+            points.join(",")
+        });
+        tree.setting("tone curve", or_none(curve), "tone_curve");
+        tree.setting("jitter", self.tile_jitter, "tile_jitter");
+        tree.setting("seed", self.seed, "seed");
+        let weight_map = self.weight_map.as_ref().map(|weight_map| {
+            let source = match &weight_map.source {
+                WeightSource::Saliency => "saliency".to_string(),
+                WeightSource::Image(img) => format!("{}x{} image", img.width(), img.height()),
+            };
+            format!(
+                "{} min_levels={} gamma={}",
+                source, weight_map.min_levels, weight_map.gamma
+            )
+        });
+        tree.setting("weight map", or_none(weight_map), "weight_map");
+
+        tree.section("pipelines");
+        let color: Vec<(&'static str, String)> = self
+            .color_preprocessors
+            .iter()
+            .map(|stage| (stage.name(), stage.params()))
+            .collect();
+        tree.stages("color", &color, "color_preprocessors");
+        let stages = |pipeline: &[Box<dyn Processor<u8, u8>>]| -> Vec<(&'static str, String)> {
+            pipeline
+                .iter()
+                .map(|stage| (stage.name(), stage.params()))
+                .collect()
+        };
+        tree.stages(
+            "tile",
+            &stages(&self.tile_preprocessors),
+            "tile_preprocessors",
+        );
+        tree.stages(
+            "edge",
+            &stages(&self.edge_preprocessors),
+            "edge_preprocessors",
+        );
+        let f32_stages: Vec<(&'static str, String)> = match &self.edge_f32_chain {
+            Some(chain) => std::iter::once((chain.promote.name(), chain.promote.params()))
+                .chain(
+                    chain
+                        .stages
+                        .iter()
+                        .map(|stage| (stage.name(), stage.params())),
+                )
+                .collect(),
+            None => vec![],
+        };
+        tree.stages("edge f32", &f32_stages, "edge_f32_stages");
+        if let Some(chain) = &self.edge_f32_chain {
+            tree.setting(
+                "edge f32 normalization",
+                format!("{:?}", chain.normalization),
+                "edge_f32_normalization",
+            );
+        }
+        let detector = format!(
+            "{} {}",
+            self.edge_detector.name(),
+            self.edge_detector.params()
+        );
+        tree.setting("edge detector", detector.trim_end(), "edge_detector");
+        tree.setting(
+            "edge source",
+            format!("{:?}", self.edge_source),
+            "edge_source",
+        );
+        let flow = self
+            .edge_flow
+            .as_ref()
+            .map(|flow| format!("iterations={} radius={}", flow.iterations, flow.radius));
+        tree.setting("edge flow", or_none(flow), "edge_flow");
+        tree.setting(
+            "edge smoothing",
+            format!("{:?}", self.edge_smoothing),
+            "edge_smoothing",
+        );
+        tree.setting("edge halo bias", self.edge_halo_bias, "edge_halo_bias");
+        tree.setting("draw edges", self.draw_edges, "draw_edges");
+        let band_rows = self.band_rows.map(|rows| rows.to_string());
+        tree.setting(
+            "band rows",
+            band_rows.unwrap_or_else(|| "whole image".to_string()),
+            "band_rows",
+        );
+
+        tree.section("resize");
+        tree.setting(
+            "filter",
+            format!("{:?}", self.resize_filter),
+            "resize_filter",
+        );
+        tree.setting("linear", self.linear_resize, "linear_resize");
+        tree.setting(
+            "tile sampling",
+            format!("{:?}", self.tile_sampling),
+            "tile_sampling",
+        );
+        tree.setting("pixel cells", self.pixel_cells, "pixel_cells");
+        tree.setting(
+            "orientation",
+            format!("{:?}", self.orientation),
+            "orientation",
+        );
+        tree.setting(
+            "max input pixels",
+            or_none(self.max_input_pixels.map(|max| max.to_string())),
+            "max_input_pixels",
+        );
+        tree.setting(
+            "auto downscale",
+            self.auto_downscale_large,
+            "auto_downscale_large",
+        );
+        tree.setting(
+            "small image fallback",
+            self.small_image_fallback,
+            "small_image_fallback",
+        );
+
+        tree.section("color");
+        tree.setting("image color", self.use_image_color, "use_image_color");
+        tree.setting("color", format!("{:?}", self.color.0), "color");
+        tree.setting("edges", format!("{:?}", self.edge_color), "edge_color");
+        tree.setting("bg color", format!("{:?}", self.bg_color.0), "bg_color");
+        tree.setting("background", format!("{:?}", self.background), "background");
+        tree.setting(
+            "adaptive glyph contrast",
+            self.adaptive_glyph_contrast,
+            "adaptive_glyph_contrast",
+        );
+        tree.setting(
+            "color management",
+            self.color_management,
+            "color_management",
+        );
+
+        tree.section("output");
+        tree.setting("detail", format!("{:?}", self.detail_mode), "detail_mode");
+        let watermark = self
+            .watermark
+            .as_ref()
+            .map(|watermark| format!("{:?} {:?}", watermark.text, watermark.corner));
+        tree.setting("watermark", or_none(watermark), "watermark");
+        let effects: Vec<String> = self
+            .post_effects
+            .iter()
+            .map(|effect| format!("{:?}", effect))
+            .collect();
+        let effects = (!effects.is_empty()).then(|| effects.join(", "));
+        tree.setting("post effects", or_none(effects), "post_effects");
+        let quantize = self
+            .output_quantize
+            .as_ref()
+            .map(|quantize| format!("colors={} dither={}", quantize.colors, quantize.dither));
+        tree.setting("quantize", or_none(quantize), "output_quantize");
+        tree.setting("trim", format!("{:?}", self.trim), "trim");
+        tree.setting("source map", self.include_source_map, "include_source_map");
+        #[cfg(feature = "serde")]
+        tree.setting(
+            "embed metadata",
+            self.embedded_config.is_some(),
+            "embed_metadata",
+        );
+        tree.setting("tolerant decode", self.tolerant_decode, "tolerant_decode");
+        tree.setting("strict", self.strict, "strict");
+        tree.setting("score fidelity", self.score_fidelity, "score_fidelity");
+    }
+
+    #[cfg(feature = "serde")]
+    fn json_source_map(&self, input: &DynamicImage) -> Result<Option<SourceMap>, ConvertError> {
+        // Source map of a JSON output when it includes one
+        self.include_source_map
+            .then(|| self.source_map(input.width(), input.height()))
+            .transpose()
+    }
+
+    pub fn fit_text_output(
+        &self,
+        input_w: u32,
+        input_h: u32,
+        target: OutputTarget,
+        max_total_chars: usize,
+    ) -> Result<TextFit, ConvertError> {
+        /*
+         * Font size giving the largest grid of an input of input_w x input_h pixels whose text
+         * for target, newlines and ANSI escapes included, takes at most max_total_chars
+         * characters. The grid keeps the aspect ratio of the input with cells of the target's
+         * cell aspect, and the bound holds for whatever colors the cells get
+         */
+        self.fit_text_with(input_w, input_h, target, max_total_chars, |fit| fit.max_len)
+    }
+
+    pub fn fit_columns(
+        &self,
+        input_w: u32,
+        input_h: u32,
+        target: OutputTarget,
+        max_cols: usize,
+    ) -> Result<TextFit, ConvertError> {
+        // Same as fit_text_output for a budget of max_cols characters per line, newline excluded
+        self.fit_text_with(input_w, input_h, target, max_cols, |fit| fit.cols as usize)
+    }
+
+    fn fit_text_with(
+        &self,
+        input_w: u32,
+        input_h: u32,
+        target: OutputTarget,
+        budget: usize,
+        cost: impl Fn(&TextFit) -> usize,
+    ) -> Result<TextFit, ConvertError> {
+        /*
+         * Smallest font size whose grid has a cost within budget. Grids only shrink as the font
+         * size grows, so the first one that fits is the largest. The font size grows until the
+         * grid is a single cell or the cells no longer fit in the input
+         */
+        target.validate()?;
+        if target == OutputTarget::Png {
+            return Err(ConvertError::InvalidSetting {
+                field: "target",
+                reason: "only text targets have a character budget",
+            });
+        }
+        if self.pixel_cells {
+            return Err(ConvertError::InvalidSetting {
+                field: "pixel_cells",
+                reason: "one cell per pixel leaves no grid size to fit",
+            });
+        }
+        let (w, h) = self.budget_size(input_w, input_h)?;
+        let (w, h) = if self.orientation.swaps_axes() {
+            (h, w)
+        } else {
+            (w, h)
+        };
+        let cell_aspect = target.cell_aspect();
+        // Fails the same way as the conversion on inputs smaller than a cell
+        self.grid_size_of(w, h, tile_size(1, cell_aspect))?;
+
+        let mut needed = 0;
+        for font_size in 1.. {
+            let Ok((cols, rows)) = self.grid_size_of(w, h, tile_size(font_size, cell_aspect))
+            else {
+                break;
+            };
+            let fit = TextFit {
+                font_size,
+                cols,
+                rows,
+                max_len: target
+                    .max_text_len(rows as usize, cols as usize)
+                    .unwrap_or_default(),
+            };
+            needed = cost(&fit);
+            if needed <= budget {
+                return Ok(fit);
+            }
+            if (cols, rows) == (1, 1) {
+                break;
+            }
+        }
+        Err(ConvertError::TextBudgetTooSmall { budget, needed })
+    }
+
+    fn budget_size(&self, w: u32, h: u32) -> Result<(u32, u32), ConvertError> {
+        /*
+         * Size an input of w x h pixels is brought to before the pipeline. Inputs over
+         * max_input_pixels are an error, or shrunk to fit with their aspect ratio kept when
+         * auto_downscale_large is set
+         */
+        let pixels = w as u64 * h as u64;
+        let max = match self.max_input_pixels {
+            Some(max) if pixels > max => max,
+            _ => return Ok((w, h)),
+        };
+        if !self.auto_downscale_large {
+            return Err(ConvertError::ImageTooLarge { pixels, max });
+        }
+        let scale = (max as f64 / pixels as f64).sqrt();
+        let fitted = (
+            ((w as f64 * scale).floor() as u32).max(1),
+            ((h as f64 * scale).floor() as u32).max(1),
+        );
+        if self.strict {
+            return Err(ConvertError::StrictViolation(ConvertWarning::Downscaled {
+                from: (w, h),
+                to: fitted,
+            }));
+        }
+        Ok(fitted)
+    }
+
+    fn pixel_cells_size(&self, w: u32, h: u32) -> Result<(u32, u32), ConvertError> {
+        /*
+         * Size an input of w x h pixels is blown up to so every pixel fills one cell, the size
+         * itself when not converting with one cell per pixel. Inputs over MAX_PIXEL_CELLS are an
+         * error
+         */
+        if !self.pixel_cells {
+            return Ok((w, h));
+        }
+        let pixels = w as u64 * h as u64;
+        if pixels > MAX_PIXEL_CELLS {
+            return Err(ConvertError::ImageTooLarge {
+                pixels,
+                max: MAX_PIXEL_CELLS,
+            });
+        }
+        let font_size = self.font_settings.font_size;
+        Ok((w * font_size, h * font_size))
+    }
+
+    fn fit_pixel_budget<'a>(
+        &self,
+        ori_img: &'a DynamicImage,
+    ) -> Result<Cow<'a, DynamicImage>, ConvertError> {
+        let (w, h) = ori_img.dimensions();
+        let (new_w, new_h) = self.budget_size(w, h)?;
+        if (new_w, new_h) == (w, h) {
+            return Ok(Cow::Borrowed(ori_img));
+        }
+        let _span = stage_span!("downscale_large", width = new_w, height = new_h);
+        stage_event!(width = w, height = h, max = self.max_input_pixels; "input over the pixel budget");
+        Ok(Cow::Owned(ori_img.resize_exact(
+            new_w,
+            new_h,
+            self.resize_filter.filter_type(),
+        )))
+    }
+
+    fn grid_size(
+        &self,
+        ori_img: &DynamicImage,
+        tile: (u32, u32),
+    ) -> Result<(u32, u32), ConvertError> {
+        let (ori_w, ori_h) = ori_img.dimensions();
+        self.grid_size_of(ori_w, ori_h, tile)
+    }
+
+    fn grid_size_of(
+        &self,
+        ori_w: u32,
+        ori_h: u32,
+        (tile_w, tile_h): (u32, u32),
+    ) -> Result<(u32, u32), ConvertError> {
+        /*
+         * Columns and rows of the grid for an image with cells over tile_w x tile_h pixels
+         */
+        let too_small = ori_w < tile_w || ori_h < tile_h;
+        if ori_w == 0 || ori_h == 0 || (too_small && !self.small_image_fallback) {
+            return Err(ConvertError::ImageTooSmall {
+                width: ori_w,
+                height: ori_h,
+                min: tile_w.max(tile_h),
+            });
+        }
+        // A side shorter than the tile still gets one cell when falling back
+        Ok(((ori_w / tile_w).max(1), (ori_h / tile_h).max(1)))
+    }
+
+    /*
+     * The conversion split into steps that can be called one at a time, so other transforms can be
+     * slotted in between them:
+     *
+     * prepare -> quantize_tiles --\
+     *                              combine -> render
+     * detect_edges ---------------/
+     */
+
+    pub fn prepare(&self, ori_img: &DynamicImage) -> Result<PreparedImage, ConvertError> {
+        /*
+         * Bring the image down to one pixel per grid cell, in color for the cell colors and in
+         * preprocessed grayscale for the tiles
+         */
+        let font_size = self.font_settings.font_size;
+        self.prepare_with(ori_img, (font_size, font_size))
+    }
+
+    fn prepare_with(
+        &self,
+        ori_img: &DynamicImage,
+        (tile_w, tile_h): (u32, u32),
+    ) -> Result<PreparedImage, ConvertError> {
+        self.validate()?;
+        let (new_w, new_h) = self.grid_size(ori_img, (tile_w, tile_h))?;
+
+        // Downscaling and grayscale the image for preprocessing
+        let resized = {
+            let _span = stage_span!(
+                "resize",
+                cols = new_w,
+                rows = new_h,
+                linear = self.linear_resize
+            );
+            let filter = self.resize_filter.filter_type();
+            // Transparent pixels hold arbitrary colors, often black, that must not darken the
+            // cells they border
+            if self.linear_resize {
+                resize_exact_linear(ori_img, new_w, new_h, filter)
+            } else if ori_img.color().has_alpha() {
+                resize_exact_premultiplied(ori_img, new_w, new_h, filter)
+            } else {
+                ori_img.resize_exact(new_w, new_h, filter)
+            }
+        };
+        // The tile preprocessors run before quantization, at the grid resolution or the original
+        // one depending on where the image is brought down to a pixel per cell
+        let mut gray = if self.tile_sampling.at_original_resolution() {
+            ori_img.to_luma8()
+        } else {
+            resized.to_luma8()
+        };
+        gray = self.run_preprocessors(&self.tile_preprocessors, gray, "tile")?;
+        let grid = (new_h as usize, new_w as usize);
+        match self.tile_sampling {
+            TileSampling::Resize => {}
+            TileSampling::ExactBoxAverage => {
+                let _span = stage_span!("box_average", cols = new_w, rows = new_h);
+                let block = (tile_h as usize, tile_w as usize);
+                gray = box_average_rect(&gray, block, grid);
+            }
+            TileSampling::TrimmedMean { trim } => {
+                let _span = stage_span!("trimmed_mean", cols = new_w, rows = new_h, trim = trim);
+                let block = (tile_h as usize, tile_w as usize);
+                gray = trimmed_mean_rect(&gray, block, grid, trim);
+            }
+        }
+
+        // Only variance aware mapping needs the original resolution tiles
+        let variance = match self.tile_mapping {
+            TileMapping::Luminance => None,
+            TileMapping::LuminanceAndVariance { .. } => {
+                let _span = stage_span!("tile_stats", cols = new_w, rows = new_h);
+                Some(TileStats::new(&ori_img.to_luma8(), (new_h as usize, new_w as usize)).variance)
+            }
+        };
+
+        // Percentile buckets split the luminance the tiles are quantized from, past the tone curve
+        let bucket_boundaries = match self.bucket_mode {
+            BucketMode::Linear => None,
+            BucketMode::Percentile => {
+                let levels = self.pixel_mapping.tile.len();
+                let _span = stage_span!("bucket_boundaries", levels = levels);
+                let curve = self.tone_curve.as_ref();
+                let luma = gray.pixels().map(|p| curve.map_or(p[0], |c| c.apply(p[0])));
+                Some(percentile_boundaries(luma, levels))
+            }
+        };
+
+        Ok(PreparedImage {
+            resized,
+            gray,
+            variance,
+            bucket_boundaries,
+        })
+    }
+
+    fn run_preprocessors<'a, P: Borrow<dyn Processor<u8, u8> + 'a>>(
+        &self,
+        preprocessors: &[P],
+        mut gray: GrayImage,
+        layer: &'static str,
+    ) -> Result<GrayImage, ConvertError> {
+        /*
+         * A u8 pipeline over gray, stage after stage on the whole image or, with band_rows, band
+         * after band overlapped by pipeline_border rows, which bounds the memory of large images
+         * and gives the same result
+         */
+        if let Some(band_rows) = self.band_rows {
+            let _span = stage_span!(
+                "preprocess_banded",
+                layer = layer,
+                rows = band_rows,
+                border = pipeline_border(preprocessors)
+            );
+            return apply_banded(preprocessors, &gray, band_rows);
+        }
+        for preproc in preprocessors.iter().map(|p| p.borrow()) {
+            let _span = stage_span!("preprocess", name = preproc.name(), layer = layer);
+            gray = preproc.apply(&gray)?;
+        }
+        Ok(gray)
+    }
+
+    pub fn quantize_tiles(&self, prepared: &PreparedImage) -> Array2<usize> {
+        /*
+         * Index into the tile set of every cell. The tone curve, if any, remaps the luminance of
+         * the preprocessed cell first, and the buckets are split as the bucket mode says. With
+         * variance aware mapping, the base bucket still comes from the preprocessed cell and only
+         * the variance from the original tile. The tile jitter is applied next, and the weight map,
+         * if any, shortens the ramp of every cell last
+         */
+        let levels = self.pixel_mapping.tile.len();
+        let mut luma = bufr_to_arr(&prepared.gray);
+        if let Some(curve) = &self.tone_curve {
+            luma.mapv_inplace(|l| curve.apply(l));
+        }
+        let options = MappingOptions::new(self.tile_mapping);
+        let mapping = &self.pixel_mapping;
+        // The variance is only measured for variance aware mapping
+        let index = |l: u8, options: &MappingOptions| match &prepared.bucket_boundaries {
+            Some(boundaries) => mapping.tile_index_in_buckets(l, boundaries, options),
+            None => mapping.tile_index_for_luminance(l, options),
+        };
+        let mut tiles = match &prepared.variance {
+            Some(variance) => Zip::from(&luma)
+                .and(variance)
+                .map_collect(|&l, &v| index(l, &options.with_variance(v))),
+            None => luma.mapv(|l| index(l, &options)),
+        };
+        jitter_tiles(&mut tiles, levels, self.tile_jitter, self.seed);
+        if let Some(weight_map) = &self.weight_map {
+            let _span = stage_span!("weight_map", cols = luma.ncols(), rows = luma.nrows());
+            weight_map.apply(&mut tiles, &weight_map.weights(&prepared.gray), levels);
+        }
+        tiles
+    }
+
+    pub fn detect_edges(
+        &self,
+        ori_img: &DynamicImage,
+        sharpen_thres: f32,
+    ) -> Result<Array2<u8>, ConvertError> {
+        /*
+         * Direction bin of the edge drawn in every grid cell, 0 where there is none. The bins
+         * index into the edge set
+         */
+        let edge_preprocessors: Vec<&dyn Processor<u8, u8>> =
+            self.edge_preprocessors.iter().map(|p| p.as_ref()).collect();
+        self.detect_edges_with(
+            ori_img,
+            self.font_settings.font_size,
+            sharpen_thres,
+            &edge_preprocessors,
+        )
+    }
+
+    fn detect_edges_with(
+        &self,
+        ori_img: &DynamicImage,
+        font_size: u32,
+        sharpen_thres: f32,
+        edge_preprocessors: &[&dyn Processor<u8, u8>],
+    ) -> Result<Array2<u8>, ConvertError> {
+        self.validate()?;
+        check_threshold(sharpen_thres)?;
+        let tile = (font_size, font_size);
+        let (new_w, new_h) = self.grid_size(ori_img, tile)?;
+        let edge_map = self.edge_map(ori_img, edge_preprocessors, None)?;
+        let grid = (new_h as usize, new_w as usize);
+        let (edges, _) = self.downscale_edges(&edge_map, tile, 1.0, sharpen_thres, grid);
+        Ok(edges)
+    }
+
+    fn edge_map(
+        &self,
+        ori_img: &DynamicImage,
+        edge_preprocessors: &[&dyn Processor<u8, u8>],
+        magnitude_reduce: Option<MagnitudeReduce>,
+    ) -> Result<EdgeMap, ConvertError> {
+        /*
+         * Preprocess the image for edges and detect them at its full resolution, the part of the
+         * edge pipeline that does not depend on the grid. With magnitude_reduce set, the gradient
+         * magnitude is measured too, on the image before the preprocessors since thresholds and
+         * the like flatten every edge to the same strength. With EdgeSource::Color every channel
+         * goes through the preprocessors on its own and the detector combines them
+         */
+        let (ori_w, ori_h) = ori_img.dimensions();
+
+        // Find edges, on the luminance or on every color channel
+        let mut layers: Vec<GrayImage> = match self.edge_source {
+            EdgeSource::Grayscale => vec![ori_img.to_luma8()],
+            EdgeSource::Color => {
+                let rgb = ori_img.to_rgb8();
+                (0..3)
+                    .map(|c| {
+                        GrayImage::from_fn(ori_w, ori_h, |x, y| Luma([rgb.get_pixel(x, y)[c]]))
+                    })
+                    .collect()
+            }
+        };
+        // The strongest edge of any layer
+        let magnitude = magnitude_reduce.map(|reduce| {
+            let _span = stage_span!("edge_magnitude", width = ori_w, height = ori_h);
+            let mut magnitudes = layers.iter().map(sobel_magnitude);
+            let first = magnitudes
+                .next()
+                .expect("Edges are found on at least one layer");
+            let strongest = magnitudes.fold(first, |mut max, magnitude| {
+                Zip::from(&mut max)
+                    .and(&magnitude)
+                    .for_each(|max, &m| *max = max.max(m));
+                max
+            });
+            (strongest, reduce)
+        });
+
+        // Apply preprocessors on every layer
+        for layer in layers.iter_mut() {
+            *layer = self.run_preprocessors(edge_preprocessors, std::mem::take(layer), "edge")?;
+            if let Some(chain) = &self.edge_f32_chain {
+                let _span = stage_span!("preprocess", name = "f32_chain", layer = "edge");
+                *layer = chain.apply(layer)?;
+            }
+        }
+
+        let _span = stage_span!("edge_detect", width = ori_w, height = ori_h);
+        let field = match self.edge_source {
+            EdgeSource::Grayscale => self
+                .edge_flow
+                .as_ref()
+                .and_then(|_| self.edge_detector.field(&layers[0])),
+            EdgeSource::Color => {
+                let channels: &[GrayImage; 3] = layers
+                    .as_slice()
+                    .try_into()
+                    .expect("Color edges are found on three channels");
+                // validate only lets color through for detectors supporting it
+                Some(self.edge_detector.channels_field(channels).ok_or(
+                    ConvertError::InvalidSetting {
+                        field: "edge_source",
+                        reason: COLOR_EDGES_UNSUPPORTED,
+                    },
+                )?)
+            }
+        };
+        let directions = match (field, &self.edge_flow) {
+            (Some(field), Some(flow)) => {
+                let _span = stage_span!("edge_flow", iterations = flow.iterations);
+                EdgeDirections::Field(flow.apply(&field))
+            }
+            (Some(field), None) => EdgeDirections::Field(field),
+            (None, _) => {
+                EdgeDirections::Bins(bufr_to_arr(&self.edge_detector.apply(&layers[0], 5)?))
+            }
+        };
+        Ok(EdgeMap {
+            directions,
+            magnitude,
+        })
+    }
+
+    fn downscale_edges(
+        &self,
+        edge_map: &EdgeMap,
+        (tile_w, tile_h): (u32, u32),
+        cell_aspect: f32,
+        sharpen_thres: f32,
+        grid: (usize, usize),
+    ) -> (Array2<u8>, Option<Array2<f32>>) {
+        /*
+         * Bring the edge map down to one direction bin per cell of grid, the cells being over
+         * tile_w x tile_h pixels and drawn cell_aspect times as wide as they are high. The
+         * strength of every edge cell comes along when the map has magnitudes
+         */
+        let qt_edge_arr = edge_map.directions.for_aspect(cell_aspect);
+        let tile = (tile_h as usize, tile_w as usize);
+
+        // Apply edge sharpening
+        let (mut ds_edge_arr, edge_strength) = {
+            let _span = stage_span!(
+                "downscale",
+                tile_size = tile_w,
+                sharpen_thres = sharpen_thres
+            );
+            match &edge_map.magnitude {
+                Some((magnitude, reduce)) => {
+                    let (edges, strength) = EdgeDownscaler::hist_downscale_with_magnitude(
+                        &qt_edge_arr,
+                        magnitude,
+                        tile,
+                        sharpen_thres,
+                        grid,
+                        *reduce,
+                    );
+                    (edges, Some(strength))
+                }
+                None => (
+                    EdgeDownscaler::hist_downscale_rect(&qt_edge_arr, tile, sharpen_thres, grid),
+                    None,
+                ),
+            }
+        };
+        if self.edge_smoothing.radius > 0 {
+            let _span = stage_span!("edge_smoothing", radius = self.edge_smoothing.radius);
+            ds_edge_arr = self.edge_smoothing.apply(&ds_edge_arr);
+        }
+        (ds_edge_arr, edge_strength)
+    }
+
+    pub fn combine(
+        &self,
+        tiles: &ArrayView2<usize>,
+        edges: &ArrayView2<u8>,
+    ) -> Result<Array2<CellValue>, ConvertError> {
+        /*
+         * Cells of the final grid, the edge where a cell has one and the tile otherwise. Edge bin 0
+         * means no edge whatever the first edge character is. With edge_halo_bias, the tiles
+         * around edge cells move that many steps denser first. Both grids need the same shape
+         */
+        if tiles.dim() != edges.dim() {
+            return Err(ConvertError::NdArrayShapeError);
+        }
+        let biased;
+        let tiles = if self.edge_halo_bias > 0 {
+            let _span = stage_span!("edge_halo", bias = self.edge_halo_bias);
+            let mut halo_tiles = tiles.to_owned();
+            let levels = self.pixel_mapping.tile.len();
+            bias_edge_halo(&mut halo_tiles, edges, self.edge_halo_bias, levels);
+            biased = halo_tiles;
+            biased.view()
+        } else {
+            tiles.view()
+        };
+        Ok(Zip::from(tiles)
+            .and(edges)
+            .par_map_collect(|&tile, &edge| match edge {
+                0 => CellValue::Tile(tile),
+                bin => CellValue::Edge(bin as usize),
+            }))
+    }
+
+    pub fn render(
+        &self,
+        cells: &ArrayView2<CellValue>,
+        colors: &ArrayView2<Rgb<u8>>,
+    ) -> Result<ImageBuffer<Rgb<u8>, Vec<u8>>, ConvertError> {
+        /*
+         * Draw the grid with every character in the color of its cell, edges recolored by the
+         * edge color mode. colors needs the shape of the grid. The edge strengths are not known
+         * here, so Magnitude draws every edge at full strength
+         */
+        if cells.dim() != colors.dim() {
+            return Err(ConvertError::NdArrayShapeError);
+        }
+        let grid = cells_to_chars(cells, &self.pixel_mapping);
+        let mut colors = colors.to_owned();
+        self.edge_color.apply(cells, None, &mut colors);
+        let mut ascii_bufr = self.draw_grid(
+            &grid.view(),
+            &colors.view(),
+            self.cell_px(),
+            None,
+            None,
+            &self.appearance(),
+        )?;
+        self.finish_render(&mut ascii_bufr)?;
+        Ok(ascii_bufr)
+    }
+
+    pub fn render_diff(
+        &self,
+        grid: &ArrayView2<char>,
+        diff: &GridDiff,
+        highlight: Rgb<u8>,
+    ) -> Result<ImageBuffer<Rgb<u8>, Vec<u8>>, ConvertError> {
+        /*
+         * Draw grid, usually the second of the two grids diff compared, with its changed cells in
+         * highlight and the others in the glyph color dimmed towards the background. diff needs
+         * the shape of the grid
+         */
+        if grid.dim() != diff.changed.dim() {
+            return Err(ConvertError::NdArrayShapeError);
+        }
+        let dimmed = Rgb(std::array::from_fn(|c| {
+            (self.color[c] as f32 * DIFF_DIM + self.bg_color[c] as f32 * (1.0 - DIFF_DIM)).round()
+                as u8
+        }));
+        let colors = diff
+            .changed
+            .mapv(|changed| if changed { highlight } else { dimmed });
+        let appearance = Appearance {
+            background: BackgroundMode::Solid,
+            adaptive_glyph_contrast: false,
+            ..self.appearance()
+        };
+        self.draw_grid(
+            grid,
+            &colors.view(),
+            self.cell_px(),
+            None,
+            None,
+            &appearance,
+        )
+    }
+
+    fn convert_to_grid(
+        &self,
+        ori_img: &DynamicImage,
+        sharpen_thres: f32,
+        cancel: Option<&CancelToken>,
+    ) -> Result<ConvertedGrid, ConvertError> {
+        let edge_preprocessors: Vec<&dyn Processor<u8, u8>> =
+            self.edge_preprocessors.iter().map(|p| p.as_ref()).collect();
+        self.convert_to_grid_with(
+            ori_img,
+            self.font_settings.font_size,
+            sharpen_thres,
+            &edge_preprocessors,
+            &self.appearance(),
+            cancel,
+        )
+    }
+
+    fn convert_to_grid_as(
+        &self,
+        ori_img: &DynamicImage,
+        sharpen_thres: f32,
+        appearance: &Appearance,
+    ) -> Result<ConvertedGrid, ConvertError> {
+        let edge_preprocessors: Vec<&dyn Processor<u8, u8>> =
+            self.edge_preprocessors.iter().map(|p| p.as_ref()).collect();
+        self.convert_to_grid_with(
+            ori_img,
+            self.font_settings.font_size,
+            sharpen_thres,
+            &edge_preprocessors,
+            appearance,
+            None,
+        )
+    }
+
+    fn convert_to_grid_with(
+        &self,
+        ori_img: &DynamicImage,
+        font_size: u32,
+        sharpen_thres: f32,
+        edge_preprocessors: &[&dyn Processor<u8, u8>],
+        appearance: &Appearance,
+        cancel: Option<&CancelToken>,
+    ) -> Result<ConvertedGrid, ConvertError> {
+        /*
+         * Run the tile and edge pipelines on a decoded image and combine them into a grid of cells.
+         * The downscaled image is returned alongside since it holds the color of each cell.
+         * font_size is the size of a cell in pixels, which only differs from the font settings for
+         * the fine pass of TwoScale. Edges are drawn and measured as the appearance asks. A cancel
+         * token is checked between the pipelines
+         */
+        let check = || cancel.map_or(Ok(()), CancelToken::check);
+        check_threshold(sharpen_thres)?;
+        let tile = (font_size, font_size);
+        let prepared = self.prepare_with(ori_img, tile)?;
+        check()?;
+        // With one cell per pixel, edges run between cells rather than through them
+        let edge_map = if appearance.draw_edges && !self.pixel_cells {
+            let magnitude_reduce = appearance.edge_color.magnitude_reduce();
+            Some(self.edge_map(ori_img, edge_preprocessors, magnitude_reduce)?)
+        } else {
+            None
+        };
+        check()?;
+        self.grid_from_prepared(
+            ori_img,
+            prepared,
+            tile,
+            1.0,
+            sharpen_thres,
+            edge_map.as_ref(),
+        )
+    }
+
+    pub(crate) fn grid_edge_map(
+        &self,
+        ori_img: &DynamicImage,
+        appearance: &Appearance,
+    ) -> Result<Option<EdgeMap>, ConvertError> {
+        // The edge map convert_to_grid detects, None when no edges are drawn
+        if !appearance.draw_edges || self.pixel_cells {
+            return Ok(None);
+        }
+        let edge_preprocessors: Vec<&dyn Processor<u8, u8>> =
+            self.edge_preprocessors.iter().map(|p| p.as_ref()).collect();
+        let magnitude_reduce = appearance.edge_color.magnitude_reduce();
+        Ok(Some(self.edge_map(
+            ori_img,
+            &edge_preprocessors,
+            magnitude_reduce,
+        )?))
+    }
+
+    pub(crate) fn grid_edges(
+        &self,
+        edge_map: Option<&EdgeMap>,
+        sharpen_thres: f32,
+        grid: (usize, usize),
+    ) -> (Array2<u8>, Option<Array2<f32>>) {
+        // Edge map brought down to a grid of square cells, no edges without a map
+        let font_size = self.font_settings.font_size;
+        match edge_map {
+            Some(edge_map) => {
+                self.downscale_edges(edge_map, (font_size, font_size), 1.0, sharpen_thres, grid)
+            }
+            None => (Array2::zeros(grid), None),
+        }
+    }
+
+    fn convert_to_grid_for(
+        &self,
+        ori_img: &DynamicImage,
+        font_size: u32,
+        cell_aspect: f32,
+        sharpen_thres: f32,
+        edge_map: Option<&EdgeMap>,
+    ) -> Result<ConvertedGrid, ConvertError> {
+        /*
+         * Grid of cells drawn cell_aspect times as wide as they are high, with edges from an
+         * edge map detected once for every target. Every cell samples a block of the image with
+         * that aspect, so a grid for tall cells gets fewer rows. No edges are drawn without a map
+         */
+        let tile = tile_size(font_size, cell_aspect);
+        let prepared = self.prepare_with(ori_img, tile)?;
+        self.grid_from_prepared(
+            ori_img,
+            prepared,
+            tile,
+            cell_aspect,
+            sharpen_thres,
+            edge_map,
+        )
+    }
+
+    fn grid_from_prepared(
+        &self,
+        ori_img: &DynamicImage,
+        prepared: PreparedImage,
+        tile: (u32, u32),
+        cell_aspect: f32,
+        sharpen_thres: f32,
+        edge_map: Option<&EdgeMap>,
+    ) -> Result<ConvertedGrid, ConvertError> {
+        let (ori_w, ori_h) = ori_img.dimensions();
+        stage_event!(
+            width = ori_w,
+            height = ori_h,
+            cols = prepared.gray.width(),
+            rows = prepared.gray.height(),
+            tile_w = tile.0,
+            tile_h = tile.1,
+            sharpen_thres = sharpen_thres,
+            draw_edges = edge_map.is_some();
+            "converting image to grid"
+        );
+
+        let tiles = self.quantize_tiles(&prepared);
+        let (edges, edge_strength) = match edge_map {
+            Some(edge_map) => {
+                self.downscale_edges(edge_map, tile, cell_aspect, sharpen_thres, tiles.dim())
+            }
+            None => (Array2::zeros(tiles.dim()), None),
+        };
+        let cells = self.combine(&tiles.view(), &edges.view())?;
+        Ok(ConvertedGrid {
+            cells,
+            resized: prepared.resized,
+            edge_strength,
+            bucket_boundaries: prepared.bucket_boundaries,
+        })
+    }
+
+    pub fn convert_image(
+        &self,
+        ori_img: &DynamicImage,
+        sharpen_thres: f32,
+    ) -> Result<ImageBuffer<Rgb<u8>, Vec<u8>>, ConvertError> {
+        /*
+         * Convert a decoded image into a rendered ascii image
+         */
+        self.load_font()?;
+        let ori_img = self.color_preprocess(ori_img)?;
+        self.convert_preprocessed(&ori_img, sharpen_thres, &self.appearance())
+    }
+
+    pub fn convert_image_with(
+        &self,
+        ori_img: &DynamicImage,
+        sharpen_thres: f32,
+        options: &ConvertOptions,
+    ) -> Result<ImageBuffer<Rgb<u8>, Vec<u8>>, ConvertError> {
+        /*
+         * convert_image with some of the cheap settings overridden for this call only, so one
+         * converter can serve differently styled requests
+         */
+        let appearance = options.apply(self.appearance());
+        self.validate_appearance(&appearance)?;
+        self.load_font()?;
+        let ori_img = self.color_preprocess(ori_img)?;
+        self.convert_preprocessed(&ori_img, sharpen_thres, &appearance)
+    }
+
+    pub fn color_preprocess<'a>(
+        &self,
+        ori_img: &'a DynamicImage,
+    ) -> Result<Cow<'a, DynamicImage>, ConvertError> {
+        /*
+         * Fit a decoded image to the pixel budget, orient it and run the color preprocessors on
+         * it. With one cell per pixel, every pixel is then blown up to a cell sized block. Every
+         * public entry point does this once, the stepwise API leaves it to the caller.
+         * Decoding does not read EXIF orientation, so the user orientation is the only one applied
+         */
+        let ori_img = self.fit_pixel_budget(ori_img)?;
+        let mut ori_img = if self.orientation == Orientation::Normal {
+            ori_img
+        } else {
+            let _span = stage_span!("orient");
+            Cow::Owned(self.orientation.apply(&ori_img).into_owned())
+        };
+        if !self.color_preprocessors.is_empty() {
+            let mut rgb = ori_img.to_rgb8();
+            for preproc in self.color_preprocessors.iter() {
+                let _span = stage_span!("preprocess", name = preproc.name(), layer = "color");
+                rgb = preproc.apply(&rgb)?;
+            }
+            ori_img = Cow::Owned(DynamicImage::ImageRgb8(rgb));
+        }
+        if self.pixel_cells {
+            let (w, h) = self.pixel_cells_size(ori_img.width(), ori_img.height())?;
+            let _span = stage_span!("pixel_cells", width = w, height = h);
+            ori_img = Cow::Owned(ori_img.resize_exact(w, h, FilterType::Nearest));
+        }
+        Ok(ori_img)
+    }
+
+    fn convert_preprocessed(
+        &self,
+        ori_img: &DynamicImage,
+        sharpen_thres: f32,
+        appearance: &Appearance,
+    ) -> Result<ImageBuffer<Rgb<u8>, Vec<u8>>, ConvertError> {
+        // convert_image on an image the color preprocessors already ran on
+        let converted = self.convert_to_grid_as(ori_img, sharpen_thres, appearance)?;
+        self.render_detail(ori_img, &converted, sharpen_thres, appearance)
+    }
+
+    fn render_detail(
+        &self,
+        ori_img: &DynamicImage,
+        converted: &ConvertedGrid,
+        sharpen_thres: f32,
+        appearance: &Appearance,
+    ) -> Result<ImageBuffer<Rgb<u8>, Vec<u8>>, ConvertError> {
+        let colors = self.grid_colors(converted, appearance);
+        self.render_cells(
+            ori_img,
+            &converted.cells.view(),
+            &colors.view(),
+            sharpen_thres,
+            appearance,
         )
-        .mapv(|x: u8| -> char { self.pixel_mapping.edge[x as usize].clone() });
-
-        // Combine tile arr and edge arr
-        Zip::from(&qt_tile_arr)
-            .and(&mut ds_edge_arr)
-            .par_for_each(|&tile_val, edge_val| {
-                if *edge_val == ' ' {
-                    *edge_val = tile_val;
+    }
+
+    pub(crate) fn render_cells(
+        &self,
+        ori_img: &DynamicImage,
+        cells: &ArrayView2<CellValue>,
+        colors: &ArrayView2<Rgb<u8>>,
+        sharpen_thres: f32,
+        appearance: &Appearance,
+    ) -> Result<ImageBuffer<Rgb<u8>, Vec<u8>>, ConvertError> {
+        // render_detail of cells already colored
+        let mut ascii_bufr = self.draw_detail(ori_img, cells, colors, sharpen_thres, appearance)?;
+        self.finish_render(&mut ascii_bufr)?;
+        Ok(ascii_bufr)
+    }
+
+    fn draw_detail(
+        &self,
+        ori_img: &DynamicImage,
+        cells: &ArrayView2<CellValue>,
+        colors: &ArrayView2<Rgb<u8>>,
+        sharpen_thres: f32,
+        appearance: &Appearance,
+    ) -> Result<ImageBuffer<Rgb<u8>, Vec<u8>>, ConvertError> {
+        /*
+         * Draw the grid converted from ori_img in colors. With TwoScale, the cells over busy
+         * tiles are replaced by a grid converted again at a fine_factor times smaller font size,
+         * drawn at a fine_factor times smaller cell size at the same position so the output keeps
+         * the size of the coarse grid
+         */
+        let grid = cells_to_chars(cells, &self.pixel_mapping);
+        let font_size = self.font_settings.font_size;
+        let cell_px = self.cell_px();
+        let DetailMode::TwoScale {
+            fine_factor,
+            variance_threshold,
+        } = self.detail_mode
+        else {
+            return self.draw_grid(
+                &grid.view(),
+                colors,
+                cell_px,
+                None,
+                Some(ori_img),
+                appearance,
+            );
+        };
+
+        let fine_size = font_size / fine_factor;
+        let (rows, cols) = grid.dim();
+        let busy = TileStats::new(&ori_img.to_luma8(), (rows, cols))
+            .variance
+            .mapv(|v| v > variance_threshold);
+        let busy_cells = busy.iter().filter(|&&b| b).count();
+        if busy_cells == 0 {
+            return self.draw_grid(
+                &grid.view(),
+                colors,
+                cell_px,
+                None,
+                Some(ori_img),
+                appearance,
+            );
+        }
+
+        let mut ascii_bufr = self.draw_grid(
+            &grid.view(),
+            colors,
+            cell_px,
+            Some(&busy.mapv(|b| !b)),
+            Some(ori_img),
+            appearance,
+        )?;
+
+        // Stretch the image over the sampled grid so the fine cells line up with the coarse ones
+        let (w, h) = (cols as u32 * font_size, rows as u32 * font_size);
+        let _span = stage_span!("fine_pass", cells = busy_cells, font_size = fine_size);
+        let stretched;
+        let fine_src = if ori_img.dimensions() == (w, h) {
+            ori_img
+        } else {
+            stretched = ori_img.resize_exact(w, h, self.resize_filter.filter_type());
+            &stretched
+        };
+        let edge_preprocessors: Vec<&dyn Processor<u8, u8>> =
+            self.edge_preprocessors.iter().map(|p| p.as_ref()).collect();
+        let fine = self.convert_to_grid_with(
+            fine_src,
+            fine_size,
+            sharpen_thres,
+            &edge_preprocessors,
+            appearance,
+            None,
+        )?;
+
+        let fine_grid = cells_to_chars(&fine.cells.view(), &self.pixel_mapping);
+        let factor = fine_factor as usize;
+        let fine_busy =
+            Array2::from_shape_fn(fine_grid.dim(), |(y, x)| busy[(y / factor, x / factor)]);
+        let fine_colors = self.grid_colors(&fine, appearance);
+        let fine_bufr = self.draw_grid(
+            &fine_grid.view(),
+            &fine_colors.view(),
+            cell_px / fine_factor,
+            Some(&fine_busy),
+            Some(ori_img),
+            appearance,
+        )?;
+
+        for (x, y, pixel) in ascii_bufr.enumerate_pixels_mut() {
+            if busy[((y / cell_px) as usize, (x / cell_px) as usize)] {
+                *pixel = *fine_bufr.get_pixel(x, y);
+            }
+        }
+        Ok(ascii_bufr)
+    }
+
+    pub fn convert_to_chars(
+        &self,
+        ori_img: &DynamicImage,
+        sharpen_thres: f32,
+    ) -> Result<Array2<char>, ConvertError> {
+        // The character grid of ori_img, for comparing conversions with grid_diff
+        let ori_img = self.color_preprocess(ori_img)?;
+        let converted = self.convert_to_grid(&ori_img, sharpen_thres, None)?;
+        Ok(cells_to_chars(&converted.cells.view(), &self.pixel_mapping))
+    }
+
+    pub fn convert_to_heatmap(
+        &self,
+        ori_img: &DynamicImage,
+        sharpen_thres: f32,
+        cell_px: u32,
+    ) -> Result<ImageBuffer<Rgb<u8>, Vec<u8>>, ConvertError> {
+        /*
+         * The tile bucket of every cell of ori_img's grid as a render_bucket_heatmap, to see
+         * where along the ramp a tone curve or tile mapping puts the image
+         */
+        let ori_img = self.color_preprocess(ori_img)?;
+        let converted = self.convert_to_grid(&ori_img, sharpen_thres, None)?;
+        Ok(render_bucket_heatmap(
+            &converted.cells.view(),
+            self.pixel_mapping.tile.len(),
+            cell_px,
+        ))
+    }
+
+    pub fn convert_to_text(
+        &self,
+        ori_img: &DynamicImage,
+        sharpen_thres: f32,
+    ) -> Result<String, ConvertError> {
+        let ori_img = self.color_preprocess(ori_img)?;
+        let converted = self.convert_to_grid(&ori_img, sharpen_thres, None)?;
+        let grid = cells_to_chars(&converted.cells.view(), &self.pixel_mapping);
+        Ok(trimmed_text(&grid.view(), self.trim).0)
+    }
+
+    pub fn convert_to_ansi(
+        &self,
+        ori_img: &DynamicImage,
+        sharpen_thres: f32,
+    ) -> Result<String, ConvertError> {
+        self.convert_to_ansi_cancellable(ori_img, sharpen_thres, &CancelToken::new())
+    }
+
+    pub fn convert_to_ansi_cancellable(
+        &self,
+        ori_img: &DynamicImage,
+        sharpen_thres: f32,
+        cancel: &CancelToken,
+    ) -> Result<String, ConvertError> {
+        /*
+         * convert_to_ansi that gives up with ConvertError::Cancelled once cancel is triggered,
+         * checked between its stages, so an interface can drop a conversion a newer one made
+         * out of date
+         */
+        let ori_img = self.color_preprocess(ori_img)?;
+        let converted = self.convert_to_grid(&ori_img, sharpen_thres, Some(cancel))?;
+        cancel.check()?;
+        let grid = cells_to_chars(&converted.cells.view(), &self.pixel_mapping);
+        let colors = self.grid_colors(&converted, &self.appearance());
+        Ok(trimmed_ansi(&grid.view(), &colors.view(), self.bg_color, self.trim).0)
+    }
+
+    pub fn convert_to_writer<W: Write>(
+        &self,
+        ori_img: &DynamicImage,
+        sharpen_thres: f32,
+        w: &mut W,
+        format: TextFormat,
+    ) -> Result<(), ConvertError> {
+        /*
+         * The output of convert_to_text or convert_to_ansi written to w a grid row at a time, so
+         * the text of very wide grids is never held whole. The grid itself is still converted in
+         * one go, its cells take far less memory than their text
+         */
+        let ori_img = self.color_preprocess(ori_img)?;
+        let converted = self.convert_to_grid(&ori_img, sharpen_thres, None)?;
+        let grid = cells_to_chars(&converted.cells.view(), &self.pixel_mapping);
+        // Trimmed as the string outputs are
+        let offsets = self.trim.offsets(&grid.view());
+        let trimmed = offsets.map_or(grid.view(), |o| o.crop(grid.view()));
+        let trim_trailing = self.trim.trims_trailing();
+        let written = match format {
+            TextFormat::Plain => write_text_rows(&trimmed, trim_trailing, w),
+            TextFormat::Ansi => {
+                let colors = self.grid_colors(&converted, &self.appearance());
+                let colors = offsets.map_or(colors.view(), |o| o.crop(colors.view()));
+                write_ansi_rows(&trimmed, &colors, self.bg_color, trim_trailing, w)
+            }
+        };
+        written
+            .and_then(|_| w.flush())
+            .map_err(|source| ConvertError::OutputError {
+                path: "writer".to_string(),
+                source,
+            })
+    }
+
+    #[cfg(feature = "serde")]
+    pub fn convert_to_json(
+        &self,
+        path: impl AsRef<Path>,
+        sharpen_thres: f32,
+        layout: JsonLayout,
+    ) -> Result<String, ConvertError> {
+        /*
+         * Read the image at path and return its grid as JSON, with the color of every cell, for
+         * consumers that draw the characters themselves
+         */
+        let decoded = self.read_image(path.as_ref())?;
+        let ori_img = self.color_preprocess(&decoded)?;
+        let converted = self.convert_to_grid(&ori_img, sharpen_thres, None)?;
+        let grid = cells_to_chars(&converted.cells.view(), &self.pixel_mapping);
+        let colors = self.grid_colors(&converted, &self.appearance());
+        Ok(grid_to_json(
+            &grid.view(),
+            &colors.view(),
+            self.bg_color,
+            self.cell_px(),
+            layout,
+            self.json_source_map(&decoded)?.as_ref(),
+        ))
+    }
+
+    pub fn convert_to_sixel(
+        &self,
+        path: impl AsRef<Path>,
+        sharpen_thres: f32,
+    ) -> Result<String, ConvertError> {
+        /*
+         * Read the image at path and return the rendered ascii image as a sixel escape sequence,
+         * for showing it inline in terminals with sixel graphics
+         */
+        self.load_font()?;
+        let ori_img = self.read_image(path.as_ref())?;
+        let ascii_img = self.convert_image(&ori_img, sharpen_thres)?;
+        let _span = stage_span!("encode", format = "sixel");
+        Ok(image_to_sixel(&ascii_img))
+    }
+
+    pub fn convert_to_ans(
+        &self,
+        path: impl AsRef<Path>,
+        sharpen_thres: f32,
+        exporter: &AnsExporter,
+    ) -> Result<Vec<u8>, ConvertError> {
+        /*
+         * Read the image at path and return it as an ANSI art file
+         */
+        let decoded = self.read_image(path.as_ref())?;
+        let ori_img = self.color_preprocess(&decoded)?;
+        let converted = self.convert_to_grid(&ori_img, sharpen_thres, None)?;
+        let grid = cells_to_chars(&converted.cells.view(), &self.pixel_mapping);
+        let colors = self.grid_colors(&converted, &self.appearance());
+        exporter.export(&grid.view(), &colors.view(), self.bg_color)
+    }
+
+    pub fn convert_to_txt(
+        &self,
+        path: impl AsRef<Path>,
+        out: impl AsRef<Path>,
+        sharpen_thres: f32,
+        exporter: &TextExporter,
+    ) -> Result<(), ConvertError> {
+        /*
+         * Read the image at path and write its grid to a text file at out. The header holds the
+         * embedded config, so it is empty for converters not built from a config
+         */
+        self.validate()?;
+        check_threshold(sharpen_thres)?;
+        let decoded = self.read_image(path.as_ref())?;
+        let ori_img = self.color_preprocess(&decoded)?;
+        let converted = self.convert_to_grid(&ori_img, sharpen_thres, None)?;
+        let grid = cells_to_chars(&converted.cells.view(), &self.pixel_mapping);
+        let colors = self.grid_colors(&converted, &self.appearance());
+        let settings = self.settings_toml(sharpen_thres)?;
+        let (grid, colors) = match self.trim.offsets(&grid.view()) {
+            Some(offsets) => (offsets.crop(grid.view()), offsets.crop(colors.view())),
+            None => (grid.view(), colors.view()),
+        };
+        let text = exporter.export_with(
+            &grid,
+            &colors,
+            self.bg_color,
+            settings.as_deref(),
+            self.trim.trims_trailing(),
+        )?;
+        let _span = stage_span!("encode", path = &*out.as_ref().to_string_lossy());
+        write_file(out, text.as_bytes(), &self.write_options)?;
+        Ok(())
+    }
+
+    pub fn convert_all_formats(
+        &self,
+        path: impl AsRef<Path>,
+        sharpen_thres: f32,
+        targets: &[OutputTarget],
+    ) -> Result<Vec<TargetOutput>, ConvertError> {
+        /*
+         * Read the image at path once and convert it for every target, each with a grid sampled
+         * for the aspect of its cells. Decoding, the color and edge preprocessing and the edge
+         * detection run once, only the downscale to the grid and the quantization run per target
+         */
+        self.validate()?;
+        check_threshold(sharpen_thres)?;
+        for target in targets {
+            target.validate()?;
+        }
+        if targets.contains(&OutputTarget::Png) {
+            self.load_font()?;
+        }
+        let decoded = self.read_image(path.as_ref())?;
+        let ori_img = self.color_preprocess(&decoded)?;
+        let appearance = self.appearance();
+        let font_size = self.font_settings.font_size;
+        // With one cell per pixel, edges run between cells rather than through them
+        let edge_map = if appearance.draw_edges && !self.pixel_cells {
+            let edge_preprocessors: Vec<&dyn Processor<u8, u8>> =
+                self.edge_preprocessors.iter().map(|p| p.as_ref()).collect();
+            let magnitude_reduce = appearance.edge_color.magnitude_reduce();
+            Some(self.edge_map(&ori_img, &edge_preprocessors, magnitude_reduce)?)
+        } else {
+            None
+        };
+
+        targets
+            .iter()
+            .map(|&target| {
+                let converted = self.convert_to_grid_for(
+                    &ori_img,
+                    font_size,
+                    target.cell_aspect(),
+                    sharpen_thres,
+                    edge_map.as_ref(),
+                )?;
+                let (rows, cols) = converted.cells.dim();
+                let (data, trimmed) = match target {
+                    OutputTarget::Png => (
+                        TargetData::Image(self.render_detail(
+                            &ori_img,
+                            &converted,
+                            sharpen_thres,
+                            &appearance,
+                        )?),
+                        None,
+                    ),
+                    OutputTarget::Txt { .. } => {
+                        let grid = cells_to_chars(&converted.cells.view(), &self.pixel_mapping);
+                        let (text, trimmed) = trimmed_text(&grid.view(), self.trim);
+                        (TargetData::Text(text), trimmed)
+                    }
+                    OutputTarget::Ansi { .. } => {
+                        let grid = cells_to_chars(&converted.cells.view(), &self.pixel_mapping);
+                        let colors = self.grid_colors(&converted, &appearance);
+                        let (text, trimmed) =
+                            trimmed_ansi(&grid.view(), &colors.view(), self.bg_color, self.trim);
+                        (TargetData::Text(text), trimmed)
+                    }
+                };
+                Ok(TargetOutput {
+                    target,
+                    cols,
+                    rows,
+                    data,
+                    trimmed,
+                })
+            })
+            .collect()
+    }
+
+    pub fn preview(
+        &self,
+        ori_img: &DynamicImage,
+        max_cols: u32,
+        sharpen_thres: f32,
+    ) -> Result<String, ConvertError> {
+        /*
+         * Quick ANSI preview for interactive use. The image is shrunk so the grid is at most
+         * max_cols wide and only the DoG and Threshold edge preprocessors are run, so the output
+         * is an approximation of convert_to_ansi rather than a smaller copy of it. Sharpening and
+         * median blurs are skipped, so edges can come out noisier or thicker than in the output
+         * of the same converter
+         */
+        self.preview_cancellable(ori_img, max_cols, sharpen_thres, &CancelToken::new())
+    }
+
+    pub fn preview_cancellable(
+        &self,
+        ori_img: &DynamicImage,
+        max_cols: u32,
+        sharpen_thres: f32,
+        cancel: &CancelToken,
+    ) -> Result<String, ConvertError> {
+        /*
+         * preview that gives up with ConvertError::Cancelled once cancel is triggered, checked
+         * between its stages, so an interface can drop a preview a newer one made out of date
+         */
+        cancel.check()?;
+        let ori_img = &*self.color_preprocess(ori_img)?;
+        let font_size = self.font_settings.font_size.max(1);
+        let (w, h) = ori_img.dimensions();
+        let cols = (w / font_size).clamp(1, max_cols.max(1));
+        let scale = (cols * font_size) as f32 / w as f32;
+
+        let thumbnail;
+        let img = if scale < 1.0 {
+            let new_h = ((h as f32 * scale).round() as u32).max(font_size);
+            thumbnail = ori_img.thumbnail_exact(cols * font_size, new_h);
+            &thumbnail
+        } else {
+            ori_img
+        };
+
+        let edge_preprocessors: Vec<&dyn Processor<u8, u8>> = self
+            .edge_preprocessors
+            .iter()
+            .filter(|p| matches!(p.name(), "dog" | "threshold"))
+            .map(|p| p.as_ref())
+            .collect();
+        let converted = self.convert_to_grid_with(
+            img,
+            font_size,
+            sharpen_thres,
+            &edge_preprocessors,
+            &self.appearance(),
+            Some(cancel),
+        )?;
+        cancel.check()?;
+        let grid = cells_to_chars(&converted.cells.view(), &self.pixel_mapping);
+        let colors = self.grid_colors(&converted, &self.appearance());
+        Ok(trimmed_ansi(&grid.view(), &colors.view(), self.bg_color, self.trim).0)
+    }
+
+    pub fn convert_bytes(
+        &self,
+        bytes: &[u8],
+        format: OutputFormat,
+        sharpen_thres: f32,
+    ) -> Result<Vec<u8>, ConvertError> {
+        /*
+         * Decode an in-memory image with a guessed format and return the encoded output, for use
+         * in pipelines where neither the input nor the output lives on disk
+         */
+        let (out, _) = self.convert_bytes_with_stats(bytes, format, sharpen_thres)?;
+        Ok(out)
+    }
+
+    pub fn convert_bytes_with_stats(
+        &self,
+        bytes: &[u8],
+        format: OutputFormat,
+        sharpen_thres: f32,
+    ) -> Result<(Vec<u8>, GridStats), ConvertError> {
+        /*
+         * Same as convert_bytes, also returning the character usage of the converted grid. When
+         * scoring fidelity, outputs that do not draw the glyphs are still rendered to be scored
+         */
+        if format.is_rendered() || self.score_fidelity {
+            // A missing font fails before the input is read
+            self.load_font()?;
+        }
+        let decoded = self.decode_input(bytes, None)?;
+        self.convert_decoded_with_stats(decoded, format, sharpen_thres)
+    }
+
+    pub fn convert_decoded_with_stats(
+        &self,
+        decoded: Decoded,
+        format: OutputFormat,
+        sharpen_thres: f32,
+    ) -> Result<(Vec<u8>, GridStats), ConvertError> {
+        /*
+         * Same as convert_bytes_with_stats, for an input the caller already decoded and needs
+         * itself. Its warnings end up in the stats, and are still an error in strict mode
+         */
+        let decoded = self.check_decoded(decoded)?;
+        let render = format.is_rendered() || self.score_fidelity;
+        if render {
+            self.load_font()?;
+        }
+        let ori_img = self.color_preprocess(&decoded.image)?;
+        let converted = self.convert_to_grid(&ori_img, sharpen_thres, None)?;
+        let mut stats =
+            self.grid_stats(&converted, decoded.image.dimensions(), format.is_rendered())?;
+        stats.warnings.extend(decoded.warnings);
+        let grid = cells_to_chars(&converted.cells.view(), &self.pixel_mapping);
+        let rendered = if render {
+            let ascii_img =
+                self.render_detail(&ori_img, &converted, sharpen_thres, &self.appearance())?;
+            stats.fidelity = self.score(&decoded.image, &ascii_img);
+            Some(ascii_img)
+        } else {
+            None
+        };
+
+        let out = match (format, rendered) {
+            (OutputFormat::Png, Some(ascii_img)) => {
+                let _span = stage_span!("encode", format = "png");
+                self.encode_png(&ascii_img, sharpen_thres)?
+            }
+            (OutputFormat::Txt, _) => {
+                let (text, trimmed) = trimmed_text(&grid.view(), self.trim);
+                stats.trimmed = trimmed;
+                text.into_bytes()
+            }
+            (OutputFormat::Ansi, _) => {
+                let colors = self.grid_colors(&converted, &self.appearance());
+                let (text, trimmed) =
+                    trimmed_ansi(&grid.view(), &colors.view(), self.bg_color, self.trim);
+                stats.trimmed = trimmed;
+                text.into_bytes()
+            }
+            (OutputFormat::Sixel, Some(ascii_img)) => {
+                let _span = stage_span!("encode", format = "sixel");
+                image_to_sixel(&ascii_img).into_bytes()
+            }
+            (OutputFormat::Inline(protocol), Some(ascii_img)) => {
+                let _span = stage_span!("encode", format = "inline");
+                encode_inline(&ascii_img, protocol)?.into_bytes()
+            }
+            #[cfg(feature = "serde")]
+            (OutputFormat::Json(layout), _) => {
+                let colors = self.grid_colors(&converted, &self.appearance());
+                grid_to_json(
+                    &grid.view(),
+                    &colors.view(),
+                    self.bg_color,
+                    self.cell_px(),
+                    layout,
+                    self.json_source_map(&decoded.image)?.as_ref(),
+                )
+                .into_bytes()
+            }
+            (_, None) => unreachable!("formats that draw the glyphs are always rendered"),
+        };
+        Ok((out, stats))
+    }
+
+    fn score(
+        &self,
+        original: &DynamicImage,
+        ascii_img: &ImageBuffer<Rgb<u8>, Vec<u8>>,
+    ) -> Option<FidelityScore> {
+        /*
+         * Fidelity of a render to the decoded image it came from, turned the same way up, when
+         * scoring is on
+         */
+        self.score_fidelity
+            .then(|| compare(&self.orientation.apply(original), ascii_img))
+    }
+
+    fn grid_stats(
+        &self,
+        converted: &ConvertedGrid,
+        (decoded_w, decoded_h): (u32, u32),
+        rendered: bool,
+    ) -> Result<GridStats, ConvertError> {
+        /*
+         * Character usage of a converted grid, with the warnings of the conversion. Font
+         * warnings only concern outputs that draw the glyphs
+         */
+        let mut stats = GridStats::new(&converted.cells.view(), &self.pixel_mapping);
+        stats.seed = (self.tile_jitter > 0.0).then_some(self.seed);
+        stats.bucket_boundaries = converted.bucket_boundaries.clone();
+        let fitted = self.budget_size(decoded_w, decoded_h)?;
+        if fitted != (decoded_w, decoded_h) {
+            stats.downscaled_from = Some((decoded_w, decoded_h));
+            stats.warnings.push(ConvertWarning::Downscaled {
+                from: (decoded_w, decoded_h),
+                to: fitted,
+            });
+        }
+        if rendered {
+            let loaded = self.loaded_font()?;
+            if let Some(used) = &loaded.fallback {
+                stats.warnings.push(ConvertWarning::FallbackFont {
+                    requested: self.font_settings.font_path.clone(),
+                    used: used.clone(),
+                });
+            }
+            let missing: Vec<char> = stats
+                .counts
+                .iter()
+                .filter(|&&(ch, count)| count > 0 && ch != ' ' && loaded.font.glyph_id(ch).0 == 0)
+                .map(|&(ch, _)| ch)
+                .collect();
+            if !missing.is_empty() {
+                stats.warnings.push(ConvertWarning::MissingGlyphs(missing));
+            }
+        }
+        Ok(stats)
+    }
+
+    pub(crate) fn read_image(&self, path: &Path) -> Result<DynamicImage, ConvertError> {
+        Ok(self.read_decoded(path.as_ref())?.image)
+    }
+
+    fn read_decoded(&self, path: &Path) -> Result<Decoded, ConvertError> {
+        /*
+         * Decode the image at a file path, or at an http(s) url when the http feature is enabled
+         */
+        #[cfg(feature = "http")]
+        if let Some(url) = path.to_str().filter(|path| is_url(path)) {
+            return self.decode_input(&fetch(url, &self.http_options)?, None);
+        }
+
+        let bytes = fs::read(path)?;
+        self.decode_input(&bytes, ImageFormat::from_path(path).ok())
+    }
+
+    pub fn convert_img(
+        &self,
+        path: impl AsRef<Path>,
+        out: impl AsRef<Path>,
+        sharpen_thres: f32,
+    ) -> Result<(), ConvertError> {
+        /*
+         * Read an image given file path and convert that image into an ascii image / txt file / or
+         * print it depending on settings
+         */
+        self.convert_img_with_stats(path, out, sharpen_thres)?;
+        Ok(())
+    }
+
+    pub fn convert_img_with_stats(
+        &self,
+        path: impl AsRef<Path>,
+        out: impl AsRef<Path>,
+        sharpen_thres: f32,
+    ) -> Result<GridStats, ConvertError> {
+        /*
+         * Same as convert_img, also returning the character usage of the converted grid and the
+         * warnings of the conversion
+         */
+        self.validate()?;
+        check_threshold(sharpen_thres)?;
+        self.load_font()?;
+        let decoded = self.read_decoded(path.as_ref())?;
+        let ori_img = self.color_preprocess(&decoded.image)?;
+        let converted = self.convert_to_grid(&ori_img, sharpen_thres, None)?;
+        let mut stats = self.grid_stats(&converted, decoded.image.dimensions(), true)?;
+        stats.warnings.extend(decoded.warnings);
+        let ascii_img =
+            self.render_detail(&ori_img, &converted, sharpen_thres, &self.appearance())?;
+        stats.fidelity = self.score(&decoded.image, &ascii_img);
+
+        self.write_render(&ascii_img, out.as_ref(), sharpen_thres)?;
+        Ok(stats)
+    }
+
+    pub(crate) fn write_render(
+        &self,
+        ascii_img: &ImageBuffer<Rgb<u8>, Vec<u8>>,
+        out: &Path,
+        sharpen_thres: f32,
+    ) -> Result<PathBuf, ConvertError> {
+        /*
+         * Save a render in the format the extension of out names, PNGs with their metadata.
+         * Returns where it was written, which the write options may have renamed
+         */
+        let _span = stage_span!("encode", path = &*out.to_string_lossy());
+        let bytes = if ImageFormat::from_path(out).ok() == Some(ImageFormat::Png) {
+            self.encode_png(ascii_img, sharpen_thres)?
+        } else {
+            encode_for_path(ascii_img, out)?
+        };
+        write_file(out, &bytes, &self.write_options)
+    }
+
+    pub fn render_text_file(
+        &self,
+        txt_path: impl AsRef<Path>,
+        out: impl AsRef<Path>,
+        colors: ColorSource,
+    ) -> Result<Vec<ConvertWarning>, ConvertError> {
+        /*
+         * Render a grid saved as plain text, after editing it by hand for example, into an image
+         * at out. With ColorSource::Image the cells are colored from the image the grid was
+         * converted from, which must give a grid of the same size, so an untouched grid renders
+         * the same as converting the image. Characters the font has no glyph for are a
+         * MissingGlyphs warning, or fail the render in strict mode
+         */
+        self.validate()?;
+        let loaded = self.loaded_font()?;
+        let grid = parse_text_grid(&fs::read_to_string(txt_path.as_ref())?)?;
+
+        let mut warnings = vec![];
+        if let Some(used) = &loaded.fallback {
+            warnings.push(ConvertWarning::FallbackFont {
+                requested: self.font_settings.font_path.clone(),
+                used: used.clone(),
+            });
+        }
+        let mut missing: Vec<char> = vec![];
+        for &ch in grid.iter() {
+            if ch != ' ' && !missing.contains(&ch) && loaded.font.glyph_id(ch).0 == 0 {
+                missing.push(ch);
+            }
+        }
+        if !missing.is_empty() {
+            let warning = ConvertWarning::MissingGlyphs(missing);
+            if self.strict {
+                return Err(ConvertError::StrictViolation(warning));
+            }
+            warnings.push(warning);
+        }
+
+        let ascii_img = match colors {
+            ColorSource::Fixed(color) => {
+                let colors = gradient_colors(color, color, grid.dim());
+                self.arr_to_img_with(&grid.view(), &colors.view(), None)?
+            }
+            ColorSource::Gradient { top, bottom } => {
+                let colors = gradient_colors(top, bottom, grid.dim());
+                self.arr_to_img_with(&grid.view(), &colors.view(), None)?
+            }
+            ColorSource::Image(path) => {
+                let img = self.read_image(&path)?;
+                let ori_img = self.color_preprocess(&img)?;
+                let resized = self.prepare(&ori_img)?.resized;
+                let (cols, rows) = resized.dimensions();
+                if grid.dim() != (rows as usize, cols as usize) {
+                    return Err(ConvertError::GridSizeMismatch {
+                        a: grid.dim(),
+                        b: (rows as usize, cols as usize),
+                    });
                 }
+                // Edges recolored as the image converts, at full strength as the text lost it
+                let appearance = self.appearance();
+                let mut colors = self.cell_colors_with(&resized, &appearance);
+                let cells = chars_to_cells(&grid.view(), &self.pixel_mapping);
+                appearance
+                    .edge_color
+                    .apply(&cells.view(), None, &mut colors);
+                self.arr_to_img_with(&grid.view(), &colors.view(), Some(&ori_img))?
+            }
+        };
+
+        // The config's own threshold goes into the metadata, the text says nothing of it
+        #[cfg(feature = "serde")]
+        let sharpen_thres = self
+            .embedded_config
+            .as_ref()
+            .map_or(0.0, |config| config.edge_threshold);
+        #[cfg(not(feature = "serde"))]
+        let sharpen_thres = 0.0;
+        self.write_render(&ascii_img, out.as_ref(), sharpen_thres)?;
+        Ok(warnings)
+    }
+
+    pub fn convert_comparison(
+        &self,
+        path: impl AsRef<Path>,
+        out: impl AsRef<Path>,
+        sharpen_thres: f32,
+        divider: &Divider,
+    ) -> Result<(), ConvertError> {
+        /*
+         * Save the original image next to its ascii render, for sharing and for comparing
+         * preprocessor changes
+         */
+        self.validate()?;
+        check_threshold(sharpen_thres)?;
+        self.load_font()?;
+        let ori_img = self.read_image(path.as_ref())?;
+        let ascii_img = self.convert_image(&ori_img, sharpen_thres)?;
+        // The original is shown the same way up as its render
+        let oriented = self.orientation.apply(&ori_img);
+        let comparison = side_by_side(&oriented, &ascii_img, divider, self.bg_color);
+
+        let _span = stage_span!("encode", path = &*out.as_ref().to_string_lossy());
+        write_file(
+            &out,
+            &encode_for_path(&comparison, out.as_ref())?,
+            &self.write_options,
+        )?;
+        Ok(())
+    }
+
+    pub fn convert_contact_sheet(
+        &self,
+        path: impl AsRef<Path>,
+        out: impl AsRef<Path>,
+        configs: &[ConverterConfig],
+    ) -> Result<(), ConvertError> {
+        /*
+         * Save the image converted under every config, each with its own edge threshold, on one
+         * sheet for comparing presets. Every render is labeled with its number and a summary of
+         * its config, drawn in this converter's font and color over its background
+         */
+        if configs.is_empty() {
+            return Err(ConvertError::InvalidSetting {
+                field: "configs",
+                reason: "must not be empty",
             });
+        }
+        self.validate()?;
+        let font = self.load_font()?;
+        let ori_img = self.read_image(path.as_ref())?;
+        let tiles = configs
+            .iter()
+            .enumerate()
+            .map(|(i, config)| {
+                let _span = stage_span!("contact_sheet_tile", index = i);
+                let render = config
+                    .build()?
+                    .convert_image(&ori_img, config.edge_threshold)?;
+                Ok((sheet_label(i, config), render))
+            })
+            .collect::<Result<Vec<_>, ConvertError>>()?;
 
-        let ascii_img = self.arr_to_img(&ds_edge_arr.view(), &resized_img)?;
+        let label_color = contrast_glyph_color(self.color, luminance(self.bg_color));
+        let sheet = contact_sheet(&tiles, font, self.cell_px(), self.bg_color, label_color);
+        let _span = stage_span!("encode", path = &*out.as_ref().to_string_lossy());
+        write_file(
+            &out,
+            &encode_for_path(&sheet, out.as_ref())?,
+            &self.write_options,
+        )?;
+        Ok(())
+    }
+
+    fn settings_toml(&self, sharpen_thres: f32) -> Result<Option<String>, ConvertError> {
+        /*
+         * The embedded config, if any, as TOML. The threshold the image was actually converted
+         * with replaces the one in the config, as callers can override it
+         */
+        #[cfg(feature = "serde")]
+        return match &self.embedded_config {
+            Some(config) => Ok(Some(
+                ConverterConfig {
+                    edge_threshold: sharpen_thres,
+                    ..config.clone()
+                }
+                .to_toml()?,
+            )),
+            None => Ok(None),
+        };
+        #[cfg(not(feature = "serde"))]
+        {
+            let _ = sharpen_thres;
+            Ok(None)
+        }
+    }
 
-        // Save image
-        ascii_img.save(out)?;
+    fn encode_png(
+        &self,
+        ascii_img: &ImageBuffer<Rgb<u8>, Vec<u8>>,
+        sharpen_thres: f32,
+    ) -> Result<Vec<u8>, ConvertError> {
+        /*
+         * Encode a rendered image as PNG with the embedded config and its hash, if any. With
+         * output_quantize, the image is reduced to its colors first and written indexed
+         */
+        let settings = self.settings_toml(sharpen_thres)?;
+        #[cfg(feature = "serde")]
+        let config_hash = settings.as_ref().and(self.config_hash.as_deref());
+        #[cfg(not(feature = "serde"))]
+        let config_hash = None;
+        match &self.output_quantize {
+            Some(quantize) => {
+                let _span = stage_span!("quantize", colors = quantize.colors);
+                let (palette, indices) = quantize_image(ascii_img, quantize);
+                encode_indexed_png(
+                    ascii_img.dimensions(),
+                    &palette,
+                    &indices,
+                    settings.as_deref(),
+                    config_hash,
+                )
+            }
+            None => encode_png(ascii_img, settings.as_deref(), config_hash),
+        }
+    }
+}
 
+fn check_threshold(sharpen_thres: f32) -> Result<(), ConvertError> {
+    // A ratio above 1 can never be reached, which would silently drop every edge
+    if (0.0..=1.0).contains(&sharpen_thres) {
         Ok(())
+    } else {
+        Err(ConvertError::InvalidSetting {
+            field: "sharpen_thres",
+            reason: "must be between 0 and 1",
+        })
     }
 }
+
+fn sheet_label(index: usize, config: &ConverterConfig) -> String {
+    // Number of a contact sheet tile and the settings that most change how it looks
+    format!(
+        "{}: {}px, {} tile chars, edges {}",
+        index + 1,
+        config.font_size,
+        config.tile_chars.chars().count(),
+        if config.draw_edges { "on" } else { "off" }
+    )
+}
+
+fn encode_for_path(
+    img: &ImageBuffer<Rgb<u8>, Vec<u8>>,
+    out: &Path,
+) -> Result<Vec<u8>, ConvertError> {
+    /*
+     * Encode an image in the format the extension of out names, as ImageBuffer::save would
+     */
+    let mut bytes = Cursor::new(Vec::new());
+    img.write_to(&mut bytes, ImageFormat::from_path(out)?)?;
+    Ok(bytes.into_inner())
+}
@@ -0,0 +1,14 @@
+/*
+* How much detail the rendered image gets. TwoScale redraws busy cells, those whose tile in the
+* original image has a luminance variance above the threshold, with fine_factor x fine_factor
+* cells of a font fine_factor times smaller
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum DetailMode {
+    #[default]
+    Single,
+    TwoScale {
+        fine_factor: u32,
+        variance_threshold: f32,
+    },
+}
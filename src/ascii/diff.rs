@@ -0,0 +1,80 @@
+use super::error::ConvertError;
+use ndarray::{Array2, ArrayView2, Axis};
+use std::fmt;
+
+/*
+* Cells that differ between two grids of the same size. changed_per_row holds the count of every
+* row, top to bottom, and changed marks the cells that differ
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub struct GridDiff {
+    pub rows: usize,
+    pub cols: usize,
+    pub changed_cells: usize,
+    pub changed_per_row: Vec<usize>,
+    pub changed: Array2<bool>,
+}
+
+impl GridDiff {
+    pub fn changed_ratio(&self) -> f32 {
+        let cells = self.rows * self.cols;
+        if cells == 0 {
+            0.0
+        } else {
+            self.changed_cells as f32 / cells as f32
+        }
+    }
+
+    pub fn changed_positions(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        // (row, col) of every changed cell, row by row
+        self.changed
+            .indexed_iter()
+            .filter(|(_, &changed)| changed)
+            .map(|(pos, _)| pos)
+    }
+}
+
+impl fmt::Display for GridDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Only rows with changes are listed
+        write!(
+            f,
+            "changed: {} of {} cells ({:.1}%)",
+            self.changed_cells,
+            self.rows * self.cols,
+            self.changed_ratio() * 100.0
+        )?;
+        for (row, &count) in self.changed_per_row.iter().enumerate() {
+            if count > 0 {
+                write!(f, "\nrow {:>4}: {} changed", row, count)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn grid_diff(a: &ArrayView2<char>, b: &ArrayView2<char>) -> Result<GridDiff, ConvertError> {
+    /*
+     * Compare two grids cell by cell, typically the same image converted under two configs.
+     * Grids of different sizes are a GridSizeMismatch error, since their cells do not line up
+     */
+    if a.dim() != b.dim() {
+        return Err(ConvertError::GridSizeMismatch {
+            a: a.dim(),
+            b: b.dim(),
+        });
+    }
+    let (rows, cols) = a.dim();
+    let changed = Array2::from_shape_fn((rows, cols), |pos| a[pos] != b[pos]);
+    let changed_per_row: Vec<usize> = changed
+        .axis_iter(Axis(0))
+        .map(|row| row.iter().filter(|&&changed| changed).count())
+        .collect();
+    Ok(GridDiff {
+        rows,
+        cols,
+        changed_cells: changed_per_row.iter().sum(),
+        changed_per_row,
+        changed,
+    })
+}
@@ -0,0 +1,90 @@
+use super::cell::CellValue;
+use crate::image_manip::edge_processor::MagnitudeReduce;
+use image::Rgb;
+use ndarray::{Array2, ArrayView2, Zip};
+
+/*
+* Color edge characters are drawn in. SameAsTile keeps the cell color, Fixed draws every edge in one
+* accent color and Brightened multiplies the cell color by factor.
+* Magnitude scales the cell color with the gradient magnitude of the edge, reduced over the pixels
+* of its tile, relative to the strongest edge of the grid. The strongest edge keeps the cell color
+* and a vanishing one keeps floor of it
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum EdgeColorMode {
+    #[default]
+    SameAsTile,
+    Fixed(Rgb<u8>),
+    Brightened {
+        factor: f32,
+    },
+    Magnitude {
+        reduce: MagnitudeReduce,
+        floor: f32,
+    },
+}
+
+impl EdgeColorMode {
+    pub fn edge_color(&self, cell_color: Rgb<u8>) -> Rgb<u8> {
+        // Magnitude needs the strength of the edge, see edge_color_with_strength
+        self.edge_color_with_strength(cell_color, 1.0)
+    }
+
+    pub fn edge_color_with_strength(&self, cell_color: Rgb<u8>, strength: f32) -> Rgb<u8> {
+        /*
+         * Color of an edge in a cell of cell_color, strength being its magnitude relative to the
+         * strongest edge in 0..=1. Only Magnitude looks at the strength
+         */
+        let scale = |factor: f32| {
+            Rgb(cell_color
+                .0
+                .map(|c| (c as f32 * factor).round().min(255.0) as u8))
+        };
+        match *self {
+            EdgeColorMode::SameAsTile => cell_color,
+            EdgeColorMode::Fixed(color) => color,
+            EdgeColorMode::Brightened { factor } => scale(factor),
+            EdgeColorMode::Magnitude { floor, .. } => {
+                scale(floor + (1.0 - floor) * strength.clamp(0.0, 1.0))
+            }
+        }
+    }
+
+    pub fn magnitude_reduce(&self) -> Option<MagnitudeReduce> {
+        // How the edge strengths are to be measured, None when the mode does not use them
+        match *self {
+            EdgeColorMode::Magnitude { reduce, .. } => Some(reduce),
+            _ => None,
+        }
+    }
+
+    pub fn apply(
+        &self,
+        cells: &ArrayView2<CellValue>,
+        edge_strength: Option<&ArrayView2<f32>>,
+        colors: &mut Array2<Rgb<u8>>,
+    ) {
+        /*
+         * Recolor the cells drawn as edges, colors and edge_strength need the shape of the grid.
+         * edge_strength holds the gradient magnitude of every edge cell, without it Magnitude
+         * draws every edge at full strength
+         */
+        if *self == EdgeColorMode::SameAsTile {
+            return;
+        }
+        let strongest = edge_strength
+            .map(|strength| strength.fold(0.0f32, |max, &s| max.max(s)))
+            .filter(|&max| max > 0.0);
+        Zip::indexed(colors)
+            .and(cells)
+            .for_each(|pos, color, cell| {
+                if let CellValue::Edge(_) = cell {
+                    let strength = match (edge_strength, strongest) {
+                        (Some(strength), Some(max)) => strength[pos] / max,
+                        _ => 1.0,
+                    };
+                    *color = self.edge_color_with_strength(*color, strength);
+                }
+            });
+    }
+}
@@ -1,4 +1,6 @@
+use super::warning::ConvertWarning;
 use ab_glyph::InvalidFont;
+use gif::EncodingError;
 use image::ImageError;
 use ndarray::ShapeError;
 use std::io::Error;
@@ -9,6 +11,76 @@ pub enum ConvertError {
     FileError,
     NdArrayShapeError,
     InvalidFont,
+    MissingFont(String),
+    FrameSizeMismatch {
+        expected: (u32, u32),
+        found: (u32, u32),
+    },
+    EmptyAnimation,
+    // A conversion stopped through its CancelToken
+    Cancelled,
+    Network(String),
+    ConfigError(String),
+    ImageTooSmall {
+        width: u32,
+        height: u32,
+        min: u32,
+    },
+    ImageTooLarge {
+        pixels: u64,
+        max: u64,
+    },
+    InvalidSetting {
+        field: &'static str,
+        reason: &'static str,
+    },
+    UnmappableChar(char),
+    GridTooWide {
+        width: usize,
+        max: usize,
+    },
+    OutputCollision(String),
+    UnsupportedFormat {
+        detected: String,
+        hint: String,
+    },
+    OutputError {
+        path: String,
+        source: Error,
+    },
+    TruncatedImage {
+        format: String,
+    },
+    // A warning raised by a converter in strict mode
+    StrictViolation(ConvertWarning),
+    // Grids compared by grid_diff, as (rows, cols)
+    GridSizeMismatch {
+        a: (usize, usize),
+        b: (usize, usize),
+    },
+    // A file pattern no file matches
+    NoMatches(String),
+    // A grid read back from text that is not one, line counting from 1
+    MalformedTextGrid {
+        line: usize,
+        reason: String,
+    },
+    // Text budget below what the smallest grid of the input takes
+    TextBudgetTooSmall {
+        budget: usize,
+        needed: usize,
+    },
+    // Pipeline stage name matching no stage, with the names of the stages there are
+    UnknownStage {
+        name: String,
+        valid: Vec<&'static str>,
+    },
+    // Glyph of the charset reaching out of its cell, with widths in pixels at the render cell size
+    GlyphTooWide {
+        ch: char,
+        width: u32,
+        cell: u32,
+    },
 }
 
 impl From<ImageError> for ConvertError {
@@ -29,6 +101,40 @@ impl From<InvalidFont> for ConvertError {
     }
 }
 
+impl From<EncodingError> for ConvertError {
+    fn from(err: EncodingError) -> Self {
+        match err {
+            EncodingError::Io(_) => ConvertError::FileError,
+            EncodingError::Format(_) => ConvertError::ImageError,
+        }
+    }
+}
+
+impl From<png::EncodingError> for ConvertError {
+    fn from(err: png::EncodingError) -> Self {
+        match err {
+            png::EncodingError::IoError(_) => ConvertError::FileError,
+            _ => ConvertError::ImageError,
+        }
+    }
+}
+
+impl From<png::DecodingError> for ConvertError {
+    fn from(err: png::DecodingError) -> Self {
+        match err {
+            png::DecodingError::IoError(_) => ConvertError::FileError,
+            _ => ConvertError::ImageError,
+        }
+    }
+}
+
+#[cfg(feature = "watch")]
+impl From<notify::Error> for ConvertError {
+    fn from(_: notify::Error) -> Self {
+        ConvertError::FileError
+    }
+}
+
 impl From<Error> for ConvertError {
     fn from(_: Error) -> Self {
         ConvertError::FileError
@@ -45,8 +151,89 @@ impl std::fmt::Display for ConvertError {
                 "Failed converting image to array with given shape or layout"
             ),
             ConvertError::InvalidFont => write!(f, "Failed reading font data"),
+            ConvertError::MissingFont(path) => write!(f, "Font file {} could not be read", path),
+            ConvertError::FrameSizeMismatch { expected, found } => write!(
+                f,
+                "Frame of size {}x{} does not match the animation size {}x{}",
+                found.0, found.1, expected.0, expected.1
+            ),
+            ConvertError::EmptyAnimation => write!(f, "Animation has no frames to write"),
+            ConvertError::Cancelled => write!(f, "Conversion was cancelled"),
+            ConvertError::Network(reason) => write!(f, "Failed fetching image: {}", reason),
+            ConvertError::ConfigError(reason) => write!(f, "Invalid config: {}", reason),
+            ConvertError::ImageTooSmall { width, height, min } => write!(
+                f,
+                "Image of size {}x{} is smaller than the font size of {} pixels",
+                width, height, min
+            ),
+            ConvertError::ImageTooLarge { pixels, max } => write!(
+                f,
+                "Image of {} pixels is over the limit of {} pixels",
+                pixels, max
+            ),
+            ConvertError::InvalidSetting { field, reason } => {
+                write!(f, "Invalid setting {}: {}", field, reason)
+            }
+            ConvertError::UnmappableChar(ch) => {
+                write!(f, "Character {:?} has no code page 437 encoding", ch)
+            }
+            ConvertError::GridTooWide { width, max } => write!(
+                f,
+                "Grid of {} columns is wider than the limit of {} columns",
+                width, max
+            ),
+            ConvertError::OutputCollision(path) => {
+                write!(f, "Several inputs would be written to {}", path)
+            }
+            ConvertError::UnsupportedFormat { detected, hint } => {
+                write!(f, "Unsupported image format {}: {}", detected, hint)
+            }
+            ConvertError::OutputError { path, source } => {
+                write!(f, "Failed writing {}: {}", path, source)
+            }
+            ConvertError::TruncatedImage { format } => write!(
+                f,
+                "{} data ends early, decode it tolerantly to convert the part that is there",
+                format
+            ),
+            ConvertError::StrictViolation(warning) => {
+                write!(f, "Strict mode does not allow this: {}", warning)
+            }
+            ConvertError::GridSizeMismatch { a, b } => write!(
+                f,
+                "Grids of {}x{} and {}x{} cells can not be compared cell by cell",
+                a.1, a.0, b.1, b.0
+            ),
+            ConvertError::NoMatches(pattern) => write!(f, "No file matches {}", pattern),
+            ConvertError::MalformedTextGrid { line, reason } => {
+                write!(f, "Text grid is malformed at line {}: {}", line, reason)
+            }
+            ConvertError::TextBudgetTooSmall { budget, needed } => write!(
+                f,
+                "Budget of {} is too small, even the smallest grid of the image takes {}",
+                budget, needed
+            ),
+            ConvertError::UnknownStage { name, valid } => write!(
+                f,
+                "No stage is named {}, the stages are: {}",
+                name,
+                valid.join(", ")
+            ),
+            ConvertError::GlyphTooWide { ch, width, cell } => write!(
+                f,
+                "Glyph {:?} is {} pixels wide and does not fit in cells of {} pixels, turn on \
+                 fit_glyphs to scale it down",
+                ch, width, cell
+            ),
         }
     }
 }
 
-impl std::error::Error for ConvertError {}
+impl std::error::Error for ConvertError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConvertError::OutputError { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
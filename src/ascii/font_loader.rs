@@ -1,5 +1,5 @@
 use super::error::ConvertError;
-use ab_glyph::{FontVec, PxScale};
+use ab_glyph::{Font, FontVec, PxScale};
 use std::fs;
 
 #[derive(Debug, Clone)]
@@ -8,6 +8,17 @@ pub struct FontSettings {
     pub font_path: String,
 }
 
+impl Default for FontSettings {
+    fn default() -> Self {
+        FontSettings {
+            // 6 is the optimal and smallest font size for displaying the characters correctly
+            // with the default settings
+            font_size: 6,
+            font_path: "font.ttf".to_string(),
+        }
+    }
+}
+
 impl FontSettings {
     pub fn new(font_size: u32, font_path: &str) -> Self {
         FontSettings {
@@ -15,33 +26,114 @@ impl FontSettings {
             font_path: font_path.to_string(),
         }
     }
+}
 
-    pub fn default() -> Self {
-        FontSettings {
-            // 6 is the optimal and smallest font size for displaying the characters correctly
-            // with the default settings
-            font_size: 6,
-            font_path: "font.ttf".to_string(),
+// Font drawn with when the configured one can not be parsed
+pub const FALLBACK_FONT_PATH: &str = "font.ttf";
+
+/*
+* A parsed font and, when the requested font could not be parsed, the path of the fallback font
+* it was replaced with
+*/
+pub struct LoadedFont {
+    pub font: FontVec,
+    pub fallback: Option<String>,
+}
+
+/*
+* Horizontal extent of the ink of a glyph drawn at the left edge of its cell, in cell widths. The
+* extent scales with the cell, so it is measured once per font. Proportional fonts have glyphs
+* such as W running past the right edge, or with a negative bearing past the left one, which
+* would draw over the neighboring cells
+*/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GlyphFit {
+    pub ch: char,
+    pub left: f32,
+    pub right: f32,
+}
+
+// Slack on the cell edges, so glyphs ending exactly on them are not counted as reaching past
+const FIT_EPSILON: f32 = 1e-3;
+
+impl GlyphFit {
+    pub fn measure(font: &impl Font, ch: char) -> Self {
+        // A glyph without an outline, such as a space, covers nothing
+        let (left, right) = match font.outline(font.glyph_id(ch)) {
+            Some(outline) => {
+                let height = font.height_unscaled();
+                (outline.bounds.min.x / height, outline.bounds.max.x / height)
+            }
+            None => (0.0, 0.0),
+        };
+        GlyphFit { ch, left, right }
+    }
+
+    pub fn overflows(&self) -> bool {
+        self.left < -FIT_EPSILON || self.right > 1.0 + FIT_EPSILON
+    }
+
+    pub fn width_px(&self, cell_px: u32) -> u32 {
+        // Pixels from the cell origin or the left of the ink, whichever is further left, to the
+        // right of the ink
+        ((self.right - self.left.min(0.0)) * cell_px as f32).ceil() as u32
+    }
+
+    pub fn fitted(&self, cell_px: u32) -> (PxScale, i32) {
+        /*
+         * Scale and horizontal offset in pixels that draw the glyph inside a cell of cell_px
+         * pixels. Only the width shrinks, so the glyph keeps the baseline of its row
+         */
+        let cell = cell_px as f32;
+        let extent = (self.right - self.left.min(0.0)).max(1.0);
+        let mut scale_x = cell / extent;
+        // The offset is whole pixels, the scale shrinks to leave room for its rounding
+        let shift = (-self.left * scale_x).max(0.0).ceil();
+        if self.right > 0.0 {
+            scale_x = scale_x.min((cell - shift) / self.right);
         }
+        (
+            PxScale {
+                x: scale_x,
+                y: cell,
+            },
+            shift as i32,
+        )
     }
 }
 
 pub struct FontLoader {}
 
 impl FontLoader {
-    pub fn load_font_from_settings(
-        settings: &FontSettings,
-    ) -> Result<(FontVec, PxScale), ConvertError> {
-        // Load font data
-        let font_dat = fs::read(&settings.font_path)?;
-        let font = match FontVec::try_from_vec(font_dat) {
-            Ok(ft) => ft,
+    pub fn load_font(font_path: &str) -> Result<FontVec, ConvertError> {
+        Ok(FontLoader::load(font_path)?.font)
+    }
+
+    pub fn load(font_path: &str) -> Result<LoadedFont, ConvertError> {
+        // Load font data, a missing file names the path it looked at
+        let font_dat =
+            fs::read(font_path).map_err(|_| ConvertError::MissingFont(font_path.to_string()))?;
+        let loaded = match FontVec::try_from_vec(font_dat) {
+            Ok(font) => LoadedFont {
+                font,
+                fallback: None,
+            },
             Err(_) => {
                 // Attempt fallback to default font if custom font fails
-                let fallback_font_dat = fs::read("font.ttf")?;
-                FontVec::try_from_vec(fallback_font_dat)?
+                let fallback_font_dat = fs::read(FALLBACK_FONT_PATH)?;
+                LoadedFont {
+                    font: FontVec::try_from_vec(fallback_font_dat)?,
+                    fallback: Some(FALLBACK_FONT_PATH.to_string()),
+                }
             }
         };
+        Ok(loaded)
+    }
+
+    pub fn load_font_from_settings(
+        settings: &FontSettings,
+    ) -> Result<(FontVec, PxScale), ConvertError> {
+        let font = FontLoader::load_font(&settings.font_path)?;
         let scale = PxScale::from(settings.font_size as f32);
 
         Ok((font, scale))
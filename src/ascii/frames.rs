@@ -0,0 +1,132 @@
+use super::converter::{Converter, OutputGeometry};
+use super::error::ConvertError;
+use image::{DynamicImage, GenericImageView, ImageBuffer, Rgb};
+use ndarray::Array2;
+
+/*
+* What FrameConverter does with a frame whose size differs from the sequence's. Error rejects it,
+* Reallocate resizes the buffers and carries on at the new size
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FrameSizeChange {
+    #[default]
+    Error,
+    Reallocate,
+}
+
+/*
+* Converts a sequence of frames of one size, keeping the output buffer, the background and the
+* cell colors between frames instead of allocating them again for every frame. Frames come out
+* identical to Converter::convert_image. Converters whose background or fine pass depends on the
+* image (BlurredImage, TwoScale) fall back to convert_image on every frame
+*/
+pub struct FrameConverter {
+    converter: Converter,
+    sharpen_thres: f32,
+    size_change: FrameSizeChange,
+    dimensions: (u32, u32),
+    geometry: OutputGeometry,
+    background: ImageBuffer<Rgb<u8>, Vec<u8>>,
+    colors: Array2<Rgb<u8>>,
+    output: ImageBuffer<Rgb<u8>, Vec<u8>>,
+}
+
+impl FrameConverter {
+    pub fn new(
+        converter: Converter,
+        width: u32,
+        height: u32,
+        sharpen_thres: f32,
+    ) -> Result<Self, ConvertError> {
+        /*
+         * Frame converter for frames of width x height pixels. Fails right away on settings or
+         * sizes the conversion would fail on
+         */
+        converter.validate()?;
+        converter.load_font()?;
+        let mut frames = FrameConverter {
+            converter,
+            sharpen_thres,
+            size_change: FrameSizeChange::default(),
+            dimensions: (width, height),
+            geometry: OutputGeometry {
+                cols: 0,
+                rows: 0,
+                pixel_w: 0,
+                pixel_h: 0,
+            },
+            background: ImageBuffer::new(0, 0),
+            colors: Array2::from_elem((0, 0), Rgb([0, 0, 0])),
+            output: ImageBuffer::new(0, 0),
+        };
+        frames.allocate(width, height)?;
+        Ok(frames)
+    }
+
+    pub fn with_size_change(mut self, size_change: FrameSizeChange) -> Self {
+        self.size_change = size_change;
+        self
+    }
+
+    pub fn converter(&self) -> &Converter {
+        &self.converter
+    }
+
+    pub fn into_converter(self) -> Converter {
+        self.converter
+    }
+
+    pub fn geometry(&self) -> OutputGeometry {
+        self.geometry
+    }
+
+    fn allocate(&mut self, width: u32, height: u32) -> Result<(), ConvertError> {
+        let geometry = self.converter.output_geometry(width, height)?;
+        let (w, h) = (geometry.pixel_w, geometry.pixel_h);
+        if self.converter.reuses_frame_buffers() {
+            self.background = self.converter.frame_background(w, h);
+            self.output = self.background.clone();
+        }
+        self.colors = Array2::from_elem(
+            (geometry.rows as usize, geometry.cols as usize),
+            Rgb([0, 0, 0]),
+        );
+        self.dimensions = (width, height);
+        self.geometry = geometry;
+        Ok(())
+    }
+
+    pub fn convert_frame(
+        &mut self,
+        frame: &DynamicImage,
+    ) -> Result<&ImageBuffer<Rgb<u8>, Vec<u8>>, ConvertError> {
+        /*
+         * Convert the next frame. The returned buffer is overwritten by the following call
+         */
+        let found = frame.dimensions();
+        if found != self.dimensions {
+            match self.size_change {
+                FrameSizeChange::Error => {
+                    return Err(ConvertError::FrameSizeMismatch {
+                        expected: self.dimensions,
+                        found,
+                    })
+                }
+                FrameSizeChange::Reallocate => self.allocate(found.0, found.1)?,
+            }
+        }
+
+        if self.converter.reuses_frame_buffers() {
+            self.converter.convert_frame_into(
+                frame,
+                self.sharpen_thres,
+                &self.background,
+                &mut self.colors,
+                &mut self.output,
+            )?;
+        } else {
+            self.output = self.converter.convert_image(frame, self.sharpen_thres)?;
+        }
+        Ok(&self.output)
+    }
+}
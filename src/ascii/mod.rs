@@ -1,4 +1,26 @@
+pub mod auto;
+pub mod background;
+pub mod cancel;
+pub mod cell;
 pub mod char_set;
+pub mod config;
 pub mod converter;
+pub mod detail;
+pub mod diff;
+pub mod edge_color;
 pub mod error;
 pub mod font_loader;
+pub mod frames;
+pub mod options;
+pub mod post_effect;
+pub mod preset;
+pub mod provenance;
+pub mod sequence;
+pub mod session;
+pub mod source_map;
+pub mod stats;
+pub mod target;
+pub mod tone_curve;
+pub mod warning;
+pub mod watermark;
+pub mod weight_map;
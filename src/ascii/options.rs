@@ -0,0 +1,86 @@
+use super::background::BackgroundMode;
+use super::edge_color::EdgeColorMode;
+use image::Rgb;
+
+/*
+* Per call overrides of the cheap settings of a Converter, those that only change how the grid is
+* colored and drawn. Fonts and pipelines stay on the shared converter. Unset fields keep the
+* converter's value
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ConvertOptions {
+    pub bg_color: Option<Rgb<u8>>,
+    pub color: Option<Rgb<u8>>,
+    pub use_image_color: Option<bool>,
+    pub draw_edges: Option<bool>,
+    pub background: Option<BackgroundMode>,
+    pub adaptive_glyph_contrast: Option<bool>,
+    pub edge_color: Option<EdgeColorMode>,
+}
+
+impl ConvertOptions {
+    pub fn new() -> Self {
+        ConvertOptions::default()
+    }
+
+    pub fn with_bg_color(mut self, bg_color: Rgb<u8>) -> Self {
+        self.bg_color = Some(bg_color);
+        self
+    }
+
+    pub fn with_color(mut self, color: Rgb<u8>) -> Self {
+        self.color = Some(color);
+        self
+    }
+
+    pub fn with_use_image_color(mut self, use_image_color: bool) -> Self {
+        self.use_image_color = Some(use_image_color);
+        self
+    }
+
+    pub fn with_edges(mut self, draw_edges: bool) -> Self {
+        self.draw_edges = Some(draw_edges);
+        self
+    }
+
+    pub fn with_background(mut self, background: BackgroundMode) -> Self {
+        self.background = Some(background);
+        self
+    }
+
+    pub fn with_adaptive_glyph_contrast(mut self, adaptive_glyph_contrast: bool) -> Self {
+        self.adaptive_glyph_contrast = Some(adaptive_glyph_contrast);
+        self
+    }
+
+    pub fn with_edge_color(mut self, edge_color: EdgeColorMode) -> Self {
+        self.edge_color = Some(edge_color);
+        self
+    }
+
+    pub(crate) fn apply(&self, base: Appearance) -> Appearance {
+        Appearance {
+            bg_color: self.bg_color.unwrap_or(base.bg_color),
+            color: self.color.unwrap_or(base.color),
+            use_image_color: self.use_image_color.unwrap_or(base.use_image_color),
+            draw_edges: self.draw_edges.unwrap_or(base.draw_edges),
+            background: self.background.unwrap_or(base.background),
+            adaptive_glyph_contrast: self
+                .adaptive_glyph_contrast
+                .unwrap_or(base.adaptive_glyph_contrast),
+            edge_color: self.edge_color.unwrap_or(base.edge_color),
+        }
+    }
+}
+
+// The settings ConvertOptions can override, resolved for one conversion
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct Appearance {
+    pub bg_color: Rgb<u8>,
+    pub color: Rgb<u8>,
+    pub use_image_color: bool,
+    pub draw_edges: bool,
+    pub background: BackgroundMode,
+    pub adaptive_glyph_contrast: bool,
+    pub edge_color: EdgeColorMode,
+}
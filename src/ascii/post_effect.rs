@@ -0,0 +1,81 @@
+use super::error::ConvertError;
+use image::RgbImage;
+
+/*
+* Stylization applied to a finished render, after the watermark. Effects run in the order they are
+* listed, each on the output of the one before.
+* Scanlines multiplies every period-th row, the last row of each period, by factor, so a period of
+* 2 darkens every odd row.
+* ChromaticAberration moves the red channel shift pixels to the right and the blue one shift
+* pixels to the left, repeating the border pixels where the channels move away from the edge.
+* Vignette darkens towards the corners, by strength times the squared distance from the center
+* relative to the distance of the image corners, which keep 1 - strength of their brightness
+*/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PostEffect {
+    Scanlines { period: u32, factor: f32 },
+    ChromaticAberration { shift: u32 },
+    Vignette { strength: f32 },
+}
+
+impl PostEffect {
+    pub fn validate(&self) -> Result<(), ConvertError> {
+        match *self {
+            PostEffect::Scanlines { period: 0, .. } => Err(ConvertError::InvalidSetting {
+                field: "scanlines.period",
+                reason: "must be at least 1",
+            }),
+            PostEffect::Scanlines { factor, .. } if !(0.0..=1.0).contains(&factor) => {
+                Err(ConvertError::InvalidSetting {
+                    field: "scanlines.factor",
+                    reason: "must be between 0 and 1",
+                })
+            }
+            PostEffect::Vignette { strength } if !(0.0..=1.0).contains(&strength) => {
+                Err(ConvertError::InvalidSetting {
+                    field: "vignette.strength",
+                    reason: "must be between 0 and 1",
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn apply(&self, bufr: &mut RgbImage) {
+        match *self {
+            PostEffect::Scanlines { period, factor } => {
+                for (y, row) in bufr.enumerate_rows_mut() {
+                    if y % period != period - 1 {
+                        continue;
+                    }
+                    for (_, _, pixel) in row {
+                        for c in pixel.0.iter_mut() {
+                            *c = (*c as f32 * factor).round() as u8;
+                        }
+                    }
+                }
+            }
+            PostEffect::ChromaticAberration { shift } => {
+                let source = bufr.clone();
+                let last = bufr.width().saturating_sub(1);
+                for (x, y, pixel) in bufr.enumerate_pixels_mut() {
+                    pixel[0] = source.get_pixel(x.saturating_sub(shift), y)[0];
+                    pixel[2] = source.get_pixel(x.saturating_add(shift).min(last), y)[2];
+                }
+            }
+            PostEffect::Vignette { strength } => {
+                let (w, h) = bufr.dimensions();
+                let (cx, cy) = (w as f32 / 2.0, h as f32 / 2.0);
+                let corner = (cx * cx + cy * cy).max(f32::EPSILON);
+                for (x, y, pixel) in bufr.enumerate_pixels_mut() {
+                    // Distances to the pixel centers, so the four corners darken alike
+                    let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+                    let keep = 1.0 - strength * (dx * dx + dy * dy) / corner;
+                    for c in pixel.0.iter_mut() {
+                        *c = (*c as f32 * keep).round() as u8;
+                    }
+                }
+            }
+        }
+    }
+}
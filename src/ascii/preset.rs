@@ -0,0 +1,133 @@
+use super::config::{
+    ConverterConfig, DetailModeConfig, EdgeDetectorConfig, EdgeSmoothingConfig, ProcessorConfig,
+    ResizeFilterConfig, TileSamplingConfig,
+};
+use crate::image_manip::processing::clipped_range;
+use image::DynamicImage;
+
+// Share of pixels close to the paper level above which an image counts as line art
+pub const LINE_ART_PAPER_SHARE: f32 = 0.9;
+// Share of ink pixels, well below the paper level, a line art image needs at least
+const MIN_INK_SHARE: f32 = 0.002;
+// Distance from the paper level still counted as paper
+const PAPER_TOLERANCE: u8 = 48;
+// Distance below the paper level from which a pixel counts as ink
+const INK_DEPTH: u8 = 96;
+// Darkest paper level, anything dimmer is a dark image rather than paper
+const MIN_PAPER_LEVEL: u8 = 150;
+// Sobel magnitude of a hard step of 48 luminance levels, the weakest pixel art edge drawn
+const PIXEL_ART_MIN_MAGNITUDE: f32 = 4.0 * 48.0;
+
+/*
+* Ready made settings for a kind of input. LineArt is for scanned sketches and whiteboard photos:
+* black edge characters on white, no tile shading, and an edge pipeline of adaptive threshold and
+* thinning that copes with uneven lighting. PixelArt is for sprites and other images drawn pixel by
+* pixel: one cell per pixel sampled with Nearest, nothing that smooths, and when pixel cells are
+* turned off, edges only where Sobel finds a hard step
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Preset {
+    #[default]
+    Default,
+    LineArt,
+    PixelArt,
+}
+
+impl Preset {
+    pub fn config(&self) -> ConverterConfig {
+        match self {
+            Preset::Default => ConverterConfig::default(),
+            Preset::LineArt => Preset::line_art(ConverterConfig::default()),
+            Preset::PixelArt => Preset::pixel_art(ConverterConfig::default()),
+        }
+    }
+
+    pub fn apply(&self, base: ConverterConfig) -> ConverterConfig {
+        /*
+         * The settings of the preset over base, keeping the base font and pixel budget
+         */
+        match self {
+            Preset::Default => base,
+            Preset::LineArt => Preset::line_art(base),
+            Preset::PixelArt => Preset::pixel_art(base),
+        }
+    }
+
+    fn line_art(base: ConverterConfig) -> ConverterConfig {
+        ConverterConfig {
+            // A single space ramp leaves every cell that is not an edge blank
+            tile_chars: " ".to_string(),
+            tile_levels: None,
+            tile_jitter: 0.0,
+            weight_map: None,
+            edge_preprocessors: vec![
+                ProcessorConfig::AdaptiveThreshold {
+                    block_radius: 7,
+                    offset: 12,
+                },
+                ProcessorConfig::Thin,
+            ],
+            edge_f32_stages: vec![],
+            draw_edges: true,
+            bg_color: [255, 255, 255],
+            use_image_color: false,
+            color: [0, 0, 0],
+            adaptive_glyph_contrast: false,
+            ..base
+        }
+    }
+
+    fn pixel_art(base: ConverterConfig) -> ConverterConfig {
+        ConverterConfig {
+            resize_filter: ResizeFilterConfig::Nearest,
+            tile_sampling: TileSamplingConfig::Resize,
+            linear_resize: false,
+            pixel_cells: true,
+            tile_jitter: 0.0,
+            color_preprocessors: vec![],
+            tile_preprocessors: vec![],
+            edge_preprocessors: vec![],
+            edge_f32_stages: vec![],
+            edge_detector: EdgeDetectorConfig::Sobel {
+                min_magnitude: PIXEL_ART_MIN_MAGNITUDE,
+            },
+            edge_flow: None,
+            edge_smoothing: EdgeSmoothingConfig {
+                radius: 0,
+                ..base.edge_smoothing
+            },
+            // The fine pass would resample the pixels the cells are made of
+            detail_mode: DetailModeConfig::Single,
+            ..base
+        }
+    }
+
+    pub fn detect(img: &DynamicImage) -> Preset {
+        /*
+         * LineArt for images that are nearly all paper with a little dark ink: over
+         * LINE_ART_PAPER_SHARE of the pixels within PAPER_TOLERANCE of the paper level, the
+         * brightest percentile, and some pixels far below it. Default for anything else
+         */
+        let gray = img.to_luma8();
+        let (_, paper) = clipped_range(&gray, 0.01);
+        if paper < MIN_PAPER_LEVEL || gray.is_empty() {
+            return Preset::Default;
+        }
+        let paper_floor = paper.saturating_sub(PAPER_TOLERANCE);
+        let ink_ceiling = paper.saturating_sub(INK_DEPTH);
+        let (mut on_paper, mut ink) = (0, 0);
+        for &v in gray.iter() {
+            if v >= paper_floor {
+                on_paper += 1;
+            } else if v <= ink_ceiling {
+                ink += 1;
+            }
+        }
+        let total = gray.len() as f32;
+        if on_paper as f32 / total > LINE_ART_PAPER_SHARE && ink as f32 / total >= MIN_INK_SHARE {
+            Preset::LineArt
+        } else {
+            Preset::Default
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use super::config::ConverterConfig;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/*
+* Where the value of a setting came from, in the order the command line resolves them: the
+* defaults, a config file, a preset over it, flags over that, and the choices of auto tuning
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Origin {
+    #[default]
+    Default,
+    ConfigFile,
+    Preset,
+    User,
+    Auto,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Origin::Default => "default",
+            Origin::ConfigFile => "config file",
+            Origin::Preset => "preset",
+            Origin::User => "user",
+            Origin::Auto => "auto",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+pub fn changed_fields(old: &ConverterConfig, new: &ConverterConfig) -> Vec<&'static str> {
+    /*
+     * Names of the fields of the config that differ between old and new. Listing every field of
+     * the config makes a new field fail to compile here until it is listed
+     */
+    macro_rules! changed {
+        ($($field:ident),* $(,)?) => {{
+            let ConverterConfig { $($field),* } = new;
+            let fields: Vec<Option<&'static str>> =
+                vec![$((*$field != old.$field).then_some(stringify!($field))),*];
+            fields.into_iter().flatten().collect()
+        }};
+    }
+    changed! {
+        font_size, render_cell_px, font_path, tile_chars, tile_levels, tile_mapping, bucket_mode,
+        tile_sampling, weight_map, tone_curve, linear_resize, resize_filter, pixel_cells,
+        orientation, tile_jitter, seed, color_preprocessors, tile_preprocessors,
+        edge_preprocessors, band_rows, edge_f32_stages, edge_f32_normalization, edge_detector,
+        edge_source, edge_flow, edge_smoothing, edge_halo_bias, edge_threshold, draw_edges,
+        small_image_fallback, max_input_pixels, auto_downscale_large, tolerant_decode,
+        color_management, strict, score_fidelity, detail_mode, bg_color, background,
+        adaptive_glyph_contrast, fit_glyphs, use_image_color, color, edge_color, watermark,
+        output_quantize, post_effects, trim, embed_metadata, include_source_map,
+    }
+}
+
+/*
+* Origin of every setting of a converter by its ConverterConfig field name, for Converter::describe.
+* Fields never recorded are defaults. A config resolved in steps is recorded step by step, every
+* field a step changes taking the origin of that step
+*/
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Provenance {
+    origins: BTreeMap<&'static str, Origin>,
+}
+
+impl Provenance {
+    pub fn new() -> Self {
+        Provenance::default()
+    }
+
+    pub fn origin(&self, field: &str) -> Origin {
+        self.origins.get(field).copied().unwrap_or_default()
+    }
+
+    pub fn set(&mut self, field: &'static str, origin: Origin) {
+        self.origins.insert(field, origin);
+    }
+
+    pub fn record(&mut self, before: &ConverterConfig, after: &ConverterConfig, origin: Origin) {
+        // Give origin to every field a resolution step changed from before to after
+        for field in changed_fields(before, after) {
+            self.set(field, origin);
+        }
+    }
+
+    pub fn overrides(&self) -> impl Iterator<Item = (&'static str, Origin)> + '_ {
+        // Fields that are not defaults, in name order
+        self.origins
+            .iter()
+            .map(|(&field, &origin)| (field, origin))
+            .filter(|&(_, origin)| origin != Origin::Default)
+    }
+}
+
+/*
+* Text of Converter::describe: sections at the left margin, their settings indented under them
+* and the stages of a pipeline under the setting. Settings read from a config field are marked
+* with its origin when there is a provenance
+*/
+pub(crate) struct SettingsTree<'a> {
+    provenance: Option<&'a Provenance>,
+    text: String,
+}
+
+impl<'a> SettingsTree<'a> {
+    pub(crate) fn new(provenance: Option<&'a Provenance>) -> Self {
+        SettingsTree {
+            provenance,
+            text: String::new(),
+        }
+    }
+
+    pub(crate) fn section(&mut self, name: &str) {
+        self.text.push_str(name);
+        self.text.push('\n');
+    }
+
+    pub(crate) fn setting(&mut self, label: &str, value: impl fmt::Display, field: &str) {
+        let mark = match self.provenance {
+            Some(provenance) => format!(" ({})", provenance.origin(field)),
+            None => String::new(),
+        };
+        self.text
+            .push_str(&format!("  {}: {}{}\n", label, value, mark));
+    }
+
+    pub(crate) fn derived(&mut self, label: &str, value: impl fmt::Display) {
+        // A value computed from settings rather than read from one, never marked
+        self.text.push_str(&format!("  {}: {}\n", label, value));
+    }
+
+    pub(crate) fn stages(&mut self, label: &str, stages: &[(&'static str, String)], field: &str) {
+        // A pipeline and, one per line under it, its stages with their parameters
+        let count = match stages.len() {
+            0 => "none".to_string(),
+            1 => "1 stage".to_string(),
+            n => format!("{} stages", n),
+        };
+        self.setting(label, count, field);
+        for (name, params) in stages {
+            let line = format!("    - {} {}", name, params);
+            self.text.push_str(line.trim_end());
+            self.text.push('\n');
+        }
+    }
+
+    pub(crate) fn into_text(self) -> String {
+        self.text
+    }
+}
@@ -0,0 +1,257 @@
+use super::converter::Converter;
+use super::error::ConvertError;
+use super::frames::{FrameConverter, FrameSizeChange};
+use super::warning::ConvertWarning;
+use crate::output::animation::AnimationWriter;
+use image::GenericImageView;
+use std::cmp::Ordering;
+use std::fs;
+use std::iter::Peekable;
+use std::path::{Path, PathBuf};
+use std::str::Chars;
+
+/*
+* GIF assembled from the converted frames of a sequence, next to the per-frame outputs
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SequenceAnimation {
+    pub path: String,
+    pub fps: u32,
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SequenceOptions {
+    pub sharpen_thres: f32,
+    // Also write the frames as one animation, None for the per-frame outputs only
+    pub animation: Option<SequenceAnimation>,
+}
+
+impl SequenceOptions {
+    pub fn new(sharpen_thres: f32) -> Self {
+        SequenceOptions {
+            sharpen_thres,
+            animation: None,
+        }
+    }
+
+    pub fn with_animation(mut self, animation: Option<SequenceAnimation>) -> Self {
+        self.animation = animation;
+        self
+    }
+}
+
+/*
+* One converted frame. number is the last run of digits in the input's file stem, None for stems
+* without digits
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SequenceFrame {
+    pub input: PathBuf,
+    pub number: Option<u64>,
+    pub output: PathBuf,
+}
+
+/*
+* What convert_sequence did, the frames in the order they were converted
+*/
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SequenceReport {
+    pub frames: Vec<SequenceFrame>,
+    pub warnings: Vec<ConvertWarning>,
+}
+
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    /*
+     * Order of file names as people count them: runs of digits compare by their value, so
+     * frame_9 comes before frame_10, and everything else character by character. Equal values
+     * written with more leading zeros come last, so different names never compare equal
+     */
+    let (mut a, mut b) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (x, y) = (digit_run(&mut a), digit_run(&mut b));
+                let (x_value, y_value) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+                let order = x_value
+                    .len()
+                    .cmp(&y_value.len())
+                    .then_with(|| x_value.cmp(y_value))
+                    .then_with(|| x.len().cmp(&y.len()));
+                if order != Ordering::Equal {
+                    return order;
+                }
+            }
+            (Some(x), Some(y)) => {
+                if x != y {
+                    return x.cmp(&y);
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+fn digit_run(chars: &mut Peekable<Chars>) -> String {
+    let mut run = String::new();
+    while let Some(ch) = chars.next_if(|ch| ch.is_ascii_digit()) {
+        run.push(ch);
+    }
+    run
+}
+
+fn frame_digits(stem: &str) -> Option<&str> {
+    // The last run of digits in stem, as written
+    let end = stem.rfind(|ch: char| ch.is_ascii_digit())? + 1;
+    let start = stem[..end]
+        .rfind(|ch: char| !ch.is_ascii_digit())
+        .map_or(0, |i| i + 1);
+    Some(&stem[start..end])
+}
+
+fn glob_match(pattern: &[char], name: &[char]) -> bool {
+    // * matches any run of characters and ? any single one
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some(('*', rest)) => (0..=name.len()).any(|skip| glob_match(rest, &name[skip..])),
+        Some((&ch, rest)) => match name.split_first() {
+            Some((&first, name_rest)) => (ch == '?' || ch == first) && glob_match(rest, name_rest),
+            None => false,
+        },
+    }
+}
+
+pub fn expand_glob(pattern: &str) -> Result<Vec<PathBuf>, ConvertError> {
+    /*
+     * Files matching pattern, in natural order of their names. Only the file name may hold
+     * wildcards, * for any run of characters and ? for any single character
+     */
+    let path = Path::new(pattern);
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    if dir.to_string_lossy().contains(['*', '?']) {
+        return Err(ConvertError::InvalidSetting {
+            field: "pattern",
+            reason: "wildcards are only supported in the file name",
+        });
+    }
+    let name_pattern: Vec<char> = path
+        .file_name()
+        .map(|name| name.to_string_lossy().chars().collect())
+        .unwrap_or_default();
+
+    let mut matches: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        .filter(|path| {
+            let name: Vec<char> = path
+                .file_name()
+                .map(|name| name.to_string_lossy().chars().collect())
+                .unwrap_or_default();
+            glob_match(&name_pattern, &name)
+        })
+        .map(|path| match path.strip_prefix(".") {
+            // Keep paths relative the way the pattern was written
+            Ok(stripped) if dir == Path::new(".") => stripped.to_path_buf(),
+            _ => path,
+        })
+        .collect();
+    if matches.is_empty() {
+        return Err(ConvertError::NoMatches(pattern.to_string()));
+    }
+    matches.sort_by(|a, b| {
+        natural_cmp(
+            &a.file_name().unwrap_or_default().to_string_lossy(),
+            &b.file_name().unwrap_or_default().to_string_lossy(),
+        )
+    });
+    Ok(matches)
+}
+
+fn output_name(out_template: &str, stem: &str) -> String {
+    // {frame} becomes the frame number as the input writes it, or the whole stem without one
+    let frame = frame_digits(stem).unwrap_or(stem);
+    out_template
+        .replace("{frame}", frame)
+        .replace("{stem}", stem)
+}
+
+pub fn convert_sequence(
+    converter: Converter,
+    pattern: &str,
+    out_dir: impl AsRef<Path>,
+    out_template: &str,
+    options: &SequenceOptions,
+) -> Result<SequenceReport, ConvertError> {
+    /*
+     * Convert every file matching pattern, in natural order, into out_dir. Outputs are named by
+     * out_template, whose {frame} keeps the frame number of the input, zero padding included, and
+     * whose {stem} is the input's file stem. The frames go through one FrameConverter, which
+     * takes on the size of a frame that differs from the one before. Missing frame numbers are
+     * reported as SequenceGap warnings, or fail the conversion in strict mode
+     */
+    if !out_template.contains("{frame}") && !out_template.contains("{stem}") {
+        return Err(ConvertError::InvalidSetting {
+            field: "out_template",
+            reason: "must contain {frame} or {stem} so every frame gets its own output",
+        });
+    }
+    let inputs = expand_glob(pattern)?;
+    let out_dir = out_dir.as_ref();
+    fs::create_dir_all(out_dir)?;
+
+    let mut report = SequenceReport::default();
+    let mut previous: Option<u64> = None;
+    for input in inputs.iter() {
+        let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+        let number = frame_digits(&stem).and_then(|digits| digits.parse::<u64>().ok());
+        if let (Some(after), Some(before)) = (previous, number) {
+            if before > after + 1 {
+                let warning = ConvertWarning::SequenceGap { after, before };
+                if converter.is_strict() {
+                    return Err(ConvertError::StrictViolation(warning));
+                }
+                report.warnings.push(warning);
+            }
+        }
+        previous = number.or(previous);
+        report.frames.push(SequenceFrame {
+            input: input.clone(),
+            number,
+            output: out_dir.join(output_name(out_template, &stem)),
+        });
+    }
+
+    let mut animation = options
+        .animation
+        .as_ref()
+        .map(|animation| AnimationWriter::gif(&animation.path, animation.fps));
+    // The pattern matched at least one file, and the first frame sets the size
+    let mut first = Some(converter.read_image(&report.frames[0].input)?);
+    let (w, h) = first.as_ref().map_or((0, 0), |img| img.dimensions());
+    let mut frames = FrameConverter::new(converter, w, h, options.sharpen_thres)?
+        .with_size_change(FrameSizeChange::Reallocate);
+    for frame in report.frames.iter_mut() {
+        let img = match first.take() {
+            Some(img) => img,
+            None => frames.converter().read_image(&frame.input)?,
+        };
+        let ascii_img = frames.convert_frame(&img)?.clone();
+        frame.output =
+            frames
+                .converter()
+                .write_render(&ascii_img, &frame.output, options.sharpen_thres)?;
+        if let Some(animation) = &mut animation {
+            animation.push_frame(ascii_img)?;
+        }
+    }
+    if let Some(animation) = animation {
+        animation.finish()?;
+    }
+    Ok(report)
+}
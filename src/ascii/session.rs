@@ -0,0 +1,336 @@
+use super::cell::CellValue;
+use super::config::ConverterConfig;
+use super::converter::{Converter, EdgeMap, PreparedImage};
+use super::error::ConvertError;
+use image::{DynamicImage, ImageBuffer, Rgb};
+use ndarray::Array2;
+
+/*
+* Stages of a conversion whose products a ConversionSession keeps, in pipeline order. Every stage
+* only reads the products of its inputs, which come before it
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SessionStage {
+    // Pixel budget, orientation, color preprocessors and pixel cells
+    Preprocess,
+    // Image brought down to a pixel per cell for the colors and the tile luminance
+    Prepare,
+    // Tile index of every cell
+    Tiles,
+    // Edge detection at the full resolution
+    EdgeMap,
+    // Edge map brought down to the grid
+    EdgeGrid,
+    // Tiles and edges combined into the cells of the grid
+    Cells,
+    // Color of every cell
+    Colors,
+    // Drawn image
+    Render,
+}
+
+pub const SESSION_STAGES: [SessionStage; 8] = [
+    SessionStage::Preprocess,
+    SessionStage::Prepare,
+    SessionStage::Tiles,
+    SessionStage::EdgeMap,
+    SessionStage::EdgeGrid,
+    SessionStage::Cells,
+    SessionStage::Colors,
+    SessionStage::Render,
+];
+
+impl SessionStage {
+    pub fn inputs(self) -> &'static [SessionStage] {
+        match self {
+            SessionStage::Preprocess => &[],
+            SessionStage::Prepare => &[SessionStage::Preprocess],
+            SessionStage::Tiles => &[SessionStage::Prepare],
+            SessionStage::EdgeMap => &[SessionStage::Preprocess],
+            // The size of the grid comes from the prepared image
+            SessionStage::EdgeGrid => &[SessionStage::Prepare, SessionStage::EdgeMap],
+            SessionStage::Cells => &[SessionStage::Tiles, SessionStage::EdgeGrid],
+            SessionStage::Colors => &[
+                SessionStage::Prepare,
+                SessionStage::EdgeGrid,
+                SessionStage::Cells,
+            ],
+            // BlurredImage backgrounds and the TwoScale fine pass read the preprocessed image
+            SessionStage::Render => &[
+                SessionStage::Preprocess,
+                SessionStage::Cells,
+                SessionStage::Colors,
+            ],
+        }
+    }
+}
+
+fn first_stages(old: &ConverterConfig, new: &ConverterConfig) -> Vec<SessionStage> {
+    /*
+     * The earliest stage every changed field of the config feeds into, None for fields no stage
+     * reads. Listing every field of the config makes a new field fail to compile here until it
+     * is given a stage
+     */
+    use SessionStage::*;
+    macro_rules! field_stages {
+        ($($field:ident => $stage:expr),* $(,)?) => {{
+            let ConverterConfig { $($field),* } = new;
+            let stages: Vec<Option<SessionStage>> =
+                vec![$(if *$field != old.$field { $stage } else { None }),*];
+            stages.into_iter().flatten().collect()
+        }};
+    }
+    field_stages! {
+        // Pixel cells blow every pixel up to a font size block
+        font_size => Some(Preprocess),
+        max_input_pixels => Some(Preprocess),
+        auto_downscale_large => Some(Preprocess),
+        // A downscale is an error in strict mode
+        strict => Some(Preprocess),
+        resize_filter => Some(Preprocess),
+        orientation => Some(Preprocess),
+        color_preprocessors => Some(Preprocess),
+        pixel_cells => Some(Preprocess),
+        linear_resize => Some(Prepare),
+        small_image_fallback => Some(Prepare),
+        tile_preprocessors => Some(Prepare),
+        // Bands give the same result as the whole image
+        band_rows => None,
+        tile_sampling => Some(Prepare),
+        // Variance aware mapping measures the variance while preparing
+        tile_mapping => Some(Prepare),
+        bucket_mode => Some(Prepare),
+        tile_chars => Some(Tiles),
+        tile_levels => Some(Tiles),
+        tone_curve => Some(Tiles),
+        tile_jitter => Some(Tiles),
+        seed => Some(Tiles),
+        weight_map => Some(Tiles),
+        edge_preprocessors => Some(EdgeMap),
+        edge_f32_stages => Some(EdgeMap),
+        edge_f32_normalization => Some(EdgeMap),
+        edge_detector => Some(EdgeMap),
+        edge_source => Some(EdgeMap),
+        edge_flow => Some(EdgeMap),
+        draw_edges => Some(EdgeMap),
+        edge_threshold => Some(EdgeGrid),
+        edge_smoothing => Some(EdgeGrid),
+        edge_halo_bias => Some(Cells),
+        // Only a change of how the edge strength is measured reaches back to the edge map
+        edge_color => if edge_color.build().magnitude_reduce()
+            != old.edge_color.build().magnitude_reduce()
+        {
+            Some(EdgeMap)
+        } else {
+            Some(Colors)
+        },
+        use_image_color => Some(Colors),
+        color => Some(Colors),
+        // Cells without an opaque pixel under them take the background color
+        bg_color => Some(Colors),
+        background => Some(Render),
+        adaptive_glyph_contrast => Some(Render),
+        fit_glyphs => Some(Render),
+        render_cell_px => Some(Render),
+        font_path => Some(Render),
+        detail_mode => Some(Render),
+        watermark => Some(Render),
+        post_effects => Some(Render),
+        tolerant_decode => None,
+        color_management => None,
+        score_fidelity => None,
+        trim => None,
+        embed_metadata => None,
+        output_quantize => None,
+        include_source_map => None,
+    }
+}
+
+pub fn invalidated_stages(old: &ConverterConfig, new: &ConverterConfig) -> Vec<SessionStage> {
+    /*
+     * Stages to run again when the config changes from old to new: the stages the changed fields
+     * feed into and every stage reading their products, in pipeline order
+     */
+    let first = first_stages(old, new);
+    let mut stages: Vec<SessionStage> = vec![];
+    for stage in SESSION_STAGES {
+        if first.contains(&stage) || stage.inputs().iter().any(|input| stages.contains(input)) {
+            stages.push(stage);
+        }
+    }
+    stages
+}
+
+fn cached<'a, T>(
+    slot: &'a mut Option<T>,
+    runs: &mut usize,
+    compute: impl FnOnce() -> Result<T, ConvertError>,
+) -> Result<&'a T, ConvertError> {
+    // The product in slot, computed and counted as a run when missing
+    let value = match slot.take() {
+        Some(value) => value,
+        None => {
+            *runs += 1;
+            compute()?
+        }
+    };
+    Ok(slot.insert(value))
+}
+
+/*
+* One image converted again and again under changing settings, as when tuning them. The products
+* of every stage are kept, and a change of the config only runs again the stages it reaches, so
+* changing a color does not decode, preprocess or detect edges again. Renders come out identical to
+* Converter::convert_image with the same config
+*/
+pub struct ConversionSession {
+    config: ConverterConfig,
+    converter: Converter,
+    image: DynamicImage,
+    preprocessed: Option<DynamicImage>,
+    prepared: Option<PreparedImage>,
+    tiles: Option<Array2<usize>>,
+    // None inside when no edges are drawn
+    edge_map: Option<Option<EdgeMap>>,
+    edges: Option<(Array2<u8>, Option<Array2<f32>>)>,
+    cells: Option<Array2<CellValue>>,
+    colors: Option<Array2<Rgb<u8>>>,
+    render: Option<ImageBuffer<Rgb<u8>, Vec<u8>>>,
+    // How many times every stage ran, indexed by SessionStage
+    runs: [usize; SESSION_STAGES.len()],
+}
+
+impl ConversionSession {
+    pub fn new(config: ConverterConfig, image: DynamicImage) -> Result<Self, ConvertError> {
+        /*
+         * Session converting the decoded image under config. Nothing runs before the first render
+         */
+        let converter = config.build()?;
+        converter.load_font()?;
+        Ok(ConversionSession {
+            config,
+            converter,
+            image,
+            preprocessed: None,
+            prepared: None,
+            tiles: None,
+            edge_map: None,
+            edges: None,
+            cells: None,
+            colors: None,
+            render: None,
+            runs: [0; SESSION_STAGES.len()],
+        })
+    }
+
+    pub fn config(&self) -> &ConverterConfig {
+        &self.config
+    }
+
+    pub fn converter(&self) -> &Converter {
+        &self.converter
+    }
+
+    pub fn stage_runs(&self, stage: SessionStage) -> usize {
+        self.runs[stage as usize]
+    }
+
+    pub fn update(
+        &mut self,
+        change: impl FnOnce(&mut ConverterConfig),
+    ) -> Result<&ImageBuffer<Rgb<u8>, Vec<u8>>, ConvertError> {
+        /*
+         * Change the config and render again, running only the stages the change invalidates. A
+         * change the config fails to build from is an error that leaves the session as it was
+         */
+        let mut config = self.config.clone();
+        change(&mut config);
+        let converter = config.build()?;
+        converter.load_font()?;
+        for stage in invalidated_stages(&self.config, &config) {
+            self.invalidate(stage);
+        }
+        self.config = config;
+        self.converter = converter;
+        self.render()
+    }
+
+    fn invalidate(&mut self, stage: SessionStage) {
+        match stage {
+            SessionStage::Preprocess => self.preprocessed = None,
+            SessionStage::Prepare => self.prepared = None,
+            SessionStage::Tiles => self.tiles = None,
+            SessionStage::EdgeMap => self.edge_map = None,
+            SessionStage::EdgeGrid => self.edges = None,
+            SessionStage::Cells => self.cells = None,
+            SessionStage::Colors => self.colors = None,
+            SessionStage::Render => self.render = None,
+        }
+    }
+
+    pub fn render(&mut self) -> Result<&ImageBuffer<Rgb<u8>, Vec<u8>>, ConvertError> {
+        /*
+         * The render under the current config, running the stages whose products are missing
+         */
+        let converter = &self.converter;
+        let appearance = converter.appearance();
+        let sharpen_thres = self.config.edge_threshold;
+        let runs = &mut self.runs;
+
+        let ori_img = cached(
+            &mut self.preprocessed,
+            &mut runs[SessionStage::Preprocess as usize],
+            || Ok(converter.color_preprocess(&self.image)?.into_owned()),
+        )?;
+        let prepared = cached(
+            &mut self.prepared,
+            &mut runs[SessionStage::Prepare as usize],
+            || converter.prepare(ori_img),
+        )?;
+        let tiles = cached(
+            &mut self.tiles,
+            &mut runs[SessionStage::Tiles as usize],
+            || Ok(converter.quantize_tiles(prepared)),
+        )?;
+        let edge_map = cached(
+            &mut self.edge_map,
+            &mut runs[SessionStage::EdgeMap as usize],
+            || converter.grid_edge_map(ori_img, &appearance),
+        )?;
+        let (edges, edge_strength) = cached(
+            &mut self.edges,
+            &mut runs[SessionStage::EdgeGrid as usize],
+            || Ok(converter.grid_edges(edge_map.as_ref(), sharpen_thres, tiles.dim())),
+        )?;
+        let cells = cached(
+            &mut self.cells,
+            &mut runs[SessionStage::Cells as usize],
+            || converter.combine(&tiles.view(), &edges.view()),
+        )?;
+        let colors = cached(
+            &mut self.colors,
+            &mut runs[SessionStage::Colors as usize],
+            || {
+                Ok(converter.grid_colors_of(
+                    &prepared.resized,
+                    &cells.view(),
+                    edge_strength.as_ref(),
+                    &appearance,
+                ))
+            },
+        )?;
+        cached(
+            &mut self.render,
+            &mut runs[SessionStage::Render as usize],
+            || {
+                converter.render_cells(
+                    ori_img,
+                    &cells.view(),
+                    &colors.view(),
+                    sharpen_thres,
+                    &appearance,
+                )
+            },
+        )
+    }
+}
@@ -0,0 +1,52 @@
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/*
+* Pixel rectangle of the input a grid cell was sampled from
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SourceRect {
+    pub x: u32,
+    pub y: u32,
+    pub w: u32,
+    pub h: u32,
+}
+
+/*
+* Where every grid cell was sampled from in the input, as the pixel edges of the grid columns and
+* rows. The grid splits the input evenly, so cell (row, col) covers xs[col]..xs[col + 1] and
+* ys[row]..ys[row + 1], rounded down to whole pixels
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SourceMap {
+    pub xs: Vec<u32>,
+    pub ys: Vec<u32>,
+}
+
+fn edges(input: u32, cells: u32) -> Vec<u32> {
+    // cells + 1 edges from 0 to input, computed in u64 so large inputs do not overflow
+    (0..=cells as u64)
+        .map(|i| (i * input as u64 / cells.max(1) as u64) as u32)
+        .collect()
+}
+
+impl SourceMap {
+    pub fn new((input_w, input_h): (u32, u32), (cols, rows): (u32, u32)) -> Self {
+        SourceMap {
+            xs: edges(input_w, cols),
+            ys: edges(input_h, rows),
+        }
+    }
+
+    pub fn rect(&self, row: usize, col: usize) -> SourceRect {
+        // Panics outside the grid
+        SourceRect {
+            x: self.xs[col],
+            y: self.ys[row],
+            w: self.xs[col + 1] - self.xs[col],
+            h: self.ys[row + 1] - self.ys[row],
+        }
+    }
+}
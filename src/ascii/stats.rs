@@ -0,0 +1,144 @@
+use super::cell::CellValue;
+use super::char_set::CharacterSet;
+use super::warning::ConvertWarning;
+use crate::image_manip::fidelity::FidelityScore;
+use crate::output::trim::TrimOffsets;
+use ndarray::ArrayView2;
+use std::fmt;
+
+/*
+* Character usage of a converted grid, used to see how much of a charset ramp an image exploits
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub struct GridStats {
+    pub cells: usize,
+    // Count of every character in the grid, in the order the charset lists them
+    pub counts: Vec<(char, usize)>,
+    pub space_ratio: f32,
+    pub edge_ratio: f32,
+    // Darkest and brightest tile ramp index drawn, None when every cell is an edge
+    pub min_bucket: Option<usize>,
+    pub max_bucket: Option<usize>,
+    pub buckets: usize,
+    // Luminances where the tile buckets after the first start with percentile buckets, None for
+    // linear ones
+    pub bucket_boundaries: Option<Vec<u8>>,
+    // Seed of the tile jitter, None when the grid was not jittered
+    pub seed: Option<u64>,
+    // Size of the input when it was downscaled to fit the pixel budget
+    pub downscaled_from: Option<(u32, u32)>,
+    // What the conversion worked around, not part of the Display output
+    pub warnings: Vec<ConvertWarning>,
+    // How closely the render approximates the original when scoring is on, not part of the
+    // Display output
+    pub fidelity: Option<FidelityScore>,
+    // Rows and columns a bounding trim removed around a text output, to align the art with the
+    // untrimmed grid
+    pub trimmed: Option<TrimOffsets>,
+}
+
+impl GridStats {
+    pub fn new(cells: &ArrayView2<CellValue>, charset: &CharacterSet) -> Self {
+        let cell_count = cells.len();
+        let mut counts: Vec<(char, usize)> = vec![];
+        for &ch in charset.tile.iter().chain(charset.edge.iter()) {
+            if !counts.iter().any(|&(c, _)| c == ch) {
+                counts.push((ch, 0));
+            }
+        }
+
+        let mut spaces = 0;
+        let mut edges = 0;
+        let mut min_bucket: Option<usize> = None;
+        let mut max_bucket: Option<usize> = None;
+        for &cell in cells.iter() {
+            let ch = cell.to_char(charset);
+            match counts.iter_mut().find(|(c, _)| *c == ch) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((ch, 1)),
+            }
+            if ch == ' ' {
+                spaces += 1;
+            }
+            match cell {
+                CellValue::Edge(_) => edges += 1,
+                CellValue::Tile(bucket) => {
+                    min_bucket = Some(min_bucket.map_or(bucket, |b| b.min(bucket)));
+                    max_bucket = Some(max_bucket.map_or(bucket, |b| b.max(bucket)));
+                }
+            }
+        }
+
+        let ratio = |n: usize| {
+            if cell_count == 0 {
+                0.0
+            } else {
+                n as f32 / cell_count as f32
+            }
+        };
+        GridStats {
+            cells: cell_count,
+            counts,
+            space_ratio: ratio(spaces),
+            edge_ratio: ratio(edges),
+            min_bucket,
+            max_bucket,
+            buckets: charset.tile.len(),
+            bucket_boundaries: None,
+            seed: None,
+            downscaled_from: None,
+            warnings: vec![],
+            fidelity: None,
+            trimmed: None,
+        }
+    }
+}
+
+impl fmt::Display for GridStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "char  count  share")?;
+        for &(ch, count) in self.counts.iter() {
+            let share = if self.cells == 0 {
+                0.0
+            } else {
+                count as f32 * 100.0 / self.cells as f32
+            };
+            writeln!(
+                f,
+                "{:>4}  {:>5}  {:>5.1}%",
+                format!("'{}'", ch),
+                count,
+                share
+            )?;
+        }
+        writeln!(f, "cells: {}", self.cells)?;
+        writeln!(f, "spaces: {:.1}%", self.space_ratio * 100.0)?;
+        writeln!(f, "edges: {:.1}%", self.edge_ratio * 100.0)?;
+        match (self.min_bucket, self.max_bucket) {
+            (Some(min), Some(max)) => write!(
+                f,
+                "tile buckets used: {}..={} of {}",
+                min, max, self.buckets
+            )?,
+            _ => write!(f, "tile buckets used: none of {}", self.buckets)?,
+        }
+        if let Some(boundaries) = &self.bucket_boundaries {
+            let boundaries: Vec<String> = boundaries.iter().map(|b| b.to_string()).collect();
+            write!(f, "\nbucket boundaries: {}", boundaries.join(", "))?;
+        }
+        if let Some(seed) = self.seed {
+            write!(f, "\njitter seed: {}", seed)?;
+        }
+        if let Some((w, h)) = self.downscaled_from {
+            write!(f, "\ndownscaled from: {}x{}", w, h)?;
+        }
+        if let Some(o) = self.trimmed {
+            write!(
+                f,
+                "\ntrimmed: {} top, {} left, {} bottom, {} right",
+                o.top, o.left, o.bottom, o.right
+            )?;
+        }
+        Ok(())
+    }
+}
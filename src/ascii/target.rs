@@ -0,0 +1,106 @@
+use super::error::ConvertError;
+use crate::output::ansi::max_ansi_len;
+use crate::output::trim::TrimOffsets;
+use image::RgbImage;
+
+// Width over height of a terminal character cell, which is about twice as high as it is wide
+pub const TERMINAL_CELL_ASPECT: f32 = 0.5;
+
+/*
+* Output of Converter::convert_all_formats, with the width / height of the cells it is viewed in.
+* Png is drawn by the renderer, whose cells are square. Txt and Ansi are shown in terminals and text
+* editors, whose cells are usually TERMINAL_CELL_ASPECT, so their grids sample every cell from a
+* taller block of the input and get fewer rows to keep the proportions of the image
+*/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputTarget {
+    Png,
+    Txt { cell_aspect: f32 },
+    Ansi { cell_aspect: f32 },
+}
+
+impl OutputTarget {
+    pub fn txt() -> Self {
+        OutputTarget::Txt {
+            cell_aspect: TERMINAL_CELL_ASPECT,
+        }
+    }
+
+    pub fn ansi() -> Self {
+        OutputTarget::Ansi {
+            cell_aspect: TERMINAL_CELL_ASPECT,
+        }
+    }
+
+    pub fn cell_aspect(&self) -> f32 {
+        match *self {
+            OutputTarget::Png => 1.0,
+            OutputTarget::Txt { cell_aspect } | OutputTarget::Ansi { cell_aspect } => cell_aspect,
+        }
+    }
+
+    pub fn max_text_len(&self, rows: usize, cols: usize) -> Option<usize> {
+        /*
+         * Most characters the text of a rows x cols grid takes, newlines included, for the worst
+         * case of ANSI escapes where every cell changes color. None for Png, which is no text
+         */
+        match self {
+            OutputTarget::Png => None,
+            OutputTarget::Txt { .. } => Some(rows * (cols + 1)),
+            OutputTarget::Ansi { .. } => Some(max_ansi_len(rows, cols)),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ConvertError> {
+        let cell_aspect = self.cell_aspect();
+        if !cell_aspect.is_finite() || cell_aspect <= 0.0 {
+            return Err(ConvertError::InvalidSetting {
+                field: "cell_aspect",
+                reason: "must be a positive number",
+            });
+        }
+        Ok(())
+    }
+}
+
+pub(crate) fn tile_size(font_size: u32, cell_aspect: f32) -> (u32, u32) {
+    /*
+     * Width and height in input pixels of the block under one cell. The width stays the font
+     * size, so the aspect only changes the number of rows
+     */
+    let tile_h = (font_size as f32 / cell_aspect).round().max(1.0) as u32;
+    (font_size, tile_h)
+}
+
+/*
+* What convert_all_formats made for one target: the rendered image for Png, the text for Txt and
+* Ansi
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub enum TargetData {
+    Image(RgbImage),
+    Text(String),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TargetOutput {
+    pub target: OutputTarget,
+    pub cols: usize,
+    pub rows: usize,
+    pub data: TargetData,
+    // What a bounding trim removed around the text, cols and rows being the untrimmed grid
+    pub trimmed: Option<TrimOffsets>,
+}
+
+/*
+* Largest grid of a text target that fits a budget, see Converter::fit_text_output. Converting
+* with font_size as the font size gives a grid of cols x rows, whose text takes at most max_len
+* characters
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TextFit {
+    pub font_size: u32,
+    pub cols: u32,
+    pub rows: u32,
+    pub max_len: usize,
+}
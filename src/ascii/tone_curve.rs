@@ -0,0 +1,137 @@
+use super::error::ConvertError;
+
+/*
+* Remapping of the tile luminance before it is quantized to a ramp index, like the curves of an
+* image editor: raising a point lifts the tones around it, lowering it crushes them. The curve runs
+* through its control points by monotone cubic (Fritsch-Carlson) interpolation, so it never
+* overshoots or turns back between them, and is flat beyond the first and last point. It is
+* evaluated once into a lookup table of every luminance
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub struct ToneCurve {
+    points: Vec<(u8, u8)>,
+    lut: [u8; 256],
+}
+
+impl ToneCurve {
+    pub fn from_points(points: &[(u8, u8)]) -> Result<Self, ConvertError> {
+        /*
+         * Curve through points, which need at least two entries with increasing inputs and
+         * outputs that never decrease
+         */
+        if points.len() < 2 {
+            return Err(ConvertError::InvalidSetting {
+                field: "tone_curve",
+                reason: "needs at least two points",
+            });
+        }
+        for pair in points.windows(2) {
+            if pair[1].0 <= pair[0].0 {
+                return Err(ConvertError::InvalidSetting {
+                    field: "tone_curve",
+                    reason: "point inputs must increase",
+                });
+            }
+            if pair[1].1 < pair[0].1 {
+                return Err(ConvertError::InvalidSetting {
+                    field: "tone_curve",
+                    reason: "must be monotone, outputs can not decrease",
+                });
+            }
+        }
+        Ok(ToneCurve {
+            points: points.to_vec(),
+            lut: monotone_cubic_lut(points),
+        })
+    }
+
+    pub fn parse(spec: &str) -> Result<Self, ConvertError> {
+        /*
+         * Curve from comma separated input:output points, such as 0:0,64:90,255:255
+         */
+        let invalid = || ConvertError::InvalidSetting {
+            field: "tone_curve",
+            reason: "points are written as input:output, 0 to 255, separated by commas",
+        };
+        let points = spec
+            .split(',')
+            .map(|point| {
+                let (x, y) = point.trim().split_once(':').ok_or_else(invalid)?;
+                let x = x.trim().parse().map_err(|_| invalid())?;
+                let y = y.trim().parse().map_err(|_| invalid())?;
+                Ok((x, y))
+            })
+            .collect::<Result<Vec<_>, ConvertError>>()?;
+        ToneCurve::from_points(&points)
+    }
+
+    pub fn points(&self) -> &[(u8, u8)] {
+        &self.points
+    }
+
+    pub fn lut(&self) -> &[u8; 256] {
+        &self.lut
+    }
+
+    pub fn apply(&self, luma: u8) -> u8 {
+        self.lut[luma as usize]
+    }
+}
+
+fn monotone_cubic_lut(points: &[(u8, u8)]) -> [u8; 256] {
+    let xs: Vec<f32> = points.iter().map(|p| p.0 as f32).collect();
+    let ys: Vec<f32> = points.iter().map(|p| p.1 as f32).collect();
+    let n = points.len();
+
+    // Secant slopes, then tangents at the points limited so no segment overshoots
+    let secants: Vec<f32> = (0..n - 1)
+        .map(|k| (ys[k + 1] - ys[k]) / (xs[k + 1] - xs[k]))
+        .collect();
+    let mut tangents = vec![0.0; n];
+    tangents[0] = secants[0];
+    tangents[n - 1] = secants[n - 2];
+    for k in 1..n - 1 {
+        tangents[k] = if secants[k - 1] * secants[k] <= 0.0 {
+            0.0
+        } else {
+            (secants[k - 1] + secants[k]) / 2.0
+        };
+    }
+    for k in 0..n - 1 {
+        if secants[k] == 0.0 {
+            (tangents[k], tangents[k + 1]) = (0.0, 0.0);
+            continue;
+        }
+        let (a, b) = (tangents[k] / secants[k], tangents[k + 1] / secants[k]);
+        let norm = a.hypot(b);
+        if norm > 3.0 {
+            tangents[k] = 3.0 / norm * a * secants[k];
+            tangents[k + 1] = 3.0 / norm * b * secants[k];
+        }
+    }
+
+    let mut lut = [0u8; 256];
+    let mut segment = 0;
+    for (x, entry) in lut.iter_mut().enumerate() {
+        let x = x as f32;
+        let y = if x <= xs[0] {
+            ys[0]
+        } else if x >= xs[n - 1] {
+            ys[n - 1]
+        } else {
+            while x > xs[segment + 1] {
+                segment += 1;
+            }
+            // Cubic Hermite basis over the segment
+            let h = xs[segment + 1] - xs[segment];
+            let t = (x - xs[segment]) / h;
+            let (t2, t3) = (t * t, t * t * t);
+            (2.0 * t3 - 3.0 * t2 + 1.0) * ys[segment]
+                + (t3 - 2.0 * t2 + t) * h * tangents[segment]
+                + (-2.0 * t3 + 3.0 * t2) * ys[segment + 1]
+                + (t3 - t2) * h * tangents[segment + 1]
+        };
+        *entry = y.round().clamp(0.0, 255.0) as u8;
+    }
+    lut
+}
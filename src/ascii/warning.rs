@@ -0,0 +1,60 @@
+use std::fmt;
+
+/*
+* Something a conversion worked around instead of failing on, reported alongside its result
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub enum ConvertWarning {
+    // The font at requested could not be parsed, so the glyphs were drawn with the one at used
+    FallbackFont { requested: String, used: String },
+    // The input was over the pixel budget and shrunk to fit
+    Downscaled { from: (u32, u32), to: (u32, u32) },
+    // Characters of the grid the font has no glyph for, drawn as the font's missing glyph box
+    MissingGlyphs(Vec<char>),
+    // The input ended early or was corrupt, and the part that could not be decoded was filled in
+    PartialDecode { format: String },
+    // The input carries a color profile that is not sRGB and could not be converted from, so its
+    // colors were taken as sRGB or by its gamma alone
+    UnsupportedColorProfile,
+    // Frame numbers between after and before are missing from an image sequence
+    SequenceGap { after: u64, before: u64 },
+}
+
+impl fmt::Display for ConvertWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConvertWarning::FallbackFont { requested, used } => write!(
+                f,
+                "Font {} could not be parsed, drew with {} instead",
+                requested, used
+            ),
+            ConvertWarning::Downscaled { from, to } => write!(
+                f,
+                "Input of size {}x{} was downscaled to {}x{} to fit the pixel limit",
+                from.0, from.1, to.0, to.1
+            ),
+            ConvertWarning::MissingGlyphs(chars) => {
+                let chars: String = chars.iter().collect();
+                write!(f, "Font has no glyph for {:?}", chars)
+            }
+            ConvertWarning::PartialDecode { format } => write!(
+                f,
+                "{} data is truncated or corrupt, the part that could not be decoded was filled in",
+                format
+            ),
+            ConvertWarning::UnsupportedColorProfile => write!(
+                f,
+                "Input has a color profile that is not supported, its colors may be off"
+            ),
+            ConvertWarning::SequenceGap { after, before } if before - after == 2 => {
+                write!(f, "Frame {} is missing from the sequence", after + 1)
+            }
+            ConvertWarning::SequenceGap { after, before } => write!(
+                f,
+                "Frames {} to {} are missing from the sequence",
+                after + 1,
+                before - 1
+            ),
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use super::error::ConvertError;
+use ab_glyph::{Font, PxScale, ScaleFont};
+use image::{ImageBuffer, Rgb};
+use imageproc::drawing::{draw_text_mut, text_size};
+
+// Smallest text size in pixels a watermark wider than the image is shrunk to before it is truncated
+const MIN_WATERMARK_PX: f32 = 6.0;
+
+/*
+* Corner of the output image a watermark sits in
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    #[default]
+    BottomRight,
+}
+
+/*
+* A line of text drawn over the finished grid in one corner of rendered images, padding pixels away
+* from both edges. The text size is the font size times scale. Text wider than the image is shrunk
+* down to MIN_WATERMARK_PX, then cut short from the end
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub struct Watermark {
+    pub text: String,
+    pub corner: Corner,
+    pub color: Rgb<u8>,
+    pub padding: u32,
+    pub scale: f32,
+}
+
+impl Watermark {
+    pub fn new(text: &str) -> Self {
+        Watermark {
+            text: text.to_string(),
+            corner: Corner::default(),
+            color: Rgb([255, 255, 255]),
+            padding: 4,
+            scale: 1.0,
+        }
+    }
+
+    pub fn with_corner(mut self, corner: Corner) -> Self {
+        self.corner = corner;
+        self
+    }
+
+    pub fn with_color(mut self, color: Rgb<u8>) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn with_padding(mut self, padding: u32) -> Self {
+        self.padding = padding;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), ConvertError> {
+        if !(self.scale > 0.0 && self.scale.is_finite()) {
+            return Err(ConvertError::InvalidSetting {
+                field: "watermark.scale",
+                reason: "must be positive",
+            });
+        }
+        Ok(())
+    }
+
+    pub fn draw(&self, bufr: &mut ImageBuffer<Rgb<u8>, Vec<u8>>, font: &impl Font, font_size: u32) {
+        /*
+         * Draw the watermark into bufr, blending the antialiased glyph edges into what is under
+         * them. Images with no room inside the padding are left as they are
+         */
+        let (w, h) = bufr.dimensions();
+        let room_w = w.saturating_sub(2 * self.padding);
+        let room_h = h.saturating_sub(2 * self.padding);
+
+        // Fit the line height into the image, then the width
+        let mut px = font_size as f32 * self.scale;
+        let line_height = |px: f32| font.as_scaled(PxScale::from(px)).height();
+        if line_height(px) > room_h as f32 {
+            px *= room_h as f32 / line_height(px);
+        }
+        let (text_w, _) = text_size(PxScale::from(px), font, &self.text);
+        if text_w > room_w {
+            px = (px * room_w as f32 / text_w as f32).max(MIN_WATERMARK_PX.min(px));
+        }
+        let scale = PxScale::from(px);
+        let mut text = self.text.clone();
+        while !text.is_empty() && text_size(scale, font, &text).0 > room_w {
+            text.pop();
+        }
+        let line_h = line_height(px).ceil() as u32;
+        if text.is_empty() || px < 1.0 || line_h > room_h {
+            return;
+        }
+
+        let (text_w, _) = text_size(scale, font, &text);
+        let x = match self.corner {
+            Corner::TopLeft | Corner::BottomLeft => self.padding,
+            Corner::TopRight | Corner::BottomRight => w - self.padding - text_w,
+        };
+        let y = match self.corner {
+            Corner::TopLeft | Corner::TopRight => self.padding,
+            Corner::BottomLeft | Corner::BottomRight => h - self.padding - line_h,
+        };
+        draw_text_mut(bufr, self.color, x as i32, y as i32, scale, font, &text);
+    }
+}
@@ -0,0 +1,106 @@
+use super::error::ConvertError;
+use image::imageops::{resize, FilterType};
+use image::GrayImage;
+use imageproc::filter::gaussian_blur_f32;
+use ndarray::{Array2, Zip};
+
+/*
+* Where the detail weight of every cell comes from. Image is a grayscale map stretched over the
+* grid, white for full detail. Saliency weighs cells by how far their luminance stands out from
+* their blurred surroundings
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub enum WeightSource {
+    Saliency,
+    Image(GrayImage),
+}
+
+/*
+* Per cell control of the ramp length. A cell of weight w in 0..=1 only uses the first
+* min_levels + (levels - min_levels) * w^gamma characters of the tile ramp, the ones with the least
+* ink, so low weight regions come out sparse. A gamma above 1 keeps the ramp short until the weight
+* gets high, below 1 lengthens it quickly
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub struct WeightMap {
+    pub source: WeightSource,
+    pub min_levels: usize,
+    pub gamma: f32,
+}
+
+impl WeightMap {
+    pub fn new(source: WeightSource, min_levels: usize, gamma: f32) -> Self {
+        WeightMap {
+            source,
+            min_levels,
+            gamma,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ConvertError> {
+        if self.min_levels == 0 {
+            return Err(ConvertError::InvalidSetting {
+                field: "weight_map.min_levels",
+                reason: "must be at least 1",
+            });
+        }
+        if !(self.gamma > 0.0 && self.gamma.is_finite()) {
+            return Err(ConvertError::InvalidSetting {
+                field: "weight_map.gamma",
+                reason: "must be positive",
+            });
+        }
+        Ok(())
+    }
+
+    pub fn weights(&self, gray: &GrayImage) -> Array2<f32> {
+        /*
+         * Weight in 0..=1 of every cell of the grid gray covers, one pixel per cell
+         */
+        let (w, h) = gray.dimensions();
+        let rows_cols = (h as usize, w as usize);
+        match &self.source {
+            WeightSource::Image(map) => {
+                let fitted = resize(map, w, h, FilterType::Triangle);
+                Array2::from_shape_fn(rows_cols, |(y, x)| {
+                    fitted.get_pixel(x as u32, y as u32)[0] as f32 / 255.0
+                })
+            }
+            WeightSource::Saliency => saliency(gray),
+        }
+    }
+
+    pub fn apply(&self, tiles: &mut Array2<usize>, weights: &Array2<f32>, levels: usize) {
+        /*
+         * Squeeze the ramp index of every cell into the levels its weight allows
+         */
+        let min_levels = self.min_levels.min(levels);
+        Zip::from(tiles).and(weights).for_each(|tile, &weight| {
+            let extra = (levels - min_levels) as f32 * weight.clamp(0.0, 1.0).powf(self.gamma);
+            let allowed = min_levels + extra.round() as usize;
+            if allowed < levels {
+                *tile = *tile * (allowed - 1) / (levels - 1);
+            }
+        });
+    }
+}
+
+fn saliency(gray: &GrayImage) -> Array2<f32> {
+    /*
+     * Center surround contrast: distance of every cell from a blur an eighth of the grid wide,
+     * scaled so the most salient cell weighs 1
+     */
+    let (w, h) = gray.dimensions();
+    let sigma = (w.max(h) as f32 / 8.0).max(1.0);
+    let surround = gaussian_blur_f32(gray, sigma);
+    let contrast = Array2::from_shape_fn((h as usize, w as usize), |(y, x)| {
+        let (x, y) = (x as u32, y as u32);
+        (gray.get_pixel(x, y)[0] as f32 - surround.get_pixel(x, y)[0] as f32).abs()
+    });
+    let max = contrast.fold(0.0f32, |m, &c| m.max(c));
+    if max > 0.0 {
+        contrast / max
+    } else {
+        contrast
+    }
+}
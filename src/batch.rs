@@ -0,0 +1,518 @@
+use crate::ascii::config::ConverterConfig;
+use crate::ascii::converter::Converter;
+use crate::ascii::error::ConvertError;
+use image::ImageFormat;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use xxhash_rust::xxh3::xxh3_64;
+
+// Sidecar index of the incremental cache, kept next to the outputs it describes
+pub const CACHE_FILE: &str = ".ruscii-cache";
+
+// Output names mirror the input names
+pub const DEFAULT_OUTPUT_TEMPLATE: &str = "{stem}.{ext}";
+
+const PLACEHOLDERS: [&str; 7] = ["stem", "ext", "cols", "rows", "preset", "date", "cfghash"];
+
+// Device names Windows reserves in every directory, whatever the extension
+const WINDOWS_RESERVED: [&str; 28] = [
+    "CON",
+    "PRN",
+    "AUX",
+    "NUL",
+    "COM1",
+    "COM2",
+    "COM3",
+    "COM4",
+    "COM5",
+    "COM6",
+    "COM7",
+    "COM8",
+    "COM9",
+    "COM\u{b9}",
+    "COM\u{b2}",
+    "COM\u{b3}",
+    "LPT1",
+    "LPT2",
+    "LPT3",
+    "LPT4",
+    "LPT5",
+    "LPT6",
+    "LPT7",
+    "LPT8",
+    "LPT9",
+    "LPT\u{b9}",
+    "LPT\u{b2}",
+    "LPT\u{b3}",
+];
+// Characters Windows does not allow in file names, besides the path separators
+const WINDOWS_INVALID: [char; 7] = ['<', '>', ':', '"', '|', '?', '*'];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BatchOptions {
+    // Skip inputs whose bytes and converter config match the last conversion into an output
+    // that still exists
+    pub incremental: bool,
+    // File name of every output, see NameTemplate for the placeholders
+    pub output_template: String,
+    // Name the {preset} placeholder expands to, usually the stem of the config file
+    pub preset: String,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        BatchOptions {
+            incremental: false,
+            output_template: DEFAULT_OUTPUT_TEMPLATE.to_string(),
+            preset: "default".to_string(),
+        }
+    }
+}
+
+impl BatchOptions {
+    pub fn new(incremental: bool) -> Self {
+        BatchOptions {
+            incremental,
+            ..BatchOptions::default()
+        }
+    }
+
+    pub fn with_output_template(mut self, output_template: &str) -> Self {
+        self.output_template = output_template.to_string();
+        self
+    }
+
+    pub fn with_preset(mut self, preset: &str) -> Self {
+        self.preset = preset.to_string();
+        self
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Segment {
+    Text(String),
+    Placeholder(&'static str),
+}
+
+/*
+* Output file name with placeholders in braces: {stem} of the input, {ext} of the output (png),
+* {cols} and {rows} of the grid, {preset} from the batch options, the {date} of the run as
+* YYYY-MM-DD and {cfghash}, the ConverterConfig::config_hash of the converter's config
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NameTemplate {
+    segments: Vec<Segment>,
+}
+
+/*
+* Values a NameTemplate is expanded with. The stem is kept as the platform gives it, so names that
+* are not valid unicode carry over to the outputs unchanged
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NameFields<'a> {
+    pub stem: &'a OsStr,
+    pub cols: u32,
+    pub rows: u32,
+    pub preset: &'a str,
+    pub date: &'a str,
+    pub cfghash: &'a str,
+}
+
+impl NameTemplate {
+    pub fn parse(template: &str) -> Result<Self, ConvertError> {
+        /*
+         * Split the template into text and placeholders, failing on unknown placeholders,
+         * unbalanced braces and names that would leave the output directory
+         */
+        let invalid = |reason: String| {
+            ConvertError::ConfigError(format!("name template {:?} {}", template, reason))
+        };
+        if template.contains(['/', '\\']) {
+            return Err(invalid("must not contain path separators".to_string()));
+        }
+        let mut segments = vec![];
+        let mut rest = template;
+        while let Some(open) = rest.find(['{', '}']) {
+            if rest[open..].starts_with('}') {
+                return Err(invalid("has a } without a matching {".to_string()));
+            }
+            let close = rest[open..]
+                .find('}')
+                .map(|close| open + close)
+                .ok_or_else(|| invalid("has a { without a matching }".to_string()))?;
+            if open > 0 {
+                segments.push(Segment::Text(rest[..open].to_string()));
+            }
+            let name = &rest[open + 1..close];
+            let placeholder = PLACEHOLDERS
+                .iter()
+                .find(|&&known| known == name)
+                .ok_or_else(|| {
+                    let valid: Vec<String> =
+                        PLACEHOLDERS.iter().map(|p| format!("{{{}}}", p)).collect();
+                    invalid(format!(
+                        "has an unknown placeholder {{{}}}, valid ones are {}",
+                        name,
+                        valid.join(", ")
+                    ))
+                })?;
+            segments.push(Segment::Placeholder(placeholder));
+            rest = &rest[close + 1..];
+        }
+        if !rest.is_empty() {
+            segments.push(Segment::Text(rest.to_string()));
+        }
+        if segments.is_empty() {
+            return Err(invalid("is empty".to_string()));
+        }
+        Ok(NameTemplate { segments })
+    }
+
+    pub fn uses_grid(&self) -> bool {
+        // Only templates naming the grid size need the input dimensions up front
+        self.segments.iter().any(|segment| {
+            matches!(
+                segment,
+                Segment::Placeholder("cols") | Segment::Placeholder("rows")
+            )
+        })
+    }
+
+    pub fn expand(&self, fields: &NameFields) -> OsString {
+        let mut name = OsString::new();
+        for segment in self.segments.iter() {
+            match segment {
+                Segment::Text(text) => name.push(text),
+                Segment::Placeholder("stem") => name.push(fields.stem),
+                Segment::Placeholder("ext") => name.push("png"),
+                Segment::Placeholder("cols") => name.push(fields.cols.to_string()),
+                Segment::Placeholder("rows") => name.push(fields.rows.to_string()),
+                Segment::Placeholder("preset") => name.push(fields.preset),
+                Segment::Placeholder("cfghash") => name.push(fields.cfghash),
+                Segment::Placeholder(_) => name.push(fields.date),
+            }
+        }
+        name
+    }
+}
+
+pub fn windows_safe_name(name: &OsStr) -> OsString {
+    /*
+     * File name Windows can create for name. Characters it does not allow become _, as do the
+     * trailing dots and spaces it would drop, and reserved device names such as CON or nul.png
+     * are prefixed with _. Names that are not unicode are read lossily, Windows names nearly
+     * always are
+     */
+    let mut safe: String = name
+        .to_string_lossy()
+        .chars()
+        .map(|c| {
+            if c.is_control() || WINDOWS_INVALID.contains(&c) {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    let kept = safe.trim_end_matches(['.', ' ']).len();
+    safe.replace_range(kept.., &"_".repeat(safe.len() - kept));
+
+    // The device is reserved with any extension and with spaces before it
+    let base = safe.split('.').next().unwrap_or_default().trim_end();
+    if WINDOWS_RESERVED
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(base))
+    {
+        safe.insert(0, '_');
+    }
+    OsString::from(safe)
+}
+
+fn today() -> String {
+    /*
+     * Current UTC date as YYYY-MM-DD, from the days since the epoch with the civil calendar
+     * conversion of Howard Hinnant's date algorithms
+     */
+    let secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let days = (secs / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let doe = days.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Converted,
+    Skipped,
+}
+
+/*
+* Progress of a convert_dir_with_progress run, reported once for every input as it is done with.
+* done counts the inputs reported so far, this one included
+*/
+#[derive(Debug)]
+pub struct BatchProgress<'a> {
+    pub done: usize,
+    pub total: usize,
+    pub input: &'a Path,
+    pub outcome: Result<Outcome, &'a ConvertError>,
+}
+
+/*
+* What a convert_dir run did with every input, a failed input does not stop the others
+*/
+#[derive(Debug, Default)]
+pub struct BatchReport {
+    pub converted: Vec<PathBuf>,
+    pub skipped: Vec<PathBuf>,
+    pub failed: Vec<(PathBuf, ConvertError)>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+struct CacheEntry {
+    input_hash: u64,
+    config_hash: u64,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+struct CacheIndex {
+    version: String,
+    // Keyed by the file name of the output
+    entries: BTreeMap<String, CacheEntry>,
+}
+
+/*
+* Hashes of the inputs and configs behind the outputs of one directory. A missing, unreadable or
+* corrupt index, or one written by another version, is treated as empty so the worst case is a
+* conversion that could have been skipped
+*/
+pub struct ConversionCache {
+    path: PathBuf,
+    index: CacheIndex,
+}
+
+impl ConversionCache {
+    pub fn load(dir: &Path) -> Self {
+        let path = dir.join(CACHE_FILE);
+        let index = fs::read_to_string(&path)
+            .ok()
+            .and_then(|text| serde_json::from_str::<CacheIndex>(&text).ok())
+            .filter(|index| index.version == env!("CARGO_PKG_VERSION"))
+            .unwrap_or_else(|| CacheIndex {
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                entries: BTreeMap::new(),
+            });
+        ConversionCache { path, index }
+    }
+
+    pub fn is_fresh(&self, output: &Path, input_hash: u64, config_hash: u64) -> bool {
+        let expected = CacheEntry {
+            input_hash,
+            config_hash,
+        };
+        output.exists() && self.index.entries.get(&cache_key(output)) == Some(&expected)
+    }
+
+    pub fn record(&mut self, output: &Path, input_hash: u64, config_hash: u64) {
+        self.index.entries.insert(
+            cache_key(output),
+            CacheEntry {
+                input_hash,
+                config_hash,
+            },
+        );
+    }
+
+    pub fn save(&self) -> Result<(), ConvertError> {
+        let text = serde_json::to_string_pretty(&self.index)
+            .map_err(|e| ConvertError::ConfigError(e.to_string()))?;
+        fs::write(&self.path, text)?;
+        Ok(())
+    }
+}
+
+fn cache_key(output: &Path) -> String {
+    output
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+pub fn hash_bytes(bytes: &[u8]) -> u64 {
+    xxh3_64(bytes)
+}
+
+pub fn hash_config(config: &ConverterConfig) -> Result<u64, ConvertError> {
+    // Hashing the serialized form keeps the hash stable across runs, unlike a derived Hash
+    Ok(hash_bytes(config.to_toml()?.as_bytes()))
+}
+
+fn is_image(path: &Path) -> bool {
+    path.is_file() && ImageFormat::from_path(path).is_ok_and(|format| format.can_read())
+}
+
+pub fn convert_dir(
+    input_dir: impl AsRef<Path>,
+    output_dir: impl AsRef<Path>,
+    config: &ConverterConfig,
+    options: &BatchOptions,
+) -> Result<BatchReport, ConvertError> {
+    /*
+     * Convert every image directly inside input_dir into a PNG in output_dir, named by the output
+     * template. Inputs whose names collide after templating are reported as failed and none of
+     * them is written. On Windows, names are made safe with windows_safe_name
+     */
+    convert_dir_with_progress(input_dir, output_dir, config, options, |_| {})
+}
+
+pub fn convert_dir_with_progress<F>(
+    input_dir: impl AsRef<Path>,
+    output_dir: impl AsRef<Path>,
+    config: &ConverterConfig,
+    options: &BatchOptions,
+    mut on_progress: F,
+) -> Result<BatchReport, ConvertError>
+where
+    F: FnMut(&BatchProgress),
+{
+    /*
+     * convert_dir calling on_progress after every input. Inputs that fail before conversion, on
+     * their name, are reported first
+     */
+    let template = NameTemplate::parse(&options.output_template)?;
+    let converter = config.build()?;
+    let config_hash = hash_config(config)?;
+    let output_dir = output_dir.as_ref();
+    fs::create_dir_all(output_dir)?;
+
+    let mut inputs: Vec<PathBuf> = fs::read_dir(input_dir.as_ref())?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| is_image(path))
+        .collect();
+    // Directory order is platform dependent
+    inputs.sort();
+
+    let total = inputs.len();
+    let mut report = BatchReport::default();
+    let mut record = |input: PathBuf, outcome: Result<Outcome, ConvertError>| {
+        on_progress(&BatchProgress {
+            done: report.converted.len() + report.skipped.len() + report.failed.len() + 1,
+            total,
+            input: &input,
+            outcome: outcome.as_ref().copied(),
+        });
+        match outcome {
+            Ok(Outcome::Converted) => report.converted.push(input),
+            Ok(Outcome::Skipped) => report.skipped.push(input),
+            Err(e) => report.failed.push((input, e)),
+        }
+    };
+
+    let mut cache = options
+        .incremental
+        .then(|| ConversionCache::load(output_dir));
+    let date = today();
+    let mut outputs: Vec<(PathBuf, PathBuf)> = vec![];
+    for input in inputs {
+        match output_name(&converter, &template, &input, &options.preset, &date) {
+            Ok(name) => outputs.push((input, output_dir.join(name))),
+            Err(e) => record(input, Err(e)),
+        }
+    }
+    let mut taken: BTreeMap<&Path, usize> = BTreeMap::new();
+    for (_, output) in outputs.iter() {
+        *taken.entry(output.as_path()).or_default() += 1;
+    }
+    let (unique, colliding): (Vec<_>, Vec<_>) = outputs
+        .iter()
+        .cloned()
+        .partition(|(_, output)| taken[output.as_path()] == 1);
+    for (input, output) in colliding {
+        let e = ConvertError::OutputCollision(output.display().to_string());
+        record(input, Err(e));
+    }
+
+    for (input, output) in unique {
+        let outcome = convert_cached(
+            &converter,
+            config,
+            config_hash,
+            &input,
+            &output,
+            cache.as_mut(),
+        );
+        record(input, outcome);
+    }
+
+    if let Some(cache) = cache {
+        cache.save()?;
+    }
+    Ok(report)
+}
+
+fn output_name(
+    converter: &Converter,
+    template: &NameTemplate,
+    input: &Path,
+    preset: &str,
+    date: &str,
+) -> Result<OsString, ConvertError> {
+    let (cols, rows) = if template.uses_grid() {
+        let (w, h) = image::image_dimensions(input)?;
+        let geometry = converter.output_geometry(w, h)?;
+        (geometry.cols, geometry.rows)
+    } else {
+        (0, 0)
+    };
+    let name = template.expand(&NameFields {
+        stem: input.file_stem().unwrap_or_default(),
+        cols,
+        rows,
+        preset,
+        date,
+        cfghash: converter.config_hash().unwrap_or_default(),
+    });
+    Ok(if cfg!(windows) {
+        windows_safe_name(&name)
+    } else {
+        name
+    })
+}
+
+pub(crate) fn convert_cached(
+    converter: &Converter,
+    config: &ConverterConfig,
+    config_hash: u64,
+    input: &Path,
+    output: &Path,
+    cache: Option<&mut ConversionCache>,
+) -> Result<Outcome, ConvertError> {
+    /*
+     * Convert input into output unless the cache shows the output came from the same bytes and
+     * config, and record the conversion in the cache otherwise
+     */
+    let input_hash = hash_bytes(&fs::read(input)?);
+    if let Some(cache) = &cache {
+        if cache.is_fresh(output, input_hash, config_hash) {
+            return Ok(Outcome::Skipped);
+        }
+    }
+
+    converter.convert_img(input, output, config.edge_threshold)?;
+    if let Some(cache) = cache {
+        cache.record(output, input_hash, config_hash);
+    }
+    Ok(Outcome::Converted)
+}
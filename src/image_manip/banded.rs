@@ -0,0 +1,49 @@
+use super::processing::Processor;
+use crate::ascii::error::ConvertError;
+use image::imageops::{crop_imm, overlay};
+use image::GrayImage;
+use std::borrow::Borrow;
+
+pub fn pipeline_border<'a, P: Borrow<dyn Processor<u8, u8> + 'a>>(processors: &[P]) -> u32 {
+    /*
+     * Overlap a band needs for its output to match the whole image. Every processor reads its
+     * radius past the pixels that were already correct after the previous one, so the radii add
+     * up
+     */
+    processors.iter().fold(0u32, |sum, p| {
+        sum.saturating_add(p.borrow().border_radius())
+    })
+}
+
+pub fn apply_banded<'a, P: Borrow<dyn Processor<u8, u8> + 'a>>(
+    processors: &[P],
+    bufr: &GrayImage,
+    band_height: u32,
+) -> Result<GrayImage, ConvertError> {
+    /*
+     * Run the pipeline over horizontal bands of band_height rows, each padded with the pipeline
+     * border above and below, and stitch the bands back together. Only one padded band is
+     * processed at a time, which bounds the working memory of large images
+     */
+    let (w, h) = bufr.dimensions();
+    let border = pipeline_border(processors);
+    // With the border covering the image, every band would be the whole image anyway
+    let band_height = if border >= h { h } else { band_height.max(1) };
+
+    let mut out = GrayImage::new(w, h);
+    let mut top = 0;
+    while top < h {
+        let bottom = top.saturating_add(band_height).min(h);
+        let padded_top = top.saturating_sub(border);
+        let padded_bottom = bottom.saturating_add(border).min(h);
+
+        let mut band = crop_imm(bufr, 0, padded_top, w, padded_bottom - padded_top).to_image();
+        for processor in processors {
+            band = processor.borrow().apply(&band)?;
+        }
+        let core = crop_imm(&band, 0, top - padded_top, w, bottom - top).to_image();
+        overlay(&mut out, &core, 0, top as i64);
+        top = bottom;
+    }
+    Ok(out)
+}
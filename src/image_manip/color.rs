@@ -0,0 +1,140 @@
+use super::util::rgb_bufr_view_mut;
+use crate::ascii::error::ConvertError;
+use image::RgbImage;
+use ndarray::Axis;
+
+/*
+* Preprocessor of the color image, run right after decoding so both the grayscale pipelines and
+* the cell colors see its result
+*/
+pub trait ColorProcessor {
+    // Stable snake_case identifier of the processor
+    fn name(&self) -> &'static str;
+
+    // Parameters of the processor as name=value pairs separated by spaces, empty when it has none
+    fn params(&self) -> String {
+        String::new()
+    }
+
+    fn validate(&self) -> Result<(), ConvertError> {
+        Ok(())
+    }
+
+    fn apply(&self, bufr: &RgbImage) -> Result<RgbImage, ConvertError>;
+}
+
+fn map_pixels(bufr: &RgbImage, f: impl Fn([f32; 3]) -> [f32; 3]) -> RgbImage {
+    let mut out = bufr.clone();
+    for mut px in rgb_bufr_view_mut(&mut out).lanes_mut(Axis(2)) {
+        let out = f([px[0] as f32, px[1] as f32, px[2] as f32]);
+        for (c, v) in px.iter_mut().zip(out) {
+            *c = v.round().clamp(0.0, 255.0) as u8;
+        }
+    }
+    out
+}
+
+/*
+* Shift the color temperature. Positive temperatures warm the image by raising red and lowering
+* blue, negative ones cool it, 0 leaves it unchanged
+*/
+#[derive(Clone, Debug)]
+pub struct WhiteBalance {
+    // Between -1 and 1, the gain applied to red is 1 + temperature and to blue 1 - temperature
+    pub temperature: f32,
+}
+
+impl Default for WhiteBalance {
+    fn default() -> Self {
+        WhiteBalance { temperature: 0.0 }
+    }
+}
+
+impl WhiteBalance {
+    pub fn new(temperature: f32) -> Self {
+        WhiteBalance { temperature }
+    }
+}
+
+impl ColorProcessor for WhiteBalance {
+    fn name(&self) -> &'static str {
+        "white_balance"
+    }
+
+    fn params(&self) -> String {
+        format!("temperature={}", self.temperature)
+    }
+
+    fn validate(&self) -> Result<(), ConvertError> {
+        if (-1.0..=1.0).contains(&self.temperature) {
+            Ok(())
+        } else {
+            Err(ConvertError::InvalidSetting {
+                field: "white_balance.temperature",
+                reason: "must be between -1 and 1",
+            })
+        }
+    }
+
+    fn apply(&self, bufr: &RgbImage) -> Result<RgbImage, ConvertError> {
+        let t = self.temperature;
+        Ok(map_pixels(bufr, |[r, g, b]| {
+            [r * (1.0 + t), g, b * (1.0 - t)]
+        }))
+    }
+}
+
+/*
+* Push every pixel away from its gray value. Amounts below 0 desaturate, -1 turns the image gray
+*/
+#[derive(Clone, Debug)]
+pub struct SaturationBoost {
+    pub amount: f32,
+}
+
+impl Default for SaturationBoost {
+    fn default() -> Self {
+        SaturationBoost { amount: 0.5 }
+    }
+}
+
+impl SaturationBoost {
+    pub fn new(amount: f32) -> Self {
+        SaturationBoost { amount }
+    }
+}
+
+impl ColorProcessor for SaturationBoost {
+    fn name(&self) -> &'static str {
+        "saturation_boost"
+    }
+
+    fn params(&self) -> String {
+        format!("amount={}", self.amount)
+    }
+
+    fn validate(&self) -> Result<(), ConvertError> {
+        // NaN fails the comparison as well
+        if self.amount >= -1.0 {
+            Ok(())
+        } else {
+            Err(ConvertError::InvalidSetting {
+                field: "saturation_boost.amount",
+                reason: "must be at least -1",
+            })
+        }
+    }
+
+    fn apply(&self, bufr: &RgbImage) -> Result<RgbImage, ConvertError> {
+        let scale = 1.0 + self.amount;
+        Ok(map_pixels(bufr, |[r, g, b]| {
+            // Same Rec. 709 weights image uses for its grayscale conversion
+            let gray = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+            [
+                gray + (r - gray) * scale,
+                gray + (g - gray) * scale,
+                gray + (b - gray) * scale,
+            ]
+        }))
+    }
+}
@@ -1,10 +1,11 @@
 use crate::ascii::error::ConvertError;
 use image::{ImageBuffer, Luma, Primitive};
+use imageproc::filter::gaussian_blur_f32;
 use imageproc::gradients::{horizontal_sobel, vertical_sobel};
 use ndarray::{Array2, Zip};
 use num_traits::Num;
 
-use std::{f32::consts::PI, usize};
+use std::f32::consts::PI;
 
 use super::util::{arr_to_bufr, bufr_to_arr};
 
@@ -12,22 +13,167 @@ use super::util::{arr_to_bufr, bufr_to_arr};
 * Detect edges and quantize the image to a number of allowed values
 */
 pub trait EdgeDetect<T: Num + Copy + Primitive, U: Copy + Num + Primitive> {
+    // Stable snake_case identifier of the detector, custom for detectors defined outside the crate
+    fn name(&self) -> &'static str {
+        "custom"
+    }
+
+    // Parameters of the detector as name=value pairs separated by spaces, empty when it has none
+    fn params(&self) -> String {
+        String::new()
+    }
+
     fn apply(
         &self,
         bufr: &ImageBuffer<Luma<T>, Vec<T>>,
         val_num: u8,
     ) -> Result<ImageBuffer<Luma<U>, Vec<U>>, ConvertError>;
+
+    // Raw gradient field for stages that work on directions before they are quantized, None when
+    // the detector has no such intermediate
+    fn field(&self, _bufr: &ImageBuffer<Luma<T>, Vec<T>>) -> Option<EdgeField> {
+        None
+    }
+
+    // Whether the detector can find edges in the color channels of an image, see channels_field
+    fn supports_color(&self) -> bool {
+        false
+    }
+
+    // Gradient field of the red, green and blue channels of an image, each preprocessed on its
+    // own, for detectors whose supports_color is true
+    fn channels_field(&self, _channels: &[ImageBuffer<Luma<T>, Vec<T>>; 3]) -> Option<EdgeField> {
+        None
+    }
+}
+
+/*
+* Image the edges are detected on. Grayscale loses the edges between colors of the same
+* luminance, such as red text on a green background, which Color keeps by running the edge
+* preprocessors and the detector on every channel. Color needs a detector that supports it
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EdgeSource {
+    #[default]
+    Grayscale,
+    Color,
+}
+
+/*
+* Per pixel gradient vectors of an image, before their directions are quantized into edge bins
+*/
+#[derive(Clone, Debug)]
+pub struct EdgeField {
+    pub gx: Array2<f32>,
+    pub gy: Array2<f32>,
+}
+
+impl EdgeField {
+    pub fn quantize(&self) -> ImageBuffer<Luma<u8>, Vec<u8>> {
+        /*
+         * Bin the edge direction of every pixel with a gradient, pixels without one stay empty
+         */
+        self.quantize_for_aspect(1.0)
+    }
+
+    pub fn quantize_for_aspect(&self, cell_aspect: f32) -> ImageBuffer<Luma<u8>, Vec<u8>> {
+        /*
+         * quantize for glyphs drawn in cells cell_aspect times as wide as they are high. The
+         * directions are measured in cells rather than pixels, so the chosen glyph visually
+         * follows the contour: in 1:2 cells '/' runs at about 63 degrees and takes the edges
+         * closer to that angle than to '|'
+         */
+        let mut bins = Array2::zeros(self.gx.dim());
+        Zip::from(&self.gx)
+            .and(&self.gy)
+            .and(&mut bins)
+            .par_for_each(|&gx, &gy, bin| {
+                if gx != 0.0 || gy != 0.0 {
+                    *bin = tangent_bin(cell_gradient_angle(gx, gy, cell_aspect));
+                }
+            });
+        arr_to_bufr(&bins)
+    }
+}
+
+pub fn cell_gradient_angle(gx: f32, gy: f32, cell_aspect: f32) -> f32 {
+    /*
+     * Angle of a pixel gradient once the image is measured in cells of width / height
+     * cell_aspect. The tangent's vertical part grows by cell_aspect relative to its horizontal
+     * part, which for the gradient perpendicular to it scales the horizontal part
+     */
+    gy.atan2(gx * cell_aspect)
+}
+
+pub fn tangent_bin(gradient_angle: f32) -> u8 {
+    /*
+     * Edge bin of the tangent a quarter turn from a gradient angle, following the default edge
+     * set: 1 '_', 2 '|', 3 '/', 4 '\'. Image y points down, so a tangent going down and right is
+     * drawn as '\'
+     */
+    let tangent = (gradient_angle + PI / 2.0).rem_euclid(PI) / PI;
+    if !(0.125..0.875).contains(&tangent) {
+        1
+    } else if (0.375..0.625).contains(&tangent) {
+        2
+    } else if tangent < 0.5 {
+        4
+    } else {
+        3
+    }
+}
+
+pub fn sobel_magnitude(bufr: &ImageBuffer<Luma<u8>, Vec<u8>>) -> Array2<f32> {
+    /*
+     * Sobel gradient magnitude of every pixel, how strong an edge runs through it whatever its
+     * direction. A hard step of d luminance levels has a magnitude of 4 * d
+     */
+    let gx = bufr_to_arr(&horizontal_sobel(bufr));
+    let gy = bufr_to_arr(&vertical_sobel(bufr));
+    Zip::from(&gx)
+        .and(&gy)
+        .par_map_collect(|&gx, &gy| (gx as f32).hypot(gy as f32))
+}
+
+/*
+* Edge directions from the Sobel gradients. Pixels whose gradient magnitude is under min_magnitude
+* get no edge, 0 keeps every pixel with a gradient. A hard step of d luminance levels has a
+* magnitude of 4 * d
+*/
+pub struct Sobel {
+    pub min_magnitude: f32,
 }
 
-pub struct Sobel {}
+impl Default for Sobel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl Sobel {
     pub fn new() -> Self {
-        Sobel {}
+        Sobel { min_magnitude: 0.0 }
+    }
+
+    pub fn with_min_magnitude(mut self, min_magnitude: f32) -> Self {
+        self.min_magnitude = min_magnitude;
+        self
+    }
+
+    fn is_weak(&self, gx: f32, gy: f32) -> bool {
+        gx.hypot(gy) < self.min_magnitude
     }
 }
 
 impl EdgeDetect<u8, u8> for Sobel {
+    fn name(&self) -> &'static str {
+        "sobel"
+    }
+
+    fn params(&self) -> String {
+        format!("min_magnitude={}", self.min_magnitude)
+    }
+
     fn apply(
         &self,
         bufr: &ImageBuffer<Luma<u8>, Vec<u8>>,
@@ -51,7 +197,12 @@ impl EdgeDetect<u8, u8> for Sobel {
                 let gx_val = gx as f32;
                 let gy_val = gy as f32;
 
-                let theta = gy_val.atan2(gx_val);
+                // Weak gradients take the angle of no gradient at all
+                let theta = if self.is_weak(gx_val, gy_val) {
+                    0.0
+                } else {
+                    gy_val.atan2(gx_val)
+                };
                 *theta_norm = (theta / PI) * 0.5 + 0.5;
             });
 
@@ -60,18 +211,228 @@ impl EdgeDetect<u8, u8> for Sobel {
             let mut res = 0;
             if x == 0.5 {
                 res = 0;
-            } else if x >= 0.95 && x <= 1.0 {
+            } else if (0.95..=1.0).contains(&x) {
                 res = 2;
-            } else if ((x >= 0.25) && (x < 0.27)) || ((x >= 0.75) && (x < 0.77)) {
+            } else if (0.25..0.27).contains(&x) || (0.75..0.77).contains(&x) {
                 res = 1;
-            } else if ((x >= 0.28) && (x < 0.55)) || ((x >= 0.78) && (x < 1.0)) {
+            } else if (0.28..0.55).contains(&x) || (0.78..1.0).contains(&x) {
                 res = 3;
-            } else if ((x >= 0.28) && (x < 0.55)) || ((x >= 0.78) && (x < 1.0)) {
-                res = 4;
             }
             res
         });
 
         Ok(arr_to_bufr(&edges))
     }
+
+    fn field(&self, bufr: &ImageBuffer<Luma<u8>, Vec<u8>>) -> Option<EdgeField> {
+        let mut gx = bufr_to_arr(&horizontal_sobel(bufr)).mapv(|x| x as f32);
+        let mut gy = bufr_to_arr(&vertical_sobel(bufr)).mapv(|y| y as f32);
+        if self.min_magnitude > 0.0 {
+            Zip::from(&mut gx).and(&mut gy).par_for_each(|gx, gy| {
+                if self.is_weak(*gx, *gy) {
+                    (*gx, *gy) = (0.0, 0.0);
+                }
+            });
+        }
+        Some(EdgeField { gx, gy })
+    }
+}
+
+fn sobel_field(bufr: &ImageBuffer<Luma<u8>, Vec<u8>>) -> EdgeField {
+    EdgeField {
+        gx: bufr_to_arr(&horizontal_sobel(bufr)).mapv(|x| x as f32),
+        gy: bufr_to_arr(&vertical_sobel(bufr)).mapv(|y| y as f32),
+    }
+}
+
+/*
+* Sobel on the color image. Every channel gets its own gradients, and every pixel takes the
+* gradient of the channel with the largest magnitude, direction included, so edges between colors
+* of the same luminance are found. On a grayscale image it has the gradients of Sobel. Pixels
+* whose strongest gradient is under min_magnitude get no edge
+*/
+pub struct ColorSobel {
+    pub min_magnitude: f32,
+}
+
+impl Default for ColorSobel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ColorSobel {
+    pub fn new() -> Self {
+        ColorSobel { min_magnitude: 0.0 }
+    }
+
+    pub fn with_min_magnitude(mut self, min_magnitude: f32) -> Self {
+        self.min_magnitude = min_magnitude;
+        self
+    }
+
+    fn drop_weak(&self, field: &mut EdgeField) {
+        if self.min_magnitude > 0.0 {
+            Zip::from(&mut field.gx)
+                .and(&mut field.gy)
+                .par_for_each(|gx, gy| {
+                    if gx.hypot(*gy) < self.min_magnitude {
+                        (*gx, *gy) = (0.0, 0.0);
+                    }
+                });
+        }
+    }
+}
+
+impl EdgeDetect<u8, u8> for ColorSobel {
+    fn name(&self) -> &'static str {
+        "color_sobel"
+    }
+
+    fn params(&self) -> String {
+        format!("min_magnitude={}", self.min_magnitude)
+    }
+
+    fn apply(
+        &self,
+        bufr: &ImageBuffer<Luma<u8>, Vec<u8>>,
+        _val_num: u8,
+    ) -> Result<ImageBuffer<Luma<u8>, Vec<u8>>, ConvertError> {
+        // Directions binned the way EdgeField::quantize does, the field holding the only channel
+        let mut field = sobel_field(bufr);
+        self.drop_weak(&mut field);
+        Ok(field.quantize())
+    }
+
+    fn field(&self, bufr: &ImageBuffer<Luma<u8>, Vec<u8>>) -> Option<EdgeField> {
+        let mut field = sobel_field(bufr);
+        self.drop_weak(&mut field);
+        Some(field)
+    }
+
+    fn supports_color(&self) -> bool {
+        true
+    }
+
+    fn channels_field(&self, channels: &[ImageBuffer<Luma<u8>, Vec<u8>>; 3]) -> Option<EdgeField> {
+        let [red, green, blue] = channels.each_ref().map(sobel_field);
+        let mut field = red;
+        // Ties keep the earlier channel
+        for other in [green, blue] {
+            Zip::from(&mut field.gx)
+                .and(&mut field.gy)
+                .and(&other.gx)
+                .and(&other.gy)
+                .par_for_each(|gx, gy, &other_gx, &other_gy| {
+                    if other_gx.hypot(other_gy) > gx.hypot(*gy) {
+                        (*gx, *gy) = (other_gx, other_gy);
+                    }
+                });
+        }
+        self.drop_weak(&mut field);
+        Some(field)
+    }
+}
+
+/*
+* Edge orientation from the structure tensor. The tensor components are smoothed over a
+* neighborhood before the orientation is derived, so directions stay stable along a contour
+* instead of following per pixel noise
+*/
+pub struct StructureTensor {
+    pub sigma: f32,
+    // Minimum coherence in 0..=1 for a pixel to count as an edge, 1 being a perfectly straight
+    // edge and 0 a flat or isotropic region
+    pub min_coherence: f32,
+}
+
+// Smoothed gradient energy below this is treated as a flat region
+const MIN_TENSOR_ENERGY: f32 = 64.0;
+
+impl Default for StructureTensor {
+    fn default() -> Self {
+        StructureTensor {
+            sigma: 1.5,
+            min_coherence: 0.5,
+        }
+    }
+}
+
+impl StructureTensor {
+    pub fn new(sigma: f32, min_coherence: f32) -> Self {
+        StructureTensor {
+            sigma,
+            min_coherence,
+        }
+    }
+}
+
+impl EdgeDetect<u8, u8> for StructureTensor {
+    fn name(&self) -> &'static str {
+        "structure_tensor"
+    }
+
+    fn params(&self) -> String {
+        format!("sigma={} min_coherence={}", self.sigma, self.min_coherence)
+    }
+
+    fn apply(
+        &self,
+        bufr: &ImageBuffer<Luma<u8>, Vec<u8>>,
+        _val_num: u8,
+    ) -> Result<ImageBuffer<Luma<u8>, Vec<u8>>, ConvertError> {
+        /*
+         * Compute the smoothed tensor (gx², gy², gx·gy) per pixel, then bin the direction of the
+         * edge, which runs perpendicular to the dominant gradient. Bins follow the default edge
+         * set: 0 none, 1 '_', 2 '|', 3 '/', 4 '\'
+         */
+        if self.sigma.is_nan() || self.sigma <= 0.0 {
+            return Err(ConvertError::InvalidSetting {
+                field: "structure_tensor.sigma",
+                reason: "must be positive",
+            });
+        }
+
+        let gx = horizontal_sobel(bufr);
+        let gy = vertical_sobel(bufr);
+        let (w, h) = bufr.dimensions();
+
+        let component = |f: fn(f32, f32) -> f32| {
+            let raw = gx
+                .pixels()
+                .zip(gy.pixels())
+                .map(|(x, y)| f(x[0] as f32, y[0] as f32))
+                .collect();
+            let img: ImageBuffer<Luma<f32>, Vec<f32>> = ImageBuffer::from_raw(w, h, raw)
+                .expect("Tensor component has the size of the gradients");
+            gaussian_blur_f32(&img, self.sigma)
+        };
+        let jxx = component(|x, _| x * x);
+        let jyy = component(|_, y| y * y);
+        let jxy = component(|x, y| x * y);
+
+        let raw = jxx
+            .pixels()
+            .zip(jyy.pixels())
+            .zip(jxy.pixels())
+            .map(|((xx, yy), xy)| {
+                let (xx, yy, xy) = (xx[0], yy[0], xy[0]);
+                let energy = xx + yy;
+                if energy < MIN_TENSOR_ENERGY {
+                    return 0;
+                }
+
+                // Eigenvalue spread over their sum
+                let spread = ((xx - yy).powi(2) + 4.0 * xy * xy).sqrt();
+                if spread / energy < self.min_coherence {
+                    return 0;
+                }
+
+                // Dominant gradient angle
+                tangent_bin(0.5 * (2.0 * xy).atan2(xx - yy))
+            })
+            .collect();
+
+        Ok(ImageBuffer::from_raw(w, h, raw).expect("Edge map has the size of the input"))
+    }
 }
@@ -0,0 +1,133 @@
+use super::edge_detect::EdgeField;
+use ndarray::{Array2, Axis, Zip};
+use rayon::prelude::*;
+
+/*
+* Edge tangent flow smoothing. Every tangent is repeatedly averaged with the tangents of nearby
+* pixels that point a similar way, so directions follow a contour smoothly instead of jittering
+* between neighboring edge bins
+*/
+#[derive(Clone, Debug)]
+pub struct EdgeTangentFlow {
+    pub iterations: u32,
+    pub radius: u32,
+}
+
+impl Default for EdgeTangentFlow {
+    fn default() -> Self {
+        EdgeTangentFlow {
+            iterations: 3,
+            radius: 3,
+        }
+    }
+}
+
+impl EdgeTangentFlow {
+    pub fn new(iterations: u32, radius: u32) -> Self {
+        EdgeTangentFlow { iterations, radius }
+    }
+
+    pub fn apply(&self, field: &EdgeField) -> EdgeField {
+        /*
+         * Smooth the directions of a gradient field while keeping its magnitudes, so pixels
+         * without a gradient stay empty after quantization
+         */
+        let magnitude = Zip::from(&field.gx)
+            .and(&field.gy)
+            .map_collect(|&gx, &gy| (gx * gx + gy * gy).sqrt());
+        let max_magnitude = magnitude.fold(0.0f32, |acc, &m| acc.max(m));
+        if max_magnitude == 0.0 {
+            return field.clone();
+        }
+        let norm_magnitude = magnitude.mapv(|m| m / max_magnitude);
+
+        // Unit tangents, a quarter turn from the gradient
+        let mut tx =
+            Zip::from(&field.gy)
+                .and(&magnitude)
+                .map_collect(|&gy, &m| if m > 0.0 { -gy / m } else { 0.0 });
+        let mut ty =
+            Zip::from(&field.gx)
+                .and(&magnitude)
+                .map_collect(|&gx, &m| if m > 0.0 { gx / m } else { 0.0 });
+
+        for _ in 0..self.iterations {
+            let (next_tx, next_ty) = self.smooth(&tx, &ty, &norm_magnitude);
+            tx = next_tx;
+            ty = next_ty;
+        }
+
+        // Back to gradient vectors scaled by the original magnitude
+        EdgeField {
+            gx: Zip::from(&ty).and(&magnitude).map_collect(|&ty, &m| ty * m),
+            gy: Zip::from(&tx)
+                .and(&magnitude)
+                .map_collect(|&tx, &m| -tx * m),
+        }
+    }
+
+    fn smooth(
+        &self,
+        tx: &Array2<f32>,
+        ty: &Array2<f32>,
+        magnitude: &Array2<f32>,
+    ) -> (Array2<f32>, Array2<f32>) {
+        let (h, w) = tx.dim();
+        let r = self.radius as isize;
+        let mut next_tx = Array2::zeros((h, w));
+        let mut next_ty = Array2::zeros((h, w));
+
+        next_tx
+            .axis_iter_mut(Axis(0))
+            .into_par_iter()
+            .zip(next_ty.axis_iter_mut(Axis(0)).into_par_iter())
+            .enumerate()
+            .for_each(|(y, (mut row_tx, mut row_ty))| {
+                for x in 0..w {
+                    let (cx, cy) = (tx[(y, x)], ty[(y, x)]);
+                    if magnitude[(y, x)] == 0.0 {
+                        continue;
+                    }
+
+                    let (mut sum_x, mut sum_y) = (0.0, 0.0);
+                    for dy in -r..=r {
+                        for dx in -r..=r {
+                            // Circular neighborhood
+                            if dx * dx + dy * dy > r * r {
+                                continue;
+                            }
+                            let (ny, nx) = (y as isize + dy, x as isize + dx);
+                            if ny < 0 || nx < 0 || ny >= h as isize || nx >= w as isize {
+                                continue;
+                            }
+                            let (ny, nx) = (ny as usize, nx as usize);
+                            if magnitude[(ny, nx)] == 0.0 {
+                                continue;
+                            }
+
+                            // Stronger neighbors pull harder and aligned ones count more. The
+                            // sign of the dot product flips opposite tangents, since a tangent
+                            // has no direction along the contour
+                            let dot = cx * tx[(ny, nx)] + cy * ty[(ny, nx)];
+                            let w_magnitude =
+                                (1.0 + (magnitude[(ny, nx)] - magnitude[(y, x)]).tanh()) / 2.0;
+                            let weight = dot * w_magnitude;
+                            sum_x += tx[(ny, nx)] * weight;
+                            sum_y += ty[(ny, nx)] * weight;
+                        }
+                    }
+
+                    let len = (sum_x * sum_x + sum_y * sum_y).sqrt();
+                    if len > 0.0 {
+                        row_tx[x] = sum_x / len;
+                        row_ty[x] = sum_y / len;
+                    } else {
+                        row_tx[x] = cx;
+                        row_ty[x] = cy;
+                    }
+                }
+            });
+
+        (next_tx, next_ty)
+    }
+}
@@ -1,17 +1,22 @@
-use ndarray::{s, Array2};
-use rayon::iter::IntoParallelIterator;
-use rayon::iter::ParallelIterator;
-use std::{
-    collections::HashMap,
-    sync::{Arc, Mutex},
-    usize,
-};
+use ndarray::{Array2, ArrayView2, Axis, Zip};
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
 
 /*
 * Sharpen edge when downscale images
 */
 pub struct EdgeDownscaler {}
 
+/*
+* How the gradient magnitudes of the pixels that voted for the edge of a tile are brought down to
+* the strength of that one edge
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MagnitudeReduce {
+    #[default]
+    Max,
+    Mean,
+}
+
 impl EdgeDownscaler {
     pub fn hist_downscale(
         qt_edge_arr: &Array2<u8>,
@@ -19,43 +24,221 @@ impl EdgeDownscaler {
         thres_ratio: f32,
         new_size: (usize, usize), // new_h , new_w
     ) -> Array2<u8> {
-        let ds_edge_arr = Arc::new(Mutex::new(Array2::zeros(new_size)));
-
-        (0..new_size.0).into_par_iter().for_each(|i| {
-            (0..new_size.1).into_par_iter().for_each(|j| {
-                // Create a local histogram of tile
-                let mut hist: HashMap<u8, usize> = HashMap::new();
-                let mut max_val = 0; // To store the maximum occurring value
-                let mut max_count = 0; // To store the count of the maximum occurring value
-
-                // Get a tile
-                let tile = qt_edge_arr.slice(s![
-                    (i * tile_size) as usize..((i + 1) * tile_size) as usize,
-                    (j * tile_size) as usize..((j + 1) * tile_size) as usize
-                ]);
-
-                for &value in tile.iter() {
-                    *hist.entry(value).or_insert(0) += 1;
-
-                    // Update max_value and max_count if current value has a higher count
-                    if hist[&value] > max_count {
-                        max_count = hist[&value];
-                        max_val = value;
+        Self::hist_downscale_rect(qt_edge_arr, (tile_size, tile_size), thres_ratio, new_size)
+    }
+
+    pub fn hist_downscale_with_magnitude(
+        qt_edge_arr: &Array2<u8>,
+        magnitude: &Array2<f32>,
+        (tile_h, tile_w): (usize, usize),
+        thres_ratio: f32,
+        new_size: (usize, usize), // new_h , new_w
+        reduce: MagnitudeReduce,
+    ) -> (Array2<u8>, Array2<f32>) {
+        /*
+         * hist_downscale_rect carrying the gradient magnitude along as a second channel. Every
+         * cell with an edge also gets the max or mean magnitude of the pixels of its tile in the
+         * winning bin, the others get 0. magnitude needs the shape of qt_edge_arr
+         */
+        let ds_edge_arr =
+            Self::hist_downscale_rect(qt_edge_arr, (tile_h, tile_w), thres_ratio, new_size);
+        let (h, w) = qt_edge_arr.dim();
+        let mut ds_magnitude = Array2::zeros(new_size);
+        Zip::indexed(&mut ds_magnitude)
+            .and(&ds_edge_arr)
+            .par_for_each(|(i, j), strength, &bin| {
+                if bin == 0 {
+                    return;
+                }
+                let (mut max, mut sum, mut count) = (0.0f32, 0.0, 0);
+                for y in (i * tile_h).min(h)..((i + 1) * tile_h).min(h) {
+                    for x in (j * tile_w).min(w)..((j + 1) * tile_w).min(w) {
+                        if qt_edge_arr[(y, x)] == bin {
+                            let m = magnitude[(y, x)];
+                            max = max.max(m);
+                            sum += m;
+                            count += 1;
+                        }
                     }
                 }
+                *strength = match reduce {
+                    MagnitudeReduce::Max => max,
+                    MagnitudeReduce::Mean => sum / count.max(1) as f32,
+                };
+            });
+        (ds_edge_arr, ds_magnitude)
+    }
 
-                // Check if the number of edge values in the histogram pass the threshold
-                if hist.values().filter(|&&x| x != 0).count() as f32
-                    / (tile_size * tile_size) as f32
-                    >= thres_ratio
-                {
-                    // Set the value in ds_edge_arr to the value that occurs most in the histogram
-                    let mut ds_edge_arr = ds_edge_arr.lock().unwrap();
-                    ds_edge_arr[(i as usize, j as usize)] = max_val as u8;
+    pub fn hist_downscale_rect(
+        qt_edge_arr: &Array2<u8>,
+        (tile_h, tile_w): (usize, usize),
+        thres_ratio: f32,
+        new_size: (usize, usize), // new_h , new_w
+    ) -> Array2<u8> {
+        /*
+         * hist_downscale with tiles of tile_h x tile_w pixels, for cells that are not square
+         */
+        let mut ds_edge_arr = Array2::zeros(new_size);
+        let (h, w) = qt_edge_arr.dim();
+        // Tiles are read as plain row slices, which is much cheaper than slicing the array
+        let qt_edge_arr = qt_edge_arr.as_standard_layout();
+        let data = qt_edge_arr.as_slice().unwrap_or_default();
+
+        // One task per output row, every row writes only to its own cells
+        ds_edge_arr
+            .axis_iter_mut(Axis(0))
+            .into_par_iter()
+            .enumerate()
+            .for_each(|(i, mut row)| {
+                // Edge values are a u8, so the histogram fits on the stack
+                let mut hist = [0usize; 256];
+                let rows = (i * tile_h).min(h)..((i + 1) * tile_h).min(h);
+                for (j, cell) in row.iter_mut().enumerate() {
+                    let mut max_seen = 0;
+
+                    // Get a tile, cut short where it would run past the edge map
+                    let cols = (j * tile_w).min(w)..((j + 1) * tile_w).min(w);
+                    let tile_len = rows.len() * cols.len();
+
+                    // Zeros are counted too, which keeps the loop free of branches
+                    for y in rows.clone() {
+                        for &value in &data[y * w + cols.start..y * w + cols.end] {
+                            hist[value as usize] += 1;
+                            max_seen = max_seen.max(value as usize);
+                        }
+                    }
+                    let edge_count = tile_len - hist[0];
+
+                    // Check if the ratio of edge pixels in the tile passes the threshold
+                    if edge_count as f32 / tile_len as f32 >= thres_ratio {
+                        // Most occurring non-zero value, as zero is not an edge it never wins.
+                        // The lowest value wins ties so the result never depends on the scan
+                        // order
+                        let mut max_val = 0;
+                        let mut max_count = 0;
+                        for (value, &count) in hist.iter().enumerate().take(max_seen + 1).skip(1) {
+                            if count > max_count {
+                                max_val = value;
+                                max_count = count;
+                            }
+                        }
+                        *cell = max_val as u8;
+                    }
+
+                    // Only values up to max_seen were counted
+                    hist[..=max_seen].fill(0);
                 }
             });
-        });
 
-        Arc::try_unwrap(ds_edge_arr).unwrap().into_inner().unwrap()
+        ds_edge_arr
+    }
+}
+
+/*
+* Settings of the mode filter run over the downscaled edge map. It is off by default, a radius of
+* 1 with 2 disagreeing neighbors cleans up most single cell flips
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub struct EdgeSmoothing {
+    // Neighborhood is a (2 * radius + 1) square around each cell, 0 turns the filter off
+    pub radius: usize,
+    // Number of disagreeing non-zero neighbors needed before a cell is replaced
+    pub min_disagreement: usize,
+}
+
+impl Default for EdgeSmoothing {
+    fn default() -> Self {
+        EdgeSmoothing {
+            radius: 0,
+            min_disagreement: 2,
+        }
     }
 }
+
+impl EdgeSmoothing {
+    pub fn new(radius: usize, min_disagreement: usize) -> Self {
+        EdgeSmoothing {
+            radius,
+            min_disagreement,
+        }
+    }
+
+    pub fn apply(&self, ds_edge_arr: &Array2<u8>) -> Array2<u8> {
+        mode_filter(ds_edge_arr, self.radius, self.min_disagreement)
+    }
+}
+
+pub fn mode_filter(arr: &Array2<u8>, radius: usize, min_disagreement: usize) -> Array2<u8> {
+    /*
+     * Replace every non-zero cell that disagrees with at least min_disagreement non-zero
+     * neighbors by the most common non-zero value around it, so single cell direction flips
+     * along a contour disappear. Zeros are never changed and never count as neighbors
+     */
+    let (h, w) = arr.dim();
+    let mut out = arr.clone();
+
+    Zip::indexed(&mut out).par_for_each(|(i, j), cell| {
+        let center = arr[(i, j)];
+        if center == 0 {
+            return;
+        }
+
+        let mut counts = [0usize; 256];
+        let mut disagreement = 0;
+        for y in i.saturating_sub(radius)..(i + radius + 1).min(h) {
+            for x in j.saturating_sub(radius)..(j + radius + 1).min(w) {
+                let val = arr[(y, x)];
+                if (y, x) == (i, j) || val == 0 {
+                    continue;
+                }
+                counts[val as usize] += 1;
+                if val != center {
+                    disagreement += 1;
+                }
+            }
+        }
+
+        if disagreement >= min_disagreement.max(1) {
+            // Ties keep the current value when it is among the most common, else the lowest value
+            let best = counts.iter().copied().max().unwrap_or(0);
+            if counts[center as usize] < best {
+                *cell = counts.iter().position(|&c| c == best).unwrap_or(0) as u8;
+            }
+        }
+    });
+
+    out
+}
+
+pub fn edge_halo(edges: &ArrayView2<u8>) -> Array2<bool> {
+    /*
+     * Cells next to an edge cell, diagonals included, that are not edge cells themselves: the
+     * edge map dilated by one cell minus the edges
+     */
+    let (h, w) = edges.dim();
+    Zip::indexed(edges).par_map_collect(|(i, j), &edge| {
+        if edge != 0 {
+            return false;
+        }
+        (i.saturating_sub(1)..(i + 2).min(h))
+            .any(|y| (j.saturating_sub(1)..(j + 2).min(w)).any(|x| edges[(y, x)] != 0))
+    })
+}
+
+pub fn bias_edge_halo(tiles: &mut Array2<usize>, edges: &ArrayView2<u8>, bias: u8, levels: usize) {
+    /*
+     * Move the tile of every cell of the edge halo bias steps towards the dense end of a ramp of
+     * levels characters, so contours read clearly where the edge character itself did not land
+     */
+    if bias == 0 {
+        return;
+    }
+    let last = levels.saturating_sub(1);
+    Zip::from(tiles)
+        .and(&edge_halo(edges))
+        .par_for_each(|tile, &halo| {
+            if halo {
+                *tile = (*tile + bias as usize).min(last);
+            }
+        });
+}
@@ -0,0 +1,183 @@
+use image::imageops::{grayscale, resize, FilterType};
+use image::{DynamicImage, ImageBuffer, Rgb, RgbImage};
+use ndarray::{Array1, Array2, Zip};
+use std::fmt;
+use std::ops::Deref;
+
+// Longest side of the common size both images are brought to before they are compared
+pub const COMPARE_SIDE: u32 = 256;
+
+// Gaussian window of SSIM, the one of the original paper
+const SSIM_SIGMA: f32 = 1.5;
+const SSIM_RADIUS: usize = 5;
+// Stabilizing constants of SSIM for values in 0..=1
+const SSIM_C1: f32 = 0.01 * 0.01;
+const SSIM_C2: f32 = 0.03 * 0.03;
+
+/*
+* How closely a render approximates its original. mse is the mean squared error over the RGB
+* channels scaled to 0..=1, 0 for identical images. ssim is the mean structural similarity of the
+* luminance over a gaussian window, 1 for identical images. luminance_correlation is the Pearson
+* correlation of the luminance, near 1 when the render keeps the light and dark regions of the
+* original and near -1 when it inverts them
+*/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FidelityScore {
+    pub mse: f32,
+    pub ssim: f32,
+    pub luminance_correlation: f32,
+}
+
+impl fmt::Display for FidelityScore {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "mse: {:.4}", self.mse)?;
+        writeln!(f, "ssim: {:.4}", self.ssim)?;
+        write!(
+            f,
+            "luminance correlation: {:.4}",
+            self.luminance_correlation
+        )
+    }
+}
+
+pub fn compare<C: Deref<Target = [u8]>>(
+    original: &DynamicImage,
+    rendered: &ImageBuffer<Rgb<u8>, C>,
+) -> FidelityScore {
+    /*
+     * Score rendered against original. Both are brought to the smaller of their sizes, shrunk
+     * further to fit COMPARE_SIDE, so a render is judged on its tones rather than the strokes of
+     * single glyphs. Images of different aspect ratios are stretched to the same shape
+     */
+    let _span = stage_span!("fidelity");
+    let w = original.width().min(rendered.width());
+    let h = original.height().min(rendered.height());
+    if w == 0 || h == 0 {
+        return FidelityScore {
+            mse: 0.0,
+            ssim: 1.0,
+            luminance_correlation: 1.0,
+        };
+    }
+    let scale = (COMPARE_SIDE as f32 / w.max(h) as f32).min(1.0);
+    let (w, h) = (
+        ((w as f32 * scale).round() as u32).max(1),
+        ((h as f32 * scale).round() as u32).max(1),
+    );
+    let a = resize(&original.to_rgb8(), w, h, FilterType::Triangle);
+    let b = resize(rendered, w, h, FilterType::Triangle);
+
+    let (luma_a, luma_b) = (luminance(&a), luminance(&b));
+    let score = FidelityScore {
+        mse: mse(&a, &b),
+        ssim: ssim(&luma_a, &luma_b),
+        luminance_correlation: correlation(&luma_a, &luma_b),
+    };
+    stage_event!(
+        mse = score.mse,
+        ssim = score.ssim,
+        luminance_correlation = score.luminance_correlation;
+        "scored render"
+    );
+    score
+}
+
+fn luminance(img: &RgbImage) -> Array2<f32> {
+    let gray = grayscale(img);
+    Array2::from_shape_fn((gray.height() as usize, gray.width() as usize), |(y, x)| {
+        gray.get_pixel(x as u32, y as u32)[0] as f32 / 255.0
+    })
+}
+
+fn mse(a: &RgbImage, b: &RgbImage) -> f32 {
+    let sum: f64 = a
+        .iter()
+        .zip(b.iter())
+        .map(|(&a, &b)| {
+            let d = (a as f64 - b as f64) / 255.0;
+            d * d
+        })
+        .sum();
+    (sum / a.len() as f64) as f32
+}
+
+fn ssim(a: &Array2<f32>, b: &Array2<f32>) -> f32 {
+    /*
+     * Mean of the SSIM map. Local means, variances and the covariance are gaussian weighted
+     * averages over the window around every pixel; windows cut by the border are renormalized
+     * over the pixels they still cover
+     */
+    let mu_a = gaussian_window(a);
+    let mu_b = gaussian_window(b);
+    let aa = gaussian_window(&(a * a));
+    let bb = gaussian_window(&(b * b));
+    let ab = gaussian_window(&(a * b));
+
+    let mut total = 0.0f64;
+    Zip::from(&mu_a)
+        .and(&mu_b)
+        .and(&aa)
+        .and(&bb)
+        .and(&ab)
+        .for_each(|&ma, &mb, &aa, &bb, &ab| {
+            let var_a = (aa - ma * ma).max(0.0);
+            let var_b = (bb - mb * mb).max(0.0);
+            let cov = ab - ma * mb;
+            let s = ((2.0 * ma * mb + SSIM_C1) * (2.0 * cov + SSIM_C2))
+                / ((ma * ma + mb * mb + SSIM_C1) * (var_a + var_b + SSIM_C2));
+            total += s as f64;
+        });
+    (total / a.len() as f64) as f32
+}
+
+fn gaussian_window(values: &Array2<f32>) -> Array2<f32> {
+    // Separable weighted average, rows then columns
+    let kernel = Array1::from_shape_fn(2 * SSIM_RADIUS + 1, |i| {
+        let d = i as f32 - SSIM_RADIUS as f32;
+        (-d * d / (2.0 * SSIM_SIGMA * SSIM_SIGMA)).exp()
+    });
+    let blur_axis = |values: &Array2<f32>, along_rows: bool| {
+        let (h, w) = values.dim();
+        Array2::from_shape_fn((h, w), |(y, x)| {
+            let (pos, len) = if along_rows { (x, w) } else { (y, h) };
+            let lo = pos.saturating_sub(SSIM_RADIUS);
+            let hi = (pos + SSIM_RADIUS).min(len - 1);
+            let (mut sum, mut weight) = (0.0, 0.0);
+            for i in lo..=hi {
+                let k = kernel[i + SSIM_RADIUS - pos];
+                sum += k * if along_rows {
+                    values[(y, i)]
+                } else {
+                    values[(i, x)]
+                };
+                weight += k;
+            }
+            sum / weight
+        })
+    };
+    blur_axis(&blur_axis(values, true), false)
+}
+
+fn correlation(a: &Array2<f32>, b: &Array2<f32>) -> f32 {
+    /*
+     * Pearson correlation. A flat image has no light and dark regions to keep: two flat images
+     * correlate fully, a flat one and a varied one not at all
+     */
+    let n = a.len() as f64;
+    let mean_a = a.iter().map(|&v| v as f64).sum::<f64>() / n;
+    let mean_b = b.iter().map(|&v| v as f64).sum::<f64>() / n;
+    let (mut cov, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+    for (&a, &b) in a.iter().zip(b.iter()) {
+        let (da, db) = (a as f64 - mean_a, b as f64 - mean_b);
+        cov += da * db;
+        var_a += da * da;
+        var_b += db * db;
+    }
+    // Deviations below a quarter of a luminance step count as flat
+    let flat = 1e-6 * n;
+    match (var_a < flat, var_b < flat) {
+        (true, true) => 1.0,
+        (true, false) | (false, true) => 0.0,
+        (false, false) => (cov / (var_a * var_b).sqrt()) as f32,
+    }
+}
@@ -1,4 +1,11 @@
+pub mod banded;
+pub mod color;
 pub mod edge_detect;
+pub mod edge_flow;
 pub mod edge_processor;
+pub mod fidelity;
+pub mod orientation;
+pub mod pipeline;
 pub mod processing;
+pub mod tile_stats;
 pub mod util;
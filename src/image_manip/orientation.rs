@@ -0,0 +1,34 @@
+use image::DynamicImage;
+use std::borrow::Cow;
+
+/*
+* Rotation or mirroring applied to the decoded image before the pipeline. Rotations are clockwise,
+* FlipH mirrors left to right and FlipV top to bottom
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Orientation {
+    #[default]
+    Normal,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    FlipH,
+    FlipV,
+}
+
+impl Orientation {
+    pub fn swaps_axes(&self) -> bool {
+        matches!(self, Orientation::Rotate90 | Orientation::Rotate270)
+    }
+
+    pub fn apply<'a>(&self, img: &'a DynamicImage) -> Cow<'a, DynamicImage> {
+        match self {
+            Orientation::Normal => Cow::Borrowed(img),
+            Orientation::Rotate90 => Cow::Owned(img.rotate90()),
+            Orientation::Rotate180 => Cow::Owned(img.rotate180()),
+            Orientation::Rotate270 => Cow::Owned(img.rotate270()),
+            Orientation::FlipH => Cow::Owned(img.fliph()),
+            Orientation::FlipV => Cow::Owned(img.flipv()),
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use super::processing::Processor;
+use crate::ascii::error::ConvertError;
+
+/*
+* Ordered preprocessor stages addressed by their names, the Processor::name of every stage, so a
+* stage of a default pipeline can be dropped or moved without building the others again. Names
+* repeat when a kind of stage runs twice, and a name then refers to its first stage
+*/
+#[derive(Default)]
+pub struct Pipeline {
+    stages: Vec<Box<dyn Processor<u8, u8>>>,
+}
+
+impl Pipeline {
+    pub fn new(stages: Vec<Box<dyn Processor<u8, u8>>>) -> Self {
+        Pipeline { stages }
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        self.stages.iter().map(|stage| stage.name()).collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.stages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    fn position(&self, name: &str) -> Result<usize, ConvertError> {
+        self.stages
+            .iter()
+            .position(|stage| stage.name() == name)
+            .ok_or_else(|| ConvertError::UnknownStage {
+                name: name.to_string(),
+                valid: self.names(),
+            })
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<Box<dyn Processor<u8, u8>>, ConvertError> {
+        // Take the first stage called name out of the pipeline
+        let index = self.position(name)?;
+        Ok(self.stages.remove(index))
+    }
+
+    pub fn push(&mut self, stage: Box<dyn Processor<u8, u8>>) {
+        self.stages.push(stage);
+    }
+
+    pub fn insert_before(
+        &mut self,
+        name: &str,
+        stage: Box<dyn Processor<u8, u8>>,
+    ) -> Result<(), ConvertError> {
+        // Run stage right before the first stage called name
+        let index = self.position(name)?;
+        self.stages.insert(index, stage);
+        Ok(())
+    }
+
+    pub fn move_before(&mut self, name: &str, before: &str) -> Result<(), ConvertError> {
+        // Run the first stage called name right before the first one called before
+        let from = self.position(name)?;
+        let to = self.position(before)?;
+        let stage = self.stages.remove(from);
+        // Taking the stage out shifts the ones after it back by one
+        self.stages
+            .insert(if from < to { to - 1 } else { to }, stage);
+        Ok(())
+    }
+
+    pub fn into_stages(self) -> Vec<Box<dyn Processor<u8, u8>>> {
+        self.stages
+    }
+}
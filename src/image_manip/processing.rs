@@ -1,39 +1,211 @@
 use super::util::{arr_to_bufr, bufr_to_arr};
 use crate::ascii::error::ConvertError;
 use image::{ImageBuffer, Luma, Primitive};
-use imageproc::contrast::{threshold, ThresholdType};
+use imageproc::contrast::{stretch_contrast, threshold, ThresholdType};
 use imageproc::filter::{
     bilateral_filter, gaussian_blur_f32, median_filter, sharpen3x3, sharpen_gaussian,
 };
+use ndarray::{Array2, Zip};
 use num_traits::Num;
 
 pub trait Processor<T: Num + Copy + Primitive, U: Copy + Num + Primitive> {
+    // Stable snake_case identifier of the processor
+    fn name(&self) -> &'static str;
+
+    // Parameters of the processor as name=value pairs separated by spaces, empty when it has none
+    fn params(&self) -> String {
+        String::new()
+    }
+
+    // Check the parameters before any image is processed, so a bad setting fails early with the
+    // name of the offending field
+    fn validate(&self) -> Result<(), ConvertError> {
+        Ok(())
+    }
+
+    // Rows and columns past an output pixel the processor reads, so an image processed in bands
+    // knows how much overlap every band needs. u32::MAX when any output pixel can depend on the
+    // whole image
+    fn border_radius(&self) -> u32 {
+        0
+    }
+
     fn apply(
         &self,
         bufr: &ImageBuffer<Luma<T>, Vec<T>>,
     ) -> Result<ImageBuffer<Luma<U>, Vec<U>>, ConvertError>;
 }
 
+fn check_positive(value: f32, field: &'static str) -> Result<(), ConvertError> {
+    // NaN fails the comparison as well
+    if value > 0.0 {
+        Ok(())
+    } else {
+        Err(ConvertError::InvalidSetting {
+            field,
+            reason: "must be positive",
+        })
+    }
+}
+
+fn gaussian_radius(sigma: f32) -> u32 {
+    // Radius of the kernel imageproc builds for gaussian_blur_f32
+    (2.0 * sigma).ceil() as u32
+}
+
+type F32Image = ImageBuffer<Luma<f32>, Vec<f32>>;
+
+fn to_f32(bufr: &ImageBuffer<Luma<u8>, Vec<u8>>) -> F32Image {
+    ImageBuffer::from_fn(bufr.width(), bufr.height(), |x, y| {
+        Luma([bufr.get_pixel(x, y)[0] as f32])
+    })
+}
+
+/*
+* How an f32 edge buffer is brought back to u8 before edge detection
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Normalization {
+    // Clamp to 0..255, which keeps the values a u8 pipeline would have produced
+    #[default]
+    Clamp,
+    // Clamp the magnitude, so negative responses count as much as positive ones
+    Abs,
+    // Stretch the magnitudes so the strongest response becomes 255, which lifts the weak
+    // responses of low contrast images
+    MinMax,
+}
+
+impl Normalization {
+    pub fn apply(&self, bufr: &F32Image) -> ImageBuffer<Luma<u8>, Vec<u8>> {
+        let scale = match self {
+            Normalization::MinMax => {
+                let max = bufr.iter().fold(0.0f32, |max, v| max.max(v.abs()));
+                if max > 0.0 {
+                    255.0 / max
+                } else {
+                    1.0
+                }
+            }
+            _ => 1.0,
+        };
+        ImageBuffer::from_fn(bufr.width(), bufr.height(), |x, y| {
+            let v = bufr.get_pixel(x, y)[0];
+            let v = match self {
+                Normalization::Clamp => v,
+                Normalization::Abs | Normalization::MinMax => v.abs() * scale,
+            };
+            Luma([v.round().clamp(0.0, 255.0) as u8])
+        })
+    }
+}
+
+/*
+* Tail of the edge pipeline that runs on f32 so values below 0 and above 255 survive between
+* stages. promote turns the u8 image into f32, the stages follow and the result is normalized
+* back to u8 for the edge detector
+*/
+pub struct F32Chain {
+    pub promote: Box<dyn Processor<u8, f32>>,
+    pub stages: Vec<Box<dyn Processor<f32, f32>>>,
+    pub normalization: Normalization,
+}
+
+impl F32Chain {
+    pub fn new(
+        promote: Box<dyn Processor<u8, f32>>,
+        stages: Vec<Box<dyn Processor<f32, f32>>>,
+        normalization: Normalization,
+    ) -> Self {
+        F32Chain {
+            promote,
+            stages,
+            normalization,
+        }
+    }
+
+    pub fn names(&self) -> Vec<&'static str> {
+        std::iter::once(self.promote.name())
+            .chain(self.stages.iter().map(|stage| stage.name()))
+            .collect()
+    }
+
+    pub fn validate(&self) -> Result<(), ConvertError> {
+        self.promote.validate()?;
+        for stage in self.stages.iter() {
+            stage.validate()?;
+        }
+        Ok(())
+    }
+
+    pub fn border_radius(&self) -> u32 {
+        self.stages
+            .iter()
+            .fold(self.promote.border_radius(), |sum, stage| {
+                sum.saturating_add(stage.border_radius())
+            })
+    }
+
+    pub fn apply(
+        &self,
+        bufr: &ImageBuffer<Luma<u8>, Vec<u8>>,
+    ) -> Result<ImageBuffer<Luma<u8>, Vec<u8>>, ConvertError> {
+        let mut out = self.promote.apply(bufr)?;
+        for stage in self.stages.iter() {
+            out = stage.apply(&out)?;
+        }
+        Ok(self.normalization.apply(&out))
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct DoG {
     pub sigma_1: f32,
     pub sigma_2: f32,
 }
 
-impl DoG {
-    pub fn default() -> Self {
+impl Default for DoG {
+    fn default() -> Self {
         DoG {
             sigma_1: 1.0,
             sigma_2: 3.5,
         }
     }
+}
 
+impl DoG {
     pub fn new(sigma_1: f32, sigma_2: f32) -> Self {
         DoG { sigma_1, sigma_2 }
     }
 }
 
 impl Processor<u8, u8> for DoG {
+    fn name(&self) -> &'static str {
+        "dog"
+    }
+
+    fn params(&self) -> String {
+        format!("sigma_1={} sigma_2={}", self.sigma_1, self.sigma_2)
+    }
+
+    fn validate(&self) -> Result<(), ConvertError> {
+        check_positive(self.sigma_1, "dog.sigma_1")?;
+        // The wider blur must come second or the difference removes the edges it should find
+        if self.sigma_2 > self.sigma_1 {
+            Ok(())
+        } else {
+            Err(ConvertError::InvalidSetting {
+                field: "dog.sigma_2",
+                reason: "must be larger than sigma_1",
+            })
+        }
+    }
+
+    fn border_radius(&self) -> u32 {
+        // The wider of the two blurs decides, imageproc cuts its kernels at 2 sigma
+        gaussian_radius(self.sigma_1.max(self.sigma_2))
+    }
+
     fn apply(
         &self,
         bufr: &ImageBuffer<Luma<u8>, Vec<u8>>,
@@ -52,30 +224,133 @@ impl Processor<u8, u8> for DoG {
         let dog_arr = &blur_2_arr - &blur_1_arr;
 
         // Convert to u8 and handle saturation
-        Ok(arr_to_bufr(&dog_arr.mapv(|x| x.max(0).min(255) as u8)))
+        Ok(arr_to_bufr(&dog_arr.mapv(|x| x.clamp(0, 255) as u8)))
     }
 }
 
+impl Processor<f32, f32> for DoG {
+    fn name(&self) -> &'static str {
+        "dog"
+    }
+
+    fn params(&self) -> String {
+        format!("sigma_1={} sigma_2={}", self.sigma_1, self.sigma_2)
+    }
+
+    fn validate(&self) -> Result<(), ConvertError> {
+        Processor::<u8, u8>::validate(self)
+    }
+
+    fn border_radius(&self) -> u32 {
+        Processor::<u8, u8>::border_radius(self)
+    }
+
+    fn apply(&self, bufr: &F32Image) -> Result<F32Image, ConvertError> {
+        /*
+         * Difference of gaussian without rounding or clamping, so negative responses are kept
+         */
+        let blur_1 = gaussian_blur_f32(bufr, self.sigma_1);
+        let mut out = gaussian_blur_f32(bufr, self.sigma_2);
+        for (v, b) in out.iter_mut().zip(blur_1.iter()) {
+            *v -= b;
+        }
+        Ok(out)
+    }
+}
+
+impl Processor<u8, f32> for DoG {
+    fn name(&self) -> &'static str {
+        "dog"
+    }
+
+    fn params(&self) -> String {
+        format!("sigma_1={} sigma_2={}", self.sigma_1, self.sigma_2)
+    }
+
+    fn validate(&self) -> Result<(), ConvertError> {
+        Processor::<u8, u8>::validate(self)
+    }
+
+    fn border_radius(&self) -> u32 {
+        Processor::<u8, u8>::border_radius(self)
+    }
+
+    fn apply(&self, bufr: &ImageBuffer<Luma<u8>, Vec<u8>>) -> Result<F32Image, ConvertError> {
+        Processor::<f32, f32>::apply(self, &to_f32(bufr))
+    }
+}
+
+// Largest median radius and bilateral window accepted, beyond it the filters take so long that a
+// typo is far more likely than intent
+pub const MAX_KERNEL_SIZE: u32 = 50;
+
+fn check_kernel_size(size: u32, field: &'static str) -> Result<(), ConvertError> {
+    if size > MAX_KERNEL_SIZE {
+        return Err(ConvertError::InvalidSetting {
+            field,
+            reason: "must be at most 50",
+        });
+    }
+    Ok(())
+}
+
+/*
+* Median over a (2 * x_radius + 1) x (2 * y_radius + 1) window. A radius of 0 on one axis filters
+* along the other only, e.g. a horizontal median for scanline noise
+*/
 pub struct MedianBlur {
-    pub kernel_size: u32,
+    pub x_radius: u32,
+    pub y_radius: u32,
 }
 
-impl MedianBlur {
-    pub fn default() -> Self {
-        MedianBlur { kernel_size: 2 }
+impl Default for MedianBlur {
+    fn default() -> Self {
+        MedianBlur::new(2)
     }
+}
 
+impl MedianBlur {
     pub fn new(kernel_size: u32) -> Self {
-        MedianBlur { kernel_size }
+        MedianBlur::asymmetric(kernel_size, kernel_size)
+    }
+
+    pub fn asymmetric(x_radius: u32, y_radius: u32) -> Self {
+        MedianBlur { x_radius, y_radius }
     }
 }
 
 impl Processor<u8, u8> for MedianBlur {
+    fn name(&self) -> &'static str {
+        "median_blur"
+    }
+
+    fn params(&self) -> String {
+        format!("x_radius={} y_radius={}", self.x_radius, self.y_radius)
+    }
+
+    fn validate(&self) -> Result<(), ConvertError> {
+        if self.x_radius == 0 && self.y_radius == 0 {
+            return Err(ConvertError::InvalidSetting {
+                field: "median_blur.kernel_size",
+                reason: "must be at least 1 along one axis",
+            });
+        }
+        check_kernel_size(self.x_radius, "median_blur.x_radius")?;
+        check_kernel_size(self.y_radius, "median_blur.y_radius")
+    }
+
+    fn border_radius(&self) -> u32 {
+        // median_filter takes the kernel radius rather than its width
+        self.x_radius.max(self.y_radius)
+    }
+
     fn apply(
         &self,
         bufr: &ImageBuffer<Luma<u8>, Vec<u8>>,
     ) -> Result<ImageBuffer<Luma<u8>, Vec<u8>>, ConvertError> {
-        Ok(median_filter(bufr, self.kernel_size, self.kernel_size))
+        // Checked here too since a bad radius makes imageproc panic or run for minutes
+        self.validate()?;
+        Ok(median_filter(bufr, self.x_radius, self.y_radius))
     }
 }
 
@@ -85,15 +360,17 @@ pub struct BilateralFilter {
     pub sigma_spatial: f32,
 }
 
-impl BilateralFilter {
-    pub fn default() -> Self {
+impl Default for BilateralFilter {
+    fn default() -> Self {
         BilateralFilter {
             window_size: 10,
             sigma_color: 2.0,
             sigma_spatial: 5.0,
         }
     }
+}
 
+impl BilateralFilter {
     pub fn new(window_size: u32, sigma_color: f32, sigma_spatial: f32) -> Self {
         BilateralFilter {
             window_size,
@@ -104,10 +381,40 @@ impl BilateralFilter {
 }
 
 impl Processor<u8, u8> for BilateralFilter {
+    fn name(&self) -> &'static str {
+        "bilateral_filter"
+    }
+
+    fn params(&self) -> String {
+        format!(
+            "window_size={} sigma_color={} sigma_spatial={}",
+            self.window_size, self.sigma_color, self.sigma_spatial
+        )
+    }
+
+    fn validate(&self) -> Result<(), ConvertError> {
+        if self.window_size == 0 {
+            return Err(ConvertError::InvalidSetting {
+                field: "bilateral_filter.window_size",
+                reason: "must be at least 1",
+            });
+        }
+        check_kernel_size(self.window_size, "bilateral_filter.window_size")?;
+        check_positive(self.sigma_color, "bilateral_filter.sigma_color")?;
+        check_positive(self.sigma_spatial, "bilateral_filter.sigma_spatial")
+    }
+
+    fn border_radius(&self) -> u32 {
+        // The window itself only reaches (size - 1) / 2 pixels, but imageproc scales the color
+        // weights by the brightest pixel of the whole image
+        u32::MAX
+    }
+
     fn apply(
         &self,
         bufr: &ImageBuffer<Luma<u8>, Vec<u8>>,
     ) -> Result<ImageBuffer<Luma<u8>, Vec<u8>>, ConvertError> {
+        self.validate()?;
         Ok(bilateral_filter(
             bufr,
             self.window_size,
@@ -117,35 +424,192 @@ impl Processor<u8, u8> for BilateralFilter {
     }
 }
 
+/*
+* How Threshold treats pixels. The first five are the imageproc ThresholdType values, which
+* compare each pixel against the threshold. They behave as imageproc implements them, where ToZero
+* zeroes the pixels above the threshold and ToZeroInverted, the default, the ones at or below it.
+* Band ignores the threshold and zeroes every pixel outside low..=high
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ThresholdMode {
+    Binary,
+    BinaryInverted,
+    Truncate,
+    ToZero,
+    #[default]
+    ToZeroInverted,
+    Band {
+        low: u8,
+        high: u8,
+    },
+}
+
+impl ThresholdMode {
+    pub fn threshold_type(&self) -> Option<ThresholdType> {
+        // None for the modes imageproc has no equivalent of
+        match self {
+            ThresholdMode::Binary => Some(ThresholdType::Binary),
+            ThresholdMode::BinaryInverted => Some(ThresholdType::BinaryInverted),
+            ThresholdMode::Truncate => Some(ThresholdType::Truncate),
+            ThresholdMode::ToZero => Some(ThresholdType::ToZero),
+            ThresholdMode::ToZeroInverted => Some(ThresholdType::ToZeroInverted),
+            ThresholdMode::Band { .. } => None,
+        }
+    }
+}
+
 pub struct Threshold {
     pub threshold: u8,
+    pub mode: ThresholdMode,
+}
+
+impl Default for Threshold {
+    fn default() -> Self {
+        Threshold::new(10)
+    }
 }
 
 impl Threshold {
     pub fn new(threshold: u8) -> Self {
-        Threshold { threshold }
+        Threshold {
+            threshold,
+            mode: ThresholdMode::default(),
+        }
+    }
+
+    pub fn binary(threshold: u8) -> Self {
+        Threshold::new(threshold).with_mode(ThresholdMode::Binary)
+    }
+
+    pub fn band(low: u8, high: u8) -> Self {
+        Threshold::new(0).with_mode(ThresholdMode::Band { low, high })
     }
 
-    pub fn default() -> Self {
-        Threshold { threshold: 10 }
+    pub fn with_mode(mut self, mode: ThresholdMode) -> Self {
+        self.mode = mode;
+        self
     }
 }
 
 impl Processor<u8, u8> for Threshold {
+    fn name(&self) -> &'static str {
+        "threshold"
+    }
+
+    fn params(&self) -> String {
+        format!("threshold={} mode={:?}", self.threshold, self.mode)
+    }
+
+    fn validate(&self) -> Result<(), ConvertError> {
+        if let ThresholdMode::Band { low, high } = self.mode {
+            if low > high {
+                return Err(ConvertError::InvalidSetting {
+                    field: "threshold.band",
+                    reason: "low must not be above high",
+                });
+            }
+        }
+        Ok(())
+    }
+
     fn apply(
         &self,
         bufr: &ImageBuffer<Luma<u8>, Vec<u8>>,
     ) -> Result<ImageBuffer<Luma<u8>, Vec<u8>>, ConvertError> {
-        Ok(threshold(
-            bufr,
-            self.threshold,
-            ThresholdType::ToZeroInverted,
-        ))
+        match self.mode {
+            ThresholdMode::Band { low, high } => {
+                let mut out = bufr.clone();
+                for v in out.iter_mut() {
+                    if !(low..=high).contains(v) {
+                        *v = 0;
+                    }
+                }
+                Ok(out)
+            }
+            mode => {
+                let threshold_type = mode
+                    .threshold_type()
+                    .expect("every mode but Band has an imageproc threshold type");
+                Ok(threshold(bufr, self.threshold, threshold_type))
+            }
+        }
+    }
+}
+
+impl Threshold {
+    fn validate_f32(&self) -> Result<(), ConvertError> {
+        match self.mode {
+            ThresholdMode::ToZeroInverted | ThresholdMode::Band { .. } => {
+                Processor::<u8, u8>::validate(self)
+            }
+            _ => Err(ConvertError::InvalidSetting {
+                field: "threshold.mode",
+                reason: "only to_zero_inverted and band run on f32",
+            }),
+        }
+    }
+}
+
+impl Processor<f32, f32> for Threshold {
+    fn name(&self) -> &'static str {
+        "threshold"
+    }
+
+    fn params(&self) -> String {
+        format!("threshold={} mode={:?}", self.threshold, self.mode)
+    }
+
+    fn validate(&self) -> Result<(), ConvertError> {
+        self.validate_f32()
+    }
+
+    fn apply(&self, bufr: &F32Image) -> Result<F32Image, ConvertError> {
+        /*
+         * Keep the values whose magnitude passes the threshold, or falls in the band, negative
+         * ones included
+         */
+        self.validate_f32()?;
+        let mut out = bufr.clone();
+        let threshold = self.threshold as f32;
+        for v in out.iter_mut() {
+            let keep = match self.mode {
+                ThresholdMode::Band { low, high } => (low as f32..=high as f32).contains(&v.abs()),
+                _ => v.abs() > threshold,
+            };
+            if !keep {
+                *v = 0.0;
+            }
+        }
+        Ok(out)
+    }
+}
+
+impl Processor<u8, f32> for Threshold {
+    fn name(&self) -> &'static str {
+        "threshold"
+    }
+
+    fn params(&self) -> String {
+        format!("threshold={} mode={:?}", self.threshold, self.mode)
+    }
+
+    fn validate(&self) -> Result<(), ConvertError> {
+        self.validate_f32()
+    }
+
+    fn apply(&self, bufr: &ImageBuffer<Luma<u8>, Vec<u8>>) -> Result<F32Image, ConvertError> {
+        Processor::<f32, f32>::apply(self, &to_f32(bufr))
     }
 }
 
 pub struct Sharpen3x3 {}
 
+impl Default for Sharpen3x3 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Sharpen3x3 {
     pub fn new() -> Self {
         Sharpen3x3 {}
@@ -153,6 +617,14 @@ impl Sharpen3x3 {
 }
 
 impl Processor<u8, u8> for Sharpen3x3 {
+    fn name(&self) -> &'static str {
+        "sharpen_3x3"
+    }
+
+    fn border_radius(&self) -> u32 {
+        1
+    }
+
     fn apply(
         &self,
         bufr: &ImageBuffer<Luma<u8>, Vec<u8>>,
@@ -166,20 +638,38 @@ pub struct SharpenGaussian {
     pub amount: f32,
 }
 
-impl SharpenGaussian {
-    pub fn default() -> Self {
+impl Default for SharpenGaussian {
+    fn default() -> Self {
         SharpenGaussian {
             sigma: 1.0,
             amount: 1.0,
         }
     }
+}
 
+impl SharpenGaussian {
     pub fn new(sigma: f32, amount: f32) -> Self {
         SharpenGaussian { sigma, amount }
     }
 }
 
 impl Processor<u8, u8> for SharpenGaussian {
+    fn name(&self) -> &'static str {
+        "sharpen_gaussian"
+    }
+
+    fn params(&self) -> String {
+        format!("sigma={} amount={}", self.sigma, self.amount)
+    }
+
+    fn validate(&self) -> Result<(), ConvertError> {
+        check_positive(self.sigma, "sharpen_gaussian.sigma")
+    }
+
+    fn border_radius(&self) -> u32 {
+        gaussian_radius(self.sigma)
+    }
+
     fn apply(
         &self,
         bufr: &ImageBuffer<Luma<u8>, Vec<u8>>,
@@ -187,3 +677,326 @@ impl Processor<u8, u8> for SharpenGaussian {
         Ok(sharpen_gaussian(bufr, self.sigma, self.amount))
     }
 }
+
+impl Processor<f32, f32> for SharpenGaussian {
+    fn name(&self) -> &'static str {
+        "sharpen_gaussian"
+    }
+
+    fn params(&self) -> String {
+        format!("sigma={} amount={}", self.sigma, self.amount)
+    }
+
+    fn validate(&self) -> Result<(), ConvertError> {
+        Processor::<u8, u8>::validate(self)
+    }
+
+    fn border_radius(&self) -> u32 {
+        Processor::<u8, u8>::border_radius(self)
+    }
+
+    fn apply(&self, bufr: &F32Image) -> Result<F32Image, ConvertError> {
+        // Same unsharp mask as imageproc's sharpen_gaussian, without clamping the result
+        let smooth = gaussian_blur_f32(bufr, self.sigma);
+        let mut out = bufr.clone();
+        for (v, s) in out.iter_mut().zip(smooth.iter()) {
+            *v = (1.0 + self.amount) * *v - self.amount * s;
+        }
+        Ok(out)
+    }
+}
+
+impl Processor<u8, f32> for SharpenGaussian {
+    fn name(&self) -> &'static str {
+        "sharpen_gaussian"
+    }
+
+    fn params(&self) -> String {
+        format!("sigma={} amount={}", self.sigma, self.amount)
+    }
+
+    fn validate(&self) -> Result<(), ConvertError> {
+        Processor::<u8, u8>::validate(self)
+    }
+
+    fn border_radius(&self) -> u32 {
+        Processor::<u8, u8>::border_radius(self)
+    }
+
+    fn apply(&self, bufr: &ImageBuffer<Luma<u8>, Vec<u8>>) -> Result<F32Image, ConvertError> {
+        Processor::<f32, f32>::apply(self, &to_f32(bufr))
+    }
+}
+
+/*
+* Zhang-Suen thinning of the non-zero pixels down to 1 pixel wide skeletons that keep their
+* connectivity. Meant for the end of the edge preprocessing chain, so thick edge bands turn into a
+* single row of edge characters
+*/
+pub struct Thin {}
+
+impl Default for Thin {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Thin {
+    pub fn new() -> Self {
+        Thin {}
+    }
+}
+
+impl Processor<u8, u8> for Thin {
+    fn name(&self) -> &'static str {
+        "thin"
+    }
+
+    fn border_radius(&self) -> u32 {
+        // Deletions can cascade along a stroke, so any pixel can depend on any other
+        u32::MAX
+    }
+
+    fn apply(
+        &self,
+        bufr: &ImageBuffer<Luma<u8>, Vec<u8>>,
+    ) -> Result<ImageBuffer<Luma<u8>, Vec<u8>>, ConvertError> {
+        let arr = bufr_to_arr(bufr);
+        let (h, w) = arr.dim();
+        let mut fg = arr.mapv(|x| x != 0);
+
+        // Pixels outside the image count as background
+        let at = |fg: &Array2<bool>, y: isize, x: isize| -> bool {
+            y >= 0 && x >= 0 && (y as usize) < h && (x as usize) < w && fg[(y as usize, x as usize)]
+        };
+
+        // Every pass deletes at least one pixel or ends the loop, so it always terminates
+        loop {
+            let mut changed = false;
+            for step in 0..2 {
+                let mut to_delete = vec![];
+                for y in 0..h {
+                    for x in 0..w {
+                        if !fg[(y, x)] {
+                            continue;
+                        }
+                        let (yi, xi) = (y as isize, x as isize);
+                        // Neighbors clockwise from north: P2..P9
+                        let p = [
+                            at(&fg, yi - 1, xi),
+                            at(&fg, yi - 1, xi + 1),
+                            at(&fg, yi, xi + 1),
+                            at(&fg, yi + 1, xi + 1),
+                            at(&fg, yi + 1, xi),
+                            at(&fg, yi + 1, xi - 1),
+                            at(&fg, yi, xi - 1),
+                            at(&fg, yi - 1, xi - 1),
+                        ];
+                        let neighbors = p.iter().filter(|&&v| v).count();
+                        let transitions = (0..8).filter(|&i| !p[i] && p[(i + 1) % 8]).count();
+                        let (p2, p4, p6, p8) = (p[0], p[2], p[4], p[6]);
+                        let removable = if step == 0 {
+                            // P2 * P4 * P6 == 0 and P4 * P6 * P8 == 0
+                            !p4 || !p6 || !p2 && !p8
+                        } else {
+                            // P2 * P4 * P8 == 0 and P2 * P6 * P8 == 0
+                            !p2 || !p8 || !p4 && !p6
+                        };
+                        if (2..=6).contains(&neighbors) && transitions == 1 && removable {
+                            to_delete.push((y, x));
+                        }
+                    }
+                }
+                changed |= !to_delete.is_empty();
+                for idx in to_delete {
+                    fg[idx] = false;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        // Skeleton pixels keep their original values
+        let mut out = arr;
+        Zip::from(&mut out).and(&fg).for_each(|val, &keep| {
+            if !keep {
+                *val = 0;
+            }
+        });
+        Ok(arr_to_bufr(&out))
+    }
+}
+
+pub fn clipped_range(bufr: &ImageBuffer<Luma<u8>, Vec<u8>>, clip: f32) -> (u8, u8) {
+    /*
+     * Darkest and brightest value left once the clip fraction of the darkest and of the
+     * brightest pixels is set aside
+     */
+    let mut histogram = [0usize; 256];
+    for &v in bufr.iter() {
+        histogram[v as usize] += 1;
+    }
+    let cut = (bufr.len() as f32 * clip) as usize;
+    let mut seen = 0;
+    let low = histogram
+        .iter()
+        .position(|&count| {
+            seen += count;
+            seen > cut
+        })
+        .unwrap_or(0);
+    seen = 0;
+    let high = 255
+        - histogram
+            .iter()
+            .rev()
+            .position(|&count| {
+                seen += count;
+                seen > cut
+            })
+            .unwrap_or(0);
+    (low as u8, high.max(low) as u8)
+}
+
+/*
+* Stretches the luminance range left after clipping the clip fraction of the darkest and of the
+* brightest pixels to the full 0..=255, so low contrast images use the whole ramp. Flat images are
+* left as they are
+*/
+pub struct AutoLevels {
+    pub clip: f32,
+}
+
+impl Default for AutoLevels {
+    fn default() -> Self {
+        AutoLevels { clip: 0.01 }
+    }
+}
+
+impl AutoLevels {
+    pub fn new(clip: f32) -> Self {
+        AutoLevels { clip }
+    }
+}
+
+impl Processor<u8, u8> for AutoLevels {
+    fn name(&self) -> &'static str {
+        "auto_levels"
+    }
+
+    fn params(&self) -> String {
+        format!("clip={}", self.clip)
+    }
+
+    fn validate(&self) -> Result<(), ConvertError> {
+        if (0.0..0.5).contains(&self.clip) {
+            Ok(())
+        } else {
+            Err(ConvertError::InvalidSetting {
+                field: "auto_levels.clip",
+                reason: "must be at least 0 and below 0.5",
+            })
+        }
+    }
+
+    fn border_radius(&self) -> u32 {
+        // The range comes from the histogram of the whole image
+        u32::MAX
+    }
+
+    fn apply(
+        &self,
+        bufr: &ImageBuffer<Luma<u8>, Vec<u8>>,
+    ) -> Result<ImageBuffer<Luma<u8>, Vec<u8>>, ConvertError> {
+        let (low, high) = clipped_range(bufr, self.clip);
+        if low < high {
+            Ok(stretch_contrast(bufr, low, high, 0, 255))
+        } else {
+            Ok(bufr.clone())
+        }
+    }
+}
+
+/*
+* Marks pixels darker than the mean of their (2 * block_radius + 1) square block by more than
+* offset as 255 and everything else as 0, so dark strokes on unevenly lit paper come out as clean
+* foreground for Thin and the edge detector
+*/
+pub struct AdaptiveThreshold {
+    pub block_radius: u32,
+    pub offset: u8,
+}
+
+impl Default for AdaptiveThreshold {
+    fn default() -> Self {
+        AdaptiveThreshold {
+            block_radius: 7,
+            offset: 12,
+        }
+    }
+}
+
+impl AdaptiveThreshold {
+    pub fn new(block_radius: u32, offset: u8) -> Self {
+        AdaptiveThreshold {
+            block_radius,
+            offset,
+        }
+    }
+}
+
+impl Processor<u8, u8> for AdaptiveThreshold {
+    fn name(&self) -> &'static str {
+        "adaptive_threshold"
+    }
+
+    fn params(&self) -> String {
+        format!("block_radius={} offset={}", self.block_radius, self.offset)
+    }
+
+    fn validate(&self) -> Result<(), ConvertError> {
+        if self.block_radius == 0 {
+            return Err(ConvertError::InvalidSetting {
+                field: "adaptive_threshold.block_radius",
+                reason: "must be at least 1",
+            });
+        }
+        Ok(())
+    }
+
+    fn border_radius(&self) -> u32 {
+        self.block_radius
+    }
+
+    fn apply(
+        &self,
+        bufr: &ImageBuffer<Luma<u8>, Vec<u8>>,
+    ) -> Result<ImageBuffer<Luma<u8>, Vec<u8>>, ConvertError> {
+        let arr = bufr_to_arr(bufr);
+        let (h, w) = arr.dim();
+        let mut integral = Array2::<u64>::zeros((h + 1, w + 1));
+        for y in 0..h {
+            let mut row_sum = 0;
+            for x in 0..w {
+                row_sum += arr[(y, x)] as u64;
+                integral[(y + 1, x + 1)] = integral[(y, x + 1)] + row_sum;
+            }
+        }
+
+        let r = self.block_radius as usize;
+        let out = Array2::from_shape_fn((h, w), |(y, x)| {
+            let (y0, x0) = (y.saturating_sub(r), x.saturating_sub(r));
+            let (y1, x1) = ((y + r + 1).min(h), (x + r + 1).min(w));
+            let sum =
+                integral[(y1, x1)] + integral[(y0, x0)] - integral[(y0, x1)] - integral[(y1, x0)];
+            let mean = sum / ((y1 - y0) * (x1 - x0)) as u64;
+            if (arr[(y, x)] as u64 + self.offset as u64) < mean {
+                255
+            } else {
+                0
+            }
+        });
+        Ok(arr_to_bufr(&out))
+    }
+}
@@ -0,0 +1,142 @@
+use super::util::arr_to_bufr;
+use image::GrayImage;
+use ndarray::Array2;
+
+/*
+* How the tile pipeline brings the image down to one pixel per cell. Resize uses resize_exact with
+* the Triangle filter, ExactBoxAverage averages the font_size x font_size block of original pixels
+* under each cell, with the tile preprocessors run at the original resolution beforehand.
+* TrimmedMean works on the same blocks but first drops the trim fraction of darkest and brightest
+* pixels, so lone hot pixels do not light up a dark cell. A trim of 0.0 is the plain mean
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum TileSampling {
+    #[default]
+    Resize,
+    ExactBoxAverage,
+    TrimmedMean {
+        trim: f32,
+    },
+}
+
+impl TileSampling {
+    pub fn at_original_resolution(&self) -> bool {
+        !matches!(self, TileSampling::Resize)
+    }
+}
+
+pub fn box_average(gray: &GrayImage, block: usize, grid: (usize, usize)) -> GrayImage {
+    /*
+     * Mean of every block x block square of the image, cut short at the image border. Sums come
+     * from an integral image so the cost does not depend on the block size
+     */
+    box_average_rect(gray, (block, block), grid)
+}
+
+pub fn box_average_rect(
+    gray: &GrayImage,
+    (block_h, block_w): (usize, usize),
+    (rows, cols): (usize, usize),
+) -> GrayImage {
+    // box_average over blocks of block_h x block_w pixels, for cells that are not square
+    let (w, h) = (gray.width() as usize, gray.height() as usize);
+    let mut integral = Array2::<u64>::zeros((h + 1, w + 1));
+    for y in 0..h {
+        let mut row_sum = 0;
+        for x in 0..w {
+            row_sum += gray.get_pixel(x as u32, y as u32)[0] as u64;
+            integral[(y + 1, x + 1)] = integral[(y, x + 1)] + row_sum;
+        }
+    }
+
+    let averaged = Array2::from_shape_fn((rows, cols), |(y, x)| {
+        let (y0, x0) = ((y * block_h).min(h - 1), (x * block_w).min(w - 1));
+        let (y1, x1) = (((y + 1) * block_h).min(h), ((x + 1) * block_w).min(w));
+        let sum = integral[(y1, x1)] + integral[(y0, x0)] - integral[(y0, x1)] - integral[(y1, x0)];
+        let n = ((y1 - y0) * (x1 - x0)) as u64;
+        ((sum + n / 2) / n) as u8
+    });
+    arr_to_bufr(&averaged)
+}
+
+pub fn trimmed_mean(gray: &GrayImage, block: usize, grid: (usize, usize), trim: f32) -> GrayImage {
+    /*
+     * Mean of every block x block square of the image, cut short at the image border, after
+     * discarding floor(trim * n) of the lowest and of the highest of its n pixels. Blocks are
+     * counted into a histogram so the trimming needs no sorting
+     */
+    trimmed_mean_rect(gray, (block, block), grid, trim)
+}
+
+pub fn trimmed_mean_rect(
+    gray: &GrayImage,
+    (block_h, block_w): (usize, usize),
+    (rows, cols): (usize, usize),
+    trim: f32,
+) -> GrayImage {
+    // trimmed_mean over blocks of block_h x block_w pixels, for cells that are not square
+    let (w, h) = (gray.width() as usize, gray.height() as usize);
+    let averaged = Array2::from_shape_fn((rows, cols), |(y, x)| {
+        let (y0, x0) = ((y * block_h).min(h - 1), (x * block_w).min(w - 1));
+        let (y1, x1) = (((y + 1) * block_h).min(h), ((x + 1) * block_w).min(w));
+        let mut histogram = [0usize; 256];
+        for py in y0..y1 {
+            for px in x0..x1 {
+                histogram[gray.get_pixel(px as u32, py as u32)[0] as usize] += 1;
+            }
+        }
+
+        let n = (y1 - y0) * (x1 - x0);
+        let cut = ((n as f32 * trim) as usize).min((n - 1) / 2);
+        // Walk the histogram keeping only the ranks cut..n - cut
+        let (mut rank, mut sum) = (0, 0);
+        for (value, &count) in histogram.iter().enumerate() {
+            let kept = (rank + count).min(n - cut).saturating_sub(rank.max(cut));
+            sum += kept * value;
+            rank += count;
+        }
+        let kept = n - 2 * cut;
+        ((sum + kept / 2) / kept) as u8
+    });
+    arr_to_bufr(&averaged)
+}
+
+/*
+* Luminance mean and variance, scaled to 0..=1, of the original pixels every grid cell covers.
+* The variance ranges from 0 for a flat tile to 0.25 for an even black and white split
+*/
+pub struct TileStats {
+    pub mean: Array2<f32>,
+    pub variance: Array2<f32>,
+}
+
+impl TileStats {
+    pub fn new(gray: &GrayImage, (rows, cols): (usize, usize)) -> Self {
+        let (w, h) = (gray.width() as usize, gray.height() as usize);
+        let mut mean = Array2::zeros((rows, cols));
+        let mut variance = Array2::zeros((rows, cols));
+        for y in 0..rows {
+            for x in 0..cols {
+                // Same split of the image as the grid, every cell covers at least one pixel
+                let (y0, x0) = ((y * h / rows).min(h - 1), (x * w / cols).min(w - 1));
+                let y1 = ((y + 1) * h / rows).clamp(y0 + 1, h);
+                let x1 = ((x + 1) * w / cols).clamp(x0 + 1, w);
+
+                let mut sum = 0.0;
+                let mut sum_sq = 0.0;
+                for py in y0..y1 {
+                    for px in x0..x1 {
+                        let luma = gray.get_pixel(px as u32, py as u32)[0] as f32 / 255.0;
+                        sum += luma;
+                        sum_sq += luma * luma;
+                    }
+                }
+                let n = ((y1 - y0) * (x1 - x0)) as f32;
+                let m = sum / n;
+                mean[(y, x)] = m;
+                variance[(y, x)] = (sum_sq / n - m * m).max(0.0);
+            }
+        }
+        TileStats { mean, variance }
+    }
+}
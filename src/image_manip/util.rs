@@ -1,19 +1,198 @@
-use image::{ImageBuffer, Luma, Primitive};
-use ndarray::{Array, Array2};
+use crate::ascii::error::ConvertError;
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageBuffer, Luma, Primitive, RgbImage};
+use ndarray::{Array, Array2, Array3, ArrayView2, ArrayView3, ArrayViewMut2, ArrayViewMut3};
 use num_traits::Num;
 
+/*
+* Conversions between image buffers and ndarrays. Arrays are indexed as (row, column) for Luma and
+* (row, column, channel) for Rgb. The try_ versions report a shape mismatch as an error, the
+* others panic on one, which can only happen for an Rgb array without exactly 3 channels
+*/
+
+fn pixels<T>(raw: &[T], w: u32, h: u32, channels: usize) -> &[T] {
+    // The raw container of a buffer may be longer than its pixels
+    &raw[..w as usize * h as usize * channels]
+}
+
+fn pixels_mut<T>(raw: &mut [T], w: u32, h: u32, channels: usize) -> &mut [T] {
+    &mut raw[..w as usize * h as usize * channels]
+}
+
+pub fn try_bufr_to_arr<T: Num + Copy + 'static + Primitive>(
+    bufr: &ImageBuffer<Luma<T>, Vec<T>>,
+) -> Result<Array2<T>, ConvertError> {
+    let (w, h) = bufr.dimensions();
+    Ok(Array::from_shape_vec(
+        (h as usize, w as usize),
+        pixels(bufr.as_raw(), w, h, 1).to_vec(),
+    )?)
+}
+
 pub fn bufr_to_arr<T: Num + Copy + 'static + Primitive>(
     bufr: &ImageBuffer<Luma<T>, Vec<T>>,
 ) -> Array2<T> {
-    let (w, h) = bufr.dimensions();
-    let raw = bufr.clone().into_raw(); // If performance is paramount, then don't clone
-    Array::from_shape_vec((h as usize, w as usize), raw).unwrap()
+    try_bufr_to_arr(bufr).expect("Luma buffer has one value per pixel")
 }
 
-pub fn arr_to_bufr<T: Copy + Num + 'static + Primitive>(
+pub fn try_arr_to_bufr<T: Copy + Num + 'static + Primitive>(
     arr: &Array2<T>,
-) -> ImageBuffer<Luma<T>, Vec<T>> {
+) -> Result<ImageBuffer<Luma<T>, Vec<T>>, ConvertError> {
+    // Iterating keeps the logical order, so non-contiguous arrays come out right too
     let (h, w) = arr.dim();
     let raw: Vec<T> = arr.iter().cloned().collect();
-    ImageBuffer::from_raw(w as u32, h as u32, raw).unwrap()
+    ImageBuffer::from_raw(w as u32, h as u32, raw).ok_or(ConvertError::NdArrayShapeError)
+}
+
+pub fn arr_to_bufr<T: Copy + Num + 'static + Primitive>(
+    arr: &Array2<T>,
+) -> ImageBuffer<Luma<T>, Vec<T>> {
+    try_arr_to_bufr(arr).expect("Array2 has one value per pixel")
+}
+
+pub fn bufr_view<T: Primitive>(bufr: &ImageBuffer<Luma<T>, Vec<T>>) -> ArrayView2<'_, T> {
+    /*
+     * Borrow the pixels of a buffer as an array without copying them
+     */
+    let (w, h) = bufr.dimensions();
+    ArrayView2::from_shape((h as usize, w as usize), pixels(bufr.as_raw(), w, h, 1))
+        .expect("Luma buffer has one value per pixel")
+}
+
+pub fn bufr_view_mut<T: Primitive>(
+    bufr: &mut ImageBuffer<Luma<T>, Vec<T>>,
+) -> ArrayViewMut2<'_, T> {
+    let (w, h) = bufr.dimensions();
+    ArrayViewMut2::from_shape((h as usize, w as usize), pixels_mut(bufr, w, h, 1))
+        .expect("Luma buffer has one value per pixel")
+}
+
+pub fn try_rgb_bufr_to_arr3(bufr: &RgbImage) -> Result<Array3<u8>, ConvertError> {
+    let (w, h) = bufr.dimensions();
+    Ok(Array::from_shape_vec(
+        (h as usize, w as usize, 3),
+        pixels(bufr.as_raw(), w, h, 3).to_vec(),
+    )?)
+}
+
+pub fn rgb_bufr_to_arr3(bufr: &RgbImage) -> Array3<u8> {
+    try_rgb_bufr_to_arr3(bufr).expect("Rgb buffer has three values per pixel")
+}
+
+pub fn try_arr3_to_rgb_bufr(arr: &Array3<u8>) -> Result<RgbImage, ConvertError> {
+    let (h, w, c) = arr.dim();
+    if c != 3 {
+        return Err(ConvertError::NdArrayShapeError);
+    }
+    let raw: Vec<u8> = arr.iter().cloned().collect();
+    ImageBuffer::from_raw(w as u32, h as u32, raw).ok_or(ConvertError::NdArrayShapeError)
+}
+
+pub fn arr3_to_rgb_bufr(arr: &Array3<u8>) -> RgbImage {
+    try_arr3_to_rgb_bufr(arr).expect("Array3 needs exactly 3 channels")
+}
+
+pub fn rgb_bufr_view(bufr: &RgbImage) -> ArrayView3<'_, u8> {
+    let (w, h) = bufr.dimensions();
+    ArrayView3::from_shape((h as usize, w as usize, 3), pixels(bufr.as_raw(), w, h, 3))
+        .expect("Rgb buffer has three values per pixel")
+}
+
+pub fn rgb_bufr_view_mut(bufr: &mut RgbImage) -> ArrayViewMut3<'_, u8> {
+    let (w, h) = bufr.dimensions();
+    ArrayViewMut3::from_shape((h as usize, w as usize, 3), pixels_mut(bufr, w, h, 3))
+        .expect("Rgb buffer has three values per pixel")
+}
+
+/*
+* Filter the image is resized to one pixel per cell with. Triangle averages the pixels under each
+* cell, Nearest takes a single one of them, which keeps the hard edges and exact colors of pixel art
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ResizeFilter {
+    #[default]
+    Triangle,
+    Nearest,
+}
+
+impl ResizeFilter {
+    pub fn filter_type(&self) -> FilterType {
+        match self {
+            ResizeFilter::Triangle => FilterType::Triangle,
+            ResizeFilter::Nearest => FilterType::Nearest,
+        }
+    }
+}
+
+pub(crate) fn srgb_to_linear(v: f32) -> f32 {
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        ((v + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+pub(crate) fn linear_to_srgb(v: f32) -> f32 {
+    if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * v.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+pub fn resize_exact_linear(img: &DynamicImage, w: u32, h: u32, filter: FilterType) -> DynamicImage {
+    /*
+     * resize_exact done in linear light, so fine detail averages to its true brightness instead
+     * of darkening the way averaging sRGB values does. Colors are weighted by alpha as in
+     * resize_exact_premultiplied
+     */
+    resize_weighted(img, w, h, filter, true)
+}
+
+pub fn resize_exact_premultiplied(
+    img: &DynamicImage,
+    w: u32,
+    h: u32,
+    filter: FilterType,
+) -> DynamicImage {
+    /*
+     * resize_exact weighting every color by its alpha, so transparent pixels add nothing to the
+     * colors around them whatever color they hold. Alpha itself is resized as is, pixels it ends
+     * up 0 for are transparent black
+     */
+    resize_weighted(img, w, h, filter, false)
+}
+
+fn resize_weighted(
+    img: &DynamicImage,
+    w: u32,
+    h: u32,
+    filter: FilterType,
+    linear: bool,
+) -> DynamicImage {
+    let mut premultiplied = img.to_rgba32f();
+    for pixel in premultiplied.pixels_mut() {
+        let alpha = pixel[3];
+        for c in pixel.0.iter_mut().take(3) {
+            let value = if linear { srgb_to_linear(*c) } else { *c };
+            *c = value * alpha;
+        }
+    }
+    let mut resized = DynamicImage::ImageRgba32F(premultiplied)
+        .resize_exact(w, h, filter)
+        .into_rgba32f();
+    for pixel in resized.pixels_mut() {
+        // Ringing filters can push alpha out of range or leave a trace of it over transparency
+        let alpha = pixel[3].clamp(0.0, 1.0);
+        for c in pixel.0.iter_mut().take(3) {
+            let value = if alpha > 0.0 {
+                (*c / alpha).clamp(0.0, 1.0)
+            } else {
+                0.0
+            };
+            *c = if linear { linear_to_srgb(value) } else { value };
+        }
+        pixel[3] = alpha;
+    }
+    DynamicImage::ImageRgba8(DynamicImage::ImageRgba32F(resized).into_rgba8())
 }
@@ -0,0 +1,178 @@
+use super::decode::Decoded;
+use crate::ascii::warning::ConvertWarning;
+use crate::image_manip::util::linear_to_srgb;
+use image::{DynamicImage, ImageBuffer, Pixel, Primitive};
+use num_traits::{NumCast, ToPrimitive};
+use std::io::Cursor;
+
+// The gAMA value of sRGB data, 1 / 2.2. Files written with it are taken as sRGB
+const SRGB_FILE_GAMMA: f32 = 0.45455;
+// Distance from SRGB_FILE_GAMMA under which the power law and the sRGB curve are not told apart
+const SRGB_GAMMA_TOLERANCE: f32 = 0.01;
+// JPEG start of image, start of scan and APP2 markers
+const JPEG_SOI: [u8; 2] = [0xFF, 0xD8];
+const JPEG_SOS: u8 = 0xDA;
+const JPEG_APP2: u8 = 0xE2;
+// An APP2 segment holding a part of an ICC profile starts with this
+const JPEG_ICC_TAG: &[u8] = b"ICC_PROFILE\0";
+
+/*
+* Color space the pixel values of an input are encoded in, as far as its metadata tells.
+* Unsupported is an ICC profile that is not sRGB, with gamma the gAMA chunk to fall back to
+*/
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SourceColorSpace {
+    Srgb,
+    // Values are linear light to the power of the gamma, as a PNG gAMA chunk says
+    Gamma(f32),
+    Unsupported { gamma: Option<f32> },
+}
+
+fn is_srgb_profile(profile: &[u8]) -> bool {
+    /*
+     * Whether an ICC profile describes sRGB. Profiles are not parsed: sRGB ones name themselves
+     * in their description, in ASCII for version 2 and in UTF-16 for version 4
+     */
+    let utf16: Vec<u8> = b"sRGB".iter().flat_map(|&b| [0, b]).collect();
+    profile.windows(4).any(|w| w == b"sRGB") || profile.windows(8).any(|w| w == utf16)
+}
+
+fn png_color_space(bytes: &[u8]) -> SourceColorSpace {
+    /*
+     * An sRGB chunk wins over the others, then a profile, then gAMA. Decoders that do not support
+     * a profile fall back to gAMA, as the PNG specification has them do
+     */
+    let Ok(reader) = png::Decoder::new(Cursor::new(bytes)).read_info() else {
+        return SourceColorSpace::Srgb;
+    };
+    let info = reader.info();
+    let gamma = info.gama_chunk.map(|g| g.into_value());
+    if info.srgb.is_some() {
+        return SourceColorSpace::Srgb;
+    }
+    match (&info.icc_profile, gamma) {
+        (Some(profile), _) if is_srgb_profile(profile) => SourceColorSpace::Srgb,
+        (Some(_), gamma) => SourceColorSpace::Unsupported { gamma },
+        (None, Some(gamma)) => SourceColorSpace::Gamma(gamma),
+        (None, None) => SourceColorSpace::Srgb,
+    }
+}
+
+fn jpeg_icc_profile(bytes: &[u8]) -> Option<Vec<u8>> {
+    /*
+     * The ICC profile of a JPEG, split over APP2 segments before the first scan. The parts are
+     * joined in the order they appear, which is their sequence order in every writer seen
+     */
+    if !bytes.starts_with(&JPEG_SOI) {
+        return None;
+    }
+    let mut profile: Vec<u8> = vec![];
+    let mut pos = JPEG_SOI.len();
+    while pos + 4 <= bytes.len() && bytes[pos] == 0xFF {
+        let marker = bytes[pos + 1];
+        if marker == JPEG_SOS {
+            break;
+        }
+        let len = u16::from_be_bytes([bytes[pos + 2], bytes[pos + 3]]) as usize;
+        let end = (pos + 2 + len).min(bytes.len());
+        let segment = &bytes[(pos + 4).min(end)..end];
+        // The tag is followed by the sequence number and count of the part
+        if marker == JPEG_APP2 && segment.starts_with(JPEG_ICC_TAG) {
+            profile.extend_from_slice(segment.get(JPEG_ICC_TAG.len() + 2..).unwrap_or(&[]));
+        }
+        pos = end;
+    }
+    (!profile.is_empty()).then_some(profile)
+}
+
+pub fn detect_color_space(bytes: &[u8]) -> SourceColorSpace {
+    /*
+     * Color space of encoded PNG or JPEG data from its metadata. Other formats, and data without
+     * color metadata, are taken as sRGB
+     */
+    if bytes.starts_with(b"\x89PNG") {
+        return png_color_space(bytes);
+    }
+    match jpeg_icc_profile(bytes) {
+        Some(profile) if !is_srgb_profile(&profile) => {
+            SourceColorSpace::Unsupported { gamma: None }
+        }
+        _ => SourceColorSpace::Srgb,
+    }
+}
+
+fn convert_channels<P: Pixel>(
+    image: &ImageBuffer<P, Vec<P::Subpixel>>,
+    convert: impl Fn(f32) -> f32,
+) -> ImageBuffer<P, Vec<P::Subpixel>> {
+    // Every integer color channel of image through convert on 0..=1, alpha left as it is
+    let max = P::Subpixel::DEFAULT_MAX_VALUE.to_f32().unwrap_or(1.0);
+    let mut converted = image.clone();
+    for pixel in converted.pixels_mut() {
+        pixel.apply_without_alpha(|c| {
+            let value = convert(c.to_f32().unwrap_or(0.0) / max).clamp(0.0, 1.0) * max;
+            <P::Subpixel as NumCast>::from(value.round()).unwrap_or(c)
+        });
+    }
+    converted
+}
+
+pub fn gamma_to_srgb(image: &DynamicImage, gamma: f32) -> DynamicImage {
+    /*
+     * Re-encode values written with a gAMA of gamma, linear light to the power of gamma, to
+     * sRGB. Float images keep unrounded values
+     */
+    // Float values can fall below 0, which the power law has no value for
+    let convert = |v: f32| linear_to_srgb(v.max(0.0).powf(1.0 / gamma));
+    match image {
+        DynamicImage::ImageLuma8(img) => DynamicImage::ImageLuma8(convert_channels(img, convert)),
+        DynamicImage::ImageLumaA8(img) => DynamicImage::ImageLumaA8(convert_channels(img, convert)),
+        DynamicImage::ImageRgb8(img) => DynamicImage::ImageRgb8(convert_channels(img, convert)),
+        DynamicImage::ImageRgba8(img) => DynamicImage::ImageRgba8(convert_channels(img, convert)),
+        DynamicImage::ImageLuma16(img) => DynamicImage::ImageLuma16(convert_channels(img, convert)),
+        DynamicImage::ImageLumaA16(img) => {
+            DynamicImage::ImageLumaA16(convert_channels(img, convert))
+        }
+        DynamicImage::ImageRgb16(img) => DynamicImage::ImageRgb16(convert_channels(img, convert)),
+        DynamicImage::ImageRgba16(img) => DynamicImage::ImageRgba16(convert_channels(img, convert)),
+        DynamicImage::ImageRgb32F(img) => {
+            let mut img = img.clone();
+            img.pixels_mut().for_each(|p| p.apply(convert));
+            DynamicImage::ImageRgb32F(img)
+        }
+        DynamicImage::ImageRgba32F(img) => {
+            let mut img = img.clone();
+            img.pixels_mut()
+                .for_each(|p| p.apply_without_alpha(convert));
+            DynamicImage::ImageRgba32F(img)
+        }
+        // Layouts added to image later go through RGBA
+        other => gamma_to_srgb(&DynamicImage::ImageRgba32F(other.to_rgba32f()), gamma),
+    }
+}
+
+pub fn manage_color(bytes: &[u8], mut decoded: Decoded) -> Decoded {
+    /*
+     * Bring a decoded image to sRGB by the color metadata of the data it was decoded from, the
+     * color space every stage of the pipeline takes its input to be in. An unsupported profile
+     * leaves the image as gAMA says, or as it is, with a warning
+     */
+    let gamma = match detect_color_space(bytes) {
+        SourceColorSpace::Srgb => None,
+        SourceColorSpace::Gamma(gamma) => Some(gamma),
+        SourceColorSpace::Unsupported { gamma } => {
+            decoded
+                .warnings
+                .push(ConvertWarning::UnsupportedColorProfile);
+            gamma
+        }
+    };
+    match gamma {
+        Some(gamma) if gamma > 0.0 && (gamma - SRGB_FILE_GAMMA).abs() > SRGB_GAMMA_TOLERANCE => {
+            let _span = stage_span!("color_management", gamma = gamma);
+            decoded.image = gamma_to_srgb(&decoded.image, gamma);
+            decoded
+        }
+        _ => decoded,
+    }
+}
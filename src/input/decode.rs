@@ -0,0 +1,141 @@
+use super::format::{decode_error, SNIFF_LEN};
+use crate::ascii::error::ConvertError;
+use crate::ascii::warning::ConvertWarning;
+use image::io::Reader as ImageReader;
+use image::{DynamicImage, GrayImage, ImageBuffer, ImageFormat, RgbImage};
+use jpeg_decoder::PixelFormat;
+use std::io::Cursor;
+
+// JPEG start of scan and end of image markers
+const JPEG_SOS: [u8; 2] = [0xFF, 0xDA];
+const JPEG_EOI: [u8; 2] = [0xFF, 0xD9];
+
+/*
+* A decoded input, with the warnings of a tolerant decode that had to fill part of it in or of color
+* metadata that could not be honored
+*/
+pub struct Decoded {
+    pub image: DynamicImage,
+    pub warnings: Vec<ConvertWarning>,
+}
+
+pub fn decode(
+    bytes: &[u8],
+    path_format: Option<ImageFormat>,
+    tolerant: bool,
+) -> Result<Decoded, ConvertError> {
+    /*
+     * Decode an image held in memory, its format guessed from the content and then from
+     * path_format. A failed tolerant decode is retried without the decoder's memory limits and,
+     * for JPEG, with a partial decode that fills in what could not be read. JPEG data cut short
+     * is a TruncatedImage error unless tolerant
+     */
+    let _span = stage_span!("decode", bytes = bytes.len(), tolerant = tolerant);
+    let mut reader = ImageReader::new(Cursor::new(bytes)).with_guessed_format()?;
+    if let (None, Some(format)) = (reader.format(), path_format) {
+        reader.set_format(format);
+    }
+    let format = reader.format();
+    let is_jpeg = format == Some(ImageFormat::Jpeg);
+
+    // The JPEG decoder can panic on a scan that ends early, so those never reach it
+    let decoded = if is_jpeg && jpeg_is_truncated(bytes) {
+        if !tolerant {
+            return Err(ConvertError::TruncatedImage {
+                format: "JPEG".to_string(),
+            });
+        }
+        partial_jpeg(bytes)?
+    } else {
+        match reader.decode() {
+            Ok(image) => Decoded {
+                image,
+                warnings: vec![],
+            },
+            Err(err) if tolerant => relaxed(bytes, format, is_jpeg)
+                .ok_or_else(|| decode_error(err, format, &bytes[..bytes.len().min(SNIFF_LEN)]))?,
+            Err(err) => {
+                return Err(decode_error(
+                    err,
+                    format,
+                    &bytes[..bytes.len().min(SNIFF_LEN)],
+                ))
+            }
+        }
+    };
+    stage_event!(
+        width = decoded.image.width(),
+        height = decoded.image.height(),
+        partial = !decoded.warnings.is_empty();
+        "decoded image"
+    );
+    Ok(decoded)
+}
+
+fn relaxed(bytes: &[u8], format: Option<ImageFormat>, is_jpeg: bool) -> Option<Decoded> {
+    // Second attempts of a tolerant decode, None when both fail
+    let mut reader = ImageReader::new(Cursor::new(bytes));
+    if let Some(format) = format {
+        reader.set_format(format);
+    }
+    reader.no_limits();
+    if let Ok(image) = reader.decode() {
+        return Some(Decoded {
+            image,
+            warnings: vec![],
+        });
+    }
+    if is_jpeg {
+        partial_jpeg(bytes).ok()
+    } else {
+        None
+    }
+}
+
+fn jpeg_is_truncated(bytes: &[u8]) -> bool {
+    /*
+     * Whether the last scan runs to the end of the data without an end of image marker. 0xFF bytes
+     * inside a scan are always followed by 0 or a restart marker, so the last start of scan marker
+     * found is the last real one. Data cut before any scan is left to the decoder to reject
+     */
+    match bytes.windows(2).rposition(|w| w == JPEG_SOS) {
+        Some(scan) => !bytes[scan..].windows(2).any(|w| w == JPEG_EOI),
+        None => false,
+    }
+}
+
+fn partial_jpeg(bytes: &[u8]) -> Result<Decoded, ConvertError> {
+    /*
+     * Decode JPEG data that ends early or is corrupt part way with an end of image marker put
+     * after it. The entropy decoder takes the marker as the end of the scan and reads zero bits
+     * from there on, so every block it could not read keeps the DC value of the last one it
+     * could: the rest of the image is filled with flat color
+     */
+    let mut data = bytes.to_vec();
+    if !data.ends_with(&JPEG_EOI) {
+        data.extend_from_slice(&JPEG_EOI);
+    }
+    let mut decoder = jpeg_decoder::Decoder::new(data.as_slice());
+    let pixels = decoder.decode().map_err(|_| ConvertError::ImageError)?;
+    let info = decoder.info().ok_or(ConvertError::ImageError)?;
+    let (w, h) = (info.width as u32, info.height as u32);
+    let image = match info.pixel_format {
+        PixelFormat::L8 => GrayImage::from_raw(w, h, pixels).map(DynamicImage::ImageLuma8),
+        PixelFormat::L16 => {
+            let values = pixels
+                .chunks_exact(2)
+                .map(|b| u16::from_be_bytes([b[0], b[1]]))
+                .collect();
+            ImageBuffer::from_raw(w, h, values).map(DynamicImage::ImageLuma16)
+        }
+        PixelFormat::RGB24 => RgbImage::from_raw(w, h, pixels).map(DynamicImage::ImageRgb8),
+        PixelFormat::CMYK32 => None,
+    }
+    .ok_or(ConvertError::ImageError)?;
+    Ok(Decoded {
+        image,
+        warnings: vec![ConvertWarning::PartialDecode {
+            format: "JPEG".to_string(),
+        }],
+    })
+}
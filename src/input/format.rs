@@ -0,0 +1,77 @@
+use crate::ascii::error::ConvertError;
+use image::error::ImageError;
+use image::ImageFormat;
+
+// Bytes read from the start of an input to tell formats the decoder does not know apart
+pub(crate) const SNIFF_LEN: usize = 32;
+
+// ISO base media brands at offset 8 that mark a HEIF / HEIC image
+const HEIF_BRANDS: [&[u8]; 5] = [b"heic", b"heix", b"hevc", b"mif1", b"msf1"];
+
+pub(crate) fn decode_error(
+    err: ImageError,
+    format: Option<ImageFormat>,
+    header: &[u8],
+) -> ConvertError {
+    /*
+     * Turn a decoding failure into an error that names the format when the decoder does not
+     * support it, with a hint on what to do about it. Other failures stay ImageError
+     */
+    if !matches!(err, ImageError::Unsupported(_)) {
+        return err.into();
+    }
+
+    match format {
+        Some(ImageFormat::Avif) if !cfg!(feature = "avif") => ConvertError::UnsupportedFormat {
+            detected: "AVIF".to_string(),
+            hint: "enable the `avif` feature (cargo build --features avif) to decode it, \
+                   which needs the dav1d library"
+                .to_string(),
+        },
+        Some(format) => ConvertError::UnsupportedFormat {
+            detected: format_name(format),
+            hint: format!("this build cannot read it: {}", err),
+        },
+        None => sniff(header),
+    }
+}
+
+fn sniff(header: &[u8]) -> ConvertError {
+    /*
+     * Name formats the decoder has no guess for, falling back to listing the readable formats
+     */
+    let text = String::from_utf8_lossy(header);
+    let text = text.trim_start_matches('\u{feff}').trim_start();
+
+    let (detected, hint) = if header.len() >= 12
+        && &header[4..8] == b"ftyp"
+        && HEIF_BRANDS.contains(&&header[8..12])
+    {
+        ("HEIC", "convert it to PNG or JPEG first".to_string())
+    } else if text.starts_with("<svg") || text.starts_with("<?xml") {
+        ("SVG", "rasterize it to PNG first".to_string())
+    } else {
+        (
+            "unknown",
+            format!("readable formats are {}", readable_formats()),
+        )
+    };
+    ConvertError::UnsupportedFormat {
+        detected: detected.to_string(),
+        hint,
+    }
+}
+
+fn format_name(format: ImageFormat) -> String {
+    format!("{:?}", format).to_uppercase()
+}
+
+fn readable_formats() -> String {
+    ImageFormat::all()
+        .filter(|format| format.reading_enabled())
+        // The avif feature of image only adds the encoder
+        .filter(|format| *format != ImageFormat::Avif || cfg!(feature = "avif"))
+        .filter_map(|format| format.extensions_str().first().copied())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
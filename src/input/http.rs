@@ -0,0 +1,80 @@
+use crate::ascii::error::ConvertError;
+use std::io::Read;
+use std::time::Duration;
+
+#[derive(Clone, Debug)]
+pub struct HttpOptions {
+    pub timeout: Duration,
+    // Responses larger than this are rejected instead of being read into memory
+    pub max_bytes: u64,
+    pub max_redirects: u32,
+}
+
+impl Default for HttpOptions {
+    fn default() -> Self {
+        HttpOptions {
+            timeout: Duration::from_secs(30),
+            max_bytes: 50 * 1024 * 1024,
+            max_redirects: 5,
+        }
+    }
+}
+
+impl HttpOptions {
+    pub fn new(timeout: Duration, max_bytes: u64, max_redirects: u32) -> Self {
+        HttpOptions {
+            timeout,
+            max_bytes,
+            max_redirects,
+        }
+    }
+}
+
+pub fn is_url(path: &str) -> bool {
+    path.starts_with("http://") || path.starts_with("https://")
+}
+
+pub fn fetch(url: &str, options: &HttpOptions) -> Result<Vec<u8>, ConvertError> {
+    /*
+     * Download the body of a url into memory, following redirects and enforcing the timeout and
+     * size limit of the options
+     */
+    let agent = ureq::AgentBuilder::new()
+        .timeout(options.timeout)
+        .redirects(options.max_redirects)
+        .build();
+
+    let response = agent.get(url).call().map_err(|e| match e {
+        ureq::Error::Status(code, _) => {
+            ConvertError::Network(format!("{} returned status {}", url, code))
+        }
+        ureq::Error::Transport(transport) => ConvertError::Network(transport.to_string()),
+    })?;
+
+    // Reject early when the server announces a body over the limit
+    if let Some(len) = response
+        .header("Content-Length")
+        .and_then(|len| len.parse::<u64>().ok())
+    {
+        if len > options.max_bytes {
+            return Err(too_large(url, options.max_bytes));
+        }
+    }
+
+    // The announced length can be missing or wrong, so read one byte past the limit to detect it
+    let mut bytes = Vec::new();
+    response
+        .into_reader()
+        .take(options.max_bytes.saturating_add(1))
+        .read_to_end(&mut bytes)
+        .map_err(|e| ConvertError::Network(e.to_string()))?;
+    if bytes.len() as u64 > options.max_bytes {
+        return Err(too_large(url, options.max_bytes));
+    }
+
+    Ok(bytes)
+}
+
+fn too_large(url: &str, max_bytes: u64) -> ConvertError {
+    ConvertError::Network(format!("{} is larger than {} bytes", url, max_bytes))
+}
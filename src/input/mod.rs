@@ -0,0 +1,6 @@
+pub mod color_space;
+pub mod decode;
+pub(crate) mod format;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod text_grid;
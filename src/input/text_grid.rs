@@ -0,0 +1,83 @@
+use crate::ascii::error::ConvertError;
+use image::Rgb;
+use ndarray::Array2;
+use std::path::PathBuf;
+
+/*
+* Where the colors of a grid read back from text come from. Image takes them from the image the
+* grid was converted from, the way the conversion did. Gradient runs from top on the first row to
+* bottom on the last
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub enum ColorSource {
+    Image(PathBuf),
+    Fixed(Rgb<u8>),
+    Gradient { top: Rgb<u8>, bottom: Rgb<u8> },
+}
+
+pub(crate) fn gradient_colors(
+    top: Rgb<u8>,
+    bottom: Rgb<u8>,
+    (rows, cols): (usize, usize),
+) -> Array2<Rgb<u8>> {
+    // Colors of a rows x cols grid running from top on the first row to bottom on the last
+    let last = rows.saturating_sub(1).max(1) as f32;
+    Array2::from_shape_fn((rows, cols), |(y, _)| {
+        let t = y as f32 / last;
+        Rgb(std::array::from_fn(|c| {
+            (top[c] as f32 + (bottom[c] as f32 - top[c] as f32) * t).round() as u8
+        }))
+    })
+}
+
+pub fn parse_text_grid(text: &str) -> Result<Array2<char>, ConvertError> {
+    /*
+     * Read a grid saved as plain text, one row per line, back into characters. Lines may end in
+     * \n or \r\n. Every line must hold as many characters as the first, so text written with
+     * trimming or a header does not parse. Tabs are rejected, as their width depends on the viewer
+     */
+    let lines: Vec<&str> = text
+        .split('\n')
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+        .collect();
+    // The newline ending the last row does not start another one
+    let lines = match lines.split_last() {
+        Some((&"", rows)) => rows,
+        _ => &lines[..],
+    };
+    let Some(first) = lines.first() else {
+        return Err(ConvertError::MalformedTextGrid {
+            line: 1,
+            reason: "the text holds no rows".to_string(),
+        });
+    };
+    let cols = first.chars().count();
+    if cols == 0 {
+        return Err(ConvertError::MalformedTextGrid {
+            line: 1,
+            reason: "the first row is empty".to_string(),
+        });
+    }
+
+    let mut chars = Vec::with_capacity(lines.len() * cols);
+    for (i, line) in lines.iter().enumerate() {
+        let before = chars.len();
+        for (col, ch) in line.chars().enumerate() {
+            if ch == '\t' {
+                return Err(ConvertError::MalformedTextGrid {
+                    line: i + 1,
+                    reason: format!("tab in column {}", col + 1),
+                });
+            }
+            chars.push(ch);
+        }
+        let found = chars.len() - before;
+        if found != cols {
+            return Err(ConvertError::MalformedTextGrid {
+                line: i + 1,
+                reason: format!("{} characters where the first row has {}", found, cols),
+            });
+        }
+    }
+    Ok(Array2::from_shape_vec((lines.len(), cols), chars)?)
+}
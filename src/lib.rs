@@ -0,0 +1,11 @@
+#[macro_use]
+mod trace;
+
+pub mod ascii;
+#[cfg(feature = "batch")]
+pub mod batch;
+pub mod image_manip;
+pub mod input;
+pub mod output;
+#[cfg(feature = "watch")]
+pub mod watch;
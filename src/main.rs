@@ -1,20 +1,961 @@
-use ascii::converter::Converter;
-
-mod ascii;
-mod image_manip;
-use std::time::Instant;
-
-fn main() {
-    let start = Instant::now();
-    let converter: Converter = ascii::converter::Converter::default();
-    let path = "2.png";
-    let _ = converter
-        .convert_img(
-            &format!("test/{}", path),
-            &format!("test_out/{}", path),
-            0.0,
-        )
-        .expect("Error");
-    let duration = start.elapsed();
-    println!("Produced ascii art in {:?}", duration);
+use ascii_gen::ascii::auto::AutoTuner;
+use ascii_gen::ascii::char_set::CharacterSet;
+use ascii_gen::ascii::config::{
+    ConverterConfig, CornerConfig, OrientationConfig, PostEffectConfig, TrimModeConfig,
+    WatermarkConfig, WeightMapConfig, WeightSourceConfig,
+};
+use ascii_gen::ascii::diff::grid_diff;
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::ascii::font_loader::FontSettings;
+use ascii_gen::ascii::preset::Preset;
+use ascii_gen::ascii::provenance::{Origin, Provenance};
+use ascii_gen::ascii::sequence::{convert_sequence, SequenceAnimation, SequenceOptions};
+use ascii_gen::ascii::target::OutputTarget;
+use ascii_gen::ascii::tone_curve::ToneCurve;
+use ascii_gen::batch::{convert_dir, BatchOptions, Outcome, DEFAULT_OUTPUT_TEMPLATE};
+use ascii_gen::input::color_space::manage_color;
+use ascii_gen::input::decode::{decode, Decoded};
+#[cfg(feature = "http")]
+use ascii_gen::input::http::{fetch, is_url, HttpOptions};
+use ascii_gen::output::inline::InlineImageProtocol;
+use ascii_gen::output::json::JsonLayout;
+use ascii_gen::output::write::{write_file, OverwritePolicy, WriteOptions};
+use ascii_gen::output::OutputFormat;
+use ascii_gen::watch::{watch_and_convert, StopHandle};
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use clap_mangen::Man;
+use image::{GenericImageView, ImageFormat, Rgb};
+use std::fs;
+use std::io::{self, Cursor, IsTerminal, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+#[cfg(feature = "http")]
+use std::time::Duration;
+
+#[cfg(feature = "tui")]
+mod tui;
+
+// Path used on the command line to mean stdin or stdout
+const STDIO_PATH: &str = "-";
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Format {
+    Png,
+    Txt,
+    Ansi,
+    Sixel,
+    Json,
+    JsonCompact,
+}
+
+impl From<Format> for OutputFormat {
+    fn from(format: Format) -> Self {
+        match format {
+            Format::Png => OutputFormat::Png,
+            Format::Txt => OutputFormat::Txt,
+            Format::Ansi => OutputFormat::Ansi,
+            Format::Sixel => OutputFormat::Sixel,
+            Format::Json => OutputFormat::Json(JsonLayout::Verbose),
+            Format::JsonCompact => OutputFormat::Json(JsonLayout::Compact),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Inline {
+    Auto,
+    Kitty,
+    Iterm2,
+    None,
+}
+
+impl Inline {
+    fn protocol(self) -> Option<InlineImageProtocol> {
+        match self {
+            Inline::Auto => InlineImageProtocol::detect(),
+            Inline::Kitty => Some(InlineImageProtocol::Kitty),
+            Inline::Iterm2 => Some(InlineImageProtocol::Iterm2),
+            Inline::None => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Rotate {
+    #[value(name = "90")]
+    Quarter,
+    #[value(name = "180")]
+    Half,
+    #[value(name = "270")]
+    ThreeQuarters,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum Flip {
+    H,
+    V,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum CornerArg {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl From<CornerArg> for CornerConfig {
+    fn from(corner: CornerArg) -> Self {
+        match corner {
+            CornerArg::TopLeft => CornerConfig::TopLeft,
+            CornerArg::TopRight => CornerConfig::TopRight,
+            CornerArg::BottomLeft => CornerConfig::BottomLeft,
+            CornerArg::BottomRight => CornerConfig::BottomRight,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum TrimArg {
+    None,
+    Trailing,
+    Bounding,
+}
+
+impl From<TrimArg> for TrimModeConfig {
+    fn from(trim: TrimArg) -> Self {
+        match trim {
+            TrimArg::None => TrimModeConfig::None,
+            TrimArg::Trailing => TrimModeConfig::TrailingSpaces,
+            TrimArg::Bounding => TrimModeConfig::Bounding,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum FxArg {
+    Scanlines,
+    Chromatic,
+    Vignette,
+}
+
+impl From<FxArg> for PostEffectConfig {
+    fn from(fx: FxArg) -> Self {
+        match fx {
+            FxArg::Scanlines => PostEffectConfig::Scanlines {
+                period: 2,
+                factor: 0.6,
+            },
+            FxArg::Chromatic => PostEffectConfig::ChromaticAberration { shift: 1 },
+            FxArg::Vignette => PostEffectConfig::Vignette { strength: 0.5 },
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum PresetArg {
+    Default,
+    LineArt,
+    PixelArt,
+    Auto,
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum OnExisting {
+    Overwrite,
+    Error,
+    Rename,
+}
+
+impl From<OnExisting> for OverwritePolicy {
+    fn from(overwrite: OnExisting) -> Self {
+        match overwrite {
+            OnExisting::Overwrite => OverwritePolicy::Overwrite,
+            OnExisting::Error => OverwritePolicy::Error,
+            OnExisting::Rename => OverwritePolicy::RenameWithSuffix,
+        }
+    }
+}
+
+#[derive(Parser, Debug)]
+#[command(
+    name = "ruscii-gen",
+    version,
+    about = "Convert images into ascii art",
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[command(flatten)]
+    convert: ConvertArgs,
+
+    /// Log pipeline stages to stderr, -v for the library debug log and -vv for everything
+    #[cfg(feature = "tracing")]
+    #[arg(short, long, action = clap::ArgAction::Count, global = true)]
+    verbose: u8,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Convert the input, then convert it again whenever the input or the config file changes
+    Watch(WatchArgs),
+    /// Convert every image in a directory into a PNG of the same name in another directory
+    Batch(BatchArgs),
+    /// Convert an image under two configs and report which cells of the grid changed
+    Diff(DiffArgs),
+    /// Convert numbered frames matching a pattern, in frame order, keeping their numbers
+    Sequence(SequenceArgs),
+    /// Tune the converter settings interactively with a live preview
+    #[cfg(feature = "tui")]
+    Tune(TuneArgs),
+    /// Draw the characters of a ramp with their measured densities, sparsest first
+    CharsetPreview(CharsetPreviewArgs),
+    /// Print the completion script of a shell
+    #[command(hide = true)]
+    Completions(CompletionsArgs),
+    /// Print the man page
+    #[command(hide = true)]
+    Man,
+}
+
+#[derive(Args, Debug)]
+struct ConvertArgs {
+    /// Input image path, `-` to read the image from stdin, or an http(s) url when built with the
+    /// http feature
+    #[arg(required = true)]
+    input: Option<PathBuf>,
+
+    /// Output path, or `-` to write to stdout
+    #[arg(short, long, default_value = STDIO_PATH)]
+    output: PathBuf,
+
+    /// What to do when the output file already exists, rename writes to name-1.ext and so on
+    #[arg(long, value_enum, default_value_t = OnExisting::Overwrite)]
+    overwrite: OnExisting,
+
+    /// Fail instead of creating the missing parent directories of the output
+    #[arg(long)]
+    no_create_dirs: bool,
+
+    /// Output format
+    #[arg(short, long, value_enum, default_value_t = Format::Png)]
+    format: Format,
+
+    /// TOML file with the converter settings
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Ratio of edge pixels a cell needs before it is drawn as an edge character, overrides the
+    /// config file
+    #[arg(long)]
+    edge_threshold: Option<f32>,
+
+    /// Size in pixels of a cell in rendered outputs, to draw the same grid larger or smaller.
+    /// Overrides the config file
+    #[arg(long, value_name = "PX")]
+    cell_px: Option<u32>,
+
+    /// Also write a PNG coloring every cell by the tile bucket it used, dark purple for the
+    /// first bucket to yellow for the last and magenta for edges, to tune tone curves and levels
+    #[arg(long, value_name = "PATH")]
+    debug_heatmap: Option<PathBuf>,
+
+    /// Rotate the image clockwise by this many degrees before converting, overrides the config
+    /// file
+    #[arg(long, value_enum)]
+    rotate: Option<Rotate>,
+
+    /// Mirror the image horizontally or vertically before converting, overrides the config file
+    #[arg(long, value_enum, conflicts_with = "rotate")]
+    flip: Option<Flip>,
+
+    /// Grayscale image whose dark regions get a shorter, sparser tile ramp, or `saliency` to weigh
+    /// cells by how much they stand out from their surroundings. Overrides the config file
+    #[arg(long)]
+    weight_map: Option<String>,
+
+    /// Text drawn in a corner of rendered outputs, such as a signature. Overrides the config file
+    #[arg(long)]
+    watermark: Option<String>,
+
+    /// Corner the watermark is drawn in
+    #[arg(long, value_enum, requires = "watermark")]
+    watermark_corner: Option<CornerArg>,
+
+    /// Retro effects applied in order over rendered outputs: scanlines darkens every other row,
+    /// chromatic shifts red and blue a pixel apart and vignette darkens the corners. Replaces the
+    /// effects of the config file
+    #[arg(long, value_enum, value_delimiter = ',')]
+    fx: Vec<FxArg>,
+
+    /// Blank space removed around the art in txt and ansi outputs: trailing drops the spaces
+    /// ending every line, bounding also drops blank rows above and below and the common left
+    /// margin. Overrides the config file
+    #[arg(long, value_enum)]
+    trim: Option<TrimArg>,
+
+    /// Pick the font size giving the largest txt or ansi output of at most N characters,
+    /// newlines and color escapes included, such as 280 for a post. Overrides the font size of
+    /// the config file
+    #[arg(long, value_name = "N")]
+    max_chars: Option<usize>,
+
+    /// Largest input in pixels, overrides the config file
+    #[arg(long)]
+    max_pixels: Option<u64>,
+
+    /// Downscale inputs over the pixel limit to fit instead of failing
+    #[arg(long)]
+    allow_huge: bool,
+
+    /// Convert images cut short or slightly corrupt as far as they decode, with a warning,
+    /// instead of failing
+    #[arg(long)]
+    tolerant_decode: bool,
+
+    /// Tone curve the tile luminance is remapped with before picking characters, as
+    /// input:output points such as 0:0,64:90,255:255
+    #[arg(long, value_name = "POINTS")]
+    curve: Option<String>,
+
+    /// Fail instead of warning: on a fallback font, missing glyphs, a downscaled input or a
+    /// partial decode
+    #[arg(long)]
+    strict: bool,
+
+    /// Print character usage statistics of the converted grid to stderr
+    #[arg(long)]
+    stats: bool,
+
+    /// Print how closely the render approximates the input to stderr: mean squared error, SSIM
+    /// and luminance correlation
+    #[arg(long)]
+    score: bool,
+
+    /// Print the resolved settings instead of converting: every stage with its parameters, the
+    /// grid the input gives and whether each value is a default or comes from the config file,
+    /// the preset, a flag or --auto
+    #[arg(long)]
+    explain: bool,
+
+    /// Do not print the warnings of the conversion, such as a fallback font or a downscaled input
+    #[arg(short, long)]
+    quiet: bool,
+
+    /// Pick auto levels, the edge preprocessing and whether to draw edges from the image, and
+    /// print the decisions to stderr
+    #[arg(long)]
+    auto: bool,
+
+    /// Settings for a kind of input applied over the config: line-art draws black edges on white
+    /// without tile shading, pixel-art turns every pixel into one cell without smoothing, auto
+    /// picks line-art for images that are nearly all paper
+    #[arg(long, value_enum)]
+    preset: Option<PresetArg>,
+
+    /// Convert pixel art one cell per pixel with nearest sampling, the same as --preset pixel-art
+    #[arg(long, conflicts_with = "preset")]
+    pixel_art: bool,
+
+    /// Show the result in the terminal instead of writing it, as an inline image where the
+    /// terminal supports one and as ANSI colored cells otherwise
+    #[arg(long, conflicts_with_all = ["output", "format"])]
+    preview: bool,
+
+    /// Inline image protocol used by --preview, detected from $TERM and $TERM_PROGRAM by default
+    #[arg(long, value_enum, default_value_t = Inline::Auto, requires = "preview")]
+    inline_protocol: Inline,
+
+    /// Write binary formats to stdout even when it is a terminal
+    #[arg(long)]
+    force: bool,
+
+    /// Seconds to wait on an http(s) input before giving up
+    #[cfg(feature = "http")]
+    #[arg(long, default_value_t = 30)]
+    http_timeout: u64,
+
+    /// Largest http(s) input to download, in megabytes
+    #[cfg(feature = "http")]
+    #[arg(long, default_value_t = 50)]
+    max_download_mb: u64,
+}
+
+#[derive(Args, Debug)]
+struct WatchArgs {
+    /// Input image path
+    input: PathBuf,
+
+    /// Output image path
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// TOML file with the converter settings, also watched for changes
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Skip conversions whose input and settings match the ones that produced the output
+    #[arg(long)]
+    incremental: bool,
+}
+
+#[derive(Args, Debug)]
+struct BatchArgs {
+    /// Directory of input images
+    input: PathBuf,
+
+    /// Directory the outputs are written to
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// TOML file with the converter settings
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Skip inputs whose bytes and settings match the ones that produced their output
+    #[arg(long)]
+    incremental: bool,
+
+    /// File name of every output, with the placeholders {stem}, {ext}, {cols}, {rows}, {preset}
+    /// (the config file name), {date} and {cfghash} (8 hex digits identifying the settings)
+    #[arg(long, default_value = DEFAULT_OUTPUT_TEMPLATE)]
+    name_template: String,
+}
+
+#[derive(Args, Debug)]
+struct DiffArgs {
+    /// Input image path
+    input: PathBuf,
+
+    /// TOML file with the settings of the first conversion, the defaults when left out
+    #[arg(long)]
+    config_a: Option<PathBuf>,
+
+    /// TOML file with the settings of the second conversion, the defaults when left out
+    #[arg(long)]
+    config_b: Option<PathBuf>,
+
+    /// Write the second grid here with its changed cells highlighted and the others dimmed
+    #[arg(short, long)]
+    output: Option<PathBuf>,
+
+    /// Color of the changed cells in the diff image, as hex such as ff3030
+    #[arg(long, default_value = "ff3030", value_parser = parse_hex_color)]
+    highlight: [u8; 3],
+}
+
+#[derive(Args, Debug)]
+struct SequenceArgs {
+    /// Frames to convert, with * and ? in the file name such as frames/frame_*.png
+    pattern: String,
+
+    /// Directory the outputs are written to
+    #[arg(short, long)]
+    output: PathBuf,
+
+    /// TOML file with the converter settings
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// File name of every output, {frame} being the frame number of the input and {stem} its
+    /// file name without the extension
+    #[arg(long, default_value = "{stem}.png")]
+    name_template: String,
+
+    /// Also assemble the converted frames into this GIF
+    #[arg(long)]
+    gif: Option<String>,
+
+    /// Frame rate of the GIF
+    #[arg(long, default_value_t = 12, requires = "gif")]
+    fps: u32,
+}
+
+fn parse_hex_color(hex: &str) -> Result<[u8; 3], String> {
+    let digits = hex.strip_prefix('#').unwrap_or(hex);
+    let channel = |i: usize| {
+        digits
+            .get(i..i + 2)
+            .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+    };
+    match (digits.len(), channel(0), channel(2), channel(4)) {
+        (6, Some(r), Some(g), Some(b)) => Ok([r, g, b]),
+        _ => Err(format!("{} is not a hex color such as ff3030", hex)),
+    }
+}
+
+#[derive(Args, Debug)]
+struct CharsetPreviewArgs {
+    /// Tile characters of the ramp, the default ramp when left out
+    #[arg(long)]
+    charset: Option<String>,
+
+    /// Font file the characters are drawn with
+    #[arg(long, default_value = "font.ttf")]
+    font: PathBuf,
+
+    /// Size in pixels the characters are drawn at
+    #[arg(long, default_value_t = 24)]
+    font_size: u32,
+
+    /// Output image path
+    #[arg(short, long)]
+    output: PathBuf,
+}
+
+#[derive(Args, Debug)]
+struct CompletionsArgs {
+    /// Shell the completions are generated for
+    #[arg(value_enum)]
+    shell: Shell,
+}
+
+#[cfg(feature = "tui")]
+#[derive(Args, Debug)]
+struct TuneArgs {
+    /// Input image path
+    input: PathBuf,
+
+    /// Path of the full resolution output written on demand
+    #[arg(short, long, default_value = "out.png")]
+    output: PathBuf,
+
+    /// TOML file with the starting converter settings
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// Path the tuned settings are exported to
+    #[arg(long, default_value = "ruscii-gen.toml")]
+    export: PathBuf,
+}
+
+fn load_config(path: Option<&Path>) -> Result<ConverterConfig, ConvertError> {
+    match path {
+        Some(path) => ConverterConfig::load(path),
+        None => Ok(ConverterConfig::default()),
+    }
+}
+
+fn orientation(args: &ConvertArgs) -> Option<OrientationConfig> {
+    match (args.rotate, args.flip) {
+        (Some(Rotate::Quarter), _) => Some(OrientationConfig::Rotate90),
+        (Some(Rotate::Half), _) => Some(OrientationConfig::Rotate180),
+        (Some(Rotate::ThreeQuarters), _) => Some(OrientationConfig::Rotate270),
+        (None, Some(Flip::H)) => Some(OrientationConfig::FlipH),
+        (None, Some(Flip::V)) => Some(OrientationConfig::FlipV),
+        (None, None) => None,
+    }
+}
+
+fn read_input(args: &ConvertArgs, input: &Path) -> Result<Vec<u8>, ConvertError> {
+    #[cfg(feature = "http")]
+    if let Some(url) = input.to_str().filter(|input| is_url(input)) {
+        let options = HttpOptions {
+            timeout: Duration::from_secs(args.http_timeout),
+            max_bytes: args.max_download_mb.saturating_mul(1024 * 1024),
+            ..HttpOptions::default()
+        };
+        return fetch(url, &options);
+    }
+    #[cfg(not(feature = "http"))]
+    let _ = args;
+
+    if input == Path::new(STDIO_PATH) {
+        let mut bytes = Vec::new();
+        io::stdin().lock().read_to_end(&mut bytes)?;
+        Ok(bytes)
+    } else {
+        Ok(fs::read(input)?)
+    }
+}
+
+fn write_output(output: &Path, bytes: &[u8], options: &WriteOptions) -> Result<(), ConvertError> {
+    if output == Path::new(STDIO_PATH) {
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(bytes)?;
+        stdout.flush()?;
+    } else {
+        write_file(output, bytes, options)?;
+    }
+    Ok(())
+}
+
+fn run_convert(args: &ConvertArgs) -> Result<(), String> {
+    let input = args
+        .input
+        .as_deref()
+        .unwrap_or_else(|| Path::new(STDIO_PATH));
+    let format = if args.preview {
+        // Terminals without an inline image protocol still get a colored preview
+        args.inline_protocol
+            .protocol()
+            .map_or(OutputFormat::Ansi, OutputFormat::Inline)
+    } else {
+        OutputFormat::from(args.format)
+    };
+    if args.output == Path::new(STDIO_PATH)
+        && format.is_binary()
+        && io::stdout().is_terminal()
+        && !args.force
+    {
+        return Err(
+            "refusing to write binary output to a terminal, redirect stdout or pass --force"
+                .to_string(),
+        );
+    }
+
+    let mut config = load_config(args.config.as_deref()).map_err(|e| e.to_string())?;
+    let mut provenance = Provenance::new();
+    provenance.record(&ConverterConfig::default(), &config, Origin::ConfigFile);
+    let mut before = config.clone();
+    config.tolerant_decode |= args.tolerant_decode;
+    config.score_fidelity |= args.score;
+    config.strict |= args.strict;
+    provenance.record(&before, &config, Origin::User);
+    let bytes = read_input(args, input).map_err(|e| format!("{}: {}", input.display(), e))?;
+    let decoded = decode(&bytes, None, config.tolerant_decode)
+        .map(|decoded| managed(&bytes, decoded, config.color_management))
+        .map_err(|e| format!("{}: {}", input.display(), e))?;
+    let preset = if args.pixel_art {
+        Some(PresetArg::PixelArt)
+    } else {
+        args.preset
+    };
+    if let Some(preset) = preset {
+        let preset = match preset {
+            PresetArg::Default => Preset::Default,
+            PresetArg::LineArt => Preset::LineArt,
+            PresetArg::PixelArt => Preset::PixelArt,
+            PresetArg::Auto => Preset::detect(&decoded.image),
+        };
+        before = config.clone();
+        config = preset.apply(config);
+        provenance.record(&before, &config, Origin::Preset);
+    }
+    before = config.clone();
+    if let Some(orientation) = orientation(args) {
+        config.orientation = orientation;
+    }
+    if let Some(source) = args.weight_map.as_deref() {
+        let source = match source {
+            "saliency" => WeightSourceConfig::Saliency,
+            path => WeightSourceConfig::Image {
+                path: path.to_string(),
+            },
+        };
+        let weight_map = config
+            .weight_map
+            .get_or_insert_with(WeightMapConfig::default);
+        weight_map.source = source;
+    }
+    if let Some(text) = args.watermark.as_deref() {
+        let watermark = config
+            .watermark
+            .get_or_insert_with(WatermarkConfig::default);
+        watermark.text = text.to_string();
+        if let Some(corner) = args.watermark_corner {
+            watermark.corner = corner.into();
+        }
+    }
+    if let Some(spec) = args.curve.as_deref() {
+        let curve = ToneCurve::parse(spec).map_err(|e| e.to_string())?;
+        config.tone_curve = Some(curve.points().to_vec());
+    }
+    if let Some(cell_px) = args.cell_px {
+        config.render_cell_px = Some(cell_px);
+    }
+    if let Some(trim) = args.trim {
+        config.trim = trim.into();
+    }
+    if !args.fx.is_empty() {
+        config.post_effects = args.fx.iter().map(|&fx| fx.into()).collect();
+    }
+    if let Some(max_pixels) = args.max_pixels {
+        config.max_input_pixels = Some(max_pixels);
+    }
+    config.auto_downscale_large |= args.allow_huge;
+    if let Some(edge_threshold) = args.edge_threshold {
+        config.edge_threshold = edge_threshold;
+    }
+    provenance.record(&before, &config, Origin::User);
+
+    if args.auto {
+        before = config.clone();
+        let tuning = AutoTuner::new(config).tune(&decoded.image);
+        eprintln!("{}", tuning);
+        config = tuning.config;
+        provenance.record(&before, &config, Origin::Auto);
+    }
+    let edge_threshold = config.edge_threshold;
+    let mut converter = config.build().map_err(|e| e.to_string())?;
+    if let Some(max_chars) = args.max_chars {
+        // Text outputs of convert_bytes sample square cells
+        let target = match format {
+            OutputFormat::Txt => OutputTarget::Txt { cell_aspect: 1.0 },
+            OutputFormat::Ansi => OutputTarget::Ansi { cell_aspect: 1.0 },
+            _ => return Err("--max-chars only applies to txt and ansi outputs".to_string()),
+        };
+        let (w, h) = decoded.image.dimensions();
+        let fit = converter
+            .fit_text_output(w, h, target, max_chars)
+            .map_err(|e| e.to_string())?;
+        config.font_size = fit.font_size;
+        provenance.set("font_size", Origin::User);
+        converter = config.build().map_err(|e| e.to_string())?;
+    }
+    if args.explain {
+        let (w, h) = decoded.image.dimensions();
+        let tree = converter
+            .with_provenance(Some(provenance))
+            .describe_input(w, h, edge_threshold)
+            .map_err(|e| e.to_string())?;
+        print!("{}", tree);
+        return Ok(());
+    }
+    let write_options = WriteOptions::new(!args.no_create_dirs, args.overwrite.into());
+    if let Some(path) = &args.debug_heatmap {
+        let cell_px = config.render_cell_px.unwrap_or(config.font_size);
+        let heatmap = converter
+            .convert_to_heatmap(&decoded.image, edge_threshold, cell_px)
+            .map_err(|e| e.to_string())?;
+        let format = ImageFormat::from_path(path).map_err(|e| e.to_string())?;
+        let mut encoded = Cursor::new(Vec::new());
+        heatmap
+            .write_to(&mut encoded, format)
+            .map_err(|e| e.to_string())?;
+        write_output(path, &encoded.into_inner(), &write_options).map_err(|e| e.to_string())?;
+    }
+    let (out, stats) = converter
+        .convert_decoded_with_stats(decoded, format, edge_threshold)
+        .map_err(|e| e.to_string())?;
+    if !args.quiet {
+        for warning in stats.warnings.iter() {
+            eprintln!("ruscii-gen: warning: {}", warning);
+        }
+    }
+    if args.stats {
+        eprintln!("{}", stats);
+    }
+    if let Some(score) = stats.fidelity {
+        eprintln!("{}", score);
+    }
+    write_output(&args.output, &out, &write_options).map_err(|e| e.to_string())?;
+    if args.preview {
+        // Leave the cursor below the image so the shell prompt does not overlap it
+        println!();
+    }
+    Ok(())
+}
+
+fn run_watch(args: &WatchArgs) -> Result<(), String> {
+    // The watch only ends with the process, so the stop handle is never triggered here
+    let stop = StopHandle::new();
+    watch_and_convert(
+        &args.input,
+        &args.output,
+        args.config.as_deref(),
+        &BatchOptions::new(args.incremental),
+        &stop,
+        |result| match result {
+            Ok(Outcome::Converted) => eprintln!("ruscii-gen: wrote {}", args.output.display()),
+            Ok(Outcome::Skipped) => {
+                eprintln!("ruscii-gen: {} is up to date", args.output.display())
+            }
+            Err(e) => eprintln!("ruscii-gen: {}", e),
+        },
+    )
+    .map_err(|e| e.to_string())
+}
+
+fn run_batch(args: &BatchArgs) -> Result<(), String> {
+    let config = load_config(args.config.as_deref()).map_err(|e| e.to_string())?;
+    let mut options = BatchOptions::new(args.incremental).with_output_template(&args.name_template);
+    if let Some(stem) = args.config.as_deref().and_then(|path| path.file_stem()) {
+        options = options.with_preset(&stem.to_string_lossy());
+    }
+    let report =
+        convert_dir(&args.input, &args.output, &config, &options).map_err(|e| e.to_string())?;
+    for (input, e) in report.failed.iter() {
+        eprintln!("ruscii-gen: {}: {}", input.display(), e);
+    }
+    eprintln!(
+        "ruscii-gen: {} converted, {} up to date, {} failed",
+        report.converted.len(),
+        report.skipped.len(),
+        report.failed.len()
+    );
+    if report.failed.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("{} inputs failed to convert", report.failed.len()))
+    }
+}
+
+fn run_sequence(args: &SequenceArgs) -> Result<(), String> {
+    let config = load_config(args.config.as_deref()).map_err(|e| e.to_string())?;
+    let converter = config.build().map_err(|e| e.to_string())?;
+    let options =
+        SequenceOptions::new(config.edge_threshold).with_animation(args.gif.as_ref().map(|path| {
+            SequenceAnimation {
+                path: path.clone(),
+                fps: args.fps,
+            }
+        }));
+    let report = convert_sequence(
+        converter,
+        &args.pattern,
+        &args.output,
+        &args.name_template,
+        &options,
+    )
+    .map_err(|e| e.to_string())?;
+    for warning in report.warnings.iter() {
+        eprintln!("ruscii-gen: warning: {}", warning);
+    }
+    eprintln!("ruscii-gen: {} frames converted", report.frames.len());
+    Ok(())
+}
+
+fn managed(bytes: &[u8], decoded: Decoded, color_management: bool) -> Decoded {
+    // The decoded input in sRGB, as a converter with color_management would read it
+    if color_management {
+        manage_color(bytes, decoded)
+    } else {
+        decoded
+    }
+}
+
+fn run_diff(args: &DiffArgs) -> Result<(), String> {
+    let config_a = load_config(args.config_a.as_deref()).map_err(|e| e.to_string())?;
+    let config_b = load_config(args.config_b.as_deref()).map_err(|e| e.to_string())?;
+    let (a, b) = (
+        config_a.build().map_err(|e| e.to_string())?,
+        config_b.build().map_err(|e| e.to_string())?,
+    );
+    let bytes = fs::read(&args.input).map_err(|e| format!("{}: {}", args.input.display(), e))?;
+    // Each side reads the input the way its own config decodes it
+    let decode_for = |config: &ConverterConfig| {
+        decode(&bytes, None, config.tolerant_decode)
+            .map(|decoded| managed(&bytes, decoded, config.color_management).image)
+            .map_err(|e| format!("{}: {}", args.input.display(), e))
+    };
+    let img_a = decode_for(&config_a)?;
+    let decoded_b;
+    let img_b = if (config_a.tolerant_decode, config_a.color_management)
+        == (config_b.tolerant_decode, config_b.color_management)
+    {
+        &img_a
+    } else {
+        decoded_b = decode_for(&config_b)?;
+        &decoded_b
+    };
+
+    let grid_a = a
+        .convert_to_chars(&img_a, config_a.edge_threshold)
+        .map_err(|e| e.to_string())?;
+    let grid_b = b
+        .convert_to_chars(img_b, config_b.edge_threshold)
+        .map_err(|e| e.to_string())?;
+    let diff = grid_diff(&grid_a.view(), &grid_b.view()).map_err(|e| e.to_string())?;
+    println!("{}", diff);
+
+    if let Some(output) = &args.output {
+        let render = b
+            .render_diff(&grid_b.view(), &diff, Rgb(args.highlight))
+            .map_err(|e| e.to_string())?;
+        let format = ImageFormat::from_path(output).map_err(|e| e.to_string())?;
+        let mut encoded = Cursor::new(Vec::new());
+        render
+            .write_to(&mut encoded, format)
+            .map_err(|e| e.to_string())?;
+        write_file(output, &encoded.into_inner(), &WriteOptions::default())
+            .map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+fn run_charset_preview(args: &CharsetPreviewArgs) -> Result<(), String> {
+    let charset = match &args.charset {
+        Some(chars) if chars.is_empty() => return Err("--charset is empty".to_string()),
+        Some(chars) => CharacterSet::new(&chars.chars().collect::<Vec<char>>()),
+        None => CharacterSet::default(),
+    };
+    let font = FontSettings::new(args.font_size, &args.font.to_string_lossy());
+    let preview = charset.preview(&font).map_err(|e| e.to_string())?;
+    let format = ImageFormat::from_path(&args.output).map_err(|e| e.to_string())?;
+    let mut encoded = Cursor::new(Vec::new());
+    preview
+        .write_to(&mut encoded, format)
+        .map_err(|e| e.to_string())?;
+    write_file(
+        &args.output,
+        &encoded.into_inner(),
+        &WriteOptions::default(),
+    )
+    .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn run_completions(args: &CompletionsArgs) -> Result<(), String> {
+    let mut command = Cli::command();
+    let name = command.get_name().to_string();
+    clap_complete::generate(args.shell, &mut command, name, &mut io::stdout());
+    Ok(())
+}
+
+fn run_man() -> Result<(), String> {
+    Man::new(Cli::command())
+        .render(&mut io::stdout())
+        .map_err(|e| e.to_string())
+}
+
+#[cfg(feature = "tracing")]
+fn init_tracing(verbose: u8) {
+    use tracing_subscriber::filter::{LevelFilter, Targets};
+    use tracing_subscriber::fmt::format::FmtSpan;
+    use tracing_subscriber::prelude::*;
+
+    let filter = match verbose {
+        0 => return,
+        1 => Targets::new().with_target("ascii_gen", LevelFilter::DEBUG),
+        _ => Targets::new().with_default(LevelFilter::TRACE),
+    };
+    // Closing spans are logged so every stage reports how long it took
+    let fmt = tracing_subscriber::fmt::layer()
+        .with_writer(io::stderr)
+        .with_ansi(io::stderr().is_terminal())
+        .with_span_events(FmtSpan::CLOSE);
+    tracing_subscriber::registry().with(fmt).with(filter).init();
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    #[cfg(feature = "tracing")]
+    init_tracing(cli.verbose);
+    let result = match &cli.command {
+        Some(Command::Watch(args)) => run_watch(args),
+        Some(Command::Batch(args)) => run_batch(args),
+        Some(Command::Diff(args)) => run_diff(args),
+        Some(Command::Sequence(args)) => run_sequence(args),
+        Some(Command::CharsetPreview(args)) => run_charset_preview(args),
+        Some(Command::Completions(args)) => run_completions(args),
+        Some(Command::Man) => run_man(),
+        #[cfg(feature = "tui")]
+        Some(Command::Tune(args)) => load_config(args.config.as_deref())
+            .map_err(|e| e.to_string())
+            .and_then(|config| tui::run_tune(&args.input, &args.output, &args.export, config)),
+        None => run_convert(&cli.convert),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("ruscii-gen: {}", e);
+            ExitCode::FAILURE
+        }
+    }
 }
@@ -0,0 +1,132 @@
+use super::quantize::diffuse_error;
+use super::NEUQUANT_SAMPLE_FAC;
+use crate::ascii::error::ConvertError;
+use color_quant::NeuQuant;
+use gif::{Encoder, Frame, Repeat};
+use image::{ImageBuffer, Rgb};
+use rayon::prelude::*;
+use std::fs::File;
+use std::io::BufWriter;
+use std::ops::Deref;
+
+// Upper bound of pixels fed to NeuQuant across all frames when building the global palette
+const MAX_PALETTE_SAMPLES: usize = 1 << 20;
+const PALETTE_SIZE: usize = 256;
+
+/*
+* Write a sequence of rgb frames into an animated file. Every frame is dithered against a single
+* global palette so colors do not shimmer between frames
+*/
+pub struct AnimationWriter {
+    path: String,
+    // Delay between frames in hundredths of a second
+    delay: u16,
+    dimensions: Option<(u32, u32)>,
+    frames: Vec<Vec<u8>>,
+}
+
+impl AnimationWriter {
+    pub fn gif(path: &str, fps: u32) -> Self {
+        AnimationWriter {
+            path: path.to_string(),
+            delay: (100.0 / fps.max(1) as f32).round().max(1.0) as u16,
+            dimensions: None,
+            frames: vec![],
+        }
+    }
+
+    pub fn push_frame<C: Deref<Target = [u8]>>(
+        &mut self,
+        frame: ImageBuffer<Rgb<u8>, C>,
+    ) -> Result<(), ConvertError> {
+        /*
+         * Queue a frame for writing. Frames are kept in memory until finish() since the global
+         * palette can only be built once every frame has been seen
+         */
+        let found = frame.dimensions();
+        match self.dimensions {
+            Some(expected) if expected != found => {
+                return Err(ConvertError::FrameSizeMismatch { expected, found })
+            }
+            Some(_) => {}
+            None => {
+                // The gif format stores dimensions as u16
+                if found.0 == 0
+                    || found.1 == 0
+                    || found.0 > u16::MAX as u32
+                    || found.1 > u16::MAX as u32
+                {
+                    return Err(ConvertError::ImageError);
+                }
+                self.dimensions = Some(found);
+            }
+        }
+
+        self.frames.push(frame.as_raw().to_vec());
+
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<(), ConvertError> {
+        /*
+         * Build the global palette from pixels sampled across all frames, then dither every frame
+         * to it and write the file
+         */
+        let (w, h) = self.dimensions.ok_or(ConvertError::EmptyAnimation)?;
+        let quantizer = self.build_palette();
+        let palette = quantizer.color_map_rgb();
+
+        let file = BufWriter::new(File::create(&self.path)?);
+        let mut encoder = Encoder::new(file, w as u16, h as u16, &palette)?;
+        encoder.set_repeat(Repeat::Infinite)?;
+
+        // Dithering is the expensive part so frames are mapped in parallel and written in order
+        let indexed_frames: Vec<Vec<u8>> = self
+            .frames
+            .par_iter()
+            .map(|frame| dither_frame(frame, w as usize, h as usize, &quantizer))
+            .collect();
+
+        for indices in indexed_frames {
+            let mut frame = Frame::from_indexed_pixels(w as u16, h as u16, indices, None);
+            frame.delay = self.delay;
+            encoder.write_frame(&frame)?;
+        }
+
+        Ok(())
+    }
+
+    fn build_palette(&self) -> NeuQuant {
+        let total_pixels: usize = self.frames.iter().map(|frame| frame.len() / 3).sum();
+        let stride = (total_pixels / MAX_PALETTE_SAMPLES).max(1);
+
+        // NeuQuant expects rgba pixels
+        let samples: Vec<u8> = self
+            .frames
+            .iter()
+            .flat_map(|frame| frame.chunks_exact(3).step_by(stride))
+            .flat_map(|px| [px[0], px[1], px[2], 255])
+            .collect();
+
+        NeuQuant::new(NEUQUANT_SAMPLE_FAC, PALETTE_SIZE, &samples)
+    }
+}
+
+fn dither_frame(frame: &[u8], w: usize, h: usize, quantizer: &NeuQuant) -> Vec<u8> {
+    /*
+     * Map a frame to palette indices with Floyd-Steinberg error diffusion
+     */
+    let pixel = |x: usize, y: usize| {
+        let offset = (y * w + x) * 3;
+        [frame[offset], frame[offset + 1], frame[offset + 2]]
+    };
+    diffuse_error(w, h, pixel, |color| {
+        let px = [color[0] as u8, color[1] as u8, color[2] as u8, 255];
+        let index = quantizer.index_of(&px);
+        let mapped = quantizer.lookup(index).unwrap_or(px);
+        (
+            index as u8,
+            [mapped[0], mapped[1], mapped[2]].map(|c| c as i32),
+        )
+    })
+}
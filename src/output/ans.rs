@@ -0,0 +1,196 @@
+use super::ansi::ansi16_index;
+use crate::ascii::error::ConvertError;
+use image::Rgb;
+use ndarray::ArrayView2;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Characters of code page 437 from 0x80 to 0xFF, 0x20 to 0x7E match ASCII
+const CP437_HIGH: &str = "ÇüéâäàåçêëèïîìÄÅÉæÆôöòûùÿÖÜ¢£¥₧ƒáíóúñÑªº¿⌐¬½¼¡«»░▒▓│┤╡╢╖╕╣║╗╝╜╛┐└┴┬├─┼╞╟╚╔╩╦╠═╬╧╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀αßΓπΣσµτΦΘΩδ∞φε∩≡±≥≤⌠⌡÷≈°∙·√ⁿ²■\u{a0}";
+
+const SAUCE_LEN: usize = 128;
+// End of file marker that viewers stop reading the art at
+const EOF_MARKER: u8 = 0x1a;
+
+pub fn to_cp437(ch: char) -> Option<u8> {
+    match ch {
+        ' '..='~' => Some(ch as u8),
+        _ => CP437_HIGH
+            .chars()
+            .position(|c| c == ch)
+            .map(|idx| 0x80 + idx as u8),
+    }
+}
+
+/*
+* Writer of ANSI art files, CP437 text with 16 color escape codes followed by a SAUCE record
+* holding the title, author and group. Rows longer than width are wrapped onto the next line.
+* Characters CP437 lacks are an error, or a '?' when lossy
+*/
+pub struct AnsExporter {
+    pub width: usize,
+    pub lossy: bool,
+    pub title: String,
+    pub author: String,
+    pub group: String,
+    // CCYYMMDD, today when None
+    pub date: Option<String>,
+}
+
+impl Default for AnsExporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AnsExporter {
+    pub fn new() -> Self {
+        AnsExporter {
+            width: 80,
+            lossy: false,
+            title: String::new(),
+            author: String::new(),
+            group: String::new(),
+            date: None,
+        }
+    }
+
+    pub fn with_width(mut self, width: usize) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub fn with_lossy(mut self, lossy: bool) -> Self {
+        self.lossy = lossy;
+        self
+    }
+
+    pub fn with_title(mut self, title: &str) -> Self {
+        self.title = title.to_string();
+        self
+    }
+
+    pub fn with_author(mut self, author: &str) -> Self {
+        self.author = author.to_string();
+        self
+    }
+
+    pub fn with_group(mut self, group: &str) -> Self {
+        self.group = group.to_string();
+        self
+    }
+
+    pub fn with_date(mut self, date: &str) -> Self {
+        self.date = Some(date.to_string());
+        self
+    }
+
+    pub fn export(
+        &self,
+        grid: &ArrayView2<char>,
+        colors: &ArrayView2<Rgb<u8>>,
+        bg_color: Rgb<u8>,
+    ) -> Result<Vec<u8>, ConvertError> {
+        /*
+         * Encode the grid as an .ans file. Foregrounds use all 16 colors through the bold
+         * attribute, the background only has the 8 normal ones
+         */
+        if self.width == 0 {
+            return Err(ConvertError::InvalidSetting {
+                field: "width",
+                reason: "must be at least 1",
+            });
+        }
+        let bg = ansi16_index(bg_color, 8);
+        let mut out: Vec<u8> = b"\x1b[0m".to_vec();
+        let mut lines = 0;
+
+        for (row, color_row) in grid.outer_iter().zip(colors.outer_iter()) {
+            let cells: Vec<(char, Rgb<u8>)> =
+                row.iter().copied().zip(color_row.iter().copied()).collect();
+            for line in cells.chunks(self.width) {
+                let mut prev_fg = None;
+                for &(ch, color) in line {
+                    let fg = ansi16_index(color, 16);
+                    if prev_fg != Some(fg) {
+                        let bold = if fg >= 8 { "1;" } else { "" };
+                        out.extend(
+                            format!("\x1b[0;{}{};{}m", bold, 30 + fg % 8, 40 + bg).as_bytes(),
+                        );
+                        prev_fg = Some(fg);
+                    }
+                    out.push(self.encode(ch)?);
+                }
+                out.extend(b"\r\n");
+                lines += 1;
+            }
+        }
+        out.extend(b"\x1b[0m");
+
+        let file_size = out.len() as u32;
+        let cols = grid.dim().1.min(self.width);
+        out.push(EOF_MARKER);
+        out.extend(self.sauce(file_size, cols as u16, lines as u16));
+        Ok(out)
+    }
+
+    fn encode(&self, ch: char) -> Result<u8, ConvertError> {
+        match to_cp437(ch) {
+            Some(byte) => Ok(byte),
+            None if self.lossy => Ok(b'?'),
+            None => Err(ConvertError::UnmappableChar(ch)),
+        }
+    }
+
+    fn sauce(&self, file_size: u32, cols: u16, lines: u16) -> Vec<u8> {
+        /*
+         * 128 byte SAUCE 00 record describing an ANSi character file
+         */
+        let mut sauce = Vec::with_capacity(SAUCE_LEN);
+        sauce.extend(b"SAUCE00");
+        sauce.extend(padded(&self.title, 35));
+        sauce.extend(padded(&self.author, 20));
+        sauce.extend(padded(&self.group, 20));
+        let date = self.date.clone().unwrap_or_else(today);
+        sauce.extend(padded(&date, 8));
+        sauce.extend(file_size.to_le_bytes());
+        // DataType Character, FileType ANSi
+        sauce.extend([1, 1]);
+        sauce.extend(cols.to_le_bytes());
+        sauce.extend(lines.to_le_bytes());
+        // TInfo3, TInfo4, no comments and no flags
+        sauce.extend([0; 6]);
+        let mut font = b"IBM VGA".to_vec();
+        font.resize(22, 0);
+        sauce.extend(font);
+        sauce
+    }
+}
+
+fn padded(text: &str, len: usize) -> Vec<u8> {
+    // SAUCE strings are CP437, cut to the field length and padded with spaces
+    let mut bytes: Vec<u8> = text
+        .chars()
+        .map(|ch| to_cp437(ch).unwrap_or(b'?'))
+        .take(len)
+        .collect();
+    bytes.resize(len, b' ');
+    bytes
+}
+
+fn today() -> String {
+    // Civil date from days since the epoch, after Howard Hinnant's days_from_civil inverse
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() / 86400)
+        .unwrap_or(0) as i64;
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}{:02}{:02}", year, month, day)
+}
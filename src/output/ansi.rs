@@ -0,0 +1,159 @@
+use super::trim::line_len;
+use image::Rgb;
+use ndarray::{s, ArrayView1, ArrayView2, Axis};
+use rayon::prelude::*;
+use std::io;
+
+// Longest 24-bit color escape, "\x1b[38;2;255;255;255m"
+const MAX_ESCAPE_LEN: usize = 19;
+pub(crate) const RESET_LINE: &str = "\x1b[0m\n";
+
+pub(crate) fn max_ansi_len(rows: usize, cols: usize) -> usize {
+    // Characters of the ANSI text of a rows x cols grid at most, with every cell changing color
+    rows * (MAX_ESCAPE_LEN + cols * (1 + MAX_ESCAPE_LEN) + RESET_LINE.len())
+}
+
+pub fn grid_to_ansi(
+    grid: &ArrayView2<char>,
+    colors: &ArrayView2<Rgb<u8>>,
+    bg_color: Rgb<u8>,
+) -> String {
+    /*
+     * Render the character grid as 24-bit ANSI colored text. A color escape is only emitted when
+     * the color changes from the previous cell to keep the output small. Rows are built in
+     * parallel into their own buffers and joined, since large grids make strings of megabytes
+     */
+    grid_to_ansi_with(grid, colors, bg_color, false)
+}
+
+pub(crate) fn grid_to_ansi_with(
+    grid: &ArrayView2<char>,
+    colors: &ArrayView2<Rgb<u8>>,
+    bg_color: Rgb<u8>,
+    trim_trailing: bool,
+) -> String {
+    let rows: Vec<String> = grid
+        .axis_iter(Axis(0))
+        .into_par_iter()
+        .zip(colors.axis_iter(Axis(0)).into_par_iter())
+        .map(|(row, color_row)| {
+            let len = line_len(&row, trim_trailing);
+            ansi_row(
+                row.slice_move(s![..len]),
+                color_row.slice_move(s![..len]),
+                bg_color,
+            )
+        })
+        .collect();
+    join_rows(&rows)
+}
+
+pub(crate) fn write_ansi_rows(
+    grid: &ArrayView2<char>,
+    colors: &ArrayView2<Rgb<u8>>,
+    bg_color: Rgb<u8>,
+    trim_trailing: bool,
+    w: &mut impl io::Write,
+) -> io::Result<()> {
+    // grid_to_ansi_with written row by row, only one row held at a time
+    for (row, color_row) in grid.axis_iter(Axis(0)).zip(colors.axis_iter(Axis(0))) {
+        let len = line_len(&row, trim_trailing);
+        let text = ansi_row(
+            row.slice_move(s![..len]),
+            color_row.slice_move(s![..len]),
+            bg_color,
+        );
+        w.write_all(text.as_bytes())?;
+    }
+    Ok(())
+}
+
+fn ansi_row(row: ArrayView1<char>, color_row: ArrayView1<Rgb<u8>>, bg_color: Rgb<u8>) -> String {
+    // Sized for the characters and one escape per run of cells of the same color
+    let mut len = MAX_ESCAPE_LEN + RESET_LINE.len();
+    let mut prev_color = None;
+    for (&ch, &color) in row.iter().zip(color_row.iter()) {
+        if prev_color != Some(color) {
+            len += MAX_ESCAPE_LEN;
+            prev_color = Some(color);
+        }
+        len += ch.len_utf8();
+    }
+
+    let mut text = String::with_capacity(len);
+    push_color_escape(&mut text, "\x1b[48;2;", bg_color);
+    let mut prev_color = None;
+    for (&ch, &color) in row.iter().zip(color_row.iter()) {
+        if prev_color != Some(color) {
+            push_color_escape(&mut text, "\x1b[38;2;", color);
+            prev_color = Some(color);
+        }
+        text.push(ch);
+    }
+    // Reset before the newline so the background does not bleed into the rest of the line
+    text.push_str(RESET_LINE);
+    text
+}
+
+fn push_color_escape(text: &mut String, prefix: &str, color: Rgb<u8>) {
+    // prefix then "r;g;bm", written digit by digit instead of going through format!
+    text.push_str(prefix);
+    for (i, &c) in color.0.iter().enumerate() {
+        if i > 0 {
+            text.push(';');
+        }
+        if c >= 100 {
+            text.push((b'0' + c / 100) as char);
+        }
+        if c >= 10 {
+            text.push((b'0' + c / 10 % 10) as char);
+        }
+        text.push((b'0' + c % 10) as char);
+    }
+    text.push('m');
+}
+
+pub(crate) fn join_rows(rows: &[String]) -> String {
+    let mut text = String::with_capacity(rows.iter().map(String::len).sum());
+    for row in rows {
+        text.push_str(row);
+    }
+    text
+}
+
+// Standard VGA colors of the 16 color ANSI palette, the 8 normal colors then their bright versions
+pub const ANSI16_PALETTE: [Rgb<u8>; 16] = [
+    Rgb([0, 0, 0]),
+    Rgb([170, 0, 0]),
+    Rgb([0, 170, 0]),
+    Rgb([170, 85, 0]),
+    Rgb([0, 0, 170]),
+    Rgb([170, 0, 170]),
+    Rgb([0, 170, 170]),
+    Rgb([170, 170, 170]),
+    Rgb([85, 85, 85]),
+    Rgb([255, 85, 85]),
+    Rgb([85, 255, 85]),
+    Rgb([255, 255, 85]),
+    Rgb([85, 85, 255]),
+    Rgb([255, 85, 255]),
+    Rgb([85, 255, 255]),
+    Rgb([255, 255, 255]),
+];
+
+pub fn ansi16_index(color: Rgb<u8>, levels: usize) -> usize {
+    /*
+     * Index of the closest of the first levels colors of the 16 color palette, by squared RGB
+     * distance. levels is 8 where only the normal colors are available, such as backgrounds
+     */
+    ANSI16_PALETTE[..levels.clamp(1, 16)]
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, p)| {
+            (0..3)
+                .map(|c| (p[c] as i32 - color[c] as i32).pow(2))
+                .sum::<i32>()
+        })
+        .map(|(idx, _)| idx)
+        .unwrap_or(0)
+}
@@ -0,0 +1,213 @@
+use super::ansi::{join_rows, ANSI16_PALETTE, RESET_LINE};
+use crate::ascii::error::ConvertError;
+use image::Rgb;
+use ndarray::{Array2, ArrayView2, Axis, Zip};
+use std::collections::HashMap;
+use std::fmt::Write;
+
+// Largest palette picked for a sequence by default, small enough to keep the escapes short
+pub const DEFAULT_SEQUENCE_COLORS: usize = 64;
+// Levels of every channel of the 6x6x6 color cube of the 256 color palette
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+// The 16 system colors before the cube are themed by the terminal, so they are never picked
+const FIRST_FIXED_INDEX: u8 = 16;
+
+pub fn xterm256_color(index: u8) -> Rgb<u8> {
+    /*
+     * Color of an index of the 256 color palette from the color cube or the gray ramp. The system
+     * colors below FIRST_FIXED_INDEX take the VGA colors of ANSI16_PALETTE
+     */
+    match index {
+        0..=15 => ANSI16_PALETTE[index as usize],
+        16..=231 => {
+            let cube = index - 16;
+            Rgb([cube / 36, cube / 6 % 6, cube % 6].map(|level| CUBE_LEVELS[level as usize]))
+        }
+        232..=255 => Rgb([8 + 10 * (index - 232); 3]),
+    }
+}
+
+fn nearest_of(color: Rgb<u8>, palette: impl Iterator<Item = u8>) -> u8 {
+    // Closest palette index by squared RGB distance, the lowest index on ties
+    palette
+        .min_by_key(|&index| {
+            let candidate = xterm256_color(index);
+            let distance: i32 = (0..3)
+                .map(|c| (candidate[c] as i32 - color[c] as i32).pow(2))
+                .sum();
+            (distance, index)
+        })
+        .unwrap_or(FIRST_FIXED_INDEX)
+}
+
+pub fn xterm256_index(color: Rgb<u8>) -> u8 {
+    // Closest fixed color of the 256 color palette, the system colors left out
+    nearest_of(color, FIRST_FIXED_INDEX..=255)
+}
+
+/*
+* Frames of an animation drawn in 256 color ANSI against one palette, as built by
+* AnsiSequenceRenderer::finish. indices holds the palette index of every cell of every frame
+*/
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnsiSequence {
+    // Indices of the 256 color palette the glyphs are drawn in, at most max_colors of them
+    pub palette: Vec<u8>,
+    // Index of the background, the closest to the background color whether in palette or not
+    pub bg_index: u8,
+    pub indices: Vec<Array2<u8>>,
+    pub frames: Vec<String>,
+}
+
+impl AnsiSequence {
+    pub fn playback(&self) -> String {
+        /*
+         * Every frame drawn over the one before, for a terminal or an asciinema style recording.
+         * The screen is cleared once, then every frame starts from the top left corner
+         */
+        let mut text = String::from("\x1b[2J");
+        for frame in self.frames.iter() {
+            text.push_str("\x1b[H");
+            text.push_str(frame);
+        }
+        text
+    }
+}
+
+/*
+* Render a sequence of grids to 256 color ANSI without the colors flickering between frames.
+* Mapping every frame on its own sends colors near the boundary of two palette entries to one or
+* the other from frame to frame. Instead, the frames are kept until finish, which samples the
+* colors of all of them, picks a palette of at most max_colors entries once, and maps every color
+* to it the same way in every frame
+*/
+pub struct AnsiSequenceRenderer {
+    bg_color: Rgb<u8>,
+    max_colors: usize,
+    // Size of the frames as (rows, cols), set by the first frame
+    dimensions: Option<(usize, usize)>,
+    frames: Vec<(Array2<char>, Array2<Rgb<u8>>)>,
+}
+
+impl AnsiSequenceRenderer {
+    pub fn new(frames_hint: usize) -> Self {
+        // frames_hint is the number of frames expected, only used to reserve room for them
+        AnsiSequenceRenderer {
+            bg_color: Rgb([0, 0, 0]),
+            max_colors: DEFAULT_SEQUENCE_COLORS,
+            dimensions: None,
+            frames: Vec::with_capacity(frames_hint),
+        }
+    }
+
+    pub fn with_bg_color(mut self, bg_color: Rgb<u8>) -> Self {
+        self.bg_color = bg_color;
+        self
+    }
+
+    pub fn with_max_colors(mut self, max_colors: usize) -> Self {
+        self.max_colors = max_colors;
+        self
+    }
+
+    pub fn push_frame(
+        &mut self,
+        grid: &ArrayView2<char>,
+        colors: &ArrayView2<Rgb<u8>>,
+    ) -> Result<(), ConvertError> {
+        /*
+         * Queue a grid and the colors of its cells. Every frame needs the size of the first
+         */
+        if grid.dim() != colors.dim() {
+            return Err(ConvertError::NdArrayShapeError);
+        }
+        let found = grid.dim();
+        match self.dimensions {
+            Some(expected) if expected != found => {
+                return Err(ConvertError::FrameSizeMismatch {
+                    expected: (expected.1 as u32, expected.0 as u32),
+                    found: (found.1 as u32, found.0 as u32),
+                })
+            }
+            Some(_) => {}
+            None => self.dimensions = Some(found),
+        }
+        self.frames.push((grid.to_owned(), colors.to_owned()));
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<AnsiSequence, ConvertError> {
+        /*
+         * Pick the palette from the colors of every frame, then render every frame against it.
+         * The palette holds the most used of the closest 256 color entries of the cell colors,
+         * and every color goes to its closest entry in the palette
+         */
+        if !(1..=(256 - FIRST_FIXED_INDEX as usize)).contains(&self.max_colors) {
+            return Err(ConvertError::InvalidSetting {
+                field: "max_colors",
+                reason: "must be between 1 and 240",
+            });
+        }
+        if self.frames.is_empty() {
+            return Err(ConvertError::EmptyAnimation);
+        }
+
+        // First pass, how many cells of all frames fall closest to every entry
+        let mut closest: HashMap<Rgb<u8>, u8> = HashMap::new();
+        let mut uses: HashMap<u8, usize> = HashMap::new();
+        for (_, colors) in self.frames.iter() {
+            for &color in colors.iter() {
+                let index = *closest
+                    .entry(color)
+                    .or_insert_with(|| xterm256_index(color));
+                *uses.entry(index).or_insert(0) += 1;
+            }
+        }
+        let mut palette: Vec<(u8, usize)> = uses.into_iter().collect();
+        // Ties go to the lower index so the palette does not depend on the hash order
+        palette.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        palette.truncate(self.max_colors);
+        let mut palette: Vec<u8> = palette.into_iter().map(|(index, _)| index).collect();
+        palette.sort_unstable();
+
+        // Second pass, every distinct color mapped once
+        let mapping: HashMap<Rgb<u8>, u8> = closest
+            .keys()
+            .map(|&color| (color, nearest_of(color, palette.iter().copied())))
+            .collect();
+        let bg_index = xterm256_index(self.bg_color);
+        let mut indices = Vec::with_capacity(self.frames.len());
+        let mut frames = Vec::with_capacity(self.frames.len());
+        for (grid, colors) in self.frames.iter() {
+            let frame_indices = colors.mapv(|color| mapping[&color]);
+            frames.push(indexed_frame(&grid.view(), &frame_indices.view(), bg_index));
+            indices.push(frame_indices);
+        }
+        Ok(AnsiSequence {
+            palette,
+            bg_index,
+            indices,
+            frames,
+        })
+    }
+}
+
+fn indexed_frame(grid: &ArrayView2<char>, indices: &ArrayView2<u8>, bg_index: u8) -> String {
+    // Rows as in grid_to_ansi, with 256 color escapes emitted when the index changes
+    let mut rows: Vec<String> = Vec::with_capacity(grid.nrows());
+    for (row, index_row) in grid.axis_iter(Axis(0)).zip(indices.axis_iter(Axis(0))) {
+        let mut text = format!("\x1b[48;5;{}m", bg_index);
+        let mut prev = None;
+        Zip::from(&row).and(&index_row).for_each(|&ch, &index| {
+            if prev != Some(index) {
+                // Writing to a String does not fail
+                let _ = write!(text, "\x1b[38;5;{}m", index);
+                prev = Some(index);
+            }
+            text.push(ch);
+        });
+        text.push_str(RESET_LINE);
+        rows.push(text);
+    }
+    join_rows(&rows)
+}
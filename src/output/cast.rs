@@ -0,0 +1,95 @@
+use crate::ascii::error::ConvertError;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+// Moves the cursor to the top left corner, so every frame draws over the one before
+const CURSOR_HOME: &str = "\x1b[H";
+
+/*
+* First line of an asciinema v2 recording, the size of the terminal in cells
+*/
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CastHeader {
+    pub version: u32,
+    pub width: usize,
+    pub height: usize,
+}
+
+fn frame_size(frame: &str) -> (usize, usize) {
+    /*
+     * Size of an ANSI frame in cells as (cols, rows): its number of lines and the widest of them
+     * once the escape sequences are taken out
+     */
+    let mut cols = 0;
+    let mut rows = 0;
+    for line in frame.lines() {
+        let mut width = 0;
+        let mut chars = line.chars();
+        while let Some(ch) = chars.next() {
+            match ch {
+                // A control sequence runs up to its final byte, from @ to ~
+                '\x1b' => {
+                    if chars.next() == Some('[') {
+                        for ch in chars.by_ref() {
+                            if ('@'..='~').contains(&ch) {
+                                break;
+                            }
+                        }
+                    }
+                }
+                '\r' => {}
+                _ => width += 1,
+            }
+        }
+        cols = cols.max(width);
+        rows += 1;
+    }
+    (cols, rows)
+}
+
+pub fn frames_to_cast(
+    frames: &[String],
+    cols: usize,
+    rows: usize,
+    frame_duration: Duration,
+) -> Result<String, ConvertError> {
+    /*
+     * asciinema v2 recording of ANSI frames, such as the ones of an AnsiSequence, played one
+     * every frame_duration in a terminal of cols x rows cells. Every frame is one output event
+     * moving the cursor home first. Frames larger than the terminal would scroll it, so they are
+     * an error
+     */
+    if frames.is_empty() {
+        return Err(ConvertError::EmptyAnimation);
+    }
+    if cols == 0 || rows == 0 {
+        return Err(ConvertError::InvalidSetting {
+            field: "cast size",
+            reason: "must be at least one cell in both directions",
+        });
+    }
+    let header = CastHeader {
+        version: 2,
+        width: cols,
+        height: rows,
+    };
+    // Plain structs and strings always serialize
+    let mut cast = serde_json::to_string(&header).expect("Failed serializing cast header");
+    cast.push('\n');
+    for (i, frame) in frames.iter().enumerate() {
+        let found = frame_size(frame);
+        if found.0 > cols || found.1 > rows {
+            return Err(ConvertError::FrameSizeMismatch {
+                expected: (cols as u32, rows as u32),
+                found: (found.0 as u32, found.1 as u32),
+            });
+        }
+        // Terminals recorded in raw mode return the carriage at every line break
+        let data = format!("{}{}", CURSOR_HOME, frame.replace('\n', "\r\n"));
+        let time = frame_duration.as_secs_f64() * i as f64;
+        let event = serde_json::to_string(&(time, "o", data)).expect("Failed serializing event");
+        cast.push_str(&event);
+        cast.push('\n');
+    }
+    Ok(cast)
+}
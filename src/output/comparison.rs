@@ -0,0 +1,59 @@
+use image::imageops::{overlay, FilterType};
+use image::{DynamicImage, ImageBuffer, Rgb, RgbImage};
+
+/*
+* Strip drawn between the original and the render in a side by side comparison
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Divider {
+    pub width: u32,
+    pub color: Rgb<u8>,
+}
+
+impl Divider {
+    pub fn new(width: u32, color: Rgb<u8>) -> Self {
+        Divider { width, color }
+    }
+}
+
+impl Default for Divider {
+    fn default() -> Self {
+        Divider {
+            width: 4,
+            color: Rgb([255, 255, 255]),
+        }
+    }
+}
+
+pub fn side_by_side(
+    ori_img: &DynamicImage,
+    render: &ImageBuffer<Rgb<u8>, Vec<u8>>,
+    divider: &Divider,
+    bg_color: Rgb<u8>,
+) -> RgbImage {
+    /*
+     * Place the original, scaled to the height of the render with its aspect kept, left of the
+     * render. Whichever side ends up shorter from rounding is padded with bg_color
+     */
+    let (render_w, render_h) = render.dimensions();
+    let scale = render_h as f64 / ori_img.height().max(1) as f64;
+    let ori_w = ((ori_img.width() as f64 * scale).round() as u32).max(1);
+    let ori_h = ((ori_img.height() as f64 * scale).round() as u32).max(1);
+    let original = ori_img
+        .resize_exact(ori_w, ori_h, FilterType::Triangle)
+        .to_rgb8();
+
+    let mut out = RgbImage::from_pixel(
+        ori_w + divider.width + render_w,
+        ori_h.max(render_h),
+        bg_color,
+    );
+    for y in 0..out.height() {
+        for x in ori_w..ori_w + divider.width {
+            out.put_pixel(x, y, divider.color);
+        }
+    }
+    overlay(&mut out, &original, 0, 0);
+    overlay(&mut out, render, (ori_w + divider.width) as i64, 0);
+    out
+}
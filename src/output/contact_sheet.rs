@@ -0,0 +1,115 @@
+use crate::ascii::watermark::{Corner, Watermark};
+use ab_glyph::{Font, PxScale, ScaleFont};
+use image::imageops::{overlay, FilterType};
+use image::{Rgb, RgbImage};
+
+// Size of the label text relative to the font size of the renders
+const LABEL_SCALE: f32 = 2.0;
+
+/*
+* Placement of the tiles of a contact sheet. Tiles fill a grid of cols x rows cells of
+* cell_w x cell_h pixels row by row, each with a label_h pixel strip for its label under it, gap
+* pixels apart and from the border. The grid is as square as it can be, wider than high when it
+* cannot be square
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SheetLayout {
+    pub cols: u32,
+    pub rows: u32,
+    pub cell_w: u32,
+    pub cell_h: u32,
+    pub label_h: u32,
+    pub gap: u32,
+}
+
+impl SheetLayout {
+    pub fn new(
+        count: usize,
+        (cell_w, cell_h): (u32, u32),
+        font: &impl Font,
+        font_size: u32,
+    ) -> Self {
+        let count = count.max(1) as u32;
+        let cols = (count as f32).sqrt().ceil() as u32;
+        let rows = count.div_ceil(cols);
+        let label_px = font_size as f32 * LABEL_SCALE;
+        let line_h = font.as_scaled(PxScale::from(label_px)).height().ceil() as u32;
+        SheetLayout {
+            cols,
+            rows,
+            cell_w,
+            cell_h,
+            label_h: line_h + font_size,
+            gap: font_size,
+        }
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (
+            self.cols * self.cell_w + (self.cols + 1) * self.gap,
+            self.rows * (self.cell_h + self.label_h) + (self.rows + 1) * self.gap,
+        )
+    }
+
+    pub fn tile_origin(&self, index: usize) -> (u32, u32) {
+        let (col, row) = (index as u32 % self.cols, index as u32 / self.cols);
+        (
+            self.gap + col * (self.cell_w + self.gap),
+            self.gap + row * (self.cell_h + self.label_h + self.gap),
+        )
+    }
+
+    pub fn label_origin(&self, index: usize) -> (u32, u32) {
+        let (x, y) = self.tile_origin(index);
+        (x, y + self.cell_h)
+    }
+}
+
+pub fn contact_sheet(
+    tiles: &[(String, RgbImage)],
+    font: &impl Font,
+    font_size: u32,
+    bg_color: Rgb<u8>,
+    label_color: Rgb<u8>,
+) -> RgbImage {
+    /*
+     * Lay out labeled renders on one image. The cells are the size of the largest render, smaller
+     * ones are scaled up to fit with their aspect kept and centered. Labels are drawn the way
+     * watermarks are, shrunk and then cut short when wider than their cell
+     */
+    let cell = tiles.iter().fold((1, 1), |(w, h), (_, render)| {
+        (w.max(render.width()), h.max(render.height()))
+    });
+    let layout = SheetLayout::new(tiles.len(), cell, font, font_size);
+    let (w, h) = layout.size();
+    let mut sheet = RgbImage::from_pixel(w, h, bg_color);
+
+    for (i, (label, render)) in tiles.iter().enumerate() {
+        let scale = (layout.cell_w as f64 / render.width().max(1) as f64)
+            .min(layout.cell_h as f64 / render.height().max(1) as f64);
+        let fit_w = ((render.width() as f64 * scale).round() as u32).clamp(1, layout.cell_w);
+        let fit_h = ((render.height() as f64 * scale).round() as u32).clamp(1, layout.cell_h);
+        let (x, y) = layout.tile_origin(i);
+        let (ox, oy) = (
+            x + (layout.cell_w - fit_w) / 2,
+            y + (layout.cell_h - fit_h) / 2,
+        );
+        if (fit_w, fit_h) == render.dimensions() {
+            overlay(&mut sheet, render, ox as i64, oy as i64);
+        } else {
+            let fitted = image::imageops::resize(render, fit_w, fit_h, FilterType::Triangle);
+            overlay(&mut sheet, &fitted, ox as i64, oy as i64);
+        }
+
+        let mut strip = RgbImage::from_pixel(layout.cell_w, layout.label_h, bg_color);
+        Watermark::new(label)
+            .with_corner(Corner::TopLeft)
+            .with_color(label_color)
+            .with_padding(font_size / 2)
+            .with_scale(LABEL_SCALE)
+            .draw(&mut strip, font, font_size);
+        let (lx, ly) = layout.label_origin(i);
+        overlay(&mut sheet, &strip, lx as i64, ly as i64);
+    }
+    sheet
+}
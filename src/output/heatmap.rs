@@ -0,0 +1,54 @@
+use crate::ascii::cell::CellValue;
+use image::{Rgb, RgbImage};
+use ndarray::ArrayView2;
+
+// Samples of the viridis colormap at eighths, from dark purple through teal to yellow
+const VIRIDIS: [[u8; 3]; 9] = [
+    [68, 1, 84],
+    [71, 44, 122],
+    [59, 81, 139],
+    [44, 113, 142],
+    [33, 144, 141],
+    [39, 173, 129],
+    [92, 200, 99],
+    [170, 220, 50],
+    [253, 231, 37],
+];
+
+// Flat color of edge cells, which appears nowhere in viridis
+pub const HEATMAP_EDGE_COLOR: Rgb<u8> = Rgb([255, 0, 255]);
+
+pub fn viridis(t: f32) -> Rgb<u8> {
+    /*
+     * Color of t, clamped to 0..=1, interpolated linearly between the viridis samples. Its
+     * brightness rises with t, so the order of the values survives a grayscale print
+     */
+    let pos = t.clamp(0.0, 1.0) * (VIRIDIS.len() - 1) as f32;
+    let i = (pos.floor() as usize).min(VIRIDIS.len() - 2);
+    let frac = pos - i as f32;
+    let (a, b) = (VIRIDIS[i], VIRIDIS[i + 1]);
+    Rgb([0, 1, 2].map(|c| (a[c] as f32 + (b[c] as f32 - a[c] as f32) * frac).round() as u8))
+}
+
+pub fn render_bucket_heatmap(
+    cells: &ArrayView2<CellValue>,
+    buckets: usize,
+    cell_px: u32,
+) -> RgbImage {
+    /*
+     * Debug view of the tile ramp: every cell a cell_px square colored by its tile bucket out of
+     * buckets through viridis, from the first bucket in dark purple to the last in yellow, and
+     * edge cells in HEATMAP_EDGE_COLOR
+     */
+    let cell_px = cell_px.max(1);
+    let (rows, cols) = cells.dim();
+    let last = buckets.saturating_sub(1).max(1) as f32;
+    RgbImage::from_fn(
+        cols as u32 * cell_px,
+        rows as u32 * cell_px,
+        |x, y| match cells[((y / cell_px) as usize, (x / cell_px) as usize)] {
+            CellValue::Tile(bucket) => viridis(bucket as f32 / last),
+            CellValue::Edge(_) => HEATMAP_EDGE_COLOR,
+        },
+    )
+}
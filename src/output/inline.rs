@@ -0,0 +1,80 @@
+use crate::ascii::error::ConvertError;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use image::{ImageBuffer, ImageFormat, Rgb};
+use std::env;
+use std::io::Cursor;
+
+// Kitty takes the base64 payload in chunks of at most 4096 bytes
+pub const KITTY_CHUNK_SIZE: usize = 4096;
+
+/*
+* Terminal graphics protocols that show a PNG inline. Kitty's graphics protocol is also spoken by
+* ghostty, iTerm2's OSC 1337 by WezTerm
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InlineImageProtocol {
+    Kitty,
+    Iterm2,
+}
+
+impl InlineImageProtocol {
+    pub fn detect() -> Option<Self> {
+        Self::detect_from(
+            env::var("TERM").ok().as_deref(),
+            env::var("TERM_PROGRAM").ok().as_deref(),
+        )
+    }
+
+    pub fn detect_from(term: Option<&str>, term_program: Option<&str>) -> Option<Self> {
+        /*
+         * Protocol the terminal described by $TERM and $TERM_PROGRAM understands, None when it
+         * is not known to show inline images
+         */
+        match (term.unwrap_or(""), term_program.unwrap_or("")) {
+            (term, _) if term.contains("kitty") || term.contains("ghostty") => Some(Self::Kitty),
+            (_, "ghostty") => Some(Self::Kitty),
+            (_, "iTerm.app" | "WezTerm") => Some(Self::Iterm2),
+            _ => None,
+        }
+    }
+}
+
+pub fn encode_inline(
+    img: &ImageBuffer<Rgb<u8>, Vec<u8>>,
+    protocol: InlineImageProtocol,
+) -> Result<String, ConvertError> {
+    /*
+     * Escape sequence showing the image at the cursor, as a base64 PNG payload
+     */
+    let mut png = Cursor::new(Vec::new());
+    img.write_to(&mut png, ImageFormat::Png)?;
+    let png = png.into_inner();
+    let payload = STANDARD.encode(&png);
+
+    let out = match protocol {
+        InlineImageProtocol::Kitty => {
+            // Transmit and display a PNG, m=1 on every chunk but the last
+            let chunks: Vec<&[u8]> = payload.as_bytes().chunks(KITTY_CHUNK_SIZE).collect();
+            let mut out = String::with_capacity(payload.len() + chunks.len() * 16);
+            for (i, chunk) in chunks.iter().enumerate() {
+                let more = u8::from(i + 1 < chunks.len());
+                out.push_str("\x1b_G");
+                if i == 0 {
+                    out.push_str("a=T,f=100,");
+                }
+                out.push_str(&format!("m={};", more));
+                // Base64 is always ascii
+                out.push_str(std::str::from_utf8(chunk).unwrap_or_default());
+                out.push_str("\x1b\\");
+            }
+            out
+        }
+        InlineImageProtocol::Iterm2 => format!(
+            "\x1b]1337;File=inline=1;size={};preserveAspectRatio=1:{}\x07",
+            png.len(),
+            payload
+        ),
+    };
+    Ok(out)
+}
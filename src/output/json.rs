@@ -0,0 +1,128 @@
+use crate::ascii::source_map::{SourceMap, SourceRect};
+use image::Rgb;
+use ndarray::ArrayView2;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/*
+* Verbose lists every cell with its character and color. Compact keeps one string of characters
+* per row and indexes every cell into a palette of the distinct colors, which stays small for
+* large grids
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum JsonLayout {
+    #[default]
+    Verbose,
+    Compact,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct JsonCell {
+    pub ch: char,
+    pub color: String,
+    // Input pixels the cell was sampled from, only with a source map
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub src: Option<SourceRect>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct JsonGrid {
+    pub cols: usize,
+    pub rows: usize,
+    pub cells: Vec<Vec<JsonCell>>,
+    pub bg: String,
+    pub font_size: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CompactJsonGrid {
+    pub cols: usize,
+    pub rows: usize,
+    pub chars: Vec<String>,
+    pub palette: Vec<String>,
+    pub colors: Vec<Vec<usize>>,
+    pub bg: String,
+    pub font_size: u32,
+    // Pixel edges of the columns and rows in the input, only with a source map
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub src: Option<SourceMap>,
+}
+
+pub fn hex_color(color: Rgb<u8>) -> String {
+    format!("#{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+}
+
+pub fn grid_to_json(
+    grid: &ArrayView2<char>,
+    colors: &ArrayView2<Rgb<u8>>,
+    bg_color: Rgb<u8>,
+    font_size: u32,
+    layout: JsonLayout,
+    source_map: Option<&SourceMap>,
+) -> String {
+    /*
+     * Serialize the character grid with the color of every cell for consumers that draw the
+     * characters themselves. With a source map, verbose cells carry their source rectangle and
+     * compact grids the edges of their columns and rows, which take far less room
+     */
+    let (rows, cols) = grid.dim();
+    let bg = hex_color(bg_color);
+    let json = match layout {
+        JsonLayout::Verbose => {
+            let cells = grid
+                .outer_iter()
+                .zip(colors.outer_iter())
+                .enumerate()
+                .map(|(y, (row, color_row))| {
+                    row.iter()
+                        .zip(color_row.iter())
+                        .enumerate()
+                        .map(|(x, (&ch, &color))| JsonCell {
+                            ch,
+                            color: hex_color(color),
+                            src: source_map.map(|map| map.rect(y, x)),
+                        })
+                        .collect()
+                })
+                .collect();
+            serde_json::to_string(&JsonGrid {
+                cols,
+                rows,
+                cells,
+                bg,
+                font_size,
+            })
+        }
+        JsonLayout::Compact => {
+            // Palette in order of first use
+            let mut palette: Vec<Rgb<u8>> = vec![];
+            let mut palette_idx: HashMap<Rgb<u8>, usize> = HashMap::new();
+            let colors = colors
+                .outer_iter()
+                .map(|color_row| {
+                    color_row
+                        .iter()
+                        .map(|&color| {
+                            *palette_idx.entry(color).or_insert_with(|| {
+                                palette.push(color);
+                                palette.len() - 1
+                            })
+                        })
+                        .collect()
+                })
+                .collect();
+            serde_json::to_string(&CompactJsonGrid {
+                cols,
+                rows,
+                chars: grid.outer_iter().map(|row| row.iter().collect()).collect(),
+                palette: palette.into_iter().map(hex_color).collect(),
+                colors,
+                bg,
+                font_size,
+                src: source_map.cloned(),
+            })
+        }
+    };
+    // Plain structs with string keys always serialize
+    json.expect("Failed serializing grid")
+}
@@ -0,0 +1,129 @@
+use crate::ascii::error::ConvertError;
+use image::{ImageBuffer, Rgb};
+use png::{BitDepth, ColorType, Decoder, Encoder};
+use std::io::{Read, Write};
+
+pub const CONFIG_KEYWORD: &str = "ruscii-gen:config";
+pub const VERSION_KEYWORD: &str = "ruscii-gen:version";
+pub const CONFIG_HASH_KEYWORD: &str = "ruscii-gen:config-hash";
+
+/*
+* Text chunks ruscii-gen leaves in the PNG outputs it writes
+*/
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PngMetadata {
+    // TOML of the ConverterConfig the image was converted with
+    pub config: Option<String>,
+    // Crate version that wrote the image
+    pub version: Option<String>,
+    // ConverterConfig::config_hash of the config
+    pub config_hash: Option<String>,
+}
+
+pub fn encode_png(
+    img: &ImageBuffer<Rgb<u8>, Vec<u8>>,
+    config: Option<&str>,
+    config_hash: Option<&str>,
+) -> Result<Vec<u8>, ConvertError> {
+    /*
+     * Encode an rgb image as PNG, embedding the config and the crate version when a config is
+     * given, and the hash of the config when one is given
+     */
+    let mut out = Vec::new();
+    let mut encoder = Encoder::new(&mut out, img.width(), img.height());
+    encoder.set_color(ColorType::Rgb);
+    encoder.set_depth(BitDepth::Eight);
+    add_text_chunks(&mut encoder, config, config_hash)?;
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(img.as_raw())?;
+    writer.finish()?;
+    Ok(out)
+}
+
+pub fn encode_indexed_png(
+    (width, height): (u32, u32),
+    palette: &[Rgb<u8>],
+    indices: &[u8],
+    config: Option<&str>,
+    config_hash: Option<&str>,
+) -> Result<Vec<u8>, ConvertError> {
+    /*
+     * Encode palette indices in row order as an indexed PNG, with the text chunks of encode_png.
+     * Indices are packed to the fewest bits per pixel the palette needs
+     */
+    let bits: u8 = match palette.len() {
+        0..=2 => 1,
+        3..=4 => 2,
+        5..=16 => 4,
+        _ => 8,
+    };
+    let mut out = Vec::new();
+    let mut encoder = Encoder::new(&mut out, width, height);
+    encoder.set_color(ColorType::Indexed);
+    encoder.set_depth(BitDepth::from_u8(bits).expect("1, 2, 4 and 8 are bit depths"));
+    encoder.set_palette(
+        palette
+            .iter()
+            .flat_map(|color| color.0)
+            .collect::<Vec<u8>>(),
+    );
+    add_text_chunks(&mut encoder, config, config_hash)?;
+
+    // Every row starts on a byte, the first pixel in the high bits
+    let per_byte = (8 / bits) as usize;
+    let mut data = Vec::with_capacity(height as usize * (width as usize).div_ceil(per_byte));
+    for row in indices.chunks(width.max(1) as usize) {
+        for pixels in row.chunks(per_byte) {
+            let byte = pixels.iter().enumerate().fold(0u8, |byte, (i, &index)| {
+                byte | index << (8 - bits * (i as u8 + 1))
+            });
+            data.push(byte);
+        }
+    }
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+    writer.finish()?;
+    Ok(out)
+}
+
+fn add_text_chunks<W: Write>(
+    encoder: &mut Encoder<W>,
+    config: Option<&str>,
+    config_hash: Option<&str>,
+) -> Result<(), ConvertError> {
+    if let Some(config) = config {
+        // iTXt as font paths and tile characters are not limited to latin-1
+        encoder.add_itxt_chunk(CONFIG_KEYWORD.to_string(), config.to_string())?;
+        encoder.add_text_chunk(
+            VERSION_KEYWORD.to_string(),
+            env!("CARGO_PKG_VERSION").to_string(),
+        )?;
+    }
+    if let Some(config_hash) = config_hash {
+        encoder.add_text_chunk(CONFIG_HASH_KEYWORD.to_string(), config_hash.to_string())?;
+    }
+    Ok(())
+}
+
+pub fn read_png_metadata<R: Read>(reader: R) -> Result<PngMetadata, ConvertError> {
+    /*
+     * Read the ruscii-gen text chunks of a PNG, only decoding up to the image data since
+     * encode_png writes them ahead of it
+     */
+    let reader = Decoder::new(reader).read_info()?;
+    let info = reader.info();
+    let mut metadata = PngMetadata::default();
+    for chunk in &info.utf8_text {
+        if chunk.keyword == CONFIG_KEYWORD {
+            metadata.config = Some(chunk.get_text()?);
+        }
+    }
+    for chunk in &info.uncompressed_latin1_text {
+        if chunk.keyword == VERSION_KEYWORD {
+            metadata.version = Some(chunk.text.clone());
+        } else if chunk.keyword == CONFIG_HASH_KEYWORD {
+            metadata.config_hash = Some(chunk.text.clone());
+        }
+    }
+    Ok(metadata)
+}
@@ -0,0 +1,46 @@
+pub mod animation;
+pub mod ans;
+pub mod ansi;
+pub mod ansi_sequence;
+#[cfg(feature = "serde")]
+pub mod cast;
+pub mod comparison;
+pub mod contact_sheet;
+pub mod heatmap;
+pub mod inline;
+#[cfg(feature = "serde")]
+pub mod json;
+pub mod metadata;
+pub mod quantize;
+pub mod sixel;
+pub mod text;
+pub mod trim;
+pub mod write;
+
+// NeuQuant sampling factor, 1 is the best quality and 30 is the fastest
+pub(crate) const NEUQUANT_SAMPLE_FAC: i32 = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    Png,
+    Txt,
+    Ansi,
+    Sixel,
+    Inline(inline::InlineImageProtocol),
+    #[cfg(feature = "serde")]
+    Json(json::JsonLayout),
+}
+
+impl OutputFormat {
+    pub fn is_binary(&self) -> bool {
+        matches!(self, OutputFormat::Png)
+    }
+
+    // Whether the output draws the glyphs, and so needs the font
+    pub fn is_rendered(&self) -> bool {
+        matches!(
+            self,
+            OutputFormat::Png | OutputFormat::Sixel | OutputFormat::Inline(_)
+        )
+    }
+}
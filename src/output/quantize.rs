@@ -0,0 +1,211 @@
+use crate::ascii::error::ConvertError;
+use image::{ImageBuffer, Rgb};
+use std::collections::HashMap;
+
+/*
+* Reduction of a rendered image to at most colors colors before it is written as an indexed PNG,
+* for posters and small files. The palette is picked by median cut, and dither spreads the error
+* of every pixel over its neighbors with Floyd-Steinberg
+*/
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OutputQuantize {
+    pub colors: u16,
+    pub dither: bool,
+}
+
+impl OutputQuantize {
+    pub fn new(colors: u16, dither: bool) -> Self {
+        OutputQuantize { colors, dither }
+    }
+
+    pub fn validate(&self) -> Result<(), ConvertError> {
+        // An indexed PNG holds at most 256 colors
+        if !(2..=256).contains(&self.colors) {
+            return Err(ConvertError::InvalidSetting {
+                field: "output_quantize",
+                reason: "colors must be between 2 and 256",
+            });
+        }
+        Ok(())
+    }
+}
+
+/*
+* Colors of an image and how many pixels have each, as one box of the median cut
+*/
+struct ColorBox {
+    colors: Vec<([u8; 3], u32)>,
+}
+
+impl ColorBox {
+    fn widest_channel(&self) -> (usize, u8) {
+        // Channel the colors spread the most along, with how far they spread
+        (0..3)
+            .map(|c| {
+                let (min, max) = self
+                    .colors
+                    .iter()
+                    .fold((u8::MAX, u8::MIN), |(min, max), (color, _)| {
+                        (min.min(color[c]), max.max(color[c]))
+                    });
+                (c, max.saturating_sub(min))
+            })
+            .max_by_key(|&(c, range)| (range, std::cmp::Reverse(c)))
+            .unwrap_or((0, 0))
+    }
+
+    fn split(mut self) -> (ColorBox, ColorBox) {
+        /*
+         * Halves of the box along its widest channel, cut at the median pixel so both hold about
+         * as many pixels. Each half keeps at least one color
+         */
+        let (channel, _) = self.widest_channel();
+        self.colors
+            .sort_unstable_by_key(|&(color, _)| color[channel]);
+        let total: u64 = self.colors.iter().map(|&(_, n)| n as u64).sum();
+        let cut = self
+            .colors
+            .iter()
+            .scan(0u64, |below, &(_, n)| {
+                *below += n as u64;
+                Some(*below)
+            })
+            .position(|below| below * 2 >= total)
+            .map_or(1, |i| i + 1)
+            .clamp(1, self.colors.len() - 1);
+        let upper = self.colors.split_off(cut);
+        (self, ColorBox { colors: upper })
+    }
+
+    fn mean(&self) -> Rgb<u8> {
+        // Average color of the box, weighted by pixel count
+        let total: u64 = self.colors.iter().map(|&(_, n)| n as u64).sum();
+        let mut sum = [0u64; 3];
+        for &(color, n) in self.colors.iter() {
+            for c in 0..3 {
+                sum[c] += color[c] as u64 * n as u64;
+            }
+        }
+        Rgb(sum.map(|s| ((s + total / 2) / total.max(1)) as u8))
+    }
+}
+
+pub fn median_cut_palette(img: &ImageBuffer<Rgb<u8>, Vec<u8>>, colors: usize) -> Vec<Rgb<u8>> {
+    /*
+     * At most colors colors standing for the pixels of img. The box of all pixel colors is split
+     * again and again, always the one spreading the most along a channel, and every box gives
+     * its mean color. Images with fewer distinct colors keep exactly those
+     */
+    let mut counts: HashMap<[u8; 3], u32> = HashMap::new();
+    for pixel in img.pixels() {
+        *counts.entry(pixel.0).or_insert(0) += 1;
+    }
+    let mut unique: Vec<([u8; 3], u32)> = counts.into_iter().collect();
+    // Sorted so the palette does not depend on the hash order
+    unique.sort_unstable();
+    let mut boxes = vec![ColorBox { colors: unique }];
+    while boxes.len() < colors {
+        let widest = boxes
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| b.colors.len() > 1)
+            .max_by_key(|&(i, b)| (b.widest_channel().1, std::cmp::Reverse(i)));
+        let Some((index, _)) = widest else {
+            break;
+        };
+        let (lower, upper) = boxes.swap_remove(index).split();
+        boxes.push(lower);
+        boxes.push(upper);
+    }
+    boxes
+        .iter()
+        .filter(|b| !b.colors.is_empty())
+        .map(ColorBox::mean)
+        .collect()
+}
+
+fn nearest(palette: &[Rgb<u8>], color: [i32; 3]) -> usize {
+    // Index of the palette entry closest to color by squared RGB distance, the first on ties
+    (0..palette.len())
+        .min_by_key(|&i| {
+            (0..3)
+                .map(|c| (palette[i][c] as i32 - color[c]).pow(2))
+                .sum::<i32>()
+        })
+        .unwrap_or(0)
+}
+
+pub fn quantize_image(
+    img: &ImageBuffer<Rgb<u8>, Vec<u8>>,
+    quantize: &OutputQuantize,
+) -> (Vec<Rgb<u8>>, Vec<u8>) {
+    /*
+     * Palette of img and the palette index of every pixel in row order, with the error of
+     * every pixel diffused over its neighbors when dithering
+     */
+    let palette = median_cut_palette(img, quantize.colors as usize);
+    let (w, h) = (img.width() as usize, img.height() as usize);
+    // Images are mostly few colors, each looked up once
+    let mut cache: HashMap<[i32; 3], u8> = HashMap::new();
+    let mut lookup = |color: [i32; 3]| {
+        *cache
+            .entry(color)
+            .or_insert_with(|| nearest(&palette, color) as u8)
+    };
+    if !quantize.dither {
+        let indices = img
+            .pixels()
+            .map(|p| lookup(p.0.map(|c| c as i32)))
+            .collect();
+        return (palette, indices);
+    }
+
+    let indices = diffuse_error(
+        w,
+        h,
+        |x, y| img.get_pixel(x as u32, y as u32).0,
+        |color| {
+            let index = lookup(color);
+            (index, palette[index as usize].0.map(|c| c as i32))
+        },
+    );
+    (palette, indices)
+}
+
+pub(crate) fn diffuse_error(
+    w: usize,
+    h: usize,
+    pixel: impl Fn(usize, usize) -> [u8; 3],
+    mut map: impl FnMut([i32; 3]) -> (u8, [i32; 3]),
+) -> Vec<u8> {
+    /*
+     * Palette index of every pixel of a w by h image in row order, with Floyd-Steinberg error
+     * diffusion. map gives the index of a color and the palette color it stands for, whose
+     * difference is carried to the pixels right and below
+     */
+    let mut indices = Vec::with_capacity(w * h);
+    // Accumulated error for the current and next row, times 16, with a cell of padding each side
+    let mut cur_err = vec![[0i32; 3]; w + 2];
+    let mut next_err = vec![[0i32; 3]; w + 2];
+    for y in 0..h {
+        for x in 0..w {
+            let pixel = pixel(x, y);
+            let mut color = [0i32; 3];
+            for c in 0..3 {
+                color[c] = (pixel[c] as i32 + cur_err[x + 1][c] / 16).clamp(0, 255);
+            }
+            let (index, mapped) = map(color);
+            indices.push(index);
+            for c in 0..3 {
+                let err = color[c] - mapped[c];
+                cur_err[x + 2][c] += err * 7;
+                next_err[x][c] += err * 3;
+                next_err[x + 1][c] += err * 5;
+                next_err[x + 2][c] += err;
+            }
+        }
+        std::mem::swap(&mut cur_err, &mut next_err);
+        next_err.iter_mut().for_each(|err| *err = [0; 3]);
+    }
+    indices
+}
@@ -0,0 +1,123 @@
+use super::NEUQUANT_SAMPLE_FAC;
+use color_quant::NeuQuant;
+use image::{ImageBuffer, Rgb};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+// Sixel terminals offer at most 256 color registers
+const PALETTE_SIZE: usize = 256;
+
+fn quantize(img: &ImageBuffer<Rgb<u8>, Vec<u8>>) -> (Vec<Rgb<u8>>, Vec<usize>) {
+    /*
+     * Palette of at most PALETTE_SIZE colors and the palette index of every pixel. Rendered ascii
+     * images rarely have more colors than that, in which case the palette is exact
+     */
+    let mut palette: Vec<Rgb<u8>> = vec![];
+    let mut lookup: HashMap<Rgb<u8>, usize> = HashMap::new();
+    for &pixel in img.pixels() {
+        if let Entry::Vacant(entry) = lookup.entry(pixel) {
+            if palette.len() == PALETTE_SIZE {
+                break;
+            }
+            entry.insert(palette.len());
+            palette.push(pixel);
+        }
+    }
+    if palette.len() < PALETTE_SIZE || img.pixels().all(|p| lookup.contains_key(p)) {
+        let indices = img.pixels().map(|p| lookup[p]).collect();
+        return (palette, indices);
+    }
+
+    // NeuQuant expects rgba pixels
+    let samples: Vec<u8> = img.pixels().flat_map(|p| [p[0], p[1], p[2], 255]).collect();
+    let quantizer = NeuQuant::new(NEUQUANT_SAMPLE_FAC, PALETTE_SIZE, &samples);
+    let palette = quantizer
+        .color_map_rgb()
+        .chunks(3)
+        .map(|c| Rgb([c[0], c[1], c[2]]))
+        .collect();
+    let indices = img
+        .pixels()
+        .map(|p| quantizer.index_of(&[p[0], p[1], p[2], 255]))
+        .collect();
+    (palette, indices)
+}
+
+fn push_run(out: &mut String, sixel: u8, run: usize) {
+    // Runs longer than 3 are shorter with the repeat introducer
+    let ch = (0x3f + sixel) as char;
+    if run > 3 {
+        let _ = write!(out, "!{}{}", run, ch);
+    } else {
+        out.extend(std::iter::repeat_n(ch, run));
+    }
+}
+
+pub fn image_to_sixel(img: &ImageBuffer<Rgb<u8>, Vec<u8>>) -> String {
+    /*
+     * Encode an image as a sixel escape sequence. Every band of 6 rows is written once per color
+     * it uses, with the bands of other colors overprinted after a carriage return
+     */
+    let (w, h) = (img.width() as usize, img.height() as usize);
+    let (palette, indices) = quantize(img);
+
+    // DCS with pixel aspect 1:1 and background left as is, then the size in raster attributes
+    let mut out = String::from("\x1bP0;1;0q");
+    let _ = write!(out, "\"1;1;{};{}", w, h);
+    for (idx, color) in palette.iter().enumerate() {
+        // Color registers take RGB as percentages
+        let pct = |c: u8| (c as u32 * 100 + 127) / 255;
+        let _ = write!(
+            out,
+            "#{};2;{};{};{}",
+            idx,
+            pct(color[0]),
+            pct(color[1]),
+            pct(color[2])
+        );
+    }
+
+    let mut band_colors: Vec<usize> = vec![];
+    for band_top in (0..h).step_by(6) {
+        let band_h = (h - band_top).min(6);
+        band_colors.clear();
+        for y in band_top..band_top + band_h {
+            for &idx in &indices[y * w..(y + 1) * w] {
+                if !band_colors.contains(&idx) {
+                    band_colors.push(idx);
+                }
+            }
+        }
+
+        for (n, &color) in band_colors.iter().enumerate() {
+            if n > 0 {
+                out.push('$');
+            }
+            let _ = write!(out, "#{}", color);
+            let mut run_sixel = 0;
+            let mut run = 0;
+            for x in 0..w {
+                let mut sixel = 0u8;
+                for dy in 0..band_h {
+                    if indices[(band_top + dy) * w + x] == color {
+                        sixel |= 1 << dy;
+                    }
+                }
+                if run > 0 && sixel != run_sixel {
+                    push_run(&mut out, run_sixel, run);
+                    run = 0;
+                }
+                run_sixel = sixel;
+                run += 1;
+            }
+            // Trailing empty columns of a color need not be written
+            if run > 0 && run_sixel != 0 {
+                push_run(&mut out, run_sixel, run);
+            }
+        }
+        out.push('-');
+    }
+    out.push_str("\x1b\\");
+    out
+}
@@ -0,0 +1,255 @@
+use super::ansi::join_rows;
+use super::trim::line_len;
+use crate::ascii::error::ConvertError;
+use image::Rgb;
+use ndarray::{s, ArrayView2, Axis};
+use rayon::prelude::*;
+use std::fmt::Write;
+use std::io;
+
+/*
+* Text output written a grid row at a time by Converter::convert_to_writer, plain or in 24-bit
+* ANSI colors as convert_to_text and convert_to_ansi give it
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextFormat {
+    #[default]
+    Plain,
+    Ansi,
+}
+
+pub fn grid_to_text(grid: &ArrayView2<char>) -> String {
+    /*
+     * Join the character grid into plain text, one line per grid row. Rows are built in parallel
+     * like in grid_to_ansi
+     */
+    grid_to_text_with(grid, false)
+}
+
+pub(crate) fn grid_to_text_with(grid: &ArrayView2<char>, trim_trailing: bool) -> String {
+    let rows: Vec<String> = grid
+        .axis_iter(Axis(0))
+        .into_par_iter()
+        .map(|row| {
+            let row = row.slice_move(s![..line_len(&row, trim_trailing)]);
+            let mut line =
+                String::with_capacity(row.iter().map(|ch| ch.len_utf8()).sum::<usize>() + 1);
+            line.extend(row.iter());
+            line.push('\n');
+            line
+        })
+        .collect();
+    join_rows(&rows)
+}
+
+pub(crate) fn write_text_rows(
+    grid: &ArrayView2<char>,
+    trim_trailing: bool,
+    w: &mut impl io::Write,
+) -> io::Result<()> {
+    // grid_to_text_with written row by row, reusing one line buffer
+    let mut line = String::new();
+    for row in grid.axis_iter(Axis(0)) {
+        line.clear();
+        line.extend(row.iter().take(line_len(&row, trim_trailing)));
+        line.push('\n');
+        w.write_all(line.as_bytes())?;
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+// What happens to grid rows longer than the column limit
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Overflow {
+    // Break the row into lines of at most the limit, every line but the last of a row ending
+    // with the marker, which counts toward the limit
+    Wrap { marker: Option<char> },
+    Error,
+}
+
+impl Default for Overflow {
+    fn default() -> Self {
+        Overflow::Wrap { marker: Some('\\') }
+    }
+}
+
+/*
+* Writer of text files holding the character grid, optionally kept in ANSI colors, wrapped to a
+* column limit and preceded by the settings it was converted with as # comment lines
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextExporter {
+    pub line_ending: LineEnding,
+    // No limit when None
+    pub max_columns: Option<usize>,
+    pub overflow: Overflow,
+    pub ansi: bool,
+    pub header: bool,
+}
+
+impl Default for TextExporter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TextExporter {
+    pub fn new() -> Self {
+        TextExporter {
+            line_ending: LineEnding::Lf,
+            max_columns: None,
+            overflow: Overflow::default(),
+            ansi: false,
+            header: false,
+        }
+    }
+
+    pub fn with_line_ending(mut self, line_ending: LineEnding) -> Self {
+        self.line_ending = line_ending;
+        self
+    }
+
+    pub fn with_max_columns(mut self, max_columns: Option<usize>) -> Self {
+        self.max_columns = max_columns;
+        self
+    }
+
+    pub fn with_overflow(mut self, overflow: Overflow) -> Self {
+        self.overflow = overflow;
+        self
+    }
+
+    pub fn with_ansi(mut self, ansi: bool) -> Self {
+        self.ansi = ansi;
+        self
+    }
+
+    pub fn with_header(mut self, header: bool) -> Self {
+        self.header = header;
+        self
+    }
+
+    fn line_width(&self, grid_width: usize) -> Result<usize, ConvertError> {
+        /*
+         * Number of grid cells per output line
+         */
+        let Some(max) = self.max_columns else {
+            return Ok(grid_width.max(1));
+        };
+        match self.overflow {
+            Overflow::Error if grid_width > max => Err(ConvertError::GridTooWide {
+                width: grid_width,
+                max,
+            }),
+            Overflow::Error => Ok(grid_width.max(1)),
+            Overflow::Wrap { marker } => {
+                let reserved = usize::from(marker.is_some());
+                if max <= reserved {
+                    return Err(ConvertError::InvalidSetting {
+                        field: "max_columns",
+                        reason: "must leave room for at least one character besides the marker",
+                    });
+                }
+                // A row that fits needs no marker
+                Ok(if grid_width <= max {
+                    max
+                } else {
+                    max - reserved
+                })
+            }
+        }
+    }
+
+    pub fn export(
+        &self,
+        grid: &ArrayView2<char>,
+        colors: &ArrayView2<Rgb<u8>>,
+        bg_color: Rgb<u8>,
+        settings: Option<&str>,
+    ) -> Result<String, ConvertError> {
+        /*
+         * Write the grid as text. Colors are only used when ansi is set, in which case every line
+         * sets its own background and ends with a reset so it can be pasted on its own. settings
+         * becomes the header when header is set
+         */
+        self.export_with(grid, colors, bg_color, settings, false)
+    }
+
+    pub(crate) fn export_with(
+        &self,
+        grid: &ArrayView2<char>,
+        colors: &ArrayView2<Rgb<u8>>,
+        bg_color: Rgb<u8>,
+        settings: Option<&str>,
+        trim_trailing: bool,
+    ) -> Result<String, ConvertError> {
+        let eol = self.line_ending.as_str();
+        let line_width = self.line_width(grid.dim().1)?;
+        let mut text = String::new();
+
+        if self.header {
+            for line in settings.unwrap_or_default().lines() {
+                let _ = write!(text, "# {}{}", line, eol);
+            }
+        }
+
+        for (row, color_row) in grid.outer_iter().zip(colors.outer_iter()) {
+            let cells: Vec<(char, Rgb<u8>)> = row
+                .iter()
+                .copied()
+                .zip(color_row.iter().copied())
+                .take(line_len(&row, trim_trailing))
+                .collect();
+            let lines: Vec<&[(char, Rgb<u8>)]> = cells.chunks(line_width).collect();
+            // An empty row still takes a line
+            let last = lines.len().saturating_sub(1);
+            for i in 0..lines.len().max(1) {
+                let line = lines.get(i).copied().unwrap_or_default();
+                if self.ansi {
+                    let _ = write!(
+                        text,
+                        "\x1b[48;2;{};{};{}m",
+                        bg_color[0], bg_color[1], bg_color[2]
+                    );
+                }
+                let mut prev_color = None;
+                for &(ch, color) in line {
+                    if self.ansi && prev_color != Some(color) {
+                        let _ = write!(text, "\x1b[38;2;{};{};{}m", color[0], color[1], color[2]);
+                        prev_color = Some(color);
+                    }
+                    text.push(ch);
+                }
+                if let Overflow::Wrap {
+                    marker: Some(marker),
+                } = self.overflow
+                {
+                    if i < last {
+                        text.push(marker);
+                    }
+                }
+                if self.ansi {
+                    text.push_str("\x1b[0m");
+                }
+                text.push_str(eol);
+            }
+        }
+        Ok(text)
+    }
+}
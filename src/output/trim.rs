@@ -0,0 +1,120 @@
+use super::ansi::grid_to_ansi_with;
+use super::text::grid_to_text_with;
+use image::Rgb;
+use ndarray::{s, ArrayView1, ArrayView2, Axis};
+
+/*
+* Removal of the blank space around the art in text outputs. TrailingSpaces drops the spaces
+* ending every line. Bounding also drops the fully blank rows above and below the art and the
+* columns left of it that are blank on every row, reporting what it removed as TrimOffsets
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TrimMode {
+    #[default]
+    None,
+    TrailingSpaces,
+    Bounding,
+}
+
+/*
+* Rows and columns a bounding trim removed from each side of the grid. A grid without any
+* character but spaces loses all of its rows, counted in top
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TrimOffsets {
+    pub top: usize,
+    pub left: usize,
+    pub bottom: usize,
+    pub right: usize,
+}
+
+impl TrimOffsets {
+    pub fn of(grid: &ArrayView2<char>) -> Self {
+        // Offsets of the bounding box of the characters that are not spaces
+        let (rows, cols) = grid.dim();
+        let filled_rows: Vec<usize> = grid
+            .axis_iter(Axis(0))
+            .enumerate()
+            .filter(|(_, row)| row.iter().any(|&ch| ch != ' '))
+            .map(|(y, _)| y)
+            .collect();
+        let (Some(&first), Some(&last)) = (filled_rows.first(), filled_rows.last()) else {
+            return TrimOffsets {
+                top: rows,
+                ..TrimOffsets::default()
+            };
+        };
+        let filled_cols: Vec<usize> = grid
+            .axis_iter(Axis(1))
+            .enumerate()
+            .filter(|(_, col)| col.iter().any(|&ch| ch != ' '))
+            .map(|(x, _)| x)
+            .collect();
+        TrimOffsets {
+            top: first,
+            left: filled_cols[0],
+            bottom: rows - 1 - last,
+            right: cols - 1 - filled_cols[filled_cols.len() - 1],
+        }
+    }
+
+    pub fn crop<'a, T>(&self, view: ArrayView2<'a, T>) -> ArrayView2<'a, T> {
+        // The part of view, the character grid or its colors, these offsets keep
+        let (rows, cols) = view.dim();
+        let bottom = rows.saturating_sub(self.bottom).max(self.top.min(rows));
+        let right = cols.saturating_sub(self.right).max(self.left.min(cols));
+        view.slice_move(s![self.top.min(rows)..bottom, self.left.min(cols)..right])
+    }
+}
+
+impl TrimMode {
+    pub fn offsets(&self, grid: &ArrayView2<char>) -> Option<TrimOffsets> {
+        // Only a bounding trim removes whole rows and columns
+        match self {
+            TrimMode::Bounding => Some(TrimOffsets::of(grid)),
+            TrimMode::None | TrimMode::TrailingSpaces => None,
+        }
+    }
+
+    pub fn trims_trailing(&self) -> bool {
+        !matches!(self, TrimMode::None)
+    }
+}
+
+pub(crate) fn line_len(row: &ArrayView1<char>, trim_trailing: bool) -> usize {
+    // Cells of row written out, all of them unless its trailing spaces are trimmed
+    if trim_trailing {
+        row.iter().rposition(|&ch| ch != ' ').map_or(0, |x| x + 1)
+    } else {
+        row.len()
+    }
+}
+
+pub fn trimmed_text(grid: &ArrayView2<char>, mode: TrimMode) -> (String, Option<TrimOffsets>) {
+    /*
+     * grid_to_text with the blank space around the art removed, and the offsets of a bounding
+     * trim
+     */
+    let offsets = mode.offsets(grid);
+    let grid = offsets.map_or(grid.view(), |o| o.crop(grid.view()));
+    (grid_to_text_with(&grid, mode.trims_trailing()), offsets)
+}
+
+pub fn trimmed_ansi(
+    grid: &ArrayView2<char>,
+    colors: &ArrayView2<Rgb<u8>>,
+    bg_color: Rgb<u8>,
+    mode: TrimMode,
+) -> (String, Option<TrimOffsets>) {
+    /*
+     * grid_to_ansi with the blank space around the art removed, and the offsets of a bounding
+     * trim. Trimmed trailing spaces take their background color with them
+     */
+    let offsets = mode.offsets(grid);
+    let (grid, colors) = match offsets {
+        Some(o) => (o.crop(grid.view()), o.crop(colors.view())),
+        None => (grid.view(), colors.view()),
+    };
+    let text = grid_to_ansi_with(&grid, &colors, bg_color, mode.trims_trailing());
+    (text, offsets)
+}
@@ -0,0 +1,102 @@
+use crate::ascii::error::ConvertError;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/*
+* What happens when the output path already exists. RenameWithSuffix writes next to it instead,
+* as name-1.ext, name-2.ext and so on
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverwritePolicy {
+    #[default]
+    Overwrite,
+    Error,
+    RenameWithSuffix,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct WriteOptions {
+    // Create the missing parent directories of the output instead of failing
+    pub create_dirs: bool,
+    pub overwrite: OverwritePolicy,
+}
+
+impl WriteOptions {
+    pub fn new(create_dirs: bool, overwrite: OverwritePolicy) -> Self {
+        WriteOptions {
+            create_dirs,
+            overwrite,
+        }
+    }
+}
+
+fn output_error(path: &Path, source: io::Error) -> ConvertError {
+    ConvertError::OutputError {
+        path: path.display().to_string(),
+        source,
+    }
+}
+
+fn suffixed(path: &Path) -> PathBuf {
+    /*
+     * First name-N.ext next to path that does not exist yet
+     */
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = path
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    (1..)
+        .map(|n| path.with_file_name(format!("{}-{}{}", stem, n, ext)))
+        .find(|candidate| !candidate.exists())
+        .unwrap()
+}
+
+pub fn write_file(
+    out: impl AsRef<Path>,
+    bytes: &[u8],
+    options: &WriteOptions,
+) -> Result<PathBuf, ConvertError> {
+    /*
+     * Write bytes to out following the options and return the path actually written. The bytes
+     * go to a temporary file in the same directory first and are renamed into place, so an
+     * interrupted write never leaves a truncated output behind
+     */
+    let out = out.as_ref();
+    let dir = match out.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    if options.create_dirs {
+        fs::create_dir_all(dir).map_err(|e| output_error(dir, e))?;
+    }
+
+    let target = match options.overwrite {
+        OverwritePolicy::Overwrite => out.to_path_buf(),
+        OverwritePolicy::Error if out.exists() => {
+            return Err(output_error(
+                out,
+                io::Error::new(io::ErrorKind::AlreadyExists, "output already exists"),
+            ))
+        }
+        OverwritePolicy::Error => out.to_path_buf(),
+        OverwritePolicy::RenameWithSuffix if out.exists() => suffixed(out),
+        OverwritePolicy::RenameWithSuffix => out.to_path_buf(),
+    };
+
+    // Hidden and unique to the process, so concurrent writers do not share a temporary file
+    let file_name = target.file_name().unwrap_or_default().to_string_lossy();
+    let tmp = dir.join(format!(".{}.{}.tmp", file_name, std::process::id()));
+    let written = File::create(&tmp)
+        .and_then(|mut file| {
+            file.write_all(bytes)?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&tmp, &target));
+    if let Err(e) = written {
+        let _ = fs::remove_file(&tmp);
+        return Err(output_error(&target, e));
+    }
+    Ok(target)
+}
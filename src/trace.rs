@@ -0,0 +1,37 @@
+/*
+* Thin wrappers over the tracing macros. Without the tracing feature they compile down to nothing
+* while still evaluating their fields, so call sites need no cfg attributes
+*/
+
+#[cfg(feature = "tracing")]
+macro_rules! stage_span {
+    ($name:literal $(, $key:ident = $val:expr)* $(,)?) => {
+        tracing::debug_span!($name $(, $key = $val)*).entered()
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! stage_span {
+    ($name:literal $(, $key:ident = $val:expr)* $(,)?) => {{
+        $(let _ = &$val;)*
+        $crate::trace::NoSpan
+    }};
+}
+
+#[cfg(feature = "tracing")]
+macro_rules! stage_event {
+    ($($key:ident = $val:expr),+ ; $msg:literal) => {
+        tracing::debug!($($key = $val),+, $msg)
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! stage_event {
+    ($($key:ident = $val:expr),+ ; $msg:literal) => {{
+        $(let _ = &$val;)+
+    }};
+}
+
+// Stand in for an entered span when tracing is disabled
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct NoSpan;
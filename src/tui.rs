@@ -0,0 +1,401 @@
+use ansi_to_tui::IntoText;
+use ascii_gen::ascii::cancel::CancelToken;
+use ascii_gen::ascii::config::{ConverterConfig, ProcessorConfig};
+use ascii_gen::ascii::error::ConvertError;
+use image::DynamicImage;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Text;
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use ratatui::{DefaultTerminal, Frame};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+// How long to wait for a key press before checking for finished conversions
+const TICK: Duration = Duration::from_millis(50);
+// Terminal cells are roughly twice as tall as they are wide
+const CELL_ASPECT: f32 = 2.0;
+
+const CHARSETS: [(&str, &str); 3] = [
+    ("default", " .,*:coPO?%&@"),
+    ("simple", " .:-=+*#%@"),
+    ("blocks", " ░▒▓█"),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Param {
+    FontSize,
+    Threshold,
+    Sigma1,
+    Sigma2,
+    Charset,
+    Edges,
+}
+
+const PARAMS: [Param; 6] = [
+    Param::FontSize,
+    Param::Threshold,
+    Param::Sigma1,
+    Param::Sigma2,
+    Param::Charset,
+    Param::Edges,
+];
+
+enum Job {
+    Preview {
+        generation: u64,
+        config: ConverterConfig,
+        cols: u32,
+        rows: u32,
+        // Triggered once a newer preview is requested
+        cancel: CancelToken,
+    },
+    Write {
+        config: ConverterConfig,
+        output: PathBuf,
+    },
+}
+
+enum JobResult {
+    Preview {
+        generation: u64,
+        result: Result<String, ConvertError>,
+    },
+    Written(Result<PathBuf, ConvertError>),
+}
+
+fn render_preview(
+    img: &DynamicImage,
+    config: &ConverterConfig,
+    cols: u32,
+    rows: u32,
+    cancel: &CancelToken,
+) -> Result<String, ConvertError> {
+    /*
+     * Preview of the image shrunk so the grid fits the preview area, squashing it vertically to
+     * make up for the tall terminal cells. Converter::preview skips some edge preprocessors, so
+     * the preview can differ from the file written with the same config
+     */
+    let (w, h) = (img.width() as f32, img.height() as f32);
+    let grid_cols = (cols as f32)
+        .min(rows as f32 * CELL_ASPECT * w / h)
+        .max(1.0);
+    let grid_rows = (grid_cols * h / w / CELL_ASPECT).max(1.0);
+
+    let font_size = config.font_size.max(1);
+    let small = img.thumbnail_exact(grid_cols as u32 * font_size, grid_rows as u32 * font_size);
+    cancel.check()?;
+    config
+        .build()?
+        .preview_cancellable(&small, grid_cols as u32, config.edge_threshold, cancel)
+}
+
+fn spawn_worker(img: Arc<DynamicImage>, input: PathBuf) -> (Sender<Job>, Receiver<JobResult>) {
+    /*
+     * Run conversions on a separate thread so the interface stays responsive. Only the newest
+     * preview request is rendered since older ones are already out of date, and a preview still
+     * rendering when a newer one is requested is cancelled
+     */
+    let (job_tx, job_rx) = channel::<Job>();
+    let (result_tx, result_rx) = channel();
+
+    thread::spawn(move || {
+        while let Ok(job) = job_rx.recv() {
+            let mut latest_preview = None;
+            for job in std::iter::once(job).chain(job_rx.try_iter()) {
+                match job {
+                    Job::Preview { .. } => latest_preview = Some(job),
+                    Job::Write { config, output } => {
+                        let result = config
+                            .build()
+                            .and_then(|converter| {
+                                converter.convert_img(&input, &output, config.edge_threshold)
+                            })
+                            .map(|_| output);
+                        if result_tx.send(JobResult::Written(result)).is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+
+            if let Some(Job::Preview {
+                generation,
+                config,
+                cols,
+                rows,
+                cancel,
+            }) = latest_preview
+            {
+                let result = render_preview(&img, &config, cols, rows, &cancel);
+                if matches!(result, Err(ConvertError::Cancelled)) {
+                    continue;
+                }
+                if result_tx
+                    .send(JobResult::Preview { generation, result })
+                    .is_err()
+                {
+                    return;
+                }
+            }
+        }
+    });
+
+    (job_tx, result_rx)
+}
+
+struct App {
+    output: PathBuf,
+    config_out: PathBuf,
+    config: ConverterConfig,
+    selected: usize,
+    preview: Text<'static>,
+    status: String,
+    // Bumped on every change so stale previews from the worker can be dropped
+    generation: u64,
+    requested: Option<(u64, Rect)>,
+    preview_area: Rect,
+    // Cancels the last requested preview when a newer one replaces it
+    cancel: CancelToken,
+    jobs: Sender<Job>,
+    results: Receiver<JobResult>,
+}
+
+impl App {
+    fn charset_index(&self) -> Option<usize> {
+        CHARSETS
+            .iter()
+            .position(|(_, chars)| *chars == self.config.tile_chars)
+    }
+
+    fn threshold_mut(&mut self) -> Option<&mut u8> {
+        self.config
+            .edge_preprocessors
+            .iter_mut()
+            .find_map(|p| match p {
+                ProcessorConfig::Threshold { threshold, .. } => Some(threshold),
+                _ => None,
+            })
+    }
+
+    fn dog_mut(&mut self) -> Option<(&mut f32, &mut f32)> {
+        self.config
+            .edge_preprocessors
+            .iter_mut()
+            .find_map(|p| match p {
+                ProcessorConfig::DoG { sigma_1, sigma_2 } => Some((sigma_1, sigma_2)),
+                _ => None,
+            })
+    }
+
+    fn param_value(&self, param: Param) -> String {
+        let edge_preprocessors = &self.config.edge_preprocessors;
+        let dog = edge_preprocessors.iter().find_map(|p| match p {
+            ProcessorConfig::DoG { sigma_1, sigma_2 } => Some((*sigma_1, *sigma_2)),
+            _ => None,
+        });
+        match param {
+            Param::FontSize => self.config.font_size.to_string(),
+            Param::Threshold => edge_preprocessors
+                .iter()
+                .find_map(|p| match p {
+                    ProcessorConfig::Threshold { threshold, .. } => Some(threshold.to_string()),
+                    _ => None,
+                })
+                .unwrap_or("-".to_string()),
+            Param::Sigma1 => dog.map_or("-".to_string(), |(s1, _)| format!("{:.2}", s1)),
+            Param::Sigma2 => dog.map_or("-".to_string(), |(_, s2)| format!("{:.2}", s2)),
+            Param::Charset => match self.charset_index() {
+                Some(i) => CHARSETS[i].0.to_string(),
+                None => "custom".to_string(),
+            },
+            Param::Edges => if self.config.draw_edges { "on" } else { "off" }.to_string(),
+        }
+    }
+
+    fn adjust(&mut self, step: i32) {
+        match PARAMS[self.selected] {
+            Param::FontSize => {
+                self.config.font_size = (self.config.font_size as i32 + step).clamp(1, 64) as u32
+            }
+            Param::Threshold => {
+                if let Some(threshold) = self.threshold_mut() {
+                    *threshold = (*threshold as i32 + step).clamp(0, 255) as u8;
+                }
+            }
+            Param::Sigma1 => {
+                if let Some((sigma_1, sigma_2)) = self.dog_mut() {
+                    // The first sigma must stay below the second for the difference to find edges.
+                    // A loaded config can hold sigmas too close for that, so the bounds never cross
+                    let max = (*sigma_2 - 0.1).max(0.1);
+                    *sigma_1 = (*sigma_1 + step as f32 * 0.1).clamp(0.1, max);
+                }
+            }
+            Param::Sigma2 => {
+                if let Some((sigma_1, sigma_2)) = self.dog_mut() {
+                    let min = (*sigma_1 + 0.1).min(20.0);
+                    *sigma_2 = (*sigma_2 + step as f32 * 0.25).clamp(min, 20.0);
+                }
+            }
+            Param::Charset => {
+                let next = match self.charset_index() {
+                    Some(i) => (i as i32 + step).rem_euclid(CHARSETS.len() as i32) as usize,
+                    None => 0,
+                };
+                self.config.tile_chars = CHARSETS[next].1.to_string();
+            }
+            Param::Edges => self.config.draw_edges = !self.config.draw_edges,
+        }
+        self.generation += 1;
+    }
+
+    fn export_config(&mut self) {
+        let result = self
+            .config
+            .to_toml()
+            .and_then(|text| Ok(fs::write(&self.config_out, text)?));
+        self.status = match result {
+            Ok(()) => format!("Exported settings to {}", self.config_out.display()),
+            Err(e) => format!("Export failed: {}", e),
+        };
+    }
+
+    fn handle_key(&mut self, code: KeyCode) -> bool {
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => return false,
+            KeyCode::Up => self.selected = (self.selected + PARAMS.len() - 1) % PARAMS.len(),
+            KeyCode::Down => self.selected = (self.selected + 1) % PARAMS.len(),
+            KeyCode::Left | KeyCode::Char('-') => self.adjust(-1),
+            KeyCode::Right | KeyCode::Char('+') | KeyCode::Enter => self.adjust(1),
+            KeyCode::Char('e') => self.export_config(),
+            KeyCode::Char('w') => {
+                let _ = self.jobs.send(Job::Write {
+                    config: self.config.clone(),
+                    output: self.output.clone(),
+                });
+                self.status = format!("Writing {}...", self.output.display());
+            }
+            _ => {}
+        }
+        true
+    }
+
+    fn poll_results(&mut self) {
+        while let Ok(result) = self.results.try_recv() {
+            match result {
+                JobResult::Preview { generation, result } if generation == self.generation => {
+                    match result.map(|ansi| ansi.into_text()) {
+                        Ok(Ok(text)) => self.preview = text,
+                        Ok(Err(e)) => self.status = format!("Preview failed: {}", e),
+                        Err(e) => self.status = format!("Preview failed: {}", e),
+                    }
+                }
+                JobResult::Preview { .. } => {}
+                JobResult::Written(Ok(path)) => self.status = format!("Wrote {}", path.display()),
+                JobResult::Written(Err(e)) => self.status = format!("Write failed: {}", e),
+            }
+        }
+    }
+
+    fn request_preview(&mut self) {
+        // A new preview is needed after a change or when the preview area was resized
+        let area = self.preview_area;
+        if area.width == 0 || self.requested == Some((self.generation, area)) {
+            return;
+        }
+        self.requested = Some((self.generation, area));
+        self.cancel.cancel();
+        self.cancel = CancelToken::new();
+        let _ = self.jobs.send(Job::Preview {
+            generation: self.generation,
+            config: self.config.clone(),
+            cols: area.width as u32,
+            rows: area.height as u32,
+            cancel: self.cancel.clone(),
+        });
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, status] =
+            Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        let [left, right] =
+            Layout::horizontal([Constraint::Min(1), Constraint::Length(32)]).areas(main);
+
+        let preview_block = Block::default().borders(Borders::ALL).title("Preview");
+        self.preview_area = preview_block.inner(left);
+        frame.render_widget(
+            Paragraph::new(self.preview.clone()).block(preview_block),
+            left,
+        );
+
+        let items: Vec<ListItem> = PARAMS
+            .iter()
+            .map(|&param| ListItem::new(format!("{:?}: {}", param, self.param_value(param))))
+            .collect();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title("Settings"))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        let mut state = ListState::default().with_selected(Some(self.selected));
+        frame.render_stateful_widget(list, right, &mut state);
+
+        let help = "up/down select  left/right change  e export  w write  q quit";
+        let status_text = if self.status.is_empty() {
+            help.to_string()
+        } else {
+            format!("{}  |  {}", self.status, help)
+        };
+        frame.render_widget(Paragraph::new(status_text), status);
+    }
+
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<(), String> {
+        loop {
+            terminal
+                .draw(|frame| self.draw(frame))
+                .map_err(|e| e.to_string())?;
+            self.request_preview();
+
+            if event::poll(TICK).map_err(|e| e.to_string())? {
+                if let Event::Key(key) = event::read().map_err(|e| e.to_string())? {
+                    if key.kind == KeyEventKind::Press && !self.handle_key(key.code) {
+                        return Ok(());
+                    }
+                }
+            }
+            self.poll_results();
+        }
+    }
+}
+
+pub fn run_tune(
+    input: &Path,
+    output: &Path,
+    config_out: &Path,
+    config: ConverterConfig,
+) -> Result<(), String> {
+    let img = image::open(input).map_err(|e| format!("{}: {}", input.display(), e))?;
+    let (jobs, results) = spawn_worker(Arc::new(img), input.to_path_buf());
+    let mut app = App {
+        output: output.to_path_buf(),
+        config_out: config_out.to_path_buf(),
+        config,
+        selected: 0,
+        preview: Text::default(),
+        status: String::new(),
+        generation: 0,
+        requested: None,
+        preview_area: Rect::default(),
+        cancel: CancelToken::new(),
+        jobs,
+        results,
+    };
+
+    let mut terminal = ratatui::init();
+    let result = app.run(&mut terminal);
+    ratatui::restore();
+    result
+}
@@ -0,0 +1,153 @@
+use crate::ascii::config::ConverterConfig;
+use crate::ascii::error::ConvertError;
+use crate::batch::{convert_cached, hash_config, BatchOptions, ConversionCache, Outcome};
+use notify::event::ModifyKind;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// Editors often write a file several times in a row when saving, so a conversion only starts once
+// the watched files have been quiet for this long
+const DEBOUNCE: Duration = Duration::from_millis(200);
+// How often the stop handle is checked while no events arrive
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/*
+* Shared flag used to stop a running watch_and_convert from another thread
+*/
+#[derive(Clone, Debug, Default)]
+pub struct StopHandle {
+    stopped: Arc<AtomicBool>,
+}
+
+impl StopHandle {
+    pub fn new() -> Self {
+        StopHandle::default()
+    }
+
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::SeqCst)
+    }
+}
+
+fn convert_once(
+    input: &Path,
+    output: &Path,
+    config: Option<&Path>,
+    options: &BatchOptions,
+) -> Result<Outcome, ConvertError> {
+    // The converter is rebuilt every time so config changes are picked up
+    let config = match config {
+        Some(path) => ConverterConfig::load(path)?,
+        None => ConverterConfig::default(),
+    };
+    let converter = config.build()?;
+    let config_hash = hash_config(&config)?;
+    if !options.incremental {
+        return convert_cached(&converter, &config, config_hash, input, output, None);
+    }
+
+    // The cache lives next to the output, like the one convert_dir keeps
+    let dir = match output.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let mut cache = ConversionCache::load(dir);
+    let outcome = convert_cached(
+        &converter,
+        &config,
+        config_hash,
+        input,
+        output,
+        Some(&mut cache),
+    )?;
+    if outcome == Outcome::Converted {
+        cache.save()?;
+    }
+    Ok(outcome)
+}
+
+fn resolve(path: &Path) -> Result<PathBuf, ConvertError> {
+    /*
+     * Absolute path of a watched file built from its canonical parent directory, which is what
+     * the watcher reports in its events
+     */
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    let file_name = path.file_name().ok_or(ConvertError::FileError)?;
+    Ok(parent.canonicalize()?.join(file_name))
+}
+
+fn is_relevant(event: &Event, targets: &[PathBuf]) -> bool {
+    // Reads of the input by the conversion itself also produce events, so only writes count
+    let is_write = matches!(
+        event.kind,
+        EventKind::Create(_)
+            | EventKind::Modify(ModifyKind::Data(_))
+            | EventKind::Modify(ModifyKind::Name(_))
+            | EventKind::Modify(ModifyKind::Any)
+    );
+    is_write && event.paths.iter().any(|path| targets.contains(path))
+}
+
+pub fn watch_and_convert<F: FnMut(Result<Outcome, ConvertError>)>(
+    input: impl AsRef<Path>,
+    output: impl AsRef<Path>,
+    config: Option<&Path>,
+    options: &BatchOptions,
+    stop: &StopHandle,
+    mut on_convert: F,
+) -> Result<(), ConvertError> {
+    /*
+     * Convert input into output, then convert again every time the input image or the config
+     * file changes until the stop handle is triggered. The result of every conversion is passed
+     * to on_convert so a failed conversion does not end the watch. With options.incremental a
+     * conversion whose input and config match the last one is skipped, which also avoids the
+     * initial conversion when restarting a watch over an up to date output
+     */
+    let (input, output) = (input.as_ref(), output.as_ref());
+    let mut targets = vec![resolve(input)?];
+    if let Some(config) = config {
+        targets.push(resolve(config)?);
+    }
+
+    // Parent directories are watched rather than the files themselves since many editors save by
+    // replacing the file, which would silently end a watch on the original file
+    let (tx, rx) = channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    let mut watched_dirs: Vec<&Path> = vec![];
+    for target in targets.iter() {
+        let dir = target.parent().ok_or(ConvertError::FileError)?;
+        if !watched_dirs.contains(&dir) {
+            watcher.watch(dir, RecursiveMode::NonRecursive)?;
+            watched_dirs.push(dir);
+        }
+    }
+
+    on_convert(convert_once(input, output, config, options));
+
+    let mut last_change: Option<Instant> = None;
+    while !stop.is_stopped() {
+        match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(Ok(event)) if is_relevant(&event, &targets) => last_change = Some(Instant::now()),
+            Ok(_) | Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        if last_change.is_some_and(|changed| changed.elapsed() >= DEBOUNCE) {
+            last_change = None;
+            on_convert(convert_once(input, output, config, options));
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,44 @@
+/*
+* Frames an animated GIF can not be written from
+*/
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::output::animation::AnimationWriter;
+use image::RgbImage;
+
+fn scratch(name: &str) -> String {
+    std::env::temp_dir()
+        .join(format!(
+            "ruscii-gen-{}-animation-{}",
+            std::process::id(),
+            name
+        ))
+        .to_string_lossy()
+        .to_string()
+}
+
+#[test]
+fn frames_of_differing_sizes_are_rejected() {
+    let mut writer = AnimationWriter::gif(&scratch("mismatch.gif"), 10);
+    writer.push_frame(RgbImage::new(4, 4)).unwrap();
+    assert!(matches!(
+        writer.push_frame(RgbImage::new(4, 5)),
+        Err(ConvertError::FrameSizeMismatch {
+            expected: (4, 4),
+            found: (4, 5)
+        })
+    ));
+    // The rejected frame is not queued, so the animation still writes
+    let path = scratch("mismatch.gif");
+    writer.finish().unwrap();
+    assert!(std::fs::metadata(&path).unwrap().len() > 0);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn an_animation_without_frames_is_an_error() {
+    let path = scratch("empty.gif");
+    let writer = AnimationWriter::gif(&path, 10);
+    assert!(matches!(writer.finish(), Err(ConvertError::EmptyAnimation)));
+    // Nothing is written for it
+    assert!(!std::path::Path::new(&path).exists());
+}
@@ -0,0 +1,130 @@
+/*
+* Byte level structure of exported ANSI art files
+*/
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::output::ans::{to_cp437, AnsExporter};
+use ascii_gen::output::ansi::ansi16_index;
+use image::Rgb;
+use ndarray::Array2;
+
+const SAUCE_LEN: usize = 128;
+
+fn grid(rows: usize, cols: usize) -> (Array2<char>, Array2<Rgb<u8>>) {
+    let chars = ['@', '░', '▒', '█', '.', ' '];
+    let grid = Array2::from_shape_fn((rows, cols), |(y, x)| chars[(y + x) % chars.len()]);
+    let colors = Array2::from_shape_fn((rows, cols), |(y, x)| {
+        Rgb([(x * 40) as u8, (y * 60) as u8, 200])
+    });
+    (grid, colors)
+}
+
+fn export(exporter: &AnsExporter, rows: usize, cols: usize) -> Vec<u8> {
+    let (grid, colors) = grid(rows, cols);
+    exporter
+        .export(&grid.view(), &colors.view(), Rgb([0, 0, 0]))
+        .unwrap()
+}
+
+fn art(file: &[u8]) -> &[u8] {
+    &file[..file.len() - SAUCE_LEN - 1]
+}
+
+#[test]
+fn sauce_record_ends_the_file() {
+    let exporter = AnsExporter::new()
+        .with_title("Circle")
+        .with_author("someone")
+        .with_group("ruscii")
+        .with_date("20240131");
+    let file = export(&exporter, 3, 10);
+    let sauce = &file[file.len() - SAUCE_LEN..];
+    assert_eq!(&sauce[..7], b"SAUCE00");
+    assert_eq!(file[file.len() - SAUCE_LEN - 1], 0x1a);
+    assert_eq!(&sauce[7..13], b"Circle");
+    assert!(sauce[13..42].iter().all(|&b| b == b' '));
+    assert_eq!(&sauce[42..49], b"someone");
+    assert_eq!(&sauce[62..68], b"ruscii");
+    assert_eq!(&sauce[82..90], b"20240131");
+
+    let file_size = u32::from_le_bytes(sauce[90..94].try_into().unwrap());
+    assert_eq!(file_size as usize, art(&file).len());
+    // Character data, ANSi file, 10 columns over 3 lines
+    assert_eq!(&sauce[94..96], [1, 1]);
+    assert_eq!(u16::from_le_bytes([sauce[96], sauce[97]]), 10);
+    assert_eq!(u16::from_le_bytes([sauce[98], sauce[99]]), 3);
+}
+
+#[test]
+fn lines_end_in_crlf_and_wrap_at_the_width() {
+    let file = export(&AnsExporter::new().with_width(8), 2, 20);
+    let art = art(&file);
+    assert!(art
+        .iter()
+        .enumerate()
+        .all(|(i, &b)| b != b'\n' || (i > 0 && art[i - 1] == b'\r')));
+    // 20 columns at 8 per line is 3 lines per row
+    assert_eq!(art.iter().filter(|&&b| b == b'\n').count(), 6);
+    let sauce = &file[file.len() - SAUCE_LEN..];
+    assert_eq!(u16::from_le_bytes([sauce[96], sauce[97]]), 8);
+    assert_eq!(u16::from_le_bytes([sauce[98], sauce[99]]), 6);
+
+    // Printed bytes between escape codes never exceed the width on a line
+    for line in art.split(|&b| b == b'\n') {
+        let mut printed = 0;
+        let mut in_escape = false;
+        for &b in line {
+            match (in_escape, b) {
+                (false, 0x1b) => in_escape = true,
+                (true, b'm') => in_escape = false,
+                (false, b'\r') => {}
+                (false, _) => printed += 1,
+                _ => {}
+            }
+        }
+        assert!(printed <= 8);
+    }
+}
+
+#[test]
+fn characters_are_cp437() {
+    assert_eq!(to_cp437('@'), Some(b'@'));
+    assert_eq!(to_cp437('░'), Some(0xb0));
+    assert_eq!(to_cp437('█'), Some(0xdb));
+    assert_eq!(to_cp437('€'), None);
+
+    let file = export(&AnsExporter::new(), 1, 6);
+    for byte in [b'@', 0xb0, 0xb1, 0xdb, b'.'] {
+        assert!(art(&file).contains(&byte));
+    }
+}
+
+#[test]
+fn unmappable_characters_fail_unless_lossy() {
+    let grid = Array2::from_elem((1, 3), '€');
+    let colors = Array2::from_elem((1, 3), Rgb([255, 255, 255]));
+    assert!(matches!(
+        AnsExporter::new().export(&grid.view(), &colors.view(), Rgb([0, 0, 0])),
+        Err(ConvertError::UnmappableChar('€'))
+    ));
+    let file = AnsExporter::new()
+        .with_lossy(true)
+        .export(&grid.view(), &colors.view(), Rgb([0, 0, 0]))
+        .unwrap();
+    assert!(art(&file).ends_with(b"???\r\n\x1b[0m"));
+}
+
+#[test]
+fn colors_use_the_16_color_palette() {
+    assert_eq!(ansi16_index(Rgb([250, 250, 250]), 16), 15);
+    assert_eq!(ansi16_index(Rgb([160, 10, 10]), 16), 1);
+    // Backgrounds only have the normal colors
+    assert_eq!(ansi16_index(Rgb([250, 250, 250]), 8), 7);
+
+    let grid = Array2::from_elem((1, 2), '@');
+    let colors =
+        Array2::from_shape_vec((1, 2), vec![Rgb([255, 255, 255]), Rgb([170, 0, 0])]).unwrap();
+    let file = AnsExporter::new()
+        .export(&grid.view(), &colors.view(), Rgb([0, 0, 170]))
+        .unwrap();
+    assert!(art(&file).starts_with(b"\x1b[0m\x1b[0;1;37;44m@\x1b[0;31;44m@\r\n"));
+}
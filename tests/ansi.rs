@@ -0,0 +1,95 @@
+/*
+* The row parallel ANSI and text renderers give the same bytes as a plain sequential rendering
+*/
+mod common;
+
+use ascii_gen::output::ansi::grid_to_ansi;
+use ascii_gen::output::text::grid_to_text;
+use image::Rgb;
+use ndarray::{Array2, ArrayView2};
+use std::fmt::Write;
+
+fn reference_ansi(grid: &ArrayView2<char>, colors: &ArrayView2<Rgb<u8>>, bg: Rgb<u8>) -> String {
+    let mut text = String::new();
+    for (row, color_row) in grid.outer_iter().zip(colors.outer_iter()) {
+        write!(text, "\x1b[48;2;{};{};{}m", bg[0], bg[1], bg[2]).unwrap();
+        let mut prev_color = None;
+        for (&ch, &color) in row.iter().zip(color_row.iter()) {
+            if prev_color != Some(color) {
+                write!(text, "\x1b[38;2;{};{};{}m", color[0], color[1], color[2]).unwrap();
+                prev_color = Some(color);
+            }
+            text.push(ch);
+        }
+        text.push_str("\x1b[0m\n");
+    }
+    text
+}
+
+fn reference_text(grid: &ArrayView2<char>) -> String {
+    let mut text = String::new();
+    for row in grid.outer_iter() {
+        text.extend(row.iter());
+        text.push('\n');
+    }
+    text
+}
+
+// Colors from noise, quantized so neighboring cells often share one and runs get merged
+fn noise_colors(rows: usize, cols: usize, seed: u32, step: u8) -> Array2<Rgb<u8>> {
+    let noise = common::noise(cols as u32, rows as u32 * 3, seed).to_luma8();
+    Array2::from_shape_fn((rows, cols), |(y, x)| {
+        Rgb(std::array::from_fn(|c| {
+            noise.get_pixel(x as u32, (y * 3 + c) as u32)[0] / step * step
+        }))
+    })
+}
+
+#[test]
+fn ansi_matches_the_reference_rendering() {
+    let bg = Rgb([0, 9, 255]);
+    for (rows, cols, step) in [(1, 1, 1), (7, 13, 1), (40, 90, 128), (500, 1000, 64)] {
+        let grid = common::char_grid(rows, cols, 5);
+        let colors = noise_colors(rows, cols, 3, step);
+        assert_eq!(
+            grid_to_ansi(&grid.view(), &colors.view(), bg),
+            reference_ansi(&grid.view(), &colors.view(), bg),
+            "{}x{}",
+            cols,
+            rows
+        );
+    }
+}
+
+#[test]
+fn every_digit_count_is_written_like_format() {
+    // Channels of one, two and three digits, including the boundaries between them
+    let values = [0, 1, 9, 10, 42, 99, 100, 109, 110, 199, 200, 255];
+    let colors = Array2::from_shape_fn((values.len(), values.len()), |(y, x)| {
+        Rgb([values[x], values[y], values[(x + y) % values.len()]])
+    });
+    let grid = Array2::from_elem(colors.dim(), 'é');
+    for bg in values.map(|v| Rgb([v, 255 - v, v / 2])) {
+        assert_eq!(
+            grid_to_ansi(&grid.view(), &colors.view(), bg),
+            reference_ansi(&grid.view(), &colors.view(), bg)
+        );
+    }
+}
+
+#[test]
+fn text_matches_the_reference_rendering() {
+    for (rows, cols) in [(0, 0), (3, 0), (1, 1), (500, 1000)] {
+        let mut grid = common::char_grid(rows, cols, 8);
+        if let Some(cell) = grid.get_mut((0, 0)) {
+            *cell = '写';
+        }
+        assert_eq!(
+            grid_to_text(&grid.view()),
+            reference_text(&grid.view()),
+            "{}x{}",
+            cols,
+            rows
+        );
+    }
+}
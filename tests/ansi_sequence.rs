@@ -0,0 +1,113 @@
+/*
+* 256 color ANSI animations mapped against one palette picked from all of their frames
+*/
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::output::ansi_sequence::{
+    xterm256_color, xterm256_index, AnsiSequenceRenderer, DEFAULT_SEQUENCE_COLORS,
+};
+use image::Rgb;
+use ndarray::Array2;
+
+const ROWS: usize = 24;
+const COLS: usize = 40;
+
+fn rainbow_colors() -> Array2<Rgb<u8>> {
+    // Far more distinct colors than the palette can hold
+    Array2::from_shape_fn((ROWS, COLS), |(y, x)| {
+        Rgb([
+            (x * 255 / (COLS - 1)) as u8,
+            (y * 255 / (ROWS - 1)) as u8,
+            ((x + y) * 4) as u8,
+        ])
+    })
+}
+
+fn escape_indices(frame: &str, prefix: &str) -> Vec<u8> {
+    frame
+        .split(prefix)
+        .skip(1)
+        .map(|rest| rest[..rest.find('m').unwrap()].parse().unwrap())
+        .collect()
+}
+
+#[test]
+fn palette_colors_map_to_themselves() {
+    assert_eq!(xterm256_color(16), Rgb([0, 0, 0]));
+    assert_eq!(xterm256_color(231), Rgb([255, 255, 255]));
+    assert_eq!(xterm256_color(196), Rgb([255, 0, 0]));
+    assert_eq!(xterm256_color(244), Rgb([128, 128, 128]));
+    for index in 16..=255u8 {
+        assert_eq!(
+            xterm256_color(xterm256_index(xterm256_color(index))),
+            xterm256_color(index)
+        );
+    }
+    // The themed system colors are never picked
+    assert!(xterm256_index(Rgb([170, 0, 0])) >= 16);
+}
+
+#[test]
+fn unchanged_cells_keep_their_index_across_frames() {
+    let grid = Array2::from_elem((ROWS, COLS), '@');
+    let first = rainbow_colors();
+    // The second frame only differs in a small block, by a little
+    let mut second = first.clone();
+    for y in 4..8 {
+        for x in 10..16 {
+            let Rgb([r, g, b]) = second[(y, x)];
+            second[(y, x)] = Rgb([r.saturating_add(9), g, b.saturating_sub(7)]);
+        }
+    }
+
+    let mut renderer = AnsiSequenceRenderer::new(2);
+    renderer.push_frame(&grid.view(), &first.view()).unwrap();
+    renderer.push_frame(&grid.view(), &second.view()).unwrap();
+    let sequence = renderer.finish().unwrap();
+    assert_eq!(sequence.indices.len(), 2);
+    assert!(sequence.palette.len() <= DEFAULT_SEQUENCE_COLORS);
+
+    for ((pos, &a), &b) in sequence.indices[0]
+        .indexed_iter()
+        .zip(sequence.indices[1].iter())
+    {
+        if first[pos] == second[pos] {
+            assert_eq!(a, b, "{:?}", pos);
+        }
+        assert!(sequence.palette.contains(&a), "{:?}", pos);
+    }
+    // The escapes only name palette entries, over the background of the row
+    for frame in sequence.frames.iter() {
+        let used = escape_indices(frame, "\x1b[38;5;");
+        assert!(!used.is_empty());
+        assert!(used.iter().all(|index| sequence.palette.contains(index)));
+        assert_eq!(
+            escape_indices(frame, "\x1b[48;5;"),
+            vec![sequence.bg_index; ROWS]
+        );
+        assert_eq!(frame.lines().count(), ROWS);
+    }
+    assert_eq!(sequence.playback().matches("\x1b[H").count(), 2);
+}
+
+#[test]
+fn frames_must_share_a_size() {
+    let mut renderer = AnsiSequenceRenderer::new(2);
+    let colors = rainbow_colors();
+    let grid = Array2::from_elem((ROWS, COLS), '.');
+    renderer.push_frame(&grid.view(), &colors.view()).unwrap();
+
+    let small = Array2::from_elem((ROWS, COLS - 1), '.');
+    let small_colors = Array2::from_elem((ROWS, COLS - 1), Rgb([0, 0, 0]));
+    assert!(matches!(
+        renderer.push_frame(&small.view(), &small_colors.view()),
+        Err(ConvertError::FrameSizeMismatch { .. })
+    ));
+    assert!(matches!(
+        renderer.push_frame(&small.view(), &colors.view()),
+        Err(ConvertError::NdArrayShapeError)
+    ));
+    assert!(matches!(
+        AnsiSequenceRenderer::new(0).finish(),
+        Err(ConvertError::EmptyAnimation)
+    ));
+}
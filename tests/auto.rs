@@ -0,0 +1,78 @@
+/*
+* The auto tuner picks the preprocessing from the statistics of the image
+*/
+mod common;
+
+use ascii_gen::ascii::auto::{AutoTuner, AutoTuning};
+use ascii_gen::ascii::config::ProcessorConfig;
+use image::{DynamicImage, GrayImage, Luma};
+
+const SIZE: u32 = common::FONT_SIZE * 24;
+
+fn tune(img: &DynamicImage) -> AutoTuning {
+    AutoTuner::new(common::test_config()).tune(img)
+}
+
+// Soft shapes squeezed into a narrow band of grays
+fn low_contrast() -> DynamicImage {
+    let circle = common::circle(SIZE, SIZE).to_luma8();
+    DynamicImage::ImageLuma8(GrayImage::from_fn(SIZE, SIZE, |x, y| {
+        Luma([100 + circle.get_pixel(x, y)[0] / 6])
+    }))
+}
+
+// Black strokes on white paper
+fn line_art() -> DynamicImage {
+    let lines = common::diagonal_lines(SIZE, SIZE).to_luma8();
+    DynamicImage::ImageLuma8(GrayImage::from_fn(SIZE, SIZE, |x, y| {
+        Luma([255 - lines.get_pixel(x, y)[0]])
+    }))
+}
+
+#[test]
+fn low_contrast_images_get_auto_levels() {
+    let tuning = tune(&low_contrast());
+    assert!(tuning.auto_levels);
+    assert!(matches!(
+        tuning.config.tile_preprocessors.first(),
+        Some(ProcessorConfig::AutoLevels { .. })
+    ));
+    assert!(matches!(
+        tuning.config.edge_preprocessors.first(),
+        Some(ProcessorConfig::AutoLevels { .. })
+    ));
+    assert!(tuning.draw_edges);
+}
+
+#[test]
+fn noisy_images_drop_the_edges() {
+    let tuning = tune(&common::noise(SIZE, SIZE, 7));
+    assert!(!tuning.auto_levels);
+    assert!(!tuning.draw_edges);
+    assert!(!tuning.config.draw_edges);
+    // Wider blurs than the default pipeline
+    assert!(tuning.dog.0 > 1.0);
+}
+
+#[test]
+fn line_art_keeps_the_default_pipeline_and_edges() {
+    let tuning = tune(&line_art());
+    assert!(!tuning.auto_levels);
+    assert!(tuning.draw_edges);
+    assert_eq!(tuning.dog, (1.0, 3.5));
+    assert!(tuning.threshold > 0);
+    // The decisions are written into the config, which then converts
+    assert!(tuning
+        .config
+        .edge_preprocessors
+        .contains(&ProcessorConfig::Threshold {
+            threshold: tuning.threshold,
+            mode: Default::default(),
+        }));
+    let text = tuning
+        .build()
+        .unwrap()
+        .convert_to_text(&line_art(), 0.0)
+        .unwrap();
+    assert!(!text.is_empty());
+}
@@ -0,0 +1,196 @@
+/*
+* Background modes fill the output behind the glyphs
+*/
+mod common;
+
+use ascii_gen::ascii::background::{composite_over, contrast_glyph_color, MIN_GLYPH_CONTRAST};
+use ascii_gen::ascii::config::{BackgroundConfig, ConverterConfig};
+use ascii_gen::ascii::error::ConvertError;
+use image::{DynamicImage, Rgb, RgbImage, Rgba};
+
+const FS: u32 = common::FONT_SIZE;
+
+// Dark enough that every cell is a space, leaving the background untouched
+fn dark_image(color: [u8; 3]) -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_pixel(FS * 6, FS * 10, Rgb(color)))
+}
+
+fn render(background: BackgroundConfig, img: &DynamicImage) -> RgbImage {
+    ConverterConfig {
+        background,
+        draw_edges: false,
+        ..common::test_config()
+    }
+    .build()
+    .unwrap()
+    .convert_image(img, 0.0)
+    .unwrap()
+}
+
+fn row(img: &RgbImage, y: u32) -> Vec<Rgb<u8>> {
+    (0..img.width()).map(|x| *img.get_pixel(x, y)).collect()
+}
+
+#[test]
+fn vertical_gradient_runs_top_to_bottom() {
+    let (top, bottom) = ([250, 10, 0], [0, 10, 250]);
+    let out = render(
+        BackgroundConfig::VerticalGradient { top, bottom },
+        &dark_image([0, 0, 0]),
+    );
+    let h = out.height();
+    assert!(row(&out, 0).iter().all(|p| p.0 == top));
+    assert!(row(&out, h - 1).iter().all(|p| p.0 == bottom));
+
+    // Every output row steps a little further, including inside a cell
+    let reds: Vec<u8> = (0..h).map(|y| out.get_pixel(0, y)[0]).collect();
+    assert!(reds.windows(2).all(|w| w[0] >= w[1]));
+    assert!(reds[1] < reds[0] && reds[FS as usize - 1] > reds[FS as usize]);
+}
+
+#[test]
+fn blurred_image_is_darkened_source() {
+    let out = render(
+        BackgroundConfig::BlurredImage {
+            sigma: 3.0,
+            darken: 0.5,
+        },
+        &dark_image([0, 0, 60]),
+    );
+    for y in [0, out.height() - 1] {
+        assert!(
+            row(&out, y).iter().all(|p| p.0 == [0, 0, 30]),
+            "{:?}",
+            row(&out, y)
+        );
+    }
+}
+
+#[test]
+fn blur_settings_are_validated() {
+    let config = ConverterConfig {
+        background: BackgroundConfig::BlurredImage {
+            sigma: 3.0,
+            darken: 1.5,
+        },
+        ..common::test_config()
+    };
+    assert!(matches!(
+        config.build(),
+        Err(ConvertError::InvalidSetting {
+            field: "blurred_image.darken",
+            ..
+        })
+    ));
+}
+
+fn half_black_half_white() -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_fn(FS * 10, FS * 4, |x, _| {
+        if x < FS * 5 {
+            Rgb([0, 0, 0])
+        } else {
+            Rgb([255, 255, 255])
+        }
+    }))
+}
+
+fn render_overlay(adaptive_glyph_contrast: bool) -> RgbImage {
+    // Every cell draws the same glyph, in the color of the image under it
+    ConverterConfig {
+        tile_chars: "@@".to_string(),
+        background: BackgroundConfig::BlurredImage {
+            sigma: 1.0,
+            darken: 0.0,
+        },
+        adaptive_glyph_contrast,
+        draw_edges: false,
+        ..common::test_config()
+    }
+    .build()
+    .unwrap()
+    .convert_image(&half_black_half_white(), 0.0)
+    .unwrap()
+}
+
+fn lumas(img: &RgbImage, cols: std::ops::Range<u32>) -> Vec<u8> {
+    let mut lumas = vec![];
+    for y in 0..img.height() {
+        for x in cols.start * FS..cols.end * FS {
+            lumas.push(image::Pixel::to_luma(img.get_pixel(x, y))[0]);
+        }
+    }
+    lumas
+}
+
+#[test]
+fn adaptive_contrast_flips_glyphs_per_half() {
+    // Cells away from the middle, where the blur mixes both halves
+    let (left, right) = (0..3, 7..10);
+    let plain = render_overlay(false);
+    assert!(lumas(&plain, left.clone()).iter().all(|&l| l < 20));
+    assert!(lumas(&plain, right.clone()).iter().all(|&l| l > 235));
+
+    let adapted = render_overlay(true);
+    let max_left = *lumas(&adapted, left).iter().max().unwrap();
+    let min_right = *lumas(&adapted, right).iter().min().unwrap();
+    assert!(max_left >= 90, "{}", max_left);
+    assert!(min_right <= 165, "{}", min_right);
+}
+
+#[test]
+fn contrast_keeps_readable_colors_and_nudges_the_rest() {
+    let red = Rgb([200, 30, 30]);
+    assert_eq!(contrast_glyph_color(red, 250.0), red);
+    assert_eq!(contrast_glyph_color(Rgb([255; 3]), 0.0), Rgb([255; 3]));
+
+    // Just far enough from the background, keeping the hue where it can
+    let nudged = contrast_glyph_color(Rgb([40, 40, 60]), 30.0);
+    let luma = 0.2126 * nudged[0] as f32 + 0.7152 * nudged[1] as f32 + 0.0722 * nudged[2] as f32;
+    assert!((luma - (30.0 + MIN_GLYPH_CONTRAST)).abs() < 1.5, "{}", luma);
+    assert!(nudged[2] > nudged[0]);
+
+    // Glyphs lighter than a light background still go dark
+    assert_eq!(contrast_glyph_color(Rgb([230; 3]), 200.0), Rgb([104; 3]));
+}
+
+#[test]
+fn composite_over_blends_by_coverage() {
+    let dst = Rgb([200, 40, 0]);
+    assert_eq!(composite_over(Rgba([0, 0, 0, 0]), dst), dst);
+    assert_eq!(
+        composite_over(Rgba([10, 20, 30, 255]), dst),
+        Rgb([10, 20, 30])
+    );
+    // Half covered white glyph, premultiplied
+    assert_eq!(
+        composite_over(Rgba([128, 128, 128, 128]), dst),
+        Rgb([228, 148, 128])
+    );
+}
+
+#[test]
+fn antialiased_glyph_edges_blend_into_the_background() {
+    let out = ConverterConfig {
+        bg_color: [255, 0, 0],
+        use_image_color: false,
+        color: [255, 255, 255],
+        draw_edges: false,
+        ..common::test_config()
+    }
+    .build()
+    .unwrap()
+    .convert_image(&common::gradient(FS * 16, FS * 4), 0.0)
+    .unwrap();
+
+    // Every pixel lies between the red background and the white glyphs, none toward the default
+    // purple background
+    let partial: Vec<&Rgb<u8>> = out
+        .pixels()
+        .filter(|p| p.0 != [255, 0, 0] && p.0 != [255, 255, 255])
+        .collect();
+    assert!(!partial.is_empty());
+    for p in partial {
+        assert_eq!(p[0], 255, "{:?}", p);
+        assert!(p[1].abs_diff(p[2]) <= 1, "{:?}", p);
+    }
+}
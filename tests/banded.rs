@@ -0,0 +1,116 @@
+/*
+* Processing in bands overlapped by the pipeline border matches processing the whole image
+*/
+mod common;
+
+use ascii_gen::ascii::config::{ConverterConfig, ProcessorConfig};
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::image_manip::banded::{apply_banded, pipeline_border};
+use ascii_gen::image_manip::processing::{
+    BilateralFilter, DoG, MedianBlur, Processor, Sharpen3x3, SharpenGaussian, Thin, Threshold,
+};
+use image::GrayImage;
+
+fn input() -> GrayImage {
+    let circle = common::circle(97, 83).to_luma8();
+    let noise = common::noise(97, 83, 5).to_luma8();
+    GrayImage::from_fn(97, 83, |x, y| {
+        image::Luma([circle.get_pixel(x, y)[0] / 2 + noise.get_pixel(x, y)[0] / 2])
+    })
+}
+
+fn whole(processors: &[Box<dyn Processor<u8, u8>>], bufr: &GrayImage) -> GrayImage {
+    let mut out = bufr.clone();
+    for processor in processors {
+        out = processor.apply(&out).unwrap();
+    }
+    out
+}
+
+fn assert_banded_matches(processors: &[Box<dyn Processor<u8, u8>>]) {
+    let bufr = input();
+    let expected = whole(processors, &bufr);
+    for band_height in [1, 7, 16, 40, 83, 200] {
+        let banded = apply_banded(processors, &bufr, band_height).unwrap();
+        assert!(
+            banded == expected,
+            "band height {} differs from the whole image",
+            band_height
+        );
+    }
+}
+
+#[test]
+fn dog_and_median_match_whole_image() {
+    let processors: Vec<Box<dyn Processor<u8, u8>>> =
+        vec![Box::new(DoG::new(1.0, 3.5)), Box::new(MedianBlur::new(2))];
+    assert_eq!(pipeline_border(&processors), 7 + 2);
+    assert_banded_matches(&processors);
+}
+
+#[test]
+fn default_edge_pipeline_matches_whole_image() {
+    let processors: Vec<Box<dyn Processor<u8, u8>>> = vec![
+        Box::new(SharpenGaussian::default()),
+        Box::new(DoG::default()),
+        Box::new(MedianBlur::default()),
+        Box::new(Threshold::default()),
+    ];
+    assert_banded_matches(&processors);
+}
+
+#[test]
+fn sharpen_matches_whole_image() {
+    let processors: Vec<Box<dyn Processor<u8, u8>>> =
+        vec![Box::new(Sharpen3x3::new()), Box::new(MedianBlur::new(1))];
+    assert_eq!(pipeline_border(&processors), 1 + 1);
+    assert_banded_matches(&processors);
+}
+
+#[test]
+fn whole_image_processors_fall_back_to_the_whole_image() {
+    let processors: Vec<Box<dyn Processor<u8, u8>>> = vec![
+        Box::new(BilateralFilter::new(5, 2.0, 5.0)),
+        Box::new(Threshold::new(128)),
+        Box::new(Thin::new()),
+    ];
+    assert_eq!(pipeline_border(&processors), u32::MAX);
+    assert_banded_matches(&processors);
+}
+
+#[test]
+fn converter_bands_match_whole_image() {
+    let img = image::DynamicImage::ImageLuma8(input());
+    let base = ConverterConfig {
+        tile_preprocessors: vec![ProcessorConfig::MedianBlur {
+            kernel_size: 1,
+            x_radius: None,
+            y_radius: None,
+        }],
+        ..common::test_config()
+    };
+    let expected = base.build().unwrap().convert_image(&img, 0.25).unwrap();
+    for band_rows in [1, 7, 40] {
+        let banded = ConverterConfig {
+            band_rows: Some(band_rows),
+            ..base.clone()
+        };
+        let out = banded.build().unwrap().convert_image(&img, 0.25).unwrap();
+        assert!(out == expected, "band rows {} differ", band_rows);
+    }
+}
+
+#[test]
+fn zero_band_rows_are_rejected() {
+    let config = ConverterConfig {
+        band_rows: Some(0),
+        ..common::test_config()
+    };
+    assert!(matches!(
+        config.build(),
+        Err(ConvertError::InvalidSetting {
+            field: "band_rows",
+            ..
+        })
+    ));
+}
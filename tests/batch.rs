@@ -0,0 +1,298 @@
+#![cfg(feature = "batch")]
+/*
+* Directory conversion and the incremental cache
+*/
+mod common;
+
+use ascii_gen::ascii::config::ConverterConfig;
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::batch::{
+    convert_dir, convert_dir_with_progress, windows_safe_name, BatchOptions, NameFields,
+    NameTemplate, Outcome, CACHE_FILE,
+};
+use ascii_gen::output::metadata::read_png_metadata;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn dirs(name: &str) -> (PathBuf, PathBuf) {
+    let root = std::env::temp_dir().join(format!("ruscii-gen-{}-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&root);
+    let (input, output) = (root.join("in"), root.join("out"));
+    fs::create_dir_all(&input).unwrap();
+    let size = common::FONT_SIZE * 6;
+    common::circle(size, size)
+        .save(input.join("circle.png"))
+        .unwrap();
+    common::gradient(size, size)
+        .save(input.join("gradient.png"))
+        .unwrap();
+    // Not an image, so never picked up
+    fs::write(input.join("notes.txt"), "skip me").unwrap();
+    (input, output)
+}
+
+fn run(input: &Path, output: &Path, config: &ConverterConfig) -> (usize, usize) {
+    let report = convert_dir(input, output, config, &BatchOptions::new(true)).unwrap();
+    assert!(report.failed.is_empty(), "{:?}", report.failed);
+    (report.converted.len(), report.skipped.len())
+}
+
+#[test]
+fn second_run_over_unchanged_tree_converts_nothing() {
+    let (input, output) = dirs("batch-unchanged");
+    let config = common::test_config();
+    assert_eq!(run(&input, &output, &config), (2, 0));
+    assert!(output.join("circle.png").exists());
+    assert!(output.join(CACHE_FILE).exists());
+    assert_eq!(run(&input, &output, &config), (0, 2));
+}
+
+#[test]
+fn changed_input_config_or_missing_output_reconverts() {
+    let (input, output) = dirs("batch-changes");
+    let config = common::test_config();
+    run(&input, &output, &config);
+
+    let size = common::FONT_SIZE * 6;
+    common::noise(size, size, 5)
+        .save(input.join("circle.png"))
+        .unwrap();
+    assert_eq!(run(&input, &output, &config), (1, 1));
+
+    fs::remove_file(output.join("gradient.png")).unwrap();
+    assert_eq!(run(&input, &output, &config), (1, 1));
+
+    let changed = ConverterConfig {
+        draw_edges: !config.draw_edges,
+        ..config
+    };
+    assert_eq!(run(&input, &output, &changed), (2, 0));
+}
+
+#[test]
+fn unusable_cache_falls_back_to_converting() {
+    let (input, output) = dirs("batch-corrupt");
+    let config = common::test_config();
+    run(&input, &output, &config);
+
+    fs::write(output.join(CACHE_FILE), "{ not json").unwrap();
+    assert_eq!(run(&input, &output, &config), (2, 0));
+    assert_eq!(run(&input, &output, &config), (0, 2));
+
+    let cache = fs::read_to_string(output.join(CACHE_FILE)).unwrap();
+    let old = cache.replace(env!("CARGO_PKG_VERSION"), "0.0.0-old");
+    fs::write(output.join(CACHE_FILE), old).unwrap();
+    assert_eq!(run(&input, &output, &config), (2, 0));
+}
+
+#[test]
+fn without_incremental_every_input_converts() {
+    let (input, output) = dirs("batch-full");
+    let config = common::test_config();
+    for _ in 0..2 {
+        let report = convert_dir(&input, &output, &config, &BatchOptions::default()).unwrap();
+        assert_eq!((report.converted.len(), report.skipped.len()), (2, 0));
+    }
+    assert!(!output.join(CACHE_FILE).exists());
+}
+
+#[test]
+fn unreadable_inputs_are_reported_without_stopping_the_run() {
+    let (input, output) = dirs("batch-failed");
+    fs::write(input.join("broken.png"), "not a png").unwrap();
+    let report = convert_dir(
+        &input,
+        &output,
+        &common::test_config(),
+        &BatchOptions::new(true),
+    )
+    .unwrap();
+    assert_eq!(report.converted.len(), 2);
+    assert_eq!(report.failed.len(), 1);
+    assert!(report.failed[0].0.ends_with("broken.png"));
+}
+
+#[test]
+fn progress_is_reported_once_per_input_in_order() {
+    let (input, output) = dirs("batch-progress");
+    fs::write(input.join("broken.png"), "not a png").unwrap();
+    let mut seen = vec![];
+    let report = convert_dir_with_progress(
+        &input,
+        &output,
+        &common::test_config(),
+        &BatchOptions::default(),
+        |progress| {
+            let name = progress
+                .input
+                .file_name()
+                .unwrap()
+                .to_str()
+                .unwrap()
+                .to_string();
+            seen.push((
+                progress.done,
+                progress.total,
+                name,
+                progress.outcome.is_ok(),
+            ));
+            if let Ok(outcome) = progress.outcome {
+                assert_eq!(outcome, Outcome::Converted);
+            }
+        },
+    )
+    .unwrap();
+    assert_eq!(report.converted.len(), 2);
+    assert_eq!(
+        seen.iter().map(|s| (s.0, s.1)).collect::<Vec<_>>(),
+        vec![(1, 3), (2, 3), (3, 3)]
+    );
+    assert!(seen
+        .iter()
+        .any(|(_, _, name, ok)| name == "broken.png" && !ok));
+}
+
+#[test]
+fn name_template_expands_placeholders() {
+    let template =
+        NameTemplate::parse("{stem}_ascii_{cols}x{rows}_{preset}_{date}_{cfghash}.{ext}").unwrap();
+    let name = template.expand(&NameFields {
+        stem: OsStr::new("cat"),
+        cols: 120,
+        rows: 45,
+        preset: "night",
+        date: "2026-10-16",
+        cfghash: "0badf00d",
+    });
+    assert_eq!(name, "cat_ascii_120x45_night_2026-10-16_0badf00d.png");
+    assert!(template.uses_grid());
+    assert!(!NameTemplate::parse("{stem}.{ext}").unwrap().uses_grid());
+}
+
+#[test]
+fn invalid_name_templates_are_rejected_up_front() {
+    match NameTemplate::parse("{stem}_{width}.png") {
+        Err(ConvertError::ConfigError(reason)) => {
+            assert!(reason.contains("{width}"), "{}", reason);
+            assert!(
+                reason.contains("{stem}, {ext}, {cols}, {rows}, {preset}, {date}"),
+                "{}",
+                reason
+            );
+        }
+        other => panic!("{:?}", other),
+    }
+    for template in ["", "{stem", "stem}.png", "{}.png", "../{stem}.png"] {
+        assert!(NameTemplate::parse(template).is_err(), "{}", template);
+    }
+
+    let (input, output) = dirs("batch-bad-template");
+    let options = BatchOptions::new(false).with_output_template("{name}.png");
+    assert!(convert_dir(input, output, &common::test_config(), &options,).is_err());
+}
+
+#[test]
+fn outputs_are_named_by_the_template() {
+    let (input, output) = dirs("batch-template");
+    let options = BatchOptions::new(false)
+        .with_output_template("{stem}_{cols}x{rows}_{preset}.{ext}")
+        .with_preset("night");
+    let report = convert_dir(&input, &output, &common::test_config(), &options).unwrap();
+    assert_eq!(report.converted.len(), 2);
+    assert!(output.join("circle_6x6_night.png").exists());
+    assert!(output.join("gradient_6x6_night.png").exists());
+}
+
+#[test]
+fn config_hash_in_the_name_matches_the_embedded_one() {
+    let (input, output) = dirs("batch-cfghash");
+    let config = common::test_config();
+    let options = BatchOptions::new(false).with_output_template("{stem}_{cfghash}.{ext}");
+    convert_dir(&input, &output, &config, &options).unwrap();
+
+    let hash = config.config_hash().unwrap();
+    let path = output.join(format!("circle_{}.png", hash));
+    let metadata = read_png_metadata(fs::File::open(&path).unwrap()).unwrap();
+    assert_eq!(metadata.config_hash.as_deref(), Some(hash.as_str()));
+    assert!(output.join(format!("gradient_{}.png", hash)).exists());
+}
+
+#[test]
+fn colliding_output_names_are_reported() {
+    let (input, output) = dirs("batch-collision");
+    let options = BatchOptions::new(false).with_output_template("{preset}.{ext}");
+    let report = convert_dir(&input, &output, &common::test_config(), &options).unwrap();
+    assert!(report.converted.is_empty());
+    assert_eq!(report.failed.len(), 2);
+    for (_, e) in report.failed.iter() {
+        assert!(
+            matches!(e, ConvertError::OutputCollision(path) if path.ends_with("default.png")),
+            "{:?}",
+            e
+        );
+    }
+    assert!(!output.join("default.png").exists());
+}
+
+#[test]
+fn unicode_file_names_round_trip() {
+    let template = NameTemplate::parse("{stem}.{ext}").unwrap();
+    let name = template.expand(&NameFields {
+        stem: OsStr::new("café ☕ 写真"),
+        cols: 0,
+        rows: 0,
+        preset: "default",
+        date: "2026-10-16",
+        cfghash: "",
+    });
+    assert_eq!(name, "café ☕ 写真.png");
+
+    let (input, output) = dirs("batch-unicode");
+    fs::rename(input.join("circle.png"), input.join("café ☕ 写真.png")).unwrap();
+    let report = convert_dir(
+        &input,
+        &output,
+        &common::test_config(),
+        &BatchOptions::default(),
+    )
+    .unwrap();
+    assert_eq!(report.converted.len(), 2);
+    assert!(output.join("café ☕ 写真.png").exists());
+}
+
+#[cfg(unix)]
+#[test]
+fn names_that_are_not_unicode_are_kept() {
+    use std::os::unix::ffi::OsStrExt;
+
+    let stem = OsStr::from_bytes(b"scan \xff\xfe");
+    let (input, output) = dirs("batch-non-utf8");
+    let mut name = stem.to_os_string();
+    name.push(".png");
+    fs::rename(input.join("circle.png"), input.join(&name)).unwrap();
+    let report = convert_dir(
+        &input,
+        &output,
+        &common::test_config(),
+        &BatchOptions::default(),
+    )
+    .unwrap();
+    assert!(report.failed.is_empty(), "{:?}", report.failed);
+    assert!(output.join(&name).exists());
+}
+
+#[test]
+fn windows_reserved_names_are_made_safe() {
+    for (name, safe) in [
+        ("CON.png", "_CON.png"),
+        ("nul.png", "_nul.png"),
+        ("Com1.tar.png", "_Com1.tar.png"),
+        ("LPT9 .png", "_LPT9 .png"),
+        ("console.png", "console.png"),
+        ("a:b?.png", "a_b_.png"),
+        ("trailing. ", "trailing__"),
+    ] {
+        assert_eq!(windows_safe_name(OsStr::new(name)), safe, "{}", name);
+    }
+}
@@ -0,0 +1,114 @@
+/*
+* Tile buckets split at the quantiles of the image's luminance rather than evenly
+*/
+mod common;
+
+use ascii_gen::ascii::char_set::{percentile_boundaries, quantize_luma_percentile, BucketMode};
+use ascii_gen::ascii::config::{BucketModeConfig, ConverterConfig, ResizeFilterConfig};
+use ascii_gen::output::OutputFormat;
+use image::{DynamicImage, GrayImage, Luma};
+
+const CELL: u32 = common::FONT_SIZE;
+const RAMP: &str = " .:-=+*#";
+const COLS: u32 = 32;
+const ROWS: u32 = 16;
+
+fn skewed_cells() -> DynamicImage {
+    // Every cell a flat luminance, most of them dark as in an underexposed photo
+    let cells = (COLS * ROWS) as f32;
+    DynamicImage::ImageLuma8(GrayImage::from_fn(COLS * CELL, ROWS * CELL, |x, y| {
+        let index = (y / CELL * COLS + x / CELL) as f32;
+        Luma([((index / cells).powi(2) * 255.0) as u8])
+    }))
+}
+
+fn config(bucket_mode: BucketModeConfig) -> ConverterConfig {
+    ConverterConfig {
+        tile_chars: RAMP.to_string(),
+        bucket_mode,
+        draw_edges: false,
+        // Every cell takes the luminance under it without blending in its neighbors
+        resize_filter: ResizeFilterConfig::Nearest,
+        ..common::test_config()
+    }
+}
+
+fn char_counts(bucket_mode: BucketModeConfig) -> Vec<usize> {
+    let grid = config(bucket_mode)
+        .build()
+        .unwrap()
+        .convert_to_chars(&skewed_cells(), 0.0)
+        .unwrap();
+    RAMP.chars()
+        .map(|ch| grid.iter().filter(|&&c| c == ch).count())
+        .collect()
+}
+
+#[test]
+fn boundaries_split_the_histogram_evenly() {
+    let luma: Vec<u8> = (0..=255).collect();
+    assert_eq!(
+        percentile_boundaries(luma.iter().copied(), 4),
+        [64, 128, 192]
+    );
+    // Ties stay in one bucket
+    let flat = [10u8; 8].into_iter().chain([200u8; 8]);
+    let boundaries = percentile_boundaries(flat, 4);
+    assert_eq!(boundaries, [11, 11, 201]);
+    assert_eq!(quantize_luma_percentile(10, &boundaries), 0);
+    assert_eq!(quantize_luma_percentile(200, &boundaries), 2);
+    assert_eq!(quantize_luma_percentile(255, &boundaries), 3);
+    assert!(percentile_boundaries(std::iter::empty(), 1).is_empty());
+}
+
+#[test]
+fn percentile_buckets_use_the_whole_ramp_evenly() {
+    let share = (COLS * ROWS) as usize / RAMP.len();
+    let percentile = char_counts(BucketModeConfig::Percentile);
+    for (ch, &count) in RAMP.chars().zip(percentile.iter()) {
+        // Cells of one luminance cannot be split, so the shares are only about even
+        assert!(
+            count.abs_diff(share) <= share / 4,
+            "{:?} {:?}",
+            ch,
+            percentile
+        );
+    }
+
+    // Evenly split buckets pile the dark cells into the first characters
+    let linear = char_counts(BucketModeConfig::Linear);
+    assert!(linear[0] > 2 * share, "{:?}", linear);
+    assert!(
+        linear[RAMP.len() - 2..].iter().sum::<usize>() < share,
+        "{:?}",
+        linear
+    );
+}
+
+#[test]
+fn boundaries_are_reported_in_stats() {
+    let mut bytes = vec![];
+    skewed_cells()
+        .write_to(
+            &mut std::io::Cursor::new(&mut bytes),
+            image::ImageFormat::Png,
+        )
+        .unwrap();
+    let converter = config(BucketModeConfig::Percentile).build().unwrap();
+    let (_, stats) = converter
+        .convert_bytes_with_stats(&bytes, OutputFormat::Txt, 0.0)
+        .unwrap();
+    let boundaries = stats.bucket_boundaries.clone().unwrap();
+    assert_eq!(boundaries.len(), RAMP.len() - 1);
+    assert!(boundaries.windows(2).all(|w| w[0] <= w[1]));
+    // The dark half of the ramp covers about a quarter of the luminance range
+    assert!(boundaries[RAMP.len() / 2 - 1] <= 64, "{:?}", boundaries);
+    assert!(stats.to_string().contains("bucket boundaries: "));
+
+    let linear = config(BucketModeConfig::Linear).build().unwrap();
+    let (_, stats) = linear
+        .convert_bytes_with_stats(&bytes, OutputFormat::Txt, 0.0)
+        .unwrap();
+    assert_eq!(stats.bucket_boundaries, None);
+    assert_eq!(BucketModeConfig::Percentile.build(), BucketMode::Percentile);
+}
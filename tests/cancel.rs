@@ -0,0 +1,28 @@
+/*
+* Conversions cancelled through a CancelToken give up instead of finishing
+*/
+use ascii_gen::ascii::cancel::CancelToken;
+use ascii_gen::ascii::converter::Converter;
+use ascii_gen::ascii::error::ConvertError;
+use image::{DynamicImage, GrayImage, Luma};
+
+#[test]
+fn cancelled_conversions_give_up() {
+    let converter = Converter::default();
+    let img = DynamicImage::ImageLuma8(GrayImage::from_fn(64, 64, |x, y| {
+        Luma([((x + y) * 2) as u8])
+    }));
+    let cancel = CancelToken::new();
+    let ansi = converter
+        .convert_to_ansi_cancellable(&img, 0.0, &cancel)
+        .unwrap();
+    assert_eq!(ansi, converter.convert_to_ansi(&img, 0.0).unwrap());
+
+    cancel.cancel();
+    assert!(matches!(
+        converter.convert_to_ansi_cancellable(&img, 0.0, &cancel),
+        Err(ConvertError::Cancelled)
+    ));
+    // Clones share the flag, so the thread that queued a conversion can cancel it
+    assert!(cancel.clone().is_cancelled());
+}
@@ -0,0 +1,87 @@
+#![cfg(feature = "serde")]
+/*
+* asciinema v2 recordings of ANSI animations
+*/
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::output::ansi::grid_to_ansi;
+use ascii_gen::output::ansi_sequence::AnsiSequenceRenderer;
+use ascii_gen::output::cast::{frames_to_cast, CastHeader};
+use image::Rgb;
+use ndarray::Array2;
+use serde_json::Value;
+use std::time::Duration;
+
+const ROWS: usize = 6;
+const COLS: usize = 10;
+
+fn frames(count: usize) -> Vec<String> {
+    // Grids of one character each, in colors changing from frame to frame
+    let mut renderer = AnsiSequenceRenderer::new(count);
+    for i in 0..count {
+        let grid = Array2::from_elem((ROWS, COLS), ['.', 'o', '@'][i % 3]);
+        let colors = Array2::from_shape_fn((ROWS, COLS), |(y, x)| {
+            Rgb([(x * 25) as u8, (y * 40) as u8, (i * 60) as u8])
+        });
+        renderer.push_frame(&grid.view(), &colors.view()).unwrap();
+    }
+    renderer.finish().unwrap().frames
+}
+
+#[test]
+fn header_declares_the_grid_size() {
+    let cast = frames_to_cast(&frames(3), COLS, ROWS, Duration::from_millis(100)).unwrap();
+    let header_line = cast.lines().next().unwrap();
+    let header: Value = serde_json::from_str(header_line).unwrap();
+    assert_eq!(header["version"], 2);
+    assert_eq!(header["width"], COLS);
+    assert_eq!(header["height"], ROWS);
+    assert_eq!(
+        serde_json::from_str::<CastHeader>(header_line).unwrap(),
+        CastHeader {
+            version: 2,
+            width: COLS,
+            height: ROWS,
+        }
+    );
+    // The header and one line per frame
+    assert_eq!(cast.lines().count(), 4);
+    assert!(cast.ends_with('\n'));
+}
+
+#[test]
+fn events_are_frames_spaced_by_the_frame_duration() {
+    let frames = frames(5);
+    let cast = frames_to_cast(&frames, COLS, ROWS, Duration::from_millis(125)).unwrap();
+    for (i, line) in cast.lines().skip(1).enumerate() {
+        let (time, kind, data): (f64, String, String) = serde_json::from_str(line).unwrap();
+        assert!((time - 0.125 * i as f64).abs() < 1e-9, "{} at {}", time, i);
+        assert_eq!(kind, "o");
+        assert!(data.starts_with("\x1b[H"));
+        // Line breaks return the carriage, as a recorded terminal writes them
+        assert_eq!(data[3..].replace("\r\n", "\n"), frames[i]);
+        assert_eq!(data.matches("\r\n").count(), ROWS);
+    }
+}
+
+#[test]
+fn frames_larger_than_the_declared_size_are_an_error() {
+    let frames = frames(2);
+    assert!(matches!(
+        frames_to_cast(&frames, COLS - 1, ROWS, Duration::from_millis(100)),
+        Err(ConvertError::FrameSizeMismatch { .. })
+    ));
+    assert!(matches!(
+        frames_to_cast(&frames, COLS, ROWS - 1, Duration::from_millis(100)),
+        Err(ConvertError::FrameSizeMismatch { .. })
+    ));
+    // A larger terminal leaves room around the frames, and 24-bit frames measure the same
+    assert!(frames_to_cast(&frames, COLS + 5, ROWS + 2, Duration::from_millis(100)).is_ok());
+    let grid = Array2::from_elem((ROWS, COLS), '@');
+    let colors = Array2::from_elem((ROWS, COLS), Rgb([255, 100, 0]));
+    let truecolor = grid_to_ansi(&grid.view(), &colors.view(), Rgb([0, 0, 0]));
+    assert!(frames_to_cast(&[truecolor], COLS, ROWS, Duration::from_millis(100)).is_ok());
+    assert!(matches!(
+        frames_to_cast(&[], COLS, ROWS, Duration::from_millis(100)),
+        Err(ConvertError::EmptyAnimation)
+    ));
+}
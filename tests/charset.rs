@@ -0,0 +1,173 @@
+/*
+* Character sets that do not start with a space are combined by index, not by character
+*/
+mod common;
+
+use ascii_gen::ascii::char_set::{CharacterSet, MappingOptions, TileMapping};
+use ascii_gen::ascii::config::ConverterConfig;
+use ascii_gen::ascii::converter::Converter;
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::ascii::font_loader::FontSettings;
+use ascii_gen::image_manip::edge_detect::Sobel;
+use image::Rgb;
+
+#[test]
+fn first_edge_character_is_not_an_edge() {
+    let converter = Converter::new(
+        FontSettings::new(common::FONT_SIZE, &common::test_font_path()),
+        CharacterSet {
+            tile: vec!['.', 'o', '@'],
+            edge: vec!['x', '_', '|', '/', '\\'],
+        },
+        vec![],
+        vec![],
+        Box::new(Sobel::new()),
+        Rgb([0, 0, 0]),
+        false,
+        Rgb([255, 255, 255]),
+    );
+    let img = common::circle(common::FONT_SIZE * 12, common::FONT_SIZE * 12);
+    let text = converter.convert_to_text(&img, 0.0).unwrap();
+
+    // The placeholder for no edge never shows up, flat cells keep their tile character
+    assert!(!text.contains('x'), "{}", text);
+    assert!(text.chars().any(|c| "_|/\\".contains(c)), "{}", text);
+    let first_row = text.lines().next().unwrap();
+    assert!(first_row.chars().all(|c| c == '.'), "{}", text);
+    assert!(text.contains('o'), "{}", text);
+}
+
+#[test]
+fn resampled_ramp_keeps_its_ends() {
+    let charset = CharacterSet::default();
+    assert_eq!(charset.tile.len(), 13);
+    let resampled = charset.resampled(5).unwrap();
+    assert_eq!(resampled.tile, vec![' ', '*', 'o', '?', '@']);
+    assert_eq!(resampled.edge, charset.edge);
+    assert_eq!(charset.resampled(13).unwrap().tile, charset.tile);
+}
+
+#[test]
+fn resampling_never_repeats_characters() {
+    let charset = CharacterSet::default();
+    for levels in [0, 1, 14] {
+        assert!(matches!(
+            charset.resampled(levels),
+            Err(ConvertError::InvalidSetting {
+                field: "tile_levels",
+                ..
+            })
+        ));
+    }
+}
+
+#[test]
+fn tile_levels_limit_the_characters_used() {
+    let config = ConverterConfig {
+        tile_levels: Some(5),
+        draw_edges: false,
+        ..common::test_config()
+    };
+    let img = common::gradient(common::FONT_SIZE * 40, common::FONT_SIZE * 2);
+    let text = config.build().unwrap().convert_to_text(&img, 0.0).unwrap();
+    assert!(text.chars().all(|c| " *o?@\n".contains(c)), "{}", text);
+    assert!(text.contains('*') && text.contains('?'), "{}", text);
+
+    let config = ConverterConfig {
+        tile_levels: Some(20),
+        ..common::test_config()
+    };
+    assert!(matches!(
+        config.build(),
+        Err(ConvertError::InvalidSetting {
+            field: "tile_levels",
+            ..
+        })
+    ));
+}
+
+fn ramp(len: usize) -> CharacterSet {
+    CharacterSet::new(
+        &(0..len as u32)
+            .map(|i| char::from_u32(0x21 + i).unwrap())
+            .collect::<Vec<_>>(),
+    )
+}
+
+#[test]
+fn luminance_mapping_is_monotone_and_in_bounds() {
+    let mappings = [
+        TileMapping::Luminance,
+        TileMapping::LuminanceAndVariance {
+            variance_weight: 0.5,
+        },
+    ];
+    for len in [1, 2, 3, 13, 64, 256] {
+        let charset = ramp(len);
+        for mapping in mappings {
+            for variance in [0.0, 0.1, 0.25] {
+                let opts = MappingOptions::new(mapping).with_variance(variance);
+                let indices: Vec<usize> = (0..=255u8)
+                    .map(|l| charset.tile_index_for_luminance(l, &opts))
+                    .collect();
+                assert!(indices.iter().all(|&i| i < len), "{} {:?}", len, opts);
+                assert!(
+                    indices.windows(2).all(|w| w[0] <= w[1]),
+                    "{} {:?}",
+                    len,
+                    opts
+                );
+                assert_eq!(indices[255], len - 1, "{} {:?}", len, opts);
+                for (l, &i) in indices.iter().enumerate() {
+                    assert_eq!(charset.char_for_luminance(l as u8, &opts), charset.tile[i]);
+                }
+            }
+        }
+        // Plain luminance spans the whole ramp
+        let indices: Vec<usize> = (0..=255u8)
+            .map(|l| charset.tile_index_for_luminance(l, &MappingOptions::default()))
+            .collect();
+        assert_eq!(indices[0], 0);
+        assert!((0..len).all(|i| indices.contains(&i)), "{}", len);
+    }
+}
+
+#[test]
+fn variance_only_moves_characters_up() {
+    let charset = CharacterSet::default();
+    let plain = MappingOptions::new(TileMapping::Luminance).with_variance(0.25);
+    let biased = MappingOptions::new(TileMapping::LuminanceAndVariance {
+        variance_weight: 0.5,
+    });
+    for l in 0..=255u8 {
+        let base = charset.tile_index_for_luminance(l, &MappingOptions::default());
+        // The variance is ignored by plain luminance mapping
+        assert_eq!(charset.tile_index_for_luminance(l, &plain), base);
+        assert_eq!(charset.tile_index_for_luminance(l, &biased), base);
+        assert!(charset.tile_index_for_luminance(l, &biased.with_variance(0.25)) >= base);
+    }
+}
+
+#[test]
+fn converter_maps_cells_like_the_character_set() {
+    let converter = ConverterConfig {
+        draw_edges: false,
+        ..common::test_config()
+    }
+    .build()
+    .unwrap();
+    let img = common::gradient(common::FONT_SIZE * 40, common::FONT_SIZE * 3);
+    let prepared = converter.prepare(&img).unwrap();
+    let charset = CharacterSet::default();
+    let expected: String = prepared
+        .gray
+        .rows()
+        .map(|row| {
+            row.map(|p| charset.char_for_luminance(p[0], &MappingOptions::default()))
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    let text = converter.convert_to_text(&img, 0.0).unwrap();
+    assert_eq!(text.trim_end_matches('\n'), expected);
+}
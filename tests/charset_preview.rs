@@ -0,0 +1,85 @@
+/*
+* Measured glyph densities and the ramp preview drawn from them
+*/
+mod common;
+
+use ascii_gen::ascii::char_set::{glyph_density, CharacterSet};
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::ascii::font_loader::{FontLoader, FontSettings};
+
+const PREVIEW_FONT_SIZE: u32 = 16;
+
+fn settings() -> FontSettings {
+    FontSettings::new(PREVIEW_FONT_SIZE, &common::test_font_path())
+}
+
+#[test]
+fn densities_sort_a_shuffled_ramp_by_calibrated_coverage() {
+    let charset = CharacterSet::new(&['@', '.', ':', 'o', ' ', ',', '*']);
+    let densities = charset.tile_densities(&settings()).unwrap();
+
+    let font = FontLoader::load_font(&common::test_font_path()).unwrap();
+    for &(ch, density) in densities.iter() {
+        assert_eq!(
+            density,
+            glyph_density(&font, ch, PREVIEW_FONT_SIZE),
+            "{:?}",
+            ch
+        );
+    }
+    assert!(
+        densities.windows(2).all(|w| w[0].1 <= w[1].1),
+        "{:?}",
+        densities
+    );
+    // The test font grows its tiles along the default ramp
+    let order: String = densities.iter().map(|&(ch, _)| ch).collect();
+    assert_eq!(order, " .,*:o@");
+    assert_eq!(densities[0].1, 0.0);
+}
+
+#[test]
+fn preview_width_scales_with_ramp_length() {
+    let short = CharacterSet::new(&" .,*:c".chars().collect::<Vec<char>>());
+    let long = CharacterSet::new(&" .,*:coPO?%&".chars().collect::<Vec<char>>());
+    let short_preview = short.preview(&settings()).unwrap();
+    let long_preview = long.preview(&settings()).unwrap();
+    assert_eq!(long_preview.width(), 2 * short_preview.width());
+    assert_eq!(long_preview.height(), short_preview.height());
+}
+
+#[test]
+fn preview_cells_get_denser_left_to_right() {
+    let chars: Vec<char> = "@:. o,*".chars().collect();
+    let preview = CharacterSet::new(&chars).preview(&settings()).unwrap();
+    let cell_w = preview.width() / chars.len() as u32;
+
+    // Ink of the glyph drawn at the top of every cell, above its label and inside its border
+    let inks: Vec<u32> = (0..chars.len() as u32)
+        .map(|col| {
+            let mut ink = 0;
+            for y in 1..PREVIEW_FONT_SIZE + 4 {
+                for x in col * cell_w + 1..(col + 1) * cell_w - 1 {
+                    let [r, _, _] = preview.get_pixel(x, y).0;
+                    if r > 100 {
+                        ink += r as u32;
+                    }
+                }
+            }
+            ink
+        })
+        .collect();
+    assert!(inks.windows(2).all(|w| w[0] < w[1]), "{:?}", inks);
+}
+
+#[test]
+fn zero_font_size_is_rejected() {
+    let settings = FontSettings::new(0, &common::test_font_path());
+    assert!(matches!(
+        CharacterSet::default().preview(&settings),
+        Err(ConvertError::InvalidSetting {
+            field: "font_size",
+            ..
+        })
+    ));
+}
@@ -0,0 +1,86 @@
+/*
+* Shell completions and the man page are generated from the argument definitions, and images can
+* be piped through the binary
+*/
+use image::{DynamicImage, GrayImage, ImageFormat, Luma};
+use std::io::{Cursor, Write};
+use std::process::{Command, Stdio};
+
+fn run(args: &[&str]) -> String {
+    let out = Command::new(env!("CARGO_BIN_EXE_ruscii-gen"))
+        .args(args)
+        .output()
+        .expect("Failed running ruscii-gen");
+    assert!(out.status.success(), "{:?}", out);
+    String::from_utf8(out.stdout).expect("Output is not utf-8")
+}
+
+fn assert_completes_flags(script: &str) {
+    for flag in [
+        "--output", "--format", "--config", "--rotate", "--flip", "--stats",
+    ] {
+        assert!(script.contains(flag), "missing {}", flag);
+    }
+    for subcommand in ["watch", "batch", "man"] {
+        assert!(script.contains(subcommand), "missing {}", subcommand);
+    }
+}
+
+#[test]
+fn bash_completions_list_flags_and_values() {
+    let script = run(&["completions", "bash"]);
+    assert_completes_flags(&script);
+    assert!(script.contains("png txt ansi sixel json json-compact"));
+    assert!(script.contains("auto kitty iterm2 none"));
+}
+
+#[test]
+fn zsh_completions_list_flags_and_values() {
+    let script = run(&["completions", "zsh"]);
+    assert!(script.starts_with("#compdef ruscii-gen"));
+    assert_completes_flags(&script);
+    assert!(script.contains("(png txt ansi sixel json json-compact)"));
+    assert!(script.contains("(90 180 270)"));
+}
+
+#[test]
+fn completions_and_man_are_hidden_from_the_help() {
+    let help = run(&["--help"]);
+    assert!(!help.contains("completions"));
+    assert!(!help.contains("Print the man page"));
+}
+
+#[test]
+fn man_page_documents_the_flags() {
+    let page = run(&["man"]);
+    assert!(page.contains(".TH ruscii-gen 1"));
+    assert!(page.contains("Convert images into ascii art"));
+    assert!(page.contains("\\-\\-format"));
+}
+
+#[test]
+fn png_is_piped_from_stdin_to_stdout() {
+    let mut png = vec![];
+    // A bright disc on a dark background
+    let disc = GrayImage::from_fn(64, 48, |x, y| {
+        let d = (x as f32 - 32.0).hypot(y as f32 - 24.0);
+        Luma([if d < 16.0 { 230 } else { 20 }])
+    });
+    DynamicImage::ImageLuma8(disc)
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_ruscii-gen"))
+        .args(["-", "--output", "-", "--format", "png"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("Failed running ruscii-gen");
+    // Dropping stdin closes it, so the binary sees the end of the input
+    child.stdin.take().unwrap().write_all(&png).unwrap();
+    let out = child.wait_with_output().unwrap();
+    assert!(out.status.success(), "{:?}", out);
+
+    let render = image::load_from_memory(&out.stdout).expect("Output is not an image");
+    assert!(render.width() > 0 && render.height() > 0);
+}
@@ -0,0 +1,102 @@
+/*
+* Edges between colors of the same luminance, found on the color channels
+*/
+mod common;
+
+use ascii_gen::ascii::config::{ConverterConfig, EdgeDetectorConfig, EdgeSourceConfig};
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::image_manip::edge_detect::{ColorSobel, EdgeDetect};
+use image::{DynamicImage, GrayImage, Luma, Rgb, RgbImage};
+use ndarray::Array2;
+
+const FS: u32 = common::FONT_SIZE;
+const RED: Rgb<u8> = Rgb([255, 0, 0]);
+
+fn equal_luminance_green() -> Rgb<u8> {
+    // The green the grayscale conversion can not tell apart from RED
+    let luma = |color: Rgb<u8>| {
+        DynamicImage::ImageRgb8(RgbImage::from_pixel(1, 1, color)).to_luma8()[(0, 0)]
+    };
+    (0..=255)
+        .map(|g| Rgb([0, g, 0]))
+        .find(|&green| luma(green) == luma(RED))
+        .expect("A green matches the luminance of red")
+}
+
+fn red_green(w: u32, h: u32) -> DynamicImage {
+    // Red on the left half and green on the right, meeting in the middle of a cell
+    let green = equal_luminance_green();
+    DynamicImage::ImageRgb8(RgbImage::from_fn(w, h, |x, _| {
+        if x < w / 2 + FS / 2 {
+            RED
+        } else {
+            green
+        }
+    }))
+}
+
+fn edge_cells(grid: &Array2<char>) -> usize {
+    grid.iter().filter(|ch| "_|/\\".contains(**ch)).count()
+}
+
+#[test]
+fn equal_luminance_boundary_is_only_found_on_color() {
+    let img = red_green(FS * 12, FS * 6);
+    assert!(img
+        .to_luma8()
+        .pixels()
+        .all(|p| *p == img.to_luma8()[(0, 0)]));
+
+    let gray = common::test_converter()
+        .convert_to_chars(&img, 0.0)
+        .unwrap();
+    assert_eq!(edge_cells(&gray), 0, "{:?}", gray);
+
+    let color = ConverterConfig {
+        edge_detector: EdgeDetectorConfig::ColorSobel { min_magnitude: 0.0 },
+        edge_source: EdgeSourceConfig::Color,
+        ..common::test_config()
+    }
+    .build()
+    .unwrap()
+    .convert_to_chars(&img, 0.0)
+    .unwrap();
+    assert!(edge_cells(&color) > 0, "{:?}", color);
+    // The boundary runs down the middle, so its cells show vertical edges
+    for row in color.rows() {
+        assert!(row.iter().any(|&ch| ch == '|'), "{:?}", color);
+    }
+}
+
+#[test]
+fn strongest_channel_gives_the_direction() {
+    // Red steps across x, green less strongly across y, blue is flat
+    let (w, h) = (9, 9);
+    let red = GrayImage::from_fn(w, h, |x, _| Luma([if x < 4 { 0 } else { 200 }]));
+    let green = GrayImage::from_fn(w, h, |_, y| Luma([if y < 4 { 0 } else { 100 }]));
+    let blue = GrayImage::new(w, h);
+    let field = ColorSobel::new()
+        .channels_field(&[red, green, blue])
+        .unwrap();
+    // Where both step the red gradient wins, elsewhere the only one there
+    assert!(field.gx[(4, 4)] > 0.0 && field.gy[(4, 4)] == 0.0);
+    assert!(field.gx[(1, 4)] > 0.0 && field.gy[(1, 4)] == 0.0);
+    assert!(field.gx[(4, 1)] == 0.0 && field.gy[(4, 1)] > 0.0);
+    assert_eq!((field.gx[(1, 1)], field.gy[(1, 1)]), (0.0, 0.0));
+}
+
+#[test]
+fn color_source_needs_a_detector_supporting_it() {
+    let result = ConverterConfig {
+        edge_source: EdgeSourceConfig::Color,
+        ..common::test_config()
+    }
+    .build();
+    assert!(matches!(
+        result,
+        Err(ConvertError::InvalidSetting {
+            field: "edge_source",
+            ..
+        })
+    ));
+}
@@ -0,0 +1,138 @@
+/*
+* Inputs brought to sRGB by their gAMA chunk or ICC profile before the pipeline reads them
+*/
+mod common;
+
+use ascii_gen::ascii::config::{ConverterConfig, ResizeFilterConfig};
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::ascii::warning::ConvertWarning;
+use ascii_gen::input::color_space::{detect_color_space, SourceColorSpace};
+use ascii_gen::output::OutputFormat;
+
+const CELL: u32 = common::FONT_SIZE;
+const RAMP: &str = " .:-=+*#";
+// Linear light values of the cells, one cell each
+const LINEAR: [u8; 6] = [0, 32, 64, 128, 192, 255];
+
+fn gray_png(gamma: Option<f32>, icc_profile: Option<&[u8]>) -> Vec<u8> {
+    // A row of flat cells of the LINEAR values, with the given color metadata
+    let width = CELL * LINEAR.len() as u32;
+    let pixels: Vec<u8> = (0..CELL * width)
+        .map(|i| LINEAR[((i % width) / CELL) as usize])
+        .collect();
+    let mut bytes = vec![];
+    let mut encoder = png::Encoder::new(&mut bytes, width, CELL);
+    encoder.set_color(png::ColorType::Grayscale);
+    if let Some(gamma) = gamma {
+        encoder.set_source_gamma(png::ScaledFloat::new(gamma));
+    }
+    let mut writer = encoder.write_header().unwrap();
+    if let Some(profile) = icc_profile {
+        writer
+            .write_chunk(png::chunk::iCCP, &iccp(profile))
+            .unwrap();
+    }
+    writer.write_image_data(&pixels).unwrap();
+    writer.finish().unwrap();
+    bytes
+}
+
+fn iccp(profile: &[u8]) -> Vec<u8> {
+    // iCCP chunk data: a name, compression method 0 and the profile in a stored zlib stream
+    let mut data = b"Test\0\0".to_vec();
+    data.extend_from_slice(&[0x78, 0x01, 0x01]);
+    let len = profile.len() as u16;
+    data.extend_from_slice(&len.to_le_bytes());
+    data.extend_from_slice(&(!len).to_le_bytes());
+    data.extend_from_slice(profile);
+    let (mut a, mut b) = (1u32, 0u32);
+    for &byte in profile {
+        a = (a + byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    data.extend_from_slice(&((b << 16) | a).to_be_bytes());
+    data
+}
+
+fn config(color_management: bool) -> ConverterConfig {
+    ConverterConfig {
+        tile_chars: RAMP.to_string(),
+        draw_edges: false,
+        color_management,
+        // Every cell takes the luminance under it without blending in its neighbors
+        resize_filter: ResizeFilterConfig::Nearest,
+        ..common::test_config()
+    }
+}
+
+fn convert(config: ConverterConfig, png: &[u8]) -> String {
+    let out = config
+        .build()
+        .unwrap()
+        .convert_bytes(png, OutputFormat::Txt, 0.0)
+        .unwrap();
+    String::from_utf8(out).unwrap().trim_end().to_string()
+}
+
+#[test]
+fn linear_gamma_inputs_are_reencoded_to_srgb() {
+    let png = gray_png(Some(1.0), None);
+    assert_eq!(detect_color_space(&png), SourceColorSpace::Gamma(1.0));
+    // 0, 32, 64, 128, 192 and 255 in linear light are 0, 99, 137, 188, 225 and 255 in sRGB
+    assert_eq!(convert(config(true), &png), " :-+*#");
+    // Taken as sRGB, the linear values come out too dark
+    assert_eq!(convert(config(false), &png), "  .-+#");
+}
+
+#[test]
+fn srgb_gamma_and_no_metadata_are_left_alone() {
+    let naive = convert(config(false), &gray_png(None, None));
+    assert_eq!(naive, "  .-+#");
+    for png in [gray_png(None, None), gray_png(Some(0.45455), None)] {
+        assert_eq!(convert(config(true), &png), naive);
+    }
+    let srgb_profile = b"....desc sRGB IEC61966-2.1....".as_slice();
+    assert_eq!(
+        detect_color_space(&gray_png(Some(1.0), Some(srgb_profile))),
+        SourceColorSpace::Srgb
+    );
+}
+
+#[test]
+fn unsupported_profiles_warn_and_fall_back_to_gamma() {
+    let profile = b"....desc Display P3....".as_slice();
+    let png = gray_png(Some(1.0), Some(profile));
+    assert_eq!(
+        detect_color_space(&png),
+        SourceColorSpace::Unsupported { gamma: Some(1.0) }
+    );
+    let converter = config(true).build().unwrap();
+    let (out, stats) = converter
+        .convert_bytes_with_stats(&png, OutputFormat::Txt, 0.0)
+        .unwrap();
+    assert_eq!(String::from_utf8(out).unwrap().trim_end(), " :-+*#");
+    assert_eq!(
+        stats.warnings,
+        vec![ConvertWarning::UnsupportedColorProfile]
+    );
+
+    let strict = ConverterConfig {
+        strict: true,
+        ..config(true)
+    }
+    .build()
+    .unwrap();
+    assert!(matches!(
+        strict.convert_bytes(&png, OutputFormat::Txt, 0.0),
+        Err(ConvertError::StrictViolation(
+            ConvertWarning::UnsupportedColorProfile
+        ))
+    ));
+    // Without color management the profile is not looked at
+    let (_, stats) = config(false)
+        .build()
+        .unwrap()
+        .convert_bytes_with_stats(&png, OutputFormat::Txt, 0.0)
+        .unwrap();
+    assert!(stats.warnings.is_empty());
+}
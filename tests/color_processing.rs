@@ -0,0 +1,108 @@
+/*
+* Color preprocessors on solid color images, and their effect on the converted cell colors
+*/
+mod common;
+
+use ascii_gen::ascii::config::{ColorProcessorConfig, ConverterConfig};
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::image_manip::color::{ColorProcessor, SaturationBoost, WhiteBalance};
+use image::{DynamicImage, Rgb, RgbImage};
+
+fn solid(color: [u8; 3]) -> RgbImage {
+    RgbImage::from_pixel(8, 6, Rgb(color))
+}
+
+fn apply(processor: &dyn ColorProcessor, color: [u8; 3]) -> [u8; 3] {
+    processor.validate().unwrap();
+    let out = processor.apply(&solid(color)).unwrap();
+    assert_eq!(out.dimensions(), (8, 6));
+    assert!(out.pixels().all(|px| px == out.get_pixel(0, 0)));
+    out.get_pixel(0, 0).0
+}
+
+#[test]
+fn white_balance_warms_and_cools() {
+    assert_eq!(
+        apply(&WhiteBalance::new(0.0), [100, 120, 140]),
+        [100, 120, 140]
+    );
+    assert_eq!(
+        apply(&WhiteBalance::new(0.5), [100, 100, 100]),
+        [150, 100, 50]
+    );
+    assert_eq!(
+        apply(&WhiteBalance::new(-0.5), [100, 100, 100]),
+        [50, 100, 150]
+    );
+    // Gains past 255 saturate
+    assert_eq!(apply(&WhiteBalance::new(1.0), [200, 10, 200]), [255, 10, 0]);
+}
+
+#[test]
+fn white_balance_rejects_out_of_range_temperatures() {
+    for temperature in [1.5, -2.0, f32::NAN] {
+        assert!(matches!(
+            WhiteBalance::new(temperature).validate(),
+            Err(ConvertError::InvalidSetting {
+                field: "white_balance.temperature",
+                ..
+            })
+        ));
+    }
+}
+
+#[test]
+fn saturation_boost_leaves_gray_alone() {
+    for amount in [-1.0, 0.5, 3.0] {
+        assert_eq!(
+            apply(&SaturationBoost::new(amount), [90, 90, 90]),
+            [90, 90, 90]
+        );
+    }
+}
+
+#[test]
+fn saturation_boost_scales_distance_from_gray() {
+    let color = [200, 100, 50];
+    let gray = 0.2126 * 200.0 + 0.7152 * 100.0 + 0.0722 * 50.0;
+    let boosted = apply(&SaturationBoost::new(1.0), color);
+    for (b, c) in boosted.iter().zip(color) {
+        let expected = (gray + (c as f32 - gray) * 2.0).round().clamp(0.0, 255.0) as u8;
+        assert_eq!(*b, expected);
+    }
+    // -1 leaves only the gray value
+    let g = gray.round() as u8;
+    assert_eq!(apply(&SaturationBoost::new(-1.0), color), [g, g, g]);
+    assert!(SaturationBoost::new(-1.5).validate().is_err());
+}
+
+#[test]
+fn color_preprocessors_change_the_cell_colors() {
+    // Fully desaturated, every cell color is gray
+    let img = DynamicImage::ImageRgb8(RgbImage::from_pixel(
+        common::FONT_SIZE * 4,
+        common::FONT_SIZE * 3,
+        Rgb([200, 60, 60]),
+    ));
+    let config = ConverterConfig {
+        color_preprocessors: vec![ColorProcessorConfig::SaturationBoost { amount: -1.0 }],
+        ..common::test_config()
+    };
+    let ansi = config.build().unwrap().convert_to_ansi(&img, 0.0).unwrap();
+    let colors: Vec<Vec<u8>> = ansi
+        .split("\x1b[38;2;")
+        .skip(1)
+        .map(|rest| {
+            let rgb = &rest[..rest.find('m').unwrap()];
+            rgb.split(';').map(|v| v.parse().unwrap()).collect()
+        })
+        .collect();
+    assert!(!colors.is_empty());
+    for color in colors {
+        assert!(
+            color[0] == color[1] && color[1] == color[2],
+            "{:?} is not gray",
+            color
+        );
+    }
+}
@@ -0,0 +1,234 @@
+/*
+* Builds a tiny TrueType font in memory for tests. Every glyph is made of axis aligned boxes on a
+* square em, so rasterization is simple and stable across platforms
+*/
+
+const UNITS_PER_EM: i16 = 1000;
+const ASCENDER: i16 = 800;
+const DESCENDER: i16 = -200;
+
+// (character, boxes as (x_min, y_min, x_max, y_max) in font units)
+fn glyph_boxes(ch: char) -> Vec<(i16, i16, i16, i16)> {
+    // Tile characters are centered squares growing along the default ramp
+    let ramp = [
+        ' ', '.', ',', '*', ':', 'c', 'o', 'P', 'O', '?', '%', '&', '@',
+    ];
+    if let Some(i) = ramp.iter().position(|&r| r == ch) {
+        if i == 0 {
+            return vec![];
+        }
+        let half = (i as i16) * 480 / (ramp.len() as i16 - 1);
+        let center = 300;
+        return vec![(500 - half, center - half, 500 + half, center + half)];
+    }
+
+    match ch {
+        '_' => vec![(0, -200, 1000, -80)],
+        '|' => vec![(440, -200, 560, 800)],
+        '/' => vec![
+            (0, -200, 340, 130),
+            (330, 130, 670, 470),
+            (660, 470, 1000, 800),
+        ],
+        '\\' => vec![
+            (660, -200, 1000, 130),
+            (330, 130, 670, 470),
+            (0, 470, 340, 800),
+        ],
+        // Wider than the em and starting left of the origin, as in a proportional font
+        'W' => vec![(-100, 0, 1500, 700)],
+        _ => vec![(300, 0, 700, 400)],
+    }
+}
+
+struct Table {
+    tag: &'static [u8; 4],
+    data: Vec<u8>,
+}
+
+fn push_u16(buf: &mut Vec<u8>, v: u16) {
+    buf.extend_from_slice(&v.to_be_bytes());
+}
+
+fn push_i16(buf: &mut Vec<u8>, v: i16) {
+    buf.extend_from_slice(&v.to_be_bytes());
+}
+
+fn push_u32(buf: &mut Vec<u8>, v: u32) {
+    buf.extend_from_slice(&v.to_be_bytes());
+}
+
+fn glyph_data(boxes: &[(i16, i16, i16, i16)]) -> Vec<u8> {
+    if boxes.is_empty() {
+        return vec![];
+    }
+    let mut buf = vec![];
+    push_i16(&mut buf, boxes.len() as i16);
+    push_i16(&mut buf, boxes.iter().map(|b| b.0).min().unwrap());
+    push_i16(&mut buf, boxes.iter().map(|b| b.1).min().unwrap());
+    push_i16(&mut buf, boxes.iter().map(|b| b.2).max().unwrap());
+    push_i16(&mut buf, boxes.iter().map(|b| b.3).max().unwrap());
+    for i in 0..boxes.len() {
+        push_u16(&mut buf, (i * 4 + 3) as u16);
+    }
+    // No hinting instructions
+    push_u16(&mut buf, 0);
+
+    // Clockwise contours with every point on the curve
+    let points: Vec<(i16, i16)> = boxes
+        .iter()
+        .flat_map(|&(x0, y0, x1, y1)| [(x0, y0), (x0, y1), (x1, y1), (x1, y0)])
+        .collect();
+    buf.extend(std::iter::repeat_n(1u8, points.len()));
+    let mut prev = 0;
+    for &(x, _) in points.iter() {
+        push_i16(&mut buf, x - prev);
+        prev = x;
+    }
+    prev = 0;
+    for &(_, y) in points.iter() {
+        push_i16(&mut buf, y - prev);
+        prev = y;
+    }
+    // Glyphs are padded to even lengths for the loca table
+    if buf.len() % 2 == 1 {
+        buf.push(0);
+    }
+    buf
+}
+
+pub fn build_test_font(chars: &[char]) -> Vec<u8> {
+    // Glyph 0 is the empty .notdef glyph, the rest follow chars in order
+    let num_glyphs = chars.len() as u16 + 1;
+
+    let mut glyf = vec![];
+    // loca holds the start of every glyph plus the end of the last one, .notdef is empty
+    let mut loca = vec![];
+    push_u32(&mut loca, 0);
+    push_u32(&mut loca, 0);
+    for &ch in chars.iter() {
+        glyf.extend(glyph_data(&glyph_boxes(ch)));
+        push_u32(&mut loca, glyf.len() as u32);
+    }
+
+    let mut head = vec![];
+    push_u32(&mut head, 0x0001_0000);
+    push_u32(&mut head, 0x0001_0000);
+    push_u32(&mut head, 0);
+    push_u32(&mut head, 0x5F0F_3CF5);
+    push_u16(&mut head, 0);
+    push_u16(&mut head, UNITS_PER_EM as u16);
+    head.extend([0u8; 16]);
+    for v in [0, DESCENDER, UNITS_PER_EM, ASCENDER] {
+        push_i16(&mut head, v);
+    }
+    push_u16(&mut head, 0);
+    push_u16(&mut head, 8);
+    push_i16(&mut head, 2);
+    // Long loca offsets
+    push_i16(&mut head, 1);
+    push_i16(&mut head, 0);
+
+    let mut hhea = vec![];
+    push_u32(&mut hhea, 0x0001_0000);
+    for v in [ASCENDER, DESCENDER, 0] {
+        push_i16(&mut hhea, v);
+    }
+    push_u16(&mut hhea, UNITS_PER_EM as u16);
+    for v in [0, 0, UNITS_PER_EM, 1, 0, 0, 0, 0, 0, 0, 0] {
+        push_i16(&mut hhea, v);
+    }
+    push_u16(&mut hhea, num_glyphs);
+
+    let mut maxp = vec![];
+    push_u32(&mut maxp, 0x0000_5000);
+    push_u16(&mut maxp, num_glyphs);
+
+    // Glyphs advance by the em, or by their width when they are wider
+    let mut hmtx = vec![];
+    push_u16(&mut hmtx, UNITS_PER_EM as u16);
+    push_i16(&mut hmtx, 0);
+    for &ch in chars.iter() {
+        let right = glyph_boxes(ch).iter().map(|b| b.2).max().unwrap_or(0);
+        push_u16(&mut hmtx, right.max(UNITS_PER_EM) as u16);
+        push_i16(&mut hmtx, 0);
+    }
+
+    // A format 12 unicode subtable with one group per character
+    let mut cmap = vec![];
+    push_u16(&mut cmap, 0);
+    push_u16(&mut cmap, 1);
+    push_u16(&mut cmap, 3);
+    push_u16(&mut cmap, 10);
+    push_u32(&mut cmap, 12);
+    push_u16(&mut cmap, 12);
+    push_u16(&mut cmap, 0);
+    push_u32(&mut cmap, 16 + 12 * chars.len() as u32);
+    push_u32(&mut cmap, 0);
+    push_u32(&mut cmap, chars.len() as u32);
+    let mut mapping: Vec<(u32, u32)> = chars
+        .iter()
+        .enumerate()
+        .map(|(i, &ch)| (ch as u32, i as u32 + 1))
+        .collect();
+    mapping.sort();
+    for (code, glyph) in mapping {
+        push_u32(&mut cmap, code);
+        push_u32(&mut cmap, code);
+        push_u32(&mut cmap, glyph);
+    }
+
+    // Table records must be sorted by tag
+    let tables = [
+        Table {
+            tag: b"cmap",
+            data: cmap,
+        },
+        Table {
+            tag: b"glyf",
+            data: glyf,
+        },
+        Table {
+            tag: b"head",
+            data: head,
+        },
+        Table {
+            tag: b"hhea",
+            data: hhea,
+        },
+        Table {
+            tag: b"hmtx",
+            data: hmtx,
+        },
+        Table {
+            tag: b"loca",
+            data: loca,
+        },
+        Table {
+            tag: b"maxp",
+            data: maxp,
+        },
+    ];
+
+    let mut font = vec![];
+    push_u32(&mut font, 0x0001_0000);
+    push_u16(&mut font, tables.len() as u16);
+    push_u16(&mut font, 64);
+    push_u16(&mut font, 2);
+    push_u16(&mut font, tables.len() as u16 * 16 - 64);
+
+    let mut offset = 12 + 16 * tables.len();
+    for table in tables.iter() {
+        font.extend_from_slice(table.tag);
+        // Checksums are not validated by the parser
+        push_u32(&mut font, 0);
+        push_u32(&mut font, offset as u32);
+        push_u32(&mut font, table.data.len() as u32);
+        offset += (table.data.len() + 3) & !3;
+    }
+    for table in tables.iter() {
+        font.extend_from_slice(&table.data);
+        font.resize((font.len() + 3) & !3, 0);
+    }
+    font
+}
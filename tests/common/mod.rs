@@ -0,0 +1,106 @@
+#![allow(dead_code)]
+
+pub mod font;
+
+use ascii_gen::ascii::char_set::CharacterSet;
+use ascii_gen::ascii::config::ConverterConfig;
+use ascii_gen::ascii::converter::Converter;
+use image::{DynamicImage, GrayImage, Luma};
+use ndarray::Array2;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+pub const FONT_SIZE: u32 = 8;
+
+pub fn gradient(w: u32, h: u32) -> DynamicImage {
+    DynamicImage::ImageLuma8(GrayImage::from_fn(w, h, |x, _| {
+        Luma([(x * 255 / (w - 1).max(1)) as u8])
+    }))
+}
+
+pub fn circle(w: u32, h: u32) -> DynamicImage {
+    let (cx, cy) = (w as f32 / 2.0, h as f32 / 2.0);
+    let r = cx.min(cy) * 0.7;
+    DynamicImage::ImageLuma8(GrayImage::from_fn(w, h, |x, y| {
+        let d = ((x as f32 - cx).powi(2) + (y as f32 - cy).powi(2)).sqrt();
+        Luma([if d < r { 230 } else { 20 }])
+    }))
+}
+
+pub fn diagonal_lines(w: u32, h: u32) -> DynamicImage {
+    DynamicImage::ImageLuma8(GrayImage::from_fn(w, h, |x, y| {
+        Luma([if (x + y) % 24 < 4 { 255 } else { 0 }])
+    }))
+}
+
+pub fn noise(w: u32, h: u32, seed: u32) -> DynamicImage {
+    // xorshift keeps the noise identical on every platform
+    let mut state = seed.max(1);
+    DynamicImage::ImageLuma8(GrayImage::from_fn(w, h, |_, _| {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        Luma([(state >> 24) as u8])
+    }))
+}
+
+pub fn char_grid(rows: usize, cols: usize, seed: u32) -> Array2<char> {
+    // Grid cycling through every tile and edge character in a scrambled but repeatable order
+    let charset = CharacterSet::default();
+    let chars: Vec<char> = charset
+        .tile
+        .iter()
+        .chain(charset.edge.iter())
+        .copied()
+        .collect();
+    let noise = noise(cols as u32, rows as u32, seed).to_luma8();
+    Array2::from_shape_fn((rows, cols), |(y, x)| {
+        chars[noise.get_pixel(x as u32, y as u32)[0] as usize % chars.len()]
+    })
+}
+
+fn write_test_font(path: &'static OnceLock<PathBuf>, name: &str, extra: &[char]) -> String {
+    /*
+     * Write a generated test font with the default charset and extra once per test binary and
+     * return its path
+     */
+    path.get_or_init(|| {
+        let charset = CharacterSet::default();
+        let mut chars: Vec<char> = vec![];
+        for &ch in charset.tile.iter().chain(charset.edge.iter()).chain(extra) {
+            if !chars.contains(&ch) {
+                chars.push(ch);
+            }
+        }
+        let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(name);
+        fs::write(&path, font::build_test_font(&chars)).expect("Failed writing test font");
+        path
+    })
+    .to_str()
+    .expect("Target dir is not valid utf-8")
+    .to_string()
+}
+
+pub fn test_font_path() -> String {
+    static PATH: OnceLock<PathBuf> = OnceLock::new();
+    write_test_font(&PATH, "ruscii-test-font.ttf", &[])
+}
+
+pub fn proportional_font_path() -> String {
+    // The test font with a W wider than its cell
+    static PATH: OnceLock<PathBuf> = OnceLock::new();
+    write_test_font(&PATH, "ruscii-test-font-proportional.ttf", &['W'])
+}
+
+pub fn test_config() -> ConverterConfig {
+    ConverterConfig {
+        font_size: FONT_SIZE,
+        font_path: test_font_path(),
+        ..ConverterConfig::default()
+    }
+}
+
+pub fn test_converter() -> Converter {
+    test_config().build().unwrap()
+}
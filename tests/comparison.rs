@@ -0,0 +1,56 @@
+/*
+* Side by side comparisons of the original and the render
+*/
+mod common;
+
+use ascii_gen::output::comparison::{side_by_side, Divider};
+use image::{DynamicImage, Rgb, RgbImage};
+use std::fs;
+
+#[test]
+fn width_is_render_plus_original_plus_gap() {
+    let input = std::env::temp_dir().join(format!("ruscii-gen-{}-cmp-in.png", std::process::id()));
+    let output =
+        std::env::temp_dir().join(format!("ruscii-gen-{}-cmp-out.png", std::process::id()));
+    let ori_img = common::circle(common::FONT_SIZE * 9 + 5, common::FONT_SIZE * 4 + 3);
+    ori_img.save(&input).unwrap();
+
+    let converter = common::test_converter();
+    let divider = Divider::new(3, Rgb([0, 255, 0]));
+    converter
+        .convert_comparison(
+            input.to_str().unwrap(),
+            output.to_str().unwrap(),
+            0.0,
+            &divider,
+        )
+        .unwrap();
+    let comparison = image::open(&output).unwrap().to_rgb8();
+    fs::remove_file(&input).unwrap();
+    fs::remove_file(&output).unwrap();
+
+    let render = converter.convert_image(&ori_img, 0.0).unwrap();
+    let scale = render.height() as f64 / ori_img.height() as f64;
+    let ori_w = (ori_img.width() as f64 * scale).round() as u32;
+    assert_eq!(comparison.width(), render.width() + ori_w + divider.width);
+    assert_eq!(comparison.height(), render.height());
+    assert_eq!(*comparison.get_pixel(ori_w + 1, 0), divider.color);
+    // The render is copied untouched on the right
+    assert_eq!(
+        *comparison.get_pixel(comparison.width() - 1, comparison.height() - 1),
+        *render.get_pixel(render.width() - 1, render.height() - 1)
+    );
+}
+
+#[test]
+fn extreme_aspect_ratios_keep_both_sides() {
+    // A very tall original shrinks to a single column next to the render
+    let ori_img = DynamicImage::ImageRgb8(RgbImage::from_pixel(3, 1000, Rgb([255, 0, 0])));
+    let render = RgbImage::from_pixel(4, 2, Rgb([0, 0, 255]));
+    let out = side_by_side(&ori_img, &render, &Divider::default(), Rgb([9, 9, 9]));
+
+    assert_eq!(out.dimensions(), (1 + Divider::default().width + 4, 2));
+    assert_eq!(*out.get_pixel(0, 1), Rgb([255, 0, 0]));
+    assert_eq!(*out.get_pixel(1, 1), Divider::default().color);
+    assert_eq!(*out.get_pixel(out.width() - 1, 1), Rgb([0, 0, 255]));
+}
@@ -0,0 +1,141 @@
+/*
+* Contact sheets of one image converted under several configs
+*/
+mod common;
+
+use ab_glyph::FontVec;
+use ascii_gen::ascii::config::ConverterConfig;
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::output::contact_sheet::SheetLayout;
+use image::RgbImage;
+use std::fs;
+use std::path::PathBuf;
+
+const FS: u32 = common::FONT_SIZE;
+
+fn scratch(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("ruscii-gen-{}-sheet-{}", std::process::id(), name))
+}
+
+fn configs(count: usize) -> Vec<ConverterConfig> {
+    (0..count)
+        .map(|i| ConverterConfig {
+            draw_edges: i % 2 == 0,
+            tile_levels: Some(4 + i),
+            ..common::test_config()
+        })
+        .collect()
+}
+
+fn make_sheet(name: &str, count: usize) -> (RgbImage, SheetLayout) {
+    let input = scratch(&format!("{}-in.png", name));
+    let output = scratch(&format!("{}-out.png", name));
+    common::circle(FS * 20, FS * 12).save(&input).unwrap();
+    let converter = common::test_converter();
+    converter
+        .convert_contact_sheet(
+            input.to_str().unwrap(),
+            output.to_str().unwrap(),
+            &configs(count),
+        )
+        .unwrap();
+    let sheet = image::open(&output).unwrap().to_rgb8();
+    fs::remove_file(&input).unwrap();
+    fs::remove_file(&output).unwrap();
+
+    let font = FontVec::try_from_vec(fs::read(common::test_font_path()).unwrap()).unwrap();
+    let layout = SheetLayout::new(count, (FS * 20, FS * 12), &font, FS);
+    (sheet, layout)
+}
+
+fn label_pixels(sheet: &RgbImage, layout: &SheetLayout, index: usize) -> usize {
+    // Pixels of the label strip under a tile that differ from the sheet background
+    let bg = *sheet.get_pixel(0, 0);
+    let (x, y) = layout.label_origin(index);
+    let mut count = 0;
+    for py in y..y + layout.label_h {
+        for px in x..x + layout.cell_w {
+            if *sheet.get_pixel(px, py) != bg {
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+#[test]
+fn four_configs_make_a_labeled_two_by_two_sheet() {
+    let (sheet, layout) = make_sheet("four", 4);
+    assert_eq!((layout.cols, layout.rows), (2, 2));
+    assert_eq!(sheet.dimensions(), layout.size());
+    assert_eq!(
+        layout.size(),
+        (
+            2 * FS * 20 + 3 * layout.gap,
+            2 * (FS * 12 + layout.label_h) + 3 * layout.gap
+        )
+    );
+    for i in 0..4 {
+        assert!(label_pixels(&sheet, &layout, i) > 20, "label {} missing", i);
+    }
+    // Every tile holds its render, not the sheet background
+    let bg = *sheet.get_pixel(0, 0);
+    let (x, y) = layout.tile_origin(3);
+    let render_pixels = (0..FS * 12)
+        .flat_map(|dy| (0..FS * 20).map(move |dx| (x + dx, y + dy)))
+        .filter(|&(px, py)| *sheet.get_pixel(px, py) != bg)
+        .count();
+    assert!(render_pixels > 100);
+}
+
+#[test]
+fn grid_grows_with_the_number_of_configs() {
+    let (sheet, layout) = make_sheet("three", 3);
+    assert_eq!((layout.cols, layout.rows), (2, 2));
+    assert_eq!(sheet.dimensions(), layout.size());
+    // The last cell stays empty
+    assert!(label_pixels(&sheet, &layout, 2) > 20);
+    assert_eq!(label_pixels(&sheet, &layout, 3), 0);
+
+    let (sheet, layout) = make_sheet("five", 5);
+    assert_eq!((layout.cols, layout.rows), (3, 2));
+    assert_eq!(sheet.dimensions(), layout.size());
+}
+
+#[test]
+fn renders_of_different_sizes_share_a_cell() {
+    let input = scratch("sizes-in.png");
+    let output = scratch("sizes-out.png");
+    common::circle(FS * 20, FS * 12).save(&input).unwrap();
+    let small = ConverterConfig {
+        font_size: FS / 2,
+        ..common::test_config()
+    };
+    common::test_converter()
+        .convert_contact_sheet(
+            input.to_str().unwrap(),
+            output.to_str().unwrap(),
+            &[common::test_config(), small],
+        )
+        .unwrap();
+    let sheet = image::open(&output).unwrap().to_rgb8();
+    fs::remove_file(&input).unwrap();
+    fs::remove_file(&output).unwrap();
+    let font = FontVec::try_from_vec(fs::read(common::test_font_path()).unwrap()).unwrap();
+    let layout = SheetLayout::new(2, (FS * 20, FS * 12), &font, FS);
+    assert_eq!(sheet.dimensions(), layout.size());
+}
+
+#[test]
+fn no_configs_is_an_error() {
+    let err = common::test_converter()
+        .convert_contact_sheet("unused.png", "unused-out.png", &[])
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        ConvertError::InvalidSetting {
+            field: "configs",
+            ..
+        }
+    ));
+}
@@ -0,0 +1,104 @@
+/*
+* Per call overrides leave the shared converter as it was
+*/
+mod common;
+
+use ascii_gen::ascii::background::BackgroundMode;
+use ascii_gen::ascii::config::ConverterConfig;
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::ascii::options::ConvertOptions;
+use image::{DynamicImage, Rgb, RgbImage};
+
+const FS: u32 = common::FONT_SIZE;
+
+// Dark enough that every cell is a space, leaving the background untouched
+fn dark_image() -> DynamicImage {
+    DynamicImage::ImageRgb8(RgbImage::from_pixel(FS * 6, FS * 4, Rgb([0, 0, 0])))
+}
+
+#[test]
+fn empty_options_match_convert_image() {
+    let converter = common::test_converter();
+    let img = common::circle(FS * 10, FS * 8);
+    assert_eq!(
+        converter
+            .convert_image_with(&img, 0.2, &ConvertOptions::new())
+            .unwrap(),
+        converter.convert_image(&img, 0.2).unwrap()
+    );
+}
+
+#[test]
+fn bg_color_and_background_are_overridden() {
+    let converter = common::test_converter();
+    let options = ConvertOptions::new().with_bg_color(Rgb([1, 2, 3]));
+    let out = converter
+        .convert_image_with(&dark_image(), 0.0, &options)
+        .unwrap();
+    assert!(out.pixels().all(|p| *p == Rgb([1, 2, 3])));
+
+    let options = options.with_background(BackgroundMode::VerticalGradient(
+        Rgb([200, 0, 0]),
+        Rgb([0, 0, 200]),
+    ));
+    let out = converter
+        .convert_image_with(&dark_image(), 0.0, &options)
+        .unwrap();
+    assert_eq!(*out.get_pixel(0, 0), Rgb([200, 0, 0]));
+    assert_eq!(*out.get_pixel(0, out.height() - 1), Rgb([0, 0, 200]));
+
+    // The converter still draws over its own background
+    let base = converter.convert_image(&dark_image(), 0.0).unwrap();
+    let bg = Rgb(common::test_config().bg_color);
+    assert!(base.pixels().all(|p| *p == bg));
+}
+
+#[test]
+fn fixed_color_replaces_image_colors() {
+    let converter = common::test_converter();
+    let img = common::gradient(FS * 12, FS * 4);
+    let options = ConvertOptions::new()
+        .with_use_image_color(false)
+        .with_color(Rgb([0, 255, 0]))
+        .with_bg_color(Rgb([0, 0, 0]));
+    let out = converter.convert_image_with(&img, 0.0, &options).unwrap();
+    // Only the background and shades of the green glyphs, antialiased over black
+    assert!(out.pixels().all(|p| p[0] == 0 && p[2] == 0));
+    assert!(out.pixels().any(|p| p[1] > 200));
+}
+
+#[test]
+fn edges_can_be_turned_off_per_call() {
+    let converter = common::test_converter();
+    let img = common::diagonal_lines(FS * 12, FS * 12);
+    let with_edges = converter.convert_image(&img, 0.0).unwrap();
+    let without = converter
+        .convert_image_with(&img, 0.0, &ConvertOptions::new().with_edges(false))
+        .unwrap();
+    assert_ne!(with_edges, without);
+
+    let config = ConverterConfig {
+        draw_edges: false,
+        ..common::test_config()
+    };
+    assert_eq!(
+        without,
+        config.build().unwrap().convert_image(&img, 0.0).unwrap()
+    );
+    assert_eq!(converter.convert_image(&img, 0.0).unwrap(), with_edges);
+}
+
+#[test]
+fn overrides_are_validated() {
+    let options = ConvertOptions::new().with_background(BackgroundMode::BlurredImage {
+        sigma: 0.0,
+        darken: 0.5,
+    });
+    assert!(matches!(
+        common::test_converter().convert_image_with(&dark_image(), 0.0, &options),
+        Err(ConvertError::InvalidSetting {
+            field: "blurred_image.sigma",
+            ..
+        })
+    ));
+}
@@ -0,0 +1,90 @@
+/*
+* Regression tests for inputs smaller than a single cell
+*/
+mod common;
+
+use ascii_gen::ascii::config::ConverterConfig;
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::image_manip::edge_processor::EdgeDownscaler;
+use image::DynamicImage;
+use ndarray::Array2;
+
+const SMALL_SIZES: [(u32, u32); 3] = [
+    (1, 1),
+    (3, 5),
+    (common::FONT_SIZE - 1, common::FONT_SIZE - 1),
+];
+
+fn fallback_config() -> ConverterConfig {
+    ConverterConfig {
+        small_image_fallback: true,
+        ..common::test_config()
+    }
+}
+
+#[test]
+fn small_images_are_rejected() {
+    let converter = common::test_converter();
+    for (w, h) in SMALL_SIZES {
+        let img = common::noise(w, h, 1);
+        match converter.convert_image(&img, 0.0) {
+            Err(ConvertError::ImageTooSmall { width, height, min }) => {
+                assert_eq!((width, height, min), (w, h, common::FONT_SIZE))
+            }
+            other => panic!("{}x{} gave {:?}", w, h, other.map(|img| img.dimensions())),
+        }
+        assert!(converter.convert_to_text(&img, 0.0).is_err());
+    }
+}
+
+#[test]
+fn small_images_fall_back_to_one_cell() {
+    for draw_edges in [true, false] {
+        let converter = ConverterConfig {
+            draw_edges,
+            ..fallback_config()
+        }
+        .build()
+        .unwrap();
+        for (w, h) in SMALL_SIZES {
+            let img = common::noise(w, h, 1);
+            let rendered = converter.convert_image(&img, 0.0).unwrap();
+            assert_eq!(
+                rendered.dimensions(),
+                (common::FONT_SIZE, common::FONT_SIZE)
+            );
+            let text = converter.convert_to_text(&img, 0.0).unwrap();
+            assert_eq!(
+                text.lines().map(|l| l.chars().count()).collect::<Vec<_>>(),
+                [1]
+            );
+        }
+    }
+}
+
+#[test]
+fn narrow_images_fall_back_to_one_column() {
+    let converter = fallback_config().build().unwrap();
+    let img = common::gradient(3, common::FONT_SIZE * 4);
+    let text = converter.convert_to_text(&img, 0.0).unwrap();
+    assert_eq!(text.lines().count(), 4);
+    assert!(text.lines().all(|l| l.chars().count() == 1));
+}
+
+#[test]
+fn empty_images_are_rejected_even_with_fallback() {
+    let converter = fallback_config().build().unwrap();
+    let img = DynamicImage::new_luma8(0, 0);
+    assert!(matches!(
+        converter.convert_image(&img, 0.0),
+        Err(ConvertError::ImageTooSmall { .. })
+    ));
+}
+
+#[test]
+fn downscale_clamps_tiles_to_the_edge_map() {
+    // Tiles of 4 over a 3x5 map, the last row and column of tiles are cut short
+    let edges = Array2::from_elem((3, 5), 2u8);
+    let ds = EdgeDownscaler::hist_downscale(&edges, 4, 1.0, (1, 2));
+    assert_eq!(ds, Array2::from_elem((1, 2), 2u8));
+}
@@ -0,0 +1,84 @@
+/*
+* TwoScale keeps the coarse output size and only redraws the busy cells
+*/
+mod common;
+
+use ascii_gen::ascii::config::{ConverterConfig, DetailModeConfig};
+use ascii_gen::image_manip::tile_stats::TileStats;
+use image::{DynamicImage, GrayImage, Luma};
+
+const SIZE: u32 = common::FONT_SIZE * 8;
+
+// Flat gray on the left half, noise on the right half
+fn half_noise() -> DynamicImage {
+    let noise = common::noise(SIZE, SIZE, 7).to_luma8();
+    DynamicImage::ImageLuma8(GrayImage::from_fn(SIZE, SIZE, |x, y| {
+        if x < SIZE / 2 {
+            Luma([128])
+        } else {
+            *noise.get_pixel(x, y)
+        }
+    }))
+}
+
+fn two_scale(variance_threshold: f32) -> ConverterConfig {
+    ConverterConfig {
+        detail_mode: DetailModeConfig::TwoScale {
+            fine_factor: 2,
+            variance_threshold,
+        },
+        ..common::test_config()
+    }
+}
+
+#[test]
+fn variance_of_flat_and_split_tiles() {
+    let flat = GrayImage::from_pixel(16, 16, Luma([90]));
+    assert!(TileStats::new(&flat, (2, 2))
+        .variance
+        .iter()
+        .all(|&v| v == 0.0));
+
+    let checker = GrayImage::from_fn(16, 16, |x, y| {
+        Luma([if (x + y) % 2 == 0 { 0 } else { 255 }])
+    });
+    assert!(TileStats::new(&checker, (2, 2))
+        .variance
+        .iter()
+        .all(|&v| (v - 0.25).abs() < 1e-4));
+}
+
+#[test]
+fn busy_cells_are_redrawn_at_the_coarse_size() {
+    let img = half_noise();
+    let single = common::test_converter().convert_image(&img, 0.0).unwrap();
+    let detail = two_scale(0.01)
+        .build()
+        .unwrap()
+        .convert_image(&img, 0.0)
+        .unwrap();
+    assert_eq!(detail.dimensions(), single.dimensions());
+
+    // The flat half is drawn by the coarse pass alone, the noisy half by the fine pass
+    let left_same = (0..SIZE)
+        .flat_map(|y| (0..SIZE / 2).map(move |x| (x, y)))
+        .all(|(x, y)| detail.get_pixel(x, y) == single.get_pixel(x, y));
+    assert!(left_same);
+    let right_same = (0..SIZE)
+        .flat_map(|y| (SIZE / 2..SIZE).map(move |x| (x, y)))
+        .all(|(x, y)| detail.get_pixel(x, y) == single.get_pixel(x, y));
+    assert!(!right_same);
+}
+
+#[test]
+fn nothing_busy_matches_a_single_pass() {
+    let img = half_noise();
+    let single = common::test_converter().convert_image(&img, 0.0).unwrap();
+    // Variance never exceeds 0.25
+    let detail = two_scale(0.3)
+        .build()
+        .unwrap()
+        .convert_image(&img, 0.0)
+        .unwrap();
+    assert_eq!(detail, single);
+}
@@ -0,0 +1,65 @@
+/*
+* Outputs are byte identical across runs and thread counts
+*/
+mod common;
+
+use ascii_gen::output::OutputFormat;
+use rayon::ThreadPoolBuilder;
+use std::io::Cursor;
+
+fn input_png() -> Vec<u8> {
+    // Noise over a circle gives plenty of edge histogram ties and busy cells
+    let circle = common::circle(common::FONT_SIZE * 16, common::FONT_SIZE * 10).to_rgb8();
+    let noise = common::noise(circle.width(), circle.height(), 11).to_rgb8();
+    let img = image::RgbImage::from_fn(circle.width(), circle.height(), |x, y| {
+        let (c, n) = (circle.get_pixel(x, y), noise.get_pixel(x, y));
+        image::Rgb([
+            c[0] / 2 + n[0] / 2,
+            c[1] / 2 + n[1] / 2,
+            c[2] / 2 + n[2] / 2,
+        ])
+    });
+    let mut png = Cursor::new(vec![]);
+    img.write_to(&mut png, image::ImageFormat::Png).unwrap();
+    png.into_inner()
+}
+
+fn convert_with_threads(threads: usize, bytes: &[u8]) -> (Vec<u8>, Vec<u8>) {
+    let pool = ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .unwrap();
+    pool.install(|| {
+        // The converter is not Sync, so it is built on the pool
+        let converter = common::test_converter();
+        (
+            converter
+                .convert_bytes(bytes, OutputFormat::Png, 0.1)
+                .unwrap(),
+            converter
+                .convert_bytes(bytes, OutputFormat::Txt, 0.1)
+                .unwrap(),
+        )
+    })
+}
+
+#[test]
+fn repeated_runs_are_identical() {
+    let bytes = input_png();
+    let first = convert_with_threads(4, &bytes);
+    for _ in 0..4 {
+        assert!(convert_with_threads(4, &bytes) == first, "output changed");
+    }
+}
+
+#[test]
+fn thread_count_does_not_change_the_output() {
+    let bytes = input_png();
+    let single = convert_with_threads(1, &bytes);
+    let many = convert_with_threads(8, &bytes);
+    assert!(single.0 == many.0, "png bytes differ");
+    assert_eq!(
+        String::from_utf8(single.1).unwrap(),
+        String::from_utf8(many.1).unwrap()
+    );
+}
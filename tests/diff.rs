@@ -0,0 +1,152 @@
+/*
+* Cell by cell comparison of two grids and the image highlighting what changed
+*/
+mod common;
+
+use ascii_gen::ascii::config::ConverterConfig;
+use ascii_gen::ascii::diff::grid_diff;
+use ascii_gen::ascii::error::ConvertError;
+use image::{DynamicImage, ImageFormat, Rgb};
+use ndarray::array;
+use std::fs;
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::process::Command;
+
+const HIGHLIGHT: Rgb<u8> = Rgb([255, 0, 0]);
+
+fn scratch(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("ruscii-gen-{}-diff-{}", std::process::id(), name))
+}
+
+#[test]
+fn changed_cells_are_counted_per_row() {
+    let a = array![['#', '.', ' '], ['.', '.', '.'], ['#', '#', '#']];
+    let b = array![['#', ':', ' '], ['.', '.', '.'], ['.', '#', '+']];
+    let diff = grid_diff(&a.view(), &b.view()).unwrap();
+
+    assert_eq!((diff.rows, diff.cols), (3, 3));
+    assert_eq!(diff.changed_cells, 3);
+    assert_eq!(diff.changed_per_row, [1, 0, 2]);
+    assert_eq!(
+        diff.changed_positions().collect::<Vec<_>>(),
+        [(0, 1), (2, 0), (2, 2)]
+    );
+    assert!((diff.changed_ratio() - 1.0 / 3.0).abs() < 1e-6);
+    assert_eq!(
+        diff.to_string(),
+        "changed: 3 of 9 cells (33.3%)\nrow    0: 1 changed\nrow    2: 2 changed"
+    );
+
+    let same = grid_diff(&a.view(), &a.view()).unwrap();
+    assert_eq!(same.changed_cells, 0);
+    assert_eq!(same.to_string(), "changed: 0 of 9 cells (0.0%)");
+}
+
+#[test]
+fn grids_of_different_sizes_are_rejected() {
+    let a = common::char_grid(4, 6, 1);
+    let b = common::char_grid(4, 5, 1);
+    match grid_diff(&a.view(), &b.view()) {
+        Err(ConvertError::GridSizeMismatch { a, b }) => {
+            assert_eq!((a, b), ((4, 6), (4, 5)));
+        }
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn only_changed_cells_are_drawn_in_the_highlight() {
+    let a = common::char_grid(3, 4, 7);
+    let mut b = a.clone();
+    b[[1, 2]] = if b[[1, 2]] == '@' { '&' } else { '@' };
+    let diff = grid_diff(&a.view(), &b.view()).unwrap();
+    assert_eq!(diff.changed_positions().collect::<Vec<_>>(), [(1, 2)]);
+
+    let converter = common::test_converter();
+    let img = converter.render_diff(&b.view(), &diff, HIGHLIGHT).unwrap();
+    let cell = common::FONT_SIZE;
+    assert_eq!(img.dimensions(), (4 * cell, 3 * cell));
+    for (x, y, pixel) in img.enumerate_pixels() {
+        let in_changed_cell = x / cell == 2 && y / cell == 1;
+        if !in_changed_cell {
+            assert_ne!(*pixel, HIGHLIGHT, "({}, {})", x, y);
+        }
+    }
+    let highlighted = img
+        .enumerate_pixels()
+        .filter(|(x, y, p)| x / cell == 2 && y / cell == 1 && **p == HIGHLIGHT)
+        .count();
+    assert!(highlighted > 0);
+
+    let wrong_shape = common::char_grid(3, 5, 7);
+    assert!(matches!(
+        converter.render_diff(&wrong_shape.view(), &diff, HIGHLIGHT),
+        Err(ConvertError::NdArrayShapeError)
+    ));
+}
+
+#[test]
+fn configs_are_compared_on_the_same_image() {
+    let img = common::circle(common::FONT_SIZE * 8, common::FONT_SIZE * 6);
+    let a = common::test_converter();
+    let grid_a = a.convert_to_chars(&img, 0.0).unwrap();
+    let again = a.convert_to_chars(&img, 0.0).unwrap();
+    assert_eq!(
+        grid_diff(&grid_a.view(), &again.view())
+            .unwrap()
+            .changed_cells,
+        0
+    );
+
+    let config = common::test_config();
+    let inverted = ConverterConfig {
+        tile_chars: config.tile_chars.chars().rev().collect(),
+        ..config
+    }
+    .build()
+    .unwrap();
+    let grid_b = inverted.convert_to_chars(&img, 0.0).unwrap();
+    assert!(
+        grid_diff(&grid_a.view(), &grid_b.view())
+            .unwrap()
+            .changed_cells
+            > 0
+    );
+}
+
+#[test]
+fn each_config_decodes_the_input_its_own_way() {
+    let mut bytes = vec![];
+    DynamicImage::ImageRgb8(common::circle(128, 128).to_rgb8())
+        .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Jpeg)
+        .unwrap();
+    let input = scratch("truncated.jpg");
+    fs::write(&input, &bytes[..bytes.len() * 2 / 3]).unwrap();
+    let toml = format!(
+        "font_size = {}\nfont_path = {:?}\n",
+        common::FONT_SIZE,
+        common::test_font_path()
+    );
+    let (tolerant, strict) = (scratch("tolerant.toml"), scratch("strict.toml"));
+    fs::write(&tolerant, format!("{}tolerant_decode = true\n", toml)).unwrap();
+    fs::write(&strict, toml).unwrap();
+    let diff = |config_b: &PathBuf| {
+        Command::new(env!("CARGO_BIN_EXE_ruscii-gen"))
+            .arg("diff")
+            .arg(&input)
+            .arg("--config-a")
+            .arg(&tolerant)
+            .arg("--config-b")
+            .arg(config_b)
+            .output()
+            .expect("Failed running ruscii-gen")
+    };
+
+    let out = diff(&tolerant);
+    assert!(out.status.success(), "{:?}", out);
+    // The second config does not decode a partial image, even though the first one does
+    let out = diff(&strict);
+    assert!(!out.status.success());
+    assert!(String::from_utf8_lossy(&out.stderr).contains("truncated.jpg"));
+}
@@ -0,0 +1,198 @@
+/*
+* Edge characters can be drawn in a color of their own
+*/
+mod common;
+
+use ascii_gen::ascii::config::{ConverterConfig, EdgeColorConfig, MagnitudeReduceConfig};
+use ascii_gen::ascii::converter::Converter;
+use ascii_gen::ascii::edge_color::EdgeColorMode;
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::image_manip::edge_processor::{EdgeDownscaler, MagnitudeReduce};
+use image::{DynamicImage, GrayImage, Luma, Rgb};
+use ndarray::array;
+
+const FS: u32 = common::FONT_SIZE;
+const EDGE_CHARS: &str = "_|/\\";
+
+fn red_edges() -> Converter {
+    ConverterConfig {
+        bg_color: [0, 0, 0],
+        use_image_color: false,
+        color: [255, 255, 255],
+        edge_color: EdgeColorConfig::Fixed { color: [255, 0, 0] },
+        ..common::test_config()
+    }
+    .build()
+    .unwrap()
+}
+
+#[test]
+fn fixed_edge_color_only_shows_on_edge_cells() {
+    let converter = red_edges();
+    let img = common::circle(FS * 16, FS * 16);
+    let grid: Vec<Vec<char>> = converter
+        .convert_to_text(&img, 0.0)
+        .unwrap()
+        .lines()
+        .map(|line| line.chars().collect())
+        .collect();
+    let out = converter.convert_image(&img, 0.0).unwrap();
+
+    let mut red_pixels = 0;
+    for (x, y, p) in out.enumerate_pixels() {
+        // Antialiased red over black stays red, white tiles never lean toward red
+        if p[0] > 40 && p[1] == 0 && p[2] == 0 {
+            red_pixels += 1;
+            let ch = grid[(y / FS) as usize][(x / FS) as usize];
+            assert!(EDGE_CHARS.contains(ch), "red pixel in a {:?} cell", ch);
+        }
+    }
+    assert!(red_pixels > 0);
+}
+
+#[test]
+fn ansi_output_honors_the_edge_color() {
+    let ansi = red_edges()
+        .convert_to_ansi(&common::circle(FS * 16, FS * 16), 0.0)
+        .unwrap();
+    let red = "\x1b[38;2;255;0;0m";
+    assert!(ansi.contains(red));
+    for run in ansi.split(red).skip(1) {
+        let chars: String = run.chars().take_while(|&c| c != '\x1b').collect();
+        assert!(!chars.is_empty());
+        assert!(chars.chars().all(|c| EDGE_CHARS.contains(c)), "{:?}", chars);
+    }
+}
+
+#[test]
+fn same_as_tile_is_the_default() {
+    let img = common::diagonal_lines(FS * 12, FS * 12);
+    let config = ConverterConfig {
+        edge_color: EdgeColorConfig::SameAsTile,
+        ..common::test_config()
+    };
+    assert_eq!(
+        config.build().unwrap().convert_image(&img, 0.0).unwrap(),
+        common::test_converter().convert_image(&img, 0.0).unwrap()
+    );
+}
+
+#[test]
+fn brightened_scales_the_cell_color() {
+    let mode = EdgeColorMode::Brightened { factor: 1.5 };
+    assert_eq!(mode.edge_color(Rgb([100, 200, 0])), Rgb([150, 255, 0]));
+
+    let config = ConverterConfig {
+        edge_color: EdgeColorConfig::Brightened { factor: -1.0 },
+        ..common::test_config()
+    };
+    assert!(matches!(
+        config.build(),
+        Err(ConvertError::InvalidSetting {
+            field: "brightened.factor",
+            ..
+        })
+    ));
+}
+
+fn magnitude_edges(floor: f32) -> ConverterConfig {
+    ConverterConfig {
+        bg_color: [0, 0, 0],
+        use_image_color: false,
+        color: [255, 255, 255],
+        edge_color: EdgeColorConfig::Magnitude {
+            reduce: MagnitudeReduceConfig::Max,
+            floor,
+        },
+        ..common::test_config()
+    }
+}
+
+#[test]
+fn edge_brightness_follows_the_gradient_magnitude() {
+    // A faint step down from 110 to 100 on the left and a hard one from 255 to 0 on the right
+    let img = DynamicImage::ImageLuma8(GrayImage::from_fn(FS * 16, FS * 8, |x, _| {
+        Luma([match x / (FS * 4) {
+            0 => 110,
+            1 => 100,
+            2 => 255,
+            _ => 0,
+        }])
+    }));
+    // Without preprocessors so the faint step is detected at all
+    let converter = ConverterConfig {
+        edge_preprocessors: vec![],
+        ..magnitude_edges(0.2)
+    }
+    .build()
+    .unwrap();
+    let grid: Vec<Vec<char>> = converter
+        .convert_to_text(&img, 0.0)
+        .unwrap()
+        .lines()
+        .map(|line| line.chars().collect())
+        .collect();
+    let out = converter.convert_image(&img, 0.0).unwrap();
+
+    // Brightest glyph pixel over the edge cells of the columns around each step
+    let brightest = |cols: std::ops::RangeInclusive<u32>| {
+        out.enumerate_pixels()
+            .filter(|(x, y, _)| {
+                cols.contains(&(x / FS))
+                    && EDGE_CHARS.contains(grid[(y / FS) as usize][(x / FS) as usize])
+            })
+            .map(|(_, _, p)| p[0])
+            .max()
+            .expect("no edge cells")
+    };
+    let (faint, strong) = (brightest(2..=5), brightest(10..=13));
+    // A tenth of the magnitude and a floor of 0.2 leave the faint edge at about a quarter
+    assert!(faint * 3 < strong, "{} {}", faint, strong);
+    // The floor keeps the faint edge visible
+    assert!(faint > 0);
+}
+
+#[test]
+fn magnitude_is_carried_through_the_downscale() {
+    // Two 2x2 tiles, the left one with a strong and a weak pixel of bin 2, the right without edges
+    let edges = array![[2u8, 2, 0, 0], [2, 1, 0, 0]];
+    let magnitude = array![[100.0f32, 20.0, 50.0, 0.0], [60.0, 500.0, 0.0, 0.0]];
+    for (reduce, expected) in [(MagnitudeReduce::Max, 100.0), (MagnitudeReduce::Mean, 60.0)] {
+        let (bins, strength) = EdgeDownscaler::hist_downscale_with_magnitude(
+            &edges,
+            &magnitude,
+            (2, 2),
+            0.0,
+            (1, 2),
+            reduce,
+        );
+        assert_eq!(bins, array![[2, 0]]);
+        // Only the pixels of the winning bin count, and cells without an edge stay at 0
+        assert_eq!(strength, array![[expected, 0.0]]);
+    }
+}
+
+#[test]
+fn magnitude_floor_is_validated() {
+    for floor in [-0.1, 1.5, f32::NAN] {
+        assert!(matches!(
+            magnitude_edges(floor).build(),
+            Err(ConvertError::InvalidSetting {
+                field: "magnitude.floor",
+                ..
+            })
+        ));
+    }
+    let mode = EdgeColorMode::Magnitude {
+        reduce: MagnitudeReduce::Mean,
+        floor: 0.5,
+    };
+    assert_eq!(
+        mode.edge_color_with_strength(Rgb([200, 100, 0]), 0.0),
+        Rgb([100, 50, 0])
+    );
+    assert_eq!(
+        mode.edge_color_with_strength(Rgb([200, 100, 0]), 1.0),
+        Rgb([200, 100, 0])
+    );
+}
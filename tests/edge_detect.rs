@@ -0,0 +1,169 @@
+/*
+* Edge detectors on noisy synthetic lines. A cell showing more than one edge direction makes the
+* downscaled edge character a coin flip, so fewer mixed cells means steadier strokes
+*/
+mod common;
+
+use ascii_gen::image_manip::edge_detect::{EdgeDetect, EdgeField, Sobel, StructureTensor};
+use ascii_gen::image_manip::edge_flow::EdgeTangentFlow;
+use image::{GrayImage, ImageBuffer, Luma};
+
+const TILE: u32 = common::FONT_SIZE;
+
+fn noisy_lines(w: u32, h: u32) -> GrayImage {
+    let lines = common::diagonal_lines(w, h).to_luma8();
+    let noise = common::noise(w, h, 11).to_luma8();
+    GrayImage::from_fn(w, h, |x, y| {
+        let n = noise.get_pixel(x, y)[0] as i32 / 4 - 32;
+        Luma([(lines.get_pixel(x, y)[0] as i32 + n).clamp(0, 255) as u8])
+    })
+}
+
+fn mixed_cells(edges: &ImageBuffer<Luma<u8>, Vec<u8>>) -> usize {
+    let (w, h) = edges.dimensions();
+    let mut mixed = 0;
+    for ty in 0..h / TILE {
+        for tx in 0..w / TILE {
+            let mut bins = vec![];
+            for y in ty * TILE..(ty + 1) * TILE {
+                for x in tx * TILE..(tx + 1) * TILE {
+                    let bin = edges.get_pixel(x, y)[0];
+                    if bin != 0 && !bins.contains(&bin) {
+                        bins.push(bin);
+                    }
+                }
+            }
+            if bins.len() > 1 {
+                mixed += 1;
+            }
+        }
+    }
+    mixed
+}
+
+#[test]
+fn structure_tensor_mixes_fewer_directions_than_sobel() {
+    let img = noisy_lines(160, 96);
+    let sobel = mixed_cells(&Sobel::new().apply(&img, 5).unwrap());
+    let tensor = mixed_cells(&StructureTensor::default().apply(&img, 5).unwrap());
+    assert!(
+        tensor < sobel,
+        "structure tensor mixed {} cells, sobel {}",
+        tensor,
+        sobel
+    );
+}
+
+#[test]
+fn structure_tensor_bins_line_directions() {
+    // Edge bins are 1 '_', 2 '|', 3 '/', 4 '\'
+    let horizontal = GrayImage::from_fn(32, 32, |_, y| Luma([if y < 16 { 0 } else { 255 }]));
+    let vertical = GrayImage::from_fn(32, 32, |x, _| Luma([if x < 16 { 0 } else { 255 }]));
+    let falling = GrayImage::from_fn(32, 32, |x, y| Luma([if x > y { 0 } else { 255 }]));
+    let rising = GrayImage::from_fn(32, 32, |x, y| Luma([if x + y < 32 { 0 } else { 255 }]));
+
+    let detector = StructureTensor::default();
+    // Each case also names a point in a flat region far from the edge, which must stay empty
+    for (img, bin, flat) in [
+        (horizontal, 1, (4, 4)),
+        (vertical, 2, (4, 4)),
+        (rising, 3, (4, 4)),
+        (falling, 4, (4, 28)),
+    ] {
+        let edges = detector.apply(&img, 5).unwrap();
+        assert_eq!(edges.get_pixel(16, 16)[0], bin);
+        assert_eq!(edges.get_pixel(flat.0, flat.1)[0], 0);
+    }
+}
+
+#[test]
+fn edge_flow_follows_a_wavy_curve() {
+    // Bright below y = 48 + 24 sin(2 pi x / 160). Over the first quarter period the contour turns
+    // from diagonal to flat, so the bins along it should only ever move towards '_'
+    let (w, h) = (160u32, 96u32);
+    let curve = |x: u32| 48.0 + 24.0 * (2.0 * std::f32::consts::PI * x as f32 / w as f32).sin();
+    let noise = common::noise(w, h, 5).to_luma8();
+    let img = GrayImage::from_fn(w, h, |x, y| {
+        let base = if y as f32 > curve(x) { 220 } else { 30 };
+        Luma([(base + noise.get_pixel(x, y)[0] as i32 / 8 - 16) as u8])
+    });
+
+    let field = Sobel::new().field(&img).unwrap();
+    let edges = EdgeTangentFlow::new(3, 3).apply(&field).quantize();
+
+    // Order of the bins as the contour flattens
+    let rank = |bin: u8| match bin {
+        2 => Some(0),
+        4 => Some(1),
+        1 => Some(2),
+        _ => None,
+    };
+    let ranks: Vec<u8> = (2..w / 4)
+        .filter_map(|x| rank(edges.get_pixel(x, curve(x).round() as u32)[0]))
+        .collect();
+    assert!(ranks.len() > (w / 4) as usize / 2, "curve mostly missed");
+    assert!(
+        ranks.windows(2).all(|pair| pair[0] <= pair[1]),
+        "bins along the curve are not monotone: {:?}",
+        ranks
+    );
+}
+
+// Edge bin of a contour at degrees from horizontal, going up and to the right
+fn contour_bin(degrees: f32, cell_aspect: f32) -> u8 {
+    // The gradient is a quarter turn from the contour, image y points down
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let field = EdgeField {
+        gx: ndarray::arr2(&[[sin]]),
+        gy: ndarray::arr2(&[[cos]]),
+    };
+    field.quantize_for_aspect(cell_aspect).get_pixel(0, 0)[0]
+}
+
+#[test]
+fn bins_follow_the_cell_aspect() {
+    const FLAT: u8 = 1;
+    const UPRIGHT: u8 = 2;
+    const SLASH: u8 = 3;
+    // Square cells keep the plain binning
+    assert_eq!(contour_bin(45.0, 1.0), SLASH);
+    assert_eq!(contour_bin(75.0, 1.0), UPRIGHT);
+    assert_eq!(contour_bin(30.0, 1.0), SLASH);
+
+    // In 1:2 cells '/' is drawn at about 63 degrees, so steep contours move from '|' to it
+    assert_eq!(contour_bin(45.0, 0.5), SLASH);
+    assert_eq!(contour_bin(75.0, 0.5), SLASH);
+    assert_eq!(contour_bin(30.0, 0.5), FLAT);
+
+    // In 2:1 cells '/' is drawn at about 27 degrees and the steep ones go to '|' sooner
+    assert_eq!(contour_bin(60.0, 2.0), UPRIGHT);
+    assert_eq!(contour_bin(20.0, 2.0), SLASH);
+}
+
+#[test]
+fn sobel_min_magnitude_keeps_only_hard_steps() {
+    // A soft ramp at the top, a hard step of 100 levels further down
+    let img = GrayImage::from_fn(16, 64, |_, y| {
+        Luma([if y < 32 {
+            (y * 2) as u8
+        } else if y < 48 {
+            100
+        } else {
+            200
+        }])
+    });
+    let all = Sobel::new().apply(&img, 5).unwrap();
+    let hard = Sobel::new()
+        .with_min_magnitude(200.0)
+        .apply(&img, 5)
+        .unwrap();
+    let edge_rows = |edges: &ImageBuffer<Luma<u8>, Vec<u8>>| -> Vec<u32> {
+        (1..63).filter(|&y| edges.get_pixel(8, y)[0] != 0).collect()
+    };
+    assert!(edge_rows(&all).iter().any(|&y| y < 30));
+    assert_eq!(edge_rows(&hard), vec![47, 48]);
+
+    let field = Sobel::new().with_min_magnitude(200.0).field(&img).unwrap();
+    assert_eq!(field.gy[(10, 8)], 0.0);
+    assert!(field.gy[(47, 8)] > 0.0);
+}
@@ -0,0 +1,95 @@
+/*
+* Tiles next to edge cells biased towards the dense end of the ramp
+*/
+mod common;
+
+use ascii_gen::ascii::cell::CellValue;
+use ascii_gen::ascii::char_set::CharacterSet;
+use ascii_gen::ascii::config::ConverterConfig;
+use ascii_gen::ascii::converter::Converter;
+use ascii_gen::image_manip::edge_processor::edge_halo;
+use ndarray::Array2;
+
+fn halo_converter(edge_halo_bias: u8) -> Converter {
+    ConverterConfig {
+        edge_halo_bias,
+        ..common::test_config()
+    }
+    .build()
+    .unwrap()
+}
+
+#[test]
+fn single_edge_cell_biases_exactly_its_eight_neighbors() {
+    let tiles = Array2::from_elem((5, 5), 3usize);
+    let mut edges = Array2::zeros((5, 5));
+    edges[(2, 2)] = 2u8;
+
+    let cells = halo_converter(1)
+        .combine(&tiles.view(), &edges.view())
+        .unwrap();
+    for ((y, x), &cell) in cells.indexed_iter() {
+        let expected = if (y, x) == (2, 2) {
+            CellValue::Edge(2)
+        } else if y.abs_diff(2) <= 1 && x.abs_diff(2) <= 1 {
+            CellValue::Tile(4)
+        } else {
+            CellValue::Tile(3)
+        };
+        assert_eq!(cell, expected, "({}, {})", y, x);
+    }
+
+    // Off by default
+    let plain = common::test_converter()
+        .combine(&tiles.view(), &edges.view())
+        .unwrap();
+    assert_eq!(
+        plain.iter().filter(|&&c| c == CellValue::Tile(3)).count(),
+        24
+    );
+}
+
+#[test]
+fn bias_is_clamped_to_the_ramp_and_the_grid() {
+    let levels = CharacterSet::default().tile.len();
+    let tiles = Array2::from_elem((3, 3), levels - 2);
+    let mut edges = Array2::zeros((3, 3));
+    edges[(0, 0)] = 1u8;
+
+    let halo = edge_halo(&edges.view());
+    assert_eq!(halo.iter().filter(|&&h| h).count(), 3);
+    assert!(!halo[(0, 0)]);
+
+    let cells = halo_converter(5)
+        .combine(&tiles.view(), &edges.view())
+        .unwrap();
+    for pos in [(0, 1), (1, 0), (1, 1)] {
+        assert_eq!(cells[pos], CellValue::Tile(levels - 1), "{:?}", pos);
+    }
+    assert_eq!(cells[(2, 2)], CellValue::Tile(levels - 2));
+}
+
+#[test]
+fn converted_contours_only_get_denser_next_to_edges() {
+    let img = common::circle(common::FONT_SIZE * 16, common::FONT_SIZE * 12);
+    let plain = common::test_converter()
+        .convert_to_chars(&img, 0.0)
+        .unwrap();
+    let biased = halo_converter(1).convert_to_chars(&img, 0.0).unwrap();
+
+    let charset = CharacterSet::default();
+    let edges = plain.mapv(|ch| u8::from("_|/\\".contains(ch)));
+    let halo = edge_halo(&edges.view());
+    let mut changed = 0;
+    for ((pos, before), after) in plain.indexed_iter().zip(biased.iter()) {
+        if halo[pos] {
+            let a = charset.find_tile_char_index(before).unwrap();
+            let b = charset.find_tile_char_index(after).unwrap();
+            assert!(b == a + 1 || b == a, "{:?}", pos);
+            changed += usize::from(b != a);
+        } else {
+            assert_eq!(before, after, "{:?}", pos);
+        }
+    }
+    assert!(changed > 0);
+}
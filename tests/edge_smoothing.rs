@@ -0,0 +1,33 @@
+use ascii_gen::image_manip::edge_processor::mode_filter;
+use ndarray::{array, Array2};
+
+#[test]
+fn single_flip_is_corrected() {
+    let mut grid = Array2::from_elem((5, 5), 1u8);
+    grid[(2, 2)] = 3;
+    assert_eq!(mode_filter(&grid, 1, 2), Array2::from_elem((5, 5), 1u8));
+}
+
+#[test]
+fn zeros_are_untouched() {
+    let grid = array![[2, 2, 2], [2, 0, 2], [2, 2, 2]];
+    assert_eq!(mode_filter(&grid, 1, 1), grid);
+}
+
+#[test]
+fn agreement_below_the_count_is_kept() {
+    // The middle 3 only has two disagreeing neighbors
+    let grid = array![[0, 0, 0], [1, 3, 1], [0, 0, 0]];
+    assert_eq!(mode_filter(&grid, 1, 3), grid);
+    assert_eq!(
+        mode_filter(&grid, 1, 2),
+        array![[0, 0, 0], [1, 1, 1], [0, 0, 0]]
+    );
+}
+
+#[test]
+fn zero_radius_changes_nothing() {
+    let mut grid = Array2::from_elem((5, 5), 1u8);
+    grid[(2, 2)] = 3;
+    assert_eq!(mode_filter(&grid, 0, 1), grid);
+}
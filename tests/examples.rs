@@ -0,0 +1,41 @@
+/*
+* Runs every example so they keep building and working as the API changes. The examples generate
+* their own inputs and write to the temp directory
+*/
+#[path = "../examples/animation.rs"]
+mod animation;
+#[path = "../examples/ansi_preview.rs"]
+mod ansi_preview;
+#[path = "../examples/basic.rs"]
+mod basic;
+#[cfg(feature = "batch")]
+#[path = "../examples/batch.rs"]
+mod batch;
+#[path = "../examples/custom_pipeline.rs"]
+mod custom_pipeline;
+
+#[test]
+fn basic_example_runs() {
+    basic::main().unwrap();
+}
+
+#[test]
+fn custom_pipeline_example_runs() {
+    custom_pipeline::main().unwrap();
+}
+
+#[test]
+fn ansi_preview_example_runs() {
+    ansi_preview::main().unwrap();
+}
+
+#[test]
+fn animation_example_runs() {
+    animation::main().unwrap();
+}
+
+#[cfg(feature = "batch")]
+#[test]
+fn batch_example_runs() {
+    batch::main().unwrap();
+}
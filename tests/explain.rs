@@ -0,0 +1,104 @@
+/*
+* The resolved settings of a converter, and where every value came from, printed as a tree
+*/
+mod common;
+
+use ascii_gen::ascii::config::ConverterConfig;
+use ascii_gen::ascii::provenance::{changed_fields, Origin, Provenance};
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+fn scratch(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "ruscii-gen-{}-explain-{}",
+        std::process::id(),
+        name
+    ))
+}
+
+#[test]
+fn describe_lists_every_stage_with_its_parameters() {
+    let tree = common::test_converter().describe();
+    assert!(tree.starts_with("font\n"));
+    assert!(tree.contains(&format!("  size: {}\n", common::FONT_SIZE)));
+    assert!(tree.contains("  edge: 4 stages\n    - sharpen_gaussian sigma="));
+    assert!(tree.contains("    - median_blur x_radius="));
+    assert!(tree.contains("  edge detector: sobel min_magnitude=0\n"));
+    // The test font loads, so the glyph densities are measured
+    assert!(tree.contains("  densities: ' ' 0.000, "));
+    // Without a provenance nothing is marked
+    assert!(!tree.contains("(default)"));
+}
+
+#[test]
+fn provenance_marks_the_fields_a_step_changed() {
+    let base = common::test_config();
+    let tuned = ConverterConfig {
+        tile_chars: " .:#".to_string(),
+        seed: 7,
+        ..base.clone()
+    };
+    assert_eq!(changed_fields(&base, &tuned), vec!["tile_chars", "seed"]);
+
+    let mut provenance = Provenance::new();
+    provenance.record(&base, &tuned, Origin::User);
+    assert_eq!(provenance.origin("seed"), Origin::User);
+    assert_eq!(provenance.origin("font_size"), Origin::Default);
+    let tree = tuned
+        .build()
+        .unwrap()
+        .with_provenance(Some(provenance))
+        .describe();
+    assert!(tree.contains("  tile: \" .:#\" (user)\n"));
+    assert!(tree.contains("  seed: 7 (user)\n"));
+    assert!(tree.contains("  jitter: 0 (default)\n"));
+}
+
+#[test]
+fn describe_input_gives_the_grid_of_the_input() {
+    let converter = common::test_converter();
+    let tree = converter.describe_input(64, 40, 0.25).unwrap();
+    assert!(tree.starts_with(&converter.describe()));
+    assert!(tree.ends_with(
+        "input\n  size: 64x40\n  grid: 8x5 cells\n  render: 64x40 px\n  edge threshold: 0.25\n"
+    ));
+}
+
+#[test]
+fn cli_marks_the_override_as_user_provided() {
+    let input = scratch("circle.png");
+    common::circle(64, 64).save(&input).unwrap();
+    let config = scratch("config.toml");
+    let toml = format!(
+        "font_size = {}\nfont_path = {:?}\n",
+        common::FONT_SIZE,
+        common::test_font_path()
+    );
+    fs::write(&config, toml).unwrap();
+
+    let out = Command::new(env!("CARGO_BIN_EXE_ruscii-gen"))
+        .arg(&input)
+        .arg("--config")
+        .arg(&config)
+        .args(["--preset", "line-art", "--trim", "bounding", "--explain"])
+        .output()
+        .expect("Failed running ruscii-gen");
+    assert!(out.status.success(), "{:?}", out);
+    let tree = String::from_utf8(out.stdout).expect("Output is not utf-8");
+
+    assert!(tree.contains("  trim: Bounding (user)\n"));
+    assert!(tree.contains("  tile: \" \" (preset)\n"));
+    assert!(tree.contains(&format!("  size: {} (config file)\n", common::FONT_SIZE)));
+    assert!(tree.contains("  filter: Triangle (default)\n"));
+    assert!(tree.contains("  grid: 8x8 cells\n"));
+    // Nothing is converted
+    assert!(
+        tree.ends_with("  edge threshold: 0 (default)\n"),
+        "{}",
+        tree
+    );
+
+    fs::remove_file(input).unwrap();
+    fs::remove_file(config).unwrap();
+}
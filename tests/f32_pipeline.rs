@@ -0,0 +1,134 @@
+/*
+* Edge stages on f32 keep the weak and negative responses a u8 pipeline clamps away
+*/
+mod common;
+
+use ascii_gen::ascii::config::{
+    ConverterConfig, NormalizationConfig, ProcessorConfig, ThresholdModeConfig,
+};
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::image_manip::processing::{
+    DoG, F32Chain, Normalization, Processor, SharpenGaussian, Threshold,
+};
+use image::{DynamicImage, GrayImage, Luma};
+
+// Faint bright square on a mid gray background
+fn low_contrast(w: u32, h: u32) -> GrayImage {
+    GrayImage::from_fn(w, h, |x, y| {
+        let inside = (w / 4..3 * w / 4).contains(&x) && (h / 4..3 * h / 4).contains(&y);
+        Luma([if inside { 126 } else { 120 }])
+    })
+}
+
+fn u8_pipeline(bufr: &GrayImage) -> GrayImage {
+    let stages: Vec<Box<dyn Processor<u8, u8>>> =
+        vec![Box::new(DoG::new(1.0, 3.5)), Box::new(Threshold::new(0))];
+    let mut out = bufr.clone();
+    for stage in stages.iter() {
+        out = stage.apply(&out).unwrap();
+    }
+    out
+}
+
+fn f32_chain(normalization: Normalization) -> F32Chain {
+    F32Chain::new(
+        Box::new(DoG::new(1.0, 3.5)),
+        vec![Box::new(Threshold::new(0))],
+        normalization,
+    )
+}
+
+fn nonzero(bufr: &GrayImage) -> usize {
+    bufr.iter().filter(|&&v| v != 0).count()
+}
+
+#[test]
+fn f32_chain_preserves_weak_edges() {
+    let bufr = low_contrast(64, 64);
+    let u8_out = u8_pipeline(&bufr);
+    let f32_out = f32_chain(Normalization::MinMax).apply(&bufr).unwrap();
+
+    // The u8 pipeline only keeps the dark side of the step and rounds most of it away
+    assert!(nonzero(&f32_out) > 2 * nonzero(&u8_out));
+    assert_eq!(*f32_out.iter().max().unwrap(), 255);
+    assert!(*u8_out.iter().max().unwrap() < 5);
+    // Both sides of the step survive on f32
+    assert_ne!(f32_out.get_pixel(16, 32)[0], 0);
+    assert_ne!(f32_out.get_pixel(15, 32)[0], 0);
+}
+
+#[test]
+fn clamped_f32_chain_stays_close_to_u8() {
+    let bufr = common::circle(64, 48).to_luma8();
+    let u8_out = u8_pipeline(&bufr);
+    let f32_out = f32_chain(Normalization::Clamp).apply(&bufr).unwrap();
+    // The u8 pipeline truncates both blurs before subtracting, so it can be off by two
+    for (a, b) in u8_out.iter().zip(f32_out.iter()) {
+        assert!(a.abs_diff(*b) <= 2, "{} vs {}", a, b);
+    }
+}
+
+#[test]
+fn f32_sharpen_is_not_clamped() {
+    let bufr = low_contrast(32, 32);
+    let stage: Box<dyn Processor<u8, f32>> = Box::new(SharpenGaussian::new(1.0, 100.0));
+    let out = stage.apply(&bufr).unwrap();
+    assert!(out.iter().any(|&v| v > 255.0));
+    assert!(out.iter().any(|&v| v < 0.0));
+}
+
+#[test]
+fn converter_finds_more_edges_with_f32_stages() {
+    let img = DynamicImage::ImageLuma8(low_contrast(common::FONT_SIZE * 12, common::FONT_SIZE * 8));
+    let stages = vec![
+        ProcessorConfig::DoG {
+            sigma_1: 1.0,
+            sigma_2: 3.5,
+        },
+        ProcessorConfig::Threshold {
+            threshold: 0,
+            mode: ThresholdModeConfig::default(),
+        },
+    ];
+    let u8_config = ConverterConfig {
+        edge_preprocessors: stages.clone(),
+        ..common::test_config()
+    };
+    let f32_config = ConverterConfig {
+        edge_preprocessors: vec![],
+        edge_f32_stages: stages,
+        edge_f32_normalization: NormalizationConfig::MinMax,
+        ..common::test_config()
+    };
+
+    let count = |config: ConverterConfig| {
+        let edges = config.build().unwrap().detect_edges(&img, 0.0).unwrap();
+        edges.iter().filter(|&&v| v != 0).count()
+    };
+    assert!(count(f32_config) > count(u8_config));
+}
+
+#[test]
+fn u8_only_stages_are_rejected() {
+    let config = ConverterConfig {
+        edge_f32_stages: vec![
+            ProcessorConfig::DoG {
+                sigma_1: 1.0,
+                sigma_2: 3.5,
+            },
+            ProcessorConfig::MedianBlur {
+                kernel_size: 2,
+                x_radius: None,
+                y_radius: None,
+            },
+        ],
+        ..common::test_config()
+    };
+    assert!(matches!(
+        config.build(),
+        Err(ConvertError::InvalidSetting {
+            field: "edge_f32_stages",
+            ..
+        })
+    ));
+}
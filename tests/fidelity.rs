@@ -0,0 +1,115 @@
+/*
+* Fidelity scores of a render against its original
+*/
+mod common;
+
+use ascii_gen::ascii::config::ConverterConfig;
+use ascii_gen::image_manip::fidelity::compare;
+use ascii_gen::output::OutputFormat;
+use image::{DynamicImage, ImageFormat, Rgb, RgbImage};
+use std::fs;
+use std::io::Cursor;
+
+const FS: u32 = common::FONT_SIZE;
+
+fn textured(w: u32, h: u32) -> DynamicImage {
+    // A circle with noise over it, so every window has some structure
+    let circle = common::circle(w, h).to_luma8();
+    let noise = common::noise(w, h, 7).to_luma8();
+    DynamicImage::ImageRgb8(RgbImage::from_fn(w, h, |x, y| {
+        let v = circle.get_pixel(x, y)[0] / 2 + noise.get_pixel(x, y)[0] / 4;
+        Rgb([v, v / 2, 255 - v])
+    }))
+}
+
+#[test]
+fn identical_images_score_perfectly() {
+    let img = textured(300, 200);
+    let score = compare(&img, &img.to_rgb8());
+    assert_eq!(score.mse, 0.0);
+    assert!((score.ssim - 1.0).abs() < 1e-4, "{:?}", score);
+    assert!(
+        (score.luminance_correlation - 1.0).abs() < 1e-4,
+        "{:?}",
+        score
+    );
+}
+
+#[test]
+fn inverted_image_correlates_negatively() {
+    let img = textured(300, 200);
+    let mut inverted = img.to_rgb8();
+    image::imageops::invert(&mut inverted);
+    let score = compare(&img, &inverted);
+    assert!(score.luminance_correlation < -0.9, "{:?}", score);
+    assert!(score.ssim < 0.2, "{:?}", score);
+    assert!(score.mse > 0.1, "{:?}", score);
+}
+
+#[test]
+fn closer_images_score_better() {
+    let img = textured(256, 256);
+    let mut blurred = image::imageops::blur(&img.to_rgb8(), 1.0);
+    let near = compare(&img, &blurred);
+    blurred = image::imageops::blur(&blurred, 6.0);
+    let far = compare(&img, &blurred);
+    assert!(near.mse < far.mse, "{:?} {:?}", near, far);
+    assert!(near.ssim > far.ssim, "{:?} {:?}", near, far);
+    assert!(near.luminance_correlation > far.luminance_correlation);
+}
+
+#[test]
+fn sizes_are_brought_together_and_flat_images_agree() {
+    let flat = DynamicImage::ImageRgb8(RgbImage::from_pixel(640, 480, Rgb([90, 90, 90])));
+    let score = compare(&flat, &RgbImage::from_pixel(33, 17, Rgb([90, 90, 90])));
+    assert_eq!(score.mse, 0.0);
+    assert!((score.ssim - 1.0).abs() < 1e-4);
+    assert_eq!(score.luminance_correlation, 1.0);
+
+    let score = compare(&flat, &textured(40, 30).to_rgb8());
+    assert_eq!(score.luminance_correlation, 0.0);
+    assert!(compare(&flat, &RgbImage::new(0, 0)).mse == 0.0);
+}
+
+#[test]
+fn conversions_report_fidelity_only_when_scoring() {
+    let img = common::circle(FS * 30, FS * 20);
+    let mut bytes = vec![];
+    img.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+        .unwrap();
+
+    let plain = common::test_converter();
+    let (_, stats) = plain
+        .convert_bytes_with_stats(&bytes, OutputFormat::Png, 0.0)
+        .unwrap();
+    assert!(stats.fidelity.is_none());
+
+    let scoring = ConverterConfig {
+        score_fidelity: true,
+        ..common::test_config()
+    }
+    .build()
+    .unwrap();
+    // Text outputs are rendered for the score as well
+    for format in [OutputFormat::Png, OutputFormat::Txt] {
+        let (_, stats) = scoring
+            .convert_bytes_with_stats(&bytes, format, 0.0)
+            .unwrap();
+        let score = stats.fidelity.expect("scored");
+        assert!(score.luminance_correlation > 0.5, "{:?}", score);
+    }
+
+    let dir = std::env::temp_dir();
+    let input = dir.join(format!("ruscii-gen-{}-fidelity-in.png", std::process::id()));
+    let output = dir.join(format!(
+        "ruscii-gen-{}-fidelity-out.png",
+        std::process::id()
+    ));
+    img.save(&input).unwrap();
+    let stats = scoring
+        .convert_img_with_stats(input.to_str().unwrap(), output.to_str().unwrap(), 0.0)
+        .unwrap();
+    fs::remove_file(&input).unwrap();
+    fs::remove_file(&output).unwrap();
+    assert!(stats.fidelity.is_some());
+}
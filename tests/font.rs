@@ -0,0 +1,81 @@
+/*
+* Fonts are loaded before any image work and only once
+*/
+mod common;
+
+use ascii_gen::ascii::config::ConverterConfig;
+use ascii_gen::ascii::converter::Converter;
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::output::OutputFormat;
+use std::io::Cursor;
+
+fn bogus_font_converter() -> Converter {
+    ConverterConfig {
+        font_path: "/nonexistent/ruscii-gen/font.ttf".to_string(),
+        ..common::test_config()
+    }
+    .build()
+    .unwrap()
+}
+
+fn input_png() -> Vec<u8> {
+    let mut png = Cursor::new(vec![]);
+    common::circle(common::FONT_SIZE * 8, common::FONT_SIZE * 5)
+        .write_to(&mut png, image::ImageFormat::Png)
+        .unwrap();
+    png.into_inner()
+}
+
+fn is_missing_font<T>(result: Result<T, ConvertError>) -> bool {
+    matches!(result, Err(ConvertError::MissingFont(path)) if path == "/nonexistent/ruscii-gen/font.ttf")
+}
+
+#[test]
+fn missing_font_fails_before_reading_the_input() {
+    // The input does not exist either, so any image work would fail with another error
+    let converter = bogus_font_converter();
+    let out = std::env::temp_dir().join(format!("ruscii-gen-{}-font.png", std::process::id()));
+    assert!(is_missing_font(converter.convert_img(
+        "/nonexistent/ruscii-gen/input.png",
+        out.to_str().unwrap(),
+        0.0
+    )));
+    assert!(is_missing_font(
+        converter.convert_to_sixel("/nonexistent/ruscii-gen/input.png", 0.0)
+    ));
+    assert!(is_missing_font(converter.convert_bytes(
+        b"not an image",
+        OutputFormat::Png,
+        0.0
+    )));
+    assert!(!out.exists());
+}
+
+#[test]
+fn text_outputs_do_not_need_the_font() {
+    let converter = bogus_font_converter();
+    assert!(converter
+        .convert_bytes(&input_png(), OutputFormat::Txt, 0.0)
+        .is_ok());
+    assert!(converter
+        .convert_bytes(&input_png(), OutputFormat::Ansi, 0.0)
+        .is_ok());
+}
+
+#[test]
+fn font_is_loaded_once() {
+    let converter = common::test_converter();
+    let first: *const _ = converter.load_font().unwrap();
+    let second: *const _ = converter.load_font().unwrap();
+    assert_eq!(first, second);
+    assert!(converter
+        .convert_bytes(&input_png(), OutputFormat::Png, 0.0)
+        .is_ok());
+}
+
+#[test]
+fn try_default_loads_the_default_font() {
+    // Tests run from the package root, which ships font.ttf
+    let converter = Converter::try_default().unwrap();
+    assert!(converter.load_font().is_ok());
+}
@@ -0,0 +1,72 @@
+/*
+* Inputs in formats the decoder cannot read fail with the detected format and a hint
+*/
+mod common;
+
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::output::OutputFormat;
+use std::fs;
+
+// Start of an AVIF file: the ftyp box with the avif brand
+const AVIF: &[u8] = b"\0\0\0 ftypavif\0\0\0\0avifmif1miafMA1B\0\0\0\0";
+const HEIC: &[u8] = b"\0\0\0\x18ftypheic\0\0\0\0mif1heic\0\0\0\0";
+const SVG: &[u8] = b"<?xml version=\"1.0\"?>\n<svg xmlns=\"http://www.w3.org/2000/svg\"/>";
+
+fn unsupported(bytes: &[u8]) -> (String, String) {
+    match common::test_converter().convert_bytes(bytes, OutputFormat::Txt, 0.0) {
+        Err(ConvertError::UnsupportedFormat { detected, hint }) => (detected, hint),
+        other => panic!("expected UnsupportedFormat, got {:?}", other.map(|_| ())),
+    }
+}
+
+#[cfg(not(feature = "avif"))]
+#[test]
+fn avif_without_the_feature_names_it() {
+    let (detected, hint) = unsupported(AVIF);
+    assert_eq!(detected, "AVIF");
+    assert!(hint.contains("enable the `avif` feature"), "{}", hint);
+}
+
+#[test]
+fn heic_and_svg_are_detected() {
+    assert_eq!(unsupported(HEIC).0, "HEIC");
+    let (detected, hint) = unsupported(SVG);
+    assert_eq!(detected, "SVG");
+    assert!(hint.contains("rasterize"), "{}", hint);
+}
+
+#[test]
+fn unknown_bytes_list_the_readable_formats() {
+    let (detected, hint) = unsupported(b"definitely not an image");
+    assert_eq!(detected, "unknown");
+    assert!(hint.contains("png") && hint.contains("jpg"), "{}", hint);
+}
+
+#[test]
+fn files_report_the_same_error() {
+    let dir = std::env::temp_dir().join(format!("ruscii_formats_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let input = dir.join("photo.heic");
+    fs::write(&input, HEIC).unwrap();
+    let out = dir.join("out.png");
+
+    let err = common::test_converter()
+        .convert_img(input.to_str().unwrap(), out.to_str().unwrap(), 0.0)
+        .unwrap_err();
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(
+        err.to_string().starts_with("Unsupported image format HEIC"),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn corrupt_images_stay_image_errors() {
+    let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+    png.extend_from_slice(&[0; 16]);
+    let err = common::test_converter()
+        .convert_bytes(&png, OutputFormat::Txt, 0.0)
+        .unwrap_err();
+    assert!(matches!(err, ConvertError::ImageError), "{:?}", err);
+}
@@ -0,0 +1,105 @@
+/*
+* Frame sequences converted with shared buffers match one-shot conversions
+*/
+mod common;
+
+use ascii_gen::ascii::config::{BackgroundConfig, ConverterConfig, DetailModeConfig};
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::ascii::frames::{FrameConverter, FrameSizeChange};
+use image::DynamicImage;
+
+const FS: u32 = common::FONT_SIZE;
+
+fn frames(w: u32, h: u32) -> Vec<DynamicImage> {
+    vec![
+        common::circle(w, h),
+        common::noise(w, h, 3),
+        common::diagonal_lines(w, h),
+        common::gradient(w, h),
+    ]
+}
+
+fn check_sequence(config: ConverterConfig) {
+    let (w, h) = (FS * 12 + 3, FS * 9 + 5);
+    let one_shot = config.build().unwrap();
+    let mut frame_converter = FrameConverter::new(config.build().unwrap(), w, h, 0.2).unwrap();
+    for frame in frames(w, h) {
+        let expected = one_shot.convert_image(&frame, 0.2).unwrap();
+        assert_eq!(*frame_converter.convert_frame(&frame).unwrap(), expected);
+    }
+}
+
+#[test]
+fn frames_match_convert_image() {
+    check_sequence(common::test_config());
+    check_sequence(ConverterConfig {
+        use_image_color: false,
+        adaptive_glyph_contrast: true,
+        background: BackgroundConfig::VerticalGradient {
+            top: [200, 0, 0],
+            bottom: [0, 0, 200],
+        },
+        ..common::test_config()
+    });
+}
+
+#[test]
+fn image_dependent_settings_fall_back_to_convert_image() {
+    check_sequence(ConverterConfig {
+        background: BackgroundConfig::BlurredImage {
+            sigma: 2.0,
+            darken: 0.5,
+        },
+        ..common::test_config()
+    });
+    check_sequence(ConverterConfig {
+        detail_mode: DetailModeConfig::TwoScale {
+            fine_factor: 2,
+            variance_threshold: 0.01,
+        },
+        ..common::test_config()
+    });
+}
+
+#[test]
+fn size_changes_are_rejected_by_default() {
+    let mut frame_converter =
+        FrameConverter::new(common::test_converter(), FS * 8, FS * 6, 0.2).unwrap();
+    frame_converter
+        .convert_frame(&common::circle(FS * 8, FS * 6))
+        .unwrap();
+    assert!(matches!(
+        frame_converter.convert_frame(&common::circle(FS * 10, FS * 6)),
+        Err(ConvertError::FrameSizeMismatch {
+            expected: (64, 48),
+            found: (80, 48)
+        })
+    ));
+}
+
+#[test]
+fn size_changes_reallocate_when_asked_to() {
+    let converter = common::test_converter();
+    let mut frame_converter = FrameConverter::new(common::test_converter(), FS * 8, FS * 6, 0.2)
+        .unwrap()
+        .with_size_change(FrameSizeChange::Reallocate);
+    for (w, h) in [(FS * 8, FS * 6), (FS * 10 + 1, FS * 4), (FS * 8, FS * 6)] {
+        let frame = common::noise(w, h, 5);
+        assert_eq!(
+            *frame_converter.convert_frame(&frame).unwrap(),
+            converter.convert_image(&frame, 0.2).unwrap()
+        );
+        assert_eq!(
+            frame_converter.geometry(),
+            converter.output_geometry(w, h).unwrap()
+        );
+    }
+}
+
+#[test]
+fn too_small_frames_fail_on_construction() {
+    assert!(matches!(
+        FrameConverter::new(common::test_converter(), FS - 1, FS, 0.2),
+        Err(ConvertError::ImageTooSmall { .. })
+    ));
+}
@@ -0,0 +1,110 @@
+/*
+* Glyphs of proportional fonts wider than a cell are squeezed into it, or refused
+*/
+mod common;
+
+use ascii_gen::ascii::config::{ConverterConfig, ResizeFilterConfig};
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::ascii::font_loader::{FontLoader, GlyphFit};
+use image::{DynamicImage, GrayImage, Luma};
+
+const CELL: u32 = common::FONT_SIZE;
+
+fn wide_config() -> ConverterConfig {
+    ConverterConfig {
+        font_path: common::proportional_font_path(),
+        tile_chars: " W".to_string(),
+        draw_edges: false,
+        // Every cell takes the luminance under it without blending in its neighbors
+        resize_filter: ResizeFilterConfig::Nearest,
+        use_image_color: false,
+        color: [255, 255, 255],
+        bg_color: [0, 0, 0],
+        ..common::test_config()
+    }
+}
+
+fn alternating_cells(cols: u32) -> DynamicImage {
+    // Dark and bright cells in turn, starting dark
+    DynamicImage::ImageLuma8(GrayImage::from_fn(cols * CELL, CELL, |x, _| {
+        Luma([if (x / CELL) % 2 == 1 { 255 } else { 0 }])
+    }))
+}
+
+#[test]
+fn wide_glyph_is_measured_in_cell_widths() {
+    let font = FontLoader::load_font(&common::proportional_font_path()).unwrap();
+    let wide = GlyphFit::measure(&font, 'W');
+    assert!((wide.left + 0.1).abs() < 1e-4, "{:?}", wide);
+    assert!((wide.right - 1.5).abs() < 1e-4, "{:?}", wide);
+    assert!(wide.overflows());
+    assert_eq!(wide.width_px(CELL), 13);
+
+    // Glyphs reaching exactly to the cell edges fit, and a space covers nothing
+    for ch in ['@', '_', '/'] {
+        assert!(!GlyphFit::measure(&font, ch).overflows(), "{:?}", ch);
+    }
+    assert_eq!(GlyphFit::measure(&font, ' ').width_px(CELL), 0);
+}
+
+#[test]
+fn wide_glyphs_are_squeezed_into_their_cell() {
+    let converter = wide_config().build().unwrap();
+    let cols = 4;
+    let grid = converter
+        .convert_to_chars(&alternating_cells(cols), 0.0)
+        .unwrap();
+    assert_eq!(grid.iter().collect::<String>(), " W W");
+
+    let render = converter
+        .convert_image(&alternating_cells(cols), 0.0)
+        .unwrap();
+    assert_eq!(render.dimensions(), (cols * CELL, CELL));
+    let column_ink = |x: u32| (0..CELL).any(|y| render.get_pixel(x, y)[0] > 0);
+    for col in 0..cols {
+        let (first, last) = (col * CELL, (col + 1) * CELL - 1);
+        if col % 2 == 0 {
+            // Neither neighbor spills into the blank cells
+            assert!((first..=last).all(|x| !column_ink(x)), "cell {}", col);
+        } else {
+            // The glyph spans its cell from edge to edge
+            assert!(column_ink(first) && column_ink(last), "cell {}", col);
+        }
+    }
+}
+
+#[test]
+fn wide_glyphs_are_an_error_without_fitting() {
+    let converter = ConverterConfig {
+        fit_glyphs: false,
+        ..wide_config()
+    }
+    .build()
+    .unwrap();
+    let too_wide = |result: Result<_, ConvertError>| {
+        matches!(
+            result,
+            Err(ConvertError::GlyphTooWide {
+                ch: 'W',
+                width: 13,
+                cell: CELL,
+            })
+        )
+    };
+    assert!(too_wide(converter.load_font().map(|_| ())));
+    assert!(too_wide(
+        converter
+            .convert_image(&alternating_cells(2), 0.0)
+            .map(|_| ())
+    ));
+
+    // Charsets whose glyphs all fit draw as usual
+    let fitting = ConverterConfig {
+        fit_glyphs: false,
+        tile_chars: " .:o@".to_string(),
+        ..wide_config()
+    }
+    .build()
+    .unwrap();
+    assert!(fitting.convert_image(&alternating_cells(2), 0.0).is_ok());
+}
@@ -0,0 +1,103 @@
+/*
+* Golden file tests of the full pipeline on small generated inputs. Character grids are compared
+* exactly, rendered images through a perceptual hash to tolerate small rasterization differences.
+* Run with UPDATE_GOLDENS=1 to rewrite the golden files
+*/
+mod common;
+
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageBuffer, Rgb};
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+// Largest number of differing perceptual hash bits still considered a match
+const MAX_HASH_DISTANCE: u32 = 6;
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("goldens")
+        .join(name)
+}
+
+fn update_goldens() -> bool {
+    env::var("UPDATE_GOLDENS").is_ok_and(|v| v == "1")
+}
+
+fn difference_hash(img: &ImageBuffer<Rgb<u8>, Vec<u8>>) -> u64 {
+    // dHash: compare horizontally adjacent pixels of a 9x8 grayscale thumbnail
+    let small = DynamicImage::ImageRgb8(img.clone())
+        .resize_exact(9, 8, FilterType::Triangle)
+        .to_luma8();
+    let mut hash = 0u64;
+    for y in 0..8 {
+        for x in 0..8 {
+            hash <<= 1;
+            if small.get_pixel(x, y)[0] > small.get_pixel(x + 1, y)[0] {
+                hash |= 1;
+            }
+        }
+    }
+    hash
+}
+
+fn check_golden(name: &str, img: &DynamicImage) {
+    let converter = common::test_converter();
+    let text = converter
+        .convert_to_text(img, 0.0)
+        .expect("Text conversion failed");
+    let hash = difference_hash(&converter.convert_image(img, 0.0).expect("Render failed"));
+
+    let text_path = golden_path(&format!("{}.txt", name));
+    let hash_path = golden_path(&format!("{}.dhash", name));
+    if update_goldens() {
+        fs::write(&text_path, &text).unwrap();
+        fs::write(&hash_path, format!("{:016x}\n", hash)).unwrap();
+        return;
+    }
+
+    let expected_text = fs::read_to_string(&text_path)
+        .unwrap_or_else(|_| panic!("Missing {:?}, run with UPDATE_GOLDENS=1", text_path));
+    assert_eq!(text, expected_text, "Character grid of {} changed", name);
+
+    let expected_hash = u64::from_str_radix(fs::read_to_string(&hash_path).unwrap().trim(), 16)
+        .expect("Malformed hash golden");
+    let distance = (hash ^ expected_hash).count_ones();
+    assert!(
+        distance <= MAX_HASH_DISTANCE,
+        "Rendered image of {} differs from golden by {} hash bits",
+        name,
+        distance
+    );
+}
+
+#[test]
+fn golden_gradient() {
+    check_golden("gradient", &common::gradient(160, 96));
+}
+
+#[test]
+fn golden_circle() {
+    check_golden("circle", &common::circle(160, 96));
+}
+
+#[test]
+fn golden_diagonal_lines() {
+    check_golden("diagonal_lines", &common::diagonal_lines(160, 96));
+}
+
+#[test]
+fn golden_noise() {
+    check_golden("noise", &common::noise(160, 96, 7));
+}
+
+#[test]
+fn text_output_is_deterministic() {
+    let converter = common::test_converter();
+    let img = common::circle(160, 96);
+    let first = converter.convert_to_text(&img, 0.0).unwrap();
+    for _ in 0..3 {
+        assert_eq!(converter.convert_to_text(&img, 0.0).unwrap(), first);
+    }
+}
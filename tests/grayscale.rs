@@ -0,0 +1,58 @@
+/*
+* Single channel inputs take a faster color path with the same output as their RGB copies
+*/
+mod common;
+
+use ascii_gen::ascii::config::ConverterConfig;
+use image::{DynamicImage, GrayAlphaImage, LumaA};
+
+const FS: u32 = common::FONT_SIZE;
+
+fn gray_photo() -> DynamicImage {
+    let noise = common::noise(FS * 30, FS * 20, 11).to_luma8();
+    let mut img = common::circle(FS * 30, FS * 20).to_luma8();
+    for (px, n) in img.pixels_mut().zip(noise.pixels()) {
+        px[0] = px[0] / 2 + n[0] / 3;
+    }
+    DynamicImage::ImageLuma8(img)
+}
+
+#[test]
+fn grayscale_renders_like_its_rgb_copy() {
+    let converter = common::test_converter();
+    let gray = gray_photo();
+    let rgb = DynamicImage::ImageRgb8(gray.to_rgb8());
+    assert_eq!(
+        converter.convert_image(&gray, 0.0).unwrap(),
+        converter.convert_image(&rgb, 0.0).unwrap()
+    );
+
+    let resized = gray.resize_exact(30, 20, image::imageops::FilterType::Triangle);
+    let colors = converter.cell_colors(&resized);
+    assert_eq!(
+        colors,
+        converter.cell_colors(&DynamicImage::ImageRgb8(resized.to_rgb8()))
+    );
+    assert!(colors.iter().any(|c| c.0 != colors[(0, 0)].0));
+}
+
+#[test]
+fn gray_alpha_renders_like_its_rgba_copy() {
+    let gray = gray_photo().to_luma8();
+    let gray_alpha = DynamicImage::ImageLumaA8(GrayAlphaImage::from_fn(
+        gray.width(),
+        gray.height(),
+        |x, y| LumaA([gray.get_pixel(x, y)[0], 255]),
+    ));
+    let rgba = DynamicImage::ImageRgba8(gray_alpha.to_rgba8());
+    let converter = ConverterConfig {
+        linear_resize: true,
+        ..common::test_config()
+    }
+    .build()
+    .unwrap();
+    assert_eq!(
+        converter.convert_image(&gray_alpha, 0.0).unwrap(),
+        converter.convert_image(&rgba, 0.0).unwrap()
+    );
+}
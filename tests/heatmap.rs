@@ -0,0 +1,118 @@
+/*
+* Heatmaps of the tile bucket every cell used
+*/
+mod common;
+
+use ascii_gen::ascii::cell::CellValue;
+use ascii_gen::ascii::config::ConverterConfig;
+use ascii_gen::output::heatmap::{render_bucket_heatmap, viridis, HEATMAP_EDGE_COLOR};
+use image::{Pixel, Rgb};
+use ndarray::array;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+fn scratch(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "ruscii-gen-{}-heatmap-{}",
+        std::process::id(),
+        name
+    ))
+}
+
+fn luma(color: Rgb<u8>) -> u8 {
+    color.to_luma()[0]
+}
+
+#[test]
+fn colormap_runs_from_purple_to_yellow_getting_brighter() {
+    assert_eq!(viridis(0.0), Rgb([68, 1, 84]));
+    assert_eq!(viridis(1.0), Rgb([253, 231, 37]));
+    assert_eq!(viridis(-1.0), viridis(0.0));
+    let lumas: Vec<u8> = (0..=100).map(|i| luma(viridis(i as f32 / 100.0))).collect();
+    assert!(lumas.windows(2).all(|w| w[0] <= w[1]), "{:?}", lumas);
+}
+
+#[test]
+fn cells_are_squares_of_their_bucket_color() {
+    let cells = array![
+        [CellValue::Tile(0), CellValue::Tile(4)],
+        [CellValue::Edge(2), CellValue::Tile(2)]
+    ];
+    let heatmap = render_bucket_heatmap(&cells.view(), 5, 3);
+    assert_eq!(heatmap.dimensions(), (6, 6));
+    for (x, y, expected) in [
+        (0, 0, viridis(0.0)),
+        (5, 2, viridis(1.0)),
+        (2, 3, HEATMAP_EDGE_COLOR),
+        (3, 5, viridis(0.5)),
+    ] {
+        assert_eq!(*heatmap.get_pixel(x, y), expected, "({}, {})", x, y);
+    }
+}
+
+#[test]
+fn gradient_heatmap_progresses_across_columns() {
+    let cols = 16;
+    let converter = ConverterConfig {
+        draw_edges: false,
+        ..common::test_config()
+    }
+    .build()
+    .unwrap();
+    let img = common::gradient(common::FONT_SIZE * cols, common::FONT_SIZE * 2);
+    let heatmap = converter.convert_to_heatmap(&img, 0.0, 4).unwrap();
+    assert_eq!(heatmap.dimensions(), (cols * 4, 8));
+
+    let column_lumas: Vec<u8> = (0..cols)
+        .map(|col| luma(*heatmap.get_pixel(col * 4 + 1, 1)))
+        .collect();
+    assert!(
+        column_lumas.windows(2).all(|w| w[0] <= w[1]),
+        "{:?}",
+        column_lumas
+    );
+    // From the darkest buckets to the brightest ones
+    assert_eq!(*heatmap.get_pixel(0, 0), viridis(0.0));
+    assert!(column_lumas[cols as usize - 1] - column_lumas[0] > 150);
+}
+
+#[test]
+fn cli_writes_the_heatmap_by_the_output_options() {
+    let dir = scratch("cli");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let input = dir.join("circle.png");
+    common::circle(64, 64).save(&input).unwrap();
+    let config = dir.join("config.toml");
+    let toml = format!(
+        "font_size = {}\nfont_path = {:?}\n",
+        common::FONT_SIZE,
+        common::test_font_path()
+    );
+    fs::write(&config, toml).unwrap();
+    let heatmap = dir.join("debug").join("heatmap.png");
+    let convert = |on_existing: &str| {
+        Command::new(env!("CARGO_BIN_EXE_ruscii-gen"))
+            .arg(&input)
+            .arg("--config")
+            .arg(&config)
+            .arg("--output")
+            .arg(dir.join("out.txt"))
+            .args(["--format", "txt", "--overwrite", on_existing])
+            .arg("--debug-heatmap")
+            .arg(&heatmap)
+            .output()
+            .expect("Failed running ruscii-gen")
+    };
+
+    // The missing parent directory is created like the output's
+    let out = convert("overwrite");
+    assert!(out.status.success(), "{:?}", out);
+    assert!(image::open(&heatmap).is_ok());
+    let out = convert("rename");
+    assert!(out.status.success(), "{:?}", out);
+    assert!(dir.join("debug").join("heatmap-1.png").exists());
+    let out = convert("error");
+    assert!(!out.status.success());
+}
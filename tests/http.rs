@@ -0,0 +1,124 @@
+#![cfg(feature = "http")]
+/*
+* Downloads of http inputs against a local server: the size limit, the redirect cap and failures
+* surfacing as network errors
+*/
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::input::http::{fetch, HttpOptions};
+use image::{DynamicImage, GrayImage, ImageFormat, Luma};
+use std::io::{BufRead, BufReader, Cursor, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process::Command;
+use std::thread;
+use std::time::Duration;
+
+fn options(max_bytes: u64, max_redirects: u32) -> HttpOptions {
+    HttpOptions::new(Duration::from_secs(5), max_bytes, max_redirects)
+}
+
+fn serve(respond: impl Fn(&str) -> Vec<u8> + Send + 'static) -> String {
+    /*
+     * Base url of a server on a free local port answering every request with respond(url), the
+     * url path of the request. The server lives until the test process ends
+     */
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base = format!("http://{}", listener.local_addr().unwrap());
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else { continue };
+            let path = read_request(&stream);
+            // The client may hang up early, as on a body over the limit
+            let _ = stream.write_all(&respond(&path));
+        }
+    });
+    base
+}
+
+fn read_request(stream: &TcpStream) -> String {
+    // Path of the request line, reading the headers up to the blank line ending them
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).unwrap();
+    let path = line.split(' ').nth(1).unwrap_or("/").to_string();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+            return path;
+        }
+    }
+}
+
+fn body_response(body: &[u8], announce_length: bool) -> Vec<u8> {
+    let mut response = b"HTTP/1.1 200 OK\r\nConnection: close\r\n".to_vec();
+    if announce_length {
+        response.extend_from_slice(format!("Content-Length: {}\r\n", body.len()).as_bytes());
+    }
+    response.extend_from_slice(b"\r\n");
+    response.extend_from_slice(body);
+    response
+}
+
+#[test]
+fn bodies_over_the_limit_are_rejected() {
+    let base = serve(|path| body_response(&[7; 200], path == "/announced"));
+    for path in ["/announced", "/unannounced"] {
+        let url = format!("{}{}", base, path);
+        match fetch(&url, &options(100, 5)) {
+            Err(ConvertError::Network(reason)) => {
+                assert!(reason.contains("larger than 100 bytes"), "{}", reason)
+            }
+            other => panic!("{} gave {:?}", path, other.map(|bytes| bytes.len())),
+        }
+        assert_eq!(fetch(&url, &options(200, 5)).unwrap(), vec![7; 200]);
+    }
+}
+
+#[test]
+fn redirect_loops_stop_at_the_cap() {
+    let base = serve(|path| {
+        format!(
+            "HTTP/1.1 302 Found\r\nLocation: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+            path
+        )
+        .into_bytes()
+    });
+    let result = fetch(&format!("{}/loop", base), &options(100, 3));
+    assert!(
+        matches!(result, Err(ConvertError::Network(_))),
+        "{:?}",
+        result.map(|bytes| bytes.len())
+    );
+}
+
+#[test]
+fn refused_connections_are_network_errors() {
+    // A port that was free a moment ago, with nothing listening on it any more
+    let port = TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let result = fetch(&format!("http://127.0.0.1:{}/", port), &options(100, 3));
+    assert!(
+        matches!(result, Err(ConvertError::Network(_))),
+        "{:?}",
+        result.map(|bytes| bytes.len())
+    );
+}
+
+#[test]
+fn huge_download_limits_do_not_overflow() {
+    let mut png = vec![];
+    DynamicImage::ImageLuma8(GrayImage::from_fn(32, 32, |x, _| Luma([(x * 8) as u8])))
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .unwrap();
+    let base = serve(move |_| body_response(&png, true));
+    let out = Command::new(env!("CARGO_BIN_EXE_ruscii-gen"))
+        .arg(format!("{}/image.png", base))
+        .args(["--output", "-", "--format", "txt"])
+        .args(["--max-download-mb", &u64::MAX.to_string()])
+        .output()
+        .expect("Failed running ruscii-gen");
+    assert!(out.status.success(), "{:?}", out);
+    assert!(!out.stdout.is_empty());
+}
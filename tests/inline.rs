@@ -0,0 +1,105 @@
+/*
+* Inline image escape sequences carry the rendered image as chunked base64 PNG
+*/
+mod common;
+
+use ascii_gen::output::inline::{encode_inline, InlineImageProtocol, KITTY_CHUNK_SIZE};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use image::RgbImage;
+
+fn decode_png(payload: &str) -> RgbImage {
+    let png = STANDARD.decode(payload).unwrap();
+    image::load_from_memory(&png).unwrap().to_rgb8()
+}
+
+#[test]
+fn kitty_chunks_round_trip() {
+    // Noise barely compresses, so the payload spans many chunks
+    let img = common::noise(96, 96, 7).to_rgb8();
+    let out = encode_inline(&img, InlineImageProtocol::Kitty).unwrap();
+
+    let chunks: Vec<&str> = out
+        .strip_suffix("\x1b\\")
+        .unwrap()
+        .split("\x1b\\")
+        .map(|c| c.strip_prefix("\x1b_G").unwrap())
+        .collect();
+    assert!(chunks.len() > 2);
+
+    let mut payload = String::new();
+    for (i, chunk) in chunks.iter().enumerate() {
+        let (control, data) = chunk.split_once(';').unwrap();
+        let last = i + 1 == chunks.len();
+        if i == 0 {
+            assert_eq!(control, "a=T,f=100,m=1");
+        } else {
+            assert_eq!(control, if last { "m=0" } else { "m=1" });
+        }
+        if last {
+            assert!(data.len() <= KITTY_CHUNK_SIZE);
+        } else {
+            assert_eq!(data.len(), KITTY_CHUNK_SIZE);
+        }
+        payload.push_str(data);
+    }
+    assert_eq!(decode_png(&payload), img);
+}
+
+#[test]
+fn small_kitty_image_is_a_single_chunk() {
+    let img = common::gradient(8, 8).to_rgb8();
+    let out = encode_inline(&img, InlineImageProtocol::Kitty).unwrap();
+    let data = out
+        .strip_prefix("\x1b_Ga=T,f=100,m=0;")
+        .unwrap()
+        .strip_suffix("\x1b\\")
+        .unwrap();
+    assert_eq!(decode_png(data), img);
+}
+
+#[test]
+fn iterm2_round_trips() {
+    let img = common::test_converter()
+        .convert_image(
+            &common::circle(common::FONT_SIZE * 6, common::FONT_SIZE * 4),
+            0.0,
+        )
+        .unwrap();
+    let out = encode_inline(&img, InlineImageProtocol::Iterm2).unwrap();
+
+    let body = out
+        .strip_prefix("\x1b]1337;File=")
+        .unwrap()
+        .strip_suffix('\x07')
+        .unwrap();
+    let (args, payload) = body.split_once(':').unwrap();
+    let size: usize = args
+        .split(';')
+        .find_map(|arg| arg.strip_prefix("size="))
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert!(args.split(';').any(|arg| arg == "inline=1"));
+    assert_eq!(STANDARD.decode(payload).unwrap().len(), size);
+    assert_eq!(decode_png(payload), img);
+}
+
+#[test]
+fn protocol_is_detected_from_the_environment() {
+    let detect = InlineImageProtocol::detect_from;
+    assert_eq!(
+        detect(Some("xterm-kitty"), None),
+        Some(InlineImageProtocol::Kitty)
+    );
+    assert_eq!(
+        detect(Some("xterm-256color"), Some("iTerm.app")),
+        Some(InlineImageProtocol::Iterm2)
+    );
+    assert_eq!(
+        detect(Some("xterm-256color"), Some("WezTerm")),
+        Some(InlineImageProtocol::Iterm2)
+    );
+    assert_eq!(detect(Some("xterm-256color"), Some("Apple_Terminal")), None);
+    assert_eq!(detect(None, None), None);
+}
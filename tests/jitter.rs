@@ -0,0 +1,150 @@
+/*
+* Seeded tile jitter breaking up flat areas
+*/
+mod common;
+
+use ascii_gen::ascii::config::ConverterConfig;
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::output::OutputFormat;
+use image::{DynamicImage, GrayImage, Luma};
+use std::io::Cursor;
+
+fn flat() -> DynamicImage {
+    DynamicImage::ImageLuma8(GrayImage::from_pixel(
+        common::FONT_SIZE * 20,
+        common::FONT_SIZE * 10,
+        Luma([128]),
+    ))
+}
+
+fn text(tile_jitter: f32, seed: u64) -> String {
+    ConverterConfig {
+        tile_jitter,
+        seed,
+        draw_edges: false,
+        ..common::test_config()
+    }
+    .build()
+    .unwrap()
+    .convert_to_text(&flat(), 0.0)
+    .unwrap()
+}
+
+#[test]
+fn same_seed_gives_same_grid() {
+    assert_eq!(text(0.3, 7), text(0.3, 7));
+}
+
+#[test]
+fn different_seeds_give_different_grids() {
+    assert_ne!(text(0.3, 7), text(0.3, 8));
+}
+
+#[test]
+fn jitter_breaks_up_flat_areas_by_one_level() {
+    let plain = text(0.0, 0);
+    let jittered = text(0.5, 1);
+    let flat_char = plain.chars().next().unwrap();
+    assert!(plain
+        .lines()
+        .all(|line| line.chars().all(|ch| ch == flat_char)));
+
+    // Only the neighbours of the flat character on the ramp show up
+    let ramp: Vec<char> = common::test_config().tile_chars.chars().collect();
+    let at = ramp.iter().position(|&ch| ch == flat_char).unwrap();
+    let allowed = &ramp[at - 1..=at + 1];
+    assert!(jittered
+        .chars()
+        .all(|ch| ch == '\n' || allowed.contains(&ch)));
+    assert!(jittered.chars().any(|ch| ch != '\n' && ch != flat_char));
+}
+
+#[test]
+fn zero_jitter_matches_the_unjittered_output() {
+    let img = common::noise(common::FONT_SIZE * 12, common::FONT_SIZE * 8, 4);
+    let mut png = Cursor::new(vec![]);
+    img.write_to(&mut png, image::ImageFormat::Png).unwrap();
+    let bytes = png.into_inner();
+
+    let config = ConverterConfig {
+        embed_metadata: false,
+        ..common::test_config()
+    };
+    let (expected, _) = config
+        .build()
+        .unwrap()
+        .convert_bytes_with_stats(&bytes, OutputFormat::Png, 0.0)
+        .unwrap();
+    for seed in [0, 1, u64::MAX] {
+        let seeded = ConverterConfig {
+            tile_jitter: 0.0,
+            seed,
+            ..config.clone()
+        };
+        let (out, stats) = seeded
+            .build()
+            .unwrap()
+            .convert_bytes_with_stats(&bytes, OutputFormat::Png, 0.0)
+            .unwrap();
+        assert_eq!(out, expected);
+        assert_eq!(stats.seed, None);
+    }
+}
+
+#[test]
+fn seed_is_recorded_in_the_stats() {
+    let mut png = Cursor::new(vec![]);
+    flat().write_to(&mut png, image::ImageFormat::Png).unwrap();
+    let (_, stats) = ConverterConfig {
+        tile_jitter: 0.2,
+        seed: 42,
+        ..common::test_config()
+    }
+    .build()
+    .unwrap()
+    .convert_bytes_with_stats(&png.into_inner(), OutputFormat::Txt, 0.0)
+    .unwrap();
+    assert_eq!(stats.seed, Some(42));
+    assert!(stats.to_string().ends_with("jitter seed: 42"));
+}
+
+#[test]
+fn jitter_out_of_range_is_rejected() {
+    for tile_jitter in [-0.1, 1.5, f32::NAN] {
+        let config = ConverterConfig {
+            tile_jitter,
+            ..common::test_config()
+        };
+        assert!(matches!(
+            config.build(),
+            Err(ConvertError::InvalidSetting {
+                field: "tile_jitter",
+                ..
+            })
+        ));
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn seed_is_recorded_in_the_png_metadata() {
+    use ascii_gen::output::metadata::read_png_metadata;
+
+    let mut png = Cursor::new(vec![]);
+    flat().write_to(&mut png, image::ImageFormat::Png).unwrap();
+    let out = ConverterConfig {
+        tile_jitter: 0.2,
+        seed: 42,
+        ..common::test_config()
+    }
+    .build()
+    .unwrap()
+    .convert_bytes(&png.into_inner(), OutputFormat::Png, 0.0)
+    .unwrap();
+    let config = read_png_metadata(Cursor::new(&out))
+        .unwrap()
+        .config
+        .unwrap();
+    let config = ConverterConfig::from_toml(&config).unwrap();
+    assert_eq!((config.seed, config.tile_jitter), (42, 0.2));
+}
@@ -0,0 +1,67 @@
+#![cfg(feature = "serde")]
+/*
+* JSON export parses back into the grid it was made from
+*/
+mod common;
+
+use ascii_gen::output::json::{CompactJsonGrid, JsonGrid, JsonLayout};
+use std::path::PathBuf;
+
+const FS: u32 = common::FONT_SIZE;
+
+fn circle_path() -> String {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("ruscii-json-circle.png");
+    common::circle(FS * 12, FS * 8).save(&path).unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+fn text_rows() -> Vec<Vec<char>> {
+    let img = common::circle(FS * 12, FS * 8);
+    let text = common::test_converter().convert_to_text(&img, 0.0).unwrap();
+    text.lines().map(|l| l.chars().collect()).collect()
+}
+
+#[test]
+fn verbose_round_trip() {
+    let json = common::test_converter()
+        .convert_to_json(circle_path(), 0.0, JsonLayout::Verbose)
+        .unwrap();
+    let grid: JsonGrid = serde_json::from_str(&json).unwrap();
+    assert_eq!((grid.cols, grid.rows, grid.font_size), (12, 8, FS));
+    assert_eq!(grid.cells.len(), 8);
+    assert!(grid.cells.iter().all(|row| row.len() == 12));
+    assert_eq!(grid.bg, "#75218d");
+
+    let rows = text_rows();
+    for (y, x) in [(0, 0), (4, 6), (7, 11), (2, 3)] {
+        assert_eq!(grid.cells[y][x].ch, rows[y][x]);
+    }
+    // Corner is the flat dark background of the circle, the center its flat light fill
+    assert_eq!(grid.cells[0][0].color, "#141414");
+    assert_eq!(grid.cells[4][6].color, "#e6e6e6");
+}
+
+#[test]
+fn compact_round_trip() {
+    let json = common::test_converter()
+        .convert_to_json(circle_path(), 0.0, JsonLayout::Compact)
+        .unwrap();
+    let grid: CompactJsonGrid = serde_json::from_str(&json).unwrap();
+    assert_eq!((grid.cols, grid.rows), (12, 8));
+
+    let rows = text_rows();
+    let chars: Vec<Vec<char>> = grid.chars.iter().map(|r| r.chars().collect()).collect();
+    assert_eq!(chars, rows);
+    assert!(grid
+        .colors
+        .iter()
+        .flatten()
+        .all(|&i| i < grid.palette.len()));
+    assert_eq!(grid.palette[grid.colors[0][0]], "#141414");
+    assert_eq!(grid.palette[grid.colors[4][6]], "#e6e6e6");
+    // Palette holds each color once
+    let mut palette = grid.palette.clone();
+    palette.sort();
+    palette.dedup();
+    assert_eq!(palette.len(), grid.palette.len());
+}
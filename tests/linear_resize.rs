@@ -0,0 +1,52 @@
+/*
+* Resizing in linear light keeps a fine checkerboard at its true brightness
+*/
+mod common;
+
+use ascii_gen::ascii::config::ConverterConfig;
+use ascii_gen::image_manip::util::resize_exact_linear;
+use image::imageops::FilterType;
+use image::{DynamicImage, GrayImage, Luma};
+
+fn checkerboard(size: u32) -> DynamicImage {
+    DynamicImage::ImageLuma8(GrayImage::from_fn(size, size, |x, y| {
+        Luma([if (x + y) % 2 == 0 { 0 } else { 255 }])
+    }))
+}
+
+#[test]
+fn checkerboard_halves_to_linear_gray() {
+    let img = checkerboard(16);
+    let linear = resize_exact_linear(&img, 8, 8, FilterType::Triangle).to_luma8();
+    let srgb = img.resize_exact(8, 8, FilterType::Triangle).to_luma8();
+    // sRGB encoding of 0.5 linear is 187.5, averaging the sRGB values gives 127.5
+    assert!(
+        linear.pixels().all(|p| p[0].abs_diff(188) <= 3),
+        "{:?}",
+        linear
+    );
+    assert!(srgb.pixels().all(|p| p[0].abs_diff(128) <= 3), "{:?}", srgb);
+}
+
+#[test]
+fn linear_resize_brightens_the_grid() {
+    let img = checkerboard(common::FONT_SIZE * 4);
+    let text = |linear_resize| {
+        ConverterConfig {
+            linear_resize,
+            draw_edges: false,
+            ..common::test_config()
+        }
+        .build()
+        .unwrap()
+        .convert_to_text(&img, 0.0)
+        .unwrap()
+    };
+    // 128 sits on the border of the 'c' and 'o' buckets of the default ramp, 188 is inside 'O'
+    assert!(
+        text(false).chars().all(|c| "co\n".contains(c)),
+        "{}",
+        text(false)
+    );
+    assert!(text(true).lines().all(|l| l == "OOOO"), "{}", text(true));
+}
@@ -0,0 +1,61 @@
+/*
+* Median blur along one or both axes
+*/
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::image_manip::processing::{BilateralFilter, MedianBlur, Processor};
+use image::{GrayImage, Luma};
+
+fn apply(blur: &MedianBlur, img: &GrayImage) -> GrayImage {
+    blur.apply(img).unwrap()
+}
+
+// Thin isolated lines one pixel wide, far enough apart that a radius 1 window only sees one
+fn lines(vertical: bool) -> GrayImage {
+    GrayImage::from_fn(12, 12, |x, y| {
+        let pos = if vertical { x } else { y };
+        Luma([if pos % 4 == 1 { 255 } else { 0 }])
+    })
+}
+
+#[test]
+fn horizontal_median_removes_vertical_lines_only() {
+    let horizontal = MedianBlur::asymmetric(1, 0);
+    assert!(apply(&horizontal, &lines(true)).iter().all(|&v| v == 0));
+    assert_eq!(apply(&horizontal, &lines(false)), lines(false));
+}
+
+#[test]
+fn vertical_median_removes_horizontal_lines_only() {
+    let vertical = MedianBlur::asymmetric(0, 1);
+    assert!(apply(&vertical, &lines(false)).iter().all(|&v| v == 0));
+    assert_eq!(apply(&vertical, &lines(true)), lines(true));
+}
+
+#[test]
+fn symmetric_median_removes_both() {
+    let blur = MedianBlur::new(1);
+    assert_eq!((blur.x_radius, blur.y_radius), (1, 1));
+    for vertical in [true, false] {
+        assert!(apply(&blur, &lines(vertical)).iter().all(|&v| v == 0));
+    }
+    assert_eq!(blur.border_radius(), 1);
+    assert_eq!(MedianBlur::asymmetric(4, 1).border_radius(), 4);
+}
+
+#[test]
+fn bad_windows_fail_instead_of_running() {
+    let img = lines(true);
+    for blur in [MedianBlur::new(0), MedianBlur::asymmetric(51, 1)] {
+        assert!(matches!(
+            blur.apply(&img),
+            Err(ConvertError::InvalidSetting { .. })
+        ));
+    }
+    assert!(matches!(
+        BilateralFilter::new(0, 2.0, 5.0).apply(&img),
+        Err(ConvertError::InvalidSetting {
+            field: "bilateral_filter.window_size",
+            ..
+        })
+    ));
+}
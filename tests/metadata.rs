@@ -0,0 +1,159 @@
+#![cfg(feature = "serde")]
+/*
+* PNG outputs carry the config they were converted with
+*/
+mod common;
+
+use ascii_gen::ascii::config::{BackgroundConfig, ConverterConfig};
+use ascii_gen::ascii::converter::Converter;
+use ascii_gen::output::metadata::read_png_metadata;
+use ascii_gen::output::OutputFormat;
+use std::fs;
+use std::io::Cursor;
+use std::path::PathBuf;
+
+fn out_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("ruscii-gen-{}-{}", std::process::id(), name))
+}
+
+fn input_png() -> Vec<u8> {
+    let mut png = Cursor::new(vec![]);
+    common::circle(common::FONT_SIZE * 8, common::FONT_SIZE * 5)
+        .write_to(&mut png, image::ImageFormat::Png)
+        .unwrap();
+    png.into_inner()
+}
+
+fn tweaked_config() -> ConverterConfig {
+    ConverterConfig {
+        tile_chars: " ·░▒▓█".to_string(),
+        background: BackgroundConfig::VerticalGradient {
+            top: [10, 20, 30],
+            bottom: [200, 100, 0],
+        },
+        draw_edges: false,
+        ..common::test_config()
+    }
+}
+
+#[test]
+fn config_round_trips_through_png_bytes() {
+    let config = tweaked_config();
+    let out = config
+        .build()
+        .unwrap()
+        .convert_bytes(&input_png(), OutputFormat::Png, 0.3)
+        .unwrap();
+
+    let path = out_path("bytes.png");
+    fs::write(&path, &out).unwrap();
+    let read = Converter::from_png_metadata(path.to_str().unwrap());
+    fs::remove_file(&path).unwrap();
+
+    // The threshold used for the conversion wins over the one in the config
+    let expected = ConverterConfig {
+        edge_threshold: 0.3,
+        ..config
+    };
+    assert_eq!(read.unwrap(), expected);
+
+    let metadata = read_png_metadata(Cursor::new(&out)).unwrap();
+    assert_eq!(metadata.version.as_deref(), Some(env!("CARGO_PKG_VERSION")));
+    // The embedded chunks leave the image itself readable
+    image::load_from_memory(&out).unwrap();
+}
+
+#[test]
+fn config_round_trips_through_a_saved_png() {
+    let config = tweaked_config();
+    let input = out_path("input.png");
+    let output = out_path("saved.png");
+    fs::write(&input, input_png()).unwrap();
+    config
+        .build()
+        .unwrap()
+        .convert_img(input.to_str().unwrap(), output.to_str().unwrap(), 0.0)
+        .unwrap();
+    let read = Converter::from_png_metadata(output.to_str().unwrap());
+    fs::remove_file(&input).unwrap();
+    fs::remove_file(&output).unwrap();
+
+    let read = read.unwrap();
+    assert_eq!(read, config);
+    // The read back config reproduces the same output
+    assert_eq!(
+        read.build()
+            .unwrap()
+            .convert_bytes(&input_png(), OutputFormat::Txt, 0.0)
+            .unwrap(),
+        config
+            .build()
+            .unwrap()
+            .convert_bytes(&input_png(), OutputFormat::Txt, 0.0)
+            .unwrap()
+    );
+}
+
+#[test]
+fn embedding_can_be_turned_off() {
+    let config = ConverterConfig {
+        embed_metadata: false,
+        ..common::test_config()
+    };
+    let out = config
+        .build()
+        .unwrap()
+        .convert_bytes(&input_png(), OutputFormat::Png, 0.0)
+        .unwrap();
+    let metadata = read_png_metadata(Cursor::new(&out)).unwrap();
+    assert_eq!(metadata.config, None);
+    assert_eq!(metadata.version, None);
+
+    let path = out_path("plain.png");
+    fs::write(&path, &out).unwrap();
+    let read = Converter::from_png_metadata(path.to_str().unwrap());
+    fs::remove_file(&path).unwrap();
+    assert!(read.is_err());
+}
+
+#[test]
+fn config_hash_follows_every_setting() {
+    let config = tweaked_config();
+    let hash = config.config_hash().unwrap();
+    assert_eq!(hash.len(), 8);
+    assert!(hash.chars().all(|ch| ch.is_ascii_hexdigit()));
+    assert_eq!(tweaked_config().config_hash().unwrap(), hash);
+    assert_eq!(config.build().unwrap().config_hash(), Some(hash.as_str()));
+
+    let changed = ConverterConfig {
+        seed: 1,
+        ..tweaked_config()
+    };
+    assert_ne!(changed.config_hash().unwrap(), hash);
+}
+
+#[test]
+fn config_hash_is_embedded_with_the_config() {
+    let config = tweaked_config();
+    let out = config
+        .build()
+        .unwrap()
+        .convert_bytes(&input_png(), OutputFormat::Png, 0.0)
+        .unwrap();
+    let metadata = read_png_metadata(Cursor::new(&out)).unwrap();
+    assert_eq!(metadata.config_hash, Some(config.config_hash().unwrap()));
+
+    let plain = ConverterConfig {
+        embed_metadata: false,
+        ..tweaked_config()
+    };
+    let out = plain
+        .build()
+        .unwrap()
+        .convert_bytes(&input_png(), OutputFormat::Png, 0.0)
+        .unwrap();
+    assert_eq!(
+        read_png_metadata(Cursor::new(&out)).unwrap().config_hash,
+        None
+    );
+}
@@ -0,0 +1,110 @@
+/*
+* The orientation is applied to the decoded image, before the grid is made
+*/
+mod common;
+
+use ascii_gen::ascii::config::{ConverterConfig, OrientationConfig, TileSamplingConfig};
+use image::{DynamicImage, GrayImage, Luma};
+
+const FS: u32 = common::FONT_SIZE;
+
+fn converter(orientation: OrientationConfig) -> ascii_gen::ascii::converter::Converter {
+    ConverterConfig {
+        orientation,
+        draw_edges: false,
+        // Box averaging keeps the marker from bleeding into the neighbouring cells
+        tile_sampling: TileSamplingConfig::ExactBoxAverage,
+        ..common::test_config()
+    }
+    .build()
+    .unwrap()
+}
+
+// Black image of 6 x 4 cells with a white top left cell
+fn marked_image() -> DynamicImage {
+    DynamicImage::ImageLuma8(GrayImage::from_fn(FS * 6, FS * 4, |x, y| {
+        Luma([if x < FS && y < FS { 255 } else { 0 }])
+    }))
+}
+
+fn grid(orientation: OrientationConfig) -> Vec<Vec<char>> {
+    converter(orientation)
+        .convert_to_text(&marked_image(), 0.0)
+        .unwrap()
+        .lines()
+        .map(|line| line.chars().collect())
+        .collect()
+}
+
+fn marker(grid: &[Vec<char>]) -> (usize, usize) {
+    let marked: Vec<(usize, usize)> = grid
+        .iter()
+        .enumerate()
+        .flat_map(|(y, row)| {
+            row.iter()
+                .enumerate()
+                .filter(|(_, &ch)| ch != ' ')
+                .map(move |(x, _)| (x, y))
+        })
+        .collect();
+    assert_eq!(marked.len(), 1, "{:?}", grid);
+    marked[0]
+}
+
+#[test]
+fn quarter_turns_swap_the_grid_dimensions() {
+    for (orientation, cols, rows) in [
+        (OrientationConfig::Normal, 6, 4),
+        (OrientationConfig::Rotate90, 4, 6),
+        (OrientationConfig::Rotate180, 6, 4),
+        (OrientationConfig::Rotate270, 4, 6),
+        (OrientationConfig::FlipH, 6, 4),
+        (OrientationConfig::FlipV, 6, 4),
+    ] {
+        let grid = grid(orientation.clone());
+        assert_eq!(
+            (grid[0].len(), grid.len()),
+            (cols, rows),
+            "{:?}",
+            orientation
+        );
+
+        let geometry = converter(orientation.clone())
+            .output_geometry(FS * 6, FS * 4)
+            .unwrap();
+        assert_eq!(
+            (geometry.cols as usize, geometry.rows as usize),
+            (cols, rows),
+            "{:?}",
+            orientation
+        );
+    }
+}
+
+#[test]
+fn marker_ends_up_in_the_expected_corner() {
+    for (orientation, corner) in [
+        (OrientationConfig::Normal, (0, 0)),
+        (OrientationConfig::Rotate90, (3, 0)),
+        (OrientationConfig::Rotate180, (5, 3)),
+        (OrientationConfig::Rotate270, (0, 5)),
+        (OrientationConfig::FlipH, (5, 0)),
+        (OrientationConfig::FlipV, (0, 3)),
+    ] {
+        assert_eq!(
+            marker(&grid(orientation.clone())),
+            corner,
+            "{:?}",
+            orientation
+        );
+    }
+}
+
+#[test]
+fn rendered_image_is_rotated() {
+    let img = common::circle(FS * 10, FS * 6);
+    let out = converter(OrientationConfig::Rotate90)
+        .convert_image(&img, 0.2)
+        .unwrap();
+    assert_eq!(out.dimensions(), (FS * 6, FS * 10));
+}
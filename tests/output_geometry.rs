@@ -0,0 +1,67 @@
+/*
+* Output geometry predicted without converting matches the converted images
+*/
+mod common;
+
+use ascii_gen::ascii::config::ConverterConfig;
+use ascii_gen::ascii::converter::OutputGeometry;
+use ascii_gen::ascii::error::ConvertError;
+use image::GenericImageView;
+
+const FS: u32 = common::FONT_SIZE;
+
+#[test]
+fn prediction_matches_converted_images() {
+    let converter = common::test_converter();
+    for (w, h) in [
+        (FS, FS),
+        (FS * 3 + 1, FS * 2 + FS - 1),
+        (FS * 10 - 1, FS * 7 + 3),
+        (97, 61),
+        (FS * 12, FS),
+    ] {
+        let img = common::circle(w, h);
+        let geometry = converter.output_geometry(w, h).unwrap();
+        let out = converter.convert_image(&img, 0.2).unwrap();
+        assert_eq!(
+            (geometry.pixel_w, geometry.pixel_h),
+            out.dimensions(),
+            "{w}x{h}"
+        );
+        let prepared = converter.prepare(&img).unwrap();
+        assert_eq!(
+            (geometry.cols, geometry.rows),
+            prepared.resized.dimensions(),
+            "{w}x{h}"
+        );
+    }
+}
+
+#[test]
+fn small_images_follow_the_fallback_setting() {
+    assert!(matches!(
+        common::test_converter().output_geometry(FS - 1, FS * 4),
+        Err(ConvertError::ImageTooSmall { .. })
+    ));
+
+    let converter = ConverterConfig {
+        small_image_fallback: true,
+        ..common::test_config()
+    }
+    .build()
+    .unwrap();
+    let geometry = converter.output_geometry(FS - 1, FS * 4 + 2).unwrap();
+    assert_eq!(
+        geometry,
+        OutputGeometry {
+            cols: 1,
+            rows: 4,
+            pixel_w: FS,
+            pixel_h: FS * 4,
+        }
+    );
+    let out = converter
+        .convert_image(&common::circle(FS - 1, FS * 4 + 2), 0.2)
+        .unwrap();
+    assert_eq!(out.dimensions(), (geometry.pixel_w, geometry.pixel_h));
+}
@@ -0,0 +1,104 @@
+/*
+* PNG outputs reduced to a few colors and written as indexed PNGs
+*/
+mod common;
+
+use ascii_gen::ascii::config::{ConverterConfig, OutputQuantizeConfig};
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::output::quantize::median_cut_palette;
+use ascii_gen::output::OutputFormat;
+use image::{DynamicImage, ImageFormat, Rgb, RgbImage};
+use std::collections::HashSet;
+use std::io::Cursor;
+
+fn rainbow() -> Vec<u8> {
+    // Colors all over the cube, so the render keeps many distinct ones
+    let img = RgbImage::from_fn(320, 240, |x, y| {
+        Rgb([
+            (x * 255 / 319) as u8,
+            (y * 255 / 239) as u8,
+            ((x + y) * 255 / 558) as u8,
+        ])
+    });
+    let mut bytes = vec![];
+    DynamicImage::ImageRgb8(img)
+        .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+        .unwrap();
+    bytes
+}
+
+fn render(output_quantize: Option<OutputQuantizeConfig>) -> Vec<u8> {
+    ConverterConfig {
+        use_image_color: true,
+        output_quantize,
+        embed_metadata: false,
+        ..common::test_config()
+    }
+    .build()
+    .unwrap()
+    .convert_bytes(&rainbow(), OutputFormat::Png, 0.0)
+    .unwrap()
+}
+
+fn distinct_colors(png: &[u8]) -> usize {
+    let img = image::load_from_memory(png).unwrap().to_rgb8();
+    img.pixels().map(|p| p.0).collect::<HashSet<_>>().len()
+}
+
+#[test]
+fn quantized_renders_are_small_indexed_pngs() {
+    let truecolor = render(None);
+    assert!(distinct_colors(&truecolor) > 8);
+    for dither in [false, true] {
+        let quantized = render(Some(OutputQuantizeConfig { colors: 8, dither }));
+        assert!(distinct_colors(&quantized) <= 8, "dither {}", dither);
+        assert!(
+            quantized.len() * 2 < truecolor.len(),
+            "{} against {}",
+            quantized.len(),
+            truecolor.len()
+        );
+        let decoder = png::Decoder::new(Cursor::new(&quantized));
+        let reader = decoder.read_info().unwrap();
+        assert_eq!(reader.info().color_type, png::ColorType::Indexed);
+        // Eight colors take four bits a pixel
+        assert_eq!(reader.info().bit_depth, png::BitDepth::Four);
+    }
+}
+
+#[test]
+fn few_color_images_keep_their_colors() {
+    let colors = [Rgb([0, 0, 0]), Rgb([200, 30, 30]), Rgb([30, 200, 30])];
+    let img = RgbImage::from_fn(30, 10, |x, _| colors[(x / 10) as usize]);
+    let mut palette = median_cut_palette(&img, 16);
+    palette.sort_by_key(|c| c.0);
+    let mut expected = colors.to_vec();
+    expected.sort_by_key(|c| c.0);
+    assert_eq!(palette, expected);
+    assert_eq!(median_cut_palette(&img, 2).len(), 2);
+}
+
+#[test]
+fn color_counts_fit_an_indexed_png() {
+    for colors in [0, 1, 257] {
+        let built = ConverterConfig {
+            output_quantize: Some(OutputQuantizeConfig {
+                colors,
+                dither: false,
+            }),
+            ..common::test_config()
+        }
+        .build();
+        assert!(
+            matches!(
+                built,
+                Err(ConvertError::InvalidSetting {
+                    field: "output_quantize",
+                    ..
+                })
+            ),
+            "{}",
+            colors
+        );
+    }
+}
@@ -0,0 +1,53 @@
+/*
+* Path taking APIs accept any path the platform does, not only valid unicode
+*/
+mod common;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+fn scratch(name: &str) -> PathBuf {
+    let root =
+        std::env::temp_dir().join(format!("ruscii-gen-{}-paths-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&root).unwrap();
+    root
+}
+
+fn write_input(path: &Path) {
+    let size = common::FONT_SIZE * 4;
+    common::circle(size, size)
+        .save_with_format(path, image::ImageFormat::Png)
+        .unwrap();
+}
+
+#[test]
+fn paths_with_spaces_and_unicode_convert() {
+    let root = scratch("unicode");
+    let dir = root.join("my pictures");
+    fs::create_dir_all(&dir).unwrap();
+    let (input, out) = (dir.join("été 写真.png"), dir.join("été 写真 ascii.png"));
+    write_input(&input);
+
+    let converter = common::test_converter();
+    converter.convert_img(&input, &out, 0.0).unwrap();
+    assert!(image::open(&out).is_ok());
+}
+
+#[cfg(unix)]
+#[test]
+fn file_names_that_are_not_utf8_convert() {
+    use std::ffi::OsStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let root = scratch("non-utf8");
+    let input = root.join(OsStr::from_bytes(b"scan \xff\xfe.png"));
+    let out = root.join(OsStr::from_bytes(b"ascii \xc3\x28.png"));
+    assert!(input.to_str().is_none() && out.to_str().is_none());
+    write_input(&input);
+
+    let converter = common::test_converter();
+    let stats = converter.convert_img_with_stats(&input, &out, 0.0).unwrap();
+    assert!(stats.cells > 0);
+    assert_eq!(image::open(&out).unwrap().width(), common::FONT_SIZE * 4);
+}
@@ -0,0 +1,76 @@
+/*
+* Default preprocessor pipelines edited by stage name before they reach the converter
+*/
+mod common;
+
+use ascii_gen::ascii::converter::Converter;
+use ascii_gen::ascii::error::ConvertError;
+
+const DEFAULT_EDGE_STAGES: [&str; 4] = ["sharpen_gaussian", "dog", "median_blur", "threshold"];
+
+#[test]
+fn default_pipelines_name_their_stages() {
+    assert_eq!(
+        Converter::default_edge_pipeline().names(),
+        DEFAULT_EDGE_STAGES
+    );
+    assert!(Converter::default_tile_pipeline().is_empty());
+}
+
+#[test]
+fn removing_a_stage_changes_the_output() {
+    let image = common::noise(96, 96, 7);
+    let reference = common::test_converter()
+        .with_edge_pipeline(Converter::default_edge_pipeline())
+        .convert_to_chars(&image, 0.0)
+        .unwrap();
+    assert_eq!(
+        reference,
+        common::test_converter()
+            .convert_to_chars(&image, 0.0)
+            .unwrap()
+    );
+
+    let mut pipeline = Converter::default_edge_pipeline();
+    pipeline.remove("threshold").unwrap();
+    assert_eq!(pipeline.names(), DEFAULT_EDGE_STAGES[..3]);
+    let unthresholded = common::test_converter()
+        .with_edge_pipeline(pipeline)
+        .convert_to_chars(&image, 0.0)
+        .unwrap();
+    assert_ne!(unthresholded, reference);
+}
+
+#[test]
+fn unknown_stages_list_the_valid_names() {
+    let mut pipeline = Converter::default_edge_pipeline();
+    let err = pipeline.remove("nope").err().unwrap();
+    let message = err.to_string();
+    match err {
+        ConvertError::UnknownStage { name, valid } => {
+            assert_eq!(name, "nope");
+            assert_eq!(valid, DEFAULT_EDGE_STAGES);
+        }
+        other => panic!("{:?}", other),
+    }
+    for stage in DEFAULT_EDGE_STAGES {
+        assert!(message.contains(stage), "{}", message);
+    }
+    // A failed edit leaves the pipeline as it was
+    assert_eq!(pipeline.len(), DEFAULT_EDGE_STAGES.len());
+}
+
+#[test]
+fn stages_move_before_others() {
+    let mut pipeline = Converter::default_edge_pipeline();
+    pipeline.move_before("dog", "sharpen_gaussian").unwrap();
+    assert_eq!(
+        pipeline.names(),
+        ["dog", "sharpen_gaussian", "median_blur", "threshold"]
+    );
+    pipeline.move_before("dog", "threshold").unwrap();
+    assert_eq!(
+        pipeline.names(),
+        ["sharpen_gaussian", "median_blur", "dog", "threshold"]
+    );
+}
@@ -0,0 +1,121 @@
+/*
+* The pixel art preset: one cell per pixel, nearest sampling and no smoothing
+*/
+mod common;
+
+use ascii_gen::ascii::char_set::{CharacterSet, MappingOptions, TileMapping};
+use ascii_gen::ascii::config::ConverterConfig;
+use ascii_gen::ascii::converter::MAX_PIXEL_CELLS;
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::ascii::preset::Preset;
+use image::{DynamicImage, GrayImage, Luma, Rgb, RgbImage};
+
+const FS: u32 = common::FONT_SIZE;
+
+// A 16x16 sprite: a face in five flat shades on a dark background
+fn sprite() -> DynamicImage {
+    let rows = [
+        "0000000000000000",
+        "0000044444400000",
+        "0004444444444000",
+        "0044444444444400",
+        "0444114444114440",
+        "0444114444114440",
+        "0444444444444440",
+        "0444444334444440",
+        "0444444334444440",
+        "0444444444444440",
+        "0442444444442440",
+        "0444222222224440",
+        "0044444444444400",
+        "0004444444444000",
+        "0000044444400000",
+        "0000000000000000",
+    ];
+    let shades = [10, 70, 130, 190, 250];
+    DynamicImage::ImageLuma8(GrayImage::from_fn(16, 16, |x, y| {
+        let shade = rows[y as usize].as_bytes()[x as usize] - b'0';
+        Luma([shades[shade as usize]])
+    }))
+}
+
+fn pixel_art_config() -> ConverterConfig {
+    Preset::PixelArt.apply(common::test_config())
+}
+
+#[test]
+fn sprite_maps_to_one_character_per_pixel() {
+    let img = sprite();
+    let text = pixel_art_config()
+        .build()
+        .unwrap()
+        .convert_to_text(&img, 0.0)
+        .unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 16, "{}", text);
+
+    let charset = CharacterSet::new(&common::test_config().tile_chars.chars().collect::<Vec<_>>());
+    let options = MappingOptions::new(TileMapping::Luminance);
+    let gray = img.to_luma8();
+    for (y, line) in lines.iter().enumerate() {
+        let expected: String = (0..16)
+            .map(|x| charset.char_for_luminance(gray.get_pixel(x, y as u32)[0], &options))
+            .collect();
+        assert_eq!(*line, expected, "row {}\n{}", y, text);
+    }
+}
+
+#[test]
+fn cells_keep_the_exact_pixel_colors() {
+    let img = DynamicImage::ImageRgb8(RgbImage::from_fn(6, 4, |x, y| {
+        Rgb([(x * 40) as u8, (y * 60) as u8, 200])
+    }));
+    let converter = ConverterConfig {
+        tile_chars: "@".to_string(),
+        ..pixel_art_config()
+    }
+    .build()
+    .unwrap();
+    let geometry = converter.output_geometry(6, 4).unwrap();
+    assert_eq!((geometry.cols, geometry.rows), (6, 4));
+    assert_eq!((geometry.pixel_w, geometry.pixel_h), (6 * FS, 4 * FS));
+
+    let ansi = converter.convert_to_ansi(&img, 0.0).unwrap();
+    for (x, y) in [(0, 0), (5, 0), (2, 3), (5, 3)] {
+        let color = format!("38;2;{};{};200m", x * 40, y * 60);
+        assert!(ansi.contains(&color), "{} missing", color);
+    }
+}
+
+#[test]
+fn sources_over_the_cell_limit_are_rejected() {
+    let converter = pixel_art_config().build().unwrap();
+    let side = (MAX_PIXEL_CELLS as f64).sqrt() as u32 + 1;
+    let err = converter.output_geometry(side, side).unwrap_err();
+    assert!(matches!(err, ConvertError::ImageTooLarge { max, .. } if max == MAX_PIXEL_CELLS));
+    let big = DynamicImage::ImageLuma8(GrayImage::new(side, side));
+    assert!(matches!(
+        converter.convert_to_text(&big, 0.0),
+        Err(ConvertError::ImageTooLarge { .. })
+    ));
+}
+
+#[test]
+fn without_pixel_cells_only_hard_steps_become_edges() {
+    let config = ConverterConfig {
+        pixel_cells: false,
+        ..pixel_art_config()
+    };
+    let converter = config.build().unwrap();
+    let edge_count = |img: &DynamicImage| {
+        converter
+            .convert_to_text(img, 0.0)
+            .unwrap()
+            .chars()
+            .filter(|c| "_|/\\".contains(*c))
+            .count()
+    };
+    // A smooth ramp has no hard step anywhere, the circle's outline is one
+    assert_eq!(edge_count(&common::gradient(FS * 32, FS * 8)), 0);
+    assert!(edge_count(&common::circle(FS * 32, FS * 32)) > 10);
+}
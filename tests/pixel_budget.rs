@@ -0,0 +1,101 @@
+/*
+* Inputs over the pixel budget are rejected, or downscaled to fit when asked to
+*/
+mod common;
+
+use ascii_gen::ascii::config::ConverterConfig;
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::output::OutputFormat;
+use image::{DynamicImage, GrayImage, ImageFormat, Luma};
+use std::io::Cursor;
+
+const MAX: u64 = 300_000;
+
+// Mostly empty 3 megapixel image, ten times the budget
+fn large_image() -> DynamicImage {
+    DynamicImage::ImageLuma8(GrayImage::from_fn(3000, 1000, |x, _| {
+        Luma([if x < 1500 { 0 } else { 200 }])
+    }))
+}
+
+fn config(auto_downscale_large: bool) -> ConverterConfig {
+    ConverterConfig {
+        max_input_pixels: Some(MAX),
+        auto_downscale_large,
+        ..common::test_config()
+    }
+}
+
+#[test]
+fn large_images_are_rejected() {
+    let converter = config(false).build().unwrap();
+    assert!(matches!(
+        converter.convert_image(&large_image(), 0.2),
+        Err(ConvertError::ImageTooLarge {
+            pixels: 3_000_000,
+            max: MAX
+        })
+    ));
+    assert!(matches!(
+        converter.output_geometry(3000, 1000),
+        Err(ConvertError::ImageTooLarge { .. })
+    ));
+
+    // The default budget leaves room for large photos but not for huge panoramas
+    let converter = common::test_converter();
+    assert!(converter.output_geometry(8000, 6000).is_ok());
+    assert!(matches!(
+        converter.output_geometry(20000, 10000),
+        Err(ConvertError::ImageTooLarge { .. })
+    ));
+}
+
+#[test]
+fn large_images_are_downscaled_to_fit() {
+    let converter = config(true).build().unwrap();
+    let geometry = converter.output_geometry(3000, 1000).unwrap();
+    // 3000 x 1000 scaled by sqrt(0.1) is 948 x 316
+    assert_eq!((geometry.cols, geometry.rows), (948 / 8, 316 / 8));
+
+    let out = converter.convert_image(&large_image(), 0.2).unwrap();
+    assert_eq!(out.dimensions(), (geometry.pixel_w, geometry.pixel_h));
+}
+
+#[test]
+fn downscaling_is_recorded_in_the_stats() {
+    let mut bytes = vec![];
+    large_image()
+        .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+        .unwrap();
+    let converter = config(true).build().unwrap();
+    let (_, stats) = converter
+        .convert_bytes_with_stats(&bytes, OutputFormat::Txt, 0.2)
+        .unwrap();
+    assert_eq!(stats.downscaled_from, Some((3000, 1000)));
+    assert!(stats.to_string().contains("downscaled from: 3000x1000"));
+
+    let small = common::circle(64, 64);
+    let mut bytes = vec![];
+    small
+        .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+        .unwrap();
+    let (_, stats) = converter
+        .convert_bytes_with_stats(&bytes, OutputFormat::Txt, 0.2)
+        .unwrap();
+    assert_eq!(stats.downscaled_from, None);
+}
+
+#[test]
+fn zero_budget_is_invalid() {
+    let config = ConverterConfig {
+        max_input_pixels: Some(0),
+        ..common::test_config()
+    };
+    assert!(matches!(
+        config.build(),
+        Err(ConvertError::InvalidSetting {
+            field: "max_input_pixels",
+            ..
+        })
+    ));
+}
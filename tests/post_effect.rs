@@ -0,0 +1,136 @@
+/*
+* Post effects on small buffers, and their place at the end of a render
+*/
+mod common;
+
+use ascii_gen::ascii::config::{ConverterConfig, PostEffectConfig};
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::ascii::post_effect::PostEffect;
+use image::{Rgb, RgbImage};
+
+fn flat(w: u32, h: u32, value: u8) -> RgbImage {
+    RgbImage::from_pixel(w, h, Rgb([value; 3]))
+}
+
+#[test]
+fn scanlines_darken_the_last_row_of_every_period() {
+    let mut bufr = flat(4, 7, 200);
+    PostEffect::Scanlines {
+        period: 3,
+        factor: 0.5,
+    }
+    .apply(&mut bufr);
+    for (y, row) in bufr.rows().enumerate() {
+        let expected = if y == 2 || y == 5 { 100 } else { 200 };
+        assert!(row.into_iter().all(|p| p.0 == [expected; 3]), "row {}", y);
+    }
+
+    let mut every_row = flat(2, 3, 90);
+    PostEffect::Scanlines {
+        period: 1,
+        factor: 0.0,
+    }
+    .apply(&mut every_row);
+    assert!(every_row.pixels().all(|p| p.0 == [0; 3]));
+}
+
+#[test]
+fn chromatic_aberration_moves_red_right_and_blue_left() {
+    let mut bufr = RgbImage::from_fn(5, 1, |x, _| {
+        let v = x as u8 * 10;
+        Rgb([v, v + 1, v + 2])
+    });
+    PostEffect::ChromaticAberration { shift: 1 }.apply(&mut bufr);
+    let reds: Vec<u8> = bufr.pixels().map(|p| p[0]).collect();
+    let greens: Vec<u8> = bufr.pixels().map(|p| p[1]).collect();
+    let blues: Vec<u8> = bufr.pixels().map(|p| p[2]).collect();
+    assert_eq!(reds, [0, 0, 10, 20, 30]);
+    assert_eq!(greens, [1, 11, 21, 31, 41]);
+    assert_eq!(blues, [12, 22, 32, 42, 42]);
+
+    let original = common::noise(6, 4, 5).to_rgb8();
+    let mut unchanged = original.clone();
+    PostEffect::ChromaticAberration { shift: 0 }.apply(&mut unchanged);
+    assert_eq!(unchanged, original);
+}
+
+#[test]
+fn vignette_darkens_towards_the_corners() {
+    let mut bufr = flat(16, 16, 200);
+    PostEffect::Vignette { strength: 0.5 }.apply(&mut bufr);
+    let center = bufr.get_pixel(8, 8)[0];
+    let edge = bufr.get_pixel(0, 8)[0];
+    let corners = [(0, 0), (15, 0), (0, 15), (15, 15)].map(|(x, y)| bufr.get_pixel(x, y)[0]);
+    assert!(center >= 199, "{}", center);
+    assert!(edge < center && corners[0] < edge);
+    assert!(corners.iter().all(|&c| c == corners[0]));
+    // The outermost pixel centers sit just inside the corners
+    assert!((100..=115).contains(&corners[0]), "{}", corners[0]);
+}
+
+#[test]
+fn effects_compose_in_order() {
+    let scanlines = PostEffect::Scanlines {
+        period: 2,
+        factor: 0.5,
+    };
+    let vignette = PostEffect::Vignette { strength: 0.3 };
+    let converter = ConverterConfig {
+        post_effects: vec![
+            PostEffectConfig::Scanlines {
+                period: 2,
+                factor: 0.5,
+            },
+            PostEffectConfig::Vignette { strength: 0.3 },
+        ],
+        ..common::test_config()
+    }
+    .build()
+    .unwrap();
+    let plain = common::test_converter();
+    let img = common::circle(common::FONT_SIZE * 6, common::FONT_SIZE * 4);
+    let mut expected = plain.convert_image(&img, 0.0).unwrap();
+    scanlines.apply(&mut expected);
+    vignette.apply(&mut expected);
+    assert_eq!(converter.convert_image(&img, 0.0).unwrap(), expected);
+
+    // Rounding after every step makes the order visible
+    let mut reversed = plain.convert_image(&img, 0.0).unwrap();
+    vignette.apply(&mut reversed);
+    scanlines.apply(&mut reversed);
+    assert_ne!(reversed, expected);
+}
+
+#[test]
+fn out_of_range_effects_are_rejected() {
+    for (effect, field) in [
+        (
+            PostEffectConfig::Scanlines {
+                period: 0,
+                factor: 0.5,
+            },
+            "scanlines.period",
+        ),
+        (
+            PostEffectConfig::Scanlines {
+                period: 2,
+                factor: 1.5,
+            },
+            "scanlines.factor",
+        ),
+        (
+            PostEffectConfig::Vignette { strength: -0.1 },
+            "vignette.strength",
+        ),
+    ] {
+        let config = ConverterConfig {
+            post_effects: vec![effect],
+            ..common::test_config()
+        };
+        match config.build() {
+            Err(ConvertError::InvalidSetting { field: f, .. }) => assert_eq!(f, field),
+            Err(e) => panic!("{:?}", e),
+            Ok(_) => panic!("{} accepted", field),
+        }
+    }
+}
@@ -0,0 +1,77 @@
+/*
+* The line art preset and its detection
+*/
+mod common;
+
+use ascii_gen::ascii::config::ConverterConfig;
+use ascii_gen::ascii::preset::Preset;
+use image::{DynamicImage, GrayImage, Luma};
+
+const FS: u32 = common::FONT_SIZE;
+
+// Marker strokes on a whiteboard lit brighter on the left, with sensor noise
+fn whiteboard() -> DynamicImage {
+    let (w, h) = (FS * 40, FS * 24);
+    let noise = common::noise(w, h, 5).to_luma8();
+    DynamicImage::ImageLuma8(GrayImage::from_fn(w, h, |x, y| {
+        let paper = 235 - (x * 40 / w) as i32;
+        let (fx, fy) = (x as i32, y as i32);
+        // A box, a diagonal and a horizontal underline, 3 pixels thick
+        let box_edge = ((40..44).contains(&fx) || (150..154).contains(&fx))
+            && (40..150).contains(&fy)
+            || ((40..44).contains(&fy) || (146..150).contains(&fy)) && (40..154).contains(&fx);
+        let diagonal = (fx - fy - 130).abs() < 2 && (170..300).contains(&fx);
+        let underline = (170..175).contains(&fy) && (40..290).contains(&fx);
+        let ink = box_edge || diagonal || underline;
+        let n = noise.get_pixel(x, y)[0] as i32 / 16 - 8;
+        let v = if ink { 50 } else { paper };
+        Luma([(v + n).clamp(0, 255) as u8])
+    }))
+}
+
+fn line_art_config() -> ConverterConfig {
+    Preset::LineArt.apply(common::test_config())
+}
+
+#[test]
+fn whiteboards_are_detected_as_line_art() {
+    assert_eq!(Preset::detect(&whiteboard()), Preset::LineArt);
+    // Photos, dark images and noise keep the default
+    assert_eq!(Preset::detect(&common::circle(256, 256)), Preset::Default);
+    assert_eq!(Preset::detect(&common::gradient(256, 64)), Preset::Default);
+    assert_eq!(Preset::detect(&common::noise(128, 128, 3)), Preset::Default);
+    // A blank page has no ink to draw
+    let blank = DynamicImage::ImageLuma8(GrayImage::from_pixel(64, 64, Luma([240])));
+    assert_eq!(Preset::detect(&blank), Preset::Default);
+}
+
+#[test]
+fn line_art_draws_edges_only() {
+    let text = line_art_config()
+        .build()
+        .unwrap()
+        .convert_to_text(&whiteboard(), 0.0)
+        .unwrap();
+    let tiles = common::test_config().tile_chars;
+    let edges: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+    assert!(edges.len() > 40, "{}", text);
+    assert!(edges.iter().all(|c| "_|/\\".contains(*c)), "{}", text);
+    assert!(!text
+        .chars()
+        .any(|c| c != ' ' && tiles.contains(c) && !"_|/\\".contains(c)));
+}
+
+#[test]
+fn line_art_renders_black_on_white() {
+    let img = line_art_config()
+        .build()
+        .unwrap()
+        .convert_image(&whiteboard(), 0.0)
+        .unwrap();
+    let white = img.pixels().filter(|p| p.0 == [255, 255, 255]).count();
+    let ink = img.pixels().filter(|p| p.0[0] < 128).count();
+    let total = img.pixels().len();
+    assert!(white * 10 > total * 8, "{} of {} white", white, total);
+    assert!(ink > 0);
+    assert!(img.pixels().all(|p| p.0[0] == p.0[1] && p.0[1] == p.0[2]));
+}
@@ -0,0 +1,70 @@
+/*
+* Quick ANSI previews for interactive use
+*/
+use ascii_gen::ascii::cancel::CancelToken;
+use ascii_gen::ascii::converter::Converter;
+use ascii_gen::ascii::error::ConvertError;
+use image::{DynamicImage, GrayImage, Luma};
+use std::time::{Duration, Instant};
+
+// Generous even for unoptimized builds, a full conversion of the input takes far longer
+const PREVIEW_BUDGET: Duration = Duration::from_secs(30);
+
+fn noise(w: u32, h: u32) -> DynamicImage {
+    // xorshift keeps the noise identical on every platform
+    let mut state = 7u32;
+    DynamicImage::ImageLuma8(GrayImage::from_fn(w, h, |_, _| {
+        state ^= state << 13;
+        state ^= state >> 17;
+        state ^= state << 5;
+        Luma([(state >> 24) as u8])
+    }))
+}
+
+fn visible_width(line: &str) -> usize {
+    // Characters of a line of ANSI text once its SGR escapes are taken out
+    let mut width = 0;
+    let mut chars = line.chars();
+    while let Some(ch) = chars.next() {
+        if ch == '\x1b' {
+            chars.by_ref().find(|&ch| ch == 'm');
+        } else {
+            width += 1;
+        }
+    }
+    width
+}
+
+#[test]
+fn previews_of_large_inputs_are_max_cols_wide() {
+    let converter = Converter::default();
+    let img = noise(4000, 3000);
+    let start = Instant::now();
+    let preview = converter.preview(&img, 60, 0.0).unwrap();
+    assert!(start.elapsed() < PREVIEW_BUDGET, "{:?}", start.elapsed());
+
+    let lines: Vec<&str> = preview.lines().collect();
+    assert!(!lines.is_empty());
+    for line in lines.iter() {
+        assert_eq!(visible_width(line), 60);
+    }
+    // The grid keeps the aspect ratio of the input
+    assert_eq!(lines.len(), 45);
+}
+
+#[test]
+fn cancelled_previews_give_up() {
+    let converter = Converter::default();
+    let img = noise(64, 64);
+    let cancel = CancelToken::new();
+    let preview = converter
+        .preview_cancellable(&img, 8, 0.0, &cancel)
+        .unwrap();
+    assert_eq!(preview, converter.preview(&img, 8, 0.0).unwrap());
+
+    cancel.cancel();
+    assert!(matches!(
+        converter.preview_cancellable(&img, 8, 0.0, &cancel),
+        Err(ConvertError::Cancelled)
+    ));
+}
@@ -0,0 +1,152 @@
+use ascii_gen::ascii::char_set::quantize_luma;
+use ascii_gen::image_manip::edge_processor::EdgeDownscaler;
+use ascii_gen::image_manip::util::{arr_to_bufr, bufr_to_arr};
+use image::{ImageBuffer, Luma};
+use ndarray::{s, Array2};
+use proptest::prelude::*;
+use std::collections::BTreeMap;
+
+/*
+* Edge map made of whole tiles along with its tile size and downscaled size. Values stay in the
+* range the edge detector produces
+*/
+fn edge_map() -> impl Strategy<Value = (Array2<u8>, usize, (usize, usize))> {
+    (1usize..6, 1usize..6, 1usize..6).prop_flat_map(|(tile_size, new_h, new_w)| {
+        let (h, w) = (new_h * tile_size, new_w * tile_size);
+        // Mostly zeros so tiles with a few edge pixels are common
+        let value = prop_oneof![3 => Just(0u8), 1 => 0u8..5];
+        prop::collection::vec(value, h * w).prop_map(move |raw| {
+            (
+                Array2::from_shape_vec((h, w), raw).unwrap(),
+                tile_size,
+                (new_h, new_w),
+            )
+        })
+    })
+}
+
+fn tile(arr: &Array2<u8>, tile_size: usize, i: usize, j: usize) -> Vec<u8> {
+    arr.slice(s![
+        (i * tile_size)..((i + 1) * tile_size),
+        (j * tile_size)..((j + 1) * tile_size)
+    ])
+    .iter()
+    .copied()
+    .collect()
+}
+
+fn reference_downscale(
+    arr: &Array2<u8>,
+    tile_size: usize,
+    thres: f32,
+    new_size: (usize, usize),
+) -> Array2<u8> {
+    // Straightforward histogram per tile, ties go to the lowest edge value
+    Array2::from_shape_fn(new_size, |(i, j)| {
+        let tile = tile(arr, tile_size, i, j);
+        let mut hist = BTreeMap::new();
+        for &v in tile.iter().filter(|&&v| v != 0) {
+            *hist.entry(v).or_insert(0usize) += 1;
+        }
+        let edge_count: usize = hist.values().sum();
+        if (edge_count as f32 / tile.len() as f32) < thres {
+            return 0;
+        }
+        let max_count = hist.values().copied().max().unwrap_or(0);
+        hist.iter()
+            .find(|&(_, &count)| count == max_count)
+            .map_or(0, |(&v, _)| v)
+    })
+}
+
+proptest! {
+    #[test]
+    fn downscale_matches_reference(
+        (arr, tile_size, new_size) in edge_map(),
+        thres in 0.0f32..=1.0,
+    ) {
+        let ds = EdgeDownscaler::hist_downscale(&arr, tile_size, thres, new_size);
+        prop_assert_eq!(ds, reference_downscale(&arr, tile_size, thres, new_size));
+    }
+
+    #[test]
+    fn downscale_has_requested_shape(
+        (arr, tile_size, new_size) in edge_map(),
+        thres in 0.0f32..=1.0,
+    ) {
+        let ds = EdgeDownscaler::hist_downscale(&arr, tile_size, thres, new_size);
+        prop_assert_eq!(ds.dim(), new_size);
+    }
+
+    #[test]
+    fn downscale_values_come_from_tile(
+        (arr, tile_size, new_size) in edge_map(),
+        thres in 0.0f32..=1.0,
+    ) {
+        let ds = EdgeDownscaler::hist_downscale(&arr, tile_size, thres, new_size);
+        for ((i, j), &val) in ds.indexed_iter() {
+            prop_assert!(val == 0 || tile(&arr, tile_size, i, j).contains(&val));
+        }
+    }
+
+    #[test]
+    fn zero_threshold_keeps_every_edge_tile((arr, tile_size, new_size) in edge_map()) {
+        let ds = EdgeDownscaler::hist_downscale(&arr, tile_size, 0.0, new_size);
+        for ((i, j), &val) in ds.indexed_iter() {
+            let has_edge = tile(&arr, tile_size, i, j).iter().any(|&v| v != 0);
+            prop_assert_eq!(val != 0, has_edge);
+        }
+    }
+
+    #[test]
+    fn full_threshold_keeps_only_full_tiles((arr, tile_size, new_size) in edge_map()) {
+        let ds = EdgeDownscaler::hist_downscale(&arr, tile_size, 1.0, new_size);
+        for ((i, j), &val) in ds.indexed_iter() {
+            let is_full = tile(&arr, tile_size, i, j).iter().all(|&v| v != 0);
+            prop_assert_eq!(val != 0, is_full);
+        }
+    }
+
+    #[test]
+    fn quantized_index_is_in_range(luma: u8, levels in 1usize..1024) {
+        prop_assert!(quantize_luma(luma, levels) < levels);
+    }
+
+    #[test]
+    fn quantized_index_is_monotone(a: u8, b: u8, levels in 1usize..1024) {
+        let (lo, hi) = (a.min(b), a.max(b));
+        prop_assert!(quantize_luma(lo, levels) <= quantize_luma(hi, levels));
+    }
+
+    #[test]
+    fn quantized_extremes_map_to_ends(levels in 1usize..1024) {
+        prop_assert_eq!(quantize_luma(0, levels), 0);
+        prop_assert_eq!(quantize_luma(255, levels), levels - 1);
+    }
+
+    #[test]
+    fn buffer_array_round_trip(
+        (w, h, raw) in (1u32..64, 1u32..64).prop_flat_map(|(w, h)| {
+            (Just(w), Just(h), prop::collection::vec(any::<u8>(), (w * h) as usize))
+        }),
+    ) {
+        let bufr: ImageBuffer<Luma<u8>, Vec<u8>> = ImageBuffer::from_raw(w, h, raw).unwrap();
+        let arr = bufr_to_arr(&bufr);
+        prop_assert_eq!(arr.dim(), (h as usize, w as usize));
+        for (x, y, px) in bufr.enumerate_pixels() {
+            prop_assert_eq!(arr[(y as usize, x as usize)], px.0[0]);
+        }
+        prop_assert_eq!(arr_to_bufr(&arr), bufr);
+    }
+
+    #[test]
+    fn single_row_and_column_round_trip(len in 1usize..256, vertical: bool) {
+        let shape = if vertical { (len, 1) } else { (1, len) };
+        let arr = Array2::from_shape_fn(shape, |(y, x)| (y + x) as u8);
+        prop_assert_eq!(bufr_to_arr(&arr_to_bufr(&arr)), arr.clone());
+
+        // Swapping axes leaves the array out of standard layout, which must not reorder pixels
+        let transposed = arr.reversed_axes();
+        prop_assert_eq!(bufr_to_arr(&arr_to_bufr(&transposed)), transposed);
+    }
+}
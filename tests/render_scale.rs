@@ -0,0 +1,129 @@
+/*
+* A render cell size draws the grid sampled at the font size with larger or smaller cells,
+* rasterizing the glyphs at that size
+*/
+mod common;
+
+use ascii_gen::ascii::cell::CellValue;
+use ascii_gen::ascii::config::{ConverterConfig, DetailModeConfig};
+use ascii_gen::ascii::error::ConvertError;
+use image::imageops::{resize, FilterType};
+use image::{Rgb, RgbImage};
+use ndarray::Array2;
+
+fn sampled_at(font_size: u32, render_cell_px: Option<u32>) -> ConverterConfig {
+    ConverterConfig {
+        font_size,
+        render_cell_px,
+        use_image_color: false,
+        color: [255, 255, 255],
+        bg_color: [0, 0, 0],
+        ..common::test_config()
+    }
+}
+
+// Share of the pixels that are neither background nor glyph, the blur of an upscaled render
+fn midtone_share(img: &RgbImage) -> f32 {
+    let midtones = img.pixels().filter(|p| (32..224).contains(&p[0])).count();
+    midtones as f32 / (img.width() * img.height()) as f32
+}
+
+#[test]
+fn larger_cells_keep_the_grid_and_scale_the_image() {
+    let img = common::circle(96, 64);
+    let small = sampled_at(4, None).build().unwrap();
+    let large = sampled_at(4, Some(8)).build().unwrap();
+
+    let small_img = small.convert_image(&img, 0.0).unwrap();
+    let large_img = large.convert_image(&img, 0.0).unwrap();
+    assert_eq!(small_img.dimensions(), (96, 64));
+    assert_eq!(large_img.dimensions(), (192, 128));
+    assert_eq!(
+        small.convert_to_text(&img, 0.0).unwrap(),
+        large.convert_to_text(&img, 0.0).unwrap()
+    );
+
+    let geometry = large.output_geometry(96, 64).unwrap();
+    assert_eq!((geometry.cols, geometry.rows), (24, 16));
+    assert_eq!((geometry.pixel_w, geometry.pixel_h), (192, 128));
+}
+
+#[test]
+fn glyphs_are_rasterized_at_the_cell_size() {
+    let cells = Array2::from_shape_fn((4, 6), |(y, x)| match (y + x) % 3 {
+        0 => CellValue::Tile(12),
+        1 => CellValue::Tile(9),
+        _ => CellValue::Edge(1),
+    });
+    let colors = Array2::from_elem((4, 6), Rgb([255, 255, 255]));
+    let scaled = sampled_at(4, Some(8))
+        .build()
+        .unwrap()
+        .render(&cells.view(), &colors.view())
+        .unwrap();
+    let native = sampled_at(8, None)
+        .build()
+        .unwrap()
+        .render(&cells.view(), &colors.view())
+        .unwrap();
+    assert_eq!(scaled, native);
+
+    // Crisp glyph edges, unlike the same grid drawn small and blown up
+    let small = sampled_at(4, None)
+        .build()
+        .unwrap()
+        .render(&cells.view(), &colors.view())
+        .unwrap();
+    let upscaled = resize(&small, 48, 32, FilterType::Triangle);
+    assert!(midtone_share(&scaled) < midtone_share(&upscaled));
+    let max_step = scaled
+        .rows()
+        .flat_map(|row| {
+            let row: Vec<_> = row.collect();
+            (1..row.len())
+                .map(|x| row[x][0].abs_diff(row[x - 1][0]))
+                .collect::<Vec<_>>()
+        })
+        .max()
+        .unwrap();
+    assert!(max_step > 200, "{max_step}");
+}
+
+#[test]
+fn two_scale_detail_follows_the_cell_size() {
+    let img = common::noise(64, 64, 3);
+    let config = ConverterConfig {
+        detail_mode: DetailModeConfig::TwoScale {
+            fine_factor: 2,
+            variance_threshold: 0.0,
+        },
+        ..sampled_at(4, Some(8))
+    };
+    let out = config.build().unwrap().convert_image(&img, 0.0).unwrap();
+    assert_eq!(out.dimensions(), (128, 128));
+}
+
+#[test]
+fn cell_size_is_validated() {
+    assert!(matches!(
+        sampled_at(4, Some(0)).build().err(),
+        Some(ConvertError::InvalidSetting {
+            field: "render_cell_px",
+            ..
+        })
+    ));
+    let odd_cells = ConverterConfig {
+        detail_mode: DetailModeConfig::TwoScale {
+            fine_factor: 2,
+            variance_threshold: 0.0,
+        },
+        ..sampled_at(4, Some(9))
+    };
+    assert!(matches!(
+        odd_cells.build().err(),
+        Some(ConvertError::InvalidSetting {
+            field: "two_scale.fine_factor",
+            ..
+        })
+    ));
+}
@@ -0,0 +1,160 @@
+/*
+* Grids saved as text read back and rendered to images
+*/
+mod common;
+
+use ascii_gen::ascii::config::ConverterConfig;
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::ascii::warning::ConvertWarning;
+use ascii_gen::input::text_grid::{parse_text_grid, ColorSource};
+use ascii_gen::output::text::TextExporter;
+use image::{ImageFormat, Rgb, RgbImage};
+use ndarray::array;
+use std::fs;
+use std::path::PathBuf;
+
+fn scratch(name: &str) -> PathBuf {
+    let root =
+        std::env::temp_dir().join(format!("ruscii-gen-{}-text-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&root).unwrap();
+    root
+}
+
+fn line_of(result: Result<ndarray::Array2<char>, ConvertError>) -> usize {
+    match result {
+        Err(ConvertError::MalformedTextGrid { line, .. }) => line,
+        other => panic!("expected a malformed grid, got {:?}", other),
+    }
+}
+
+#[test]
+fn grids_parse_from_either_line_ending() {
+    let expected = array![['@', ' ', '.'], ['_', 'o', '|']];
+    assert_eq!(parse_text_grid("@ .\n_o|\n").unwrap(), expected);
+    assert_eq!(parse_text_grid("@ .\r\n_o|\r\n").unwrap(), expected);
+    assert_eq!(parse_text_grid("@ .\n_o|").unwrap(), expected);
+}
+
+#[test]
+fn ragged_lines_tabs_and_empty_text_are_rejected() {
+    assert_eq!(line_of(parse_text_grid("@@@\n@@@\n@@\n")), 3);
+    assert_eq!(line_of(parse_text_grid("@@@\n@\t@\n")), 2);
+    assert_eq!(line_of(parse_text_grid("")), 1);
+    assert_eq!(line_of(parse_text_grid("\n@@\n")), 1);
+    assert_eq!(
+        parse_text_grid("@@@\n@\t@\n").unwrap_err().to_string(),
+        "Text grid is malformed at line 2: tab in column 2"
+    );
+}
+
+#[test]
+fn saved_grid_renders_like_the_image() {
+    let dir = scratch("roundtrip");
+    let input = dir.join("circle.png");
+    common::circle(common::FONT_SIZE * 12, common::FONT_SIZE * 8)
+        .save_with_format(&input, ImageFormat::Png)
+        .unwrap();
+    let converter = common::test_converter();
+    let (txt, direct, rendered) = (
+        dir.join("circle.txt"),
+        dir.join("direct.png"),
+        dir.join("rendered.png"),
+    );
+    converter
+        .convert_to_txt(&input, &txt, 0.0, &TextExporter::new())
+        .unwrap();
+    converter.convert_img(&input, &direct, 0.0).unwrap();
+
+    let warnings = converter
+        .render_text_file(&txt, &rendered, ColorSource::Image(input.clone()))
+        .unwrap();
+    assert!(warnings.is_empty(), "{:?}", warnings);
+    assert_eq!(
+        image::open(&rendered).unwrap().to_rgb8(),
+        image::open(&direct).unwrap().to_rgb8()
+    );
+
+    // A grid of another size than the image's does not line up with its colors
+    fs::write(&txt, "@@\n@@\n").unwrap();
+    assert!(matches!(
+        converter.render_text_file(&txt, &rendered, ColorSource::Image(input)),
+        Err(ConvertError::GridSizeMismatch { a: (2, 2), .. })
+    ));
+}
+
+#[test]
+fn fixed_and_gradient_colors_need_no_image() {
+    let dir = scratch("colors");
+    let txt = dir.join("grid.txt");
+    fs::write(&txt, "@@@@\n@@@@\n@@@@\n@@@@\n").unwrap();
+    let converter = ConverterConfig {
+        bg_color: [0, 0, 0],
+        ..common::test_config()
+    }
+    .build()
+    .unwrap();
+    let brightest = |img: &RgbImage, rows: std::ops::Range<u32>| {
+        img.enumerate_pixels()
+            .filter(|(_, y, _)| rows.contains(y))
+            .map(|(_, _, p)| *p)
+            .max_by_key(|p| p.0.iter().map(|&c| c as u32).sum::<u32>())
+            .unwrap()
+    };
+
+    let out = dir.join("fixed.png");
+    converter
+        .render_text_file(&txt, &out, ColorSource::Fixed(Rgb([255, 0, 0])))
+        .unwrap();
+    let img = image::open(&out).unwrap().to_rgb8();
+    assert_eq!(
+        img.dimensions(),
+        (common::FONT_SIZE * 4, common::FONT_SIZE * 4)
+    );
+    assert!(img.pixels().all(|p| p[1] == 0 && p[2] == 0));
+    assert_eq!(brightest(&img, 0..img.height()), Rgb([255, 0, 0]));
+
+    let out = dir.join("gradient.png");
+    converter
+        .render_text_file(
+            &txt,
+            &out,
+            ColorSource::Gradient {
+                top: Rgb([255, 0, 0]),
+                bottom: Rgb([0, 0, 255]),
+            },
+        )
+        .unwrap();
+    let img = image::open(&out).unwrap().to_rgb8();
+    let cell = common::FONT_SIZE;
+    assert_eq!(brightest(&img, 0..cell), Rgb([255, 0, 0]));
+    assert_eq!(brightest(&img, cell * 3..cell * 4), Rgb([0, 0, 255]));
+}
+
+#[test]
+fn characters_missing_from_the_font_warn_or_fail_strictly() {
+    let dir = scratch("missing");
+    let txt = dir.join("grid.txt");
+    fs::write(&txt, "@λ\nλ#\n").unwrap();
+    let warnings = common::test_converter()
+        .render_text_file(&txt, dir.join("out.png"), ColorSource::Fixed(Rgb([255; 3])))
+        .unwrap();
+    assert_eq!(warnings, [ConvertWarning::MissingGlyphs(vec!['λ', '#'])]);
+
+    let strict = ConverterConfig {
+        strict: true,
+        ..common::test_config()
+    }
+    .build()
+    .unwrap();
+    assert!(matches!(
+        strict.render_text_file(
+            &txt,
+            dir.join("strict.png"),
+            ColorSource::Fixed(Rgb([255; 3]))
+        ),
+        Err(ConvertError::StrictViolation(
+            ConvertWarning::MissingGlyphs(_)
+        ))
+    ));
+}
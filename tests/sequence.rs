@@ -0,0 +1,225 @@
+/*
+* Image sequences matched by a pattern, converted in frame order with their numbers kept
+*/
+mod common;
+
+use ascii_gen::ascii::config::ConverterConfig;
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::ascii::sequence::{
+    convert_sequence, expand_glob, natural_cmp, SequenceAnimation, SequenceOptions,
+};
+use ascii_gen::ascii::warning::ConvertWarning;
+use image::codecs::gif::GifDecoder;
+use image::AnimationDecoder;
+use std::cmp::Ordering;
+use std::fs;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+
+fn scratch(name: &str) -> PathBuf {
+    let root = std::env::temp_dir().join(format!("ruscii-gen-{}-seq-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&root).unwrap();
+    root
+}
+
+fn write_frames(dir: &Path, names: &[&str]) {
+    let size = common::FONT_SIZE * 4;
+    for (i, name) in names.iter().enumerate() {
+        common::noise(size, size, i as u32)
+            .save_with_format(dir.join(name), image::ImageFormat::Png)
+            .unwrap();
+    }
+}
+
+fn file_names(paths: &[PathBuf]) -> Vec<String> {
+    paths
+        .iter()
+        .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+        .collect()
+}
+
+#[test]
+fn digit_runs_compare_by_value() {
+    assert_eq!(natural_cmp("frame_9", "frame_10"), Ordering::Less);
+    assert_eq!(natural_cmp("frame_10", "frame_9"), Ordering::Greater);
+    assert_eq!(natural_cmp("a2b10", "a2b9"), Ordering::Greater);
+    assert_eq!(natural_cmp("shot", "shot_1"), Ordering::Less);
+    assert_eq!(natural_cmp("frame_007", "frame_7"), Ordering::Greater);
+    assert_eq!(natural_cmp("frame_07", "frame_07"), Ordering::Equal);
+}
+
+#[test]
+fn matches_are_sorted_naturally() {
+    let dir = scratch("order");
+    write_frames(
+        &dir,
+        &[
+            "frame_10.png",
+            "frame_9.png",
+            "frame_100.png",
+            "frame_1.png",
+        ],
+    );
+    fs::write(dir.join("frame_2.txt"), "not a frame").unwrap();
+    fs::write(dir.join("notes.png"), "not a frame").unwrap();
+
+    let pattern = dir.join("frame_*.png");
+    let matches = expand_glob(&pattern.to_string_lossy()).unwrap();
+    assert_eq!(
+        file_names(&matches),
+        [
+            "frame_1.png",
+            "frame_9.png",
+            "frame_10.png",
+            "frame_100.png"
+        ]
+    );
+    let single = expand_glob(&dir.join("frame_?.png").to_string_lossy()).unwrap();
+    assert_eq!(file_names(&single), ["frame_1.png", "frame_9.png"]);
+
+    assert!(matches!(
+        expand_glob(&dir.join("clip_*.png").to_string_lossy()),
+        Err(ConvertError::NoMatches(_))
+    ));
+    assert!(matches!(
+        expand_glob(&dir.join("*").join("frame_*.png").to_string_lossy()),
+        Err(ConvertError::InvalidSetting {
+            field: "pattern",
+            ..
+        })
+    ));
+}
+
+#[test]
+fn outputs_keep_the_frame_numbers_and_gaps_warn() {
+    let dir = scratch("numbers");
+    let (input, out) = (dir.join("in"), dir.join("out"));
+    fs::create_dir_all(&input).unwrap();
+    write_frames(
+        &input,
+        &[
+            "frame_0010.png",
+            "frame_0002.png",
+            "frame_0001.png",
+            "frame_0003.png",
+            "frame_0006.png",
+        ],
+    );
+
+    let pattern = input.join("frame_*.png");
+    let report = convert_sequence(
+        common::test_converter(),
+        &pattern.to_string_lossy(),
+        &out,
+        "ascii_{frame}.png",
+        &SequenceOptions::new(0.0),
+    )
+    .unwrap();
+    let numbers: Vec<Option<u64>> = report.frames.iter().map(|frame| frame.number).collect();
+    assert_eq!(numbers, [Some(1), Some(2), Some(3), Some(6), Some(10)]);
+    let outputs: Vec<PathBuf> = report.frames.iter().map(|f| f.output.clone()).collect();
+    assert_eq!(
+        file_names(&outputs),
+        [
+            "ascii_0001.png",
+            "ascii_0002.png",
+            "ascii_0003.png",
+            "ascii_0006.png",
+            "ascii_0010.png"
+        ]
+    );
+    assert!(outputs.iter().all(|output| image::open(output).is_ok()));
+    assert_eq!(
+        report.warnings,
+        [
+            ConvertWarning::SequenceGap {
+                after: 3,
+                before: 6
+            },
+            ConvertWarning::SequenceGap {
+                after: 6,
+                before: 10
+            },
+        ]
+    );
+    assert_eq!(
+        report.warnings[0].to_string(),
+        "Frames 4 to 5 are missing from the sequence"
+    );
+
+    // A converted frame is the same as converting its input on its own
+    let expected = common::test_converter()
+        .convert_image(&image::open(input.join("frame_0006.png")).unwrap(), 0.0)
+        .unwrap();
+    assert_eq!(image::open(&outputs[3]).unwrap().to_rgb8(), expected);
+}
+
+#[test]
+fn strict_mode_fails_on_gaps() {
+    let dir = scratch("strict");
+    write_frames(&dir, &["f1.png", "f3.png"]);
+    let converter = ConverterConfig {
+        strict: true,
+        ..common::test_config()
+    }
+    .build()
+    .unwrap();
+    let result = convert_sequence(
+        converter,
+        &dir.join("f*.png").to_string_lossy(),
+        dir.join("out"),
+        "{stem}.png",
+        &SequenceOptions::new(0.0),
+    );
+    assert!(matches!(
+        result,
+        Err(ConvertError::StrictViolation(ConvertWarning::SequenceGap {
+            after: 1,
+            before: 3
+        }))
+    ));
+}
+
+#[test]
+fn frames_are_assembled_into_a_gif() {
+    let dir = scratch("gif");
+    write_frames(&dir, &["shot_2.png", "shot_11.png", "shot_1.png"]);
+    let gif = dir.join("shots.gif");
+    let options = SequenceOptions::new(0.0).with_animation(Some(SequenceAnimation {
+        path: gif.to_string_lossy().into_owned(),
+        fps: 10,
+    }));
+    let report = convert_sequence(
+        common::test_converter(),
+        &dir.join("shot_*.png").to_string_lossy(),
+        dir.join("out"),
+        "{stem}.png",
+        &options,
+    )
+    .unwrap();
+    assert_eq!(report.frames.len(), 3);
+
+    let decoder = GifDecoder::new(BufReader::new(fs::File::open(&gif).unwrap())).unwrap();
+    assert_eq!(decoder.into_frames().count(), 3);
+}
+
+#[test]
+fn templates_without_a_frame_or_stem_are_rejected() {
+    let dir = scratch("template");
+    write_frames(&dir, &["f1.png"]);
+    let result = convert_sequence(
+        common::test_converter(),
+        &dir.join("f*.png").to_string_lossy(),
+        dir.join("out"),
+        "ascii.png",
+        &SequenceOptions::new(0.0),
+    );
+    assert!(matches!(
+        result,
+        Err(ConvertError::InvalidSetting {
+            field: "out_template",
+            ..
+        })
+    ));
+}
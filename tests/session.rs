@@ -0,0 +1,134 @@
+/*
+* Sessions converting one image under changing settings, running only the invalidated stages
+*/
+mod common;
+
+use ascii_gen::ascii::config::{ConverterConfig, EdgeColorConfig, MagnitudeReduceConfig};
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::ascii::session::{
+    invalidated_stages, ConversionSession, SessionStage, SESSION_STAGES,
+};
+use image::DynamicImage;
+
+fn image() -> DynamicImage {
+    common::circle(common::FONT_SIZE * 12, common::FONT_SIZE * 9)
+}
+
+fn direct(config: &ConverterConfig) -> image::RgbImage {
+    config
+        .build()
+        .unwrap()
+        .convert_image(&image(), config.edge_threshold)
+        .unwrap()
+}
+
+fn runs(session: &ConversionSession) -> Vec<usize> {
+    SESSION_STAGES
+        .iter()
+        .map(|&stage| session.stage_runs(stage))
+        .collect()
+}
+
+#[test]
+fn fields_invalidate_their_stage_and_everything_after_it() {
+    use SessionStage::*;
+    let old = common::test_config();
+    let changed = |change: fn(&mut ConverterConfig)| {
+        let mut new = old.clone();
+        change(&mut new);
+        invalidated_stages(&old, &new)
+    };
+    assert_eq!(changed(|c| c.bg_color = [1, 2, 3]), [Colors, Render]);
+    assert_eq!(changed(|c| c.render_cell_px = Some(4)), [Render]);
+    assert_eq!(
+        changed(|c| c.edge_threshold = 0.5),
+        [EdgeGrid, Cells, Colors, Render]
+    );
+    assert_eq!(
+        changed(|c| c.tile_chars = " .:#".to_string()),
+        [Tiles, Cells, Colors, Render]
+    );
+    assert_eq!(
+        changed(|c| c.draw_edges = false),
+        [EdgeMap, EdgeGrid, Cells, Colors, Render]
+    );
+    assert_eq!(
+        changed(|c| c.linear_resize = true),
+        [Prepare, Tiles, EdgeGrid, Cells, Colors, Render]
+    );
+    assert_eq!(changed(|c| c.font_size = 4), SESSION_STAGES);
+    assert_eq!(changed(|c| c.trim = Default::default()), []);
+    assert_eq!(changed(|c| c.embed_metadata = false), []);
+
+    // Edge colors only reach the edge map when they measure the edge strength
+    assert_eq!(
+        changed(|c| c.edge_color = EdgeColorConfig::Fixed { color: [9; 3] }),
+        [Colors, Render]
+    );
+    assert_eq!(
+        changed(|c| {
+            c.edge_color = EdgeColorConfig::Magnitude {
+                reduce: MagnitudeReduceConfig::default(),
+                floor: 0.2,
+            }
+        }),
+        [EdgeMap, EdgeGrid, Cells, Colors, Render]
+    );
+}
+
+#[test]
+fn renders_match_converting_directly() {
+    let config = common::test_config();
+    let mut session = ConversionSession::new(config.clone(), image()).unwrap();
+    assert_eq!(runs(&session), [0; 8]);
+    assert_eq!(*session.render().unwrap(), direct(&config));
+    assert_eq!(runs(&session), [1; 8]);
+
+    // Nothing changed, nothing runs
+    session.render().unwrap();
+    session.update(|_| {}).unwrap();
+    assert_eq!(runs(&session), [1; 8]);
+}
+
+#[test]
+fn background_color_skips_the_edge_detector() {
+    let mut session = ConversionSession::new(common::test_config(), image()).unwrap();
+    session.render().unwrap();
+    let rendered = session
+        .update(|c| c.bg_color = [10, 200, 30])
+        .unwrap()
+        .clone();
+    assert_eq!(rendered, direct(session.config()));
+    assert_eq!(runs(&session), [1, 1, 1, 1, 1, 1, 2, 2]);
+    assert_eq!(session.stage_runs(SessionStage::EdgeMap), 1);
+
+    let rendered = session.update(|c| c.edge_threshold = 0.4).unwrap().clone();
+    assert_eq!(rendered, direct(session.config()));
+    assert_eq!(runs(&session), [1, 1, 1, 1, 2, 2, 3, 3]);
+}
+
+#[test]
+fn font_size_reruns_every_stage() {
+    let mut session = ConversionSession::new(common::test_config(), image()).unwrap();
+    session.render().unwrap();
+    let rendered = session.update(|c| c.font_size = 4).unwrap().clone();
+    assert_eq!(rendered, direct(session.config()));
+    assert_eq!(runs(&session), [2; 8]);
+}
+
+#[test]
+fn failed_updates_leave_the_session_as_it_was() {
+    let config = common::test_config();
+    let mut session = ConversionSession::new(config.clone(), image()).unwrap();
+    session.render().unwrap();
+    assert!(matches!(
+        session.update(|c| c.edge_threshold = 2.0),
+        Err(ConvertError::InvalidSetting {
+            field: "edge_threshold",
+            ..
+        })
+    ));
+    assert_eq!(session.config(), &config);
+    assert_eq!(*session.render().unwrap(), direct(&config));
+    assert_eq!(runs(&session), [1; 8]);
+}
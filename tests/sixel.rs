@@ -0,0 +1,160 @@
+/*
+* Sixel output decodes back to the rendered image
+*/
+mod common;
+
+use ascii_gen::output::sixel::image_to_sixel;
+use image::{Rgb, RgbImage};
+use std::collections::HashMap;
+
+struct Decoded {
+    width: usize,
+    height: usize,
+    registers: HashMap<usize, [u32; 3]>,
+    pixels: HashMap<(usize, usize), usize>,
+}
+
+fn number(bytes: &[u8], i: &mut usize) -> usize {
+    let start = *i;
+    while *i < bytes.len() && bytes[*i].is_ascii_digit() {
+        *i += 1;
+    }
+    std::str::from_utf8(&bytes[start..*i])
+        .unwrap()
+        .parse()
+        .unwrap()
+}
+
+// Minimal decoder for the subset of sixel the encoder writes
+fn decode(sixel: &str) -> Decoded {
+    let bytes = sixel.as_bytes();
+    assert!(sixel.starts_with("\x1bP"), "missing DCS introducer");
+    assert!(sixel.ends_with("\x1b\\"), "missing string terminator");
+    let mut i = sixel.find('q').unwrap() + 1;
+    let end = bytes.len() - 2;
+
+    let mut decoded = Decoded {
+        width: 0,
+        height: 0,
+        registers: HashMap::new(),
+        pixels: HashMap::new(),
+    };
+    let (mut x, mut band, mut color) = (0, 0, 0);
+    while i < end {
+        match bytes[i] {
+            b'"' => {
+                i += 1;
+                let mut params = vec![];
+                loop {
+                    params.push(number(bytes, &mut i));
+                    if bytes[i] != b';' {
+                        break;
+                    }
+                    i += 1;
+                }
+                decoded.width = params[2];
+                decoded.height = params[3];
+            }
+            b'#' => {
+                i += 1;
+                color = number(bytes, &mut i);
+                if bytes[i] == b';' {
+                    let mut params = vec![];
+                    while bytes[i] == b';' {
+                        i += 1;
+                        params.push(number(bytes, &mut i) as u32);
+                    }
+                    assert_eq!(params[0], 2, "only RGB registers are written");
+                    decoded
+                        .registers
+                        .insert(color, [params[1], params[2], params[3]]);
+                }
+            }
+            b'$' => {
+                x = 0;
+                i += 1;
+            }
+            b'-' => {
+                x = 0;
+                band += 1;
+                i += 1;
+            }
+            b'!' | 0x3f..=0x7e => {
+                let run = if bytes[i] == b'!' {
+                    i += 1;
+                    number(bytes, &mut i)
+                } else {
+                    1
+                };
+                let sixel = bytes[i] - 0x3f;
+                i += 1;
+                for _ in 0..run {
+                    for dy in 0..6 {
+                        if sixel & (1 << dy) != 0 {
+                            let prev = decoded.pixels.insert((x, band * 6 + dy), color);
+                            assert!(prev.is_none(), "pixel drawn twice");
+                        }
+                    }
+                    x += 1;
+                }
+            }
+            other => panic!("unexpected byte {:?}", other as char),
+        }
+    }
+    decoded
+}
+
+fn assert_decodes_to(img: &RgbImage) {
+    let decoded = decode(&image_to_sixel(img));
+    assert_eq!(
+        (decoded.width, decoded.height),
+        (img.width() as usize, img.height() as usize)
+    );
+    assert!(decoded.registers.len() <= 256);
+    for (x, y, pixel) in img.enumerate_pixels() {
+        let color = decoded.pixels[&(x as usize, y as usize)];
+        let pct = decoded.registers[&color];
+        for c in 0..3 {
+            let value = pct[c] * 255 / 100;
+            assert!(
+                value.abs_diff(pixel[c] as u32) <= 3,
+                "{:?} at {},{}",
+                pixel,
+                x,
+                y
+            );
+        }
+    }
+    // Nothing drawn outside the image
+    assert_eq!(decoded.pixels.len(), (img.width() * img.height()) as usize);
+}
+
+#[test]
+fn rendered_image_round_trips() {
+    let img = common::circle(common::FONT_SIZE * 8, common::FONT_SIZE * 5);
+    let rendered = common::test_converter().convert_image(&img, 0.0).unwrap();
+    assert_decodes_to(&rendered);
+}
+
+#[test]
+fn heights_off_the_band_size_round_trip() {
+    // 13 rows end on a band of a single row, long runs use the repeat introducer
+    let img = RgbImage::from_fn(40, 13, |x, y| {
+        if x < 30 {
+            Rgb([255, 0, 0])
+        } else {
+            Rgb([0, (y * 19) as u8, 255])
+        }
+    });
+    let sixel = image_to_sixel(&img);
+    assert!(sixel.contains("!30"));
+    assert_decodes_to(&img);
+}
+
+#[test]
+fn many_colors_are_quantized() {
+    let img = RgbImage::from_fn(64, 64, |x, y| Rgb([(x * 4) as u8, (y * 4) as u8, 128]));
+    let decoded = decode(&image_to_sixel(&img));
+    assert!(decoded.registers.len() <= 256);
+    assert_eq!(decoded.pixels.len(), 64 * 64);
+}
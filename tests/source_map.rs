@@ -0,0 +1,100 @@
+#![cfg(feature = "serde")]
+/*
+* JSON outputs locate every cell in the input pixels it was sampled from
+*/
+mod common;
+
+use ascii_gen::ascii::config::{ConverterConfig, OrientationConfig};
+use ascii_gen::ascii::source_map::SourceRect;
+use ascii_gen::output::json::{CompactJsonGrid, JsonGrid, JsonLayout};
+use ascii_gen::output::OutputFormat;
+use image::ImageFormat;
+use std::io::Cursor;
+
+// Not a multiple of the font size, so the cells do not split the input into whole pixels
+const W: u32 = 203;
+const H: u32 = 117;
+
+fn png() -> Vec<u8> {
+    let mut bytes = vec![];
+    common::circle(W, H)
+        .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+        .unwrap();
+    bytes
+}
+
+fn mapped(config: ConverterConfig) -> ConverterConfig {
+    ConverterConfig {
+        include_source_map: true,
+        ..config
+    }
+}
+
+fn json(config: ConverterConfig, layout: JsonLayout) -> String {
+    let out = config
+        .build()
+        .unwrap()
+        .convert_bytes(&png(), OutputFormat::Json(layout), 0.0)
+        .unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn verbose_cells_carry_their_source_rect() {
+    let converter = mapped(common::test_config()).build().unwrap();
+    let geometry = converter.output_geometry(W, H).unwrap();
+    let grid: JsonGrid =
+        serde_json::from_str(&json(mapped(common::test_config()), JsonLayout::Verbose)).unwrap();
+    assert_eq!(
+        (grid.cols, grid.rows),
+        (geometry.cols as usize, geometry.rows as usize)
+    );
+
+    let (cols, rows) = (geometry.cols, geometry.rows);
+    assert_eq!(
+        grid.cells[0][0].src,
+        Some(SourceRect {
+            x: 0,
+            y: 0,
+            w: W / cols,
+            h: H / rows,
+        })
+    );
+    let last = grid.cells[rows as usize - 1][cols as usize - 1]
+        .src
+        .unwrap();
+    assert_eq!((last.x + last.w, last.y + last.h), (W, H));
+    assert_eq!(
+        (last.x, last.y),
+        ((cols - 1) * W / cols, (rows - 1) * H / rows)
+    );
+
+    // Without the option, cells keep their size
+    let plain = json(common::test_config(), JsonLayout::Verbose);
+    assert!(!plain.contains("\"src\""));
+    assert!(plain.len() * 2 < json(mapped(common::test_config()), JsonLayout::Verbose).len());
+}
+
+#[test]
+fn compact_grids_carry_the_cell_edges() {
+    let converter = mapped(common::test_config()).build().unwrap();
+    let map = converter.source_map(W, H).unwrap();
+    let grid: CompactJsonGrid =
+        serde_json::from_str(&json(mapped(common::test_config()), JsonLayout::Compact)).unwrap();
+    let src = grid.src.unwrap();
+    assert_eq!(src, map);
+    assert_eq!((src.xs.len(), src.ys.len()), (grid.cols + 1, grid.rows + 1));
+    assert_eq!((src.xs[0], src.ys[0]), (0, 0));
+    assert_eq!((*src.xs.last().unwrap(), *src.ys.last().unwrap()), (W, H));
+}
+
+#[test]
+fn quarter_turns_map_into_the_oriented_input() {
+    let config = mapped(ConverterConfig {
+        orientation: OrientationConfig::Rotate90,
+        ..common::test_config()
+    });
+    let grid: CompactJsonGrid = serde_json::from_str(&json(config, JsonLayout::Compact)).unwrap();
+    let src = grid.src.unwrap();
+    assert_eq!((*src.xs.last().unwrap(), *src.ys.last().unwrap()), (H, W));
+}
@@ -0,0 +1,87 @@
+/*
+* Character usage statistics of converted grids
+*/
+mod common;
+
+use ascii_gen::ascii::cell::CellValue;
+use ascii_gen::ascii::char_set::CharacterSet;
+use ascii_gen::ascii::config::ConverterConfig;
+use ascii_gen::ascii::stats::GridStats;
+use ascii_gen::output::OutputFormat;
+use ndarray::array;
+use std::io::Cursor;
+
+use CellValue::{Edge, Tile};
+
+#[test]
+fn histogram_counts_every_character_in_charset_order() {
+    let charset = CharacterSet::new(&[' ', '.', '#']);
+    let cells = array![[Tile(2), Tile(0), Tile(2)], [Edge(2), Tile(1), Tile(2)]];
+    let stats = GridStats::new(&cells.view(), &charset);
+    assert_eq!(stats.cells, 6);
+    assert_eq!(
+        stats.counts,
+        vec![
+            (' ', 1),
+            ('.', 1),
+            ('#', 3),
+            ('_', 0),
+            ('|', 1),
+            ('/', 0),
+            ('\\', 0)
+        ]
+    );
+    assert_eq!(stats.space_ratio, 1.0 / 6.0);
+    assert_eq!(stats.buckets, 3);
+}
+
+#[test]
+fn edge_ratio_is_the_share_of_edge_cells() {
+    let charset = CharacterSet::new(&[' ', '#']);
+    let cells = array![[Edge(1), Edge(3)], [Edge(4), Tile(1)]];
+    let stats = GridStats::new(&cells.view(), &charset);
+    assert_eq!(stats.edge_ratio, 0.75);
+    assert_eq!((stats.min_bucket, stats.max_bucket), (Some(1), Some(1)));
+
+    let only_edges = array![[Edge(1), Edge(2)]];
+    let stats = GridStats::new(&only_edges.view(), &charset);
+    assert_eq!(stats.edge_ratio, 1.0);
+    assert_eq!((stats.min_bucket, stats.max_bucket), (None, None));
+    assert!(stats.to_string().contains("tile buckets used: none of 2"));
+}
+
+#[test]
+fn buckets_of_repeated_characters_are_told_apart() {
+    // Buckets 1 and 2 draw the same character, so only the cells know which one they are
+    let charset = CharacterSet::new(&[' ', '#', '#']);
+    let cells = array![[Tile(2), Tile(1)], [Tile(2), Tile(2)]];
+    let stats = GridStats::new(&cells.view(), &charset);
+    assert_eq!((stats.min_bucket, stats.max_bucket), (Some(1), Some(2)));
+    assert_eq!(stats.counts[..2], [(' ', 0), ('#', 4)]);
+}
+
+#[test]
+fn conversions_report_the_buckets_they_quantized_to() {
+    let size = common::FONT_SIZE * 8;
+    let mut png = vec![];
+    // Black on the left half and white on the right, the two ends of the ramp
+    let step = image::GrayImage::from_fn(size, size, |x, _| {
+        image::Luma([if x < size / 2 { 0 } else { 255 }])
+    });
+    image::DynamicImage::ImageLuma8(step)
+        .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+        .unwrap();
+    let converter = ConverterConfig {
+        tile_chars: " ..##".to_string(),
+        draw_edges: false,
+        ..common::test_config()
+    }
+    .build()
+    .unwrap();
+    let (_, stats) = converter
+        .convert_bytes_with_stats(&png, OutputFormat::Txt, 0.0)
+        .unwrap();
+    // The brightest cells land in the last bucket, not the first one drawing its character
+    assert_eq!((stats.min_bucket, stats.max_bucket), (Some(0), Some(4)));
+    assert_eq!(stats.edge_ratio, 0.0);
+}
@@ -0,0 +1,110 @@
+/*
+* Each public pipeline step on its own, and their composition matching the one shot conversion
+*/
+mod common;
+
+use ascii_gen::ascii::cell::{cells_to_chars, CellValue};
+use ascii_gen::ascii::char_set::CharacterSet;
+use ascii_gen::ascii::error::ConvertError;
+use image::Rgb;
+use ndarray::{array, Array2};
+
+const FS: u32 = common::FONT_SIZE;
+
+#[test]
+fn prepare_has_one_pixel_per_cell() {
+    let img = common::gradient(FS * 6 + 3, FS * 4);
+    let prepared = common::test_converter().prepare(&img).unwrap();
+    assert_eq!(prepared.resized.width(), 6);
+    assert_eq!(prepared.resized.height(), 4);
+    assert_eq!(prepared.gray.dimensions(), (6, 4));
+    assert!(prepared.variance.is_none());
+
+    // Left to right gradient stays increasing
+    let row: Vec<u8> = (0..6).map(|x| prepared.gray.get_pixel(x, 0)[0]).collect();
+    assert!(row.windows(2).all(|w| w[0] < w[1]), "{:?}", row);
+}
+
+#[test]
+fn quantize_tiles_spans_the_ramp() {
+    let img = common::gradient(FS * 16, FS);
+    let converter = common::test_converter();
+    let tiles = converter.quantize_tiles(&converter.prepare(&img).unwrap());
+    assert_eq!(tiles.dim(), (1, 16));
+    assert_eq!(tiles[(0, 0)], 0);
+    // The last cell averages a little below white
+    assert!(tiles[(0, 15)] >= CharacterSet::default().tile.len() - 2);
+    assert!(tiles
+        .windows((1, 2))
+        .into_iter()
+        .all(|w| w[(0, 0)] <= w[(0, 1)]));
+}
+
+#[test]
+fn detect_edges_finds_the_circle_outline() {
+    let img = common::circle(FS * 12, FS * 12);
+    let edges = common::test_converter().detect_edges(&img, 0.0).unwrap();
+    assert_eq!(edges.dim(), (12, 12));
+    let edge_count = CharacterSet::default().edge.len() as u8;
+    assert!(edges.iter().all(|&bin| bin < edge_count));
+    // Outline only, the flat center and corners have no edge
+    assert!(edges.iter().any(|&bin| bin != 0));
+    assert_eq!(edges[(6, 6)], 0);
+    assert_eq!(edges[(0, 0)], 0);
+}
+
+#[test]
+fn combine_prefers_edges() {
+    let converter = common::test_converter();
+    let tiles = array![[0, 12], [5, 1]];
+    let edges = array![[0, 0], [2, 4]];
+    let cells = converter.combine(&tiles.view(), &edges.view()).unwrap();
+    assert_eq!(
+        cells,
+        array![
+            [CellValue::Tile(0), CellValue::Tile(12)],
+            [CellValue::Edge(2), CellValue::Edge(4)]
+        ]
+    );
+    let grid = cells_to_chars(&cells.view(), &CharacterSet::default());
+    assert_eq!(grid, array![[' ', '@'], ['|', '\\']]);
+
+    let wrong_shape = Array2::<u8>::zeros((1, 2));
+    assert!(matches!(
+        converter.combine(&tiles.view(), &wrong_shape.view()),
+        Err(ConvertError::NdArrayShapeError)
+    ));
+}
+
+#[test]
+fn render_draws_cells_of_font_size() {
+    let converter = common::test_converter();
+    let cells = Array2::from_shape_fn((3, 5), |(y, x)| match (y + x) % 3 {
+        0 => CellValue::Tile(12),
+        1 => CellValue::Tile(7),
+        _ => CellValue::Edge(3),
+    });
+    let colors = Array2::from_elem((3, 5), Rgb([255, 0, 0]));
+    let img = converter.render(&cells.view(), &colors.view()).unwrap();
+    assert_eq!(img.dimensions(), (FS * 5, FS * 3));
+    // Glyphs come out in the cell color
+    assert!(img.pixels().any(|p| p[0] > 200 && p[2] < 100));
+
+    let wrong_shape = Array2::from_elem((2, 5), Rgb([0, 0, 0]));
+    assert!(converter
+        .render(&cells.view(), &wrong_shape.view())
+        .is_err());
+}
+
+#[test]
+fn steps_compose_to_convert_image() {
+    let img = common::circle(FS * 10, FS * 8);
+    let converter = common::test_converter();
+    let prepared = converter.prepare(&img).unwrap();
+    let tiles = converter.quantize_tiles(&prepared);
+    let edges = converter.detect_edges(&img, 0.0).unwrap();
+    let cells = converter.combine(&tiles.view(), &edges.view()).unwrap();
+    let colors = converter.cell_colors(&prepared.resized);
+    let rendered = converter.render(&cells.view(), &colors.view()).unwrap();
+    assert_eq!(rendered, converter.convert_image(&img, 0.0).unwrap());
+}
@@ -0,0 +1,83 @@
+/*
+* Text outputs streamed to a writer a row at a time match the string outputs
+*/
+mod common;
+
+use ascii_gen::ascii::config::{ConverterConfig, TrimModeConfig};
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::output::text::TextFormat;
+use std::io::{self, Write};
+
+/*
+* Writer that accepts limit bytes, then fails every write
+*/
+struct FailingWriter {
+    written: Vec<u8>,
+    limit: usize,
+}
+
+impl Write for FailingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let room = self.limit - self.written.len();
+        if room == 0 {
+            return Err(io::Error::new(io::ErrorKind::BrokenPipe, "pipe closed"));
+        }
+        let n = buf.len().min(room);
+        self.written.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn streamed_text_matches_the_string_outputs() {
+    let img = common::circle(640, 96);
+    for trim in [TrimModeConfig::None, TrimModeConfig::Bounding] {
+        let converter = ConverterConfig {
+            trim,
+            ..common::test_config()
+        }
+        .build()
+        .unwrap();
+
+        let mut plain = vec![];
+        converter
+            .convert_to_writer(&img, 0.0, &mut plain, TextFormat::Plain)
+            .unwrap();
+        let text = converter.convert_to_text(&img, 0.0).unwrap();
+        assert_eq!(String::from_utf8(plain).unwrap(), text);
+
+        let mut ansi = vec![];
+        converter
+            .convert_to_writer(&img, 0.0, &mut ansi, TextFormat::Ansi)
+            .unwrap();
+        let expected = converter.convert_to_ansi(&img, 0.0).unwrap();
+        assert_eq!(String::from_utf8(ansi).unwrap(), expected);
+    }
+}
+
+#[test]
+fn writer_errors_are_output_errors() {
+    let converter = common::test_converter();
+    let img = common::circle(640, 96);
+    let mut writer = FailingWriter {
+        written: vec![],
+        limit: 100,
+    };
+    let err = converter
+        .convert_to_writer(&img, 0.0, &mut writer, TextFormat::Ansi)
+        .unwrap_err();
+    match err {
+        ConvertError::OutputError { source, .. } => {
+            assert_eq!(source.kind(), io::ErrorKind::BrokenPipe)
+        }
+        other => panic!("{:?}", other),
+    }
+    // Nothing past the failure was attempted
+    assert_eq!(writer.written.len(), 100);
+    let text = converter.convert_to_ansi(&img, 0.0).unwrap();
+    assert_eq!(writer.written, text.as_bytes()[..100]);
+}
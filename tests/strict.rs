@@ -0,0 +1,178 @@
+/*
+* Strict mode turns every warning of a conversion into an error
+*/
+mod common;
+
+use ascii_gen::ascii::config::ConverterConfig;
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::ascii::warning::ConvertWarning;
+use ascii_gen::input::decode::decode;
+use ascii_gen::output::OutputFormat;
+use image::{DynamicImage, ImageFormat};
+use std::fs;
+use std::io::Cursor;
+use std::path::PathBuf;
+
+fn scratch(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("ruscii-gen-{}-strict-{}", std::process::id(), name))
+}
+
+fn encode(img: &DynamicImage, format: ImageFormat) -> Vec<u8> {
+    let mut bytes = vec![];
+    img.write_to(&mut Cursor::new(&mut bytes), format).unwrap();
+    bytes
+}
+
+fn strict(config: ConverterConfig) -> ConverterConfig {
+    ConverterConfig {
+        strict: true,
+        ..config
+    }
+}
+
+#[test]
+fn bogus_font_fails_only_when_strict() {
+    let font = scratch("font.ttf");
+    fs::write(&font, b"not a font").unwrap();
+    let config = ConverterConfig {
+        font_path: font.to_str().unwrap().to_string(),
+        ..common::test_config()
+    };
+    let bytes = encode(&common::circle(64, 64), ImageFormat::Png);
+
+    let (_, stats) = config
+        .build()
+        .unwrap()
+        .convert_bytes_with_stats(&bytes, OutputFormat::Png, 0.0)
+        .unwrap();
+    assert!(matches!(
+        stats.warnings.as_slice(),
+        [ConvertWarning::FallbackFont { .. }]
+    ));
+
+    let converter = strict(config).build().unwrap();
+    let err = converter
+        .convert_bytes_with_stats(&bytes, OutputFormat::Png, 0.0)
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        ConvertError::StrictViolation(ConvertWarning::FallbackFont { .. })
+    ));
+    assert!(matches!(
+        converter.convert_image(&common::circle(64, 64), 0.0),
+        Err(ConvertError::StrictViolation(_))
+    ));
+}
+
+#[test]
+fn downscaling_fails_when_strict() {
+    let config = ConverterConfig {
+        max_input_pixels: Some(64 * 64),
+        auto_downscale_large: true,
+        ..common::test_config()
+    };
+    let img = common::circle(128, 128);
+    assert!(config.build().unwrap().convert_to_text(&img, 0.0).is_ok());
+    let err = strict(config)
+        .build()
+        .unwrap()
+        .convert_to_text(&img, 0.0)
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        ConvertError::StrictViolation(ConvertWarning::Downscaled {
+            from: (128, 128),
+            ..
+        })
+    ));
+}
+
+#[test]
+fn missing_glyphs_fail_when_strict() {
+    // The test font has no glyph for '#'
+    let config = ConverterConfig {
+        tile_chars: " .#".to_string(),
+        ..common::test_config()
+    };
+    assert!(config
+        .build()
+        .unwrap()
+        .convert_image(&common::gradient(64, 64), 0.0)
+        .is_ok());
+    let err = strict(config)
+        .build()
+        .unwrap()
+        .convert_image(&common::gradient(64, 64), 0.0)
+        .unwrap_err();
+    match err {
+        ConvertError::StrictViolation(ConvertWarning::MissingGlyphs(chars)) => {
+            assert_eq!(chars, vec!['#'])
+        }
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn partial_decode_fails_when_strict() {
+    let bytes = encode(
+        &DynamicImage::ImageRgb8(common::circle(128, 128).to_rgb8()),
+        ImageFormat::Jpeg,
+    );
+    let truncated = &bytes[..bytes.len() * 2 / 3];
+    let config = ConverterConfig {
+        tolerant_decode: true,
+        ..common::test_config()
+    };
+    let (_, stats) = config
+        .build()
+        .unwrap()
+        .convert_bytes_with_stats(truncated, OutputFormat::Txt, 0.0)
+        .unwrap();
+    assert!(!stats.warnings.is_empty());
+    let err = strict(config)
+        .build()
+        .unwrap()
+        .convert_bytes_with_stats(truncated, OutputFormat::Txt, 0.0)
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        ConvertError::StrictViolation(ConvertWarning::PartialDecode { .. })
+    ));
+}
+
+#[test]
+fn clean_conversions_pass_when_strict() {
+    let bytes = encode(&common::circle(64, 64), ImageFormat::Png);
+    let (_, stats) = strict(common::test_config())
+        .build()
+        .unwrap()
+        .convert_bytes_with_stats(&bytes, OutputFormat::Png, 0.0)
+        .unwrap();
+    assert!(stats.warnings.is_empty());
+}
+
+#[test]
+fn decoded_inputs_keep_their_warnings() {
+    let bytes = encode(
+        &DynamicImage::ImageRgb8(common::circle(128, 128).to_rgb8()),
+        ImageFormat::Jpeg,
+    );
+    let decoded = || decode(&bytes[..bytes.len() * 2 / 3], None, true).unwrap();
+    let config = common::test_config();
+    let (_, stats) = config
+        .clone()
+        .build()
+        .unwrap()
+        .convert_decoded_with_stats(decoded(), OutputFormat::Txt, 0.0)
+        .unwrap();
+    assert!(!stats.warnings.is_empty());
+    let err = strict(config)
+        .build()
+        .unwrap()
+        .convert_decoded_with_stats(decoded(), OutputFormat::Txt, 0.0)
+        .unwrap_err();
+    assert!(matches!(
+        err,
+        ConvertError::StrictViolation(ConvertWarning::PartialDecode { .. })
+    ));
+}
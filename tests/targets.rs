@@ -0,0 +1,149 @@
+/*
+* One conversion for several targets, each with a grid sampled for the aspect of its cells
+*/
+mod common;
+
+use ascii_gen::ascii::config::ConverterConfig;
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::ascii::target::{OutputTarget, TargetData, TargetOutput, TERMINAL_CELL_ASPECT};
+use image::DynamicImage;
+use std::fs;
+use std::path::PathBuf;
+
+fn write_input(name: &str, img: &DynamicImage) -> PathBuf {
+    let root = std::env::temp_dir().join(format!(
+        "ruscii-gen-{}-targets-{}",
+        std::process::id(),
+        name
+    ));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&root).unwrap();
+    let path = root.join("input.png");
+    img.save_with_format(&path, image::ImageFormat::Png)
+        .unwrap();
+    path
+}
+
+fn text(output: &TargetOutput) -> &str {
+    match &output.data {
+        TargetData::Text(text) => text,
+        TargetData::Image(_) => panic!("{:?} made an image", output.target),
+    }
+}
+
+// Columns over rows of a grid, scaled by the aspect of its cells into the aspect it is seen at
+fn seen_aspect(output: &TargetOutput) -> f32 {
+    output.cols as f32 * output.target.cell_aspect() / output.rows as f32
+}
+
+#[test]
+fn png_and_txt_are_both_proportioned_from_one_call() {
+    let (w, h) = (common::FONT_SIZE * 24, common::FONT_SIZE * 12);
+    let path = write_input("proportions", &common::circle(w, h));
+    let outputs = common::test_converter()
+        .convert_all_formats(&path, 0.0, &[OutputTarget::Png, OutputTarget::txt()])
+        .unwrap();
+    let (png, txt) = (&outputs[0], &outputs[1]);
+
+    assert_eq!((png.cols, png.rows), (24, 12));
+    match &png.data {
+        TargetData::Image(img) => assert_eq!(img.dimensions(), (w, h)),
+        TargetData::Text(_) => panic!("png made text"),
+    }
+    assert_eq!((txt.cols, txt.rows), (24, 6));
+    let lines: Vec<&str> = text(txt).lines().collect();
+    assert_eq!(lines.len(), txt.rows);
+    assert!(lines.iter().all(|line| line.chars().count() == txt.cols));
+
+    let image_aspect = w as f32 / h as f32;
+    for output in &outputs {
+        assert!((seen_aspect(output) - image_aspect).abs() < 1e-6);
+    }
+}
+
+#[test]
+fn a_circle_stays_round_in_terminal_cells() {
+    let size = common::FONT_SIZE * 32;
+    let path = write_input("circle", &common::circle(size, size));
+    let converter = ConverterConfig {
+        draw_edges: false,
+        ..common::test_config()
+    }
+    .build()
+    .unwrap();
+    let outputs = converter
+        .convert_all_formats(&path, 0.0, &[OutputTarget::txt()])
+        .unwrap();
+    let lines: Vec<Vec<char>> = text(&outputs[0])
+        .lines()
+        .map(|line| line.chars().collect())
+        .collect();
+
+    // The disk is drawn in dense characters over a sparse background
+    let dense = |c: char| !matches!(c, ' ' | '.' | ',');
+    let (rows, cols) = (lines.len(), lines[0].len());
+    let across = lines[rows / 2].iter().filter(|&&c| dense(c)).count();
+    let down = lines.iter().filter(|line| dense(line[cols / 2])).count();
+    let seen = across as f32 * TERMINAL_CELL_ASPECT / down as f32;
+    assert!(
+        (0.85..=1.15).contains(&seen),
+        "{} across, {} down",
+        across,
+        down
+    );
+}
+
+#[test]
+fn square_targets_match_the_single_format_conversions() {
+    let img = common::noise(common::FONT_SIZE * 10, common::FONT_SIZE * 7, 11);
+    let path = write_input("single", &img);
+    let converter = common::test_converter();
+    let square_text = OutputTarget::Txt { cell_aspect: 1.0 };
+    let outputs = converter
+        .convert_all_formats(&path, 0.3, &[OutputTarget::Png, square_text])
+        .unwrap();
+
+    match &outputs[0].data {
+        TargetData::Image(render) => {
+            assert_eq!(*render, converter.convert_image(&img, 0.3).unwrap())
+        }
+        TargetData::Text(_) => panic!("png made text"),
+    }
+    assert_eq!(
+        text(&outputs[1]),
+        converter.convert_to_text(&img, 0.3).unwrap()
+    );
+}
+
+#[test]
+fn ansi_targets_carry_their_own_aspect() {
+    let path = write_input(
+        "ansi",
+        &common::gradient(common::FONT_SIZE * 8, common::FONT_SIZE * 8),
+    );
+    let outputs = common::test_converter()
+        .convert_all_formats(
+            &path,
+            0.0,
+            &[
+                OutputTarget::ansi(),
+                OutputTarget::Ansi { cell_aspect: 0.25 },
+            ],
+        )
+        .unwrap();
+    assert_eq!((outputs[0].cols, outputs[0].rows), (8, 4));
+    assert_eq!((outputs[1].cols, outputs[1].rows), (8, 2));
+    assert!(text(&outputs[0]).contains("\x1b["));
+}
+
+#[test]
+fn invalid_cell_aspects_are_rejected() {
+    let path = write_input("invalid", &common::gradient(32, 32));
+    let converter = common::test_converter();
+    for cell_aspect in [0.0, -1.0, f32::NAN, f32::INFINITY] {
+        match converter.convert_all_formats(&path, 0.0, &[OutputTarget::Txt { cell_aspect }]) {
+            Err(ConvertError::InvalidSetting { field, .. }) => assert_eq!(field, "cell_aspect"),
+            other => panic!("{}: {:?}", cell_aspect, other.map(|o| o.len())),
+        }
+    }
+}
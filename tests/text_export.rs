@@ -0,0 +1,148 @@
+/*
+* Text file output with line endings, wrapping and a settings header
+*/
+mod common;
+
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::output::text::{LineEnding, Overflow, TextExporter};
+use image::Rgb;
+use ndarray::Array2;
+use std::fs;
+
+fn grid(rows: usize, cols: usize) -> (Array2<char>, Array2<Rgb<u8>>) {
+    let chars = ['@', '#', '+', '.', ' '];
+    let grid = Array2::from_shape_fn((rows, cols), |(y, x)| chars[(y + x) % chars.len()]);
+    let colors = Array2::from_shape_fn((rows, cols), |(_, x)| Rgb([(x * 20) as u8, 0, 0]));
+    (grid, colors)
+}
+
+fn export(exporter: &TextExporter, rows: usize, cols: usize) -> Result<String, ConvertError> {
+    let (grid, colors) = grid(rows, cols);
+    exporter.export(&grid.view(), &colors.view(), Rgb([0, 0, 0]), None)
+}
+
+#[test]
+fn plain_export_matches_grid_rows() {
+    let text = export(&TextExporter::new(), 3, 5).unwrap();
+    assert_eq!(text, "@#+. \n#+. @\n+. @#\n");
+}
+
+#[test]
+fn crlf_ends_every_line() {
+    let exporter = TextExporter::new().with_line_ending(LineEnding::CrLf);
+    let text = export(&exporter, 4, 6).unwrap();
+    assert_eq!(text.matches("\r\n").count(), 4);
+    assert_eq!(text.matches('\n').count(), 4);
+    assert!(text.ends_with("\r\n"));
+}
+
+#[test]
+fn wrapping_splits_rows_into_marked_lines() {
+    // 10 columns at 4 per line with a marker leaves 3 cells per line, so 4 lines per row
+    let exporter = TextExporter::new().with_max_columns(Some(4));
+    let text = export(&exporter, 2, 10).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 8);
+    assert_eq!(lines[0], "@#+\\");
+    assert_eq!(lines[3], " ");
+    assert!(lines.iter().all(|line| line.chars().count() <= 4));
+
+    let exporter = exporter.with_overflow(Overflow::Wrap { marker: None });
+    let text = export(&exporter, 2, 10).unwrap();
+    assert_eq!(text.lines().count(), 6);
+    assert_eq!(text.lines().next(), Some("@#+."));
+}
+
+#[test]
+fn rows_that_fit_are_not_wrapped() {
+    let exporter = TextExporter::new().with_max_columns(Some(5));
+    assert_eq!(export(&exporter, 3, 5).unwrap(), "@#+. \n#+. @\n+. @#\n");
+}
+
+#[test]
+fn overflow_error_rejects_wide_grids() {
+    let exporter = TextExporter::new()
+        .with_max_columns(Some(4))
+        .with_overflow(Overflow::Error);
+    assert!(matches!(
+        export(&exporter, 2, 10),
+        Err(ConvertError::GridTooWide { width: 10, max: 4 })
+    ));
+    assert!(export(&exporter, 2, 4).is_ok());
+
+    let no_room = TextExporter::new().with_max_columns(Some(1));
+    assert!(matches!(
+        export(&no_room, 2, 10),
+        Err(ConvertError::InvalidSetting {
+            field: "max_columns",
+            ..
+        })
+    ));
+}
+
+#[test]
+fn ansi_lines_stand_on_their_own() {
+    let exporter = TextExporter::new()
+        .with_ansi(true)
+        .with_max_columns(Some(4));
+    let text = export(&exporter, 1, 10).unwrap();
+    for line in text.lines() {
+        assert!(line.starts_with("\x1b[48;2;0;0;0m"));
+        assert!(line.ends_with("\x1b[0m"));
+    }
+    assert!(text.contains("\x1b[38;2;20;0;0m#"));
+}
+
+#[test]
+fn header_prefixes_settings_lines() {
+    let (grid, colors) = grid(2, 3);
+    let exporter = TextExporter::new()
+        .with_header(true)
+        .with_line_ending(LineEnding::CrLf);
+    let text = exporter
+        .export(
+            &grid.view(),
+            &colors.view(),
+            Rgb([0, 0, 0]),
+            Some("font_size = 8\nedge_threshold = 0.5"),
+        )
+        .unwrap();
+    assert_eq!(
+        text,
+        "# font_size = 8\r\n# edge_threshold = 0.5\r\n@#+\r\n#+.\r\n"
+    );
+
+    let without = TextExporter::new()
+        .export(&grid.view(), &colors.view(), Rgb([0, 0, 0]), Some("x"))
+        .unwrap();
+    assert_eq!(without, "@#+\n#+.\n");
+}
+
+#[test]
+fn converter_writes_text_file_with_its_settings() {
+    let input = std::env::temp_dir().join(format!("ruscii-gen-{}-txt-in.png", std::process::id()));
+    let out = std::env::temp_dir().join(format!("ruscii-gen-{}-txt-out.txt", std::process::id()));
+    let size = common::FONT_SIZE * 6;
+    common::circle(size, size).save(&input).unwrap();
+
+    let exporter = TextExporter::new()
+        .with_header(true)
+        .with_line_ending(LineEnding::CrLf);
+    common::test_converter()
+        .convert_to_txt(
+            input.to_str().unwrap(),
+            out.to_str().unwrap(),
+            0.3,
+            &exporter,
+        )
+        .unwrap();
+    let text = fs::read_to_string(&out).unwrap();
+    let grid: Vec<&str> = text
+        .split("\r\n")
+        .filter(|l| !l.is_empty() && !l.starts_with('#'))
+        .collect();
+    assert_eq!(grid.len(), 6);
+    assert!(grid.iter().all(|line| line.chars().count() == 6));
+    #[cfg(feature = "serde")]
+    assert!(text.contains("# edge_threshold = 0.3"));
+}
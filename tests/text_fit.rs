@@ -0,0 +1,150 @@
+/*
+* Text outputs fitted to a character budget, such as a post or a fixed width code block
+*/
+mod common;
+
+use ascii_gen::ascii::config::ConverterConfig;
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::ascii::target::{OutputTarget, TargetData, TERMINAL_CELL_ASPECT};
+use ascii_gen::output::OutputFormat;
+use image::{DynamicImage, ImageFormat};
+use std::io::Cursor;
+
+// Wide, square and tall inputs
+const SIZES: [(u32, u32); 5] = [(640, 120), (300, 200), (256, 256), (180, 320), (90, 600)];
+
+fn png_bytes(img: &DynamicImage) -> Vec<u8> {
+    let mut bytes = vec![];
+    img.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+        .unwrap();
+    bytes
+}
+
+fn convert(font_size: u32, bytes: &[u8], format: OutputFormat) -> String {
+    let converter = ConverterConfig {
+        font_size,
+        ..common::test_config()
+    }
+    .build()
+    .unwrap();
+    let (out, _) = converter
+        .convert_bytes_with_stats(bytes, format, 0.0)
+        .unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+#[test]
+fn fitted_text_never_exceeds_the_budget() {
+    let converter = common::test_converter();
+    for (w, h) in SIZES {
+        let bytes = png_bytes(&common::noise(w, h, w + h));
+        for (target, format) in [
+            (OutputTarget::Txt { cell_aspect: 1.0 }, OutputFormat::Txt),
+            (OutputTarget::Ansi { cell_aspect: 1.0 }, OutputFormat::Ansi),
+        ] {
+            for budget in [280, 1500] {
+                let fit = converter.fit_text_output(w, h, target, budget).unwrap();
+                assert!(fit.max_len <= budget);
+                let text = convert(fit.font_size, &bytes, format);
+                let len = text.chars().count();
+                assert!(
+                    len <= budget,
+                    "{}x{} {:?}: {} > {}",
+                    w,
+                    h,
+                    target,
+                    len,
+                    budget
+                );
+                assert_eq!(text.lines().count(), fit.rows as usize);
+
+                // The next larger grid would not have been guaranteed to fit
+                if fit.font_size > 1 {
+                    let cols = w / (fit.font_size - 1);
+                    let rows = h / (fit.font_size - 1);
+                    let larger = target.max_text_len(rows as usize, cols as usize).unwrap();
+                    assert!(larger > budget);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn plain_text_fills_most_of_the_budget() {
+    // Without escapes the bound is exact, rows of cols characters and a newline
+    let converter = common::test_converter();
+    let fit = converter
+        .fit_text_output(300, 200, OutputTarget::Txt { cell_aspect: 1.0 }, 280)
+        .unwrap();
+    assert_eq!(fit.max_len, (fit.cols as usize + 1) * fit.rows as usize);
+    assert!(fit.max_len > 200, "{:?}", fit);
+}
+
+#[test]
+fn columns_fit_a_code_block() {
+    let converter = common::test_converter();
+    for (w, h) in SIZES {
+        let bytes = png_bytes(&common::gradient(w, h));
+        let fit = converter
+            .fit_columns(w, h, OutputTarget::Txt { cell_aspect: 1.0 }, 100)
+            .unwrap();
+        assert!(fit.cols <= 100);
+        let text = convert(fit.font_size, &bytes, OutputFormat::Txt);
+        assert!(text.lines().all(|line| line.chars().count() <= 100));
+        assert!(fit.font_size == 1 || w / (fit.font_size - 1) > 100);
+    }
+}
+
+#[test]
+fn terminal_cells_keep_the_image_proportions() {
+    let converter = common::test_converter();
+    let target = OutputTarget::txt();
+    let fit = converter.fit_text_output(400, 300, target, 2000).unwrap();
+    let seen = fit.cols as f32 * TERMINAL_CELL_ASPECT / fit.rows as f32;
+    assert!((seen - 4.0 / 3.0).abs() < 0.15, "{:?}", fit);
+
+    let dir = std::env::temp_dir().join(format!("ruscii-gen-{}-text-fit", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let input = dir.join("input.png");
+    common::circle(400, 300)
+        .save_with_format(&input, ImageFormat::Png)
+        .unwrap();
+    let fitted = ConverterConfig {
+        font_size: fit.font_size,
+        ..common::test_config()
+    }
+    .build()
+    .unwrap();
+    let outputs = fitted.convert_all_formats(&input, 0.0, &[target]).unwrap();
+    let TargetData::Text(text) = &outputs[0].data else {
+        panic!("txt made an image");
+    };
+    assert!(text.chars().count() <= 2000);
+}
+
+#[test]
+fn budgets_below_the_smallest_grid_fail() {
+    let converter = common::test_converter();
+    let result = converter.fit_text_output(64, 640, OutputTarget::Txt { cell_aspect: 1.0 }, 12);
+    // One column of ten rows, every row a character and a newline
+    assert!(matches!(
+        result,
+        Err(ConvertError::TextBudgetTooSmall {
+            budget: 12,
+            needed: 20
+        })
+    ));
+    assert!(result.unwrap_err().to_string().contains("too small"));
+    assert!(matches!(
+        converter.fit_columns(64, 64, OutputTarget::Txt { cell_aspect: 1.0 }, 0),
+        Err(ConvertError::TextBudgetTooSmall { needed: 1, .. })
+    ));
+    assert!(matches!(
+        converter.fit_text_output(64, 64, OutputTarget::Png, 280),
+        Err(ConvertError::InvalidSetting {
+            field: "target",
+            ..
+        })
+    ));
+}
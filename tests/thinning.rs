@@ -0,0 +1,88 @@
+/*
+* Thinning reduces bands to connected 1 pixel skeletons and terminates on solid input
+*/
+use ascii_gen::image_manip::processing::{Processor, Thin};
+use image::{GrayImage, Luma};
+
+const W: u32 = 40;
+const H: u32 = 21;
+
+fn foreground(img: &GrayImage) -> Vec<(u32, u32)> {
+    img.enumerate_pixels()
+        .filter(|(_, _, p)| p[0] != 0)
+        .map(|(x, y, _)| (x, y))
+        .collect()
+}
+
+// Size of the 8-connected component containing `start`
+fn component_size(img: &GrayImage, start: (u32, u32)) -> usize {
+    let mut seen = vec![false; (W * H) as usize];
+    let mut stack = vec![start];
+    seen[(start.1 * W + start.0) as usize] = true;
+    let mut size = 0;
+    while let Some((x, y)) = stack.pop() {
+        size += 1;
+        for dy in -1i64..=1 {
+            for dx in -1i64..=1 {
+                let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+                if nx < 0 || ny < 0 || nx >= W as i64 || ny >= H as i64 {
+                    continue;
+                }
+                let (nx, ny) = (nx as u32, ny as u32);
+                let idx = (ny * W + nx) as usize;
+                if !seen[idx] && img.get_pixel(nx, ny)[0] != 0 {
+                    seen[idx] = true;
+                    stack.push((nx, ny));
+                }
+            }
+        }
+    }
+    size
+}
+
+#[test]
+fn thick_line_thins_to_one_pixel() {
+    // 5 pixel thick horizontal band from x = 5 to x = 34
+    let img = GrayImage::from_fn(W, H, |x, y| {
+        if (5..35).contains(&x) && (8..13).contains(&y) {
+            Luma([200])
+        } else {
+            Luma([0])
+        }
+    });
+    let thin = Thin::new().apply(&img).unwrap();
+    let pixels = foreground(&thin);
+    assert!(!pixels.is_empty());
+
+    // Away from the line ends every column holds exactly one pixel
+    for x in 8..32 {
+        let column = pixels.iter().filter(|&&(px, _)| px == x).count();
+        assert_eq!(column, 1, "column {} is {} pixels wide", x, column);
+    }
+
+    // Skeleton stays one piece reaching from end to end
+    assert_eq!(component_size(&thin, pixels[0]), pixels.len());
+    let min_x = pixels.iter().map(|p| p.0).min().unwrap();
+    let max_x = pixels.iter().map(|p| p.0).max().unwrap();
+    assert!(
+        min_x <= 8 && max_x >= 31,
+        "skeleton spans {}..={}",
+        min_x,
+        max_x
+    );
+
+    // Kept pixels retain their value
+    assert!(pixels.iter().all(|&(x, y)| thin.get_pixel(x, y)[0] == 200));
+}
+
+#[test]
+fn solid_and_empty_images_terminate() {
+    let solid = GrayImage::from_pixel(W, H, Luma([255]));
+    let thin = Thin::new().apply(&solid).unwrap();
+    let pixels = foreground(&thin);
+    assert!(!pixels.is_empty());
+    assert_eq!(component_size(&thin, pixels[0]), pixels.len());
+
+    let empty = GrayImage::new(W, H);
+    assert!(foreground(&Thin::new().apply(&empty).unwrap()).is_empty());
+}
@@ -0,0 +1,131 @@
+/*
+* Every threshold mode on a ramp of known values
+*/
+use ascii_gen::ascii::config::{ProcessorConfig, ThresholdModeConfig};
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::image_manip::processing::{Processor, Threshold, ThresholdMode};
+use image::{GrayImage, ImageBuffer, Luma};
+
+const RAMP: [u8; 8] = [0, 10, 49, 50, 51, 100, 200, 255];
+
+fn ramp() -> GrayImage {
+    ImageBuffer::from_fn(RAMP.len() as u32, 1, |x, _| Luma([RAMP[x as usize]]))
+}
+
+fn apply(threshold: &Threshold) -> Vec<u8> {
+    Processor::<u8, u8>::apply(threshold, &ramp())
+        .unwrap()
+        .into_raw()
+}
+
+#[test]
+fn imageproc_modes_compare_against_the_threshold() {
+    let cases = [
+        (ThresholdMode::Binary, [0, 0, 0, 0, 255, 255, 255, 255]),
+        (
+            ThresholdMode::BinaryInverted,
+            [255, 255, 255, 255, 0, 0, 0, 0],
+        ),
+        (ThresholdMode::Truncate, [0, 10, 49, 50, 50, 50, 50, 50]),
+        // imageproc swaps the two to zero modes relative to their usual meaning
+        (ThresholdMode::ToZero, [0, 10, 49, 50, 0, 0, 0, 0]),
+        (
+            ThresholdMode::ToZeroInverted,
+            [0, 0, 0, 0, 51, 100, 200, 255],
+        ),
+    ];
+    for (mode, expected) in cases {
+        let threshold = Threshold::new(50).with_mode(mode);
+        assert_eq!(apply(&threshold), expected, "{:?}", mode);
+    }
+}
+
+#[test]
+fn default_mode_is_unchanged() {
+    assert_eq!(Threshold::new(50).mode, ThresholdMode::ToZeroInverted);
+    assert_eq!(Threshold::default().threshold, 10);
+}
+
+#[test]
+fn binary_constructor() {
+    assert_eq!(
+        apply(&Threshold::binary(50)),
+        [0, 0, 0, 0, 255, 255, 255, 255]
+    );
+}
+
+#[test]
+fn band_keeps_inclusive_range() {
+    assert_eq!(
+        apply(&Threshold::band(10, 100)),
+        [0, 10, 49, 50, 51, 100, 0, 0]
+    );
+    assert_eq!(apply(&Threshold::band(50, 50)), [0, 0, 0, 50, 0, 0, 0, 0]);
+    assert_eq!(apply(&Threshold::band(0, 255)), RAMP);
+}
+
+#[test]
+fn inverted_band_is_rejected() {
+    assert!(matches!(
+        Processor::<u8, u8>::validate(&Threshold::band(100, 10)),
+        Err(ConvertError::InvalidSetting {
+            field: "threshold.band",
+            ..
+        })
+    ));
+}
+
+#[test]
+fn f32_threshold_supports_default_and_band_only() {
+    let values = image::ImageBuffer::from_raw(4, 1, vec![-120.0f32, -20.0, 20.0, 120.0]).unwrap();
+    let band = Processor::<f32, f32>::apply(&Threshold::band(10, 100), &values).unwrap();
+    assert_eq!(band.into_raw(), [0.0, -20.0, 20.0, 0.0]);
+
+    let binary = Threshold::binary(50);
+    assert!(matches!(
+        Processor::<f32, f32>::validate(&binary),
+        Err(ConvertError::InvalidSetting {
+            field: "threshold.mode",
+            ..
+        })
+    ));
+    assert!(Processor::<f32, f32>::apply(&binary, &values).is_err());
+}
+
+#[test]
+fn config_builds_the_mode() {
+    let config = ProcessorConfig::Threshold {
+        threshold: 0,
+        mode: ThresholdModeConfig::Band { low: 10, high: 100 },
+    };
+    let threshold = config.build();
+    assert_eq!(
+        threshold.apply(&ramp()).unwrap().into_raw(),
+        [0, 10, 49, 50, 51, 100, 0, 0]
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn mode_defaults_when_missing_from_toml() {
+    use ascii_gen::ascii::config::ConverterConfig;
+
+    let config = ConverterConfig::from_toml(
+        "[[edge_preprocessors]]\ntype = \"threshold\"\nthreshold = 5\n\n\
+         [[edge_preprocessors]]\ntype = \"threshold\"\nthreshold = 0\nmode = { type = \"band\", low = 1, high = 9 }\n",
+    )
+    .unwrap();
+    assert_eq!(
+        config.edge_preprocessors,
+        vec![
+            ProcessorConfig::Threshold {
+                threshold: 5,
+                mode: ThresholdModeConfig::ToZeroInverted,
+            },
+            ProcessorConfig::Threshold {
+                threshold: 0,
+                mode: ThresholdModeConfig::Band { low: 1, high: 9 },
+            },
+        ]
+    );
+}
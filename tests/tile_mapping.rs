@@ -0,0 +1,64 @@
+/*
+* Variance aware tile mapping gives busy tiles a busier character than flat tiles of the same mean
+*/
+mod common;
+
+use ascii_gen::ascii::char_set::CharacterSet;
+use ascii_gen::ascii::config::{ConverterConfig, TileMappingConfig};
+use image::{DynamicImage, GrayImage, Luma};
+
+const FS: u32 = common::FONT_SIZE;
+
+// Checkerboard tile on the left, uniform 50% gray tile on the right
+fn checker_and_gray() -> DynamicImage {
+    DynamicImage::ImageLuma8(GrayImage::from_fn(FS * 2, FS, |x, y| {
+        if x >= FS {
+            Luma([128])
+        } else if (x + y) % 2 == 0 {
+            Luma([0])
+        } else {
+            Luma([255])
+        }
+    }))
+}
+
+fn tile_chars(tile_mapping: TileMappingConfig) -> (usize, usize) {
+    let text = ConverterConfig {
+        tile_mapping,
+        draw_edges: false,
+        ..common::test_config()
+    }
+    .build()
+    .unwrap()
+    .convert_to_text(&checker_and_gray(), 0.0)
+    .unwrap();
+    let chars: Vec<char> = text.lines().next().unwrap().chars().collect();
+    assert_eq!(chars.len(), 2);
+    let charset = CharacterSet::default();
+    (
+        charset.find_tile_char_index(&chars[0]).unwrap(),
+        charset.find_tile_char_index(&chars[1]).unwrap(),
+    )
+}
+
+#[test]
+fn checkerboard_is_busier_than_flat_gray() {
+    // By luminance alone the two tiles land in neighboring buckets at most
+    let (checker, gray) = tile_chars(TileMappingConfig::Luminance);
+    assert!(checker.abs_diff(gray) <= 1);
+
+    let (checker, gray) = tile_chars(TileMappingConfig::LuminanceAndVariance {
+        variance_weight: 0.5,
+    });
+    assert!(checker > gray + 1, "checker {} gray {}", checker, gray);
+}
+
+#[test]
+fn zero_weight_matches_luminance() {
+    assert_eq!(
+        tile_chars(TileMappingConfig::LuminanceAndVariance {
+            variance_weight: 0.0
+        }),
+        tile_chars(TileMappingConfig::Luminance)
+    );
+}
@@ -0,0 +1,100 @@
+/*
+* Exact box averaging keeps fine patterns free of the moire a Triangle resize leaves behind
+*/
+mod common;
+
+use ascii_gen::ascii::char_set::quantize_luma;
+use ascii_gen::ascii::config::{ConverterConfig, TileSamplingConfig};
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::image_manip::tile_stats::{box_average, box_average_rect, trimmed_mean};
+use image::{DynamicImage, GrayImage, Luma};
+
+const FS: u32 = common::FONT_SIZE;
+
+// One pixel wide black and white vertical stripes, slightly wider than a whole number of cells
+fn stripes() -> DynamicImage {
+    DynamicImage::ImageLuma8(GrayImage::from_fn(FS * 16 + 3, FS * 2, |x, _| {
+        Luma([if x % 2 == 0 { 255 } else { 0 }])
+    }))
+}
+
+fn row_chars(tile_sampling: TileSamplingConfig) -> Vec<char> {
+    let text = ConverterConfig {
+        tile_sampling,
+        draw_edges: false,
+        ..common::test_config()
+    }
+    .build()
+    .unwrap()
+    .convert_to_text(&stripes(), 0.0)
+    .unwrap();
+    text.lines().next().unwrap().chars().collect()
+}
+
+#[test]
+fn box_average_is_the_block_mean() {
+    let gray = GrayImage::from_fn(10, 4, |x, _| Luma([x as u8 * 10]));
+    let avg = box_average(&gray, 4, (1, 3));
+    // Blocks over x = 0..4, 4..8 and the cut short 8..10
+    assert_eq!(avg.into_raw(), vec![15, 55, 85]);
+}
+
+#[test]
+fn rectangular_blocks_average_their_own_rows() {
+    let gray = GrayImage::from_fn(4, 8, |x, y| Luma([(y * 20 + x) as u8]));
+    let avg = box_average_rect(&gray, (4, 2), (2, 2));
+    // Blocks two pixels wide over rows 0..4 and 4..8
+    assert_eq!(avg.into_raw(), vec![31, 33, 111, 113]);
+}
+
+#[test]
+fn stripes_average_evenly() {
+    let exact = row_chars(TileSamplingConfig::ExactBoxAverage);
+    assert_eq!(exact.len(), 16);
+    assert!(exact.iter().all(|&c| c == exact[0]), "{:?}", exact);
+
+    let resized = row_chars(TileSamplingConfig::Resize);
+    assert!(resized.iter().any(|&c| c != resized[0]), "{:?}", resized);
+}
+
+// A 4x4 tile of zeros with a single hot pixel
+fn hot_pixel_tile() -> GrayImage {
+    GrayImage::from_fn(4, 4, |x, y| Luma([if (x, y) == (2, 1) { 255 } else { 0 }]))
+}
+
+#[test]
+fn trimming_drops_a_hot_pixel() {
+    const LEVELS: usize = 70;
+    let trimmed = trimmed_mean(&hot_pixel_tile(), 4, (1, 1), 0.1).get_pixel(0, 0)[0];
+    assert_eq!(quantize_luma(trimmed, LEVELS), 0);
+
+    let plain = trimmed_mean(&hot_pixel_tile(), 4, (1, 1), 0.0).get_pixel(0, 0)[0];
+    assert_eq!(plain, 16);
+    assert!(quantize_luma(plain, LEVELS) > 0);
+}
+
+#[test]
+fn untrimmed_mean_matches_the_box_average() {
+    let gray = GrayImage::from_fn(10, 4, |x, y| Luma([(x * 25 + y * 7) as u8]));
+    assert_eq!(
+        trimmed_mean(&gray, 4, (1, 3), 0.0),
+        box_average(&gray, 4, (1, 3))
+    );
+}
+
+#[test]
+fn trim_must_leave_pixels() {
+    let err = ConverterConfig {
+        tile_sampling: TileSamplingConfig::TrimmedMean { trim: 0.5 },
+        ..common::test_config()
+    }
+    .build()
+    .err();
+    assert!(matches!(
+        err,
+        Some(ConvertError::InvalidSetting {
+            field: "trimmed_mean.trim",
+            ..
+        })
+    ));
+}
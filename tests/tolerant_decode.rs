@@ -0,0 +1,115 @@
+/*
+* JPEGs cut short fail to decode unless decoding is tolerant, which converts what is there
+*/
+mod common;
+
+use ascii_gen::ascii::config::ConverterConfig;
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::ascii::warning::ConvertWarning;
+use ascii_gen::input::decode::decode;
+use ascii_gen::output::OutputFormat;
+use image::{DynamicImage, GenericImageView, ImageFormat};
+use std::fs;
+use std::io::Cursor;
+use std::path::PathBuf;
+
+const FS: u32 = common::FONT_SIZE;
+
+fn scratch(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!(
+        "ruscii-gen-{}-tolerant-{}",
+        std::process::id(),
+        name
+    ))
+}
+
+fn jpeg() -> (DynamicImage, Vec<u8>) {
+    let img = DynamicImage::ImageRgb8(common::circle(FS * 24, FS * 24).to_rgb8());
+    let mut bytes = vec![];
+    img.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Jpeg)
+        .unwrap();
+    (img, bytes)
+}
+
+fn truncated(share: f32) -> Vec<u8> {
+    let (_, bytes) = jpeg();
+    bytes[..(bytes.len() as f32 * share) as usize].to_vec()
+}
+
+fn tolerant() -> ConverterConfig {
+    ConverterConfig {
+        tolerant_decode: true,
+        ..common::test_config()
+    }
+}
+
+#[test]
+fn strict_decoding_rejects_truncated_jpegs() {
+    for share in [0.3, 0.6, 0.95] {
+        let result =
+            common::test_converter().convert_bytes(&truncated(share), OutputFormat::Txt, 0.0);
+        assert!(
+            matches!(result, Err(ConvertError::TruncatedImage { ref format }) if format == "JPEG"),
+            "{} {:?}",
+            share,
+            result.err()
+        );
+    }
+}
+
+#[test]
+fn tolerant_decoding_converts_truncated_jpegs_with_a_warning() {
+    let input = scratch("cut.jpg");
+    fs::write(&input, truncated(0.6)).unwrap();
+    let out = scratch("cut.png");
+    let stats = tolerant()
+        .build()
+        .unwrap()
+        .convert_img_with_stats(input.to_str().unwrap(), out.to_str().unwrap(), 0.0)
+        .unwrap();
+    assert!(stats.warnings.contains(&ConvertWarning::PartialDecode {
+        format: "JPEG".to_string()
+    }));
+    assert!(image::open(&out).is_ok());
+    fs::remove_file(&input).unwrap();
+    fs::remove_file(&out).unwrap();
+
+    let (_, stats) = tolerant()
+        .build()
+        .unwrap()
+        .convert_bytes_with_stats(&truncated(0.3), OutputFormat::Txt, 0.0)
+        .unwrap();
+    assert_eq!(stats.warnings.len(), 1);
+}
+
+#[test]
+fn partial_decode_keeps_the_rows_that_were_there() {
+    let (img, _) = jpeg();
+    let decoded = decode(&truncated(0.6), None, true).unwrap();
+    assert!(!decoded.warnings.is_empty());
+    assert_eq!(decoded.image.dimensions(), (img.width(), img.height()));
+
+    // The top rows come from the data, close to the original up to compression
+    let (original, partial) = (img.to_luma8(), decoded.image.to_luma8());
+    let rows = FS * 4;
+    let diff: u64 = (0..rows)
+        .flat_map(|y| (0..img.width()).map(move |x| (x, y)))
+        .map(|(x, y)| original.get_pixel(x, y)[0].abs_diff(partial.get_pixel(x, y)[0]) as u64)
+        .sum();
+    assert!(diff / ((rows * img.width()) as u64) < 8, "{}", diff);
+}
+
+#[test]
+fn complete_inputs_decode_without_a_warning() {
+    let (_, bytes) = jpeg();
+    assert!(decode(&bytes, None, true).unwrap().warnings.is_empty());
+    assert!(decode(&bytes, None, false).unwrap().warnings.is_empty());
+    // Tolerance does not make anything decodable
+    assert!(decode(b"not an image at all", None, true).is_err());
+    let mut png = vec![];
+    common::circle(64, 64)
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .unwrap();
+    png.truncate(png.len() / 2);
+    assert!(decode(&png, None, true).is_err());
+}
@@ -0,0 +1,113 @@
+/*
+* Tone curves remapping the tile luminance before quantization
+*/
+mod common;
+
+use ascii_gen::ascii::config::ConverterConfig;
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::ascii::tone_curve::ToneCurve;
+use image::{DynamicImage, GrayImage, Luma};
+
+const FS: u32 = common::FONT_SIZE;
+
+fn assert_monotone(curve: &ToneCurve) {
+    for pair in curve.lut().windows(2) {
+        assert!(pair[1] >= pair[0], "{:?}", curve.lut());
+    }
+}
+
+#[test]
+fn lut_passes_through_the_points_and_stays_monotone() {
+    let point_sets: [&[(u8, u8)]; 4] = [
+        &[(0, 0), (64, 90), (255, 255)],
+        &[(0, 0), (255, 255)],
+        &[(0, 30), (40, 31), (60, 200), (200, 210), (255, 255)],
+        &[(10, 0), (128, 128), (128 + 64, 128), (240, 250)],
+    ];
+    for points in point_sets {
+        let curve = ToneCurve::from_points(points).unwrap();
+        for &(x, y) in points {
+            assert_eq!(curve.apply(x), y, "{:?} at {}", points, x);
+        }
+        assert_monotone(&curve);
+        // Flat beyond the first and last points
+        let (first, last) = (points[0], points[points.len() - 1]);
+        assert!(curve.lut()[..=first.0 as usize]
+            .iter()
+            .all(|&y| y == first.1));
+        assert!(curve.lut()[last.0 as usize..].iter().all(|&y| y == last.1));
+    }
+}
+
+#[test]
+fn identity_curve_keeps_every_luminance() {
+    let curve = ToneCurve::from_points(&[(0, 0), (128, 128), (255, 255)]).unwrap();
+    assert!((0..=255u8).all(|l| curve.apply(l) == l));
+}
+
+#[test]
+fn lifted_point_lifts_the_shadows_around_it() {
+    let curve = ToneCurve::from_points(&[(0, 0), (64, 90), (255, 255)]).unwrap();
+    assert!(curve.apply(32) > 32);
+    assert!(curve.apply(128) > 128);
+}
+
+#[test]
+fn bad_point_sets_are_rejected() {
+    for points in [
+        vec![(0, 0)],
+        vec![(0, 0), (128, 200), (255, 100)],
+        vec![(0, 0), (128, 100), (128, 120), (255, 255)],
+        vec![(128, 0), (0, 255)],
+    ] {
+        assert!(
+            matches!(
+                ToneCurve::from_points(&points),
+                Err(ConvertError::InvalidSetting {
+                    field: "tone_curve",
+                    ..
+                })
+            ),
+            "{:?}",
+            points
+        );
+    }
+}
+
+#[test]
+fn curves_parse_from_the_cli_syntax() {
+    let curve = ToneCurve::parse("0:0, 64:90,255:255").unwrap();
+    assert_eq!(curve.points(), &[(0, 0), (64, 90), (255, 255)]);
+    for spec in ["", "0:0", "0-0,255:255", "0:0,300:255", "0:0,a:255"] {
+        assert!(ToneCurve::parse(spec).is_err(), "{}", spec);
+    }
+}
+
+#[test]
+fn converter_quantizes_the_remapped_luminance() {
+    // Every cell at luminance 64, which the curve moves to 200
+    let flat =
+        |luma: u8| DynamicImage::ImageLuma8(GrayImage::from_pixel(FS * 4, FS * 4, Luma([luma])));
+    let base = ConverterConfig {
+        draw_edges: false,
+        ..common::test_config()
+    };
+    let curved = ConverterConfig {
+        tone_curve: Some(vec![(0, 0), (64, 200), (255, 255)]),
+        ..base.clone()
+    };
+    let plain = base.build().unwrap();
+    let lifted = curved
+        .build()
+        .unwrap()
+        .convert_to_text(&flat(64), 0.0)
+        .unwrap();
+    assert_ne!(plain.convert_to_text(&flat(64), 0.0).unwrap(), lifted);
+    assert_eq!(plain.convert_to_text(&flat(200), 0.0).unwrap(), lifted);
+
+    let invalid = ConverterConfig {
+        tone_curve: Some(vec![(0, 255), (255, 0)]),
+        ..common::test_config()
+    };
+    assert!(invalid.build().is_err());
+}
@@ -0,0 +1,72 @@
+/*
+* The pipeline stages show up as tracing spans in the order they run
+*/
+#![cfg(feature = "tracing")]
+
+mod common;
+
+use ascii_gen::ascii::config::{ConverterConfig, EdgeSmoothingConfig};
+use ascii_gen::output::OutputFormat;
+use std::io::Cursor;
+use std::sync::{Arc, Mutex};
+use tracing::span::{Attributes, Id};
+use tracing::Subscriber;
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::prelude::*;
+
+// Records the name of every span as it is created
+struct SpanRecorder {
+    names: Arc<Mutex<Vec<&'static str>>>,
+}
+
+impl<S: Subscriber> Layer<S> for SpanRecorder {
+    fn on_new_span(&self, attrs: &Attributes<'_>, _id: &Id, _ctx: Context<'_, S>) {
+        self.names.lock().unwrap().push(attrs.metadata().name());
+    }
+}
+
+#[test]
+fn pipeline_spans_are_emitted_in_order() {
+    let mut png = Cursor::new(vec![]);
+    common::circle(64, 48)
+        .write_to(&mut png, image::ImageFormat::Png)
+        .unwrap();
+    let converter = ConverterConfig {
+        // Off by default, turned on so its span shows up
+        edge_smoothing: EdgeSmoothingConfig {
+            radius: 1,
+            min_disagreement: 2,
+        },
+        ..common::test_config()
+    }
+    .build()
+    .unwrap();
+
+    let names = Arc::new(Mutex::new(vec![]));
+    let subscriber = tracing_subscriber::registry().with(SpanRecorder {
+        names: names.clone(),
+    });
+    tracing::subscriber::with_default(subscriber, || {
+        converter
+            .convert_bytes(png.get_ref(), OutputFormat::Png, 0.0)
+            .unwrap();
+    });
+
+    let names = names.lock().unwrap();
+    assert_eq!(
+        *names,
+        [
+            "decode",
+            "resize",
+            "preprocess",
+            "preprocess",
+            "preprocess",
+            "preprocess",
+            "edge_detect",
+            "downscale",
+            "edge_smoothing",
+            "render",
+            "encode"
+        ]
+    );
+}
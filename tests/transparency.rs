@@ -0,0 +1,86 @@
+/*
+* Transparent pixels, as paletted GIFs and PNGs with a transparency index decode to, weigh nothing
+* in the cell colors
+*/
+mod common;
+
+use ascii_gen::ascii::config::ConverterConfig;
+use ascii_gen::ascii::converter::Converter;
+use image::{DynamicImage, Rgb, Rgba, RgbaImage};
+
+const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+// Transparency index entries usually hold black
+const CLEAR: Rgba<u8> = Rgba([0, 0, 0, 0]);
+
+fn converter(linear_resize: bool) -> Converter {
+    ConverterConfig {
+        linear_resize,
+        use_image_color: true,
+        bg_color: [10, 20, 30],
+        ..common::test_config()
+    }
+    .build()
+    .unwrap()
+}
+
+fn cell_colors(converter: &Converter, img: &DynamicImage) -> Vec<Rgb<u8>> {
+    let prepared = converter.prepare(img).unwrap();
+    converter
+        .cell_colors(&prepared.resized)
+        .into_iter()
+        .collect()
+}
+
+#[test]
+fn a_mostly_transparent_cell_samples_its_opaque_color() {
+    // One cell, a tenth of its pixels red and the rest transparent
+    let fs = common::FONT_SIZE;
+    let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(fs, fs * 2, |x, y| {
+        if (x + y * fs).is_multiple_of(10) {
+            RED
+        } else {
+            CLEAR
+        }
+    }));
+    for linear_resize in [false, true] {
+        let colors = cell_colors(&converter(linear_resize), &img);
+        assert_eq!(colors, [Rgb([255, 0, 0]); 2], "linear {}", linear_resize);
+    }
+}
+
+#[test]
+fn fully_transparent_cells_take_the_background() {
+    let fs = common::FONT_SIZE;
+    // Two clear cells keep the filter of the first one away from the opaque third
+    let img = DynamicImage::ImageRgba8(RgbaImage::from_fn(fs * 3, fs, |x, _| {
+        if x >= fs * 2 {
+            Rgba([0, 0, 255, 255])
+        } else {
+            CLEAR
+        }
+    }));
+    for linear_resize in [false, true] {
+        let colors = cell_colors(&converter(linear_resize), &img);
+        assert_eq!(colors[0], Rgb([10, 20, 30]), "linear {}", linear_resize);
+        assert_eq!(colors[2], Rgb([0, 0, 255]), "linear {}", linear_resize);
+    }
+}
+
+#[test]
+fn opaque_alpha_samples_like_no_alpha() {
+    let img = common::noise(common::FONT_SIZE * 6, common::FONT_SIZE * 4, 9).to_rgb8();
+    let converter = converter(false);
+    let opaque = cell_colors(
+        &converter,
+        &DynamicImage::ImageRgba8(DynamicImage::ImageRgb8(img.clone()).to_rgba8()),
+    );
+    let plain = cell_colors(&converter, &DynamicImage::ImageRgb8(img));
+    for (a, b) in opaque.iter().zip(&plain) {
+        assert!(
+            a.0.iter().zip(b.0).all(|(&a, b)| a.abs_diff(b) <= 1),
+            "{:?} {:?}",
+            a,
+            b
+        );
+    }
+}
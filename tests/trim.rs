@@ -0,0 +1,150 @@
+/*
+* Trimming of the blank space around the art in text outputs
+*/
+mod common;
+
+use ascii_gen::ascii::config::{ConverterConfig, TrimModeConfig};
+use ascii_gen::output::trim::{trimmed_ansi, trimmed_text, TrimMode, TrimOffsets};
+use ascii_gen::output::OutputFormat;
+use image::{DynamicImage, GrayImage, ImageFormat, Luma, Rgb};
+use ndarray::Array2;
+use std::io::Cursor;
+
+// 10 x 12 grid of spaces holding a figure on rows 4..=5 and columns 5..=7, with a gap in it
+fn figure() -> Array2<char> {
+    let mut grid = Array2::from_elem((10, 12), ' ');
+    for (y, x, ch) in [
+        (4, 5, '@'),
+        (4, 6, ' '),
+        (4, 7, '%'),
+        (5, 6, 'o'),
+        (5, 7, ':'),
+    ] {
+        grid[(y, x)] = ch;
+    }
+    grid
+}
+
+fn lines(text: &str) -> Vec<&str> {
+    text.lines().collect()
+}
+
+#[test]
+fn each_mode_trims_to_its_dimensions() {
+    let grid = figure();
+
+    let (text, offsets) = trimmed_text(&grid.view(), TrimMode::None);
+    assert_eq!(offsets, None);
+    assert_eq!(lines(&text).len(), 10);
+    assert!(lines(&text).iter().all(|line| line.len() == 12));
+
+    let (text, offsets) = trimmed_text(&grid.view(), TrimMode::TrailingSpaces);
+    assert_eq!(offsets, None);
+    let widths: Vec<usize> = lines(&text).iter().map(|line| line.len()).collect();
+    assert_eq!(widths, [0, 0, 0, 0, 8, 8, 0, 0, 0, 0]);
+
+    let (text, offsets) = trimmed_text(&grid.view(), TrimMode::Bounding);
+    assert_eq!(
+        offsets,
+        Some(TrimOffsets {
+            top: 4,
+            left: 5,
+            bottom: 4,
+            right: 4,
+        })
+    );
+    assert_eq!(text, "@ %\n o:\n");
+}
+
+#[test]
+fn bounding_keeps_inner_spaces_and_the_ragged_right() {
+    // The shorter first row loses its trailing spaces, the gap in the figure stays
+    let mut grid = figure();
+    grid[(4, 7)] = ' ';
+    let (text, offsets) = trimmed_text(&grid.view(), TrimMode::Bounding);
+    assert_eq!(offsets.unwrap().right, 4);
+    assert_eq!(text, "@\n o:\n");
+}
+
+#[test]
+fn blank_grids_trim_to_nothing() {
+    let grid = Array2::from_elem((3, 4), ' ');
+    let (text, offsets) = trimmed_text(&grid.view(), TrimMode::Bounding);
+    assert_eq!(text, "");
+    assert_eq!(offsets.unwrap().top, 3);
+    let (text, _) = trimmed_text(&grid.view(), TrimMode::TrailingSpaces);
+    assert_eq!(text, "\n\n\n");
+}
+
+#[test]
+fn ansi_trims_the_colors_with_the_cells() {
+    let grid = figure();
+    let colors = Array2::from_shape_fn(grid.dim(), |(y, x)| Rgb([y as u8, x as u8, 0]));
+    let bg = Rgb([0, 0, 0]);
+    let (text, offsets) = trimmed_ansi(&grid.view(), &colors.view(), bg, TrimMode::Bounding);
+    assert!(offsets.is_some());
+    assert_eq!(lines(&text).len(), 2);
+    // The first kept cell is (4, 5), drawn in its own color
+    assert!(text.starts_with("\x1b[48;2;0;0;0m\x1b[38;2;4;5;0m@"));
+    assert!(!text.contains("38;2;4;4;0m") && !text.contains("38;2;4;8;0m"));
+
+    let (text, _) = trimmed_ansi(&grid.view(), &colors.view(), bg, TrimMode::TrailingSpaces);
+    assert_eq!(lines(&text).len(), 10);
+    assert!(!text.contains("38;2;4;8;0m"));
+}
+
+#[test]
+fn converter_trims_text_outputs_only() {
+    // A bright square on black, centered, so spaces surround it
+    let img = DynamicImage::ImageLuma8(GrayImage::from_fn(96, 80, |x, y| {
+        let inside = (40..64).contains(&x) && (32..48).contains(&y);
+        Luma([if inside { 255 } else { 0 }])
+    }));
+    let mut bytes = vec![];
+    img.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+        .unwrap();
+    let build = |trim| {
+        ConverterConfig {
+            trim,
+            ..common::test_config()
+        }
+        .build()
+        .unwrap()
+    };
+    let (plain, trimmed) = (build(TrimModeConfig::None), build(TrimModeConfig::Bounding));
+
+    let (full, stats) = plain
+        .convert_bytes_with_stats(&bytes, OutputFormat::Txt, 0.0)
+        .unwrap();
+    assert_eq!(stats.trimmed, None);
+    let (text, stats) = trimmed
+        .convert_bytes_with_stats(&bytes, OutputFormat::Txt, 0.0)
+        .unwrap();
+    let offsets = stats.trimmed.unwrap();
+    assert!(offsets.top > 0 && offsets.left > 0 && offsets.bottom > 0 && offsets.right > 0);
+    assert!(stats.to_string().contains("trimmed: "));
+
+    // The offsets place the trimmed art back on the full grid
+    let full = String::from_utf8(full).unwrap();
+    let text = String::from_utf8(text).unwrap();
+    let full_lines = lines(&full);
+    assert_eq!(
+        lines(&text).len(),
+        full_lines.len() - offsets.top - offsets.bottom
+    );
+    for (y, line) in lines(&text).iter().enumerate() {
+        let original = &full_lines[y + offsets.top][offsets.left..];
+        assert_eq!(*line, original.trim_end());
+    }
+
+    let (plain_png, _) = plain
+        .convert_bytes_with_stats(&bytes, OutputFormat::Png, 0.0)
+        .unwrap();
+    let (trimmed_png, stats) = trimmed
+        .convert_bytes_with_stats(&bytes, OutputFormat::Png, 0.0)
+        .unwrap();
+    // Compared decoded, the embedded configs differ in their trim
+    let decode = |png: &[u8]| image::load_from_memory(png).unwrap().to_rgb8();
+    assert_eq!(decode(&plain_png), decode(&trimmed_png));
+    assert_eq!(stats.trimmed, None);
+}
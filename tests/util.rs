@@ -0,0 +1,120 @@
+/*
+* Conversions between image buffers and ndarrays
+*/
+mod common;
+
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::image_manip::util::{
+    arr3_to_rgb_bufr, arr_to_bufr, bufr_to_arr, bufr_view, bufr_view_mut, rgb_bufr_to_arr3,
+    rgb_bufr_view, rgb_bufr_view_mut, try_arr3_to_rgb_bufr, try_arr_to_bufr, try_bufr_to_arr,
+    try_rgb_bufr_to_arr3,
+};
+use image::{GrayImage, ImageBuffer, Luma, Rgb, RgbImage};
+use ndarray::{s, Array2, Array3, Axis};
+
+#[test]
+fn luma_round_trips() {
+    let img = common::noise(7, 5, 1).to_luma8();
+    let arr = bufr_to_arr(&img);
+    assert_eq!(arr.dim(), (5, 7));
+    assert_eq!(arr[(4, 6)], img.get_pixel(6, 4)[0]);
+    assert_eq!(arr_to_bufr(&arr), img);
+    assert_eq!(bufr_view(&img), arr);
+}
+
+#[test]
+fn rgb_round_trips() {
+    let img = common::noise(7, 5, 3).to_rgb8();
+    let arr = rgb_bufr_to_arr3(&img);
+    assert_eq!(arr.dim(), (5, 7, 3));
+    assert_eq!(arr[(4, 6, 2)], img.get_pixel(6, 4)[2]);
+    assert_eq!(arr[(0, 1, 0)], img.get_pixel(1, 0)[0]);
+    assert_eq!(arr3_to_rgb_bufr(&arr), img);
+    assert_eq!(rgb_bufr_view(&img), arr);
+}
+
+#[test]
+fn views_write_through_to_the_buffer() {
+    let mut gray = GrayImage::new(4, 3);
+    bufr_view_mut(&mut gray)[(2, 3)] = 9;
+    assert_eq!(gray.get_pixel(3, 2)[0], 9);
+
+    let mut rgb = RgbImage::new(4, 3);
+    rgb_bufr_view_mut(&mut rgb)
+        .slice_mut(s![1, .., 0])
+        .fill(200);
+    for x in 0..4 {
+        assert_eq!(*rgb.get_pixel(x, 1), Rgb([200, 0, 0]));
+        assert_eq!(*rgb.get_pixel(x, 0), Rgb([0, 0, 0]));
+    }
+}
+
+#[test]
+fn non_contiguous_arrays_keep_their_logical_order() {
+    // Transposed and stepped arrays are not in standard layout
+    let arr = Array2::from_shape_fn((6, 4), |(y, x)| (y * 10 + x) as u8);
+    let transposed = arr.t().to_owned();
+    assert!(!transposed.is_standard_layout());
+    let bufr = arr_to_bufr(&transposed);
+    assert_eq!(bufr.dimensions(), (6, 4));
+    assert_eq!(bufr.get_pixel(5, 3)[0], 53);
+
+    let rgb = Array3::from_shape_fn((3, 5, 3), |(y, x, c)| (y * 100 + x * 10 + c) as u8);
+    let mut flipped = rgb.clone();
+    flipped.invert_axis(Axis(1));
+    assert!(!flipped.is_standard_layout());
+    let bufr = arr3_to_rgb_bufr(&flipped);
+    assert_eq!(*bufr.get_pixel(0, 2), Rgb([240, 241, 242]));
+    assert_eq!(*bufr.get_pixel(4, 0), Rgb([0, 1, 2]));
+
+    let stepped = rgb.slice(s![.., ..;2, ..]).to_owned();
+    assert_eq!(arr3_to_rgb_bufr(&stepped).dimensions(), (3, 3));
+}
+
+#[test]
+fn zero_sized_dimensions_convert() {
+    for (w, h) in [(0, 0), (0, 4), (4, 0)] {
+        let gray = GrayImage::new(w, h);
+        let arr = bufr_to_arr(&gray);
+        assert_eq!(arr.dim(), (h as usize, w as usize));
+        assert_eq!(arr_to_bufr(&arr).dimensions(), (w, h));
+        assert_eq!(bufr_view(&gray).len(), 0);
+
+        let rgb = RgbImage::new(w, h);
+        let arr = rgb_bufr_to_arr3(&rgb);
+        assert_eq!(arr.dim(), (h as usize, w as usize, 3));
+        assert_eq!(arr3_to_rgb_bufr(&arr).dimensions(), (w, h));
+        assert_eq!(rgb_bufr_view(&rgb).len(), 0);
+    }
+}
+
+#[test]
+fn oversized_containers_only_expose_the_pixels() {
+    // from_raw accepts a container longer than the image needs
+    let gray: GrayImage = ImageBuffer::from_raw(2, 2, vec![1, 2, 3, 4, 5, 6]).unwrap();
+    assert_eq!(try_bufr_to_arr(&gray).unwrap().len(), 4);
+    assert_eq!(bufr_view(&gray).len(), 4);
+
+    let rgb: RgbImage = ImageBuffer::from_raw(1, 1, vec![1, 2, 3, 4]).unwrap();
+    assert_eq!(try_rgb_bufr_to_arr3(&rgb).unwrap().len(), 3);
+}
+
+#[test]
+fn wrong_channel_counts_are_errors() {
+    for channels in [0, 1, 4] {
+        let arr = Array3::<u8>::zeros((2, 2, channels));
+        assert!(matches!(
+            try_arr3_to_rgb_bufr(&arr),
+            Err(ConvertError::NdArrayShapeError)
+        ));
+    }
+    let arr = Array2::<u8>::zeros((2, 3));
+    let bufr: ImageBuffer<Luma<u8>, Vec<u8>> = try_arr_to_bufr(&arr).unwrap();
+    assert_eq!(bufr.dimensions(), (3, 2));
+}
+
+#[test]
+#[should_panic]
+fn wrong_channel_count_panics_without_try() {
+    arr3_to_rgb_bufr(&Array3::<u8>::zeros((2, 2, 4)));
+}
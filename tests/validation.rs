@@ -0,0 +1,185 @@
+/*
+* Every validation rule rejects its bad setting and names the offending field
+*/
+mod common;
+
+use ascii_gen::ascii::char_set::CharacterSet;
+use ascii_gen::ascii::config::{ConverterConfig, DetailModeConfig, ProcessorConfig};
+use ascii_gen::ascii::converter::Converter;
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::ascii::font_loader::FontSettings;
+use ascii_gen::image_manip::edge_detect::Sobel;
+use image::Rgb;
+
+fn invalid_field(result: Result<Converter, ConvertError>) -> &'static str {
+    match result {
+        Err(ConvertError::InvalidSetting { field, .. }) => field,
+        Err(e) => panic!("expected an invalid setting, got {}", e),
+        Ok(_) => panic!("expected an invalid setting, got a converter"),
+    }
+}
+
+fn with_edge_preprocessor(preproc: ProcessorConfig) -> ConverterConfig {
+    ConverterConfig {
+        edge_preprocessors: vec![preproc],
+        ..common::test_config()
+    }
+}
+
+#[test]
+fn default_config_is_valid() {
+    assert!(common::test_config().build().is_ok());
+    assert!(Converter::default().validate().is_ok());
+}
+
+#[test]
+fn font_size_must_be_positive() {
+    let config = ConverterConfig {
+        font_size: 0,
+        ..common::test_config()
+    };
+    assert_eq!(invalid_field(config.build()), "font_size");
+}
+
+#[test]
+fn tile_set_must_not_be_empty() {
+    let config = ConverterConfig {
+        tile_chars: String::new(),
+        ..common::test_config()
+    };
+    assert_eq!(invalid_field(config.build()), "tile");
+}
+
+#[test]
+fn edge_set_must_cover_every_direction() {
+    let converter = Converter::new(
+        FontSettings::new(common::FONT_SIZE, &common::test_font_path()),
+        CharacterSet {
+            tile: vec![' ', '@'],
+            edge: vec![' ', '|'],
+        },
+        vec![],
+        vec![],
+        Box::new(Sobel::new()),
+        Rgb([0, 0, 0]),
+        false,
+        Rgb([255, 255, 255]),
+    );
+    assert!(matches!(
+        converter.validate(),
+        Err(ConvertError::InvalidSetting { field: "edge", .. })
+    ));
+    // The edge set is unused when edges are not drawn
+    assert!(converter.with_edges(false).validate().is_ok());
+}
+
+#[test]
+fn dog_sigmas_must_be_ordered() {
+    let config = with_edge_preprocessor(ProcessorConfig::DoG {
+        sigma_1: 3.0,
+        sigma_2: 1.0,
+    });
+    assert_eq!(invalid_field(config.build()), "dog.sigma_2");
+
+    let config = with_edge_preprocessor(ProcessorConfig::DoG {
+        sigma_1: 0.0,
+        sigma_2: 1.0,
+    });
+    assert_eq!(invalid_field(config.build()), "dog.sigma_1");
+}
+
+#[test]
+fn blur_sigmas_must_be_positive() {
+    let config = with_edge_preprocessor(ProcessorConfig::SharpenGaussian {
+        sigma: -1.0,
+        amount: 1.0,
+    });
+    assert_eq!(invalid_field(config.build()), "sharpen_gaussian.sigma");
+
+    let config = with_edge_preprocessor(ProcessorConfig::BilateralFilter {
+        window_size: 5,
+        sigma_color: f32::NAN,
+        sigma_spatial: 1.0,
+    });
+    assert_eq!(
+        invalid_field(config.build()),
+        "bilateral_filter.sigma_color"
+    );
+}
+
+#[test]
+fn filter_windows_must_be_in_range() {
+    let median = |kernel_size, x_radius, y_radius| {
+        with_edge_preprocessor(ProcessorConfig::MedianBlur {
+            kernel_size,
+            x_radius,
+            y_radius,
+        })
+    };
+    assert_eq!(
+        invalid_field(median(0, None, None).build()),
+        "median_blur.kernel_size"
+    );
+    assert_eq!(
+        invalid_field(median(2, Some(51), None).build()),
+        "median_blur.x_radius"
+    );
+    assert_eq!(
+        invalid_field(median(2, None, Some(1000)).build()),
+        "median_blur.y_radius"
+    );
+    // One axis alone is enough
+    assert!(median(0, Some(3), None).build().is_ok());
+    assert!(median(50, None, None).build().is_ok());
+
+    for window_size in [0, 51, u32::MAX] {
+        let config = with_edge_preprocessor(ProcessorConfig::BilateralFilter {
+            window_size,
+            sigma_color: 2.0,
+            sigma_spatial: 5.0,
+        });
+        assert_eq!(
+            invalid_field(config.build()),
+            "bilateral_filter.window_size"
+        );
+    }
+}
+
+#[test]
+fn edge_threshold_must_be_a_ratio() {
+    for edge_threshold in [-0.1, 1.5, f32::NAN] {
+        let config = ConverterConfig {
+            edge_threshold,
+            ..common::test_config()
+        };
+        assert_eq!(invalid_field(config.build()), "edge_threshold");
+    }
+
+    // The threshold passed on each call is checked the same way
+    let img = common::gradient(32, 32);
+    assert!(matches!(
+        common::test_converter().convert_image(&img, 2.0),
+        Err(ConvertError::InvalidSetting {
+            field: "sharpen_thres",
+            ..
+        })
+    ));
+}
+
+#[test]
+fn fine_factor_must_divide_the_font_size() {
+    for (fine_factor, field) in [
+        (1, "two_scale.fine_factor"),
+        (3, "two_scale.fine_factor"),
+        (2, "two_scale.variance_threshold"),
+    ] {
+        let config = ConverterConfig {
+            detail_mode: DetailModeConfig::TwoScale {
+                fine_factor,
+                variance_threshold: -1.0,
+            },
+            ..common::test_config()
+        };
+        assert_eq!(invalid_field(config.build()), field);
+    }
+}
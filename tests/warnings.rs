@@ -0,0 +1,85 @@
+/*
+* Conversions report what they worked around next to their result
+*/
+mod common;
+
+use ascii_gen::ascii::config::ConverterConfig;
+use ascii_gen::ascii::font_loader::FALLBACK_FONT_PATH;
+use ascii_gen::ascii::warning::ConvertWarning;
+use ascii_gen::output::OutputFormat;
+use image::ImageFormat;
+use std::fs;
+use std::io::Cursor;
+use std::path::PathBuf;
+
+fn scratch(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("ruscii-gen-{}-warn-{}", std::process::id(), name))
+}
+
+fn png_bytes(w: u32, h: u32) -> Vec<u8> {
+    let mut bytes = vec![];
+    common::circle(w, h)
+        .write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)
+        .unwrap();
+    bytes
+}
+
+#[test]
+fn unparsable_font_warns_about_the_fallback() {
+    let font = scratch("font.ttf");
+    fs::write(&font, b"not a font").unwrap();
+    let input = scratch("in.png");
+    fs::write(&input, png_bytes(64, 64)).unwrap();
+    let out = scratch("out.png");
+
+    let converter = ConverterConfig {
+        font_path: font.to_str().unwrap().to_string(),
+        ..common::test_config()
+    }
+    .build()
+    .unwrap();
+    let stats = converter
+        .convert_img_with_stats(input.to_str().unwrap(), out.to_str().unwrap(), 0.0)
+        .unwrap();
+    assert!(stats.warnings.contains(&ConvertWarning::FallbackFont {
+        requested: font.to_str().unwrap().to_string(),
+        used: FALLBACK_FONT_PATH.to_string(),
+    }));
+    assert!(image::open(&out).is_ok());
+
+    // Text outputs never draw the glyphs, so the font is not theirs to warn about
+    let (_, stats) = converter
+        .convert_bytes_with_stats(&png_bytes(64, 64), OutputFormat::Txt, 0.0)
+        .unwrap();
+    assert!(stats.warnings.is_empty(), "{:?}", stats.warnings);
+}
+
+#[test]
+fn downscaled_inputs_warn_with_both_sizes() {
+    let converter = ConverterConfig {
+        max_input_pixels: Some(64 * 64),
+        auto_downscale_large: true,
+        ..common::test_config()
+    }
+    .build()
+    .unwrap();
+    let (_, stats) = converter
+        .convert_bytes_with_stats(&png_bytes(256, 128), OutputFormat::Png, 0.0)
+        .unwrap();
+    assert_eq!(
+        stats.warnings,
+        vec![ConvertWarning::Downscaled {
+            from: (256, 128),
+            to: (90, 45),
+        }]
+    );
+    assert!(stats.warnings[0].to_string().contains("256x128"));
+}
+
+#[test]
+fn clean_conversions_have_no_warnings() {
+    let (_, stats) = common::test_converter()
+        .convert_bytes_with_stats(&png_bytes(64, 64), OutputFormat::Png, 0.0)
+        .unwrap();
+    assert!(stats.warnings.is_empty(), "{:?}", stats.warnings);
+}
@@ -0,0 +1,65 @@
+#![cfg(feature = "watch")]
+/*
+* Watching an input converts it whenever a new version is dropped in, until stopped
+*/
+use ascii_gen::batch::BatchOptions;
+use ascii_gen::watch::{watch_and_convert, StopHandle};
+use image::{GrayImage, Luma};
+use std::fs;
+use std::sync::mpsc::channel;
+use std::thread;
+use std::time::Duration;
+
+const FONT_SIZE: u32 = 8;
+// Longest wait for the watcher to notice a file, far over its debounce
+const WAIT: Duration = Duration::from_secs(10);
+
+#[test]
+fn dropped_inputs_are_converted_until_stopped() {
+    let dir = std::env::temp_dir().join(format!("ruscii-gen-{}-watch", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let path = |name: &str| dir.join(name);
+    let (input, output, config) = (path("in.png"), path("out.png"), path("cfg.toml"));
+    let toml = format!(
+        "font_size = {}\nfont_path = {:?}\n",
+        FONT_SIZE,
+        concat!(env!("CARGO_MANIFEST_DIR"), "/font.ttf")
+    );
+    fs::write(&config, toml).unwrap();
+
+    let stop = StopHandle::new();
+    let (tx, rx) = channel();
+    let watch = {
+        let (input, output, config, stop) =
+            (input.clone(), output.clone(), config.clone(), stop.clone());
+        thread::spawn(move || {
+            let options = BatchOptions::new(false);
+            watch_and_convert(
+                &input,
+                &output,
+                Some(config.as_path()),
+                &options,
+                &stop,
+                |result| tx.send(result.is_ok()).unwrap(),
+            )
+        })
+    };
+    // The input does not exist yet, so the first conversion fails without ending the watch
+    assert_eq!(rx.recv_timeout(WAIT), Ok(false));
+    assert!(!output.exists());
+
+    // Written aside and moved in, so the watcher never sees a partial file
+    let staged = path("staged.png");
+    let size = FONT_SIZE * 4;
+    GrayImage::from_fn(size, size, |x, _| Luma([(x * 8) as u8]))
+        .save(&staged)
+        .unwrap();
+    fs::rename(&staged, &input).unwrap();
+    assert_eq!(rx.recv_timeout(WAIT), Ok(true));
+    assert_eq!(image::open(&output).unwrap().width(), size);
+
+    stop.stop();
+    watch.join().unwrap().unwrap();
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,137 @@
+/*
+* Watermark text drawn over the finished grid
+*/
+mod common;
+
+use ascii_gen::ascii::config::{ConverterConfig, CornerConfig, WatermarkConfig};
+use ascii_gen::ascii::error::ConvertError;
+use image::{DynamicImage, GrayImage, ImageBuffer, Luma, Rgb};
+
+const FS: u32 = common::FONT_SIZE;
+const BG: [u8; 3] = [0, 0, 0];
+const PADDING: u32 = 4;
+
+// Config whose grid renders as plain background, so only the watermark leaves pixels
+fn blank_config(watermark: WatermarkConfig) -> ConverterConfig {
+    ConverterConfig {
+        tile_chars: " ".to_string(),
+        draw_edges: false,
+        bg_color: BG,
+        watermark: Some(watermark),
+        ..common::test_config()
+    }
+}
+
+fn watermark(text: &str, corner: CornerConfig) -> WatermarkConfig {
+    WatermarkConfig {
+        text: text.to_string(),
+        corner,
+        color: [255, 255, 0],
+        padding: PADDING,
+        ..WatermarkConfig::default()
+    }
+}
+
+fn render(config: &ConverterConfig) -> ImageBuffer<Rgb<u8>, Vec<u8>> {
+    let img = DynamicImage::ImageLuma8(GrayImage::from_pixel(FS * 40, FS * 20, Luma([0])));
+    config.build().unwrap().convert_image(&img, 0.0).unwrap()
+}
+
+fn marked(img: &ImageBuffer<Rgb<u8>, Vec<u8>>) -> Vec<(u32, u32)> {
+    img.enumerate_pixels()
+        .filter(|(_, _, p)| p.0 != BG)
+        .map(|(x, y, _)| (x, y))
+        .collect()
+}
+
+#[test]
+fn watermark_stays_in_its_corner() {
+    for corner in [
+        CornerConfig::TopLeft,
+        CornerConfig::TopRight,
+        CornerConfig::BottomLeft,
+        CornerConfig::BottomRight,
+    ] {
+        let img = render(&blank_config(watermark("@Poco", corner.clone())));
+        let (w, h) = img.dimensions();
+        let marked = marked(&img);
+        assert!(!marked.is_empty(), "{:?}", corner);
+
+        let left = matches!(corner, CornerConfig::TopLeft | CornerConfig::BottomLeft);
+        let top = matches!(corner, CornerConfig::TopLeft | CornerConfig::TopRight);
+        for &(x, y) in marked.iter() {
+            // One line of text inside the padding, in the corner's half of the image
+            assert!(
+                x >= PADDING && x < w - PADDING,
+                "{:?} at {}, {}",
+                corner,
+                x,
+                y
+            );
+            assert!(
+                y >= PADDING && y < h - PADDING,
+                "{:?} at {}, {}",
+                corner,
+                x,
+                y
+            );
+            assert_eq!(x < w / 2, left, "{:?} at {}, {}", corner, x, y);
+            if top {
+                assert!(y < PADDING + FS * 2, "{:?} at {}, {}", corner, x, y);
+            } else {
+                assert!(y >= h - PADDING - FS * 2, "{:?} at {}, {}", corner, x, y);
+            }
+        }
+    }
+}
+
+#[test]
+fn right_corners_end_at_the_padding() {
+    let img = render(&blank_config(watermark("@Poco", CornerConfig::BottomRight)));
+    let right = marked(&img).iter().map(|&(x, _)| x).max().unwrap();
+    // Up to the side bearing of the last glyph short of the padding
+    assert!(right + PADDING + FS / 2 >= img.width(), "ends at {}", right);
+}
+
+#[test]
+fn wide_text_is_shrunk_and_cut_to_fit() {
+    let text = "@Poco".repeat(40);
+    let img = render(&blank_config(watermark(&text, CornerConfig::BottomLeft)));
+    let marked = marked(&img);
+    assert!(!marked.is_empty());
+    assert!(marked
+        .iter()
+        .all(|&(x, _)| x >= PADDING && x < img.width() - PADDING));
+}
+
+#[test]
+fn no_watermark_leaves_the_grid_alone() {
+    let config = ConverterConfig {
+        watermark: None,
+        ..blank_config(WatermarkConfig::default())
+    };
+    assert!(marked(&render(&config)).is_empty());
+    // Converting to text never carries the watermark
+    let img = DynamicImage::ImageLuma8(GrayImage::from_pixel(FS * 4, FS * 2, Luma([0])));
+    let text = blank_config(watermark("@Poco", CornerConfig::TopLeft))
+        .build()
+        .unwrap()
+        .convert_to_text(&img, 0.0)
+        .unwrap();
+    assert!(text.chars().all(char::is_whitespace));
+}
+
+#[test]
+fn watermark_scale_must_be_positive() {
+    let config = blank_config(WatermarkConfig {
+        scale: 0.0,
+        ..watermark("@Poco", CornerConfig::TopLeft)
+    });
+    assert!(matches!(
+        config.build().err(),
+        Some(ConvertError::InvalidSetting {
+            field: "watermark.scale",
+            ..
+        })
+    ));
+}
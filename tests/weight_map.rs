@@ -0,0 +1,131 @@
+/*
+* The weight map shortens the tile ramp of low weight cells
+*/
+mod common;
+
+use ascii_gen::ascii::config::{
+    ConverterConfig, TileSamplingConfig, WeightMapConfig, WeightSourceConfig,
+};
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::ascii::weight_map::{WeightMap, WeightSource};
+use image::{DynamicImage, GrayImage, Luma};
+use std::collections::HashSet;
+
+const FS: u32 = common::FONT_SIZE;
+const COLS: u32 = 32;
+const ROWS: u32 = 8;
+
+// Every cell flat, cycling through 16 luminance levels the same way in both halves
+fn cells() -> DynamicImage {
+    DynamicImage::ImageLuma8(GrayImage::from_fn(COLS * FS, ROWS * FS, |x, y| {
+        let (cx, cy) = (x / FS, y / FS);
+        Luma([((cx + 5 * cy) % 16 * 17) as u8])
+    }))
+}
+
+fn half_map_path() -> String {
+    let path = std::env::temp_dir().join(format!("ruscii-gen-{}-weights.png", std::process::id()));
+    GrayImage::from_fn(COLS * 2, ROWS * 2, |x, _| {
+        Luma([if x < COLS { 0 } else { 255 }])
+    })
+    .save(&path)
+    .unwrap();
+    path.to_str().unwrap().to_string()
+}
+
+fn config(weight_map: Option<WeightMapConfig>) -> ConverterConfig {
+    ConverterConfig {
+        weight_map,
+        tile_sampling: TileSamplingConfig::ExactBoxAverage,
+        draw_edges: false,
+        ..common::test_config()
+    }
+}
+
+fn distinct(lines: &[Vec<char>], cols: std::ops::Range<usize>) -> usize {
+    lines
+        .iter()
+        .flat_map(|line| line[cols.clone()].iter().copied())
+        .collect::<HashSet<_>>()
+        .len()
+}
+
+#[test]
+fn dark_half_of_the_map_uses_a_short_ramp() {
+    let weight_map = WeightMapConfig {
+        source: WeightSourceConfig::Image {
+            path: half_map_path(),
+        },
+        min_levels: 4,
+        gamma: 1.0,
+    };
+    let text = config(Some(weight_map))
+        .build()
+        .unwrap()
+        .convert_to_text(&cells(), 0.0)
+        .unwrap();
+    let lines: Vec<Vec<char>> = text.lines().map(|line| line.chars().collect()).collect();
+    assert_eq!(lines.len(), ROWS as usize);
+
+    // The column on the boundary is blended by the resize, so it is left out
+    let left = distinct(&lines, 0..COLS as usize / 2 - 1);
+    let right = distinct(&lines, COLS as usize / 2 + 1..COLS as usize);
+    assert!(left <= 4, "{} characters on the left", left);
+    assert!(right > 8, "{} characters on the right", right);
+
+    // Full weight leaves the cells as they are without a map
+    let plain = config(None)
+        .build()
+        .unwrap()
+        .convert_to_text(&cells(), 0.0)
+        .unwrap();
+    for (line, plain) in lines.iter().zip(plain.lines()) {
+        let plain: Vec<char> = plain.chars().collect();
+        assert_eq!(
+            line[COLS as usize / 2 + 1..],
+            plain[COLS as usize / 2 + 1..]
+        );
+    }
+}
+
+#[test]
+fn saliency_weighs_the_cells_that_stand_out() {
+    let gray = GrayImage::from_fn(32, 32, |x, y| {
+        Luma([if (12..20).contains(&x) && (12..20).contains(&y) {
+            220
+        } else {
+            40
+        }])
+    });
+    let weights = WeightMap::new(WeightSource::Saliency, 2, 1.0).weights(&gray);
+    assert_eq!(weights.dim(), (32, 32));
+    assert!(weights[(16, 16)] > 0.5);
+    assert!(weights[(0, 0)] < 0.1);
+    assert!(weights.iter().all(|w| (0.0..=1.0).contains(w)));
+}
+
+#[test]
+fn missing_map_and_bad_settings_are_errors() {
+    let missing = WeightMapConfig {
+        source: WeightSourceConfig::Image {
+            path: "does/not/exist.png".to_string(),
+        },
+        ..WeightMapConfig::default()
+    };
+    assert!(matches!(
+        config(Some(missing)).build().err(),
+        Some(ConvertError::ConfigError(_))
+    ));
+
+    let zero = WeightMapConfig {
+        min_levels: 0,
+        ..WeightMapConfig::default()
+    };
+    assert!(matches!(
+        config(Some(zero)).build().err(),
+        Some(ConvertError::InvalidSetting {
+            field: "weight_map.min_levels",
+            ..
+        })
+    ));
+}
@@ -0,0 +1,108 @@
+/*
+* Output writing: missing directories, existing files and atomic replacement
+*/
+mod common;
+
+use ascii_gen::ascii::error::ConvertError;
+use ascii_gen::output::write::{write_file, OverwritePolicy, WriteOptions};
+use std::error::Error;
+use std::fs;
+use std::path::PathBuf;
+
+fn scratch(name: &str) -> PathBuf {
+    let root =
+        std::env::temp_dir().join(format!("ruscii-gen-{}-write-{}", std::process::id(), name));
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(&root).unwrap();
+    root
+}
+
+fn policy(overwrite: OverwritePolicy) -> WriteOptions {
+    WriteOptions::new(false, overwrite)
+}
+
+#[test]
+fn overwrite_replaces_the_file() {
+    let root = scratch("overwrite");
+    let out = root.join("out.txt");
+    fs::write(&out, "old").unwrap();
+    let written = write_file(&out, b"new", &policy(OverwritePolicy::Overwrite)).unwrap();
+    assert_eq!(written, out);
+    assert_eq!(fs::read_to_string(&out).unwrap(), "new");
+}
+
+#[test]
+fn error_keeps_the_existing_file() {
+    let root = scratch("error");
+    let out = root.join("out.txt");
+    fs::write(&out, "old").unwrap();
+    let err = write_file(&out, b"new", &policy(OverwritePolicy::Error)).unwrap_err();
+    match &err {
+        ConvertError::OutputError { path, .. } => assert_eq!(path, &out.display().to_string()),
+        other => panic!("expected OutputError, got {:?}", other),
+    }
+    assert!(err.source().is_some());
+    assert_eq!(fs::read_to_string(&out).unwrap(), "old");
+
+    // Without an existing file the write goes through
+    let fresh = root.join("fresh.txt");
+    write_file(&fresh, b"new", &policy(OverwritePolicy::Error)).unwrap();
+    assert_eq!(fs::read_to_string(&fresh).unwrap(), "new");
+}
+
+#[test]
+fn rename_picks_the_next_free_suffix() {
+    let root = scratch("rename");
+    let out = root.join("out.png");
+    fs::write(&out, "0").unwrap();
+    fs::write(root.join("out-1.png"), "1").unwrap();
+    let written = write_file(&out, b"2", &policy(OverwritePolicy::RenameWithSuffix)).unwrap();
+    assert_eq!(written, root.join("out-2.png"));
+    assert_eq!(fs::read_to_string(&out).unwrap(), "0");
+    assert_eq!(fs::read_to_string(&written).unwrap(), "2");
+}
+
+#[test]
+fn missing_directories_are_created_on_request() {
+    let root = scratch("dirs");
+    let out = root.join("a").join("b").join("out.txt");
+    let err = write_file(&out, b"x", &WriteOptions::default()).unwrap_err();
+    assert!(matches!(err, ConvertError::OutputError { .. }), "{:?}", err);
+
+    let options = WriteOptions::new(true, OverwritePolicy::Overwrite);
+    write_file(&out, b"x", &options).unwrap();
+    assert_eq!(fs::read_to_string(&out).unwrap(), "x");
+}
+
+#[test]
+fn no_temporary_file_is_left_behind() {
+    let root = scratch("atomic");
+    let out = root.join("out.txt");
+    write_file(&out, b"done", &WriteOptions::default()).unwrap();
+    // A failed rename, onto a directory, removes the temporary file too
+    fs::create_dir(root.join("dir.txt")).unwrap();
+    assert!(write_file(root.join("dir.txt"), b"x", &WriteOptions::default()).is_err());
+
+    let mut names: Vec<_> = fs::read_dir(&root)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    names.sort();
+    assert_eq!(names, ["dir.txt", "out.txt"]);
+}
+
+#[test]
+fn converter_writes_follow_the_options() {
+    let root = scratch("converter");
+    let input = root.join("in.png");
+    let size = common::FONT_SIZE * 4;
+    common::circle(size, size).save(&input).unwrap();
+    let out = root.join("nested").join("out.png");
+    let converter = common::test_converter()
+        .with_write_options(WriteOptions::new(true, OverwritePolicy::Error));
+
+    let convert = || converter.convert_img(input.to_str().unwrap(), out.to_str().unwrap(), 0.0);
+    convert().unwrap();
+    assert!(image::open(&out).is_ok());
+    assert!(matches!(convert(), Err(ConvertError::OutputError { .. })));
+}